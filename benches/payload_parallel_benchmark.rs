@@ -0,0 +1,113 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use fastpasta::stats::stats_controller::StatType;
+use fastpasta::validators::link_validator::LinkValidator;
+use fastpasta::words::lib::{ByteSlice, RDH};
+use fastpasta::words::rdh_cru::{test_data::CORRECT_RDH_CRU_V7, RdhCRU, V7};
+
+const HBFS: u32 = 200;
+const DATA_WORDS_PER_PAGE: usize = 500;
+
+const IHW: [u8; 10] = [0xFF, 0x3F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE0];
+const OB_DATA_WORD: [u8; 10] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40];
+const TDT_PACKET_DONE: [u8; 10] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xF0];
+const DDW0: [u8; 10] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE4];
+
+/// RDH of a page of an HBF, where the last page is the second page
+fn rdh_for_page(orbit: u32, pages_counter: u16, payload_size: u16) -> RdhCRU<V7> {
+    let mut rdh_bytes = CORRECT_RDH_CRU_V7.to_byte_slice().to_vec();
+    let packet_size = 64 + payload_size;
+    // offset_new_packet and memory_size
+    rdh_bytes[8..10].copy_from_slice(&packet_size.to_le_bytes());
+    rdh_bytes[10..12].copy_from_slice(&packet_size.to_le_bytes());
+    rdh_bytes[20..24].copy_from_slice(&orbit.to_le_bytes());
+    // Trigger type without the SOC bit, as it is the same on every page
+    rdh_bytes[32..36].copy_from_slice(&0x6803_u32.to_le_bytes());
+    rdh_bytes[36..38].copy_from_slice(&pages_counter.to_le_bytes());
+    // stop_bit
+    rdh_bytes[38] = pages_counter as u8;
+    RdhCRU::load(&mut rdh_bytes.as_slice()).unwrap()
+}
+
+/// Single link data, where each HBF is a page full of data words, followed by a page with a DDW0
+fn single_link_cdps() -> Vec<(RdhCRU<V7>, Vec<u8>, u64)> {
+    let mut cdps = Vec::new();
+    let mut mem_pos = 0;
+    for orbit in (0..HBFS).map(|hbf| 0x0B7D_D575 + hbf) {
+        let orbit_bytes = orbit.to_le_bytes();
+        let tdh = [
+            0x03,
+            0x18,
+            0x00,
+            0x00,
+            orbit_bytes[0],
+            orbit_bytes[1],
+            orbit_bytes[2],
+            orbit_bytes[3],
+            0x00,
+            0xE8,
+        ];
+        let mut data_page = vec![IHW, tdh];
+        data_page.extend([OB_DATA_WORD; DATA_WORDS_PER_PAGE]);
+        data_page.push(TDT_PACKET_DONE);
+        for (pages_counter, words) in [data_page, vec![DDW0]].into_iter().enumerate() {
+            let mut payload: Vec<u8> = words.concat();
+            payload.resize((payload.len() + 15) / 16 * 16, 0xFF);
+            let rdh = rdh_for_page(orbit, pages_counter as u16, payload.len() as u16);
+            let next_mem_pos = mem_pos + rdh.offset_to_next() as u64;
+            cdps.push((rdh, payload, mem_pos));
+            mem_pos = next_mem_pos;
+        }
+    }
+    cdps
+}
+
+/// Link validator with all the CDPs already received in its data channel, and the receiver of its stats channel
+fn link_validator_with_cdps(
+    parallel: &str,
+) -> (
+    LinkValidator<RdhCRU<V7>>,
+    std::sync::mpsc::Receiver<StatType>,
+) {
+    let config = <fastpasta::util::config::Opt as structopt::StructOpt>::from_iter([
+        "fastpasta",
+        "check",
+        "all",
+        "its",
+        "--parallel",
+        parallel,
+    ]);
+    let (send_stats, recv_stats) = std::sync::mpsc::channel();
+    let (send_data, recv_data) = crossbeam_channel::unbounded();
+    single_link_cdps()
+        .into_iter()
+        .for_each(|cdp| send_data.send(cdp).unwrap());
+    (
+        LinkValidator::new(&config, send_stats, recv_data),
+        recv_stats,
+    )
+}
+
+// The speedup of `--parallel payload` scales with the number of cores, run with e.g.: cargo bench --bench payload_parallel_benchmark
+fn bench_parallel_payload(c: &mut Criterion) {
+    let mut group = c.benchmark_group("single_link_payload_checks");
+    for parallel in ["link", "payload"] {
+        group.bench_with_input(
+            BenchmarkId::new("parallel", parallel),
+            parallel,
+            |b, parallel| {
+                b.iter_batched(
+                    || link_validator_with_cdps(parallel),
+                    |(mut link_validator, recv_stats)| {
+                        link_validator.run();
+                        recv_stats
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_parallel_payload);
+criterion_main!(benches);
@@ -0,0 +1,83 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use fastpasta::stats::{accumulators::Accumulators, stats_controller::StatType};
+
+const CDP_CHUNK_SIZE: usize = 100;
+const PAYLOAD_SIZE: u32 = 5024;
+
+/// Drains the stats channel on a separate thread like the StatsController would, and returns the number of messages received.
+fn spawn_stats_consumer(
+    recv: std::sync::mpsc::Receiver<StatType>,
+) -> std::thread::JoinHandle<(u64, Accumulators)> {
+    std::thread::spawn(move || {
+        let mut messages = 0;
+        let mut totals = Accumulators::default();
+        for stat in recv {
+            messages += 1;
+            match stat {
+                StatType::RDHsSeen(val) => totals.rdhs_seen += val as u64,
+                StatType::PayloadSize(size) => totals.payload_size += size as u64,
+                StatType::HBFsSeen(val) => totals.hbfs_seen += val,
+                StatType::Accumulated(acc) => totals.merge(&acc),
+                _ => (),
+            }
+        }
+        (messages, totals)
+    })
+}
+
+/// Sends one message per counter per RDH
+fn send_per_rdh(rdhs: usize) -> (u64, Accumulators) {
+    let (send, recv) = std::sync::mpsc::channel();
+    let consumer = spawn_stats_consumer(recv);
+    for _ in 0..rdhs {
+        send.send(StatType::RDHsSeen(1)).unwrap();
+        send.send(StatType::PayloadSize(PAYLOAD_SIZE)).unwrap();
+        send.send(StatType::HBFsSeen(1)).unwrap();
+    }
+    drop(send);
+    consumer.join().unwrap()
+}
+
+/// Accumulates the counters locally and sends them once per CDP chunk
+fn send_per_chunk(rdhs: usize) -> (u64, Accumulators) {
+    let (send, recv) = std::sync::mpsc::channel();
+    let consumer = spawn_stats_consumer(recv);
+    let mut acc = Accumulators::default();
+    for rdh_idx in 0..rdhs {
+        acc.rdhs_seen += 1;
+        acc.payload_size += PAYLOAD_SIZE as u64;
+        acc.hbfs_seen += 1;
+        if (rdh_idx + 1) % CDP_CHUNK_SIZE == 0 {
+            acc.flush(&send);
+        }
+    }
+    acc.flush(&send);
+    drop(send);
+    consumer.join().unwrap()
+}
+
+// Run with: cargo bench --bench stats_channel_benchmark
+fn bench_stats_channel(c: &mut Criterion) {
+    // Report the traffic reduction once, the totals must be identical
+    let (per_rdh_msgs, per_rdh_totals) = send_per_rdh(100_000);
+    let (per_chunk_msgs, per_chunk_totals) = send_per_chunk(100_000);
+    assert_eq!(per_rdh_totals, per_chunk_totals);
+    println!(
+        "Stats channel messages for 100000 RDHs: per RDH: {per_rdh_msgs}, per chunk: {per_chunk_msgs} ({}x fewer)",
+        per_rdh_msgs / per_chunk_msgs
+    );
+
+    let mut group = c.benchmark_group("stats_channel");
+    for rdhs in [10_000, 100_000].iter() {
+        group.bench_with_input(BenchmarkId::new("per_rdh", rdhs), rdhs, |b, rdhs| {
+            b.iter(|| send_per_rdh(black_box(*rdhs)))
+        });
+        group.bench_with_input(BenchmarkId::new("per_chunk", rdhs), rdhs, |b, rdhs| {
+            b.iter(|| send_per_chunk(black_box(*rdhs)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_stats_channel);
+criterion_main!(benches);
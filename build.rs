@@ -0,0 +1,14 @@
+//! Sets `FASTPASTA_GIT_HASH` to the short git hash of the build, shown in the report for traceability.
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/index");
+    let hash = std::process::Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+    if let Some(hash) = hash {
+        println!("cargo:rustc-env=FASTPASTA_GIT_HASH={}", hash.trim());
+    }
+}
@@ -0,0 +1,106 @@
+//! Sanity checks pages received one at a time on a channel, e.g. from a message queue, and collects their errors with the code and memory position of each error.
+//!
+//! Each page is checked with [validate_cdp_with_sink], that passes the errors of a page to a callback as they are found.
+//!
+//! ```shell
+//! $ cargo run --example check_pages_from_channel -- tests/test-data/its_2_links_errors.raw
+//! ```
+use fastpasta::api::validate_cdp_with_sink;
+use fastpasta::util::config::Opt;
+use std::sync::mpsc::Receiver;
+use structopt::StructOpt;
+
+/// An error found in a page.
+#[derive(Debug, PartialEq)]
+struct PageError {
+    /// Memory position of the error in the input.
+    mem_pos: u64,
+    /// The error code, e.g. `E30`, if the error has one.
+    code: Option<String>,
+    /// The error message, with the memory position relative to the start of the page.
+    message: String,
+}
+
+/// Sends each page of `data` on a channel from another thread, as they would be received from a message queue.
+///
+/// The pages are split at the offset to the next RDH, at bytes 8 and 9 of each RDH.
+fn send_pages(data: Vec<u8>) -> Receiver<(u64, Vec<u8>)> {
+    let (send_page_ch, recv_page_ch) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut pos = 0;
+        while pos + 64 <= data.len() {
+            let offset_to_next = u16::from_le_bytes([data[pos + 8], data[pos + 9]]) as usize;
+            if offset_to_next == 0 {
+                break;
+            }
+            let end = (pos + offset_to_next).min(data.len());
+            if send_page_ch
+                .send((pos as u64, data[pos..end].to_vec()))
+                .is_err()
+            {
+                break;
+            }
+            pos = end;
+        }
+    });
+    recv_page_ch
+}
+
+/// Sanity checks the pages received on `recv_page_ch` with the ITS specific checks, until the sender is done.
+fn check_pages(recv_page_ch: Receiver<(u64, Vec<u8>)>) -> Vec<PageError> {
+    // The same options as `fastpasta check sanity its`
+    let checks = Opt::from_iter(["fastpasta", "check", "sanity", "its"]);
+    let mut errors = Vec::new();
+    for (page_mem_pos, page) in recv_page_ch {
+        validate_cdp_with_sink(&page, &checks, |e| {
+            errors.push(PageError {
+                mem_pos: page_mem_pos + e.mem_pos().unwrap_or(0),
+                code: e.code().map(str::to_string),
+                message: e.message().to_string(),
+            })
+        });
+    }
+    errors
+}
+
+fn main() -> std::io::Result<()> {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "tests/test-data/its_2_links_errors.raw".to_string());
+    let errors = check_pages(send_pages(std::fs::read(path)?));
+    for error in &errors {
+        println!(
+            "{:#X} {}: {}",
+            error.mem_pos,
+            error.code.as_deref().unwrap_or("-"),
+            error.message
+        );
+    }
+    println!("{} errors", errors.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_pages_without_errors() {
+        let data = std::fs::read("tests/test-data/its_2_links.raw").unwrap();
+        assert_eq!(check_pages(send_pages(data)), []);
+    }
+
+    #[test]
+    fn test_check_pages_with_bad_ihw() {
+        let mut data = std::fs::read("tests/test-data/its_2_links.raw").unwrap();
+        // The ID of the IHW at the start of the payload of the second page
+        data[0xB9] = 0xE1;
+        let errors = check_pages(send_pages(data));
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(errors[0].mem_pos, 0xB0);
+        assert_eq!(errors[0].code.as_deref(), Some("E30"));
+        assert!(errors[0]
+            .message
+            .starts_with("0x40: [E30] ID is not 0xE0: 0xE1"));
+    }
+}
@@ -0,0 +1,66 @@
+//! Counts the HBFs of each link in a file, with the [InputScanner] that the `fastpasta` binary reads its input with.
+//!
+//! An HBF is counted at its stop page, the page of an HBF with the stop bit set.
+//!
+//! ```shell
+//! $ cargo run --example count_hbfs_per_link -- tests/test-data/its_2_links.raw
+//! ```
+use fastpasta::input::input_scanner::InputScanner;
+use fastpasta::input::lib::init_reader;
+use fastpasta::input::mem_pos_tracker::MemPosTracker;
+use fastpasta::util::config::Opt;
+use fastpasta::words::lib::RDH;
+use fastpasta::words::rdh_cru::{RdhCRU, V7};
+use std::collections::BTreeMap;
+use structopt::StructOpt;
+
+/// Returns the number of HBFs of each link in the file at `path`, ordered by link ID.
+fn count_hbfs_per_link(path: &str) -> std::io::Result<BTreeMap<u8, u32>> {
+    // The same options as the command line, here only the input file
+    let config = Opt::from_iter(["fastpasta", path]);
+    let reader = init_reader(&config)?;
+    // The scanner reports stats of the input, they are not used here
+    let (stats_send_ch, _stats_recv_ch) = std::sync::mpsc::channel();
+    let mut scanner = InputScanner::new(
+        std::sync::Arc::new(config),
+        reader,
+        MemPosTracker::new(),
+        stats_send_ch,
+    );
+
+    let mut hbfs_per_link = BTreeMap::new();
+    loop {
+        // Only the RDHs are needed, the payloads are skipped
+        let rdh = match scanner.load_rdh_skip_payload::<RdhCRU<V7>>() {
+            Ok(rdh) => rdh,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        };
+        let hbfs = hbfs_per_link.entry(rdh.link_id()).or_insert(0);
+        if rdh.stop_bit() == 1 {
+            *hbfs += 1;
+        }
+    }
+    Ok(hbfs_per_link)
+}
+
+fn main() -> std::io::Result<()> {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "tests/test-data/its_2_links.raw".to_string());
+    for (link_id, hbfs) in count_hbfs_per_link(&path)? {
+        println!("Link {link_id}: {hbfs} HBFs");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_hbfs_per_link() {
+        let hbfs_per_link = count_hbfs_per_link("tests/test-data/its_2_links.raw").unwrap();
+        assert_eq!(hbfs_per_link, BTreeMap::from([(0, 3), (2, 3)]));
+    }
+}
@@ -0,0 +1,130 @@
+//! Filters the CDPs of a link into memory, with a custom [Writer] instead of the [BufferedWriter][fastpasta::write::writer::BufferedWriter] that writes to a file or stdout.
+//!
+//! The link is filtered by the [InputScanner], configured with the same `--filter-link` option as the `fastpasta` binary.
+//!
+//! ```shell
+//! $ cargo run --example filter_link_to_vec -- tests/test-data/its_2_links.raw 2
+//! ```
+use fastpasta::input::data_wrapper::CdpChunk;
+use fastpasta::input::input_scanner::{InputScanner, ScanCDP};
+use fastpasta::input::lib::init_reader;
+use fastpasta::input::mem_pos_tracker::MemPosTracker;
+use fastpasta::util::config::Opt;
+use fastpasta::words::lib::RDH;
+use fastpasta::words::rdh_cru::{RdhCRU, V7};
+use fastpasta::write::writer::Writer;
+use structopt::StructOpt;
+
+/// Writes the CDPs to a [Vec], the RDHs and payloads are buffered until they are flushed.
+struct VecWriter<T: RDH> {
+    rdhs: Vec<T>,
+    payloads: Vec<Vec<u8>>,
+    output: Vec<u8>,
+}
+
+impl<T: RDH> VecWriter<T> {
+    fn new() -> Self {
+        Self {
+            rdhs: Vec::new(),
+            payloads: Vec::new(),
+            output: Vec::new(),
+        }
+    }
+}
+
+impl<T: RDH> Writer<T> for VecWriter<T> {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
+        self.output.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn push_rdhs(&mut self, rdhs: Vec<T>) {
+        self.rdhs.extend(rdhs);
+    }
+
+    fn push_payload(&mut self, payload: Vec<u8>) {
+        self.payloads.push(payload);
+    }
+
+    fn push_cdp_chunk(&mut self, cdp_chunk: CdpChunk<T>) {
+        cdp_chunk.into_iter().for_each(|(rdh, payload, _)| {
+            self.rdhs.push(rdh);
+            self.payloads.push(payload);
+        });
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let rdhs = std::mem::take(&mut self.rdhs);
+        let payloads = std::mem::take(&mut self.payloads);
+        for (rdh, payload) in rdhs.iter().zip(payloads) {
+            self.write(rdh.to_byte_slice())?;
+            self.write(&payload)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the CDPs of the link `link_id` in the file at `path`, in the order they are in the file.
+fn filter_link_to_vec(path: &str, link_id: u8) -> std::io::Result<Vec<u8>> {
+    let config = Opt::from_iter(["fastpasta", path, "--filter-link", &link_id.to_string()]);
+    let reader = init_reader(&config)?;
+    // The scanner reports stats of the input, they are not used here
+    let (stats_send_ch, _stats_recv_ch) = std::sync::mpsc::channel();
+    let mut scanner = InputScanner::new(
+        std::sync::Arc::new(config),
+        reader,
+        MemPosTracker::new(),
+        stats_send_ch,
+    );
+
+    let mut writer = VecWriter::<RdhCRU<V7>>::new();
+    loop {
+        // Only the CDPs of the link are returned by the scanner
+        let cdp = match scanner.load_cdp::<RdhCRU<V7>>() {
+            Ok(cdp) => cdp,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        };
+        let mut cdp_chunk = CdpChunk::new();
+        cdp_chunk.push(cdp.0, cdp.1, cdp.2);
+        writer.push_cdp_chunk(cdp_chunk);
+    }
+    writer.flush()?;
+    Ok(writer.output)
+}
+
+fn main() -> std::io::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let path = args
+        .next()
+        .unwrap_or_else(|| "tests/test-data/its_2_links.raw".to_string());
+    let link_id = args
+        .next()
+        .map_or(Ok(2), |link_id| link_id.parse())
+        .map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Invalid link ID: {e}"),
+            )
+        })?;
+    let output = filter_link_to_vec(&path, link_id)?;
+    println!("Link {link_id}: {} bytes", output.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_link_to_vec() {
+        let output = filter_link_to_vec("tests/test-data/its_2_links.raw", 2).unwrap();
+        // The 3 HBFs of link 2 follow the 3 HBFs of link 0 in each orbit
+        let data = std::fs::read("tests/test-data/its_2_links.raw").unwrap();
+        let link_2_hbfs = [0x130..0x260, 0x390..0x4C0, 0x5F0..0x720]
+            .into_iter()
+            .flat_map(|hbf| data[hbf].to_vec())
+            .collect::<Vec<u8>>();
+        assert_eq!(output, link_2_hbfs);
+    }
+}
@@ -0,0 +1,398 @@
+//! Contains functions for parsing and validating a single CDP (RDH + payload) from memory, and rendering views of CDPs, without spawning any threads or channels.
+//!
+//! Useful for tools that already have a single page in memory, e.g. received from a message queue,
+//! or that show the views of `fastpasta view` themselves, e.g. in a GUI.
+//!
+//! # Example
+//! ```
+//! use fastpasta::api::{parse_cdp, validate_cdp};
+//! use fastpasta::util::config::Opt;
+//! use fastpasta::words::lib::RDH;
+//! use structopt::StructOpt;
+//!
+//! # use fastpasta::words::lib::ByteSlice;
+//! # let mut page = fastpasta::words::rdh_cru::test_data::CORRECT_RDH_CRU_V7.to_byte_slice().to_vec();
+//! # page.resize(0x13E0, 0xFF);
+//! let (rdh, payload) = parse_cdp(&page).unwrap();
+//! assert_eq!(payload.len(), rdh.payload_size() as usize);
+//!
+//! // The same options as `fastpasta check sanity`
+//! let config = Opt::from_iter(["fastpasta", "check", "sanity"]);
+//! assert!(validate_cdp(&page, &config).is_empty());
+//! ```
+use crate::{
+    input::data_wrapper::CdpChunk,
+    util::{
+        config::{HbfViewOptions, System},
+        lib::Config,
+    },
+    validators::{
+        cdp_running::CdpRunningValidator,
+        its_payload_fsm_cont::ItsPayloadFsmContinuous,
+        link_validator::payload_gbt_words,
+        rdh::{RdhCruSanityValidator, SpecializeChecks},
+    },
+    words::{
+        lib::RDH,
+        rdh_cru::{RdhCRU, V7},
+    },
+};
+use std::fmt::{self, Display};
+
+/// Size of an RDH in bytes.
+const RDH_SIZE: usize = 64;
+
+/// Errors that can occur when parsing a CDP with [parse_cdp].
+#[derive(Debug)]
+pub enum CdpParseError {
+    /// Fewer bytes than the size of an RDH.
+    TooShort(usize),
+    /// The RDH version is not 7.
+    UnsupportedVersion(u8),
+    /// The RDH `memory_size` is smaller than an RDH, or larger than the bytes available.
+    InvalidMemorySize {
+        /// The `memory_size` field of the RDH.
+        memory_size: u16,
+        /// The number of bytes available.
+        available: usize,
+    },
+}
+
+impl Display for CdpParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CdpParseError::TooShort(len) => {
+                write!(f, "{len} bytes is too short to contain an RDH")
+            }
+            CdpParseError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported RDH version: {version}")
+            }
+            CdpParseError::InvalidMemorySize {
+                memory_size,
+                available,
+            } => write!(
+                f,
+                "RDH memory size of {memory_size} bytes is invalid, {available} bytes available"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CdpParseError {}
+
+/// An error found by [validate_cdp], formatted the same way as error messages reported while checking a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError(String);
+
+impl ValidationError {
+    /// The error message.
+    pub fn message(&self) -> &str {
+        &self.0
+    }
+
+    /// The error code, e.g. `E30`, [None] if the error has no code, e.g. RDH sanity errors.
+    pub fn code(&self) -> Option<&str> {
+        crate::stats::lib::error_code_from_error_msg(&self.0)
+    }
+
+    /// The memory position of the error, relative to the start of the byte slice of the CDP.
+    pub fn mem_pos(&self) -> Option<u64> {
+        crate::stats::lib::mem_pos_from_error_msg(&self.0)
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Parses a single CDP from a byte slice, starting with an RDH CRU v7.
+///
+/// Returns the RDH and the payload, which is the `memory_size` of the RDH minus the RDH itself.
+/// Any bytes beyond the `memory_size` are ignored.
+pub fn parse_cdp(bytes: &[u8]) -> Result<(RdhCRU<V7>, &[u8]), CdpParseError> {
+    if bytes.len() < RDH_SIZE {
+        return Err(CdpParseError::TooShort(bytes.len()));
+    }
+    let rdh = RdhCRU::<V7>::load(&mut &bytes[..RDH_SIZE])
+        .expect("Loading an RDH from a slice of sufficient size cannot fail");
+    if rdh.version() != 7 {
+        return Err(CdpParseError::UnsupportedVersion(rdh.version()));
+    }
+    let memory_size = rdh.memory_size;
+    if (memory_size as usize) < RDH_SIZE || memory_size as usize > bytes.len() {
+        return Err(CdpParseError::InvalidMemorySize {
+            memory_size,
+            available: bytes.len(),
+        });
+    }
+    Ok((rdh, &bytes[RDH_SIZE..memory_size as usize]))
+}
+
+/// Parses and validates a single CDP from a byte slice.
+///
+/// Performs the RDH sanity checks, and if the target system of the check of the `config` is ITS, the payload is checked as well.
+/// The payload is checked with a validator that is freshly created for this CDP, so no state is carried over from other CDPs.
+/// The payload checks use the same options of the `config` as the checks of a file, warnings are logged and not returned.
+/// The payload of a stop page may start with the DDW0 that closes the HBF, the payload of any other page must start with an IHW.
+/// Memory positions in the error messages are relative to the start of the byte slice.
+pub fn validate_cdp(bytes: &[u8], config: &impl Config) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    validate_cdp_with_sink(bytes, config, |e| errors.push(e));
+    errors
+}
+
+/// Same as [validate_cdp], but each error is passed to `sink` as it is found instead of being collected.
+///
+/// Useful to route the errors of CDPs received one at a time, e.g. from a channel, to the caller's own error handling.
+pub fn validate_cdp_with_sink(
+    bytes: &[u8],
+    config: &impl Config,
+    mut sink: impl FnMut(ValidationError),
+) {
+    let (rdh, payload) = match parse_cdp(bytes) {
+        Ok(cdp) => cdp,
+        Err(e) => return sink(ValidationError(format!("{:#X}: {e}", 0))),
+    };
+    let target = config.check().and_then(|check| check.target());
+
+    let mut rdh_sanity_validator = match target {
+        Some(System::ITS) => RdhCruSanityValidator::with_specialization(SpecializeChecks::ITS),
+        None => RdhCruSanityValidator::default(),
+    };
+    if let Err(e) = rdh_sanity_validator.sanity_check(&rdh) {
+        sink(ValidationError(format!("{:#X}: {e}", 0)));
+    }
+
+    if let Some(System::ITS) = target {
+        if !payload.is_empty() {
+            let mut cdp_validator = CdpRunningValidator::new_collecting(config);
+            cdp_validator.set_current_rdh(&rdh, 0);
+            if rdh.stop_bit() == 1 {
+                cdp_validator.start_at_stop_page();
+            }
+            let gbt_wide = config.gbt_wide();
+            match payload_gbt_words(payload, rdh.data_format(), gbt_wide) {
+                Ok(gbt_word_chunks) => {
+                    if gbt_wide {
+                        gbt_word_chunks
+                            .for_each(|wide_word| cdp_validator.check_wide(wide_word, None));
+                    } else {
+                        gbt_word_chunks.for_each(|gbt_word| cdp_validator.check(&gbt_word[..10]));
+                    }
+                    cdp_validator.check_payload_end();
+                }
+                Err(e) => sink(ValidationError(e)),
+            }
+            cdp_validator
+                .take_errors()
+                .into_iter()
+                .map(ValidationError)
+                .for_each(sink);
+        }
+    }
+}
+
+/// Renders the HBF view of the CDPs in a [CdpChunk] as text, the same way as `fastpasta view hbf` prints it.
+///
+/// Payloads that can't be split into GBT words are left out of the view, use [write_hbf_view] to get their errors.
+pub fn render_hbf_view<T: RDH>(cdp_chunk: CdpChunk<T>, options: &HbfViewOptions) -> String {
+    let mut view = Vec::new();
+    write_hbf_view(cdp_chunk, options, &mut view).expect("Writing to a Vec cannot fail");
+    String::from_utf8(view).expect("The HBF view is valid UTF-8")
+}
+
+/// Writes the HBF view of the CDPs in a [CdpChunk] to any writer, e.g. a file or an in-memory buffer.
+///
+/// The words are decoded with a payload state machine that is freshly created for this chunk, so the chunk should start at the beginning of an HBF.
+/// Returns the errors of the payloads that can't be split into GBT words, which are left out of the view.
+pub fn write_hbf_view<T: RDH>(
+    cdp_chunk: CdpChunk<T>,
+    options: &HbfViewOptions,
+    writer: &mut impl std::io::Write,
+) -> std::io::Result<Vec<ValidationError>> {
+    let mut errors = Vec::new();
+    crate::view::hbf_view::write_hbf_view(
+        &cdp_chunk,
+        &mut ItsPayloadFsmContinuous::default(),
+        options,
+        false,
+        writer,
+        |e| errors.push(ValidationError(e)),
+    )?;
+    Ok(errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::config::Opt;
+
+    /// The RDH from `test_load_rdhcruv7_from_byte_slice` with `offset_new_packet` and `memory_size` set to 0x60,
+    /// followed by a payload of an IHW, TDH and TDT padded to 16 bytes.
+    const CDP_V7: [u8; 96] = [
+        0x07, 0x40, 0x2a, 0x50, 0x00, 0x20, 0x00, 0x00, 0x60, 0x00, 0x60, 0x00, 0x00, 0x00, 0x18,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x75, 0xd5, 0x7d, 0x0b, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x6a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, // End of RDH
+        0xFF, 0x3F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE0, // IHW
+        0x00, 0x1A, 0x00, 0x00, 0x75, 0xD5, 0x7D, 0x0B, 0x00, 0xE8, // TDH
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xF0, // TDT
+        0xFF, 0xFF, // Padding
+    ];
+
+    fn config_from(args: &[&str]) -> Opt {
+        <Opt as structopt::StructOpt>::from_iter(std::iter::once(&"fastpasta").chain(args))
+    }
+
+    #[test]
+    fn test_parse_cdp() {
+        let (rdh, payload) = parse_cdp(&CDP_V7).unwrap();
+        assert_eq!(rdh.link_id(), 0);
+        assert_eq!(rdh.data_format(), 2);
+        assert_eq!(payload.len(), 32);
+        assert_eq!(payload, &CDP_V7[64..]);
+    }
+
+    #[test]
+    fn test_parse_cdp_too_short() {
+        let err = parse_cdp(&CDP_V7[..63]).unwrap_err();
+        assert!(matches!(err, CdpParseError::TooShort(63)));
+    }
+
+    #[test]
+    fn test_parse_cdp_truncated_payload() {
+        let err = parse_cdp(&CDP_V7[..80]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "RDH memory size of 96 bytes is invalid, 80 bytes available"
+        );
+    }
+
+    #[test]
+    fn test_parse_cdp_bad_version() {
+        let mut cdp = CDP_V7;
+        cdp[0] = 6;
+        let err = parse_cdp(&cdp).unwrap_err();
+        assert!(matches!(err, CdpParseError::UnsupportedVersion(6)));
+    }
+
+    #[test]
+    fn test_validate_cdp_valid() {
+        let checks = config_from(&["check", "all", "its"]);
+        let errors = validate_cdp(&CDP_V7, &checks);
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+
+    #[test]
+    fn test_validate_cdp_uses_check_options() {
+        // The TDH is internally triggered, which is an error if the run is configured for external triggers
+        let config = config_from(&["check", "all", "its", "--trigger-source", "external"]);
+        let errors = validate_cdp(&CDP_V7, &config);
+        assert!(
+            errors.iter().any(|e| e.message().starts_with(
+                "0x4A: [E43] TDH internal_trigger is 1, expected 0 for --trigger-source external"
+            )),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn test_validate_cdp_bad_ihw() {
+        let mut cdp = CDP_V7;
+        cdp[73] = 0xE1;
+        let checks = config_from(&["check", "sanity", "its"]);
+        let errors = validate_cdp(&cdp, &checks);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].message(),
+            "0x40: [E30] ID is not 0xE0: 0xE1  [FF 3F 00 00 00 00 00 00 00 E1]"
+        );
+    }
+
+    #[test]
+    fn test_validate_cdp_stop_page() {
+        // A stop page with only a DDW0
+        let mut cdp = [0xFF; 80];
+        cdp[..64].copy_from_slice(&CDP_V7[..64]);
+        cdp[8] = 80; // offset_new_packet
+        cdp[10] = 80; // memory_size
+        cdp[36] = 1; // pages_counter
+        cdp[38] = 1; // stop_bit
+        cdp[64..74].copy_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE4]);
+        let checks = config_from(&["check", "sanity", "its"]);
+        let errors = validate_cdp(&cdp, &checks);
+        assert!(errors.is_empty(), "{errors:?}");
+        // Without the stop bit, the page should start with an IHW
+        cdp[38] = 0;
+        let errors = validate_cdp(&cdp, &checks);
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(errors[0].code(), Some("E30"));
+    }
+
+    #[test]
+    fn test_validate_cdp_with_sink() {
+        let mut cdp = CDP_V7;
+        cdp[73] = 0xE1;
+        let checks = config_from(&["check", "sanity", "its"]);
+        let mut errors = Vec::new();
+        validate_cdp_with_sink(&cdp, &checks, |e| {
+            errors.push((e.code().map(str::to_string), e.mem_pos()))
+        });
+        assert_eq!(errors, [(Some("E30".to_string()), Some(0x40))]);
+    }
+
+    #[test]
+    fn test_validate_cdp_payload_skipped_without_target() {
+        let mut cdp = CDP_V7;
+        cdp[73] = 0xE1;
+        let checks = config_from(&["check", "sanity"]);
+        assert!(validate_cdp(&cdp, &checks).is_empty());
+    }
+
+    #[test]
+    fn test_validate_cdp_bad_rdh() {
+        let mut cdp = CDP_V7;
+        cdp[24] = 0xFF; // data_format
+        let checks = config_from(&[]);
+        let errors = validate_cdp(&cdp, &checks);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0]
+            .message()
+            .starts_with("0x0: RDH sanity check failed: data_format = 0xff"));
+    }
+
+    #[test]
+    fn test_render_hbf_view() {
+        let view = render_hbf_view(
+            crate::view::hbf_view::tests::cdp_chunk_fixture(),
+            &HbfViewOptions::default(),
+        );
+        let lines: Vec<&str> = view.lines().filter(|line| !line.is_empty()).collect();
+        assert!(lines[0].starts_with("Memory    Word"));
+        assert_eq!(lines.len(), 2 + 5);
+        assert!(lines[2].starts_with("       0: RDH v7"), "{}", lines[2]);
+        assert!(lines[3].starts_with("      40: IHW [FF 3F 00 00 00 00 00 00 00 E0]"));
+        assert!(lines[6].starts_with("      5E: DDW [00 00 00 00 00 00 00 00 00 E4]"));
+    }
+
+    #[test]
+    fn test_write_hbf_view_returns_payload_errors() {
+        let mut cdp_chunk = crate::view::hbf_view::tests::cdp_chunk_fixture();
+        let mut bad_rdh = crate::words::rdh_cru::test_data::CORRECT_RDH_CRU_V7;
+        bad_rdh.rdh2.pages_counter = 1;
+        cdp_chunk.push(bad_rdh, vec![0xFF; 20], 0x100);
+        let options = HbfViewOptions {
+            no_rdh: true,
+            ..Default::default()
+        };
+        let mut view = Vec::new();
+        let errors = write_hbf_view(cdp_chunk, &options, &mut view).unwrap();
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        let view = String::from_utf8(view).unwrap();
+        assert!(!view.contains("RDH"));
+        assert_eq!(view.lines().filter(|line| line.contains(": ")).count(), 4);
+    }
+}
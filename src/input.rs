@@ -1,7 +1,10 @@
 //! All functionality related to reading data from a file or stdin, tracking memory offset and filtering data.
 
 pub mod bufreader_wrapper;
+pub mod cdp_filter;
+pub mod cdp_trace;
 pub mod data_wrapper;
+pub mod input_probe;
 pub mod input_scanner;
 pub mod lib;
 pub mod mem_pos_tracker;
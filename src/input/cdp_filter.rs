@@ -0,0 +1,539 @@
+//! Contains the [CdpFilter] trait that the [InputScanner][super::input_scanner::InputScanner] consults once per RDH to decide if a CDP is forwarded, and the filters implementing it.
+//!
+//! Filters are combined with [CdpFilter::and], a CDP is only kept if all the combined filters keep it.
+//! An [Option] of a filter is itself a filter, that keeps everything if it is [None].
+use crate::stats::stats_controller::StatType;
+use crate::util::config::{ItsStave, PacketRange};
+use crate::util::rdh_predicate::RdhPredicate;
+use crate::words::lib::RDH;
+use crate::words::rdh::TriggerType;
+use std::collections::VecDeque;
+
+/// What the [InputScanner][super::input_scanner::InputScanner] should do with the CDP of an RDH.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterDecision {
+    /// Forward the CDP.
+    Keep,
+    /// Skip the payload of the CDP and continue with the next RDH.
+    SkipPayload,
+    /// No more CDPs will be kept, stop reading the input.
+    Stop,
+}
+
+/// Trait for a filter that decides which CDPs are forwarded from the input.
+pub trait CdpFilter {
+    /// Decides what to do with the CDP of the [RDH] at the memory position `mem_pos`.
+    ///
+    /// Takes `&mut self` as some filters keep track of what they have already seen.
+    fn keep<T: RDH>(&mut self, rdh: &T, mem_pos: u64) -> FilterDecision;
+
+    /// Called when the end of the input is reached.
+    fn end_of_input(&mut self) {}
+
+    /// Combines two filters, the CDP is only kept if both filters keep it.
+    fn and<F: CdpFilter>(self, other: F) -> And<Self, F>
+    where
+        Self: Sized,
+    {
+        And(self, other)
+    }
+}
+
+/// Two filters combined with [CdpFilter::and].
+///
+/// The second filter is only consulted if the first keeps the CDP.
+#[derive(Debug)]
+pub struct And<A: CdpFilter, B: CdpFilter>(pub A, pub B);
+
+impl<A: CdpFilter, B: CdpFilter> CdpFilter for And<A, B> {
+    fn keep<T: RDH>(&mut self, rdh: &T, mem_pos: u64) -> FilterDecision {
+        match self.0.keep(rdh, mem_pos) {
+            FilterDecision::Keep => self.1.keep(rdh, mem_pos),
+            decision => decision,
+        }
+    }
+
+    fn end_of_input(&mut self) {
+        self.0.end_of_input();
+        self.1.end_of_input();
+    }
+}
+
+impl<F: CdpFilter> CdpFilter for Option<F> {
+    fn keep<T: RDH>(&mut self, rdh: &T, mem_pos: u64) -> FilterDecision {
+        match self {
+            Some(filter) => filter.keep(rdh, mem_pos),
+            None => FilterDecision::Keep,
+        }
+    }
+
+    fn end_of_input(&mut self) {
+        if let Some(filter) = self {
+            filter.end_of_input();
+        }
+    }
+}
+
+/// Keeps the CDPs of any of a list of links.
+#[derive(Debug, Clone)]
+pub struct FilterLink(pub Vec<u8>);
+
+impl CdpFilter for FilterLink {
+    fn keep<T: RDH>(&mut self, rdh: &T, _mem_pos: u64) -> FilterDecision {
+        if self.0.contains(&rdh.link_id()) {
+            FilterDecision::Keep
+        } else {
+            FilterDecision::SkipPayload
+        }
+    }
+}
+
+/// Keeps the CDPs of all links except a list of links.
+#[derive(Debug, Clone)]
+pub struct ExcludeLink(pub Vec<u8>);
+
+impl CdpFilter for ExcludeLink {
+    fn keep<T: RDH>(&mut self, rdh: &T, _mem_pos: u64) -> FilterDecision {
+        if self.0.contains(&rdh.link_id()) {
+            FilterDecision::SkipPayload
+        } else {
+            FilterDecision::Keep
+        }
+    }
+}
+
+/// Keeps the CDPs with a FEE ID.
+#[derive(Debug, Clone, Copy)]
+pub struct FilterFee(pub u16);
+
+impl CdpFilter for FilterFee {
+    fn keep<T: RDH>(&mut self, rdh: &T, _mem_pos: u64) -> FilterDecision {
+        if rdh.fee_id() == self.0 {
+            FilterDecision::Keep
+        } else {
+            FilterDecision::SkipPayload
+        }
+    }
+}
+
+/// Keeps the CDPs of a CRU.
+#[derive(Debug, Clone, Copy)]
+pub struct FilterCru(pub u16);
+
+impl CdpFilter for FilterCru {
+    fn keep<T: RDH>(&mut self, rdh: &T, _mem_pos: u64) -> FilterDecision {
+        if rdh.cru_id() == self.0 {
+            FilterDecision::Keep
+        } else {
+            FilterDecision::SkipPayload
+        }
+    }
+}
+
+/// Keeps the CDPs with the bit of a trigger type set in the RDH trigger type.
+#[derive(Debug, Clone, Copy)]
+pub struct FilterTrigger(pub TriggerType);
+
+impl CdpFilter for FilterTrigger {
+    fn keep<T: RDH>(&mut self, rdh: &T, _mem_pos: u64) -> FilterDecision {
+        if self.0.is_set(rdh.trigger_type()) {
+            FilterDecision::Keep
+        } else {
+            FilterDecision::SkipPayload
+        }
+    }
+}
+
+/// Keeps the CDPs whose FEE ID belongs to an ITS stave.
+#[derive(Debug, Clone, Copy)]
+pub struct FilterItsStave(pub ItsStave);
+
+impl CdpFilter for FilterItsStave {
+    fn keep<T: RDH>(&mut self, rdh: &T, _mem_pos: u64) -> FilterDecision {
+        if ItsStave::from_fee_id(rdh.fee_id()) == self.0 {
+            FilterDecision::Keep
+        } else {
+            FilterDecision::SkipPayload
+        }
+    }
+}
+
+/// Keeps the CDPs whose RDH orbit is in an inclusive range.
+///
+/// The orbits of a link only increase, so when all links seen so far are past the end of the range, the rest of the input is not needed.
+#[derive(Debug, Clone)]
+pub struct FilterOrbitRange {
+    start: u32,
+    end: u32,
+    // CRU ID and link ID of each link seen, and if it is past the end of the range
+    links_past_end: Vec<((u16, u8), bool)>,
+}
+
+impl FilterOrbitRange {
+    /// Creates a new [FilterOrbitRange] from the first and last orbit to keep.
+    pub fn new(start: u32, end: u32) -> Self {
+        Self {
+            start,
+            end,
+            links_past_end: Vec::new(),
+        }
+    }
+}
+
+impl CdpFilter for FilterOrbitRange {
+    fn keep<T: RDH>(&mut self, rdh: &T, _mem_pos: u64) -> FilterDecision {
+        let orbit = rdh.rdh1().orbit;
+        let link = (rdh.cru_id(), rdh.link_id());
+        let past_end = orbit > self.end;
+        match self
+            .links_past_end
+            .iter_mut()
+            .find(|(seen, _)| *seen == link)
+        {
+            Some((_, link_past_end)) => *link_past_end = past_end,
+            None => self.links_past_end.push((link, past_end)),
+        }
+        if orbit >= self.start && !past_end {
+            FilterDecision::Keep
+        } else if self.links_past_end.iter().all(|(_, past_end)| *past_end) {
+            FilterDecision::Stop
+        } else {
+            FilterDecision::SkipPayload
+        }
+    }
+}
+
+/// Keeps the CDPs whose packet number is in a [PacketRange].
+///
+/// The packet number of a link is its 8 bit `packet_counter`, plus 256 for each time the `packet_counter` wrapped around since the start of the input,
+/// a wrap-around is seen as a `packet_counter` lower than the one before on the same link.
+/// The packet numbers of a link only increase, so when all links seen so far are past the end of the range, the rest of the input is not needed.
+#[derive(Debug, Clone)]
+pub struct FilterPacketRange {
+    range: PacketRange,
+    // CRU ID and link ID of each link seen, and its last packet number
+    links_last_packet: Vec<((u16, u8), u64)>,
+}
+
+impl FilterPacketRange {
+    /// Creates a new [FilterPacketRange] from the range of packet numbers to keep.
+    pub fn new(range: PacketRange) -> Self {
+        Self {
+            range,
+            links_last_packet: Vec::new(),
+        }
+    }
+}
+
+impl CdpFilter for FilterPacketRange {
+    fn keep<T: RDH>(&mut self, rdh: &T, _mem_pos: u64) -> FilterDecision {
+        let packet_counter = rdh.packet_counter() as u64;
+        let link = (rdh.cru_id(), rdh.link_id());
+        let packet_number = match self
+            .links_last_packet
+            .iter_mut()
+            .find(|(seen, _)| *seen == link)
+        {
+            Some((_, last_packet)) => {
+                let mut packet_number = (*last_packet & !0xFF) | packet_counter;
+                if packet_number < *last_packet {
+                    packet_number += 0x100;
+                }
+                *last_packet = packet_number;
+                packet_number
+            }
+            None => {
+                self.links_last_packet.push((link, packet_counter));
+                packet_counter
+            }
+        };
+        if self.range.contains(packet_number) {
+            FilterDecision::Keep
+        } else if self
+            .links_last_packet
+            .iter()
+            .all(|(_, last_packet)| *last_packet > self.range.end)
+        {
+            FilterDecision::Stop
+        } else {
+            FilterDecision::SkipPayload
+        }
+    }
+}
+
+/// Keeps the CDPs whose RDH matches an [RdhPredicate].
+#[derive(Debug, Clone)]
+pub struct FilterWhere(pub RdhPredicate);
+
+impl CdpFilter for FilterWhere {
+    fn keep<T: RDH>(&mut self, rdh: &T, _mem_pos: u64) -> FilterDecision {
+        if self.0.eval(rdh) {
+            FilterDecision::Keep
+        } else {
+            FilterDecision::SkipPayload
+        }
+    }
+}
+
+/// Keeps the CDPs with an RDH at one of a list of memory offsets.
+///
+/// Offsets that are passed without finding an RDH at them are reported as errors.
+/// When all offsets are found, the rest of the input is not needed.
+#[derive(Debug)]
+pub struct FilterOffsets {
+    // Sorted memory offsets of the CDPs to extract that are not reached yet
+    offsets: VecDeque<u64>,
+    stats_ch: std::sync::mpsc::Sender<StatType>,
+}
+
+impl FilterOffsets {
+    /// Creates a new [FilterOffsets] from the offsets to extract, and a producer channel for [StatType] to report offsets that are not found.
+    pub fn new(mut offsets: Vec<u64>, stats_ch: std::sync::mpsc::Sender<StatType>) -> Self {
+        offsets.sort_unstable();
+        offsets.dedup();
+        Self {
+            offsets: offsets.into(),
+            stats_ch,
+        }
+    }
+
+    fn report_offset_not_extracted(&self, offset: u64) {
+        self.stats_ch
+            .send(StatType::Error(format!(
+                "{offset:#X}: Offset to extract is not the memory position of an RDH"
+            )))
+            .unwrap();
+    }
+}
+
+impl CdpFilter for FilterOffsets {
+    fn keep<T: RDH>(&mut self, _rdh: &T, mem_pos: u64) -> FilterDecision {
+        while matches!(self.offsets.front(), Some(offset) if *offset < mem_pos) {
+            let missed_offset = self.offsets.pop_front().unwrap();
+            self.report_offset_not_extracted(missed_offset);
+        }
+        if self.offsets.front() == Some(&mem_pos) {
+            self.offsets.pop_front();
+            FilterDecision::Keep
+        } else if self.offsets.is_empty() {
+            FilterDecision::Stop
+        } else {
+            FilterDecision::SkipPayload
+        }
+    }
+
+    fn end_of_input(&mut self) {
+        while let Some(offset) = self.offsets.pop_front() {
+            self.report_offset_not_extracted(offset);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh_cru::test_data::{CORRECT_RDH_CRU_V6, CORRECT_RDH_CRU_V7};
+
+    #[test]
+    fn test_exclude_link() {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        let mut filter = ExcludeLink(vec![3, 8]);
+        rdh.link_id = 8;
+        assert_eq!(filter.keep(&rdh, 0), FilterDecision::SkipPayload);
+        rdh.link_id = 2;
+        assert_eq!(filter.keep(&rdh, 0), FilterDecision::Keep);
+    }
+
+    #[test]
+    fn test_filter_fee() {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        let mut filter = FilterFee(0x502A);
+        assert_eq!(filter.keep(&rdh, 0), FilterDecision::Keep);
+        rdh.rdh0.fee_id = crate::words::rdh::FeeId(0x502B);
+        assert_eq!(filter.keep(&rdh, 0), FilterDecision::SkipPayload);
+    }
+
+    #[test]
+    fn test_filter_packet_range() {
+        let mut filter = FilterPacketRange::new("250..260".parse().unwrap());
+        let rdh_of = |link_id: u8, packet_counter: u8| {
+            let mut rdh = CORRECT_RDH_CRU_V7;
+            rdh.link_id = link_id;
+            rdh.packet_counter = packet_counter;
+            rdh
+        };
+        assert_eq!(filter.keep(&rdh_of(3, 249), 0), FilterDecision::SkipPayload);
+        assert_eq!(filter.keep(&rdh_of(3, 250), 0), FilterDecision::Keep);
+        assert_eq!(filter.keep(&rdh_of(0, 4), 0), FilterDecision::SkipPayload);
+        assert_eq!(filter.keep(&rdh_of(3, 255), 0), FilterDecision::Keep);
+        // Wrapped around, packet 256 and 260
+        assert_eq!(filter.keep(&rdh_of(3, 0), 0), FilterDecision::Keep);
+        assert_eq!(filter.keep(&rdh_of(3, 4), 0), FilterDecision::Keep);
+        // Link 0 is not past the end yet
+        assert_eq!(filter.keep(&rdh_of(3, 5), 0), FilterDecision::SkipPayload);
+        // Link 0 wraps around to packet 259, then to packet 356, past the end
+        assert_eq!(filter.keep(&rdh_of(0, 200), 0), FilterDecision::SkipPayload);
+        assert_eq!(filter.keep(&rdh_of(0, 3), 0), FilterDecision::Keep);
+        assert_eq!(filter.keep(&rdh_of(0, 100), 0), FilterDecision::Stop);
+    }
+
+    #[test]
+    fn test_filter_orbit_range() {
+        let mut filter = FilterOrbitRange::new(0x0B7D_D576, 0x0B7D_D577);
+        let rdh_of = |link_id: u8, orbit: u32| {
+            let mut rdh = CORRECT_RDH_CRU_V7;
+            rdh.link_id = link_id;
+            rdh.rdh1.orbit = orbit;
+            rdh
+        };
+        assert_eq!(
+            filter.keep(&rdh_of(0, 0x0B7D_D575), 0),
+            FilterDecision::SkipPayload
+        );
+        assert_eq!(
+            filter.keep(&rdh_of(2, 0x0B7D_D576), 0),
+            FilterDecision::Keep
+        );
+        assert_eq!(
+            filter.keep(&rdh_of(0, 0x0B7D_D577), 0),
+            FilterDecision::Keep
+        );
+        // Link 2 is not past the end yet
+        assert_eq!(
+            filter.keep(&rdh_of(0, 0x0B7D_D578), 0),
+            FilterDecision::SkipPayload
+        );
+        assert_eq!(
+            filter.keep(&rdh_of(2, 0x0B7D_D578), 0),
+            FilterDecision::Stop
+        );
+    }
+
+    #[test]
+    fn test_filter_cru() {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        let cru_id = rdh.cru_id();
+        let link_id = rdh.link_id;
+        // Composes with the link filter
+        let mut filter = FilterLink(vec![link_id]).and(FilterCru(cru_id));
+        assert_eq!(filter.keep(&rdh, 0), FilterDecision::Keep);
+        rdh.cruid_dw = crate::words::rdh::CruidDw(cru_id + 1);
+        assert_eq!(filter.keep(&rdh, 0), FilterDecision::SkipPayload);
+        rdh.cruid_dw = crate::words::rdh::CruidDw(cru_id);
+        rdh.link_id = link_id + 1;
+        assert_eq!(filter.keep(&rdh, 0), FilterDecision::SkipPayload);
+    }
+
+    #[test]
+    fn test_filter_trigger() {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        let mut filter = FilterTrigger(TriggerType::PhT);
+        // ORBIT|HB|TF
+        rdh.rdh2.trigger_type = 0x803;
+        assert_eq!(filter.keep(&rdh, 0), FilterDecision::SkipPayload);
+        rdh.rdh2.trigger_type = 0x813;
+        assert_eq!(filter.keep(&rdh, 0), FilterDecision::Keep);
+        assert_eq!(
+            FilterTrigger(TriggerType::Soc).keep(&rdh, 0),
+            FilterDecision::SkipPayload
+        );
+    }
+
+    #[test]
+    fn test_filter_its_stave() {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        // FEE ID 0x5029 is layer 5, stave 41
+        rdh.rdh0.fee_id = crate::words::rdh::FeeId(0x5029);
+        let mut filter = FilterItsStave("L5_41".parse().unwrap());
+        assert_eq!(filter.keep(&rdh, 0), FilterDecision::Keep);
+        // Another stave of the same layer on the same link
+        rdh.rdh0.fee_id = crate::words::rdh::FeeId(0x5028);
+        assert_eq!(filter.keep(&rdh, 0), FilterDecision::SkipPayload);
+        // The same stave number in another layer
+        rdh.rdh0.fee_id = crate::words::rdh::FeeId(0x3029);
+        assert_eq!(filter.keep(&rdh, 0), FilterDecision::SkipPayload);
+    }
+
+    #[test]
+    fn test_filter_where() {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        let mut filter = FilterWhere("stop_bit==1 || pages_counter>=2".parse().unwrap());
+        assert_eq!(filter.keep(&rdh, 0), FilterDecision::SkipPayload);
+        rdh.rdh2.stop_bit = 1;
+        assert_eq!(filter.keep(&rdh, 0), FilterDecision::Keep);
+    }
+
+    #[test]
+    fn test_filter_any_of_links() {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        let mut filter = FilterLink(vec![0, 2, 5]);
+        for (link_id, decision) in [
+            (0, FilterDecision::Keep),
+            (1, FilterDecision::SkipPayload),
+            (5, FilterDecision::Keep),
+            (6, FilterDecision::SkipPayload),
+        ] {
+            rdh.link_id = link_id;
+            assert_eq!(filter.keep(&rdh, 0), decision);
+        }
+    }
+
+    /// Link IDs of the RDHs kept by a [FilterLink] of links 0 and 2
+    fn links_kept<T: RDH>(rdhs: &[T]) -> Vec<u8> {
+        let mut filter = FilterLink(vec![0, 2]);
+        rdhs.iter()
+            .filter(|rdh| filter.keep(*rdh, 0) == FilterDecision::Keep)
+            .map(|rdh| rdh.link_id())
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_link_rdh_v6_and_v7() {
+        let rdhs_v6: Vec<_> = [0, 1, 2, 3]
+            .into_iter()
+            .map(|link_id| {
+                let mut rdh = CORRECT_RDH_CRU_V6;
+                rdh.link_id = link_id;
+                rdh
+            })
+            .collect();
+        let rdhs_v7: Vec<_> = [0, 1, 2, 3]
+            .into_iter()
+            .map(|link_id| {
+                let mut rdh = CORRECT_RDH_CRU_V7;
+                rdh.link_id = link_id;
+                rdh
+            })
+            .collect();
+        assert_eq!(links_kept(&rdhs_v6), [0, 2]);
+        assert_eq!(links_kept(&rdhs_v7), [0, 2]);
+    }
+
+    #[test]
+    fn test_and_keeps_only_if_both_keep() {
+        let rdh = CORRECT_RDH_CRU_V7;
+        let link_id = rdh.link_id();
+        let mut both = FilterLink(vec![link_id]).and(FilterFee(0x502A));
+        assert_eq!(both.keep(&rdh, 0), FilterDecision::Keep);
+        let mut wrong_fee = FilterLink(vec![link_id]).and(FilterFee(0x502B));
+        assert_eq!(wrong_fee.keep(&rdh, 0), FilterDecision::SkipPayload);
+        let mut wrong_link = FilterLink(vec![link_id + 1]).and(FilterFee(0x502A));
+        assert_eq!(wrong_link.keep(&rdh, 0), FilterDecision::SkipPayload);
+        let mut no_filter: And<Option<FilterLink>, Option<FilterFee>> = And(None, None);
+        assert_eq!(no_filter.keep(&rdh, 0), FilterDecision::Keep);
+    }
+
+    #[test]
+    fn test_filter_offsets_stops_when_all_found() {
+        let rdh = CORRECT_RDH_CRU_V7;
+        let (send_stats_ch, recv_stats_ch) = std::sync::mpsc::channel();
+        let mut filter = FilterOffsets::new(vec![0x2000, 0x40, 0x1000], send_stats_ch);
+        assert_eq!(filter.keep(&rdh, 0), FilterDecision::SkipPayload);
+        assert_eq!(filter.keep(&rdh, 0x1000), FilterDecision::Keep);
+        assert_eq!(filter.keep(&rdh, 0x2000), FilterDecision::Keep);
+        assert_eq!(filter.keep(&rdh, 0x3000), FilterDecision::Stop);
+        let errors: Vec<StatType> = recv_stats_ch.try_iter().collect();
+        assert!(matches!(errors.as_slice(),
+            [StatType::Error(msg)] if msg == "0x40: Offset to extract is not the memory position of an RDH"));
+    }
+}
@@ -0,0 +1,79 @@
+//! Contains the [CdpTracer] that writes one line per CDP to stderr as it is handed to the thread that handles it, for debugging the pipeline.
+//!
+//! The lines are written directly to stderr, independent of the verbosity, so tracing CDPs doesn't enable other log messages.
+//! With `--log-format json` they are written as JSON lines.
+use crate::util::json_logger::JsonLogger;
+use crate::util::lib::Config;
+use crate::words::lib::RDH;
+use std::io::Write;
+
+/// Writes a line for every Nth CDP handed to a thread.
+#[derive(Debug)]
+pub struct CdpTracer {
+    every: u32,
+    cdps_until_trace: u32,
+    json: bool,
+}
+
+impl CdpTracer {
+    /// Creates a new [CdpTracer] if CDP tracing is enabled in the [Config].
+    pub fn new(config: &impl Config) -> Option<Self> {
+        let every = config.trace_cdps()?;
+        Some(Self {
+            every,
+            cdps_until_trace: 0,
+            json: config.log_format() == crate::util::config::LogFormat::Json,
+        })
+    }
+
+    /// Writes the trace line of the CDP handed to the thread named `handler`, if it is one of every Nth CDP.
+    pub fn trace<T: RDH>(&mut self, rdh: &T, payload_size: usize, mem_pos: u64, handler: &str) {
+        if self.cdps_until_trace == 0 {
+            let line = Self::trace_line(rdh, payload_size, mem_pos, handler);
+            let line = if self.json {
+                JsonLogger::format_message(log::Level::Info, &line)
+            } else {
+                line
+            };
+            // Lock stderr to avoid interleaving lines from different threads
+            let _ = writeln!(std::io::stderr().lock(), "{line}");
+            self.cdps_until_trace = self.every;
+        }
+        self.cdps_until_trace -= 1;
+    }
+
+    fn trace_line<T: RDH>(rdh: &T, payload_size: usize, mem_pos: u64, handler: &str) -> String {
+        let orbit = rdh.rdh1().orbit;
+        format!(
+            "CDP {mem_pos:#X}: link {}, FEE {:#X}, orbit {:#X}, pages_counter {}, stop_bit {}, payload {payload_size} B, handled by {handler}",
+            rdh.link_id(),
+            rdh.fee_id(),
+            orbit,
+            rdh.pages_counter(),
+            rdh.stop_bit(),
+        )
+    }
+}
+
+/// Name of the current thread, as reported in the trace lines of the CDPs it handles.
+pub fn current_thread_name() -> String {
+    std::thread::current()
+        .name()
+        .unwrap_or("unnamed")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh_cru::test_data::CORRECT_RDH_CRU_V7;
+
+    #[test]
+    fn test_trace_line() {
+        let rdh = CORRECT_RDH_CRU_V7;
+        assert_eq!(
+            CdpTracer::trace_line(&rdh, rdh.payload_size() as usize, 0x13E0, "Link 0 Validator"),
+            "CDP 0x13E0: link 0, FEE 0x502A, orbit 0xB7DD575, pages_counter 0, stop_bit 0, payload 5024 B, handled by Link 0 Validator"
+        );
+    }
+}
@@ -2,6 +2,12 @@
 //!
 //! [CdpChunk] can be treated similarly to a `std::vec::Vec::<T>` where `T` is a tuple of `(impl RDH, vec<u8>, u64)`
 //!
+//! # Invariant
+//!
+//! The memory positions of the CDPs of a chunk are strictly increasing, and the first memory position of a chunk is larger than the last of the chunk before it.
+//! Position-based reporting, such as ordered errors and error-free stretches, relies on it.
+//! [CdpChunk::push] records the first violation within a chunk, and a [MemPosOrderGuard] verifies it across chunks, see [CdpChunk::mem_pos_order_violation].
+//!
 //!  # Examples
 //!
 //! ```
@@ -15,9 +21,12 @@
 //! chunk.push_tuple(cdp_tup);
 //!
 //! // Push a tuple of (RDH, payload, mem_pos) using the push method
-//! let (rdh, payload, mem_pos) = (CORRECT_RDH_CRU_V7, vec![0; 10], 0);
+//! let (rdh, payload, mem_pos) = (CORRECT_RDH_CRU_V7, vec![0; 10], 0x4A);
 //! chunk.push(rdh, payload, mem_pos);
 //!
+//! // The memory positions are strictly increasing
+//! assert!(chunk.mem_pos_order_violation().is_none());
+//!
 //! // Get the length of the CdpChunk
 //! let len = chunk.len();
 //!
@@ -36,6 +45,9 @@
 //! // Get a borrowed slice of the RDHs
 //! let rdh_slice = chunk.rdh_slice();
 //!
+//! // Summarize the CdpChunk without copying the payloads, e.g. for logging
+//! let summary = chunk.summary();
+//!
 //! // Iterate over the CdpChunk using a consuming iterator (no copying)
 //! chunk.into_iter()
 //!         .for_each(|(rdh, payload, mem_pos)| {
@@ -47,11 +59,33 @@ use crate::words::lib::RDH;
 
 type CdpTuple<T> = (T, Vec<u8>, u64);
 
+/// A CDP with a memory position that is not larger than the memory position of the CDP before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemPosOrderViolation {
+    /// Memory position of the CDP before it.
+    pub previous_mem_pos: u64,
+    /// Memory position of the CDP.
+    pub mem_pos: u64,
+}
+
+impl std::fmt::Display for MemPosOrderViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:#X}: CDP memory position out of order, the CDP before it is at {:#X}",
+            self.mem_pos, self.previous_mem_pos
+        )
+    }
+}
+
 /// The vector-like wrapper struct for CDPs
+///
+/// The memory positions are expected to be strictly increasing, see the [module documentation][self].
 pub struct CdpChunk<T: RDH> {
     rdhs: Vec<T>,
     payloads: Vec<Vec<u8>>,
     rdh_mem_pos: Vec<u64>,
+    mem_pos_order_violation: Option<MemPosOrderViolation>,
 }
 
 impl<T: RDH> Default for CdpChunk<T> {
@@ -67,6 +101,7 @@ impl<T: RDH> CdpChunk<T> {
             rdhs: Vec::new(),
             payloads: Vec::new(),
             rdh_mem_pos: Vec::new(),
+            mem_pos_order_violation: None,
         }
     }
     /// Construct a new, empty `CdpChunk<T: RDH>` with at least the specified capacity.
@@ -88,11 +123,15 @@ impl<T: RDH> CdpChunk<T> {
             rdhs: Vec::with_capacity(capacity),
             payloads: Vec::with_capacity(capacity),
             rdh_mem_pos: Vec::with_capacity(capacity),
+            mem_pos_order_violation: None,
         }
     }
 
     /// Appends an [RDH], payload, and memory position to the back of the CdpChunk
+    ///
+    /// If the memory position is not larger than the one of the CDP before it, the violation is recorded, see [CdpChunk::mem_pos_order_violation].
     pub fn push(&mut self, rdh: T, payload: Vec<u8>, mem_pos: u64) {
+        self.verify_mem_pos_order(mem_pos);
         self.rdhs.push(rdh);
         self.payloads.push(payload);
         self.rdh_mem_pos.push(mem_pos);
@@ -102,11 +141,31 @@ impl<T: RDH> CdpChunk<T> {
     ///
     /// Removes the need to destructure the tuple before pushing
     pub fn push_tuple(&mut self, cdp_tuple: CdpTuple<T>) {
+        self.verify_mem_pos_order(cdp_tuple.2);
         self.rdhs.push(cdp_tuple.0);
         self.payloads.push(cdp_tuple.1);
         self.rdh_mem_pos.push(cdp_tuple.2);
     }
 
+    fn verify_mem_pos_order(&mut self, mem_pos: u64) {
+        if self.mem_pos_order_violation.is_some() {
+            return;
+        }
+        if let Some(&previous_mem_pos) = self.rdh_mem_pos.last() {
+            if mem_pos <= previous_mem_pos {
+                self.mem_pos_order_violation = Some(MemPosOrderViolation {
+                    previous_mem_pos,
+                    mem_pos,
+                });
+            }
+        }
+    }
+
+    /// Returns the first CDP pushed with a memory position that is not larger than the one of the CDP before it, [None] if the memory positions are strictly increasing.
+    pub fn mem_pos_order_violation(&self) -> Option<MemPosOrderViolation> {
+        self.mem_pos_order_violation
+    }
+
     /// Get the length of the CdpChunk, corresponding to the number of CDPs
     pub fn len(&self) -> usize {
         debug_assert!(self.rdhs.len() == self.payloads.len());
@@ -126,6 +185,7 @@ impl<T: RDH> CdpChunk<T> {
         self.rdhs.clear();
         self.payloads.clear();
         self.rdh_mem_pos.clear();
+        self.mem_pos_order_violation = None;
     }
 
     /// Get a borrowed slice of the [RDH]s
@@ -137,6 +197,112 @@ impl<T: RDH> CdpChunk<T> {
     pub fn rdh_mem_pos_slice(&self) -> &[u64] {
         &self.rdh_mem_pos
     }
+
+    /// Summarize the CdpChunk, without copying any payloads
+    ///
+    /// # Examples
+    /// ```
+    /// # use fastpasta::input::data_wrapper::CdpChunk;
+    /// # use fastpasta::words::rdh_cru::test_data::CORRECT_RDH_CRU_V7;
+    /// # use fastpasta::words::rdh_cru::{RdhCRU, V7};
+    /// let mut chunk = CdpChunk::<RdhCRU<V7>>::new();
+    /// chunk.push(CORRECT_RDH_CRU_V7, vec![0; 16], 0x40);
+    /// let summary = chunk.summary();
+    /// assert_eq!(summary.cdps, 1);
+    /// assert_eq!(summary.total_bytes, 64 + 16);
+    /// ```
+    pub fn summary(&self) -> CdpChunkSummary {
+        let mut links: Vec<u8> = Vec::new();
+        for rdh in &self.rdhs {
+            if let Err(idx) = links.binary_search(&rdh.link_id()) {
+                links.insert(idx, rdh.link_id());
+            }
+        }
+        CdpChunkSummary {
+            cdps: self.len(),
+            total_bytes: self
+                .payloads
+                .iter()
+                .map(|payload| RDH_SIZE + payload.len())
+                .sum(),
+            first_mem_pos: self.rdh_mem_pos.first().copied(),
+            last_mem_pos: self.rdh_mem_pos.last().copied(),
+            links,
+        }
+    }
+}
+
+/// Size of an [RDH] in bytes.
+const RDH_SIZE: usize = 64;
+
+/// Verifies that the memory positions of consecutive [CdpChunk]s are in order, as consumers of the chunks assume.
+///
+/// Used by the dispatcher of the chunks in debug builds or with `--paranoid`, a violation is an internal error of fastpasta, not an error in the data.
+#[derive(Debug, Default)]
+pub struct MemPosOrderGuard {
+    last_mem_pos: Option<u64>,
+}
+
+impl MemPosOrderGuard {
+    /// Verifies the order of the memory positions within the chunk and relative to the chunk before it.
+    ///
+    /// Returns the violations found, at most one within the chunk and one between the chunks.
+    pub fn verify<T: RDH>(&mut self, cdp_chunk: &CdpChunk<T>) -> Vec<MemPosOrderViolation> {
+        let mut violations = Vec::new();
+        let mem_pos_slice = cdp_chunk.rdh_mem_pos_slice();
+        if let (Some(previous_mem_pos), Some(&mem_pos)) = (self.last_mem_pos, mem_pos_slice.first())
+        {
+            if mem_pos <= previous_mem_pos {
+                violations.push(MemPosOrderViolation {
+                    previous_mem_pos,
+                    mem_pos,
+                });
+            }
+        }
+        violations.extend(cdp_chunk.mem_pos_order_violation());
+        if let Some(&last_mem_pos) = mem_pos_slice.last() {
+            self.last_mem_pos = Some(last_mem_pos);
+        }
+        violations
+    }
+}
+
+/// Summary of a [CdpChunk], see [CdpChunk::summary]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CdpChunkSummary {
+    /// Number of CDPs in the chunk.
+    pub cdps: usize,
+    /// Total size of the RDHs and payloads in bytes.
+    pub total_bytes: usize,
+    /// Memory position of the first RDH, `None` if the chunk is empty.
+    pub first_mem_pos: Option<u64>,
+    /// Memory position of the last RDH, `None` if the chunk is empty.
+    pub last_mem_pos: Option<u64>,
+    /// Link IDs present in the chunk, sorted and without duplicates.
+    pub links: Vec<u8>,
+}
+
+impl std::fmt::Display for CdpChunkSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} CDPs, {} bytes", self.cdps, self.total_bytes)?;
+        if let (Some(first), Some(last)) = (self.first_mem_pos, self.last_mem_pos) {
+            write!(f, ", RDHs at {first:#X}..={last:#X}")?;
+        }
+        write!(f, ", links: {:?}", self.links)
+    }
+}
+
+impl<T: RDH> std::fmt::Debug for CdpChunk<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let summary = self.summary();
+        f.debug_struct("CdpChunk")
+            .field("cdps", &summary.cdps)
+            .field("total_bytes", &summary.total_bytes)
+            .field("first_mem_pos", &summary.first_mem_pos)
+            .field("last_mem_pos", &summary.last_mem_pos)
+            .field("links", &summary.links)
+            .finish()
+    }
 }
 
 /// Implementation of a consuming iterator for CdpChunk, with a helper struct
@@ -210,7 +376,7 @@ impl<'a, T: RDH> Iterator for CdpChunkIter<'a, T> {
 
 #[cfg(test)]
 mod tests {
-    use super::CdpChunk;
+    use super::{CdpChunk, MemPosOrderGuard, MemPosOrderViolation};
     use crate::words::lib::RDH;
     use crate::words::rdh_cru::{
         test_data::{CORRECT_RDH_CRU_V6, CORRECT_RDH_CRU_V7},
@@ -228,6 +394,65 @@ mod tests {
         assert_eq!(chunk.rdh_mem_pos.len(), 2);
     }
 
+    #[test]
+    fn test_push_out_of_order_is_detected() {
+        let mut chunk = CdpChunk::<RdhCRU<V7>>::new();
+        chunk.push(CORRECT_RDH_CRU_V7, vec![0; 10], 0x100);
+        chunk.push(CORRECT_RDH_CRU_V7, vec![0; 10], 0x200);
+        assert_eq!(chunk.mem_pos_order_violation(), None);
+        // A repeated memory position is also a violation
+        chunk.push_tuple((CORRECT_RDH_CRU_V7, vec![0; 10], 0x200));
+        chunk.push(CORRECT_RDH_CRU_V7, vec![0; 10], 0x50);
+        // Only the first violation is kept
+        let violation = chunk.mem_pos_order_violation().unwrap();
+        assert_eq!(
+            violation,
+            MemPosOrderViolation {
+                previous_mem_pos: 0x200,
+                mem_pos: 0x200
+            }
+        );
+        assert_eq!(
+            violation.to_string(),
+            "0x200: CDP memory position out of order, the CDP before it is at 0x200"
+        );
+        chunk.clear();
+        assert_eq!(chunk.mem_pos_order_violation(), None);
+    }
+
+    #[test]
+    fn test_guard_detects_chunk_to_chunk_violation() {
+        let mut guard = MemPosOrderGuard::default();
+        let mut chunk = CdpChunk::<RdhCRU<V7>>::new();
+        chunk.push(CORRECT_RDH_CRU_V7, vec![0; 10], 0x100);
+        chunk.push(CORRECT_RDH_CRU_V7, vec![0; 10], 0x200);
+        assert!(guard.verify(&chunk).is_empty());
+
+        let mut next_chunk = CdpChunk::<RdhCRU<V7>>::new();
+        next_chunk.push(CORRECT_RDH_CRU_V7, vec![0; 10], 0x300);
+        assert!(guard.verify(&next_chunk).is_empty());
+        // An empty chunk is not a violation, and does not reset the last position
+        assert!(guard.verify(&CdpChunk::<RdhCRU<V7>>::new()).is_empty());
+
+        // Starts before the end of the chunk before it, and is out of order within the chunk
+        let mut bad_chunk = CdpChunk::<RdhCRU<V7>>::new();
+        bad_chunk.push(CORRECT_RDH_CRU_V7, vec![0; 10], 0x280);
+        bad_chunk.push(CORRECT_RDH_CRU_V7, vec![0; 10], 0x180);
+        assert_eq!(
+            guard.verify(&bad_chunk),
+            vec![
+                MemPosOrderViolation {
+                    previous_mem_pos: 0x300,
+                    mem_pos: 0x280
+                },
+                MemPosOrderViolation {
+                    previous_mem_pos: 0x280,
+                    mem_pos: 0x180
+                }
+            ]
+        );
+    }
+
     #[test]
     fn test_push_tup() {
         let mut chunk = CdpChunk::<RdhCRU<V7>>::new();
@@ -302,6 +527,7 @@ mod tests {
             rdhs: vec![CORRECT_RDH_CRU_V7, CORRECT_RDH_CRU_V7],
             payloads: vec![vec![0; 10], vec![0; 10]],
             rdh_mem_pos: vec![0, 1],
+            mem_pos_order_violation: None,
         };
 
         cdp_chunk
@@ -320,6 +546,7 @@ mod tests {
             rdhs: vec![CORRECT_RDH_CRU_V7, CORRECT_RDH_CRU_V7],
             payloads: vec![vec![0; 10], vec![0; 10]],
             rdh_mem_pos: vec![255, 255],
+            mem_pos_order_violation: None,
         };
 
         for (rdh, payload, mem_pos) in &cdp_chunk {
@@ -335,6 +562,7 @@ mod tests {
             rdhs: vec![CORRECT_RDH_CRU_V6, CORRECT_RDH_CRU_V6],
             payloads: vec![vec![0; 10], vec![0; 10]],
             rdh_mem_pos: vec![0, 1],
+            mem_pos_order_violation: None,
         };
 
         cdp_chunk
@@ -353,6 +581,7 @@ mod tests {
             rdhs: vec![CORRECT_RDH_CRU_V6, CORRECT_RDH_CRU_V6],
             payloads: vec![vec![0; 10], vec![0; 10]],
             rdh_mem_pos: vec![0xd, 0xd],
+            mem_pos_order_violation: None,
         };
 
         for (rdh, payload, mem_pos) in &cdp_chunk {
@@ -377,11 +606,60 @@ mod tests {
             rdhs: vec![CORRECT_RDH_CRU_V6, CORRECT_RDH_CRU_V6],
             payloads: vec![vec![0; 10], vec![0; 10]],
             rdh_mem_pos: vec![0xd, 0xd],
+            mem_pos_order_violation: None,
         };
 
         print_cdp_chunk(&cdp_chunk);
     }
 
+    /// Three CDPs on links 3 and 1, with payloads of 32, 16 and 0 bytes
+    fn three_cdps_two_links() -> CdpChunk<RdhCRU<V7>> {
+        let rdh_with_link = |link_id| {
+            let mut rdh = CORRECT_RDH_CRU_V7;
+            rdh.link_id = link_id;
+            rdh
+        };
+        CdpChunk {
+            rdhs: vec![rdh_with_link(3), rdh_with_link(1), rdh_with_link(3)],
+            payloads: vec![vec![0; 32], vec![0; 16], vec![]],
+            rdh_mem_pos: vec![0x0, 0x60, 0xB0],
+            mem_pos_order_violation: None,
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        let summary = three_cdps_two_links().summary();
+        assert_eq!(summary.cdps, 3);
+        assert_eq!(summary.total_bytes, 3 * 64 + 32 + 16);
+        assert_eq!(summary.first_mem_pos, Some(0x0));
+        assert_eq!(summary.last_mem_pos, Some(0xB0));
+        assert_eq!(summary.links, vec![1, 3]);
+        assert_eq!(
+            summary.to_string(),
+            "3 CDPs, 240 bytes, RDHs at 0x0..=0xB0, links: [1, 3]"
+        );
+    }
+
+    #[test]
+    fn test_summary_empty() {
+        let summary = CdpChunk::<RdhCRU<V7>>::new().summary();
+        assert_eq!(summary.cdps, 0);
+        assert_eq!(summary.total_bytes, 0);
+        assert_eq!(summary.first_mem_pos, None);
+        assert!(summary.links.is_empty());
+        assert_eq!(summary.to_string(), "0 CDPs, 0 bytes, links: []");
+    }
+
+    #[test]
+    fn test_debug() {
+        let debug = format!("{:?}", three_cdps_two_links());
+        assert_eq!(
+            debug,
+            "CdpChunk { cdps: 3, total_bytes: 240, first_mem_pos: Some(0), last_mem_pos: Some(176), links: [1, 3] }"
+        );
+    }
+
     fn consume_cdp_chunk<T: RDH>(cdp_chunk: CdpChunk<T>) {
         for (rdh, payload, mem_pos) in cdp_chunk {
             println!("rdh: {rdh}, payload: {:?}, mem_pos: {:?}", payload, mem_pos);
@@ -394,6 +672,7 @@ mod tests {
             rdhs: vec![CORRECT_RDH_CRU_V6, CORRECT_RDH_CRU_V6],
             payloads: vec![vec![0; 10], vec![0; 10]],
             rdh_mem_pos: vec![0xd, 0xd],
+            mem_pos_order_violation: None,
         };
 
         consume_cdp_chunk(cdp_chunk);
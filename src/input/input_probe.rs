@@ -0,0 +1,270 @@
+//! Contains [probe_input] that checks whether the first RDHs of the input look like CRU raw data before processing begins, and the [PrefixedReader] that gives the probed bytes back to the [InputScanner][super::input_scanner::InputScanner].
+//!
+//! Inputs that are not raw CRU data at all would otherwise produce confusing errors downstream, such as an unknown RDH version or a flood of RDH sanity errors.
+//! The probe follows the `offset_to_next` of the first RDHs through a buffered prefix of the input, so it works the same for files and stdin.
+use super::bufreader_wrapper::BufferedReaderWrapper;
+use crate::validators::rdh::{system_name, RdhCruSanityValidator};
+use crate::words::lib::RDH;
+use crate::words::rdh_cru::{RdhCRU, V6, V7};
+use std::io::Read;
+
+/// Bytes of the input buffered for the probe, enough for several pages of the largest CRU page size.
+pub const PROBE_PREFIX_BYTES: usize = 0x10000;
+/// Maximum number of RDHs probed.
+const MAX_PROBED_RDHS: u8 = 4;
+/// Size of an RDH in bytes.
+const RDH_SIZE: usize = 64;
+
+/// How confident the probe is that the input is raw CRU data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confidence {
+    /// All probed RDHs are sane and point to the next RDH.
+    High,
+    /// The first RDH is plausible, but a probed RDH failed a check.
+    Low,
+    /// The first RDH is not plausible, processing is aborted unless `--skip-input-probe` is given.
+    NotRecognized,
+}
+
+/// The result of probing the first RDHs of the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputProbe {
+    /// Confidence that the input is raw CRU data.
+    pub confidence: Confidence,
+    rdh_version: u8,
+    system_id: u8,
+    rdhs_probed: u8,
+    problems: Vec<String>,
+}
+
+impl InputProbe {
+    /// A one line verdict, e.g. `Input recognized as RDHv7/ITS with high confidence (4 RDHs probed)`.
+    pub fn verdict(&self) -> String {
+        let recognized_as = format!(
+            "RDHv{}/{}",
+            self.rdh_version,
+            system_name(self.system_id)
+                .map_or_else(|| format!("system {:#x}", self.system_id), String::from)
+        );
+        let rdhs = if self.rdhs_probed == 1 { "RDH" } else { "RDHs" };
+        match self.confidence {
+            Confidence::High => format!(
+                "Input recognized as {recognized_as} with high confidence ({} {rdhs} probed)",
+                self.rdhs_probed
+            ),
+            Confidence::Low => format!(
+                "Input recognized as {recognized_as} with low confidence: {}",
+                self.problems.join("; ")
+            ),
+            Confidence::NotRecognized => format!(
+                "Input does not look like CRU raw data: {}",
+                self.problems.join("; ")
+            ),
+        }
+    }
+}
+
+/// Probes the first RDHs in the `prefix` of the input, checking the `header_id`, `header_size`, that the `offset_to_next` points to another plausible RDH, the `system_id` and the RDH sanity checks.
+pub fn probe_input(prefix: &[u8]) -> InputProbe {
+    let mut probe = InputProbe {
+        confidence: Confidence::High,
+        rdh_version: prefix.first().copied().unwrap_or(0),
+        system_id: prefix.get(5).copied().unwrap_or(0),
+        rdhs_probed: 0,
+        problems: Vec::new(),
+    };
+    if prefix.len() < RDH_SIZE {
+        probe.problems.push(format!(
+            "the input is {} bytes, shorter than an RDH",
+            prefix.len()
+        ));
+    } else {
+        match probe.rdh_version {
+            6 => probe_rdhs::<RdhCRU<V6>>(prefix, &mut probe),
+            7 => probe_rdhs::<RdhCRU<V7>>(prefix, &mut probe),
+            header_id => probe.problems.push(format!(
+                "header_id {header_id:#x} at 0x0 is not a known RDH version (6 or 7)"
+            )),
+        }
+    }
+    if !probe.problems.is_empty() {
+        probe.confidence = if probe.rdhs_probed == 0 {
+            Confidence::NotRecognized
+        } else {
+            Confidence::Low
+        };
+    }
+    probe
+}
+
+/// Follows the RDHs from the start of the `prefix` until [MAX_PROBED_RDHS] are probed, the end of the prefix or the first implausible RDH.
+///
+/// An RDH counts as probed if its `header_id`, `header_size` and `offset_to_next` are plausible, a failed sanity check is only noted as a problem.
+fn probe_rdhs<T: RDH>(prefix: &[u8], probe: &mut InputProbe) {
+    let mut sanity_validator = RdhCruSanityValidator::<T>::new();
+    sanity_validator.forbid_unknown_system_ids();
+    let mut mem_pos: usize = 0;
+    while probe.rdhs_probed < MAX_PROBED_RDHS && mem_pos + RDH_SIZE <= prefix.len() {
+        let rdh_bytes = &prefix[mem_pos..mem_pos + RDH_SIZE];
+        if rdh_bytes[0] != probe.rdh_version {
+            probe.problems.push(format!(
+                "the RDH at {mem_pos:#X} has header_id {:#x}, not {:#x} like the first RDH",
+                rdh_bytes[0], probe.rdh_version
+            ));
+            return;
+        }
+        if rdh_bytes[1] as usize != RDH_SIZE {
+            probe.problems.push(format!(
+                "the RDH at {mem_pos:#X} has header_size {:#x}, not 0x40",
+                rdh_bytes[1]
+            ));
+            return;
+        }
+        let rdh = T::load(&mut &rdh_bytes[..]).expect("RDH bytes are in the prefix");
+        let offset_to_next = rdh.offset_to_next() as usize;
+        let memory_size = rdh.memory_size() as usize;
+        if offset_to_next < RDH_SIZE || memory_size < RDH_SIZE || memory_size > offset_to_next {
+            probe.problems.push(format!(
+                "the RDH at {mem_pos:#X} has offset_to_next {offset_to_next:#x} and memory_size {memory_size:#x}, which do not point to a next RDH"
+            ));
+            return;
+        }
+        if let Err(e) = sanity_validator.sanity_check(&rdh) {
+            probe
+                .problems
+                .push(format!("{} at {mem_pos:#X}", e.trim_end()));
+        }
+        probe.rdhs_probed += 1;
+        mem_pos += offset_to_next;
+    }
+}
+
+/// Reads the prefix of the input to probe, up to [PROBE_PREFIX_BYTES] or the end of the input.
+pub fn read_probe_prefix(reader: &mut impl Read) -> Result<Vec<u8>, std::io::Error> {
+    let mut prefix = Vec::with_capacity(PROBE_PREFIX_BYTES);
+    reader
+        .take(PROBE_PREFIX_BYTES as u64)
+        .read_to_end(&mut prefix)?;
+    Ok(prefix)
+}
+
+/// Reads the buffered prefix of the input before the rest of the input, so the probed bytes are read again without rewinding the reader, which stdin cannot do.
+pub struct PrefixedReader {
+    prefix: std::io::Cursor<Vec<u8>>,
+    reader: Box<dyn BufferedReaderWrapper>,
+}
+
+impl PrefixedReader {
+    /// Creates a [PrefixedReader] that reads the `prefix` and then the `reader`.
+    pub fn new(prefix: Vec<u8>, reader: Box<dyn BufferedReaderWrapper>) -> Self {
+        Self {
+            prefix: std::io::Cursor::new(prefix),
+            reader,
+        }
+    }
+
+    fn prefix_remaining(&self) -> usize {
+        self.prefix.get_ref().len() - self.prefix.position() as usize
+    }
+}
+
+impl Read for PrefixedReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.prefix_remaining() > 0 {
+            self.prefix.read(buf)
+        } else {
+            self.reader.read(buf)
+        }
+    }
+}
+
+impl std::io::Seek for PrefixedReader {
+    fn seek(&mut self, _pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        Err(std::io::Error::other(
+            "Cannot seek in a prefixed input, use seek_relative instead",
+        ))
+    }
+}
+
+impl BufferedReaderWrapper for PrefixedReader {
+    fn seek_relative(&mut self, offset: i64) -> std::io::Result<()> {
+        let in_prefix = offset.min(self.prefix_remaining() as i64);
+        self.prefix
+            .set_position(self.prefix.position() + in_prefix as u64);
+        if offset > in_prefix {
+            self.reader.seek_relative(offset - in_prefix)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::stdin_reader::StdInReaderSeeker;
+
+    fn test_data(file_name: &str) -> Vec<u8> {
+        std::fs::read(format!(
+            "{}/tests/test-data/{file_name}",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_probe_valid_input() {
+        let probe = probe_input(&test_data("its_2_links.raw"));
+        assert_eq!(probe.confidence, Confidence::High);
+        assert_eq!(
+            probe.verdict(),
+            "Input recognized as RDHv7/ITS with high confidence (4 RDHs probed)"
+        );
+    }
+
+    #[test]
+    fn test_probe_text_input() {
+        let text = "Run 535069, ITS, 2 links\n".repeat(100);
+        let probe = probe_input(text.as_bytes());
+        assert_eq!(probe.confidence, Confidence::NotRecognized);
+        assert_eq!(
+            probe.verdict(),
+            "Input does not look like CRU raw data: header_id 0x52 at 0x0 is not a known RDH version (6 or 7)"
+        );
+        assert_eq!(
+            probe_input(b"7").verdict(),
+            "Input does not look like CRU raw data: the input is 1 bytes, shorter than an RDH"
+        );
+    }
+
+    #[test]
+    fn test_probe_broken_offset_after_first_rdh() {
+        let mut data = test_data("its_2_links.raw");
+        let second_rdh = u16::from_le_bytes([data[8], data[9]]) as usize;
+        data[second_rdh + 1] = 0x20;
+        let probe = probe_input(&data);
+        assert_eq!(probe.confidence, Confidence::Low);
+        assert!(
+            probe.verdict().ends_with(&format!(
+                "low confidence: the RDH at {second_rdh:#X} has header_size 0x20, not 0x40"
+            )),
+            "{}",
+            probe.verdict()
+        );
+    }
+
+    #[test]
+    fn test_prefixed_reader_reads_prefix_then_input() {
+        let data = test_data("its_2_links.raw");
+        let mut stdin = StdInReaderSeeker {
+            reader: std::io::Cursor::new(data.clone()),
+        };
+        let prefix = read_probe_prefix(&mut stdin.reader.by_ref().take(100)).unwrap();
+        assert_eq!(prefix.len(), 100);
+        let mut reader = PrefixedReader::new(prefix, Box::new(stdin));
+        // Skip across the end of the prefix
+        reader.seek_relative(90).unwrap();
+        let mut read_back = Vec::new();
+        reader.read_to_end(&mut read_back).unwrap();
+        assert_eq!(read_back, data[90..]);
+    }
+}
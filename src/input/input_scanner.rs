@@ -2,10 +2,18 @@
 //!
 //! The [InputScanner] implements the [ScanCDP] trait, and uses the [CdpWrapper] tuple for convenience to wrap an RDH, its payload and its memory position.
 use super::bufreader_wrapper::BufferedReaderWrapper;
+use super::cdp_filter::{
+    And, CdpFilter, ExcludeLink, FilterCru, FilterDecision, FilterFee, FilterItsStave, FilterLink,
+    FilterOffsets, FilterOrbitRange, FilterPacketRange, FilterTrigger, FilterWhere,
+};
 use super::mem_pos_tracker::MemPosTracker;
+use crate::stats::{
+    accumulators::Accumulators, interleaving::InterleavingTracker,
+    pipeline_counts::ComponentCounts, stats_controller::StatType,
+};
+use crate::util::config::Analysis;
 use crate::util::lib::Config;
-use crate::words::lib::RDH;
-use crate::{stats::stats_controller::StatType, words::rdh::Rdh0};
+use crate::words::lib::{RDH, RDH_SIZE_BYTES};
 use std::io::Read;
 
 /// Trait for a scanner that reads CDPs from a file or stdin
@@ -27,27 +35,91 @@ pub trait ScanCDP {
         Ok(CdpWrapper(rdh, payload, mem_pos))
     }
 
-    /// Loads the next [RDH] that matches the user specified link from the input and returns it
-    fn load_next_rdh_to_filter<T: RDH>(&mut self) -> Result<T, std::io::Error>;
-
     /// Convenience function to return the current memory position in the input stream
     fn current_mem_pos(&self) -> u64;
 }
 
+/// The filters of the [InputScanner], the offsets to extract are set after it is created.
+///
+/// The packet range is consulted first, so it sees every RDH of a link to count the wrap-arounds of its packet_counter.
+type ScannerFilter = And<
+    And<
+        Option<FilterPacketRange>,
+        And<
+            And<
+                And<Option<FilterLink>, Option<ExcludeLink>>,
+                And<Option<FilterFee>, And<Option<FilterCru>, Option<FilterTrigger>>>,
+            >,
+            And<Option<FilterItsStave>, And<Option<FilterWhere>, Option<FilterOrbitRange>>>,
+        >,
+    >,
+    Option<FilterOffsets>,
+>;
+
+/// Combines the filters set in the [Config], without offsets to extract.
+fn scanner_filter(config: &impl Config) -> ScannerFilter {
+    And(
+        And(
+            config.filter_packet_range().map(FilterPacketRange::new),
+            And(
+                And(
+                    And(
+                        config
+                            .filter_links()
+                            .map(|link_ids| FilterLink(link_ids.to_vec())),
+                        config
+                            .exclude_links()
+                            .map(|link_ids| ExcludeLink(link_ids.to_vec())),
+                    ),
+                    And(
+                        config.filter_fee().map(FilterFee),
+                        And(
+                            config.filter_cru().map(FilterCru),
+                            config.filter_trigger().map(FilterTrigger),
+                        ),
+                    ),
+                ),
+                And(
+                    config.filter_its_stave().map(FilterItsStave),
+                    And(
+                        config.filter_where().map(FilterWhere),
+                        config
+                            .filter_orbit_range()
+                            .map(|(start, end)| FilterOrbitRange::new(start, end)),
+                    ),
+                ),
+            ),
+        ),
+        None,
+    )
+}
+
 /// Convenience tuple to wrap an [RDH], its payload and memory position.
 pub struct CdpWrapper<T: RDH>(pub T, pub Vec<u8>, pub u64);
 
 /// Scans data received through a [BufferedReaderWrapper], tracks the position in memory and sends stats to the stats controller.
 ///
-/// Uses the [Config] to filter for user specified links.
+/// Uses the [Config] to filter for user specified links, through a [CdpFilter] consulted once per RDH.
 /// Implements [ScanCDP] for a [BufferedReaderWrapper].
 pub struct InputScanner<R: ?Sized + BufferedReaderWrapper> {
     reader: Box<R>,
     tracker: MemPosTracker,
     stats_controller_sender_ch: std::sync::mpsc::Sender<StatType>,
-    link_to_filter: Option<u8>,
+    cdp_filter: ScannerFilter,
+    // Links excluded with `--exclude-link`, to count the CDPs excluded of each link
+    links_to_exclude: Vec<u8>,
     unique_links_observed: Vec<u8>,
-    initial_rdh0: Option<Rdh0>,
+    unique_fee_ids_observed: Vec<u16>,
+    unique_cru_ids_observed: Vec<u16>,
+    // The first RDH of the input, if it was already read to determine the RDH version
+    initial_rdh: Option<[u8; 64]>,
+    // Version of the first RDH, all following RDHs are parsed as this version
+    initial_rdh_version: Option<u8>,
+    previous_rdh_version: Option<u8>,
+    strict_version: bool,
+    accumulated_stats: Accumulators,
+    // Measures the interleaving of the links if `--analyze interleaving` is set
+    interleaving: Option<InterleavingTracker>,
 }
 
 impl<R: ?Sized + BufferedReaderWrapper> InputScanner<R> {
@@ -62,49 +134,185 @@ impl<R: ?Sized + BufferedReaderWrapper> InputScanner<R> {
             reader,
             tracker,
             stats_controller_sender_ch,
-            link_to_filter: config.filter_link(),
+            cdp_filter: scanner_filter(&*config),
+            links_to_exclude: config.exclude_links().map_or(Vec::new(), <[u8]>::to_vec),
             unique_links_observed: vec![],
-            initial_rdh0: None,
+            unique_fee_ids_observed: vec![],
+            unique_cru_ids_observed: vec![],
+            initial_rdh: None,
+            initial_rdh_version: None,
+            previous_rdh_version: None,
+            strict_version: config.strict_version(),
+            accumulated_stats: Accumulators::default(),
+            interleaving: config
+                .analyze(Analysis::Interleaving)
+                .then(InterleavingTracker::default),
         }
     }
-    /// Creates a new [InputScanner] from a [Config], [BufferedReaderWrapper], [MemPosTracker], a producer channel for [StatType] and an initial [Rdh0].
+    /// Creates a new [InputScanner] from a [Config], [BufferedReaderWrapper], a producer channel for [StatType] and the bytes of the first RDH, read with [read_initial_rdh][super::lib::read_initial_rdh].
     ///
-    /// The [Rdh0] is used to determine the RDH version before instantiating the [InputScanner].
-    pub fn new_from_rdh0(
+    /// The first RDH is read before instantiating the [InputScanner] to determine the RDH version, and is parsed as the first RDH of the input by the [InputScanner].
+    /// As the reader is never rewound, files and stdin are handled the same way.
+    pub fn with_initial_rdh(
         config: std::sync::Arc<impl Config>,
         reader: Box<R>,
         stats_controller_sender_ch: std::sync::mpsc::Sender<StatType>,
-        rdh0: Rdh0,
+        initial_rdh: [u8; 64],
     ) -> Self {
         InputScanner {
             reader,
             tracker: MemPosTracker::new(),
             stats_controller_sender_ch,
-            link_to_filter: config.filter_link(),
+            cdp_filter: scanner_filter(&*config),
+            links_to_exclude: config.exclude_links().map_or(Vec::new(), <[u8]>::to_vec),
             unique_links_observed: vec![],
-            initial_rdh0: Some(rdh0),
+            unique_fee_ids_observed: vec![],
+            unique_cru_ids_observed: vec![],
+            initial_rdh_version: Some(super::lib::rdh_version_of(&initial_rdh)),
+            previous_rdh_version: None,
+            initial_rdh: Some(initial_rdh),
+            strict_version: config.strict_version(),
+            accumulated_stats: Accumulators::default(),
+            interleaving: config
+                .analyze(Analysis::Interleaving)
+                .then(InterleavingTracker::default),
         }
     }
-    fn report_rdh_seen(&self) {
-        self.stats_controller_sender_ch
-            .send(StatType::RDHsSeen(1))
-            .unwrap();
+    /// Sets the memory position of the first RDH, for when the reader was moved past the start of the input before the [InputScanner] was created.
+    pub fn start_at_mem_pos(&mut self, mem_pos: u64) {
+        self.tracker.memory_address_bytes = mem_pos;
+    }
+    /// Only forward the CDPs with an RDH at one of the given memory offsets.
+    ///
+    /// Offsets that are passed without finding an RDH at them are reported as errors.
+    pub fn extract_offsets(&mut self, offsets: Vec<u64>) {
+        self.cdp_filter.1 = Some(FilterOffsets::new(
+            offsets,
+            self.stats_controller_sender_ch.clone(),
+        ));
+    }
+    fn report_rdh_seen(&mut self, offset_to_next: u16) {
+        self.accumulated_stats.rdhs_seen += 1;
+        self.accumulated_stats.input_size += offset_to_next as u64;
     }
     fn report_link_seen(&self, link_id: u8) {
         self.stats_controller_sender_ch
             .send(StatType::LinksObserved(link_id))
             .unwrap();
     }
-    fn report_payload_size(&self, payload_size: usize) {
+    fn report_fee_id_seen(&self, fee_id: u16, link_id: u8) {
+        self.stats_controller_sender_ch
+            .send(StatType::FeeIdObserved { fee_id, link_id })
+            .unwrap();
+    }
+    fn report_cru_id_seen(&self, cru_id: u16) {
         self.stats_controller_sender_ch
-            .send(StatType::PayloadSize(payload_size as u32))
+            .send(StatType::CruIdObserved(cru_id))
             .unwrap();
     }
-    fn report_rdh_filtered(&self) {
+    fn report_payload_size(&mut self, payload_size: usize) {
+        self.accumulated_stats.payload_size += payload_size as u64;
+    }
+    fn report_rdh_filtered(&mut self, link_id: u8) {
+        self.accumulated_stats.count_rdh_filtered(link_id);
+    }
+    /// Reads the next RDH from the input, starting with the initial RDH if it was already read.
+    ///
+    /// At the end of the input, the filter is notified, e.g. to report the offsets to extract that were not found.
+    fn read_rdh<T: RDH>(&mut self) -> Result<T, std::io::Error> {
+        let rdh = match self.initial_rdh.take() {
+            Some(initial_rdh) => RDH::load(&mut initial_rdh.as_slice()),
+            None => RDH::load(&mut self.reader),
+        };
+        if matches!(&rdh, Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof) {
+            self.cdp_filter.end_of_input();
+        }
+        rdh
+    }
+    /// Sends the CDPs read and sent by the reader to the [StatsController][crate::stats::stats_controller::StatsController], to reconcile them with the CDPs received by the analysis or the writer.
+    pub fn report_component_counts(&self, counts: ComponentCounts) {
         self.stats_controller_sender_ch
-            .send(StatType::RDHsFiltered(1))
+            .send(StatType::PipelineCounts(counts))
             .unwrap();
     }
+    /// Sends the stats accumulated since the last flush to the [StatsController][crate::stats::stats_controller::StatsController].
+    ///
+    /// Should be called once per [CdpChunk][super::data_wrapper::CdpChunk] to avoid sending a message per RDH.
+    pub fn flush_stats(&mut self) {
+        self.accumulated_stats
+            .flush(&self.stats_controller_sender_ch);
+        if let Some(interleaving) = self
+            .interleaving
+            .as_mut()
+            .and_then(InterleavingTracker::take_update)
+        {
+            self.stats_controller_sender_ch
+                .send(StatType::Interleaving(Box::new(interleaving)))
+                .unwrap();
+        }
+    }
+    /// Checks that the version of the RDH is the same as the version of the first RDH of the input.
+    ///
+    /// A mismatch is only reported where the version changes, not for every following RDH of the same version.
+    /// The RDH is still parsed as the initial version, unless `--strict-version` is set, then it is a fatal error.
+    fn check_rdh_version<T: RDH>(&mut self, rdh: &T) -> Result<(), std::io::Error> {
+        let version = rdh.version();
+        let initial_version = *self.initial_rdh_version.get_or_insert(version);
+        let previous_version = self.previous_rdh_version.replace(version);
+        if version == initial_version || previous_version == Some(version) {
+            return Ok(());
+        }
+        let mem_pos = self.tracker.memory_address_bytes;
+        let error_string = if self.strict_version {
+            format!("{mem_pos:#X}: [E15] RDH version is {version}, but the input started with version {initial_version}, stopping due to --strict-version")
+        } else {
+            format!("{mem_pos:#X}: [E15] RDH version is {version}, but the input started with version {initial_version}, continuing to parse as version {initial_version}")
+        };
+        self.stats_controller_sender_ch
+            .send(StatType::Error(error_string.clone()))
+            .unwrap();
+        if self.strict_version {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                error_string,
+            ));
+        }
+        Ok(())
+    }
+    /// Reads up to `payload_size` bytes of payload, fewer if the end of the input is reached first.
+    fn read_payload_up_to(&mut self, payload_size: usize) -> Result<Vec<u8>, std::io::Error> {
+        let mut payload = Vec::with_capacity(payload_size);
+        Read::take(&mut self.reader, payload_size as u64).read_to_end(&mut payload)?;
+        Ok(payload)
+    }
+    /// Reports an RDH with a header_size other than [RDH_SIZE_BYTES], and returns the number of bytes of the header following the fields of the RDH.
+    ///
+    /// A [plausible][RDH::is_header_size_plausible] header_size is honored, so the payload is read after the header.
+    /// Otherwise the header is assumed to be [RDH_SIZE_BYTES], either way the next RDH is found with the offset to the next RDH.
+    fn check_header_size<T: RDH>(&self, rdh: &T, mem_pos: u64) -> u16 {
+        let header_size = rdh.rdh0().header_size;
+        if header_size as u16 == RDH_SIZE_BYTES {
+            return 0;
+        }
+        let warning = if rdh.is_header_size_plausible() {
+            format!("{mem_pos:#X}: RDH header_size is {header_size} bytes instead of {RDH_SIZE_BYTES}, reading the payload after the {header_size} byte header")
+        } else {
+            format!("{mem_pos:#X}: RDH header_size is {header_size} bytes, which is implausible for a memory size of {} and offset to next of {}, reading the payload after a {RDH_SIZE_BYTES} byte header", rdh.memory_size(), rdh.offset_to_next())
+        };
+        self.stats_controller_sender_ch
+            .send(StatType::Warning(warning))
+            .unwrap();
+        rdh.header_size_bytes() - RDH_SIZE_BYTES
+    }
+    /// Loads the next [RDH] and seeks past its payload without reading it.
+    ///
+    /// Used for scans of the input that only need the RDHs.
+    pub fn load_rdh_skip_payload<T: RDH>(&mut self) -> Result<T, std::io::Error> {
+        let rdh: T = self.load_rdh_cru()?;
+        self.reader
+            .seek_relative(self.tracker.next(rdh.offset_to_next() as u64))?;
+        Ok(rdh)
+    }
 }
 
 impl<R> ScanCDP for InputScanner<R>
@@ -112,104 +320,143 @@ where
     R: ?Sized + BufferedReaderWrapper,
 {
     /// Reads the next RDH from file
-    /// If a link filter is set, it checks if the RDH matches the chosen link and returns it if it does.
-    /// If it doesn't match, it jumps to the next RDH and tries again.
-    /// If no link filter is set, it simply returns the RDH.
+    /// The RDH is passed to the [CdpFilter], if the filter keeps it, the RDH is returned.
+    /// If the filter skips it, it jumps to the next RDH and tries again.
+    /// If the filter stops, the rest of the input is not read, and an [UnexpectedEof][std::io::ErrorKind::UnexpectedEof] error is returned.
     #[inline]
     fn load_rdh_cru<T: RDH>(&mut self) -> Result<T, std::io::Error> {
-        // If it is the first time we get an RDH, we would already have loaded the initial RDH0
-        //  from the input. If so, we use it to create the first RDH.
-        let rdh: T = match self.initial_rdh0.is_some() {
-            true => RDH::load_from_rdh0(&mut self.reader, self.initial_rdh0.take().unwrap())?,
-            false => RDH::load(&mut self.reader)?,
-        };
-        log::debug!(
-            "Loaded RDH at [{:#X}]: \n       {rdh}",
-            self.tracker.memory_address_bytes,
-            rdh = rdh
-        );
+        loop {
+            // If it is the first time we get an RDH, we would already have loaded the initial RDH0
+            //  from the input. If so, we use it to create the first RDH.
+            let rdh: T = self.read_rdh()?;
+            log::debug!(
+                "Loaded RDH at [{:#X}]: \n       {rdh}",
+                self.tracker.memory_address_bytes,
+                rdh = rdh
+            );
 
-        // Set the link ID and report another RDH seen
-        let current_link_id = rdh.link_id();
-        self.report_rdh_seen();
+            self.check_rdh_version(&rdh)?;
+            // Set the link ID and report another RDH seen
+            let current_link_id = rdh.link_id();
+            self.report_rdh_seen(rdh.offset_to_next());
 
-        // If we haven't seen this link before, report it and add it to the list of unique links
-        if !self.unique_links_observed.contains(&current_link_id) {
-            self.unique_links_observed.push(current_link_id);
-            self.report_link_seen(current_link_id);
-        }
-        sanity_check_offset_next(
-            &rdh,
-            self.tracker.memory_address_bytes,
-            &self.stats_controller_sender_ch,
-        )?;
-        // If we have a link filter set, check if the current link matches the filter
-        if let Some(x) = self.link_to_filter {
-            // If it matches, return the RDH
-            if x == current_link_id {
-                self.report_rdh_filtered();
+            // If we haven't seen this link before, report it and add it to the list of unique links
+            if !self.unique_links_observed.contains(&current_link_id) {
+                self.unique_links_observed.push(current_link_id);
+                self.report_link_seen(current_link_id);
+            }
+            let current_fee_id = rdh.fee_id();
+            if !self.unique_fee_ids_observed.contains(&current_fee_id) {
+                self.unique_fee_ids_observed.push(current_fee_id);
+                self.report_fee_id_seen(current_fee_id, current_link_id);
+            }
+            let current_cru_id = rdh.cru_id();
+            if !self.unique_cru_ids_observed.contains(&current_cru_id) {
+                self.unique_cru_ids_observed.push(current_cru_id);
+                self.report_cru_id_seen(current_cru_id);
+            }
+            sanity_check_offset_next(
+                &rdh,
+                self.tracker.memory_address_bytes,
+                &self.stats_controller_sender_ch,
+            )?;
+            // Measured on the input stream, before filtering
+            if let Some(interleaving) = &mut self.interleaving {
+                interleaving.record(&rdh, self.tracker.memory_address_bytes);
+            }
+            match self
+                .cdp_filter
+                .keep(&rdh, self.tracker.memory_address_bytes)
+            {
                 // no jump. current pos -> start of payload
-                Ok(rdh)
-            } else {
-                // If it doesn't match: Set tracker to jump to next RDH and try until we find a matching link or EOF
-                log::debug!("Loaded RDH offset to next: {}", rdh.offset_to_next());
-
-                self.reader
-                    .seek_relative(self.tracker.next(rdh.offset_to_next() as u64))?;
-                self.load_next_rdh_to_filter()
+                FilterDecision::Keep => {
+                    self.report_rdh_filtered(rdh.link_id());
+                    return Ok(rdh);
+                }
+                // Set tracker to jump to next RDH and try until the filter keeps an RDH or EOF
+                FilterDecision::SkipPayload => {
+                    log::debug!("Loaded RDH offset to next: {}", rdh.offset_to_next());
+                    if self.links_to_exclude.contains(&current_link_id) {
+                        self.accumulated_stats.count_rdh_excluded(current_link_id);
+                    }
+                    self.reader
+                        .seek_relative(self.tracker.next(rdh.offset_to_next() as u64))?;
+                }
+                FilterDecision::Stop => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "No more CDPs to keep from the input",
+                    ))
+                }
             }
-        } else {
-            // No filter set, return the RDH
-            // No jump, current position is start of payload
-            Ok(rdh)
         }
     }
 
     /// Reads the next payload from file, using the payload size from the RDH
     #[inline]
     fn load_payload_raw(&mut self, payload_size: usize) -> Result<Vec<u8>, std::io::Error> {
-        let mut payload = vec![0; payload_size];
-        Read::read_exact(&mut self.reader, &mut payload)?;
+        let payload = self.read_payload_up_to(payload_size)?;
+        if payload.len() < payload_size {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "Payload truncated by end of input",
+            ));
+        }
         self.report_payload_size(payload_size);
         Ok(payload)
     }
     /// Reads the next CDP from file
+    ///
+    /// CDPs with a memory size that doesn't fit between the RDH and the next RDH are reported and skipped.
+    /// A payload truncated by the end of the input is reported with the number of missing bytes, as a truncated input rather than corrupted data,
+    /// and returned as an [InvalidData][std::io::ErrorKind::InvalidData] error.
     #[inline]
     fn load_cdp<T: RDH>(&mut self) -> Result<CdpWrapper<T>, std::io::Error> {
-        log::trace!("Attempting to load CDP - 1. loading RDH");
-        let loading_at_memory_offset = self.tracker.memory_address_bytes;
-        let rdh: T = self.load_rdh_cru()?;
-
-        self.tracker.memory_address_bytes += rdh.offset_to_next() as u64;
+        loop {
+            log::trace!("Attempting to load CDP - 1. loading RDH");
+            let rdh: T = self.load_rdh_cru()?;
+            let rdh_memory_address = self.tracker.memory_address_bytes;
 
-        log::trace!("Attempting to load CDP - 2. loading Payload");
-        let payload = self.load_payload_raw(rdh.payload_size() as usize)?;
+            if let Err(e) = sanity_check_memory_size(&rdh, rdh_memory_address) {
+                self.stats_controller_sender_ch
+                    .send(StatType::Error(e))
+                    .unwrap();
+                // Skip the packet using the offset to the next RDH
+                self.reader
+                    .seek_relative(self.tracker.next(rdh.offset_to_next() as u64))?;
+                continue;
+            }
 
-        Ok(CdpWrapper(rdh, payload, loading_at_memory_offset))
-    }
+            self.tracker.memory_address_bytes += rdh.offset_to_next() as u64;
 
-    fn load_next_rdh_to_filter<T: RDH>(&mut self) -> Result<T, std::io::Error> {
-        loop {
-            let rdh: T = RDH::load(&mut self.reader)?;
-            log::debug!("Loaded RDH: \n      {rdh}");
-            log::debug!("Loaded RDH offset to next: {}", rdh.offset_to_next());
-            sanity_check_offset_next(
-                &rdh,
-                self.tracker.memory_address_bytes,
-                &self.stats_controller_sender_ch,
-            )?;
-            let current_link_id = rdh.link_id();
-            self.report_rdh_seen();
-            if !self.unique_links_observed.contains(&current_link_id) {
-                self.unique_links_observed.push(current_link_id);
-                self.report_link_seen(current_link_id);
+            let extra_header_bytes = self.check_header_size(&rdh, rdh_memory_address);
+            if extra_header_bytes > 0 {
+                self.reader.seek_relative(extra_header_bytes as i64)?;
             }
-            if self.link_to_filter.unwrap() == current_link_id {
-                self.report_rdh_filtered();
-                return Ok(rdh);
+
+            log::trace!("Attempting to load CDP - 2. loading Payload");
+            let payload_size = rdh.payload_size() as usize;
+            let payload = self.read_payload_up_to(payload_size)?;
+            if payload.len() < payload_size {
+                let missing_bytes = payload_size - payload.len();
+                self.stats_controller_sender_ch
+                    .send(StatType::TruncatedPayload {
+                        link_id: rdh.link_id(),
+                        mem_pos: rdh_memory_address,
+                        payload_size,
+                        missing_bytes,
+                    })
+                    .unwrap();
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "{rdh_memory_address:#X}: Payload truncated by end of input, RDH memory size is {} bytes, {missing_bytes} payload bytes missing",
+                        rdh.memory_size()
+                    ),
+                ));
             }
-            self.reader
-                .seek_relative(self.tracker.next(rdh.offset_to_next() as u64))?;
+            self.report_payload_size(payload_size);
+            return Ok(CdpWrapper(rdh, payload, rdh_memory_address));
         }
     }
 
@@ -218,6 +465,24 @@ where
     }
 }
 
+// The payload size is the memory size minus the RDH size, so the memory size has to be checked before calculating it.
+// The packet can still be skipped using the offset to the next RDH, so the error is not fatal to the input scanner.
+fn sanity_check_memory_size<T: RDH>(rdh: &T, current_memory_address: u64) -> Result<(), String> {
+    let memory_size = rdh.memory_size();
+    if memory_size < 64 {
+        Err(format!(
+            "{current_memory_address:#X}: [E10] RDH memory size is {memory_size} (less than 64 bytes), skipping packet"
+        ))
+    } else if memory_size > rdh.offset_to_next() {
+        Err(format!(
+            "{current_memory_address:#X}: [E10] RDH memory size is {memory_size} (larger than offset to next of {} bytes), skipping packet",
+            rdh.offset_to_next()
+        ))
+    } else {
+        Ok(())
+    }
+}
+
 // The error is fatal to the input scanner, so parsing input is stopped, but the previously read data is still forwarded for checking etc.
 fn sanity_check_offset_next<T: RDH>(
     rdh: &T,
@@ -265,7 +530,7 @@ mod tests {
 
     use crate::stats::stats_controller::StatsController;
     use crate::util::config::Opt;
-    use crate::util::lib::InputOutput;
+    use crate::util::lib::{Filter, InputOutput};
     use crate::words::lib::ByteSlice;
     use crate::words::rdh_cru::{RdhCRU, V6, V7};
 
@@ -313,6 +578,373 @@ mod tests {
 
     use super::*;
     use crate::words::rdh_cru::test_data::{CORRECT_RDH_CRU_V6, CORRECT_RDH_CRU_V7};
+
+    // Scanner without link filter, where the stats are received by the test instead of a stats controller
+    fn setup_scanner_with_stats_recv(
+        path: &str,
+    ) -> (
+        InputScanner<BufReader<std::fs::File>>,
+        std::sync::mpsc::Receiver<StatType>,
+    ) {
+        let config: Opt =
+            <Opt as structopt::StructOpt>::from_iter(&["fastpasta", path, "check", "sanity"]);
+        let (send_stats_ch, recv_stats_ch) = std::sync::mpsc::channel();
+        let reader = std::fs::OpenOptions::new()
+            .read(true)
+            .open(path)
+            .expect("File not found");
+        (
+            InputScanner::new(
+                std::sync::Arc::new(config),
+                Box::new(BufReader::new(reader)),
+                MemPosTracker::new(),
+                send_stats_ch,
+            ),
+            recv_stats_ch,
+        )
+    }
+
+    fn errors_received(recv_stats_ch: &std::sync::mpsc::Receiver<StatType>) -> Vec<String> {
+        recv_stats_ch
+            .try_iter()
+            .filter_map(|stat| match stat {
+                StatType::Error(msg) => Some(msg),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_load_cdp_skips_invalid_memory_size() {
+        let invalid_memory_sizes = [
+            (0, "0x0: [E10] RDH memory size is 0 (less than 64 bytes), skipping packet"),
+            (63, "0x0: [E10] RDH memory size is 63 (less than 64 bytes), skipping packet"),
+            (0x13E1, "0x0: [E10] RDH memory size is 5089 (larger than offset to next of 5088 bytes), skipping packet"),
+        ];
+        for (memory_size, expected_error) in invalid_memory_sizes {
+            let mut invalid_rdh = CORRECT_RDH_CRU_V7;
+            invalid_rdh.memory_size = memory_size;
+            let mut valid_rdh = CORRECT_RDH_CRU_V7;
+            valid_rdh.packet_counter = 1;
+            let payload = vec![0xFF; CORRECT_RDH_CRU_V7.payload_size() as usize];
+            let file_name = format!("test_invalid_memory_size_{memory_size}.raw");
+            let filepath = PathBuf::from(&file_name);
+            let mut file = File::create(&filepath).unwrap();
+            file.write_all(invalid_rdh.to_byte_slice()).unwrap();
+            file.write_all(&payload).unwrap();
+            file.write_all(valid_rdh.to_byte_slice()).unwrap();
+            file.write_all(&payload).unwrap();
+
+            let (mut scanner, recv_stats_ch) = setup_scanner_with_stats_recv(&file_name);
+            let cdp = scanner.load_cdp::<RdhCRU<V7>>().unwrap();
+            assert_eq!(cdp.0, valid_rdh);
+            assert_eq!(cdp.1.len(), payload.len());
+            assert_eq!(errors_received(&recv_stats_ch), vec![expected_error]);
+
+            std::fs::remove_file(filepath).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_load_cdp_honors_plausible_header_size() {
+        let file_name = "test_header_size.raw";
+        let payload_size = 0x20;
+        let mut rdhs = [CORRECT_RDH_CRU_V7; 4];
+        // The second RDH has a 128 byte header, the third declares a header larger than its memory size
+        rdhs[1].rdh0.header_size = 0x80;
+        rdhs[2].rdh0.header_size = 0x80;
+        for (idx, rdh) in rdhs.iter_mut().enumerate() {
+            let header_size = if idx == 1 { 0x80 } else { 64 };
+            rdh.memory_size = header_size + payload_size;
+            rdh.offset_new_packet = rdh.memory_size;
+            rdh.packet_counter = idx as u8;
+        }
+        let mut file = File::create(file_name).unwrap();
+        for (idx, rdh) in rdhs.iter().enumerate() {
+            file.write_all(rdh.to_byte_slice()).unwrap();
+            if idx == 1 {
+                file.write_all(&[0xAA; 64]).unwrap();
+            }
+            file.write_all(&vec![idx as u8; payload_size as usize])
+                .unwrap();
+        }
+        drop(file);
+
+        let (mut scanner, recv_stats_ch) = setup_scanner_with_stats_recv(file_name);
+        let mut mem_pos = 0;
+        for (idx, rdh) in rdhs.iter().enumerate() {
+            let cdp = scanner.load_cdp::<RdhCRU<V7>>().unwrap();
+            assert_eq!(cdp.0, *rdh);
+            assert_eq!(cdp.1, vec![idx as u8; payload_size as usize]);
+            assert_eq!(cdp.2, mem_pos);
+            mem_pos += rdh.offset_to_next() as u64;
+        }
+        let warnings: Vec<String> = recv_stats_ch
+            .try_iter()
+            .filter_map(|stat| match stat {
+                StatType::Warning(msg) => Some(msg),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "0x60: RDH header_size is 128 bytes instead of 64, reading the payload after the 128 byte header".to_string(),
+                "0x100: RDH header_size is 128 bytes, which is implausible for a memory size of 96 and offset to next of 96, reading the payload after a 64 byte header".to_string(),
+            ]
+        );
+
+        std::fs::remove_file(file_name).unwrap();
+    }
+
+    // Two v7 CDPs followed by two v6 CDPs, as if files of different RDH versions were concatenated
+    fn write_mixed_version_file(file_name: &str) {
+        let mut file = File::create(file_name).unwrap();
+        for rdh in [CORRECT_RDH_CRU_V7, CORRECT_RDH_CRU_V7] {
+            file.write_all(rdh.to_byte_slice()).unwrap();
+            file.write_all(&vec![0xFF; rdh.offset_to_next() as usize - 64])
+                .unwrap();
+        }
+        for rdh in [CORRECT_RDH_CRU_V6, CORRECT_RDH_CRU_V6] {
+            file.write_all(rdh.to_byte_slice()).unwrap();
+            file.write_all(&vec![0xFF; rdh.offset_to_next() as usize - 64])
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_load_cdp_mixed_rdh_versions() {
+        let file_name = "test_mixed_rdh_versions.raw";
+        write_mixed_version_file(file_name);
+        let seam_mem_pos = 2 * CORRECT_RDH_CRU_V7.offset_to_next() as u64;
+
+        let (mut scanner, recv_stats_ch) = setup_scanner_with_stats_recv(file_name);
+        for _ in 0..4 {
+            scanner.load_cdp::<RdhCRU<V7>>().unwrap();
+        }
+        assert_eq!(
+            errors_received(&recv_stats_ch),
+            vec![format!("{seam_mem_pos:#X}: [E15] RDH version is 6, but the input started with version 7, continuing to parse as version 7")]
+        );
+
+        std::fs::remove_file(file_name).unwrap();
+    }
+
+    #[test]
+    fn test_load_cdp_mixed_rdh_versions_strict() {
+        let file_name = "test_mixed_rdh_versions_strict.raw";
+        write_mixed_version_file(file_name);
+        let seam_mem_pos = 2 * CORRECT_RDH_CRU_V7.offset_to_next() as u64;
+        let config: Opt = <Opt as structopt::StructOpt>::from_iter(&[
+            "fastpasta",
+            file_name,
+            "--strict-version",
+            "check",
+            "sanity",
+        ]);
+        let (send_stats_ch, recv_stats_ch) = std::sync::mpsc::channel();
+        let mut scanner = InputScanner::new(
+            std::sync::Arc::new(config),
+            Box::new(BufReader::new(File::open(file_name).unwrap())),
+            MemPosTracker::new(),
+            send_stats_ch,
+        );
+
+        scanner.load_cdp::<RdhCRU<V7>>().unwrap();
+        scanner.load_cdp::<RdhCRU<V7>>().unwrap();
+        let err = match scanner.load_cdp::<RdhCRU<V7>>() {
+            Ok(_) => panic!("Loading an RDH of another version should fail with --strict-version"),
+            Err(e) => e,
+        };
+        let expected_error = format!("{seam_mem_pos:#X}: [E15] RDH version is 6, but the input started with version 7, stopping due to --strict-version");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), expected_error);
+        assert_eq!(errors_received(&recv_stats_ch), vec![expected_error]);
+
+        std::fs::remove_file(file_name).unwrap();
+    }
+
+    #[test]
+    fn test_extract_offsets_written_output_reparses() {
+        let input_file = "test_extract_offsets_input.raw";
+        let offsets_file = "test_extract_offsets.txt";
+        let output_file = "test_extract_offsets_output.raw";
+        let cdp_size = CORRECT_RDH_CRU_V7.offset_to_next() as u64;
+        let mut file = File::create(input_file).unwrap();
+        for packet_counter in 0..5 {
+            let mut rdh = CORRECT_RDH_CRU_V7;
+            rdh.packet_counter = packet_counter;
+            file.write_all(rdh.to_byte_slice()).unwrap();
+            file.write_all(&vec![0xFF; rdh.payload_size() as usize])
+                .unwrap();
+        }
+        // Offsets of the 4th and 2nd CDP, an offset in the payload of the 1st CDP, and an offset past the end of the input
+        std::fs::write(
+            offsets_file,
+            format!(
+                "{:#X}\n# Comment\n\n{:X}\n0x40\n{:#X}\n",
+                3 * cdp_size,
+                cdp_size,
+                10 * cdp_size
+            ),
+        )
+        .unwrap();
+        let config: Opt = <Opt as structopt::StructOpt>::from_iter(&[
+            "fastpasta",
+            input_file,
+            "--extract-offsets",
+            offsets_file,
+            "-o",
+            output_file,
+        ]);
+        let offsets =
+            crate::input::lib::read_offsets_file(config.extract_offsets().as_ref().unwrap())
+                .unwrap();
+        let (send_stats_ch, recv_stats_ch) = std::sync::mpsc::channel();
+        let mut scanner = InputScanner::new(
+            std::sync::Arc::new(config),
+            Box::new(BufReader::new(File::open(input_file).unwrap())),
+            MemPosTracker::new(),
+            send_stats_ch,
+        );
+        scanner.extract_offsets(offsets);
+
+        let mut cdp_chunk = crate::input::data_wrapper::CdpChunk::<RdhCRU<V7>>::new();
+        let err = loop {
+            match scanner.load_cdp::<RdhCRU<V7>>() {
+                Ok(cdp) => cdp_chunk.push(cdp.0, cdp.1, cdp.2),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+        assert_eq!(cdp_chunk.rdh_mem_pos_slice(), [cdp_size, 3 * cdp_size]);
+        assert_eq!(
+            errors_received(&recv_stats_ch),
+            vec![
+                "0x40: Offset to extract is not the memory position of an RDH".to_string(),
+                format!(
+                    "{:#X}: Offset to extract is not the memory position of an RDH",
+                    10 * cdp_size
+                )
+            ]
+        );
+        {
+            let config: Opt = <Opt as structopt::StructOpt>::from_iter(&[
+                "fastpasta",
+                input_file,
+                "--extract-offsets",
+                offsets_file,
+                "-o",
+                output_file,
+            ]);
+            let (send_stats_ch, _recv_stats_ch) = std::sync::mpsc::channel();
+            let mut writer = crate::write::writer::BufferedWriter::<RdhCRU<V7>>::new(
+                &config,
+                1024,
+                send_stats_ch,
+            );
+            crate::write::writer::Writer::push_cdp_chunk(&mut writer, cdp_chunk);
+            writer.finalize().unwrap();
+        }
+
+        // The output is the extracted CDPs back to back
+        let (mut scanner, recv_stats_ch) = setup_scanner_with_stats_recv(output_file);
+        let mut packet_counters = Vec::new();
+        while let Ok(cdp) = scanner.load_cdp::<RdhCRU<V7>>() {
+            packet_counters.push(cdp.0.packet_counter);
+        }
+        assert_eq!(packet_counters, [1, 3]);
+        assert!(errors_received(&recv_stats_ch).is_empty());
+
+        for file in [input_file, offsets_file, output_file] {
+            std::fs::remove_file(file).unwrap();
+        }
+    }
+
+    // Loads all CDPs of link 0 through a scanner created from the initial RDH, as in main, and returns their packet counters
+    fn load_link_0_cdps_with_initial_rdh<R: BufferedReaderWrapper + 'static>(
+        mut reader: Box<R>,
+    ) -> Vec<u8> {
+        let config: Opt =
+            <Opt as structopt::StructOpt>::from_iter(&["fastpasta", "-f", "0", "check", "sanity"]);
+        let initial_rdh = crate::input::lib::read_initial_rdh(&mut reader).unwrap();
+        let (send_stats_ch, _recv_stats_ch) = std::sync::mpsc::channel();
+        let mut scanner = InputScanner::with_initial_rdh(
+            std::sync::Arc::new(config),
+            reader,
+            send_stats_ch,
+            initial_rdh,
+        );
+        let mut packet_counters = Vec::new();
+        while let Ok(cdp) = scanner.load_cdp::<RdhCRU<V7>>() {
+            packet_counters.push(cdp.0.packet_counter);
+        }
+        packet_counters
+    }
+
+    #[test]
+    fn test_initial_rdh_same_cdps_from_file_and_stdin() {
+        let file_name = "test_initial_rdh_file_and_stdin.raw";
+        let mut input = Vec::new();
+        for packet_counter in 0..6 {
+            let mut rdh = CORRECT_RDH_CRU_V7;
+            rdh.packet_counter = packet_counter;
+            // Every third CDP is from another link, and is skipped by the link filter
+            rdh.link_id = if packet_counter % 3 == 1 { 2 } else { 0 };
+            input.extend_from_slice(rdh.to_byte_slice());
+            input.extend(vec![0xFF; rdh.payload_size() as usize]);
+        }
+        std::fs::write(file_name, &input).unwrap();
+
+        let from_file = load_link_0_cdps_with_initial_rdh(Box::new(BufReader::new(
+            File::open(file_name).unwrap(),
+        )));
+        let from_stdin = load_link_0_cdps_with_initial_rdh(Box::new(
+            crate::input::stdin_reader::StdInReaderSeeker {
+                reader: std::io::Cursor::new(input),
+            },
+        ));
+        assert_eq!(from_file, [0, 2, 3, 5]);
+        assert_eq!(from_stdin, from_file);
+
+        std::fs::remove_file(file_name).unwrap();
+    }
+
+    #[test]
+    fn test_load_cdp_truncated_payload() {
+        let rdh = CORRECT_RDH_CRU_V7;
+        let file_name = "test_truncated_payload.raw";
+        let filepath = PathBuf::from(file_name);
+        let mut file = File::create(&filepath).unwrap();
+        file.write_all(rdh.to_byte_slice()).unwrap();
+        // Payload is much smaller than the memory size of the RDH
+        file.write_all(&[0xFF; 100]).unwrap();
+
+        let (mut scanner, recv_stats_ch) = setup_scanner_with_stats_recv(file_name);
+        let err = match scanner.load_cdp::<RdhCRU<V7>>() {
+            Ok(_) => panic!("Loading a truncated CDP should fail"),
+            Err(e) => e,
+        };
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "0x0: Payload truncated by end of input, RDH memory size is 5088 bytes, 4924 payload bytes missing"
+        );
+        // Reported as a truncated input, not as an error
+        let stats: Vec<StatType> = recv_stats_ch.try_iter().collect();
+        assert!(stats.iter().all(|stat| !matches!(stat, StatType::Error(_))));
+        assert!(stats.iter().any(|stat| matches!(
+            stat,
+            StatType::TruncatedPayload {
+                link_id: 0,
+                mem_pos: 0,
+                payload_size: 5024,
+                missing_bytes: 4924
+            }
+        )));
+
+        std::fs::remove_file(filepath).unwrap();
+    }
     #[test]
     fn test_load_rdhcruv7_test() {
         let test_data = CORRECT_RDH_CRU_V7;
@@ -336,6 +968,39 @@ mod tests {
         std::fs::remove_file(filepath).unwrap();
     }
 
+    #[test]
+    fn test_load_rdh_skip_payload() {
+        let first_rdh = CORRECT_RDH_CRU_V7;
+        let mut second_rdh = CORRECT_RDH_CRU_V7;
+        second_rdh.packet_counter = 1;
+        let payload = vec![0xFF; first_rdh.payload_size() as usize];
+        let file_name = "test_skip_payload.raw";
+        let filepath = PathBuf::from(file_name);
+        let mut file = File::create(&filepath).unwrap();
+        // Write to file for testing
+        file.write_all(first_rdh.to_byte_slice()).unwrap();
+        file.write_all(&payload).unwrap();
+        file.write_all(second_rdh.to_byte_slice()).unwrap();
+        file.write_all(&payload).unwrap();
+
+        let stats_handle_super: Option<JoinHandle<()>>;
+        {
+            let (mut scanner, stats_handle) = setup_scanner_for_file(file_name);
+            stats_handle_super = Some(stats_handle);
+            let rdh = scanner.load_rdh_skip_payload::<RdhCRU<V7>>().unwrap();
+            assert_eq!(first_rdh, rdh);
+            let rdh = scanner.load_rdh_skip_payload::<RdhCRU<V7>>().unwrap();
+            assert_eq!(second_rdh, rdh);
+            assert_eq!(scanner.current_mem_pos(), 2 * 0x13E0);
+            let eof = scanner.load_rdh_skip_payload::<RdhCRU<V7>>();
+            assert!(eof.unwrap_err().kind() == std::io::ErrorKind::UnexpectedEof);
+        }
+        stats_handle_super.unwrap().join().unwrap();
+
+        // delete output file
+        std::fs::remove_file(filepath).unwrap();
+    }
+
     #[test]
     fn test_load_rdhcruv7_test_unexp_eof() {
         let mut test_data = CORRECT_RDH_CRU_V7;
@@ -12,6 +12,8 @@ use super::data_wrapper::CdpChunk;
 use super::input_scanner::{InputScanner, ScanCDP};
 use super::stdin_reader::StdInReaderSeeker;
 use super::util::buf_reader_with_capacity;
+use crate::stats::memory_usage::InFlightBytes;
+use crate::stats::pipeline_counts::{CdpCount, ComponentCounts};
 use crate::util::config::Opt;
 use crate::util::lib::InputOutput;
 use crate::words;
@@ -29,7 +31,7 @@ const CHANNEL_CDP_CHUNK_CAPACITY: usize = 100;
 pub fn init_reader(config: &Opt) -> Result<Box<dyn BufferedReaderWrapper>, std::io::Error> {
     if let Some(path) = config.input_file() {
         log::trace!("Reading from file: {:?}", &path);
-        let f = std::fs::OpenOptions::new().read(true).open(path)?;
+        let f = open_input_file(path)?;
         Ok(Box::new(buf_reader_with_capacity(f, 1024 * 50)))
     } else {
         log::trace!("Reading from stdin");
@@ -42,31 +44,126 @@ pub fn init_reader(config: &Opt) -> Result<Box<dyn BufferedReaderWrapper>, std::
     }
 }
 
-/// Spawns a reader thread that reads CDPs from the input and sends them to a producer channel
+/// Opens an input file for reading, the error names the file and says if it does not exist, is a directory or can't be read.
+///
+/// A directory can be opened on some platforms and only fails on the first read, so it is checked before opening.
+pub fn open_input_file(path: &std::path::Path) -> Result<std::fs::File, std::io::Error> {
+    use std::io::{Error, ErrorKind};
+    let input_error = |kind: ErrorKind, reason: &str| {
+        Error::new(kind, format!("input file '{}' {reason}", path.display()))
+    };
+    let describe = |e: Error| match e.kind() {
+        ErrorKind::NotFound => input_error(ErrorKind::NotFound, "does not exist"),
+        ErrorKind::PermissionDenied => {
+            input_error(ErrorKind::PermissionDenied, "permission denied")
+        }
+        kind => input_error(kind, &format!("can't be opened: {e}")),
+    };
+    let metadata = std::fs::metadata(path).map_err(describe)?;
+    if metadata.is_dir() {
+        return Err(input_error(ErrorKind::InvalidInput, "is a directory"));
+    }
+    std::fs::OpenOptions::new()
+        .read(true)
+        .open(path)
+        .map_err(describe)
+}
+
+/// Reads the bytes of the first RDH of the input, to determine the RDH version before instantiating the [InputScanner].
+///
+/// The bytes are given to the [InputScanner] with [InputScanner::with_initial_rdh], so the first RDH is neither skipped nor read twice.
+pub fn read_initial_rdh(reader: &mut impl std::io::Read) -> Result<[u8; 64], std::io::Error> {
+    let mut initial_rdh = [0; 64];
+    reader.read_exact(&mut initial_rdh)?;
+    Ok(initial_rdh)
+}
+
+/// Returns the RDH version of the bytes of an RDH, the version is the `header_id` of the [Rdh0][crate::words::rdh::Rdh0].
+pub fn rdh_version_of(rdh_bytes: &[u8; 64]) -> u8 {
+    rdh_bytes[0]
+}
+
+/// Reads the memory offsets of the CDPs to extract from a file with one hex offset per line, e.g. `0x13E0`
+///
+/// Empty lines and lines starting with `#` are ignored.
+pub fn read_offsets_file(path: &std::path::Path) -> Result<Vec<u64>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read offsets file {}: {e}", path.display()))?;
+    content
+        .lines()
+        .enumerate()
+        .map(|(line_idx, line)| (line_idx + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_number, line)| {
+            let hex_digits = line
+                .strip_prefix("0x")
+                .or_else(|| line.strip_prefix("0X"))
+                .unwrap_or(line);
+            u64::from_str_radix(hex_digits, 16).map_err(|e| {
+                format!(
+                    "Invalid offset '{line}' on line {line_number} of {}: {e}",
+                    path.display()
+                )
+            })
+        })
+        .collect()
+}
+
+/// Spawns a reader thread that reads CDPs from the input and sends them to a producer channel, in chunks of `cdp_chunk_size` CDPs
+///
+/// The chunks are only a unit of transfer, consumers must keep their state across chunks, e.g. for HBFs spanning two chunks.
+///
+/// If the memory usage is tracked, the bytes of each chunk sent are added to the [InFlightBytes], the consumer removes them when it receives the chunk.
 ///
 /// Returns the thread handle and the receiver channel
 pub fn spawn_reader<T: RDH + 'static>(
     stop_flag: std::sync::Arc<AtomicBool>,
     input_scanner: InputScanner<impl BufferedReaderWrapper + ?Sized + std::marker::Send + 'static>,
+    cdp_chunk_size: usize,
+    in_flight_bytes: Option<std::sync::Arc<InFlightBytes>>,
+) -> (std::thread::JoinHandle<()>, Receiver<CdpChunk<T>>) {
+    spawn_reader_with_drop_hook(
+        stop_flag,
+        input_scanner,
+        cdp_chunk_size,
+        in_flight_bytes,
+        |_| false,
+    )
+}
+
+/// Same as [spawn_reader], but the chunks for which `drop_chunk` returns true, given the index of the chunk, are counted as sent but dropped.
+///
+/// Used to test that CDPs lost between the reader and its consumers are caught when the counts of the pipeline are reconciled.
+fn spawn_reader_with_drop_hook<T: RDH + 'static>(
+    stop_flag: std::sync::Arc<AtomicBool>,
+    input_scanner: InputScanner<impl BufferedReaderWrapper + ?Sized + std::marker::Send + 'static>,
+    cdp_chunk_size: usize,
+    in_flight_bytes: Option<std::sync::Arc<InFlightBytes>>,
+    drop_chunk: impl Fn(usize) -> bool + std::marker::Send + 'static,
 ) -> (std::thread::JoinHandle<()>, Receiver<CdpChunk<T>>) {
     let reader_thread = std::thread::Builder::new().name("Reader".to_string());
     let (send_channel, rcv_channel) = crossbeam_channel::bounded(CHANNEL_CDP_CHUNK_CAPACITY);
     let mut local_stop_on_non_full_chunk = false;
-    const CDP_CHUNK_SIZE: usize = 100;
     let thread_handle = reader_thread
         .spawn({
             move || {
                 let mut input_scanner = input_scanner;
+                // CDPs read and sent, reconciled with the CDPs received by the consumer at the end of the run
+                let mut matched = CdpCount::default();
+                let mut produced = CdpCount::default();
+                let mut stopped = false;
+                let mut chunk_idx = 0;
 
                 // Automatically extracts link to filter if one is supplied
                 loop {
                     if stop_flag.load(Ordering::SeqCst) || local_stop_on_non_full_chunk {
                         log::trace!("Stopping reader thread on stop flag");
+                        stopped = stop_flag.load(Ordering::SeqCst);
                         break;
                     }
-                    let cdps = match get_chunk::<T>(&mut input_scanner, CDP_CHUNK_SIZE) {
+                    let cdps = match get_chunk::<T>(&mut input_scanner, cdp_chunk_size) {
                         Ok(cdp) => {
-                            if cdp.len() < CDP_CHUNK_SIZE {
+                            if cdp.len() < cdp_chunk_size {
                                 local_stop_on_non_full_chunk = true; // Stop on non-full chunk, could be InvalidData
                                 log::trace!("Stopping reader thread on non-full chunk");
                             }
@@ -82,18 +179,41 @@ pub fn spawn_reader<T: RDH + 'static>(
                             }
                         }
                     };
+                    let chunk_count = CdpCount::of_chunk(&cdps);
+                    matched += chunk_count;
+                    chunk_idx += 1;
+                    if drop_chunk(chunk_idx - 1) {
+                        produced += chunk_count;
+                        continue;
+                    }
 
+                    log::trace!("Reader: sending chunk of {}", cdps.summary());
+                    if let Some(in_flight_bytes) = &in_flight_bytes {
+                        in_flight_bytes.add(chunk_count.bytes);
+                    }
                     // Send a chunk to the checker
                     if let Err(e) = send_channel.send(cdps) {
+                        if let Some(in_flight_bytes) = &in_flight_bytes {
+                            in_flight_bytes.remove(chunk_count.bytes);
+                        }
                         if !stop_flag.load(Ordering::SeqCst) {
                             log::trace!("Unexpected error while sending data to checker: {e}");
                             break;
                         }
-                    } else if stop_flag.load(Ordering::SeqCst) {
-                        log::trace!("Stopping reader thread");
-                        break;
+                    } else {
+                        produced += chunk_count;
+                        if stop_flag.load(Ordering::SeqCst) {
+                            log::trace!("Stopping reader thread");
+                            stopped = true;
+                            break;
+                        }
                     }
                 }
+                input_scanner.report_component_counts(ComponentCounts::Reader {
+                    matched,
+                    produced,
+                    stopped,
+                });
             }
         })
         .expect("Failed to spawn reader thread");
@@ -122,10 +242,20 @@ fn get_chunk<T: words::lib::RDH>(
                 log::info!("EOF reached! ");
                 break;
             }
-            Err(e) => return Err(e),
+            Err(e) if cdp_chunk.is_empty() => {
+                file_scanner.flush_stats();
+                return Err(e);
+            }
+            Err(e) => {
+                // The CDPs read so far are still checked, regardless of where the chunk boundary falls
+                log::error!("Unexpected Error reading CDP chunks: {e}");
+                break;
+            }
         };
         cdp_chunk.push(cdp_tuple.0, cdp_tuple.1, cdp_tuple.2);
     }
+    // Send the stats of the whole chunk at once
+    file_scanner.flush_stats();
 
     if cdp_chunk.is_empty() {
         return Err(std::io::Error::new(
@@ -136,3 +266,89 @@ fn get_chunk<T: words::lib::RDH>(
 
     Ok(cdp_chunk)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::mem_pos_tracker::MemPosTracker;
+    use crate::stats::pipeline_counts::PipelineCounts;
+    use crate::stats::stats_controller::StatType;
+    use crate::words::rdh_cru::{RdhCRU, V7};
+    use std::io::BufReader;
+    use std::sync::Arc;
+
+    /// Reads `its_2_links.raw` in chunks of 4 CDPs, dropping the chunks selected by `drop_chunk`, and returns the reconciled counts
+    fn reconcile_read_and_received(
+        drop_chunk: impl Fn(usize) -> bool + Send + 'static,
+    ) -> Vec<String> {
+        let path = "tests/test-data/its_2_links.raw";
+        let config: Opt =
+            <Opt as structopt::StructOpt>::from_iter(&["fastpasta", path, "check", "sanity"]);
+        let (send_stats_ch, recv_stats_ch) = std::sync::mpsc::channel();
+        let reader = std::fs::OpenOptions::new()
+            .read(true)
+            .open(path)
+            .expect("File not found");
+        let input_scanner = InputScanner::new(
+            Arc::new(config),
+            Box::new(BufReader::new(reader)),
+            MemPosTracker::new(),
+            send_stats_ch,
+        );
+
+        let (handle, rcv_channel) = spawn_reader_with_drop_hook::<RdhCRU<V7>>(
+            Arc::new(AtomicBool::new(false)),
+            input_scanner,
+            4,
+            None,
+            drop_chunk,
+        );
+        let mut consumed = CdpCount::default();
+        rcv_channel
+            .iter()
+            .for_each(|cdp_chunk| consumed += CdpCount::of_chunk(&cdp_chunk));
+        handle.join().unwrap();
+
+        let mut pipeline_counts = PipelineCounts::default();
+        recv_stats_ch.try_iter().for_each(|stat| {
+            if let StatType::PipelineCounts(counts) = stat {
+                pipeline_counts.record(counts);
+            }
+        });
+        pipeline_counts.record(ComponentCounts::Analysis {
+            consumed,
+            drained: true,
+        });
+        pipeline_counts.reconcile()
+    }
+
+    #[test]
+    fn test_reader_counts_match_received() {
+        assert!(reconcile_read_and_received(|_| false).is_empty());
+    }
+
+    #[test]
+    fn test_open_input_file_errors() {
+        let missing_file = std::env::temp_dir().join("fastpasta_no_such_input.raw");
+        let err = open_input_file(&missing_file).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(
+            err.to_string(),
+            format!("input file '{}' does not exist", missing_file.display())
+        );
+        let dir = std::env::temp_dir();
+        assert_eq!(
+            open_input_file(&dir).unwrap_err().to_string(),
+            format!("input file '{}' is a directory", dir.display())
+        );
+    }
+
+    #[test]
+    fn test_dropped_chunk_reported() {
+        // The second chunk of 4 CDPs is lost between the reader and the analysis
+        assert_eq!(
+            reconcile_read_and_received(|chunk_idx| chunk_idx == 1),
+            ["The reader sent 18 CDPs (1824 bytes), but the analysis received 14 CDPs (1408 bytes)"]
+        );
+    }
+}
@@ -20,7 +20,7 @@ impl MemPosTracker {
         MemPosTracker {
             offset_next: 0,
             memory_address_bytes: 0,
-            rdh_cru_size_bytes: 64, // RDH size in bytes
+            rdh_cru_size_bytes: crate::words::lib::RDH_SIZE_BYTES as u64, // Bytes read for the RDH fields, a larger header is skipped with the payload
         }
     }
     /// Get the relative offset of the next RDH.
@@ -12,22 +12,22 @@ pub struct StdInReaderSeeker<R> {
     pub reader: R,
 }
 
-/// Specialization for [std::io::Stdin]
-impl BufferedReaderWrapper for StdInReaderSeeker<std::io::Stdin> {
+/// Implemented for any reader, such as [std::io::Stdin], or a [std::io::Cursor] standing in for stdin in tests
+impl<R: Read + Send> BufferedReaderWrapper for StdInReaderSeeker<R> {
     fn seek_relative(&mut self, offset: i64) -> io::Result<()> {
         // Seeking is not supported in stdin, so we have to read the bytes and discard them
         let mut buf = vec![0; offset as usize];
-        std::io::stdin().lock().read_exact(&mut buf)?;
+        self.reader.read_exact(&mut buf)?;
         Ok(())
     }
 }
 
-impl io::Read for StdInReaderSeeker<std::io::Stdin> {
+impl<R: Read> io::Read for StdInReaderSeeker<R> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.reader.lock().read(buf)
+        self.reader.read(buf)
     }
 }
-impl io::Seek for StdInReaderSeeker<std::io::Stdin> {
+impl<R> io::Seek for StdInReaderSeeker<R> {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
         match pos {
             SeekFrom::Start(_) => Err(io::Error::new(
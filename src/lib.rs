@@ -33,6 +33,12 @@
 //! $ fastpasta <input_file> --filter-link 3 -o <output_file>
 //! ```
 //!
+//! ## Reading from file and writing the CDPs at the memory offsets listed in a file (one hex offset per line) to another
+//!
+//! ```bash
+//! $ fastpasta <input_file> --extract-offsets <offsets_file> -o <output_file>
+//! ```
+//!
 //! ## Reading from stdin and filtering by link ID and writing to stdout
 //! Writing to stdout is implicit when no checks or views are specified
 //! ```bash
@@ -44,32 +50,73 @@
 //! ```bash
 //! $ fastpasta <input_file> view rdh
 //! ```
+//!
+//! ## Listing the links found in a file, only scanning the first 100 MB
+//!
+//! ```bash
+//! $ fastpasta <input_file> list-links --first-n-mb 100
+//! ```
+
+//!
+//! # Features
+//! * `pipeline` (default): reading, checking, viewing and writing data with threads and channels, configured with [Config][util::lib::Config].
+//! * `cli` (default): the `fastpasta` binary, implies `pipeline`.
+//!
+//! With `default-features = false`, only the [words] and the sanity validators of the words in [validators] are built, e.g. to parse and sanity check RDHs:
+//! ```
+//! use fastpasta::validators::rdh::RdhCruSanityValidator;
+//! use fastpasta::words::lib::RDH;
+//! use fastpasta::words::rdh_cru::{RdhCRU, V7};
+//!
+//! # use fastpasta::words::lib::ByteSlice;
+//! # let bytes = fastpasta::words::rdh_cru::test_data::CORRECT_RDH_CRU_V7.to_byte_slice().to_vec();
+//! let rdh = RdhCRU::<V7>::load(&mut bytes.as_slice()).unwrap();
+//! assert_eq!(rdh.version(), 7);
+//! assert!(RdhCruSanityValidator::default().sanity_check(&rdh).is_ok());
+//! ```
 
+#[cfg(feature = "pipeline")]
 use crossbeam_channel::Receiver;
+#[cfg(feature = "pipeline")]
 use util::lib::{Config, DataOutputMode};
 
+#[cfg(feature = "pipeline")]
+pub mod api;
+#[cfg(feature = "pipeline")]
 pub mod input;
+#[cfg(feature = "pipeline")]
 pub mod stats;
+#[cfg(feature = "pipeline")]
 pub mod util;
 pub mod validators;
+#[cfg(feature = "pipeline")]
 pub mod view;
 pub mod words;
+#[cfg(feature = "pipeline")]
 pub mod write;
 
 /// Capacity of the channel (FIFO) to Link Validator threads in terms of CDPs (RDH, Payload, Memory position)
 ///
 /// Larger capacity means less overhead, but more memory usage
 /// Too small capacity will cause the producer thread to block
+#[cfg(feature = "pipeline")]
 const CHANNEL_CDP_CAPACITY: usize = 100;
 
 /// Entry point for scanning the input and delegating to checkers, view generators and/or writers depending on config
 ///
+/// If listing links or counting CDPs is enabled, only the RDHs are scanned with [view::lib::list_links] or [view::lib::count_cdps] and the steps below are skipped.
+///
+/// If a [Checkpoint][util::checkpoint::Checkpoint] is given, the link validators are restored from it, and if a checkpoint file is set, a new checkpoint is saved periodically while checking and when checks finish or are stopped.
+///
+/// If a hardware map is set with checks enabled, it is read with [HwMap::read][validators::hw_map::HwMap::read] before any data is processed, and failing to read it is returned as an error.
+///
 /// Follows these steps:
 /// 1. Setup reading (`file` or `stdin`) using [input::lib::spawn_reader].
 /// 2. Depending on [Config] do one of:
 ///     - Validate data with [validators::lib::check_cdp_chunk].
 ///     - Generate views of data with [view::lib::generate_view].
 ///     - Write data to `file` or `stdout` with [write::lib::spawn_writer].
+#[cfg(feature = "pipeline")]
 pub fn process<T: words::lib::RDH + 'static>(
     config: std::sync::Arc<impl Config + 'static>,
     loader: input::input_scanner::InputScanner<
@@ -77,40 +124,71 @@ pub fn process<T: words::lib::RDH + 'static>(
     >,
     send_stats_ch: std::sync::mpsc::Sender<stats::stats_controller::StatType>,
     thread_stopper: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    checkpoint: Option<util::checkpoint::Checkpoint>,
 ) -> std::io::Result<()> {
+    // Listing links and counting CDPs only need the RDHs, which are scanned on this thread without spawning any others
+    if let Some(list_links) = config.list_links() {
+        drop(send_stats_ch);
+        return view::lib::list_links::<T>(loader, &list_links);
+    }
+    if let Some(count) = config.count() {
+        drop(send_stats_ch);
+        return view::lib::count_cdps::<T>(loader, &count);
+    }
+    let hw_map = match config.hw_map() {
+        Some(path) if config.check().is_some() => Some(validators::hw_map::HwMap::read(path)?),
+        _ => None,
+    };
+    // Bytes of the CDP chunks in flight between the reader and its consumer, if the memory usage is tracked
+    let in_flight_bytes = config
+        .track_memory()
+        .then(|| std::sync::Arc::new(stats::memory_usage::InFlightBytes::default()));
     // 1. Launch reader thread to read data from file or stdin
     let (reader_handle, reader_rcv_channel): (
         std::thread::JoinHandle<()>,
         crossbeam_channel::Receiver<input::data_wrapper::CdpChunk<T>>,
-    ) = input::lib::spawn_reader(thread_stopper.clone(), loader);
+    ) = input::lib::spawn_reader(
+        thread_stopper.clone(),
+        loader,
+        config.cdp_chunk_size(),
+        in_flight_bytes.clone(),
+    );
 
     // 2. Launch analysis thread if an analysis action is set (view or check)
     let analysis_handle = if config.check().is_some() || config.view().is_some() {
         debug_assert!(
-            config.output_mode() == util::lib::DataOutputMode::None
-                || config.filter_link().is_some()
+            config.output_mode() == util::lib::DataOutputMode::None || config.filter_enabled()
         );
         let handle = spawn_analysis(
             config.clone(),
             thread_stopper.clone(),
-            send_stats_ch,
+            send_stats_ch.clone(),
             reader_rcv_channel.clone(),
+            checkpoint,
+            hw_map,
+            in_flight_bytes.clone(),
         );
         Some(handle)
     } else {
         None
     };
 
-    // 3. Write data out only in the case where no analysis is performed and a filter link is set
+    // 3. Write data out only in the case where no analysis is performed and a filter link or offsets to extract are set
     let output_handle: Option<std::thread::JoinHandle<()>> = match (
         config.check(),
         config.view(),
-        config.filter_link(),
+        config.filter_enabled(),
         config.output_mode(),
     ) {
-        (None, None, Some(_), output_mode) if output_mode != DataOutputMode::None => Some(
-            write::lib::spawn_writer(config.clone(), thread_stopper, reader_rcv_channel),
-        ),
+        (None, None, true, output_mode) if output_mode != DataOutputMode::None => {
+            Some(write::lib::spawn_writer(
+                config.clone(),
+                thread_stopper,
+                send_stats_ch.clone(),
+                reader_rcv_channel,
+                in_flight_bytes.clone(),
+            ))
+        }
         (Some(_), None, _, output_mode) | (None, Some(_), _, output_mode)
             if output_mode != DataOutputMode::None =>
         {
@@ -132,15 +210,28 @@ pub fn process<T: words::lib::RDH + 'static>(
     if let Some(output) = output_handle {
         output.join().expect("Could not join writer thread");
     }
+    if let Some(in_flight_bytes) = in_flight_bytes {
+        send_stats_ch
+            .send(stats::stats_controller::StatType::PeakInFlightBytes(
+                in_flight_bytes.peak(),
+            ))
+            .unwrap();
+    }
     Ok(())
 }
 
 /// Analysis thread that performs checks with [validators::lib::check_cdp_chunk] or generate views with [view::lib::generate_view].
+///
+/// If the memory usage is tracked, the bytes of each chunk received are removed from the [InFlightBytes][stats::memory_usage::InFlightBytes].
+#[cfg(feature = "pipeline")]
 fn spawn_analysis<T: words::lib::RDH + 'static>(
     config: std::sync::Arc<impl Config + 'static>,
     stop_flag: std::sync::Arc<std::sync::atomic::AtomicBool>,
     stats_sender_channel: std::sync::mpsc::Sender<stats::stats_controller::StatType>,
     data_channel: Receiver<input::data_wrapper::CdpChunk<T>>,
+    checkpoint: Option<util::checkpoint::Checkpoint>,
+    hw_map: Option<validators::hw_map::HwMap>,
+    in_flight_bytes: Option<std::sync::Arc<stats::memory_usage::InFlightBytes>>,
 ) -> std::thread::JoinHandle<()> {
     let analysis_thread = std::thread::Builder::new().name("Analysis".to_string());
 
@@ -152,10 +243,95 @@ fn spawn_analysis<T: words::lib::RDH + 'static>(
                 let mut links: Vec<u8> = Vec::new();
                 let mut link_process_channels: Vec<crossbeam_channel::Sender<CdpTuple<T>>> =
                     Vec::new();
-                let mut validator_thread_handles: Vec<std::thread::JoinHandle<()>> = Vec::new();
+                let mut validator_thread_handles: Vec<
+                    std::thread::JoinHandle<Option<validators::link_validator::LinkValidatorState>>,
+                > = Vec::new();
+                let mut rdh_version = checkpoint.as_ref().map(|c| c.rdh_version());
+                // CDPs with errors are written to a separate file, if they are saved
+                let (failing_cdps_ch, failing_cdps_handle) = match config.save_failing_cdps() {
+                    Some(path) if config.check().is_some() => {
+                        match write::failing_cdps::spawn_failing_cdps_writer::<T>(
+                            path,
+                            stats_sender_channel.clone(),
+                        ) {
+                            Ok((ch, handle)) => (Some(ch), Some(handle)),
+                            Err(e) => {
+                                stats_sender_channel
+                                    .send(stats::stats_controller::StatType::Error(format!(
+                                        "Failed to create the file for failing CDPs {}: {e}",
+                                        path.display()
+                                    )))
+                                    .unwrap();
+                                (None, None)
+                            }
+                        }
+                    }
+                    _ => (None, None),
+                };
+                // Checkpoints are also saved periodically while checking, so a crashed run can resume
+                let (mut periodic_checkpoint, snapshot_ch) = match config.checkpoint_file() {
+                    Some(_) if config.check().is_some() => {
+                        let (periodic_checkpoint, snapshot_ch) =
+                            util::checkpoint::PeriodicCheckpoint::new();
+                        (Some(periodic_checkpoint), Some(snapshot_ch))
+                    }
+                    _ => (None, None),
+                };
+                let mut link_validator_init = validators::lib::LinkValidatorInit {
+                    restored_link_states: checkpoint
+                        .map(|mut c| c.take_link_states())
+                        .unwrap_or_default(),
+                    failing_cdps_ch,
+                    snapshot_ch,
+                    cdp_tracer: input::cdp_trace::CdpTracer::new(&*config),
+                };
                 // Setup for view case
                 let mut its_payload_fsm_cont =
                     validators::its_payload_fsm_cont::ItsPayloadFsmContinuous::default();
+                // View of the first HBFs rendered while the checks run, if set
+                let mut concurrent_view =
+                    config.with_view().map(|util::config::ConcurrentView::Hbf| {
+                        view::concurrent_view::ConcurrentHbfView::new(
+                            config.view_limit(),
+                            config.gbt_wide(),
+                            config.view_output().cloned(),
+                        )
+                    });
+                // Last HBF of each link, to detect HBFs left open at the end of the input
+                let mut final_hbfs = stats::final_hbf::FinalHbfs::default();
+                // Last page of each link, to detect links that went silent before the end of the input
+                let mut link_activities = stats::link_activity::LinkActivities::default();
+                // Timeframes of each link, if the HBFs per timeframe are verified
+                let mut tf_tracker = config.hbf_per_tf().map(stats::timeframes::TfTracker::new);
+                // Trigger types of the HBFs of each CRU in recent orbits, compared across links with `check all`
+                let mut trigger_consistency_tracker =
+                    matches!(config.check(), Some(util::config::Check::All(_))).then(|| {
+                        stats::trigger_consistency::TriggerConsistencyTracker::new(
+                            config.trigger_consistency_orbits(),
+                        )
+                    });
+                // FEE ID, CRU ID, link ID and endpoint of every CDP, if a hardware map is given
+                let mut hw_map_validator = hw_map.map(validators::hw_map::HwMapValidator::new);
+                // Bytes and orbits with data of each link, if the throughput is analyzed
+                let mut throughput_tracker =
+                    config.analyze(util::config::Analysis::Throughput).then(|| {
+                        stats::link_throughput::ThroughputTracker::new(config.throughput_window())
+                    });
+                // Samples the payload bytes of each link, if the entropy is analyzed
+                let mut entropy_sampler =
+                    config.analyze(util::config::Analysis::Entropy).then(|| {
+                        stats::payload_entropy::EntropySampler::new(config.entropy_sample_every())
+                    });
+                // Counts the distinct payloads of each link, if the payload uniformity is analyzed
+                let analyze_payload_uniformity =
+                    config.analyze(util::config::Analysis::PayloadUniformity);
+                // Consumers assume the memory positions of the CDPs are in order, verified in debug builds or with `--paranoid`
+                let mut mem_pos_order_guard = config
+                    .paranoid()
+                    .then(input::data_wrapper::MemPosOrderGuard::default);
+                let mut end_of_input = false;
+                // CDPs received, reconciled with the CDPs sent by the reader at the end of the run
+                let mut consumed = stats::pipeline_counts::CdpCount::default();
                 loop {
                     if stop_flag.load(std::sync::atomic::Ordering::SeqCst) {
                         log::warn!("Stopping reader thread on stop flag!");
@@ -166,17 +342,72 @@ fn spawn_analysis<T: words::lib::RDH + 'static>(
                         Ok(cdp) => cdp,
                         Err(e) => {
                             debug_assert_eq!(e, crossbeam_channel::RecvError);
+                            end_of_input = true;
                             break;
                         }
                     };
-                    // Collect global stats
-                    // Send HBF seen if stop bit is 1
-                    for rdh in cdp_chunk.rdh_slice().iter() {
-                        if rdh.stop_bit() == 1 {
+                    let chunk_count = stats::pipeline_counts::CdpCount::of_chunk(&cdp_chunk);
+                    consumed += chunk_count;
+                    if let Some(in_flight_bytes) = &in_flight_bytes {
+                        in_flight_bytes.remove(chunk_count.bytes);
+                    }
+                    if let Some(mem_pos_order_guard) = &mut mem_pos_order_guard {
+                        for violation in mem_pos_order_guard.verify(&cdp_chunk) {
                             stats_sender_channel
-                                .send(stats::stats_controller::StatType::HBFsSeen(1))
+                                .send(stats::stats_controller::StatType::InternalError(
+                                    violation.to_string(),
+                                ))
                                 .unwrap();
                         }
+                    }
+                    if let Some(rdh) = cdp_chunk.rdh_slice().first() {
+                        rdh_version.get_or_insert(rdh.version());
+                    }
+                    // Collect global stats
+                    // Count HBF seen if stop bit is 1
+                    let mut accumulated_stats = stats::accumulators::Accumulators::default();
+                    for (rdh, mem_pos) in cdp_chunk
+                        .rdh_slice()
+                        .iter()
+                        .zip(cdp_chunk.rdh_mem_pos_slice())
+                    {
+                        if rdh.stop_bit() == 1 {
+                            accumulated_stats.hbfs_seen += 1;
+                        }
+                        final_hbfs.record(rdh);
+                        link_activities.record(rdh, *mem_pos);
+                        if let Some(throughput_tracker) = &mut throughput_tracker {
+                            throughput_tracker.record(rdh);
+                        }
+                        if let Some(tracker) = &mut trigger_consistency_tracker {
+                            if let Some(mismatch) = tracker.record(rdh, *mem_pos) {
+                                stats_sender_channel
+                                    .send(stats::stats_controller::StatType::Error(
+                                        mismatch.to_string(),
+                                    ))
+                                    .unwrap();
+                            }
+                        }
+                        if let Some(validator) = &mut hw_map_validator {
+                            if let Some(mismatch) = validator.check(rdh, *mem_pos) {
+                                stats_sender_channel
+                                    .send(stats::stats_controller::StatType::LinkError {
+                                        link_id: mismatch.seen.link_id,
+                                        msg: mismatch.to_string(),
+                                    })
+                                    .unwrap();
+                            }
+                        }
+                        if let Some(tf_tracker) = &mut tf_tracker {
+                            if let Some(deviation) = tf_tracker.record(rdh, *mem_pos) {
+                                stats_sender_channel
+                                    .send(stats::stats_controller::StatType::LinkError {
+                                        link_id: deviation.link_id(),
+                                        msg: deviation.to_string(),
+                                    })
+                                    .unwrap();
+                            }
+                        }
                         let layer = words::lib::layer_from_feeid(rdh.fee_id());
                         let stave = words::lib::stave_number_from_feeid(rdh.fee_id());
                         stats_sender_channel
@@ -191,23 +422,82 @@ fn spawn_analysis<T: words::lib::RDH + 'static>(
                             ))
                             .unwrap();
                     }
+                    if let Some(entropy_sampler) = &mut entropy_sampler {
+                        for (rdh, payload, _) in &cdp_chunk {
+                            entropy_sampler.record(
+                                rdh,
+                                payload,
+                                &mut accumulated_stats.byte_histograms,
+                            );
+                        }
+                    }
+                    if analyze_payload_uniformity {
+                        for (rdh, payload, mem_pos) in &cdp_chunk {
+                            stats::payload_uniformity::record_payload(
+                                rdh,
+                                payload,
+                                mem_pos,
+                                &mut accumulated_stats.payload_uniformity,
+                            );
+                        }
+                    }
+                    accumulated_stats.flush(&stats_sender_channel);
+                    for (link_id, page_counts) in stats::page_counts::count_pages_per_link(
+                        cdp_chunk.rdh_slice(),
+                        cdp_chunk.rdh_mem_pos_slice(),
+                    ) {
+                        stats_sender_channel
+                            .send(stats::stats_controller::StatType::LinkPages {
+                                link_id,
+                                page_counts,
+                            })
+                            .unwrap();
+                    }
 
+                    if let Some(concurrent_view) = &mut concurrent_view {
+                        // Not a data error, the checks are unaffected
+                        if let Err(e) = concurrent_view.view(&cdp_chunk) {
+                            log::warn!("Failed to write the view of the first HBFs: {e}");
+                        }
+                    }
                     // Do checks or view
                     if config.check().is_some() {
+                        if let Some(periodic_checkpoint) = &mut periodic_checkpoint {
+                            periodic_checkpoint
+                                .dispatched(cdp_chunk.rdh_slice(), cdp_chunk.rdh_mem_pos_slice());
+                        }
                         validators::lib::check_cdp_chunk(
                             cdp_chunk,
                             &mut links,
                             &mut link_process_channels,
                             &mut validator_thread_handles,
+                            &mut link_validator_init,
                             config.clone(),
                             stats_sender_channel.clone(),
                         );
+                        if let (Some(periodic_checkpoint), Some(rdh_version)) =
+                            (&mut periodic_checkpoint, rdh_version)
+                        {
+                            periodic_checkpoint.save_if_due(
+                                &*config,
+                                rdh_version,
+                                &link_validator_init.restored_link_states,
+                            );
+                        }
                     } else if config.view().is_some() {
+                        if let Some(cdp_tracer) = &mut link_validator_init.cdp_tracer {
+                            let handler = input::cdp_trace::current_thread_name();
+                            for (rdh, payload, mem_pos) in &cdp_chunk {
+                                cdp_tracer.trace(rdh, payload.len(), mem_pos, &handler);
+                            }
+                        }
                         if let Err(e) = view::lib::generate_view(
                             config.view().unwrap(),
                             cdp_chunk,
                             &stats_sender_channel,
                             &mut its_payload_fsm_cont,
+                            config.payload_word_size(),
+                            config.gbt_wide(),
                         ) {
                             stats_sender_channel
                                 .send(stats::stats_controller::StatType::Fatal(e.to_string()))
@@ -215,23 +505,137 @@ fn spawn_analysis<T: words::lib::RDH + 'static>(
                         }
                     }
                 }
+                if let Some(Err(e)) = concurrent_view.map(|view| view.finish()) {
+                    log::warn!("Failed to write the view of the first HBFs: {e}");
+                }
+                stats_sender_channel
+                    .send(stats::stats_controller::StatType::PipelineCounts(
+                        stats::pipeline_counts::ComponentCounts::Analysis {
+                            consumed,
+                            drained: end_of_input,
+                        },
+                    ))
+                    .unwrap();
+                // Only report open HBFs if the whole input was processed
+                if end_of_input && !stop_flag.load(std::sync::atomic::Ordering::SeqCst) {
+                    for (link_id, hbf) in final_hbfs.unclosed() {
+                        stats_sender_channel
+                            .send(stats::stats_controller::StatType::UnclosedHbf { link_id, hbf })
+                            .unwrap();
+                    }
+                    stats_sender_channel
+                        .send(stats::stats_controller::StatType::LinkActivity(Box::new(
+                            link_activities.into_links(),
+                        )))
+                        .unwrap();
+                    if let Some(tf_tracker) = tf_tracker {
+                        let mut check_evaluations =
+                            validators::check_registry::CheckEvaluations::default();
+                        check_evaluations.count_n(
+                            validators::check_registry::KnownCheck::HbfPerTf,
+                            tf_tracker.evaluations(),
+                        );
+                        stats_sender_channel
+                            .send(stats::stats_controller::StatType::CheckEvaluations(
+                                Box::new(check_evaluations),
+                            ))
+                            .unwrap();
+                        let (deviations, tf_summary) = tf_tracker.finish();
+                        for deviation in deviations {
+                            stats_sender_channel
+                                .send(stats::stats_controller::StatType::LinkError {
+                                    link_id: deviation.link_id(),
+                                    msg: deviation.to_string(),
+                                })
+                                .unwrap();
+                        }
+                        stats_sender_channel
+                            .send(stats::stats_controller::StatType::Timeframes(tf_summary))
+                            .unwrap();
+                    }
+                    if let Some(throughput_tracker) = throughput_tracker {
+                        stats_sender_channel
+                            .send(stats::stats_controller::StatType::Throughput(Box::new(
+                                throughput_tracker.finish(),
+                            )))
+                            .unwrap();
+                    }
+                }
+                if let Some(tracker) = trigger_consistency_tracker {
+                    let mut check_evaluations =
+                        validators::check_registry::CheckEvaluations::default();
+                    check_evaluations.count_n(
+                        validators::check_registry::KnownCheck::TriggerAcrossLinks,
+                        tracker.comparisons(),
+                    );
+                    stats_sender_channel
+                        .send(stats::stats_controller::StatType::CheckEvaluations(
+                            Box::new(check_evaluations),
+                        ))
+                        .unwrap();
+                }
+                if let Some(validator) = hw_map_validator {
+                    let mut check_evaluations =
+                        validators::check_registry::CheckEvaluations::default();
+                    check_evaluations.count_n(
+                        validators::check_registry::KnownCheck::HwMap,
+                        validator.evaluations(),
+                    );
+                    stats_sender_channel
+                        .send(stats::stats_controller::StatType::CheckEvaluations(
+                            Box::new(check_evaluations),
+                        ))
+                        .unwrap();
+                    stats_sender_channel
+                        .send(stats::stats_controller::StatType::HwMap(
+                            validator.summary(),
+                        ))
+                        .unwrap();
+                }
                 // Stop all threads
                 link_process_channels.clear();
-                validator_thread_handles.into_iter().for_each(|handle| {
-                    handle.join().expect("Failed to join a validator thread");
-                });
+                let mut link_states: Vec<validators::link_validator::LinkValidatorState> =
+                    validator_thread_handles
+                        .into_iter()
+                        .filter_map(|handle| {
+                            handle.join().expect("Failed to join a validator thread")
+                        })
+                        .collect();
+                // The link validators are done, dropping the last sender stops the failing CDPs writer
+                link_validator_init.failing_cdps_ch = None;
+                if let Some(handle) = failing_cdps_handle {
+                    handle
+                        .join()
+                        .expect("Failed to join the failing CDPs writer thread");
+                }
+                // Also saved if checking was stopped, as the link validators checked every CDP dispatched to them
+                if config.check().is_some() {
+                    if let Some(rdh_version) = rdh_version {
+                        // Links that were not seen since the checkpoint was restored keep their state
+                        link_states.append(&mut link_validator_init.restored_link_states);
+                        util::checkpoint::save_checkpoint(&*config, rdh_version, link_states);
+                    }
+                }
             }
         })
         .expect("Failed to spawn checker thread")
 }
 
-/// Start the [stderrlog] instance, and immediately use it to log the configured [DataOutputMode].
+/// Start the [stderrlog] instance, or the [JsonLogger][util::json_logger::JsonLogger] if JSON log format is set, and immediately use it to log the configured [DataOutputMode].
+///
+#[cfg(feature = "cli")]
 pub fn init_error_logger(cfg: &impl Config) {
-    stderrlog::new()
-        .module(module_path!())
-        .verbosity(cfg.verbosity() as usize)
-        .init()
-        .expect("Failed to initialize logger");
+    let verbosity = cfg.verbosity();
+    match cfg.log_format() {
+        util::config::LogFormat::Human => stderrlog::new()
+            .module(module_path!())
+            .verbosity(verbosity as usize)
+            .init()
+            .expect("Failed to initialize logger"),
+        util::config::LogFormat::Json => util::json_logger::JsonLogger::new(verbosity)
+            .init()
+            .expect("Failed to initialize logger"),
+    }
     match cfg.output_mode() {
         util::lib::DataOutputMode::Stdout => log::trace!("Data ouput set to stdout"),
         util::lib::DataOutputMode::File => log::trace!("Data ouput set to file"),
@@ -242,8 +646,11 @@ pub fn init_error_logger(cfg: &impl Config) {
 }
 
 /// Get the [config][util::config::Opt] from the command line arguments and return it as an [Arc][std::sync::Arc].
+///
+/// The options implied by `--strict` are applied, see [apply_strict_mode][util::config::Opt::apply_strict_mode].
+#[cfg(feature = "pipeline")]
 pub fn get_config() -> std::sync::Arc<util::config::Opt> {
-    let cfg = <util::config::Opt as structopt::StructOpt>::from_args();
+    let cfg = <util::config::Opt as structopt::StructOpt>::from_args().apply_strict_mode();
     std::sync::Arc::new(cfg)
 }
 
@@ -1,13 +1,12 @@
 use fastpasta::input::{
-    bufreader_wrapper::BufferedReaderWrapper, input_scanner::InputScanner, lib::init_reader,
+    bufreader_wrapper::BufferedReaderWrapper,
+    input_probe::{probe_input, read_probe_prefix, Confidence, PrefixedReader},
+    input_scanner::InputScanner,
+    lib::{init_reader, rdh_version_of, read_initial_rdh, read_offsets_file},
 };
 use fastpasta::stats::{lib::init_stats_controller, stats_controller};
 use fastpasta::util::lib::Config;
-use fastpasta::words::{
-    lib::RdhSubWord,
-    rdh::Rdh0,
-    rdh_cru::{RdhCRU, V6, V7},
-};
+use fastpasta::words::rdh_cru::{RdhCRU, V6, V7};
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
@@ -24,9 +23,51 @@ pub fn main() -> std::process::ExitCode {
         fastpasta::util::lib::Views::view(&*config)
     );
 
+    // Decoding a binary error log doesn't read any raw data
+    if let Some(decode_errors) = fastpasta::util::lib::Views::decode_errors(&*config) {
+        return match fastpasta::stats::error_log::decode_error_log(&decode_errors) {
+            Ok(()) => fastpasta::exit_success(),
+            Err(e) => {
+                log::error!("{e}");
+                std::process::ExitCode::from(1)
+            }
+        };
+    }
+
+    // Verifying a filtered output reads the source and the output directly, without the pipeline
+    if let Some(verify_filter) = fastpasta::util::lib::Views::verify_filter(&*config) {
+        return match fastpasta::write::verify_filter::run_verify_filter(&verify_filter) {
+            Ok(true) => fastpasta::exit_success(),
+            Ok(false) => std::process::ExitCode::from(1),
+            Err(e) => {
+                log::error!("{e}");
+                std::process::ExitCode::from(2)
+            }
+        };
+    }
+
     // Launch statistics thread
     // If max allowed errors is reached, stop the processing from the stats thread
     let (stat_controller, stat_send_channel, stop_flag) = init_stats_controller(&*config);
+    stat_send_channel
+        .send(stats_controller::StatType::RunMetadata(Box::new(
+            fastpasta::util::run_metadata::RunMetadata::new(&*config),
+        )))
+        .unwrap();
+
+    if let Err(e) = fastpasta::util::lib::validate_payload_word_size(&*config)
+        .and_then(|_| fastpasta::util::lib::validate_output(&*config))
+        .and_then(|_| fastpasta::util::lib::validate_filter_orbit_range(&*config))
+        .and_then(|_| fastpasta::util::lib::validate_report_detail(&*config))
+        .and_then(|_| fastpasta::util::lib::validate_with_view(&*config))
+    {
+        stat_send_channel
+            .send(stats_controller::StatType::Fatal(e))
+            .unwrap();
+        drop(stat_send_channel);
+        stat_controller.join().expect("Failed to join stats thread");
+        return std::process::ExitCode::from(1);
+    }
 
     let exit_code: std::process::ExitCode = match init_reader(&config) {
         Ok(readable) => init_processing(config, readable, stat_send_channel, stop_flag),
@@ -39,7 +80,11 @@ pub fn main() -> std::process::ExitCode {
         }
     };
 
-    stat_controller.join().expect("Failed to join stats thread");
+    let failed_fast = stat_controller.join().expect("Failed to join stats thread");
+    if failed_fast {
+        // Processing was stopped at the first error with `--fail-fast`
+        return std::process::ExitCode::from(4);
+    }
     exit_code
 }
 
@@ -49,16 +94,93 @@ fn init_processing(
     stat_send_channel: std::sync::mpsc::Sender<stats_controller::StatType>,
     thread_stopper: Arc<AtomicBool>,
 ) -> std::process::ExitCode {
+    // Resume from the offset of the checkpoint if one is saved
+    let checkpoint = match fastpasta::util::checkpoint::resume_input(&*config, &mut *reader) {
+        Ok(checkpoint) => checkpoint,
+        Err(e) => {
+            stat_send_channel
+                .send(stats_controller::StatType::Fatal(e))
+                .unwrap();
+            return std::process::ExitCode::from(1);
+        }
+    };
+    // Probe the first RDHs to tell if the input is CRU raw data at all, and give the probed bytes back to the reader
+    let prefix = if config.skip_input_probe() {
+        Vec::new()
+    } else {
+        match read_probe_prefix(&mut reader) {
+            Ok(prefix) => prefix,
+            Err(e) => {
+                stat_send_channel
+                    .send(stats_controller::StatType::Fatal(e.to_string()))
+                    .unwrap();
+                return std::process::ExitCode::from(1);
+            }
+        }
+    };
+    if !prefix.is_empty() {
+        let probe = probe_input(&prefix);
+        match probe.confidence {
+            Confidence::High => log::info!("{}", probe.verdict()),
+            Confidence::Low => log::warn!("{}", probe.verdict()),
+            Confidence::NotRecognized => {
+                stat_send_channel
+                    .send(stats_controller::StatType::Fatal(format!(
+                        "{}, use --skip-input-probe to process it anyway",
+                        probe.verdict()
+                    )))
+                    .unwrap();
+                return std::process::ExitCode::from(3);
+            }
+        }
+    }
+    let mut reader: Box<dyn BufferedReaderWrapper> = Box::new(PrefixedReader::new(prefix, reader));
     // Determine RDH version
-    let rdh0 = Rdh0::load(&mut reader).expect("Failed to read first RDH0");
-    let rdh_version = rdh0.header_id;
+    let initial_rdh = match read_initial_rdh(&mut reader) {
+        Ok(initial_rdh) => initial_rdh,
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof && checkpoint.is_some() => {
+            log::info!("No new data since the checkpoint");
+            return fastpasta::exit_success();
+        }
+        Err(e) => panic!("Failed to read first RDH: {e:?}"),
+    };
+    let rdh_version = rdh_version_of(&initial_rdh);
+    if let Some(checkpoint) = &checkpoint {
+        if checkpoint.rdh_version() != rdh_version {
+            stat_send_channel
+                .send(stats_controller::StatType::Fatal(format!(
+                    "RDH version {rdh_version} does not match the RDH version {} of the checkpoint",
+                    checkpoint.rdh_version()
+                )))
+                .unwrap();
+            return std::process::ExitCode::from(3);
+        }
+    }
     // Send RDH version to stats thread
     stat_send_channel
         .send(stats_controller::StatType::RdhVersion(rdh_version))
         .unwrap();
-    // Create input scanner from the already read RDH0 (to avoid seeking back and reading it twice, which would also break with stdin piping)
-    let loader =
-        InputScanner::new_from_rdh0(config.clone(), reader, stat_send_channel.clone(), rdh0);
+    // Create input scanner from the already read RDH (to avoid seeking back and reading it twice, which would also break with stdin piping)
+    let mut loader = InputScanner::with_initial_rdh(
+        config.clone(),
+        reader,
+        stat_send_channel.clone(),
+        initial_rdh,
+    );
+    if let Some(checkpoint) = &checkpoint {
+        loader.start_at_mem_pos(checkpoint.last_offset());
+    }
+    if let Some(path) = config.extract_offsets() {
+        match read_offsets_file(path) {
+            Ok(offsets) => loader.extract_offsets(offsets),
+            Err(e) => {
+                stat_send_channel
+                    .send(stats_controller::StatType::Fatal(e))
+                    .unwrap();
+                return std::process::ExitCode::from(1);
+            }
+        }
+    }
 
     // Choose the rest of the execution based on the RDH version
     // Necessary to prevent heap allocation and allow static dispatch as the type cannot be known at compile time
@@ -68,6 +190,7 @@ fn init_processing(
             loader,
             stat_send_channel.clone(),
             thread_stopper,
+            checkpoint,
         ) {
             Ok(_) => fastpasta::exit_success(),
             Err(e) => {
@@ -82,6 +205,7 @@ fn init_processing(
             loader,
             stat_send_channel.clone(),
             thread_stopper,
+            checkpoint,
         ) {
             Ok(_) => fastpasta::exit_success(),
             Err(e) => {
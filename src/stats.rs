@@ -1,4 +1,24 @@
 //! All stat collecting functionality, and controller that can stop the program based on the collected stats.
+pub mod accumulators;
+pub mod cdp_detail;
+pub mod error_csv;
+mod error_free_stretch;
+pub mod error_log;
+pub mod final_hbf;
+mod first_errors;
+pub mod interleaving;
 pub mod lib;
+pub mod link_activity;
+mod link_error_mute;
+pub mod link_throughput;
+pub mod memory_usage;
+pub mod page_counts;
+pub mod payload_entropy;
+pub mod payload_uniformity;
+pub mod pipeline_counts;
+pub mod quality_score;
+mod reorder_buffer;
 mod report;
 pub mod stats_controller;
+pub mod timeframes;
+pub mod trigger_consistency;
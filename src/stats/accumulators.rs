@@ -0,0 +1,157 @@
+//! Contains the [Accumulators] struct, used to aggregate counters locally before sending them to the [StatsController][super::stats_controller::StatsController].
+//!
+//! Sending a [StatType] message for every RDH (or GBT word) adds significant overhead to the stats channel.
+//! Instead, counters are accumulated while processing a [CdpChunk][crate::input::data_wrapper::CdpChunk] and sent in a single [StatType::Accumulated] message.
+use super::stats_controller::StatType;
+
+/// Counters accumulated locally, and sent to the [StatsController][super::stats_controller::StatsController] in a single message.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Accumulators {
+    /// RDHs seen.
+    pub rdhs_seen: u64,
+    /// RDHs matching the link filter.
+    pub rdhs_filtered: u64,
+    /// RDHs matching the filter of each link, by link ID.
+    pub rdhs_filtered_per_link: Vec<(u8, u64)>,
+    /// RDHs excluded with `--exclude-link` of each link, by link ID.
+    pub rdhs_excluded_per_link: Vec<(u8, u64)>,
+    /// Payload size in bytes.
+    pub payload_size: u64,
+    /// HBFs seen.
+    pub hbfs_seen: u32,
+    /// Bytes of the input scanned, the sum of the offsets to the next RDH of the RDHs seen.
+    pub input_size: u64,
+    /// Histograms of the sampled payload bytes of each link, empty unless the entropy is analyzed.
+    pub byte_histograms: Vec<super::payload_entropy::LinkByteHistogram>,
+    /// Counts of the distinct payloads of each link, empty unless the payload uniformity is analyzed.
+    pub payload_uniformity: Vec<super::payload_uniformity::LinkPayloadUniformity>,
+}
+
+impl Accumulators {
+    /// Adds the counters of `other` to the counters of `self`.
+    pub fn merge(&mut self, other: &Accumulators) {
+        self.rdhs_seen += other.rdhs_seen;
+        self.rdhs_filtered += other.rdhs_filtered;
+        other
+            .rdhs_filtered_per_link
+            .iter()
+            .for_each(|(link_id, rdhs)| {
+                add_link_count(&mut self.rdhs_filtered_per_link, *link_id, *rdhs)
+            });
+        other
+            .rdhs_excluded_per_link
+            .iter()
+            .for_each(|(link_id, rdhs)| {
+                add_link_count(&mut self.rdhs_excluded_per_link, *link_id, *rdhs)
+            });
+        self.payload_size += other.payload_size;
+        self.hbfs_seen += other.hbfs_seen;
+        self.input_size += other.input_size;
+        super::payload_entropy::merge_histograms(&mut self.byte_histograms, &other.byte_histograms);
+        super::payload_uniformity::merge_uniformity(
+            &mut self.payload_uniformity,
+            &other.payload_uniformity,
+        );
+    }
+
+    /// Counts an RDH of the link `link_id` matching the filter.
+    pub fn count_rdh_filtered(&mut self, link_id: u8) {
+        self.rdhs_filtered += 1;
+        add_link_count(&mut self.rdhs_filtered_per_link, link_id, 1);
+    }
+
+    /// Counts an RDH of the link `link_id` excluded with `--exclude-link`.
+    pub fn count_rdh_excluded(&mut self, link_id: u8) {
+        add_link_count(&mut self.rdhs_excluded_per_link, link_id, 1);
+    }
+
+    /// Returns true if nothing has been accumulated.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Sends the accumulated counters as a [StatType::Accumulated] message and resets them.
+    ///
+    /// Nothing is sent if nothing has been accumulated.
+    pub fn flush(&mut self, stats_send_ch: &std::sync::mpsc::Sender<StatType>) {
+        if self.is_empty() {
+            return;
+        }
+        stats_send_ch
+            .send(StatType::Accumulated(std::mem::take(self)))
+            .unwrap();
+    }
+}
+
+/// Adds `count` to the count of the link `link_id` in `counts`, keeping the counts ordered by link ID.
+pub fn add_link_count(counts: &mut Vec<(u8, u64)>, link_id: u8, count: u64) {
+    match counts.binary_search_by_key(&link_id, |(link, _)| *link) {
+        Ok(idx) => counts[idx].1 += count,
+        Err(idx) => counts.insert(idx, (link_id, count)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let mut acc = Accumulators {
+            rdhs_seen: 1,
+            rdhs_filtered: 1,
+            rdhs_filtered_per_link: vec![(2, 1)],
+            rdhs_excluded_per_link: vec![(8, 2)],
+            payload_size: 100,
+            hbfs_seen: 0,
+            input_size: 0x2000,
+            ..Default::default()
+        };
+        acc.merge(&Accumulators {
+            rdhs_seen: 2,
+            rdhs_filtered: 3,
+            rdhs_filtered_per_link: vec![(0, 1), (2, 2)],
+            rdhs_excluded_per_link: vec![(3, 1), (8, 1)],
+            payload_size: 50,
+            hbfs_seen: 1,
+            input_size: 0x1000,
+            ..Default::default()
+        });
+        assert_eq!(
+            acc,
+            Accumulators {
+                rdhs_seen: 3,
+                rdhs_filtered: 4,
+                rdhs_filtered_per_link: vec![(0, 1), (2, 3)],
+                rdhs_excluded_per_link: vec![(3, 1), (8, 3)],
+                payload_size: 150,
+                hbfs_seen: 1,
+                input_size: 0x3000,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_flush_sends_once_and_resets() {
+        let (send, recv) = std::sync::mpsc::channel();
+        let mut acc = Accumulators::default();
+        acc.flush(&send);
+        assert!(recv.try_recv().is_err());
+
+        (0..100).for_each(|_| {
+            acc.rdhs_seen += 1;
+            acc.payload_size += 64;
+        });
+        acc.flush(&send);
+        assert!(acc.is_empty());
+        match recv.try_recv() {
+            Ok(StatType::Accumulated(flushed)) => {
+                assert_eq!(flushed.rdhs_seen, 100);
+                assert_eq!(flushed.payload_size, 6400);
+            }
+            _ => unreachable!(),
+        }
+        assert!(recv.try_recv().is_err());
+    }
+}
@@ -0,0 +1,119 @@
+//! Contains the [CdpDetail] of a single CDP, listed in the detail section of the JSON report with `--report-detail cdp`.
+//!
+//! The word counts are collected by the [CdpRunningValidator][crate::validators::cdp_running::CdpRunningValidator] while it checks the payload,
+//! and the details are sent to the [StatsController][super::stats_controller::StatsController] in batches by each link validator.
+use crate::words::lib::RDH;
+
+/// Number of each kind of GBT word found in a payload by the payload state machine.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WordCounts {
+    /// IHWs, including IHWs of continued packets.
+    pub ihw: u32,
+    /// TDHs.
+    pub tdh: u32,
+    /// TDTs.
+    pub tdt: u32,
+    /// DDW0s.
+    pub ddw0: u32,
+    /// CDWs.
+    pub cdw: u32,
+    /// Data words.
+    pub data: u32,
+}
+
+/// The RDH fields and word counts of a CDP.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CdpDetail {
+    /// Memory position of the RDH.
+    pub mem_pos: u64,
+    /// Link ID.
+    pub link_id: u8,
+    /// FEE ID.
+    pub fee_id: u16,
+    /// Orbit.
+    pub orbit: u32,
+    /// Pages counter.
+    pub pages_counter: u16,
+    /// Stop bit.
+    pub stop_bit: u8,
+    /// Bytes of the payload.
+    pub payload_bytes: usize,
+    /// Words found in the payload.
+    pub words: WordCounts,
+}
+
+impl CdpDetail {
+    /// Creates the [CdpDetail] of the CDP with the [RDH] at `mem_pos`.
+    pub fn new<T: RDH>(rdh: &T, mem_pos: u64, payload_bytes: usize, words: WordCounts) -> Self {
+        Self {
+            mem_pos,
+            link_id: rdh.link_id(),
+            fee_id: rdh.fee_id(),
+            orbit: rdh.rdh1().orbit,
+            pages_counter: rdh.pages_counter(),
+            stop_bit: rdh.stop_bit(),
+            payload_bytes,
+            words,
+        }
+    }
+
+    /// The CDP as a JSON object.
+    pub fn to_json(&self) -> String {
+        let words = &self.words;
+        format!(
+            r#"{{"offset":{},"link":{},"fee":{},"orbit":{},"pages_counter":{},"stop_bit":{},"payload_bytes":{},"words":{{"ihw":{},"tdh":{},"tdt":{},"ddw0":{},"cdw":{},"data":{}}}}}"#,
+            self.mem_pos,
+            self.link_id,
+            self.fee_id,
+            self.orbit,
+            self.pages_counter,
+            self.stop_bit,
+            self.payload_bytes,
+            words.ihw,
+            words.tdh,
+            words.tdt,
+            words.ddw0,
+            words.cdw,
+            words.data
+        )
+    }
+}
+
+/// Formats the details of the CDPs as the detail section of the JSON report, ordered by memory position.
+pub fn cdp_details_to_json(details: &[CdpDetail]) -> String {
+    let mut details: Vec<&CdpDetail> = details.iter().collect();
+    details.sort_by_key(|detail| detail.mem_pos);
+    let cdps = details
+        .into_iter()
+        .map(CdpDetail::to_json)
+        .collect::<Vec<String>>()
+        .join(",");
+    format!(r#"{{"cdps":[{cdps}]}}"#)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh_cru::test_data::CORRECT_RDH_CRU_V7;
+
+    #[test]
+    fn test_cdp_details_to_json() {
+        let words = WordCounts {
+            ihw: 1,
+            tdh: 1,
+            tdt: 1,
+            ddw0: 0,
+            cdw: 0,
+            data: 3,
+        };
+        let details = [
+            CdpDetail::new(&CORRECT_RDH_CRU_V7, 0x100, 60, words),
+            CdpDetail::new(&CORRECT_RDH_CRU_V7, 0x0, 60, WordCounts::default()),
+        ];
+        let json = cdp_details_to_json(&details);
+        assert!(json.starts_with(r#"{"cdps":[{"offset":0,"link":0,"fee":20522,"orbit":192796021,"pages_counter":0,"stop_bit":0,"payload_bytes":60,"words":{"ihw":0,"#));
+        assert!(json.ends_with(
+            r#"{"offset":256,"link":0,"fee":20522,"orbit":192796021,"pages_counter":0,"stop_bit":0,"payload_bytes":60,"words":{"ihw":1,"tdh":1,"tdt":1,"ddw0":0,"cdw":0,"data":3}}]}"#
+        ));
+    }
+}
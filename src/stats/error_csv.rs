@@ -0,0 +1,237 @@
+//! Contains the [ErrorCsv] writer, that writes the errors to a CSV file as they are reported, for analysis in a spreadsheet.
+//!
+//! The fields of a row are read from the error message, formatted as `0x<hex>: [Exx] <message> [<word bytes>] (<context>)`,
+//! where the word bytes are only present for payload errors, and the context of the RDH only with `--verbose-errors`.
+//! Fields that are not in the message are left empty.
+use std::io::Write;
+
+/// The header of the error CSV file.
+pub const ERROR_CSV_HEADER: &str =
+    "code,memory_pos,link,fee_id,stave,orbit,severity,message,word_bytes";
+
+/// Severity of a row of the error CSV.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Counted in the total errors.
+    Error,
+    /// Reported as a warning, e.g. an ignored TDH trigger bit.
+    Warning,
+    /// Not reported as the link was auto-muted, only written with `--error-csv-include-muted`.
+    Muted,
+}
+
+impl Severity {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Muted => "muted",
+        }
+    }
+}
+
+/// A row of the error CSV.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ErrorCsvRow<'a> {
+    /// Error code, e.g. `E30`.
+    pub code: Option<&'a str>,
+    /// Memory position.
+    pub mem_pos: Option<u64>,
+    /// Link ID.
+    pub link_id: Option<u8>,
+    /// FEE ID.
+    pub fee_id: Option<u16>,
+    /// Layer and stave, e.g. `L5_42`.
+    pub stave: Option<&'a str>,
+    /// Orbit, as formatted in the message.
+    pub orbit: Option<&'a str>,
+    /// The message, without the memory position, error code, word bytes and context.
+    pub message: &'a str,
+    /// Bytes of the GBT word, e.g. `03 1A 00 00 75 D5 7D 0B 00 E8`.
+    pub word_bytes: Option<&'a str>,
+}
+
+impl<'a> ErrorCsvRow<'a> {
+    /// Reads the fields of a row from an error message, and the link of the error if it is known.
+    pub fn from_error_msg(msg: &'a str, link_id: Option<u8>) -> Self {
+        let mut row = ErrorCsvRow {
+            link_id,
+            mem_pos: super::reorder_buffer::mem_pos_from_error_msg(msg),
+            code: super::first_errors::error_code_from_error_msg(msg),
+            ..Default::default()
+        };
+        let mut message = match (row.mem_pos, msg.split_once(": ")) {
+            (Some(_), Some((_, after_mem_pos))) => after_mem_pos,
+            _ => msg,
+        };
+        if let Some(code) = row.code {
+            message = message
+                .strip_prefix(&format!("[{code}]"))
+                .unwrap_or(message)
+                .trim_start();
+        }
+        // The context of the RDH: (link 3, L5_42, orbit 0x0B7DD575, page 2)
+        if let Some((before, context)) = message.rsplit_once(" (link ") {
+            if let Some(context) = context.strip_suffix(')') {
+                let mut fields = context.split(", ");
+                let context_link = fields.next().and_then(|link| link.parse().ok());
+                row.stave = fields.next();
+                row.orbit = fields.next().and_then(|orbit| orbit.strip_prefix("orbit "));
+                row.link_id = row.link_id.or(context_link);
+                message = before;
+            }
+        }
+        // The bytes of the GBT word: [03 1A 00 00 75 D5 7D 0B 00 E8]
+        if let Some((before, word_bytes)) = message.trim_end().rsplit_once('[') {
+            if let Some(word_bytes) = word_bytes.strip_suffix(']') {
+                if is_word_bytes(word_bytes) {
+                    row.word_bytes = Some(word_bytes);
+                    message = before;
+                }
+            }
+        }
+        row.message = message.trim();
+        row
+    }
+
+    /// Formats the row as a line of the CSV, with the `severity`.
+    pub fn to_csv_line(&self, severity: Severity) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            self.code.unwrap_or_default(),
+            self.mem_pos
+                .map(|mem_pos| format!("{mem_pos:#X}"))
+                .unwrap_or_default(),
+            self.link_id
+                .map(|link_id| link_id.to_string())
+                .unwrap_or_default(),
+            self.fee_id
+                .map(|fee_id| format!("{fee_id:#X}"))
+                .unwrap_or_default(),
+            self.stave.unwrap_or_default(),
+            self.orbit.unwrap_or_default(),
+            severity.as_str(),
+            quote(self.message),
+            self.word_bytes.unwrap_or_default(),
+        )
+    }
+}
+
+/// Returns true if the string is bytes in hex separated by spaces, e.g. `03 1A 00`.
+fn is_word_bytes(s: &str) -> bool {
+    !s.is_empty()
+        && s.split(' ')
+            .all(|byte| byte.len() == 2 && byte.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Quotes a field of the CSV, doubling the quotes in it.
+fn quote(field: &str) -> String {
+    format!("\"{}\"", field.replace('"', "\"\""))
+}
+
+/// Writes the errors to a CSV file as they are reported, a line at a time, so that the file is complete up to the last error even if processing is interrupted.
+pub struct ErrorCsv {
+    writer: std::io::LineWriter<Box<dyn Write + Send>>,
+    include_muted: bool,
+}
+
+impl ErrorCsv {
+    /// Creates the CSV file and writes the header.
+    pub fn create(path: &std::path::Path, include_muted: bool) -> Result<Self, String> {
+        let file = std::fs::File::create(path)
+            .map_err(|e| format!("Failed to create error CSV {}: {e}", path.display()))?;
+        Self::new(Box::new(file), include_muted)
+            .map_err(|e| format!("Failed to write error CSV {}: {e}", path.display()))
+    }
+
+    fn new(output: Box<dyn Write + Send>, include_muted: bool) -> Result<Self, std::io::Error> {
+        let mut writer = std::io::LineWriter::new(output);
+        writeln!(writer, "{ERROR_CSV_HEADER}")?;
+        Ok(Self {
+            writer,
+            include_muted,
+        })
+    }
+
+    /// Writes the row, if rows of the `severity` are written.
+    pub fn write(&mut self, row: &ErrorCsvRow, severity: Severity) -> Result<(), std::io::Error> {
+        if severity == Severity::Muted && !self.include_muted {
+            return Ok(());
+        }
+        self.writer.write_all(row.to_csv_line(severity).as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_error_with_context() {
+        let msg = "0x140: [E30] ID is not 0xE0: 0xE1  [FF 3F 00 00 00 00 00 00 00 E1] (link 3, L5_42, orbit 0x0B7DD575, page 2)";
+        let row = ErrorCsvRow::from_error_msg(msg, None);
+        assert_eq!(
+            row,
+            ErrorCsvRow {
+                code: Some("E30"),
+                mem_pos: Some(0x140),
+                link_id: Some(3),
+                fee_id: None,
+                stave: Some("L5_42"),
+                orbit: Some("0x0B7DD575"),
+                message: "ID is not 0xE0: 0xE1",
+                word_bytes: Some("FF 3F 00 00 00 00 00 00 00 E1"),
+            }
+        );
+        assert_eq!(
+            row.to_csv_line(Severity::Error),
+            "E30,0x140,3,,L5_42,0x0B7DD575,error,\"ID is not 0xE0: 0xE1\",FF 3F 00 00 00 00 00 00 00 E1\n"
+        );
+    }
+
+    #[test]
+    fn test_rdh_error_and_unpositioned_error() {
+        let row = ErrorCsvRow::from_error_msg(
+            "0xE450FFD: [E10] RDH sanity check failed: data_format = 255",
+            Some(0),
+        );
+        assert_eq!(
+            row.to_csv_line(Severity::Warning),
+            "E10,0xE450FFD,0,,,,warning,\"RDH sanity check failed: data_format = 255\",\n"
+        );
+        // Quotes in the message are doubled, the link isn't read from other parentheses
+        let row = ErrorCsvRow::from_error_msg("Link 3: \"unterminated\" HBFs (2 pages)", None);
+        assert_eq!(
+            row.to_csv_line(Severity::Error),
+            ",,,,,,error,\"Link 3: \"\"unterminated\"\" HBFs (2 pages)\",\n"
+        );
+    }
+
+    #[test]
+    fn test_muted_rows_only_written_if_included() {
+        for include_muted in [false, true] {
+            let output = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+            let mut error_csv =
+                ErrorCsv::new(Box::new(SharedBuffer(output.clone())), include_muted).unwrap();
+            let row = ErrorCsvRow::from_error_msg("0x40: [E30] ID is not 0xE0", Some(1));
+            error_csv.write(&row, Severity::Error).unwrap();
+            error_csv.write(&row, Severity::Muted).unwrap();
+            let csv = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+            let lines: Vec<&str> = csv.lines().collect();
+            assert_eq!(lines[0], ERROR_CSV_HEADER);
+            assert_eq!(lines.len(), if include_muted { 3 } else { 2 });
+        }
+    }
+
+    /// A writer to a buffer that is read after the [ErrorCsv] wrote to it.
+    struct SharedBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+}
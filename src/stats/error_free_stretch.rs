@@ -0,0 +1,200 @@
+//! Contains the [ErrorPositions] that records the memory positions of errors, and computes the [ErrorFreeStretch] between them.
+//!
+//! The longest error-free stretch and the mean distance between errors quantify the data quality of a run, globally and per link.
+use tabled::Tabled;
+
+/// Tracks the errors of the input, or of a link, by their memory positions, without storing them.
+///
+/// Errors are expected in memory order, as each link validator checks its CDPs in order.
+/// An error behind the last one, e.g. from another link, still counts, and if it falls in the longest gap between errors the gap is split,
+/// so the longest gap can be underestimated but is never overestimated.
+#[derive(Debug, Default, Clone)]
+pub struct ErrorPositions {
+    /// Number of errors at distinct memory positions.
+    errors: u64,
+    first: u64,
+    last: u64,
+    /// Start and length of the longest gap between two errors.
+    longest_gap_start: u64,
+    longest_gap: u64,
+}
+
+impl ErrorPositions {
+    /// Records the memory position of an error.
+    pub fn record(&mut self, mem_pos: u64) {
+        if self.errors == 0 {
+            self.first = mem_pos;
+            self.last = mem_pos;
+            self.errors = 1;
+            return;
+        }
+        if [
+            self.first,
+            self.last,
+            self.longest_gap_start,
+            self.longest_gap_start + self.longest_gap,
+        ]
+        .contains(&mem_pos)
+        {
+            return;
+        }
+        self.errors += 1;
+        if mem_pos > self.last {
+            self.update_longest_gap(self.last, mem_pos);
+            self.last = mem_pos;
+        } else if mem_pos < self.first {
+            self.update_longest_gap(mem_pos, self.first);
+            self.first = mem_pos;
+        } else if mem_pos > self.longest_gap_start
+            && mem_pos < self.longest_gap_start + self.longest_gap
+        {
+            let gap_end = self.longest_gap_start + self.longest_gap;
+            if mem_pos - self.longest_gap_start >= gap_end - mem_pos {
+                self.longest_gap = mem_pos - self.longest_gap_start;
+            } else {
+                self.longest_gap_start = mem_pos;
+                self.longest_gap = gap_end - mem_pos;
+            }
+        }
+    }
+
+    fn update_longest_gap(&mut self, gap_start: u64, gap_end: u64) {
+        if gap_end - gap_start > self.longest_gap {
+            self.longest_gap_start = gap_start;
+            self.longest_gap = gap_end - gap_start;
+        }
+    }
+
+    /// Computes the error-free stretches of the memory positions from `start` to `end` with `cdps` CDPs.
+    ///
+    /// The stretches are the distances from `start` to the first error, between consecutive errors, and from the last error to `end`.
+    /// Several errors at the same memory position count as one.
+    pub fn error_free_stretch(&self, start: u64, end: u64, cdps: u64) -> ErrorFreeStretch {
+        let span = end.saturating_sub(start);
+        if self.errors == 0 {
+            return ErrorFreeStretch::new(span, None, span, cdps);
+        }
+        let longest_bytes = self
+            .first
+            .saturating_sub(start)
+            .max(end.saturating_sub(self.last))
+            .max(self.longest_gap);
+        let mean_distance = if self.errors > 1 {
+            Some((self.last - self.first) as f64 / (self.errors - 1) as f64)
+        } else {
+            None
+        };
+        ErrorFreeStretch::new(longest_bytes, mean_distance, span, cdps)
+    }
+}
+
+/// The longest error-free stretch, and the mean distance between errors.
+#[derive(Debug, Default, Clone, PartialEq, Tabled)]
+pub struct ErrorFreeStretch {
+    /// Longest error-free stretch in bytes.
+    #[tabled(
+        rename = "Longest error-free stretch",
+        display_with = "super::report::format_byte_size"
+    )]
+    pub longest_bytes: u64,
+    /// Longest error-free stretch in CDPs, estimated from the mean CDP size.
+    #[tabled(rename = "Longest error-free CDPs (est.)")]
+    pub longest_cdps: u64,
+    /// Mean distance between errors in bytes, [None] if there are less than 2 errors.
+    #[tabled(
+        rename = "Mean distance between errors",
+        display_with = "display_mean_distance"
+    )]
+    pub mean_distance: Option<f64>,
+}
+
+impl ErrorFreeStretch {
+    fn new(longest_bytes: u64, mean_distance: Option<f64>, span: u64, cdps: u64) -> Self {
+        let longest_cdps = if span == 0 {
+            0
+        } else {
+            (longest_bytes as f64 * cdps as f64 / span as f64).round() as u64
+        };
+        Self {
+            longest_bytes,
+            longest_cdps,
+            mean_distance,
+        }
+    }
+}
+
+fn display_mean_distance(mean_distance: &Option<f64>) -> String {
+    match mean_distance {
+        Some(mean_distance) => super::report::format_byte_size(&(mean_distance.round() as u64)),
+        None => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stretch_of(positions: &[u64], input_size: u64, cdps: u64) -> ErrorFreeStretch {
+        let mut error_positions = ErrorPositions::default();
+        positions
+            .iter()
+            .for_each(|mem_pos| error_positions.record(*mem_pos));
+        error_positions.error_free_stretch(0, input_size, cdps)
+    }
+
+    #[test]
+    fn test_no_errors_whole_input() {
+        assert_eq!(
+            stretch_of(&[], 0x10000, 16),
+            ErrorFreeStretch {
+                longest_bytes: 0x10000,
+                longest_cdps: 16,
+                mean_distance: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_errors_between() {
+        // Unsorted, and two errors at the same position
+        let stretch = stretch_of(&[0x3000, 0x1000, 0x8000, 0x3000], 0x9000, 9);
+        assert_eq!(stretch.longest_bytes, 0x5000);
+        assert_eq!(stretch.longest_cdps, 5);
+        assert_eq!(stretch.mean_distance, Some(0x3800 as f64));
+    }
+
+    #[test]
+    fn test_error_at_start_and_end() {
+        let stretch = stretch_of(&[0x0, 0x9000], 0x9000, 9);
+        assert_eq!(stretch.longest_bytes, 0x9000);
+        assert_eq!(stretch.mean_distance, Some(0x9000 as f64));
+
+        // A single error at the start, the rest of the input is error-free
+        let stretch = stretch_of(&[0x0], 0x9000, 9);
+        assert_eq!(stretch.longest_bytes, 0x9000);
+        assert_eq!(stretch.mean_distance, None);
+
+        // A single error near the end
+        let stretch = stretch_of(&[0x8FC0], 0x9000, 9);
+        assert_eq!(stretch.longest_bytes, 0x8FC0);
+        assert_eq!(stretch.longest_cdps, 9);
+    }
+
+    #[test]
+    fn test_split_longest_gap_out_of_order() {
+        // The error at 0x6000 arrives last, and splits the longest gap from 0x1000 to 0x8000
+        let stretch = stretch_of(&[0x1000, 0x8000, 0x9000, 0x6000], 0xA000, 10);
+        assert_eq!(stretch.longest_bytes, 0x5000);
+        assert_eq!(stretch.mean_distance, Some(0x8000 as f64 / 3.0));
+    }
+
+    #[test]
+    fn test_stretch_of_link_span() {
+        let mut error_positions = ErrorPositions::default();
+        error_positions.record(0x5000);
+        // The link spans 0x4000 to 0x6000 of a larger input, the stretches are within the span
+        let stretch = error_positions.error_free_stretch(0x4000, 0x6000, 8);
+        assert_eq!(stretch.longest_bytes, 0x1000);
+        assert_eq!(stretch.longest_cdps, 4);
+    }
+}
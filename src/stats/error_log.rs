@@ -0,0 +1,623 @@
+//! Contains the [ErrorLogWriter], that writes the errors to a compact binary file as they are reported, and the [ErrorLogReader] that reads them back for `fastpasta errors decode`.
+//!
+//! For runs with millions of errors, a text log or the [ErrorCsv][super::error_csv::ErrorCsv] is larger than the data it describes.
+//! The binary error log stores the fields of an error in a fixed size record, and each distinct message only once in a string table.
+//!
+//! The file starts with the magic bytes `FPEL` and the format version, followed by entries that each start with a tag byte. All integers are little-endian.
+//! - `S`: a message of the string table, its length as a u32 followed by its UTF-8 bytes. Messages are numbered in the order they are written, starting from 0.
+//! - `E`: an error record of [RECORD_SIZE] bytes: code u16, flags u8, link u8, fee u16, mem_pos u64, orbit u32, word [u8; 10] and message u32.
+//!   The flags hold the [Severity] and which of the optional fields are present, the message is the number of a message written before the record.
+use super::error_csv::{ErrorCsvRow, Severity, ERROR_CSV_HEADER};
+use crate::util::config::DecodeErrors;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+/// Magic bytes at the start of a binary error log.
+pub const MAGIC: &[u8; 4] = b"FPEL";
+/// Version of the format of the binary error log.
+pub const FORMAT_VERSION: u8 = 1;
+/// Size in bytes of an error record, without its tag.
+pub const RECORD_SIZE: usize = 32;
+/// Maximum number of messages remembered by the writer to write each message once, further messages are written for every error.
+const MAX_REMEMBERED_MESSAGES: usize = 0x10000;
+
+const TAG_MESSAGE: u8 = b'S';
+const TAG_RECORD: u8 = b'E';
+
+const SEVERITY_MASK: u8 = 0b11;
+const HAS_CODE: u8 = 1 << 2;
+const HAS_LINK: u8 = 1 << 3;
+const HAS_FEE: u8 = 1 << 4;
+const HAS_MEM_POS: u8 = 1 << 5;
+const HAS_ORBIT: u8 = 1 << 6;
+const HAS_WORD: u8 = 1 << 7;
+
+/// An error of the binary error log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorRecord {
+    /// Number of the error code, e.g. 30 for `E30`.
+    pub code: Option<u16>,
+    /// Severity of the error.
+    pub severity: Severity,
+    /// Link ID.
+    pub link_id: Option<u8>,
+    /// FEE ID.
+    pub fee_id: Option<u16>,
+    /// Memory position.
+    pub mem_pos: Option<u64>,
+    /// Orbit of the RDH, only in the message with `--verbose-errors`.
+    pub orbit: Option<u32>,
+    /// The GBT word of a payload error.
+    pub word: Option<[u8; 10]>,
+    /// The message, without the memory position, error code, word and context.
+    pub message: String,
+}
+
+impl ErrorRecord {
+    /// Creates a record from the fields read from an error message.
+    ///
+    /// Word bytes that are not a GBT word are kept in the message, as the record only fits a GBT word.
+    pub fn from_row(row: &ErrorCsvRow, severity: Severity) -> Self {
+        let word = row.word_bytes.and_then(parse_gbt_word);
+        let message = match (row.word_bytes, word) {
+            (Some(word_bytes), None) => format!("{} [{word_bytes}]", row.message),
+            _ => row.message.to_string(),
+        };
+        Self {
+            code: row
+                .code
+                .and_then(|code| code.strip_prefix('E'))
+                .and_then(|digits| digits.parse().ok()),
+            severity,
+            link_id: row.link_id,
+            fee_id: row.fee_id,
+            mem_pos: row.mem_pos,
+            orbit: row.orbit.and_then(|orbit| {
+                let hex_digits = orbit.strip_prefix("0x").unwrap_or(orbit);
+                u32::from_str_radix(hex_digits, 16).ok()
+            }),
+            word,
+            message,
+        }
+    }
+
+    /// Formats the record as a line of text, like the error was logged, with the link, FEE ID and orbit appended.
+    pub fn to_text_line(&self) -> String {
+        let mut line = String::from(match self.severity {
+            Severity::Error => "ERROR - ",
+            Severity::Warning => "WARN - ",
+            Severity::Muted => "MUTED - ",
+        });
+        if let Some(mem_pos) = self.mem_pos {
+            line.push_str(&format!("{mem_pos:#X}: "));
+        }
+        if let Some(code) = self.code {
+            line.push_str(&format!("[E{code}] "));
+        }
+        line.push_str(&self.message);
+        if let Some(word) = self.word {
+            line.push_str(&format!(" [{}]", word_as_string(&word)));
+        }
+        let mut context = Vec::new();
+        if let Some(link_id) = self.link_id {
+            context.push(format!("link {link_id}"));
+        }
+        if let Some(fee_id) = self.fee_id {
+            context.push(format!("fee {fee_id:#X}"));
+        }
+        if let Some(orbit) = self.orbit {
+            context.push(format!("orbit {orbit:#010X}"));
+        }
+        if !context.is_empty() {
+            line.push_str(&format!(" ({})", context.join(", ")));
+        }
+        line.push('\n');
+        line
+    }
+
+    /// Formats the record as a line of the error CSV. The stave is left empty, it is not stored in the record.
+    pub fn to_csv_line(&self) -> String {
+        let code = self.code.map(|code| format!("E{code}"));
+        let orbit = self.orbit.map(|orbit| format!("{orbit:#010X}"));
+        let word_bytes = self.word.map(|word| word_as_string(&word));
+        ErrorCsvRow {
+            code: code.as_deref(),
+            mem_pos: self.mem_pos,
+            link_id: self.link_id,
+            fee_id: self.fee_id,
+            stave: None,
+            orbit: orbit.as_deref(),
+            message: &self.message,
+            word_bytes: word_bytes.as_deref(),
+        }
+        .to_csv_line(self.severity)
+    }
+
+    /// Returns true if the record passes the filters of the decode options.
+    fn is_selected(&self, opts: &DecodeErrors) -> bool {
+        let in_range = |mem_pos: u64| {
+            !matches!(opts.from, Some(from) if mem_pos < from)
+                && !matches!(opts.to, Some(to) if mem_pos > to)
+        };
+        (opts.code.is_none() || self.code == opts.code)
+            && (opts.link.is_none() || self.link_id == opts.link)
+            && match self.mem_pos {
+                Some(mem_pos) => in_range(mem_pos),
+                None => opts.from.is_none() && opts.to.is_none(),
+            }
+    }
+
+    fn encode(&self, message_id: u32) -> [u8; RECORD_SIZE] {
+        let mut flags = match self.severity {
+            Severity::Error => 0,
+            Severity::Warning => 1,
+            Severity::Muted => 2,
+        };
+        for (is_present, flag) in [
+            (self.code.is_some(), HAS_CODE),
+            (self.link_id.is_some(), HAS_LINK),
+            (self.fee_id.is_some(), HAS_FEE),
+            (self.mem_pos.is_some(), HAS_MEM_POS),
+            (self.orbit.is_some(), HAS_ORBIT),
+            (self.word.is_some(), HAS_WORD),
+        ] {
+            if is_present {
+                flags |= flag;
+            }
+        }
+        let mut record = [0; RECORD_SIZE];
+        record[0..2].copy_from_slice(&self.code.unwrap_or_default().to_le_bytes());
+        record[2] = flags;
+        record[3] = self.link_id.unwrap_or_default();
+        record[4..6].copy_from_slice(&self.fee_id.unwrap_or_default().to_le_bytes());
+        record[6..14].copy_from_slice(&self.mem_pos.unwrap_or_default().to_le_bytes());
+        record[14..18].copy_from_slice(&self.orbit.unwrap_or_default().to_le_bytes());
+        record[18..28].copy_from_slice(&self.word.unwrap_or_default());
+        record[28..32].copy_from_slice(&message_id.to_le_bytes());
+        record
+    }
+
+    /// Decodes a record, with the message it refers to.
+    fn decode(record: &[u8; RECORD_SIZE], message: String) -> Result<Self, String> {
+        let flags = record[2];
+        let severity = match flags & SEVERITY_MASK {
+            0 => Severity::Error,
+            1 => Severity::Warning,
+            2 => Severity::Muted,
+            severity => return Err(format!("unknown severity {severity}")),
+        };
+        let field = |flag: u8, range: std::ops::Range<usize>| {
+            if flags & flag == 0 {
+                None
+            } else {
+                Some(&record[range])
+            }
+        };
+        Ok(Self {
+            code: field(HAS_CODE, 0..2).map(|b| u16::from_le_bytes([b[0], b[1]])),
+            severity,
+            link_id: field(HAS_LINK, 3..4).map(|b| b[0]),
+            fee_id: field(HAS_FEE, 4..6).map(|b| u16::from_le_bytes([b[0], b[1]])),
+            mem_pos: field(HAS_MEM_POS, 6..14)
+                .map(|b| u64::from_le_bytes(b.try_into().expect("8 bytes"))),
+            orbit: field(HAS_ORBIT, 14..18)
+                .map(|b| u32::from_le_bytes(b.try_into().expect("4 bytes"))),
+            word: field(HAS_WORD, 18..28).map(|b| b.try_into().expect("10 bytes")),
+            message,
+        })
+    }
+}
+
+/// Parses the bytes of a GBT word formatted as hex separated by spaces, e.g. `03 1A 00 00 75 D5 7D 0B 00 E8`.
+fn parse_gbt_word(word_bytes: &str) -> Option<[u8; 10]> {
+    let bytes = word_bytes
+        .split(' ')
+        .map(|byte| u8::from_str_radix(byte, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    bytes.try_into().ok()
+}
+
+fn word_as_string(word: &[u8; 10]) -> String {
+    word.iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Writes the errors to a binary error log as they are reported.
+///
+/// The output is buffered, the buffer is written when the writer is dropped.
+pub struct ErrorLogWriter {
+    writer: std::io::BufWriter<Box<dyn Write + Send>>,
+    message_ids: HashMap<String, u32>,
+    messages_written: u32,
+}
+
+impl ErrorLogWriter {
+    /// Creates the binary error log and writes the magic bytes and format version.
+    pub fn create(path: &std::path::Path) -> Result<Self, String> {
+        let file = std::fs::File::create(path)
+            .map_err(|e| format!("Failed to create binary error log {}: {e}", path.display()))?;
+        Self::new(Box::new(file))
+            .map_err(|e| format!("Failed to write binary error log {}: {e}", path.display()))
+    }
+
+    fn new(output: Box<dyn Write + Send>) -> Result<Self, std::io::Error> {
+        let mut writer = std::io::BufWriter::new(output);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION])?;
+        Ok(Self {
+            writer,
+            message_ids: HashMap::new(),
+            messages_written: 0,
+        })
+    }
+
+    /// Writes the error, and its message to the string table if it was not written before.
+    pub fn write(&mut self, row: &ErrorCsvRow, severity: Severity) -> Result<(), std::io::Error> {
+        let record = ErrorRecord::from_row(row, severity);
+        let message_id = match self.message_ids.get(&record.message) {
+            Some(message_id) => *message_id,
+            None => self.write_message(&record.message)?,
+        };
+        self.writer.write_all(&[TAG_RECORD])?;
+        self.writer.write_all(&record.encode(message_id))
+    }
+
+    fn write_message(&mut self, message: &str) -> Result<u32, std::io::Error> {
+        let message_id = self.messages_written;
+        self.writer.write_all(&[TAG_MESSAGE])?;
+        self.writer
+            .write_all(&(message.len() as u32).to_le_bytes())?;
+        self.writer.write_all(message.as_bytes())?;
+        self.messages_written += 1;
+        if self.message_ids.len() < MAX_REMEMBERED_MESSAGES {
+            self.message_ids.insert(message.to_string(), message_id);
+        }
+        Ok(message_id)
+    }
+
+    /// Writes the buffered errors.
+    pub fn flush(&mut self) -> Result<(), std::io::Error> {
+        self.writer.flush()
+    }
+}
+
+/// Reads the errors of a binary error log, the string table is read along the way.
+pub struct ErrorLogReader<R: Read> {
+    reader: R,
+    messages: Vec<String>,
+    /// Byte position in the log, for error messages.
+    pos: u64,
+}
+
+impl<R: Read> ErrorLogReader<R> {
+    /// Checks the magic bytes and format version at the start of the log.
+    pub fn new(mut reader: R) -> Result<Self, String> {
+        let mut header = [0; 5];
+        reader
+            .read_exact(&mut header)
+            .map_err(|e| format!("Failed to read the header: {e}"))?;
+        if &header[..4] != MAGIC {
+            return Err("Not a binary error log, it does not start with `FPEL`".to_string());
+        }
+        if header[4] != FORMAT_VERSION {
+            return Err(format!(
+                "Unsupported binary error log format version {}, expected {FORMAT_VERSION}",
+                header[4]
+            ));
+        }
+        Ok(Self {
+            reader,
+            messages: Vec::new(),
+            pos: header.len() as u64,
+        })
+    }
+
+    /// Reads the next error, [None] at the end of the log.
+    pub fn next_record(&mut self) -> Result<Option<ErrorRecord>, String> {
+        loop {
+            let entry_pos = self.pos;
+            let mut tag = [0];
+            match self.reader.read(&mut tag) {
+                Ok(0) => return Ok(None),
+                Ok(_) => self.pos += 1,
+                Err(e) => return Err(format!("Failed to read at byte {entry_pos:#X}: {e}")),
+            }
+            match tag[0] {
+                TAG_MESSAGE => {
+                    let mut len = [0; 4];
+                    self.read_entry(&mut len, entry_pos)?;
+                    let mut message = vec![0; u32::from_le_bytes(len) as usize];
+                    self.read_entry(&mut message, entry_pos)?;
+                    let message = String::from_utf8(message).map_err(|e| {
+                        format!("Message at byte {entry_pos:#X} is not valid UTF-8: {e}")
+                    })?;
+                    self.messages.push(message);
+                }
+                TAG_RECORD => {
+                    let mut record = [0; RECORD_SIZE];
+                    self.read_entry(&mut record, entry_pos)?;
+                    let message_id =
+                        u32::from_le_bytes(record[28..32].try_into().expect("4 bytes")) as usize;
+                    let message = self.messages.get(message_id).cloned().ok_or_else(|| {
+                        format!(
+                            "Error at byte {entry_pos:#X} refers to unknown message {message_id}"
+                        )
+                    })?;
+                    return ErrorRecord::decode(&record, message)
+                        .map(Some)
+                        .map_err(|e| format!("Error at byte {entry_pos:#X} has an {e}"));
+                }
+                tag => return Err(format!("Unknown entry tag {tag:#X} at byte {entry_pos:#X}")),
+            }
+        }
+    }
+
+    fn read_entry(&mut self, buf: &mut [u8], entry_pos: u64) -> Result<(), String> {
+        self.reader
+            .read_exact(buf)
+            .map_err(|e| format!("Truncated entry at byte {entry_pos:#X}: {e}"))?;
+        self.pos += buf.len() as u64;
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for ErrorLogReader<R> {
+    type Item = Result<ErrorRecord, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+/// Decodes the binary error log of the [DecodeErrors] options and prints the selected errors to stdout, as text or CSV.
+///
+/// The errors read before a broken entry, e.g. at the end of a log of an interrupted run, are printed before the error is returned.
+pub fn decode_error_log(opts: &DecodeErrors) -> Result<(), String> {
+    let file = std::fs::File::open(&opts.file).map_err(|e| {
+        format!(
+            "Failed to open binary error log {}: {e}",
+            opts.file.display()
+        )
+    })?;
+    let reader = ErrorLogReader::new(std::io::BufReader::new(file))
+        .map_err(|e| format!("{}: {e}", opts.file.display()))?;
+    let mut stdout = std::io::BufWriter::new(std::io::stdout().lock());
+    decode_errors(reader, opts, &mut stdout).map_err(|e| format!("{}: {e}", opts.file.display()))
+}
+
+fn decode_errors<R: Read>(
+    reader: ErrorLogReader<R>,
+    opts: &DecodeErrors,
+    out: &mut impl Write,
+) -> Result<(), String> {
+    let write_failed = |e: std::io::Error| format!("Failed to write the decoded errors: {e}");
+    if opts.csv {
+        writeln!(out, "{ERROR_CSV_HEADER}").map_err(write_failed)?;
+    }
+    for record in reader {
+        let record = record?;
+        if !record.is_selected(opts) {
+            continue;
+        }
+        let line = if opts.csv {
+            record.to_csv_line()
+        } else {
+            record.to_text_line()
+        };
+        out.write_all(line.as_bytes()).map_err(write_failed)?;
+    }
+    out.flush().map_err(write_failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ERROR_MSGS: [(&str, Option<u8>, Option<u16>, Severity); 5] = [
+        (
+            "0x140: [E30] ID is not 0xE0: 0xE1  [FF 3F 00 00 00 00 00 00 00 E1] (link 3, L5_42, orbit 0x0B7DD575, page 2)",
+            None,
+            Some(0x502A),
+            Severity::Error,
+        ),
+        (
+            "0xE450FFD: [E10] RDH sanity check failed: data_format = 255",
+            Some(0),
+            None,
+            Severity::Warning,
+        ),
+        (
+            "0x180: [E30] ID is not 0xE0: 0xE1  [FF 3F 00 00 00 00 00 00 00 E1]",
+            Some(3),
+            Some(0x502A),
+            Severity::Muted,
+        ),
+        ("Link 3: \"unterminated\" HBFs (2 pages)", None, None, Severity::Error),
+        ("0x40: [E4401] short word [AB CD]", Some(255), None, Severity::Error),
+    ];
+
+    fn encoded_log() -> Vec<u8> {
+        let output = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut error_log = ErrorLogWriter::new(Box::new(SharedBuffer(output.clone()))).unwrap();
+        for (msg, link_id, fee_id, severity) in ERROR_MSGS {
+            let mut row = ErrorCsvRow::from_error_msg(msg, link_id);
+            row.fee_id = fee_id;
+            error_log.write(&row, severity).unwrap();
+        }
+        error_log.flush().unwrap();
+        let encoded = output.lock().unwrap().clone();
+        encoded
+    }
+
+    fn decode_opts() -> DecodeErrors {
+        DecodeErrors {
+            file: std::path::PathBuf::new(),
+            csv: false,
+            code: None,
+            link: None,
+            from: None,
+            to: None,
+        }
+    }
+
+    fn decoded(encoded: &[u8], opts: &DecodeErrors) -> String {
+        let mut out = Vec::new();
+        decode_errors(ErrorLogReader::new(encoded).unwrap(), opts, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let encoded = encoded_log();
+        let decoded: Vec<ErrorRecord> = ErrorLogReader::new(&encoded[..])
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let expected: Vec<ErrorRecord> = ERROR_MSGS
+            .iter()
+            .map(|(msg, link_id, fee_id, severity)| {
+                let mut row = ErrorCsvRow::from_error_msg(msg, *link_id);
+                row.fee_id = *fee_id;
+                ErrorRecord::from_row(&row, *severity)
+            })
+            .collect();
+        assert_eq!(decoded, expected);
+        assert_eq!(
+            decoded[0],
+            ErrorRecord {
+                code: Some(30),
+                severity: Severity::Error,
+                link_id: Some(3),
+                fee_id: Some(0x502A),
+                mem_pos: Some(0x140),
+                orbit: Some(0x0B7DD575),
+                word: Some([0xFF, 0x3F, 0, 0, 0, 0, 0, 0, 0, 0xE1]),
+                message: "ID is not 0xE0: 0xE1".to_string(),
+            }
+        );
+        // Link 255 is not confused with an unknown link, and bytes that are not a GBT word stay in the message
+        assert_eq!(decoded[4].link_id, Some(255));
+        assert_eq!(decoded[4].message, "short word [AB CD]");
+        // The repeated message is only written once: 5 header bytes, 4 messages and 5 records
+        let messages_len: usize = [
+            "ID is not 0xE0: 0xE1",
+            "RDH sanity check failed: data_format = 255",
+        ]
+        .iter()
+        .chain(&[
+            "Link 3: \"unterminated\" HBFs (2 pages)",
+            "short word [AB CD]",
+        ])
+        .map(|message| 1 + 4 + message.len())
+        .sum();
+        assert_eq!(encoded.len(), 5 + messages_len + 5 * (1 + RECORD_SIZE));
+    }
+
+    #[test]
+    fn test_decode_as_csv_matches_error_csv() {
+        let opts = DecodeErrors {
+            csv: true,
+            ..decode_opts()
+        };
+        let expected_csv: String = std::iter::once(format!("{ERROR_CSV_HEADER}\n"))
+            .chain(
+                ERROR_MSGS[..4]
+                    .iter()
+                    .map(|(msg, link_id, fee_id, severity)| {
+                        let mut row = ErrorCsvRow::from_error_msg(msg, *link_id);
+                        row.fee_id = *fee_id;
+                        // The stave is not stored
+                        row.stave = None;
+                        row.to_csv_line(*severity)
+                    }),
+            )
+            .collect();
+        let decoded_csv = decoded(&encoded_log(), &opts);
+        let (decoded_csv, short_word_line) = decoded_csv.trim_end().rsplit_once('\n').unwrap();
+        assert_eq!(format!("{decoded_csv}\n"), expected_csv);
+        // Bytes that are not a GBT word are in the message instead of the word bytes
+        assert_eq!(
+            short_word_line,
+            "E4401,0x40,255,,,,error,\"short word [AB CD]\","
+        );
+    }
+
+    #[test]
+    fn test_decode_as_text_with_filters() {
+        let encoded = encoded_log();
+        let opts = DecodeErrors {
+            code: Some(30),
+            ..decode_opts()
+        };
+        assert_eq!(
+            decoded(&encoded, &opts),
+            "ERROR - 0x140: [E30] ID is not 0xE0: 0xE1 [FF 3F 00 00 00 00 00 00 00 E1] (link 3, fee 0x502A, orbit 0x0B7DD575)\n\
+             MUTED - 0x180: [E30] ID is not 0xE0: 0xE1 [FF 3F 00 00 00 00 00 00 00 E1] (link 3, fee 0x502A)\n"
+        );
+        let opts = DecodeErrors {
+            link: Some(0),
+            ..decode_opts()
+        };
+        assert_eq!(
+            decoded(&encoded, &opts),
+            "WARN - 0xE450FFD: [E10] RDH sanity check failed: data_format = 255 (link 0)\n"
+        );
+        // Errors without a memory position are left out of a range
+        let opts = DecodeErrors {
+            from: Some(0x40),
+            to: Some(0x140),
+            ..decode_opts()
+        };
+        let lines: Vec<String> = decoded(&encoded, &opts).lines().map(String::from).collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("ERROR - 0x140: "), "{}", lines[0]);
+        assert!(lines[1].starts_with("ERROR - 0x40: [E4401] short word [AB CD]"));
+    }
+
+    #[test]
+    fn test_truncated_and_invalid_logs() {
+        let encoded = encoded_log();
+        // A log of an interrupted run, cut in the middle of the last record
+        let mut reader = ErrorLogReader::new(&encoded[..encoded.len() - 10]).unwrap();
+        assert_eq!(reader.by_ref().take_while(Result::is_ok).count(), 4);
+        let error = ErrorLogReader::new(&encoded[..encoded.len() - 10])
+            .unwrap()
+            .find_map(Result::err)
+            .unwrap();
+        assert!(error.starts_with("Truncated entry at byte "), "{error}");
+
+        assert_eq!(
+            ErrorLogReader::new(&b"code,memory_pos"[..]).err().unwrap(),
+            "Not a binary error log, it does not start with `FPEL`"
+        );
+        assert_eq!(
+            ErrorLogReader::new(&b"FPEL\x02"[..]).err().unwrap(),
+            "Unsupported binary error log format version 2, expected 1"
+        );
+        let mut unknown_message = b"FPEL\x01E".to_vec();
+        unknown_message.extend([0; RECORD_SIZE]);
+        assert_eq!(
+            ErrorLogReader::new(&unknown_message[..])
+                .unwrap()
+                .next()
+                .unwrap()
+                .err()
+                .unwrap(),
+            "Error at byte 0x5 refers to unknown message 0"
+        );
+    }
+
+    /// A writer to a buffer that is read after the [ErrorLogWriter] wrote to it.
+    struct SharedBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Contains the [FinalHbfs] that keeps track of the last HBF of each link, to detect HBFs left open at the end of the input.
+//!
+//! When a run is stopped abruptly, the input ends in the middle of an HBF, without a stop page.
+//! This is reported as a truncated final HBF instead of as corrupted data.
+use crate::words::lib::RDH;
+
+/// The last HBF seen on a link.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FinalHbf {
+    /// Orbit of the HBF.
+    pub orbit: u32,
+    /// Pages of the HBF read so far.
+    pub pages: u32,
+    /// True if the last page read was a stop page.
+    pub closed: bool,
+}
+
+/// The last HBF of each link, updated with every RDH in the order they appear in the input.
+#[derive(Debug, Default)]
+pub struct FinalHbfs {
+    links: Vec<(u8, FinalHbf)>,
+}
+
+impl FinalHbfs {
+    /// Records the page of an [RDH], a page following a stop page or with pages_counter 0 opens a new HBF.
+    pub fn record<T: RDH>(&mut self, rdh: &T) {
+        let link_id = rdh.link_id();
+        let hbf = match self.links.iter_mut().find(|(link, _)| *link == link_id) {
+            Some((_, hbf)) => hbf,
+            None => {
+                self.links.push((link_id, FinalHbf::default()));
+                &mut self.links.last_mut().unwrap().1
+            }
+        };
+        if hbf.closed || hbf.pages == 0 || rdh.pages_counter() == 0 {
+            *hbf = FinalHbf {
+                orbit: rdh.rdh1().orbit,
+                pages: 0,
+                closed: false,
+            };
+        }
+        hbf.pages += 1;
+        hbf.closed = rdh.stop_bit() == 1;
+    }
+
+    /// Returns the links where the last HBF has no stop page, ordered by link ID.
+    pub fn unclosed(&self) -> Vec<(u8, FinalHbf)> {
+        let mut unclosed: Vec<(u8, FinalHbf)> = self
+            .links
+            .iter()
+            .filter(|(_, hbf)| !hbf.closed)
+            .copied()
+            .collect();
+        unclosed.sort_by_key(|(link, _)| *link);
+        unclosed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh_cru::test_data::CORRECT_RDH_CRU_V7;
+
+    #[test]
+    fn test_final_hbf_not_closed() {
+        let mut final_hbfs = FinalHbfs::default();
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        // A closed HBF of 2 pages on link 0
+        rdh.rdh2.pages_counter = 0;
+        final_hbfs.record(&rdh);
+        rdh.rdh2.pages_counter = 1;
+        rdh.rdh2.stop_bit = 1;
+        final_hbfs.record(&rdh);
+        assert!(final_hbfs.unclosed().is_empty());
+
+        // The next HBF on link 0 is cut off after 2 pages, link 3 only has a closed HBF
+        rdh.rdh1.orbit += 1;
+        for pages_counter in 0..2 {
+            rdh.rdh2.pages_counter = pages_counter;
+            rdh.rdh2.stop_bit = 0;
+            final_hbfs.record(&rdh);
+        }
+        let mut other_link_rdh = CORRECT_RDH_CRU_V7;
+        other_link_rdh.link_id = 3;
+        other_link_rdh.rdh2.stop_bit = 1;
+        final_hbfs.record(&other_link_rdh);
+
+        let orbit = rdh.rdh1().orbit;
+        assert_eq!(
+            final_hbfs.unclosed(),
+            vec![(
+                0,
+                FinalHbf {
+                    orbit,
+                    pages: 2,
+                    closed: false
+                }
+            )]
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Contains the [FirstErrors] struct that remembers the first occurrence of each error code.
+//!
+//! With many errors reported, the first occurrence of each class of errors is usually the most useful information, and is shown at the top of the report.
+use super::reorder_buffer::mem_pos_from_error_msg;
+use std::collections::HashMap;
+
+/// Remembers the error message at the lowest memory position for each error code.
+#[derive(Default)]
+pub struct FirstErrors {
+    first_by_code: HashMap<String, (u64, String)>,
+}
+
+impl FirstErrors {
+    /// Record an error message formatted as `0x<hex>: [E<code>] <message>`.
+    ///
+    /// Messages without an error code or a memory position are ignored.
+    pub fn record(&mut self, msg: &str) {
+        let (code, mem_pos) = match (error_code_from_error_msg(msg), mem_pos_from_error_msg(msg)) {
+            (Some(code), Some(mem_pos)) => (code, mem_pos),
+            _ => return,
+        };
+        match self.first_by_code.get(code) {
+            Some((first_mem_pos, _)) if *first_mem_pos <= mem_pos => (),
+            _ => {
+                // Only keep the first line, RDH errors are followed by a printout of the RDHs
+                let first_line = msg.lines().next().unwrap_or_default().to_string();
+                self.first_by_code
+                    .insert(code.to_string(), (mem_pos, first_line));
+            }
+        }
+    }
+
+    /// Returns true if no errors with an error code have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.first_by_code.is_empty()
+    }
+
+    /// Returns the error code and message of the first occurrence of each error code, ordered by memory position.
+    pub fn sorted_by_mem_pos(&self) -> Vec<(String, String)> {
+        let mut first_errors: Vec<(&String, &(u64, String))> = self.first_by_code.iter().collect();
+        first_errors.sort_by_key(|(code, (mem_pos, _))| (*mem_pos, *code));
+        first_errors
+            .into_iter()
+            .map(|(code, (_, msg))| (code.clone(), msg.clone()))
+            .collect()
+    }
+}
+
+/// Extracts the error code from an error message formatted as `0x<hex>: [E<code>] <message>`.
+pub(crate) fn error_code_from_error_msg(msg: &str) -> Option<&str> {
+    let (_, after_mem_pos) = msg.split_once(": [")?;
+    let (code, _) = after_mem_pos.split_once(']')?;
+    let digits = code.strip_prefix('E')?;
+    if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        Some(code)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_code_from_error_msg() {
+        assert_eq!(
+            error_code_from_error_msg("0x40: [E30] ID is not 0xE0: 0xE1"),
+            Some("E30")
+        );
+        assert_eq!(
+            error_code_from_error_msg("0x40: RDH sanity check failed: data_format = 0xff"),
+            None
+        );
+        assert_eq!(error_code_from_error_msg("0x40: [Eab] oops"), None);
+    }
+
+    #[test]
+    fn test_first_errors_interleaved_codes() {
+        let mut first_errors = FirstErrors::default();
+        first_errors.record("0x1A0: [E40] second TDH error");
+        first_errors.record("0x54: [E70] first data word error");
+        first_errors.record("0x4A: [E40] first TDH error");
+        first_errors.record("0x5E: [E70] second data word error");
+        first_errors.record("0x200: [E30] only IHW error");
+        first_errors.record("0x10: RDH sanity check failed: no error code");
+
+        assert_eq!(
+            first_errors.sorted_by_mem_pos(),
+            vec![
+                ("E40".to_string(), "0x4A: [E40] first TDH error".to_string()),
+                (
+                    "E70".to_string(),
+                    "0x54: [E70] first data word error".to_string()
+                ),
+                ("E30".to_string(), "0x200: [E30] only IHW error".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_first_errors_keeps_first_line() {
+        let mut first_errors = FirstErrors::default();
+        first_errors.record("0x0: [E13] SOC/SOT trigger bit set\n  previous: ...\n  current: ...");
+        assert_eq!(
+            first_errors.sorted_by_mem_pos()[0].1,
+            "0x0: [E13] SOC/SOT trigger bit set"
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! Contains the [InterleavingTracker] that measures how far apart consecutive pages of the same HBF are in the input stream.
+//!
+//! The CRU interleaves the pages of its links, a consumer reordering the stream per link needs a buffer at least as large as the largest gap between two pages of the same HBF.
+//! The gap is measured both in CDPs of other links in between, and in bytes in between.
+use crate::words::lib::RDH;
+use tabled::Tabled;
+
+/// Largest gaps between consecutive pages of the same HBF of a link, and where they occurred.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LinkInterleaving {
+    /// Link ID.
+    pub link_id: u8,
+    /// Most CDPs in between two consecutive pages of the same HBF.
+    pub max_gap_cdps: u64,
+    /// Memory position of the page after the gap with the most CDPs.
+    pub max_gap_cdps_mem_pos: u64,
+    /// Most bytes in between two consecutive pages of the same HBF.
+    pub max_gap_bytes: u64,
+    /// Memory position of the page after the gap with the most bytes.
+    pub max_gap_bytes_mem_pos: u64,
+}
+
+/// A row of the interleaving table of the report.
+#[derive(Tabled)]
+pub struct InterleavingRow {
+    #[tabled(rename = "Link")]
+    link_id: u8,
+    #[tabled(rename = "Max gap (CDPs)")]
+    max_gap_cdps: u64,
+    #[tabled(rename = "At")]
+    max_gap_cdps_mem_pos: String,
+    #[tabled(rename = "Max gap (bytes)")]
+    max_gap_bytes: u64,
+    #[tabled(rename = "At")]
+    max_gap_bytes_mem_pos: String,
+}
+
+impl From<&LinkInterleaving> for InterleavingRow {
+    fn from(link: &LinkInterleaving) -> Self {
+        Self {
+            link_id: link.link_id,
+            max_gap_cdps: link.max_gap_cdps,
+            max_gap_cdps_mem_pos: format_gap_mem_pos(link.max_gap_cdps, link.max_gap_cdps_mem_pos),
+            max_gap_bytes: link.max_gap_bytes,
+            max_gap_bytes_mem_pos: format_gap_mem_pos(
+                link.max_gap_bytes,
+                link.max_gap_bytes_mem_pos,
+            ),
+        }
+    }
+}
+
+/// Formats where a gap occurred, `-` if there was no gap.
+fn format_gap_mem_pos(gap: u64, mem_pos: u64) -> String {
+    if gap == 0 {
+        "-".to_string()
+    } else {
+        format!("{mem_pos:#X}")
+    }
+}
+
+/// The last page seen of a link.
+#[derive(Debug, Clone, Copy)]
+struct LastPage {
+    link_id: u8,
+    /// Index of the CDP in the input stream.
+    stream_index: u64,
+    /// Memory position just after the CDP.
+    end_mem_pos: u64,
+    /// The page closed its HBF, so the next page of the link starts a new HBF.
+    stop_bit: bool,
+}
+
+/// Tracks the largest gap between consecutive pages of the same HBF, per link.
+///
+/// Only the last page seen of each link is kept as state.
+#[derive(Debug, Default)]
+pub struct InterleavingTracker {
+    stream_index: u64,
+    last_pages: Vec<LastPage>,
+    links: Vec<LinkInterleaving>,
+    /// Set when a link was measured for the first time or a maximum increased since the last call to [InterleavingTracker::take_update].
+    updated: bool,
+}
+
+impl InterleavingTracker {
+    /// Records the page of an [RDH] at the memory position `mem_pos`, every RDH of the input has to be recorded in stream order.
+    pub fn record<T: RDH>(&mut self, rdh: &T, mem_pos: u64) {
+        let link_id = rdh.link_id();
+        let page = LastPage {
+            link_id,
+            stream_index: self.stream_index,
+            end_mem_pos: mem_pos + rdh.offset_to_next() as u64,
+            stop_bit: rdh.stop_bit() == 1,
+        };
+        self.stream_index += 1;
+        let previous = match self.last_pages.iter_mut().find(|p| p.link_id == link_id) {
+            Some(last_page) => std::mem::replace(last_page, page),
+            None => {
+                self.last_pages.push(page);
+                return;
+            }
+        };
+        if previous.stop_bit {
+            // The page opens a new HBF, the gap between HBFs is not relevant
+            return;
+        }
+        let gap_cdps = page.stream_index - previous.stream_index - 1;
+        let gap_bytes = mem_pos.saturating_sub(previous.end_mem_pos);
+        let link = self.link_mut(link_id);
+        if gap_cdps > link.max_gap_cdps {
+            link.max_gap_cdps = gap_cdps;
+            link.max_gap_cdps_mem_pos = mem_pos;
+            self.updated = true;
+        }
+        let link = self.link_mut(link_id);
+        if gap_bytes > link.max_gap_bytes {
+            link.max_gap_bytes = gap_bytes;
+            link.max_gap_bytes_mem_pos = mem_pos;
+            self.updated = true;
+        }
+    }
+
+    fn link_mut(&mut self, link_id: u8) -> &mut LinkInterleaving {
+        match self.links.iter().position(|link| link.link_id == link_id) {
+            Some(idx) => &mut self.links[idx],
+            None => {
+                self.updated = true;
+                self.links.push(LinkInterleaving {
+                    link_id,
+                    ..Default::default()
+                });
+                self.links.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// Returns the maxima of all links measured so far, ordered by link ID, if any of them changed since the last call.
+    pub fn take_update(&mut self) -> Option<Vec<LinkInterleaving>> {
+        if !std::mem::take(&mut self.updated) {
+            return None;
+        }
+        let mut links = self.links.clone();
+        links.sort_by_key(|link| link.link_id);
+        Some(links)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh_cru::{test_data::CORRECT_RDH_CRU_V7, RdhCRU, V7};
+
+    const CDP_SIZE: u64 = 0x50;
+
+    fn page(link_id: u8, stop_bit: u8) -> RdhCRU<V7> {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        rdh.link_id = link_id;
+        rdh.rdh2.stop_bit = stop_bit;
+        rdh.offset_new_packet = CDP_SIZE as u16;
+        rdh
+    }
+
+    fn record_stream(tracker: &mut InterleavingTracker, stream: &[RdhCRU<V7>]) {
+        for (idx, rdh) in stream.iter().enumerate() {
+            tracker.record(rdh, idx as u64 * CDP_SIZE);
+        }
+    }
+
+    #[test]
+    fn test_known_gap() {
+        let mut tracker = InterleavingTracker::default();
+        // Link 0 HBF of 3 pages, with 1 and then 3 pages of other links in between
+        let stream = [
+            page(0, 0),
+            page(1, 0),
+            page(0, 0),
+            page(1, 1),
+            page(2, 0),
+            page(2, 1),
+            page(0, 1),
+        ];
+        record_stream(&mut tracker, &stream);
+        let links = tracker.take_update().unwrap();
+        assert_eq!(links.len(), 3);
+        assert_eq!(
+            links[0],
+            LinkInterleaving {
+                link_id: 0,
+                max_gap_cdps: 3,
+                max_gap_cdps_mem_pos: 6 * CDP_SIZE,
+                max_gap_bytes: 3 * CDP_SIZE,
+                max_gap_bytes_mem_pos: 6 * CDP_SIZE,
+            }
+        );
+        // Link 1 has 1 CDP in between its pages, link 2 none
+        assert_eq!(links[1].link_id, 1);
+        assert_eq!(links[1].max_gap_cdps, 1);
+        assert_eq!(links[2].link_id, 2);
+        assert_eq!(links[2].max_gap_cdps, 0);
+        assert!(tracker.take_update().is_none());
+    }
+
+    #[test]
+    fn test_gap_between_hbfs_ignored() {
+        let mut tracker = InterleavingTracker::default();
+        // Link 0 closes an HBF, then opens the next after 3 pages of link 1
+        let stream = [page(0, 1), page(1, 0), page(1, 0), page(1, 1), page(0, 1)];
+        record_stream(&mut tracker, &stream);
+        let links = tracker.take_update().unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].link_id, 1);
+        assert_eq!(links[0].max_gap_cdps, 0);
+    }
+}
@@ -1,13 +1,18 @@
 //! Contains the [init_stats_controller] function, which spawns a thread with the [StatsController] running, and returns the thread handle, the channel to send stats to, and the stop flag.
 use super::stats_controller::StatsController;
+pub(crate) use super::{
+    first_errors::error_code_from_error_msg, reorder_buffer::mem_pos_from_error_msg,
+};
 use crate::util::lib::Config;
 use std::sync::atomic::AtomicBool;
 
 /// Spawns a thread with the StatsController running, and returns the thread handle, the channel to send stats to, and the stop flag.
+///
+/// The thread returns true if processing was stopped at the first error with `--fail-fast`.
 pub fn init_stats_controller(
     config: &impl Config,
 ) -> (
-    std::thread::JoinHandle<()>,
+    std::thread::JoinHandle<bool>,
     std::sync::mpsc::Sender<super::stats_controller::StatType>,
     std::sync::Arc<AtomicBool>,
 ) {
@@ -21,6 +26,7 @@ pub fn init_stats_controller(
         .name("stats_thread".to_string())
         .spawn(move || {
             stats.run();
+            stats.failed_fast()
         })
         .expect("Failed to spawn stats thread");
     (stats_thread, send_stats_channel, thread_stop_flag)
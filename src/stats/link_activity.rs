@@ -0,0 +1,138 @@
+//! Contains the [LinkActivities] that keeps track of the last page of each link, to detect links that went silent before the end of the data.
+//!
+//! A link can send pages at the start of the run and then stop, while the other links continue.
+//! As the link is observed, it is not reported as missing, instead its last orbit is compared to the last orbit of the data with [silent_links].
+use crate::words::lib::RDH;
+
+/// The last page seen on a link.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LinkActivity {
+    /// Link ID.
+    pub link_id: u8,
+    /// Orbit of the last page.
+    pub last_orbit: u32,
+    /// Memory position of the last page.
+    pub last_mem_pos: u64,
+}
+
+/// A link whose last page is more orbits before the end of the data than allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SilentLink {
+    /// The last page of the link.
+    pub activity: LinkActivity,
+    /// Orbits between the last page of the link and the last orbit of the data.
+    pub orbits_before_end: u32,
+}
+
+impl std::fmt::Display for SilentLink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:#X}: link {} went silent at orbit {:#X} ({} orbits before end of data)",
+            self.activity.last_mem_pos,
+            self.activity.link_id,
+            self.activity.last_orbit,
+            self.orbits_before_end
+        )
+    }
+}
+
+/// The last page of each link, updated with every RDH in the order they appear in the input.
+#[derive(Debug, Default)]
+pub struct LinkActivities {
+    links: Vec<LinkActivity>,
+}
+
+impl LinkActivities {
+    /// Records the page of an [RDH] at the memory position `mem_pos`.
+    pub fn record<T: RDH>(&mut self, rdh: &T, mem_pos: u64) {
+        let activity = LinkActivity {
+            link_id: rdh.link_id(),
+            last_orbit: rdh.rdh1().orbit,
+            last_mem_pos: mem_pos,
+        };
+        match self
+            .links
+            .iter_mut()
+            .find(|link| link.link_id == activity.link_id)
+        {
+            Some(link) => *link = activity,
+            None => self.links.push(activity),
+        }
+    }
+
+    /// Returns the last page of each link, ordered by link ID.
+    pub fn into_links(mut self) -> Vec<LinkActivity> {
+        self.links.sort_by_key(|link| link.link_id);
+        self.links
+    }
+}
+
+/// Returns the links whose last page is more than `max_silent_orbits` orbits before the last orbit of the data, which is the latest last orbit of all links.
+pub fn silent_links(links: &[LinkActivity], max_silent_orbits: u32) -> Vec<SilentLink> {
+    let end_orbit = match links.iter().map(|link| link.last_orbit).max() {
+        Some(end_orbit) => end_orbit,
+        None => return Vec::new(),
+    };
+    links
+        .iter()
+        .map(|link| SilentLink {
+            activity: *link,
+            orbits_before_end: end_orbit - link.last_orbit,
+        })
+        .filter(|silent_link| silent_link.orbits_before_end > max_silent_orbits)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh_cru::test_data::CORRECT_RDH_CRU_V7;
+
+    #[test]
+    fn test_link_stops_halfway() {
+        let mut activities = LinkActivities::default();
+        let start_orbit = CORRECT_RDH_CRU_V7.rdh1().orbit;
+        let mut mem_pos = 0;
+        // Link 0 sends a page every orbit for 1000 orbits, link 3 stops after 500 orbits
+        for orbit_offset in 0..1000 {
+            for link_id in [0, 3] {
+                if link_id == 3 && orbit_offset >= 500 {
+                    continue;
+                }
+                let mut rdh = CORRECT_RDH_CRU_V7;
+                rdh.link_id = link_id;
+                rdh.rdh1.orbit = start_orbit + orbit_offset;
+                activities.record(&rdh, mem_pos);
+                mem_pos += 0x50;
+            }
+        }
+        let links = activities.into_links();
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].last_orbit, start_orbit + 999);
+
+        let silent = silent_links(&links, 128);
+        assert_eq!(
+            silent,
+            vec![SilentLink {
+                activity: LinkActivity {
+                    link_id: 3,
+                    last_orbit: start_orbit + 499,
+                    last_mem_pos: 999 * 0x50,
+                },
+                orbits_before_end: 500,
+            }]
+        );
+        assert_eq!(
+            silent[0].to_string(),
+            "0x13830: link 3 went silent at orbit 0xB7DD768 (500 orbits before end of data)"
+        );
+        // Allowed to be silent for the rest of the data
+        assert!(silent_links(&links, 500).is_empty());
+    }
+
+    #[test]
+    fn test_no_links() {
+        assert!(silent_links(&[], 0).is_empty());
+    }
+}
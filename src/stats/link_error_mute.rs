@@ -0,0 +1,110 @@
+//! Contains the [LinkErrorMute] that counts errors per link, and decides when a link is auto-muted.
+//!
+//! When one link is hopelessly corrupted, its errors drown the errors of all other links.
+//! After a set number of errors from a link, its errors are only counted, while other links keep full reporting.
+use std::collections::HashMap;
+
+/// What to do with an error attributed to a link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkErrorAction {
+    /// Report the error.
+    Report,
+    /// The link just reached the limit, report that it is muted instead of the error.
+    Mute,
+    /// The link is already muted, only count the error.
+    Muted,
+}
+
+/// Counts the errors of each link, and mutes a link after a number of errors.
+pub struct LinkErrorMute {
+    mute_after: u32,
+    error_counts: HashMap<u8, u32>,
+}
+
+impl LinkErrorMute {
+    /// Creates a new [LinkErrorMute] that mutes a link after `mute_after` errors have been reported from it.
+    pub fn new(mute_after: u32) -> Self {
+        Self {
+            mute_after,
+            error_counts: HashMap::new(),
+        }
+    }
+
+    /// Number of errors reported from a link before it is muted.
+    pub fn mute_after(&self) -> u32 {
+        self.mute_after
+    }
+
+    /// Counts an error from a link, and returns what to do with it.
+    pub fn record(&mut self, link_id: u8) -> LinkErrorAction {
+        let count = self.error_counts.entry(link_id).or_insert(0);
+        *count += 1;
+        match (*count).cmp(&(self.mute_after + 1)) {
+            std::cmp::Ordering::Less => LinkErrorAction::Report,
+            std::cmp::Ordering::Equal => LinkErrorAction::Mute,
+            std::cmp::Ordering::Greater => LinkErrorAction::Muted,
+        }
+    }
+
+    /// Returns the muted links and the number of errors that were not reported for each, ordered by link ID.
+    pub fn muted_links(&self) -> Vec<(u8, u32)> {
+        let mut muted: Vec<(u8, u32)> = self
+            .error_counts
+            .iter()
+            .filter(|(_, count)| **count > self.mute_after)
+            .map(|(link_id, count)| (*link_id, count - self.mute_after))
+            .collect();
+        muted.sort();
+        muted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flooding_link_muted_other_link_reported() {
+        let mut mute = LinkErrorMute::new(3);
+        let mut actions = Vec::new();
+        // Link 3 floods, link 1 has an error now and then
+        for i in 0..10 {
+            actions.push((3, mute.record(3)));
+            if i % 4 == 0 {
+                actions.push((1, mute.record(1)));
+            }
+        }
+        let link_3_actions: Vec<LinkErrorAction> = actions
+            .iter()
+            .filter(|(link_id, _)| *link_id == 3)
+            .map(|(_, action)| *action)
+            .collect();
+        assert_eq!(
+            link_3_actions[..5],
+            [
+                LinkErrorAction::Report,
+                LinkErrorAction::Report,
+                LinkErrorAction::Report,
+                LinkErrorAction::Mute,
+                LinkErrorAction::Muted
+            ]
+        );
+        // Errors from link 1 after link 3 is muted are still reported
+        assert!(actions
+            .iter()
+            .filter(|(link_id, _)| *link_id == 1)
+            .all(|(_, action)| *action == LinkErrorAction::Report));
+        let mute_idx = actions
+            .iter()
+            .position(|action| *action == (3, LinkErrorAction::Mute))
+            .unwrap();
+        assert_eq!(
+            actions[mute_idx..]
+                .iter()
+                .filter(|action| **action == (1, LinkErrorAction::Report))
+                .count(),
+            2
+        );
+        assert_eq!(mute.muted_links(), vec![(3, 7)]);
+    }
+}
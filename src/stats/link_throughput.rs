@@ -0,0 +1,305 @@
+//! Contains the [ThroughputTracker] that counts the bytes and the orbits with data of each link, from which the throughput and dead time of each link are derived at report time.
+//!
+//! The throughput is the average number of bytes a link sends per orbit over the orbits spanned by the data, and per second with the LHC orbit frequency.
+//! The dead time is estimated as the fraction of the orbits spanned by the data in which the link sent no payload.
+//! With a window of N orbits, the orbits are also split into consecutive windows of N orbits from the first orbit of the data, and each window is reported on its own.
+pub use crate::words::constants::LHC_ORBIT_FREQUENCY_HZ;
+use crate::words::lib::RDH;
+use tabled::Tabled;
+
+/// Bytes and orbits with data of a link, in the whole data or in a window of orbits.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LinkThroughputCounters {
+    /// Link ID.
+    pub link_id: u8,
+    /// Index of the window of orbits, [None] for the whole data.
+    pub window: Option<u32>,
+    /// Bytes of the CDPs of the link, the sum of their offsets to the next RDH.
+    pub bytes: u64,
+    /// Orbits in which the link sent a page with a payload.
+    pub orbits_with_data: u32,
+    /// The last orbit counted in `orbits_with_data`.
+    last_data_orbit: Option<u32>,
+}
+
+/// The counters of all links, and the orbits spanned by the data, sent to the [StatsController][super::stats_controller::StatsController] when the whole input was processed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ThroughputCounters {
+    /// First orbit of the data.
+    pub first_orbit: u32,
+    /// Last orbit of the data.
+    pub last_orbit: u32,
+    /// Orbits per window, [None] if the data is not split into windows.
+    pub window_orbits: Option<u32>,
+    /// The counters of each link, for the whole data followed by each window if the data is split into windows.
+    pub links: Vec<LinkThroughputCounters>,
+}
+
+/// Counts the bytes and orbits with data of each link, updated with every RDH in the order they appear in the input.
+#[derive(Debug, Default)]
+pub struct ThroughputTracker {
+    window_orbits: Option<u32>,
+    /// First and last orbit of the data, [None] before the first RDH.
+    orbits: Option<(u32, u32)>,
+    links: Vec<LinkThroughputCounters>,
+}
+
+impl ThroughputTracker {
+    /// Creates a [ThroughputTracker], that also counts each window of `window_orbits` orbits if it is set.
+    pub fn new(window_orbits: Option<u32>) -> Self {
+        Self {
+            window_orbits,
+            ..Default::default()
+        }
+    }
+
+    /// Records the page of an [RDH].
+    pub fn record<T: RDH>(&mut self, rdh: &T) {
+        let orbit = rdh.rdh1().orbit;
+        let (first_orbit, last_orbit) = self.orbits.get_or_insert((orbit, orbit));
+        *last_orbit = (*last_orbit).max(orbit);
+        let first_orbit = *first_orbit;
+        count_page(&mut self.links, rdh, None);
+        if let Some(window_orbits) = self.window_orbits {
+            let window = orbit.saturating_sub(first_orbit) / window_orbits;
+            count_page(&mut self.links, rdh, Some(window));
+        }
+    }
+
+    /// Returns the counters of all links, ordered by link ID and window.
+    pub fn finish(mut self) -> ThroughputCounters {
+        let (first_orbit, last_orbit) = self.orbits.unwrap_or_default();
+        self.links.sort_by_key(|link| (link.link_id, link.window));
+        ThroughputCounters {
+            first_orbit,
+            last_orbit,
+            window_orbits: self.window_orbits,
+            links: self.links,
+        }
+    }
+}
+
+/// Adds the page of an [RDH] to the counters of its link in the `window`.
+fn count_page<T: RDH>(counters: &mut Vec<LinkThroughputCounters>, rdh: &T, window: Option<u32>) {
+    let link_id = rdh.link_id();
+    let idx = match counters
+        .iter()
+        .position(|link| link.link_id == link_id && link.window == window)
+    {
+        Some(idx) => idx,
+        None => {
+            counters.push(LinkThroughputCounters {
+                link_id,
+                window,
+                ..Default::default()
+            });
+            counters.len() - 1
+        }
+    };
+    let link = &mut counters[idx];
+    link.bytes += rdh.offset_to_next() as u64;
+    let orbit = rdh.rdh1().orbit;
+    if rdh.payload_size() > 0 && link.last_data_orbit != Some(orbit) {
+        link.orbits_with_data += 1;
+        link.last_data_orbit = Some(orbit);
+    }
+}
+
+/// The throughput and dead time of a link, in the whole data or in a window of orbits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkThroughput {
+    /// Link ID.
+    pub link_id: u8,
+    /// First orbit of the data or the window.
+    pub first_orbit: u32,
+    /// Last orbit of the data or the window.
+    pub last_orbit: u32,
+    /// Bytes of the CDPs of the link.
+    pub bytes: u64,
+    /// Average bytes per orbit.
+    pub bytes_per_orbit: f64,
+    /// Average bytes per second.
+    pub bytes_per_second: f64,
+    /// Fraction of the orbits without any payload from the link.
+    pub dead_time: f64,
+}
+
+impl LinkThroughput {
+    /// The throughput as a JSON object.
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"link":{},"first_orbit":{},"last_orbit":{},"bytes":{},"bytes_per_orbit":{:.3},"bytes_per_second":{:.3},"dead_time":{:.6}}}"#,
+            self.link_id,
+            self.first_orbit,
+            self.last_orbit,
+            self.bytes,
+            self.bytes_per_orbit,
+            self.bytes_per_second,
+            self.dead_time
+        )
+    }
+}
+
+impl ThroughputCounters {
+    /// Derives the throughput and dead time of each link from the counters, in the same order as the counters.
+    ///
+    /// The orbits of the whole data span from the first to the last orbit of all links, a window spans its orbits up to the last orbit of the data.
+    pub fn throughputs(&self) -> Vec<LinkThroughput> {
+        self.links
+            .iter()
+            .map(|link| {
+                let (first_orbit, last_orbit) = self.orbit_span(link);
+                let orbits = (last_orbit - first_orbit) as f64 + 1.0;
+                let bytes_per_orbit = link.bytes as f64 / orbits;
+                LinkThroughput {
+                    link_id: link.link_id,
+                    first_orbit,
+                    last_orbit,
+                    bytes: link.bytes,
+                    bytes_per_orbit,
+                    bytes_per_second: bytes_per_orbit * LHC_ORBIT_FREQUENCY_HZ,
+                    dead_time: 1.0 - (link.orbits_with_data as f64 / orbits).min(1.0),
+                }
+            })
+            .collect()
+    }
+
+    /// First and last orbit spanned by the counters of a link, the whole data or its window.
+    fn orbit_span(&self, link: &LinkThroughputCounters) -> (u32, u32) {
+        match (link.window, self.window_orbits) {
+            (Some(window), Some(window_orbits)) => {
+                let first_orbit = self.first_orbit + window * window_orbits;
+                let last_orbit = (first_orbit + (window_orbits - 1)).min(self.last_orbit);
+                (first_orbit, last_orbit)
+            }
+            _ => (self.first_orbit, self.last_orbit),
+        }
+    }
+}
+
+/// A row of the throughput table of the report.
+#[derive(Tabled)]
+pub struct ThroughputRow {
+    #[tabled(rename = "Link")]
+    link_id: u8,
+    #[tabled(rename = "Orbits")]
+    orbits: String,
+    #[tabled(rename = "Bytes")]
+    bytes: String,
+    #[tabled(rename = "Bytes/orbit")]
+    bytes_per_orbit: String,
+    #[tabled(rename = "Throughput")]
+    throughput: String,
+    #[tabled(rename = "Dead time")]
+    dead_time: String,
+}
+
+impl From<&LinkThroughput> for ThroughputRow {
+    fn from(link: &LinkThroughput) -> Self {
+        Self {
+            link_id: link.link_id,
+            orbits: format!("{:#X}-{:#X}", link.first_orbit, link.last_orbit),
+            bytes: super::report::format_byte_size(&link.bytes),
+            bytes_per_orbit: format!("{:.1}", link.bytes_per_orbit),
+            throughput: format!(
+                "{}/s",
+                super::report::format_byte_size(&(link.bytes_per_second.round() as u64))
+            ),
+            dead_time: format!("{:.2} %", link.dead_time * 100.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh_cru::{test_data::CORRECT_RDH_CRU_V7, RdhCRU, V7};
+
+    fn page(link_id: u8, orbit_offset: u32, payload_size: u16) -> RdhCRU<V7> {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        rdh.link_id = link_id;
+        rdh.rdh1.orbit += orbit_offset;
+        rdh.memory_size = 64 + payload_size;
+        rdh.offset_new_packet = 64 + payload_size;
+        rdh
+    }
+
+    /// Link 0 sends 2 pages of 0x200 bytes every orbit for 10 orbits,
+    /// link 1 sends a page of 0x100 bytes in the first 5 orbits, and a page without payload in the last 5.
+    fn two_links_tracker(window_orbits: Option<u32>) -> ThroughputTracker {
+        let mut tracker = ThroughputTracker::new(window_orbits);
+        for orbit_offset in 0..10 {
+            tracker.record(&page(0, orbit_offset, 0x200 - 64));
+            tracker.record(&page(0, orbit_offset, 0x200 - 64));
+            let payload_size = if orbit_offset < 5 { 0x100 - 64 } else { 0 };
+            tracker.record(&page(1, orbit_offset, payload_size));
+        }
+        tracker
+    }
+
+    #[test]
+    fn test_two_links_whole_data() {
+        let counters = two_links_tracker(None).finish();
+        let first_orbit = CORRECT_RDH_CRU_V7.rdh1().orbit;
+        assert_eq!(counters.first_orbit, first_orbit);
+        assert_eq!(counters.last_orbit, first_orbit + 9);
+        let throughputs = counters.throughputs();
+        assert_eq!(throughputs.len(), 2);
+
+        let link_0 = throughputs[0];
+        assert_eq!(link_0.link_id, 0);
+        assert_eq!(link_0.bytes, 20 * 0x200);
+        assert_eq!(link_0.bytes_per_orbit, 1024.0);
+        assert_eq!(link_0.bytes_per_second, 1024.0 * LHC_ORBIT_FREQUENCY_HZ);
+        assert_eq!(link_0.dead_time, 0.0);
+
+        let link_1 = throughputs[1];
+        assert_eq!(link_1.link_id, 1);
+        assert_eq!(link_1.bytes, 5 * 0x100 + 5 * 64);
+        assert_eq!(link_1.bytes_per_orbit, 160.0);
+        assert_eq!(link_1.dead_time, 0.5);
+        assert_eq!(
+            link_1.to_json(),
+            format!(
+                r#"{{"link":1,"first_orbit":{first_orbit},"last_orbit":{},"bytes":1600,"bytes_per_orbit":160.000,"bytes_per_second":1799280.000,"dead_time":0.500000}}"#,
+                first_orbit + 9
+            )
+        );
+    }
+
+    #[test]
+    fn test_two_links_windows() {
+        // Windows of 4 orbits, the last window only spans 2 orbits
+        let throughputs = two_links_tracker(Some(4)).finish().throughputs();
+        let first_orbit = CORRECT_RDH_CRU_V7.rdh1().orbit;
+        assert_eq!(throughputs.len(), 2 + 2 * 3);
+        // The whole data of each link comes before its windows
+        assert_eq!(throughputs[0].link_id, 0);
+        assert_eq!(throughputs[0].last_orbit, first_orbit + 9);
+        assert_eq!(throughputs[4].link_id, 1);
+        assert_eq!(throughputs[4].dead_time, 0.5);
+
+        let link_1_windows: Vec<(u32, u32, u64, f64)> = throughputs[5..]
+            .iter()
+            .map(|link| {
+                assert_eq!(link.link_id, 1);
+                (
+                    link.first_orbit - first_orbit,
+                    link.last_orbit - first_orbit,
+                    link.bytes,
+                    link.dead_time,
+                )
+            })
+            .collect();
+        assert_eq!(
+            link_1_windows,
+            vec![
+                (0, 3, 4 * 0x100, 0.0),
+                (4, 7, 0x100 + 3 * 64, 0.75),
+                (8, 9, 2 * 64, 1.0)
+            ]
+        );
+        // The last window of link 0 has the same bytes per orbit as the others, despite being shorter
+        assert_eq!(throughputs[3].bytes_per_orbit, 1024.0);
+    }
+}
@@ -0,0 +1,157 @@
+//! Contains the [MemoryUsage] that tracks the peak memory usage of the pipeline with `--track-memory`, and the [InFlightBytes] of the CDP chunks passed between the threads.
+//!
+//! The resident set size (RSS) of the process is sampled by the [StatsController][super::stats_controller::StatsController] at most every [SAMPLE_INTERVAL] while it receives stats,
+//! and every [SAMPLE_INTERVAL] while it waits for them. It is read from `/proc/self/status` on Linux, and reported as unavailable elsewhere.
+//! The bytes in flight are the bytes of the CDP chunks sent by the reader, and not yet received by the analysis or the writer.
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Interval between two samples of the RSS.
+pub const SAMPLE_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Bytes of the CDP chunks in flight between the reader and its consumer, shared by the threads of the pipeline.
+#[derive(Debug, Default)]
+pub struct InFlightBytes {
+    current: AtomicU64,
+    peak: AtomicU64,
+}
+
+impl InFlightBytes {
+    /// Counts the bytes of a chunk about to be sent, must be called before it is sent, so the consumer never removes bytes that were not added.
+    pub fn add(&self, bytes: u64) {
+        let current = self.current.fetch_add(bytes, Ordering::SeqCst) + bytes;
+        self.peak.fetch_max(current, Ordering::SeqCst);
+    }
+
+    /// Removes the bytes of a chunk that was received, or could not be sent.
+    pub fn remove(&self, bytes: u64) {
+        self.current.fetch_sub(bytes, Ordering::SeqCst);
+    }
+
+    /// The most bytes in flight at once so far.
+    pub fn peak(&self) -> u64 {
+        self.peak.load(Ordering::SeqCst)
+    }
+}
+
+/// Returns the RSS of the process in bytes, or [None] if it is unavailable.
+pub fn sample_rss_bytes() -> Option<u64> {
+    if cfg!(target_os = "linux") {
+        std::fs::read_to_string("/proc/self/status")
+            .ok()
+            .and_then(|status| vm_rss_bytes(&status))
+    } else {
+        None
+    }
+}
+
+/// Parses the `VmRSS` line of `/proc/self/status`, e.g. `VmRSS:      5012 kB`.
+fn vm_rss_bytes(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib: u64 = line
+        .trim_start_matches("VmRSS:")
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+/// The peaks of the memory usage of the pipeline, reported in the summary and the JSON report.
+#[derive(Debug, Default)]
+pub struct MemoryUsage {
+    /// Largest RSS sampled, [None] if it is unavailable.
+    peak_rss: Option<u64>,
+    /// The most bytes of CDP chunks in flight at once.
+    peak_in_flight: u64,
+    last_sample: Option<std::time::Instant>,
+}
+
+impl MemoryUsage {
+    /// Samples the RSS if it was not sampled in the last [SAMPLE_INTERVAL].
+    pub fn sample_if_due(&mut self) {
+        match self.last_sample {
+            Some(last_sample) if last_sample.elapsed() < SAMPLE_INTERVAL => (),
+            _ => self.sample(),
+        }
+    }
+
+    /// Samples the RSS, and keeps it if it is the largest so far.
+    pub fn sample(&mut self) {
+        if let Some(rss) = sample_rss_bytes() {
+            self.peak_rss = Some(self.peak_rss.unwrap_or(0).max(rss));
+        }
+        self.last_sample = Some(std::time::Instant::now());
+    }
+
+    /// Records the peak of the [InFlightBytes], sent when the pipeline is done.
+    pub fn record_peak_in_flight(&mut self, peak_in_flight: u64) {
+        self.peak_in_flight = self.peak_in_flight.max(peak_in_flight);
+    }
+
+    /// Largest RSS sampled, [None] if it is unavailable.
+    pub fn peak_rss(&self) -> Option<u64> {
+        self.peak_rss
+    }
+
+    /// The most bytes of CDP chunks in flight at once.
+    pub fn peak_in_flight(&self) -> u64 {
+        self.peak_in_flight
+    }
+
+    /// Formats the peaks as the `memory` object of the JSON report, the RSS is `null` if it is unavailable.
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"peak_rss_bytes":{},"peak_in_flight_bytes":{}}}"#,
+            match self.peak_rss {
+                Some(rss) => rss.to_string(),
+                None => "null".to_string(),
+            },
+            self.peak_in_flight
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vm_rss_bytes() {
+        let status = "Name:\tfastpasta\nVmPeak:\t   20480 kB\nVmRSS:\t    5012 kB\nThreads:\t4\n";
+        assert_eq!(vm_rss_bytes(status), Some(5012 * 1024));
+        assert_eq!(vm_rss_bytes("Name:\tfastpasta\n"), None);
+        assert_eq!(vm_rss_bytes("VmRSS:\t    5012 MB\n"), None);
+    }
+
+    #[test]
+    fn test_in_flight_bytes_peak() {
+        let in_flight = InFlightBytes::default();
+        in_flight.add(100);
+        in_flight.add(50);
+        in_flight.remove(100);
+        in_flight.add(20);
+        assert_eq!(in_flight.peak(), 150);
+        in_flight.remove(70);
+        in_flight.add(120);
+        assert_eq!(in_flight.peak(), 150);
+        in_flight.add(31);
+        assert_eq!(in_flight.peak(), 151);
+    }
+
+    #[test]
+    fn test_memory_usage_json() {
+        let mut memory_usage = MemoryUsage::default();
+        memory_usage.record_peak_in_flight(1824);
+        assert_eq!(
+            memory_usage.to_json(),
+            r#"{"peak_rss_bytes":null,"peak_in_flight_bytes":1824}"#
+        );
+        memory_usage.sample();
+        if cfg!(target_os = "linux") {
+            assert!(memory_usage.peak_rss().unwrap() > 0);
+        } else {
+            assert_eq!(memory_usage.peak_rss(), None);
+        }
+    }
+}
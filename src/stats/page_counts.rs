@@ -0,0 +1,174 @@
+//! Contains the [PageCounts] struct, counting the pages of a link, and how many of them open and close an HBF.
+//!
+//! A healthy continuous-mode link has exactly one opening page (pages_counter is 0) and one stop page per HBF.
+//! Counts that drift apart indicate truncated or runaway HBFs.
+use crate::words::lib::RDH;
+use tabled::Tabled;
+
+/// Pages of a link, counted locally and sent to the [StatsController][super::stats_controller::StatsController] in a [StatType::LinkPages][super::stats_controller::StatType::LinkPages] message.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Tabled)]
+pub struct PageCounts {
+    /// Total pages.
+    #[tabled(rename = "Pages")]
+    pub pages: u32,
+    /// Pages opening an HBF (pages_counter is 0).
+    #[tabled(rename = "Opening pages")]
+    pub opening_pages: u32,
+    /// Pages closing an HBF (stop bit is 1).
+    #[tabled(rename = "Stop pages")]
+    pub stop_pages: u32,
+    /// Memory position of the first page, [None] if no page was counted.
+    #[tabled(skip)]
+    pub first_page_pos: Option<u64>,
+    /// Memory position just after the last page.
+    #[tabled(skip)]
+    pub end_pos: u64,
+}
+
+impl PageCounts {
+    /// Counts the page of an [RDH] at memory position `mem_pos`.
+    pub fn count<T: RDH>(&mut self, rdh: &T, mem_pos: u64) {
+        self.pages += 1;
+        self.first_page_pos = Some(self.first_page_pos.map_or(mem_pos, |pos| pos.min(mem_pos)));
+        self.end_pos = self.end_pos.max(mem_pos + u64::from(rdh.offset_to_next()));
+        if rdh.pages_counter() == 0 {
+            self.opening_pages += 1;
+        }
+        if rdh.stop_bit() == 1 {
+            self.stop_pages += 1;
+        }
+    }
+
+    /// Adds the counts of `other` to the counts of `self`.
+    pub fn merge(&mut self, other: &PageCounts) {
+        self.pages += other.pages;
+        self.opening_pages += other.opening_pages;
+        self.stop_pages += other.stop_pages;
+        self.first_page_pos = match (self.first_page_pos, other.first_page_pos) {
+            (Some(pos), Some(other_pos)) => Some(pos.min(other_pos)),
+            (pos, other_pos) => pos.or(other_pos),
+        };
+        self.end_pos = self.end_pos.max(other.end_pos);
+    }
+
+    /// The memory positions spanned by the pages, from the first page to the end of the last page.
+    pub fn span(&self) -> (u64, u64) {
+        (self.first_page_pos.unwrap_or(self.end_pos), self.end_pos)
+    }
+
+    /// Average number of pages per HBF, or [None] if no HBF was closed.
+    pub fn avg_pages_per_hbf(&self) -> Option<f64> {
+        if self.stop_pages == 0 {
+            None
+        } else {
+            Some(self.pages as f64 / self.stop_pages as f64)
+        }
+    }
+
+    /// Returns true if the opening and stop pages differ by more than 1.
+    ///
+    /// A difference of 1 is expected if the input ends in the middle of an HBF.
+    pub fn has_unterminated_hbfs(&self) -> bool {
+        self.opening_pages.abs_diff(self.stop_pages) > 1
+    }
+}
+
+/// Counts the pages of each link in a slice of [RDH]s and their memory positions, ordered by link ID.
+pub fn count_pages_per_link<T: RDH>(rdhs: &[T], mem_positions: &[u64]) -> Vec<(u8, PageCounts)> {
+    let mut link_page_counts: Vec<(u8, PageCounts)> = Vec::new();
+    for (rdh, mem_pos) in rdhs.iter().zip(mem_positions) {
+        let link_id = rdh.link_id();
+        match link_page_counts
+            .iter_mut()
+            .find(|(link, _)| *link == link_id)
+        {
+            Some((_, page_counts)) => page_counts.count(rdh, *mem_pos),
+            None => {
+                let mut page_counts = PageCounts::default();
+                page_counts.count(rdh, *mem_pos);
+                link_page_counts.push((link_id, page_counts));
+            }
+        }
+    }
+    link_page_counts.sort_by_key(|(link, _)| *link);
+    link_page_counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh_cru::{test_data::CORRECT_RDH_CRU_V7, RdhCRU, V7};
+
+    // HBFs of 2 pages on link 0, where `missing_stop_pages` of the HBFs have no stop page
+    fn link_0_hbfs(hbfs: u16, missing_stop_pages: u16) -> Vec<RdhCRU<V7>> {
+        let mut rdhs = Vec::new();
+        for hbf in 0..hbfs {
+            for pages_counter in 0..2 {
+                let mut rdh = CORRECT_RDH_CRU_V7;
+                rdh.link_id = 0;
+                rdh.rdh2.pages_counter = pages_counter;
+                rdh.rdh2.stop_bit = (pages_counter == 1 && hbf >= missing_stop_pages) as u8;
+                rdhs.push(rdh);
+            }
+        }
+        rdhs
+    }
+
+    // Memory positions of consecutive pages starting at `start`
+    fn mem_positions_of(rdhs: &[RdhCRU<V7>], start: u64) -> Vec<u64> {
+        rdhs.iter()
+            .scan(start, |mem_pos, rdh| {
+                let page_pos = *mem_pos;
+                *mem_pos += u64::from(rdh.offset_to_next());
+                Some(page_pos)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_count_pages_missing_one_stop_page() {
+        let mut rdhs = link_0_hbfs(4, 1);
+        let mut other_link_rdh = CORRECT_RDH_CRU_V7;
+        other_link_rdh.link_id = 3;
+        rdhs.push(other_link_rdh);
+
+        let link_page_counts = count_pages_per_link(&rdhs, &mem_positions_of(&rdhs, 0));
+        assert_eq!(link_page_counts.len(), 2);
+        let (link_id, page_counts) = link_page_counts[0];
+        assert_eq!(link_id, 0);
+        assert_eq!(
+            page_counts,
+            PageCounts {
+                pages: 8,
+                opening_pages: 4,
+                stop_pages: 3,
+                first_page_pos: Some(0),
+                end_pos: 8 * u64::from(CORRECT_RDH_CRU_V7.offset_to_next()),
+            }
+        );
+        assert_eq!(page_counts.avg_pages_per_hbf(), Some(8.0 / 3.0));
+        // Could be the input ending in the middle of an HBF
+        assert!(!page_counts.has_unterminated_hbfs());
+        assert_eq!(link_page_counts[1].0, 3);
+        assert_eq!(link_page_counts[1].1.avg_pages_per_hbf(), None);
+    }
+
+    #[test]
+    fn test_unterminated_hbfs_across_chunks() {
+        let mut page_counts = PageCounts::default();
+        let chunk_size = 4 * u64::from(CORRECT_RDH_CRU_V7.offset_to_next());
+        // The second chunk follows the first, with 0x100 bytes of another link in between
+        for (chunk, start) in [
+            (link_0_hbfs(2, 1), 0x100),
+            (link_0_hbfs(2, 1), 0x200 + chunk_size),
+        ] {
+            let (_, chunk_page_counts) =
+                count_pages_per_link(&chunk, &mem_positions_of(&chunk, start))[0];
+            page_counts.merge(&chunk_page_counts);
+        }
+        assert_eq!(page_counts.span(), (0x100, 0x200 + 2 * chunk_size));
+        assert_eq!(page_counts.opening_pages, 4);
+        assert_eq!(page_counts.stop_pages, 2);
+        assert!(page_counts.has_unterminated_hbfs());
+    }
+}
@@ -0,0 +1,251 @@
+//! Contains the [EntropySampler] that samples the payload bytes of every Kth CDP of each link into a [LinkByteHistogram], from which the entropy of the payloads of each link is estimated at report time.
+//!
+//! The entropy is the Shannon entropy of the byte values, in bits per byte: 0 for payloads of a constant byte, 8 for uniformly random payloads.
+//! It is a cheap estimate of how compressible the payloads of a link are, as `entropy / 8` approximates the compression ratio of a byte-oriented compressor.
+//! The histograms are accumulated per chunk in the [Accumulators][super::accumulators::Accumulators] and merged by the [StatsController][super::stats_controller::StatsController].
+use crate::words::lib::RDH;
+use tabled::Tabled;
+
+/// Counts of each byte value in the sampled payloads of a link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkByteHistogram {
+    /// Link ID.
+    pub link_id: u8,
+    /// CDPs sampled.
+    pub sampled_cdps: u64,
+    counts: Vec<u64>,
+}
+
+impl LinkByteHistogram {
+    /// Creates an empty histogram for a link.
+    pub fn new(link_id: u8) -> Self {
+        Self {
+            link_id,
+            sampled_cdps: 0,
+            counts: vec![0; 256],
+        }
+    }
+
+    /// Adds the bytes of a sampled payload.
+    pub fn add_payload(&mut self, payload: &[u8]) {
+        self.sampled_cdps += 1;
+        payload
+            .iter()
+            .for_each(|byte| self.counts[*byte as usize] += 1);
+    }
+
+    /// Adds the counts of `other` to the counts of `self`.
+    pub fn merge(&mut self, other: &LinkByteHistogram) {
+        debug_assert_eq!(self.link_id, other.link_id);
+        self.sampled_cdps += other.sampled_cdps;
+        self.counts
+            .iter_mut()
+            .zip(&other.counts)
+            .for_each(|(count, other_count)| *count += other_count);
+    }
+
+    /// Payload bytes sampled.
+    pub fn sampled_bytes(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Shannon entropy of the sampled bytes in bits per byte, [None] if no payload bytes were sampled.
+    pub fn entropy_bits_per_byte(&self) -> Option<f64> {
+        let total = self.sampled_bytes();
+        if total == 0 {
+            return None;
+        }
+        let entropy = self
+            .counts
+            .iter()
+            .filter(|count| **count > 0)
+            .map(|count| {
+                let p = *count as f64 / total as f64;
+                // Rather than -p * log2(p), which is -0.0 for payloads of a constant byte
+                p * (1.0 / p).log2()
+            })
+            .sum::<f64>();
+        Some(entropy)
+    }
+
+    /// The entropy of the link as a JSON object.
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"link":{},"sampled_cdps":{},"sampled_bytes":{},"bits_per_byte":{}}}"#,
+            self.link_id,
+            self.sampled_cdps,
+            self.sampled_bytes(),
+            self.entropy_bits_per_byte()
+                .map_or("null".to_string(), |entropy| format!("{entropy:.3}"))
+        )
+    }
+}
+
+/// Adds the histograms of `other` to the histograms of the same link in `histograms`, ordered by link ID.
+pub fn merge_histograms(histograms: &mut Vec<LinkByteHistogram>, other: &[LinkByteHistogram]) {
+    for other_histogram in other {
+        histogram_of_link(histograms, other_histogram.link_id).merge(other_histogram);
+    }
+}
+
+/// The histogram of a link, created if the link has none yet, keeping the histograms ordered by link ID.
+fn histogram_of_link(
+    histograms: &mut Vec<LinkByteHistogram>,
+    link_id: u8,
+) -> &mut LinkByteHistogram {
+    let idx = match histograms.binary_search_by_key(&link_id, |histogram| histogram.link_id) {
+        Ok(idx) => idx,
+        Err(idx) => {
+            histograms.insert(idx, LinkByteHistogram::new(link_id));
+            idx
+        }
+    };
+    &mut histograms[idx]
+}
+
+/// Samples the payload of every Kth CDP of each link, counting the CDPs of each link in the order they appear in the input.
+#[derive(Debug)]
+pub struct EntropySampler {
+    sample_every: u32,
+    /// CDPs seen of each link, by link ID.
+    cdps_seen: Vec<(u8, u64)>,
+}
+
+impl EntropySampler {
+    /// Creates an [EntropySampler] sampling every `sample_every` CDP of each link, starting with the first.
+    pub fn new(sample_every: std::num::NonZeroU32) -> Self {
+        Self {
+            sample_every: sample_every.get(),
+            cdps_seen: Vec::new(),
+        }
+    }
+
+    /// Records a CDP, and adds its payload to the histogram of its link in `histograms` if it is sampled.
+    pub fn record<T: RDH>(
+        &mut self,
+        rdh: &T,
+        payload: &[u8],
+        histograms: &mut Vec<LinkByteHistogram>,
+    ) {
+        let link_id = rdh.link_id();
+        let cdps_seen = match self.cdps_seen.iter_mut().find(|(link, _)| *link == link_id) {
+            Some((_, cdps_seen)) => cdps_seen,
+            None => {
+                self.cdps_seen.push((link_id, 0));
+                &mut self.cdps_seen.last_mut().unwrap().1
+            }
+        };
+        let sampled = *cdps_seen % self.sample_every as u64 == 0;
+        *cdps_seen += 1;
+        if sampled {
+            histogram_of_link(histograms, link_id).add_payload(payload);
+        }
+    }
+}
+
+/// A row of the entropy table of the report.
+#[derive(Tabled)]
+pub struct EntropyRow {
+    #[tabled(rename = "Link")]
+    link_id: u8,
+    #[tabled(rename = "Sampled CDPs")]
+    sampled_cdps: u64,
+    #[tabled(rename = "Sampled bytes")]
+    sampled_bytes: String,
+    #[tabled(rename = "Entropy (bits/byte)")]
+    entropy: String,
+}
+
+impl From<&LinkByteHistogram> for EntropyRow {
+    fn from(histogram: &LinkByteHistogram) -> Self {
+        Self {
+            link_id: histogram.link_id,
+            sampled_cdps: histogram.sampled_cdps,
+            sampled_bytes: super::report::format_byte_size(&histogram.sampled_bytes()),
+            entropy: histogram
+                .entropy_bits_per_byte()
+                .map_or("-".to_string(), |entropy| format!("{entropy:.3}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh_cru::{test_data::CORRECT_RDH_CRU_V7, RdhCRU, V7};
+
+    fn cdp(link_id: u8) -> RdhCRU<V7> {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        rdh.link_id = link_id;
+        rdh
+    }
+
+    /// Bytes from a xorshift generator, as close to uniformly random as a test needs.
+    fn random_payload(len: usize) -> Vec<u8> {
+        let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 32) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_constant_and_random_payloads() {
+        let mut sampler = EntropySampler::new(std::num::NonZeroU32::new(1).unwrap());
+        let mut histograms = Vec::new();
+        let random = random_payload(1 << 16);
+        for chunk in random.chunks(0x2000) {
+            sampler.record(&cdp(0), &[0xAB; 0x2000], &mut histograms);
+            sampler.record(&cdp(3), chunk, &mut histograms);
+        }
+        assert_eq!(histograms.len(), 2);
+        let constant = &histograms[0];
+        assert_eq!(constant.link_id, 0);
+        assert_eq!(constant.sampled_cdps, 8);
+        assert_eq!(constant.sampled_bytes(), 8 * 0x2000);
+        assert!(constant.entropy_bits_per_byte().unwrap() < 0.001);
+        let random = &histograms[1];
+        assert_eq!(random.link_id, 3);
+        assert!(random.entropy_bits_per_byte().unwrap() > 7.99);
+        assert_eq!(
+            constant.to_json(),
+            r#"{"link":0,"sampled_cdps":8,"sampled_bytes":65536,"bits_per_byte":0.000}"#
+        );
+    }
+
+    #[test]
+    fn test_every_kth_cdp_of_each_link_sampled() {
+        let mut sampler = EntropySampler::new(std::num::NonZeroU32::new(3).unwrap());
+        let mut histograms = Vec::new();
+        for _ in 0..7 {
+            sampler.record(&cdp(2), &[0x00, 0xFF], &mut histograms);
+            sampler.record(&cdp(1), &[], &mut histograms);
+        }
+        // CDPs 0, 3 and 6 of each link
+        assert_eq!(histograms[0].link_id, 1);
+        assert_eq!(histograms[0].sampled_cdps, 3);
+        assert_eq!(histograms[0].entropy_bits_per_byte(), None);
+        assert_eq!(histograms[1].sampled_cdps, 3);
+        assert_eq!(histograms[1].sampled_bytes(), 6);
+        assert_eq!(histograms[1].entropy_bits_per_byte(), Some(1.0));
+    }
+
+    #[test]
+    fn test_merge_histograms_of_chunks() {
+        let mut sampler = EntropySampler::new(std::num::NonZeroU32::new(1).unwrap());
+        let mut merged = Vec::new();
+        // Each chunk flushes its own histograms
+        for payload in [[0x00; 4], [0x01; 4]] {
+            let mut chunk_histograms = Vec::new();
+            sampler.record(&cdp(5), &payload, &mut chunk_histograms);
+            merge_histograms(&mut merged, &chunk_histograms);
+        }
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].sampled_cdps, 2);
+        assert_eq!(merged[0].entropy_bits_per_byte(), Some(1.0));
+    }
+}
@@ -0,0 +1,304 @@
+//! Contains the [LinkPayloadUniformity] that counts the distinct payload contents of a link by their hash, to verify that the payloads of calibration runs are uniform.
+//!
+//! The payload of every CDP is hashed in the analysis thread, and the counts are accumulated per chunk in the [Accumulators][super::accumulators::Accumulators] and merged by the [StatsController][super::stats_controller::StatsController].
+//! At most [MAX_DISTINCT_PAYLOADS] distinct payloads are tracked per link, payloads with a hash seen after that are only counted.
+use crate::words::lib::RDH;
+use tabled::Tabled;
+
+/// Maximum number of distinct payloads tracked per link, beyond it the number of distinct payloads is reported as "more than N".
+pub const MAX_DISTINCT_PAYLOADS: usize = 1024;
+/// Number of outliers listed per link, the first in memory order.
+const MAX_OUTLIERS_LISTED: usize = 5;
+
+/// A payload content seen on a link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DistinctPayload {
+    hash: u64,
+    count: u64,
+    /// Memory positions of the RDHs of the first CDPs with the payload, at most [MAX_OUTLIERS_LISTED].
+    first_mem_pos: Vec<u64>,
+}
+
+/// Counts of the distinct payload contents of a link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkPayloadUniformity {
+    /// Link ID.
+    pub link_id: u8,
+    /// Payloads seen.
+    pub payloads: u64,
+    distinct: Vec<DistinctPayload>,
+    /// Payloads not tracked as there were already [MAX_DISTINCT_PAYLOADS] distinct payloads.
+    untracked_payloads: u64,
+}
+
+impl LinkPayloadUniformity {
+    /// Creates empty counts for a link.
+    pub fn new(link_id: u8) -> Self {
+        Self {
+            link_id,
+            payloads: 0,
+            distinct: Vec::new(),
+            untracked_payloads: 0,
+        }
+    }
+
+    /// Counts the payload of the CDP with the RDH at `mem_pos`.
+    pub fn add_payload(&mut self, payload: &[u8], mem_pos: u64) {
+        self.add_hash(fnv1a_64(payload), 1, &[mem_pos]);
+    }
+
+    fn add_hash(&mut self, hash: u64, count: u64, mem_positions: &[u64]) {
+        self.payloads += count;
+        match self
+            .distinct
+            .iter()
+            .position(|distinct| distinct.hash == hash)
+        {
+            Some(idx) => {
+                let distinct = &mut self.distinct[idx];
+                distinct.count += count;
+                distinct.first_mem_pos.extend_from_slice(mem_positions);
+                distinct.first_mem_pos.truncate(MAX_OUTLIERS_LISTED);
+            }
+            None if self.distinct.len() < MAX_DISTINCT_PAYLOADS => {
+                let mut first_mem_pos = mem_positions.to_vec();
+                first_mem_pos.truncate(MAX_OUTLIERS_LISTED);
+                self.distinct.push(DistinctPayload {
+                    hash,
+                    count,
+                    first_mem_pos,
+                });
+            }
+            None => self.untracked_payloads += count,
+        }
+    }
+
+    /// Adds the counts of `other`, which must be of payloads that came after the payloads of `self`.
+    pub fn merge(&mut self, other: &LinkPayloadUniformity) {
+        debug_assert_eq!(self.link_id, other.link_id);
+        other.distinct.iter().for_each(|distinct| {
+            self.add_hash(distinct.hash, distinct.count, &distinct.first_mem_pos)
+        });
+        self.payloads += other.untracked_payloads;
+        self.untracked_payloads += other.untracked_payloads;
+    }
+
+    /// Returns true if more than [MAX_DISTINCT_PAYLOADS] distinct payloads were seen.
+    pub fn is_overflowed(&self) -> bool {
+        self.untracked_payloads > 0
+    }
+
+    /// Number of distinct payloads tracked.
+    pub fn distinct_payloads(&self) -> usize {
+        self.distinct.len()
+    }
+
+    /// The payload seen the most, the first seen if several are seen as often.
+    fn most_common(&self) -> Option<&DistinctPayload> {
+        self.distinct
+            .iter()
+            .rev()
+            .max_by_key(|distinct| distinct.count)
+    }
+
+    /// Share of the payloads that are the most common payload, [None] if no payloads were seen.
+    pub fn most_common_share(&self) -> Option<f64> {
+        self.most_common()
+            .map(|most_common| most_common.count as f64 / self.payloads as f64)
+    }
+
+    /// Memory positions of the RDHs of the first CDPs with a payload other than the most common payload, in memory order.
+    ///
+    /// Untracked payloads are not listed.
+    pub fn first_outliers(&self) -> Vec<u64> {
+        let most_common_hash = match self.most_common() {
+            Some(most_common) => most_common.hash,
+            None => return Vec::new(),
+        };
+        let mut outliers: Vec<u64> = self
+            .distinct
+            .iter()
+            .filter(|distinct| distinct.hash != most_common_hash)
+            .flat_map(|distinct| distinct.first_mem_pos.iter().copied())
+            .collect();
+        outliers.sort_unstable();
+        outliers.truncate(MAX_OUTLIERS_LISTED);
+        outliers
+    }
+
+    fn distinct_as_string(&self) -> String {
+        if self.is_overflowed() {
+            format!("more than {MAX_DISTINCT_PAYLOADS}")
+        } else {
+            self.distinct_payloads().to_string()
+        }
+    }
+
+    /// The uniformity of the payloads of the link as a JSON object.
+    pub fn to_json(&self) -> String {
+        let outliers: Vec<String> = self
+            .first_outliers()
+            .iter()
+            .map(|mem_pos| mem_pos.to_string())
+            .collect();
+        format!(
+            r#"{{"link":{},"payloads":{},"distinct":{},"more_distinct":{},"most_common_share":{},"first_outliers":[{}]}}"#,
+            self.link_id,
+            self.payloads,
+            self.distinct_payloads(),
+            self.is_overflowed(),
+            self.most_common_share()
+                .map_or("null".to_string(), |share| format!("{share:.3}")),
+            outliers.join(",")
+        )
+    }
+}
+
+/// Adds the counts of `other` to the counts of the same link in `uniformity`, ordered by link ID.
+pub fn merge_uniformity(
+    uniformity: &mut Vec<LinkPayloadUniformity>,
+    other: &[LinkPayloadUniformity],
+) {
+    for other_link in other {
+        uniformity_of_link(uniformity, other_link.link_id).merge(other_link);
+    }
+}
+
+/// Counts the payload of a CDP in the counts of its link in `uniformity`.
+pub fn record_payload<T: RDH>(
+    rdh: &T,
+    payload: &[u8],
+    mem_pos: u64,
+    uniformity: &mut Vec<LinkPayloadUniformity>,
+) {
+    uniformity_of_link(uniformity, rdh.link_id()).add_payload(payload, mem_pos);
+}
+
+/// The counts of a link, created if the link has none yet, keeping the counts ordered by link ID.
+fn uniformity_of_link(
+    uniformity: &mut Vec<LinkPayloadUniformity>,
+    link_id: u8,
+) -> &mut LinkPayloadUniformity {
+    let idx = match uniformity.binary_search_by_key(&link_id, |link| link.link_id) {
+        Ok(idx) => idx,
+        Err(idx) => {
+            uniformity.insert(idx, LinkPayloadUniformity::new(link_id));
+            idx
+        }
+    };
+    &mut uniformity[idx]
+}
+
+/// 64-bit FNV-1a hash, stable across runs and platforms.
+fn fnv1a_64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+/// A row of the payload uniformity table of the report.
+#[derive(Tabled)]
+pub struct PayloadUniformityRow {
+    #[tabled(rename = "Link")]
+    link_id: u8,
+    #[tabled(rename = "Payloads")]
+    payloads: u64,
+    #[tabled(rename = "Distinct payloads")]
+    distinct: String,
+    #[tabled(rename = "Most common share")]
+    most_common_share: String,
+    #[tabled(rename = "First outliers")]
+    first_outliers: String,
+}
+
+impl From<&LinkPayloadUniformity> for PayloadUniformityRow {
+    fn from(link: &LinkPayloadUniformity) -> Self {
+        let outliers = link.first_outliers();
+        Self {
+            link_id: link.link_id,
+            payloads: link.payloads,
+            distinct: link.distinct_as_string(),
+            most_common_share: link
+                .most_common_share()
+                .map_or("-".to_string(), |share| format!("{:.1}%", share * 100.0)),
+            first_outliers: if outliers.is_empty() {
+                "-".to_string()
+            } else {
+                outliers
+                    .iter()
+                    .map(|mem_pos| format!("{mem_pos:#X}"))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh_cru::{test_data::CORRECT_RDH_CRU_V7, RdhCRU, V7};
+
+    fn cdp(link_id: u8) -> RdhCRU<V7> {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        rdh.link_id = link_id;
+        rdh
+    }
+
+    #[test]
+    fn test_repeated_payloads_with_an_outlier() {
+        let mut uniformity = Vec::new();
+        for idx in 0..10 {
+            let payload = if idx == 6 { [0xAB; 32] } else { [0x11; 32] };
+            record_payload(&cdp(3), &payload, idx * 0x100, &mut uniformity);
+        }
+        assert_eq!(uniformity.len(), 1);
+        let link = &uniformity[0];
+        assert_eq!(link.payloads, 10);
+        assert_eq!(link.distinct_payloads(), 2);
+        assert_eq!(link.most_common_share(), Some(0.9));
+        assert_eq!(link.first_outliers(), vec![0x600]);
+        assert_eq!(
+            link.to_json(),
+            r#"{"link":3,"payloads":10,"distinct":2,"more_distinct":false,"most_common_share":0.900,"first_outliers":[1536]}"#
+        );
+    }
+
+    #[test]
+    fn test_distinct_payloads_beyond_cap_are_counted() {
+        let mut uniformity = Vec::new();
+        let distinct_payloads = MAX_DISTINCT_PAYLOADS as u64 + 10;
+        for idx in 0..distinct_payloads {
+            record_payload(&cdp(0), &idx.to_le_bytes(), idx, &mut uniformity);
+        }
+        let link = &uniformity[0];
+        assert_eq!(link.payloads, distinct_payloads);
+        assert_eq!(link.distinct_payloads(), MAX_DISTINCT_PAYLOADS);
+        assert!(link.is_overflowed());
+        assert_eq!(link.distinct_as_string(), "more than 1024");
+        // All are seen once, the first is the most common
+        assert_eq!(link.first_outliers(), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_merge_chunks_keeps_first_outliers() {
+        let mut merged = Vec::new();
+        // Each chunk flushes its own counts, the outliers are in the second and third chunk
+        for (chunk_idx, payloads) in [[0x00, 0x00], [0x00, 0x01], [0x02, 0x01]]
+            .into_iter()
+            .enumerate()
+        {
+            let mut chunk_uniformity = Vec::new();
+            for (idx, payload) in payloads.into_iter().enumerate() {
+                let mem_pos = (chunk_idx * 2 + idx) as u64 * 0x40;
+                record_payload(&cdp(5), &[payload; 16], mem_pos, &mut chunk_uniformity);
+            }
+            merge_uniformity(&mut merged, &chunk_uniformity);
+        }
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].payloads, 6);
+        assert_eq!(merged[0].distinct_payloads(), 3);
+        assert_eq!(merged[0].most_common_share(), Some(0.5));
+        assert_eq!(merged[0].first_outliers(), vec![0xC0, 0x100, 0x140]);
+    }
+}
@@ -0,0 +1,232 @@
+//! Contains the [PipelineCounts] that reconciles the CDPs passed between the reader, the analysis and the writer at the end of the run.
+//!
+//! Each component reports the CDPs it handled with [ComponentCounts] when it finishes.
+//! CDPs lost between the components, e.g. a chunk dropped on a channel error, are reported as internal errors instead of only showing up as low totals.
+use crate::input::data_wrapper::CdpChunk;
+use crate::words::lib::{RDH, RDH_SIZE_BYTES};
+
+/// A number of CDPs and their bytes, counting [RDH_SIZE_BYTES] for each RDH and the payload.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CdpCount {
+    /// CDPs counted.
+    pub cdps: u64,
+    /// Bytes of the RDHs and payloads of the CDPs.
+    pub bytes: u64,
+}
+
+impl CdpCount {
+    /// Counts the CDPs of a [CdpChunk].
+    pub fn of_chunk<T: RDH>(cdp_chunk: &CdpChunk<T>) -> Self {
+        Self {
+            cdps: cdp_chunk.len() as u64,
+            bytes: cdp_chunk
+                .into_iter()
+                .map(|(_, payload, _)| RDH_SIZE_BYTES as u64 + payload.len() as u64)
+                .sum(),
+        }
+    }
+}
+
+impl std::ops::AddAssign for CdpCount {
+    fn add_assign(&mut self, other: Self) {
+        self.cdps += other.cdps;
+        self.bytes += other.bytes;
+    }
+}
+
+impl std::fmt::Display for CdpCount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} CDPs ({} bytes)", self.cdps, self.bytes)
+    }
+}
+
+/// The CDPs handled by a component of the pipeline, reported when it finishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentCounts {
+    /// The reader read `matched` CDPs that match the filters, and sent `produced` CDPs to the analysis or the writer.
+    ///
+    /// `stopped` is true if reading was stopped by the stop flag, in which case CDPs read are expected to be dropped.
+    Reader {
+        /// CDPs read that match the filters.
+        matched: CdpCount,
+        /// CDPs sent to the analysis or the writer.
+        produced: CdpCount,
+        /// Reading was stopped by the stop flag.
+        stopped: bool,
+    },
+    /// The analysis received `consumed` CDPs, `drained` is true if it received CDPs until the reader was done.
+    Analysis {
+        /// CDPs received from the reader.
+        consumed: CdpCount,
+        /// All CDPs sent by the reader were received.
+        drained: bool,
+    },
+    /// The writer wrote `written` CDPs, `complete` is true if every CDP received from the reader was to be written.
+    ///
+    /// That is not the case if the writer was stopped, the HBFs to write were selected, or the output was dropped after a write failed.
+    Writer {
+        /// CDPs written.
+        written: CdpCount,
+        /// Every CDP received from the reader was to be written.
+        complete: bool,
+    },
+}
+
+/// The [ComponentCounts] reported by the components of the pipeline.
+#[derive(Debug, Default)]
+pub struct PipelineCounts {
+    reader: Option<(CdpCount, CdpCount, bool)>,
+    analysis: Option<(CdpCount, bool)>,
+    writer: Option<(CdpCount, bool)>,
+}
+
+impl PipelineCounts {
+    /// Records the counts reported by a component.
+    pub fn record(&mut self, counts: ComponentCounts) {
+        match counts {
+            ComponentCounts::Reader {
+                matched,
+                produced,
+                stopped,
+            } => self.reader = Some((matched, produced, stopped)),
+            ComponentCounts::Analysis { consumed, drained } => {
+                self.analysis = Some((consumed, drained))
+            }
+            ComponentCounts::Writer { written, complete } => {
+                self.writer = Some((written, complete))
+            }
+        }
+    }
+
+    /// Cross-checks the counts of the components, and returns a message with the numbers of each discrepancy.
+    ///
+    /// Only counts that are expected to be equal are compared, e.g. nothing is compared to the reader if it was stopped.
+    pub fn reconcile(&self) -> Vec<String> {
+        let (matched, produced, stopped) = match self.reader {
+            Some(reader) => reader,
+            None => return Vec::new(),
+        };
+        let mut discrepancies = Vec::new();
+        if !stopped && produced != matched {
+            discrepancies.push(format!(
+                "The reader read {matched} matching the filters, but sent {produced}"
+            ));
+        }
+        if let Some((consumed, true)) = self.analysis {
+            if consumed != produced {
+                discrepancies.push(format!(
+                    "The reader sent {produced}, but the analysis received {consumed}"
+                ));
+            }
+        }
+        if let Some((written, true)) = self.writer {
+            if !stopped && written != matched {
+                discrepancies.push(format!(
+                    "The reader read {matched} matching the filters, but the writer wrote {written}"
+                ));
+            }
+        }
+        discrepancies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COUNT: CdpCount = CdpCount {
+        cdps: 18,
+        bytes: 1824,
+    };
+
+    fn counts(components: &[ComponentCounts]) -> PipelineCounts {
+        let mut pipeline_counts = PipelineCounts::default();
+        components
+            .iter()
+            .for_each(|counts| pipeline_counts.record(*counts));
+        pipeline_counts
+    }
+
+    #[test]
+    fn test_reconcile_consistent_counts() {
+        let reader = ComponentCounts::Reader {
+            matched: COUNT,
+            produced: COUNT,
+            stopped: false,
+        };
+        let analysis = ComponentCounts::Analysis {
+            consumed: COUNT,
+            drained: true,
+        };
+        let writer = ComponentCounts::Writer {
+            written: COUNT,
+            complete: true,
+        };
+        assert!(counts(&[reader, analysis]).reconcile().is_empty());
+        assert!(counts(&[reader, writer]).reconcile().is_empty());
+        // Nothing to compare without the reader
+        assert!(PipelineCounts::default().reconcile().is_empty());
+    }
+
+    #[test]
+    fn test_reconcile_writer_discrepancy() {
+        let reader = ComponentCounts::Reader {
+            matched: COUNT,
+            produced: COUNT,
+            stopped: false,
+        };
+        let written = CdpCount {
+            cdps: 12,
+            bytes: 1200,
+        };
+        assert_eq!(
+            counts(&[
+                reader,
+                ComponentCounts::Writer {
+                    written,
+                    complete: true
+                }
+            ])
+            .reconcile(),
+            ["The reader read 18 CDPs (1824 bytes) matching the filters, but the writer wrote 12 CDPs (1200 bytes)"]
+        );
+        // The HBFs to write were selected, or the writer was stopped
+        assert!(counts(&[
+            reader,
+            ComponentCounts::Writer {
+                written,
+                complete: false
+            }
+        ])
+        .reconcile()
+        .is_empty());
+    }
+
+    #[test]
+    fn test_reconcile_stopped_reader() {
+        let produced = CdpCount {
+            cdps: 12,
+            bytes: 1200,
+        };
+        let stopped_reader = ComponentCounts::Reader {
+            matched: COUNT,
+            produced,
+            stopped: true,
+        };
+        let analysis = ComponentCounts::Analysis {
+            consumed: produced,
+            drained: true,
+        };
+        assert!(counts(&[stopped_reader, analysis]).reconcile().is_empty());
+        // The reader failed to send without being stopped
+        let reader = ComponentCounts::Reader {
+            matched: COUNT,
+            produced,
+            stopped: false,
+        };
+        assert_eq!(
+            counts(&[reader, analysis]).reconcile(),
+            ["The reader read 18 CDPs (1824 bytes) matching the filters, but sent 12 CDPs (1200 bytes)"]
+        );
+    }
+}
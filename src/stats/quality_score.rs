@@ -0,0 +1,488 @@
+//! Contains the [QualityScore] that summarizes the quality of a run as a single 0-100 score, from weighted [QualityComponent]s.
+//!
+//! The score is computed at report time from the stats already collected by the [StatsController][super::stats_controller::StatsController], gathered in [QualityInputs].
+//! A component that can't be computed, e.g. the fatal lanes if no DDW0 was checked, is left out, and the score is computed from the weights of the other components.
+//!
+//! The weights and thresholds are read from a file with [QualityConfig::read], with a `key = value` setting per line.
+use tabled::Tabled;
+
+/// A component of the [QualityScore], each is a value from 0 (worst) to 1 (best).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QualityComponent {
+    /// Fraction of the HBFs checked without errors.
+    ErrorFreeHbfs,
+    /// Mean of the HBFs of each expected link, relative to the link with the most HBFs.
+    LinkCompleteness,
+    /// Fraction of the lanes that never had the fatal status in a DDW0.
+    FatalLanes,
+}
+
+impl QualityComponent {
+    /// All components, in the order they are reported.
+    pub const ALL: [QualityComponent; 3] = [
+        QualityComponent::ErrorFreeHbfs,
+        QualityComponent::LinkCompleteness,
+        QualityComponent::FatalLanes,
+    ];
+
+    /// Name of the component in the JSON report, and of its weight in the quality config as `weight.<key>`.
+    pub fn key(&self) -> &'static str {
+        match self {
+            QualityComponent::ErrorFreeHbfs => "error_free_hbfs",
+            QualityComponent::LinkCompleteness => "link_completeness",
+            QualityComponent::FatalLanes => "fatal_lanes",
+        }
+    }
+
+    /// Name of the component in the report table.
+    pub fn description(&self) -> &'static str {
+        match self {
+            QualityComponent::ErrorFreeHbfs => "Error-free HBFs",
+            QualityComponent::LinkCompleteness => "Link completeness",
+            QualityComponent::FatalLanes => "Lanes without fatal status",
+        }
+    }
+
+    fn default_weight(&self) -> f64 {
+        match self {
+            QualityComponent::ErrorFreeHbfs => 0.4,
+            QualityComponent::LinkCompleteness | QualityComponent::FatalLanes => 0.2,
+        }
+    }
+
+    fn idx(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Weights and thresholds of the [QualityScore].
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityConfig {
+    /// Weight of each component, by [QualityComponent::ALL] order, relative to the others.
+    weights: [f64; 3],
+    /// A warning is reported if the score is below it.
+    pub warn_below: Option<f64>,
+    /// Links expected in the data, [None] if the link completeness is not computed.
+    pub expected_links: Option<Vec<u8>>,
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        Self {
+            weights: QualityComponent::ALL.map(|component| component.default_weight()),
+            warn_below: None,
+            expected_links: None,
+        }
+    }
+}
+
+impl QualityConfig {
+    /// Reads the quality config from a file.
+    pub fn read(path: &std::path::Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read quality config {}: {e}", path.display()))?;
+        Self::parse(&content).map_err(|e| format!("Invalid quality config {}: {e}", path.display()))
+    }
+
+    /// Parses the quality config from lines of `key = value`, settings that are not set keep their default.
+    ///
+    /// The keys are `weight.<component>` (see [QualityComponent::key]), `warn_below` and `expected_links` (comma-separated link IDs).
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+        for (line_idx, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let line_no = line_idx + 1;
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(format!("line {line_no}: expected `key = value`")),
+            };
+            let number = || {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|number| number.is_finite() && *number >= 0.0)
+                    .ok_or_else(|| format!("line {line_no}: `{key}` must be a non-negative number"))
+            };
+            match key {
+                "warn_below" => match number()? {
+                    score if score <= 100.0 => config.warn_below = Some(score),
+                    _ => return Err(format!("line {line_no}: `{key}` must be 0 to 100")),
+                },
+                "expected_links" => {
+                    let links = value
+                        .split(',')
+                        .map(|link| link.trim().parse::<u8>())
+                        .collect::<Result<Vec<u8>, _>>()
+                        .map_err(|e| format!("line {line_no}: invalid link ID: {e}"))?;
+                    config.expected_links = Some(links);
+                }
+                _ => match QualityComponent::ALL
+                    .iter()
+                    .find(|component| key.strip_prefix("weight.") == Some(component.key()))
+                {
+                    Some(component) => config.weights[component.idx()] = number()?,
+                    None => return Err(format!("line {line_no}: unknown key `{key}`")),
+                },
+            }
+        }
+        Ok(config)
+    }
+
+    /// Weight of a component.
+    pub fn weight(&self, component: QualityComponent) -> f64 {
+        self.weights[component.idx()]
+    }
+}
+
+/// HBFs checked by the link validator of a link, and the lanes in the DDW0s of the link, sent when it finishes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LinkQualityCounts {
+    /// Link ID.
+    pub link_id: u8,
+    /// HBFs checked.
+    pub hbfs: u64,
+    /// HBFs checked with at least one error.
+    pub errored_hbfs: u64,
+    /// Lanes in the lane status of the DDW0s, 0 if no DDW0 was checked.
+    pub lanes: u32,
+    /// Lanes with the fatal status in any DDW0.
+    pub fatal_lanes: u32,
+}
+
+/// The stats the [QualityScore] is computed from.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct QualityInputs {
+    /// HBFs and lanes checked on each link.
+    pub link_quality: Vec<LinkQualityCounts>,
+    /// HBFs of each link, by link ID.
+    pub link_hbfs: Vec<(u8, u32)>,
+}
+
+/// Value of a component, or the reason it can't be computed.
+pub type ComponentValue = Result<f64, &'static str>;
+
+/// The quality score of a run, and the components it is computed from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityScore {
+    /// Score from 0 to 100, [None] if no component could be computed.
+    pub score: Option<f64>,
+    /// Weight and value of each component, by [QualityComponent::ALL] order.
+    pub components: Vec<(QualityComponent, f64, ComponentValue)>,
+    warn_below: Option<f64>,
+}
+
+impl QualityScore {
+    /// Computes the score of the run from the components that can be computed.
+    pub fn compute(config: &QualityConfig, inputs: &QualityInputs) -> Self {
+        let components: Vec<(QualityComponent, f64, ComponentValue)> = QualityComponent::ALL
+            .iter()
+            .map(|component| {
+                (
+                    *component,
+                    config.weight(*component),
+                    component_value(*component, config, inputs),
+                )
+            })
+            .collect();
+        let (weighted_sum, total_weight) = components
+            .iter()
+            .filter_map(|(_, weight, value)| value.ok().map(|value| (weight * value, *weight)))
+            .fold((0.0, 0.0), |(sum, total), (weighted, weight)| {
+                (sum + weighted, total + weight)
+            });
+        Self {
+            score: (total_weight > 0.0).then(|| 100.0 * weighted_sum / total_weight),
+            components,
+            warn_below: config.warn_below,
+        }
+    }
+
+    /// Number of components the score is computed from.
+    pub fn components_used(&self) -> usize {
+        self.components
+            .iter()
+            .filter(|(_, weight, value)| *weight > 0.0 && value.is_ok())
+            .count()
+    }
+
+    /// Warning if the score is below the threshold of the quality config.
+    pub fn warning(&self) -> Option<String> {
+        match (self.score, self.warn_below) {
+            (Some(score), Some(warn_below)) if score < warn_below => Some(format!(
+                "Quality score {score:.1} is below the threshold of {warn_below:.1}"
+            )),
+            _ => None,
+        }
+    }
+
+    /// The score and its components as a JSON object, components that can't be computed have a `null` value.
+    pub fn to_json(&self) -> String {
+        let components: Vec<String> = self
+            .components
+            .iter()
+            .map(|(component, weight, value)| {
+                format!(
+                    r#"{{"component":"{}","weight":{weight},"value":{}}}"#,
+                    component.key(),
+                    value
+                        .as_ref()
+                        .map_or("null".to_string(), |value| format!("{value:.4}"))
+                )
+            })
+            .collect();
+        format!(
+            r#"{{"score":{},"components":[{}]}}"#,
+            self.score
+                .map_or("null".to_string(), |score| format!("{score:.1}")),
+            components.join(",")
+        )
+    }
+
+    /// Rows of the quality score table of the report.
+    pub fn rows(&self) -> Vec<QualityScoreRow> {
+        let total_weight: f64 = self
+            .components
+            .iter()
+            .filter(|(_, _, value)| value.is_ok())
+            .map(|(_, weight, _)| weight)
+            .sum();
+        self.components
+            .iter()
+            .map(|(component, weight, value)| QualityScoreRow {
+                component: component.description(),
+                weight: format!("{weight}"),
+                value: match value {
+                    Ok(value) => format!("{:.1} %", value * 100.0),
+                    Err(_) => "-".to_string(),
+                },
+                points: match value {
+                    Ok(value) if total_weight > 0.0 => {
+                        format!("{:.1}", 100.0 * weight * value / total_weight)
+                    }
+                    _ => "-".to_string(),
+                },
+                notes: match value {
+                    Ok(_) => String::new(),
+                    Err(reason) => reason.to_string(),
+                },
+            })
+            .collect()
+    }
+}
+
+fn component_value(
+    component: QualityComponent,
+    config: &QualityConfig,
+    inputs: &QualityInputs,
+) -> ComponentValue {
+    match component {
+        QualityComponent::ErrorFreeHbfs => {
+            let (hbfs, errored_hbfs) = inputs
+                .link_quality
+                .iter()
+                .fold((0, 0), |(hbfs, errored), link| {
+                    (hbfs + link.hbfs, errored + link.errored_hbfs)
+                });
+            if hbfs == 0 {
+                return Err("no HBFs checked");
+            }
+            Ok((hbfs - errored_hbfs) as f64 / hbfs as f64)
+        }
+        QualityComponent::LinkCompleteness => {
+            let expected_links = match &config.expected_links {
+                Some(expected_links) if !expected_links.is_empty() => expected_links,
+                _ => return Err("no expected links in the quality config"),
+            };
+            let most_hbfs = inputs.link_hbfs.iter().map(|(_, hbfs)| *hbfs).max();
+            let most_hbfs = match most_hbfs {
+                Some(most_hbfs) if most_hbfs > 0 => most_hbfs,
+                _ => return Err("no HBFs counted"),
+            };
+            let completeness_sum: f64 = expected_links
+                .iter()
+                .map(|expected| {
+                    inputs
+                        .link_hbfs
+                        .iter()
+                        .find(|(link_id, _)| link_id == expected)
+                        .map_or(0.0, |(_, hbfs)| *hbfs as f64 / most_hbfs as f64)
+                })
+                .sum();
+            Ok(completeness_sum / expected_links.len() as f64)
+        }
+        QualityComponent::FatalLanes => {
+            let (lanes, fatal_lanes) = inputs
+                .link_quality
+                .iter()
+                .fold((0, 0), |(lanes, fatal), link| {
+                    (lanes + link.lanes as u64, fatal + link.fatal_lanes as u64)
+                });
+            if lanes == 0 {
+                return Err("no DDW0 checked");
+            }
+            Ok(1.0 - fatal_lanes as f64 / lanes as f64)
+        }
+    }
+}
+
+/// A row of the quality score table of the report.
+#[derive(Tabled)]
+pub struct QualityScoreRow {
+    #[tabled(rename = "Component")]
+    component: &'static str,
+    #[tabled(rename = "Weight")]
+    weight: String,
+    #[tabled(rename = "Value")]
+    value: String,
+    #[tabled(rename = "Points")]
+    points: String,
+    #[tabled(rename = "Notes")]
+    notes: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::status_words::Ddw0;
+
+    fn link(link_id: u8, hbfs: u64, errored_hbfs: u64) -> LinkQualityCounts {
+        LinkQualityCounts {
+            link_id,
+            hbfs,
+            errored_hbfs,
+            lanes: Ddw0::LANES,
+            fatal_lanes: 0,
+        }
+    }
+
+    fn value(score: &QualityScore, component: QualityComponent) -> ComponentValue {
+        score.components[component.idx()].2
+    }
+
+    #[test]
+    fn test_all_components() {
+        let mut config = QualityConfig::parse("expected_links = 0, 2, 5").unwrap();
+        config.warn_below = Some(90.0);
+        let mut link_0 = link(0, 90, 9);
+        link_0.fatal_lanes = 7;
+        let inputs = QualityInputs {
+            link_quality: vec![link_0, link(2, 10, 1)],
+            link_hbfs: vec![(0, 100), (2, 50)],
+        };
+        let score = QualityScore::compute(&config, &inputs);
+        assert_eq!(value(&score, QualityComponent::ErrorFreeHbfs), Ok(0.9));
+        // Link 5 is missing
+        assert_eq!(value(&score, QualityComponent::LinkCompleteness), Ok(0.5));
+        assert_eq!(value(&score, QualityComponent::FatalLanes), Ok(0.875));
+        // (0.4 * 0.9 + 0.2 * 0.5 + 0.2 * 0.875) / 0.8
+        let expected = 100.0 * (0.36 + 0.1 + 0.175) / 0.8;
+        assert!((score.score.unwrap() - expected).abs() < 1e-9);
+        assert_eq!(score.components_used(), 3);
+        assert_eq!(
+            score.warning().unwrap(),
+            "Quality score 79.4 is below the threshold of 90.0"
+        );
+        assert_eq!(
+            score.to_json(),
+            r#"{"score":79.4,"components":[{"component":"error_free_hbfs","weight":0.4,"value":0.9000},{"component":"link_completeness","weight":0.2,"value":0.5000},{"component":"fatal_lanes","weight":0.2,"value":0.8750}]}"#
+        );
+    }
+
+    #[test]
+    fn test_missing_components_are_left_out() {
+        // No expected links and no DDW0, the score is only computed from the error-free HBFs
+        let mut link_0 = link(0, 10, 2);
+        link_0.lanes = 0;
+        let inputs = QualityInputs {
+            link_quality: vec![link_0],
+            link_hbfs: vec![(0, 10)],
+        };
+        let score = QualityScore::compute(&QualityConfig::default(), &inputs);
+        assert_eq!(
+            value(&score, QualityComponent::LinkCompleteness),
+            Err("no expected links in the quality config")
+        );
+        assert_eq!(
+            value(&score, QualityComponent::FatalLanes),
+            Err("no DDW0 checked")
+        );
+        assert_eq!(score.components_used(), 1);
+        assert!((score.score.unwrap() - 80.0).abs() < 1e-9);
+        assert!(score
+            .to_json()
+            .contains(r#"{"component":"fatal_lanes","weight":0.2,"value":null}"#));
+        let rows = score.rows();
+        assert_eq!(rows[2].value, "-");
+        assert_eq!(rows[2].notes, "no DDW0 checked");
+        assert_eq!(rows[0].points, "80.0");
+    }
+
+    #[test]
+    fn test_zero_hbfs() {
+        let config = QualityConfig::parse("expected_links = 0").unwrap();
+        let inputs = QualityInputs {
+            link_quality: vec![link(0, 0, 0)],
+            link_hbfs: vec![(0, 0)],
+        };
+        let score = QualityScore::compute(&config, &inputs);
+        assert_eq!(
+            value(&score, QualityComponent::ErrorFreeHbfs),
+            Err("no HBFs checked")
+        );
+        assert_eq!(
+            value(&score, QualityComponent::LinkCompleteness),
+            Err("no HBFs counted")
+        );
+        // Only the lanes are left
+        assert_eq!(score.score, Some(100.0));
+    }
+
+    #[test]
+    fn test_no_components() {
+        let score = QualityScore::compute(&QualityConfig::default(), &QualityInputs::default());
+        assert_eq!(score.score, None);
+        assert_eq!(score.components_used(), 0);
+        assert_eq!(score.warning(), None);
+        assert!(score.to_json().starts_with(r#"{"score":null,"#));
+        // Components with a weight of 0 don't count
+        let config =
+            QualityConfig::parse("weight.error_free_hbfs = 0\nweight.fatal_lanes = 0").unwrap();
+        let inputs = QualityInputs {
+            link_quality: vec![link(0, 10, 10)],
+            link_hbfs: vec![(0, 10)],
+        };
+        assert_eq!(QualityScore::compute(&config, &inputs).score, None);
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = QualityConfig::parse(
+            "# Weights\n\nweight.error_free_hbfs = 1\n weight.link_completeness=0.5 \nwarn_below = 75\nexpected_links = 3,4",
+        )
+        .unwrap();
+        assert_eq!(config.weight(QualityComponent::ErrorFreeHbfs), 1.0);
+        assert_eq!(config.weight(QualityComponent::LinkCompleteness), 0.5);
+        assert_eq!(config.weight(QualityComponent::FatalLanes), 0.2);
+        assert_eq!(config.warn_below, Some(75.0));
+        assert_eq!(config.expected_links, Some(vec![3, 4]));
+        for (content, expected_err) in [
+            ("weight.lanes = 1", "line 1: unknown key `weight.lanes`"),
+            (
+                "\nweight.fatal_lanes = -1",
+                "line 2: `weight.fatal_lanes` must be a non-negative number",
+            ),
+            ("warn_below 90", "line 1: expected `key = value`"),
+            ("warn_below = 101", "line 1: `warn_below` must be 0 to 100"),
+            (
+                "expected_links = 1, 256",
+                "line 1: invalid link ID: number too large to fit in target type",
+            ),
+        ] {
+            assert_eq!(QualityConfig::parse(content).unwrap_err(), expected_err);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Contains the [ReorderBuffer] used by the [StatsController][super::stats_controller::StatsController] to release error messages strictly in memory position order.
+//!
+//! Link validators run in parallel, so the order in which their errors arrive varies between runs.
+//! The [ReorderBuffer] holds back errors until all lower memory positions are accounted for, using per-link watermarks:
+//! * The analysis thread reports each CDP it dispatches to a link validator, along with the memory position of the next CDP.
+//! * The link validator reports the memory position of each CDP it has fully processed.
+//!
+//! An error at memory position `p` can be released once no link has a pending CDP at a position `<= p`, and the dispatched data extends beyond `p`.
+use std::collections::{BTreeMap, VecDeque};
+
+/// Buffers error messages and releases them in memory position order.
+///
+/// The messages can carry more than the text of the error, such as the link it was reported from, as long as they can be read as text.
+pub struct ReorderBuffer<M = String> {
+    /// Errors waiting to be released, keyed by memory position.
+    buffered: BTreeMap<u64, Vec<M>>,
+    /// Memory positions of CDPs dispatched but not yet processed, per link.
+    pending: Vec<(u8, VecDeque<u64>)>,
+    /// Memory position of the first CDP that has not been dispatched yet.
+    dispatch_end: u64,
+}
+
+impl<M> Default for ReorderBuffer<M> {
+    fn default() -> Self {
+        Self {
+            buffered: BTreeMap::new(),
+            pending: Vec::new(),
+            dispatch_end: 0,
+        }
+    }
+}
+
+impl<M: AsRef<str> + Ord> ReorderBuffer<M> {
+    /// Record a CDP at `mem_pos` dispatched to the validator of `link_id`, where `next_mem_pos` is the memory position of the following CDP.
+    pub fn dispatched(&mut self, link_id: u8, mem_pos: u64, next_mem_pos: u64) {
+        match self.pending.iter_mut().find(|(link, _)| *link == link_id) {
+            Some((_, queue)) => queue.push_back(mem_pos),
+            None => self.pending.push((link_id, VecDeque::from([mem_pos]))),
+        }
+        self.dispatch_end = self.dispatch_end.max(next_mem_pos);
+    }
+
+    /// Record that the validator of `link_id` has fully processed the CDP at `mem_pos`.
+    pub fn progress(&mut self, link_id: u8, mem_pos: u64) {
+        if let Some((_, queue)) = self.pending.iter_mut().find(|(link, _)| *link == link_id) {
+            debug_assert_eq!(queue.front(), Some(&mem_pos));
+            queue.pop_front();
+        }
+    }
+
+    /// Add an error message to the buffer.
+    ///
+    /// The memory position is read from the `0x<hex>:` prefix of the message.
+    /// Messages without a memory position are held back until [flush][ReorderBuffer::flush] is called.
+    pub fn push(&mut self, msg: M) {
+        let mem_pos = mem_pos_from_error_msg(msg.as_ref()).unwrap_or(u64::MAX);
+        self.buffered.entry(mem_pos).or_default().push(msg);
+    }
+
+    /// Returns all error messages that can no longer be preceded by another error, in memory position order.
+    pub fn release(&mut self) -> Vec<M> {
+        let lowest_pending = self
+            .pending
+            .iter()
+            .filter_map(|(_, queue)| queue.front())
+            .min()
+            .copied()
+            .unwrap_or(u64::MAX);
+        let watermark = lowest_pending.min(self.dispatch_end);
+        // Everything at or above the watermark stays in the buffer
+        let held_back = self.buffered.split_off(&watermark);
+        let released = std::mem::replace(&mut self.buffered, held_back);
+        Self::into_sorted_msgs(released)
+    }
+
+    /// Returns all remaining error messages in memory position order, regardless of watermarks.
+    ///
+    /// Should only be called once all data has been processed.
+    pub fn flush(&mut self) -> Vec<M> {
+        Self::into_sorted_msgs(std::mem::take(&mut self.buffered))
+    }
+
+    fn into_sorted_msgs(buffered: BTreeMap<u64, Vec<M>>) -> Vec<M> {
+        buffered
+            .into_values()
+            .flat_map(|mut msgs| {
+                // Errors at the same position can arrive from different threads, sort them to be deterministic
+                msgs.sort();
+                msgs
+            })
+            .collect()
+    }
+}
+
+/// Extracts the memory position from an error message formatted as `0x<hex>: <message>`.
+pub(crate) fn mem_pos_from_error_msg(msg: &str) -> Option<u64> {
+    let (mem_pos, _) = msg.split_once(':')?;
+    u64::from_str_radix(mem_pos.strip_prefix("0x")?, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mem_pos_from_error_msg() {
+        assert_eq!(
+            mem_pos_from_error_msg("0x40: [E30] ID is not 0xE0"),
+            Some(0x40)
+        );
+        assert_eq!(
+            mem_pos_from_error_msg("0xE450FFD: [E10] RDH"),
+            Some(0xE450FFD)
+        );
+        assert_eq!(mem_pos_from_error_msg("End of payload 0xFF padding"), None);
+    }
+
+    #[test]
+    fn test_release_waits_for_pending_links() {
+        let mut buffer = ReorderBuffer::default();
+        buffer.dispatched(0, 0x0, 0x100);
+        buffer.dispatched(1, 0x100, 0x200);
+        // Link 1 finishes first and reports an error
+        buffer.push("0x140: error link 1".to_string());
+        buffer.progress(1, 0x100);
+        assert!(buffer.release().is_empty());
+        // Link 0 finishes later with an error at a lower position
+        buffer.push("0x40: error link 0".to_string());
+        buffer.progress(0, 0x0);
+        assert_eq!(
+            buffer.release(),
+            vec!["0x40: error link 0", "0x140: error link 1"]
+        );
+    }
+
+    #[test]
+    fn test_release_bounded_by_dispatched_data() {
+        let mut buffer = ReorderBuffer::default();
+        buffer.dispatched(0, 0x0, 0x100);
+        buffer.progress(0, 0x0);
+        // An error ahead of the dispatched data (e.g. from the input scanner) is held back
+        buffer.push("0x180: scanner error".to_string());
+        assert!(buffer.release().is_empty());
+        buffer.dispatched(1, 0x100, 0x200);
+        buffer.push("0x120: error link 1".to_string());
+        buffer.progress(1, 0x100);
+        assert_eq!(
+            buffer.release(),
+            vec!["0x120: error link 1", "0x180: scanner error"]
+        );
+    }
+
+    #[test]
+    fn test_flush_releases_unpositioned_last() {
+        let mut buffer = ReorderBuffer::default();
+        buffer.dispatched(0, 0x0, 0x100);
+        buffer.push("no position".to_string());
+        buffer.push("0x50: error".to_string());
+        assert!(buffer.release().is_empty());
+        assert_eq!(buffer.flush(), vec!["0x50: error", "no position"]);
+    }
+
+    #[test]
+    fn test_delayed_workers_released_in_order() {
+        enum Msg {
+            Error(String),
+            Progress(u8, u64),
+        }
+        let mut buffer = ReorderBuffer::default();
+        let (send, recv) = std::sync::mpsc::channel();
+        let mut handles = Vec::new();
+        // Each worker owns every 3rd CDP, later links are faster than earlier ones
+        for link in 0..3_u8 {
+            let positions: Vec<u64> = (0..10_u64)
+                .filter(|cdp| cdp % 3 == link as u64)
+                .map(|cdp| cdp * 0x100)
+                .collect();
+            positions
+                .iter()
+                .for_each(|pos| buffer.dispatched(link, *pos, pos + 0x100));
+            let send = send.clone();
+            handles.push(std::thread::spawn(move || {
+                for pos in positions {
+                    std::thread::sleep(std::time::Duration::from_millis(3 - link as u64));
+                    send.send(Msg::Error(format!("{:#X}: error", pos + 0x40)))
+                        .unwrap();
+                    send.send(Msg::Progress(link, pos)).unwrap();
+                }
+            }));
+        }
+        drop(send);
+        let mut released = Vec::new();
+        for msg in recv {
+            match msg {
+                Msg::Error(err) => buffer.push(err),
+                Msg::Progress(link, pos) => buffer.progress(link, pos),
+            }
+            released.extend(buffer.release());
+        }
+        handles.into_iter().for_each(|h| h.join().unwrap());
+        released.extend(buffer.flush());
+
+        let expected: Vec<String> = (0..10_u64)
+            .map(|cdp| format!("{:#X}: error", cdp * 0x100 + 0x40))
+            .collect();
+        assert_eq!(released, expected);
+    }
+}
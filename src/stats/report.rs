@@ -37,6 +37,126 @@ impl std::default::Default for StatSummary {
         }
     }
 }
+/// Describes the columns of the trigger stats table, counting trigger bit violations and missing HBFs per link
+#[derive(Tabled, Clone)]
+pub struct TriggerStats {
+    pub link: u8,
+    #[tabled(rename = "HBFs missing HB")]
+    pub missing_hb: u32,
+    #[tabled(rename = "SOC/SOT not opening HBF")]
+    pub illegal_soc_sot: u32,
+    #[tabled(rename = "Missing HBFs (skipped orbits)")]
+    pub missing_hbfs: u32,
+}
+
+impl TriggerStats {
+    pub fn new(link: u8) -> Self {
+        Self {
+            link,
+            missing_hb: 0,
+            illegal_soc_sot: 0,
+            missing_hbfs: 0,
+        }
+    }
+}
+
+/// Describes the columns of the link health table, counting pages without information, DDW0 transmission timeouts and pages opening and closing HBFs per link, and the error-free stretches of the link
+#[derive(Tabled, Clone)]
+pub struct LinkHealth {
+    pub link: u8,
+    #[tabled(rename = "Pages with only padding/idle")]
+    pub idle_pages: u32,
+    #[tabled(rename = "DDW0 transmission timeouts")]
+    pub ddw0_transmission_timeouts: u32,
+    #[tabled(inline)]
+    pub page_counts: crate::stats::page_counts::PageCounts,
+    #[tabled(
+        rename = "Avg pages per HBF",
+        display_with = "display_avg_pages_per_hbf"
+    )]
+    pub avg_pages_per_hbf: Option<f64>,
+    #[tabled(inline)]
+    pub error_free_stretch: crate::stats::error_free_stretch::ErrorFreeStretch,
+}
+
+impl LinkHealth {
+    pub fn new(link: u8) -> Self {
+        Self {
+            link,
+            idle_pages: 0,
+            ddw0_transmission_timeouts: 0,
+            page_counts: crate::stats::page_counts::PageCounts::default(),
+            avg_pages_per_hbf: None,
+            error_free_stretch: crate::stats::error_free_stretch::ErrorFreeStretch::default(),
+        }
+    }
+    pub fn add_page_counts(&mut self, page_counts: &crate::stats::page_counts::PageCounts) {
+        self.page_counts.merge(page_counts);
+        self.avg_pages_per_hbf = self.page_counts.avg_pages_per_hbf();
+    }
+}
+
+fn display_avg_pages_per_hbf(avg_pages_per_hbf: &Option<f64>) -> String {
+    match avg_pages_per_hbf {
+        Some(avg) => format!("{avg:.2}"),
+        None => "-".to_string(),
+    }
+}
+/// Formats a size in bytes as B, KiB, MiB or GiB
+pub(crate) fn format_byte_size(size: &u64) -> String {
+    match *size {
+        0..=1024 => format!("{size} B"),
+        1025..=1048576 => format!("{:.3} KiB", *size as f64 / 1024_f64),
+        1048577..=1073741824 => format!("{:.3} MiB", *size as f64 / 1048576_f64),
+        _ => format!("{:.3} GiB", *size as f64 / 1073741824_f64),
+    }
+}
+/// Describes the columns of the TDT timeout stats table, counting the timeout flags set in TDTs per stave
+#[derive(Tabled, Clone)]
+pub struct TdtTimeoutStats {
+    pub layer: u8,
+    pub stave: u8,
+    #[tabled(rename = "timeout_to_start")]
+    pub to_start: u32,
+    #[tabled(rename = "timeout_start_stop")]
+    pub start_stop: u32,
+    #[tabled(rename = "timeout_in_idle")]
+    pub in_idle: u32,
+}
+
+impl TdtTimeoutStats {
+    pub fn new(layer: u8, stave: u8) -> Self {
+        Self {
+            layer,
+            stave,
+            to_start: 0,
+            start_stop: 0,
+            in_idle: 0,
+        }
+    }
+    pub fn increment(&mut self, timeout: crate::words::status_words::TdtTimeout) {
+        use crate::words::status_words::TdtTimeout;
+        match timeout {
+            TdtTimeout::ToStart => self.to_start += 1,
+            TdtTimeout::StartStop => self.start_stop += 1,
+            TdtTimeout::InIdle => self.in_idle += 1,
+        }
+    }
+}
+/// Describes the columns of the first error occurrences table
+#[derive(Tabled)]
+pub struct FirstErrorOccurrence {
+    pub code: String,
+    #[tabled(rename = "first occurrence")]
+    pub first_occurrence: String,
+}
+/// Describes the columns of the checks executed table, one row per known check
+#[derive(Tabled)]
+pub struct CheckExecuted {
+    pub code: String,
+    pub check: String,
+    pub status: String,
+}
 /// Describes the columns of the detected attributes table
 #[derive(Tabled)]
 struct DetectedAttribute {
@@ -50,9 +170,22 @@ struct DetectedAttribute {
 pub struct Report {
     pub(crate) stats: Vec<StatSummary>,
     filter_stats_table: Option<Table>,
+    trigger_stats_table: Option<Table>,
+    link_health_table: Option<Table>,
+    tdt_timeout_stats_table: Option<Table>,
+    interleaving_table: Option<Table>,
+    throughput_table: Option<Table>,
+    entropy_table: Option<Table>,
+    payload_uniformity_table: Option<Table>,
+    quality_score_table: Option<Table>,
+    first_errors_table: Option<Table>,
+    checks_executed_table: Option<Table>,
     detected_attributes: Vec<DetectedAttribute>,
     processing_time: std::time::Duration,
     fatal_error: Option<String>,
+    warning: Option<String>,
+    run_metadata: Option<crate::util::run_metadata::RunMetadata>,
+    json_fields: Vec<(&'static str, String)>,
     report_table: Option<Table>,
 }
 impl Report {
@@ -62,13 +195,56 @@ impl Report {
             detected_attributes: Vec::new(),
             processing_time,
             filter_stats_table: None,
+            trigger_stats_table: None,
+            link_health_table: None,
+            tdt_timeout_stats_table: None,
+            interleaving_table: None,
+            throughput_table: None,
+            entropy_table: None,
+            payload_uniformity_table: None,
+            quality_score_table: None,
+            first_errors_table: None,
+            checks_executed_table: None,
             fatal_error: None,
+            warning: None,
+            run_metadata: None,
+            json_fields: Vec::new(),
             report_table: None,
         }
     }
     pub fn add_filter_stats(&mut self, filter_stats_table: Table) {
         self.filter_stats_table = Some(filter_stats_table);
     }
+    pub fn add_trigger_stats(&mut self, trigger_stats_table: Table) {
+        self.trigger_stats_table = Some(trigger_stats_table);
+    }
+    pub fn add_link_health(&mut self, link_health_table: Table) {
+        self.link_health_table = Some(link_health_table);
+    }
+    pub fn add_tdt_timeout_stats(&mut self, tdt_timeout_stats_table: Table) {
+        self.tdt_timeout_stats_table = Some(tdt_timeout_stats_table);
+    }
+    pub fn add_interleaving(&mut self, interleaving_table: Table) {
+        self.interleaving_table = Some(interleaving_table);
+    }
+    pub fn add_throughput(&mut self, throughput_table: Table) {
+        self.throughput_table = Some(throughput_table);
+    }
+    pub fn add_entropy(&mut self, entropy_table: Table) {
+        self.entropy_table = Some(entropy_table);
+    }
+    pub fn add_payload_uniformity(&mut self, payload_uniformity_table: Table) {
+        self.payload_uniformity_table = Some(payload_uniformity_table);
+    }
+    pub fn add_quality_score(&mut self, quality_score_table: Table) {
+        self.quality_score_table = Some(quality_score_table);
+    }
+    pub fn add_checks_executed(&mut self, checks_executed_table: Table) {
+        self.checks_executed_table = Some(checks_executed_table);
+    }
+    pub fn add_first_errors(&mut self, first_errors_table: Table) {
+        self.first_errors_table = Some(first_errors_table);
+    }
     pub fn add_stat(&mut self, stat: StatSummary) {
         self.stats.push(stat);
     }
@@ -81,7 +257,56 @@ impl Report {
     pub fn add_fatal_error(&mut self, error: String) {
         self.fatal_error = Some(error);
     }
+    /// Adds a warning shown at the top of the report, for problems that are not errors in the data, but likely mistakes in the command.
+    pub fn add_warning(&mut self, warning: String) {
+        self.warning = Some(warning);
+    }
+    /// Adds the [RunMetadata][crate::util::run_metadata::RunMetadata] that the report begins with.
+    pub fn add_run_metadata(&mut self, run_metadata: crate::util::run_metadata::RunMetadata) {
+        self.run_metadata = Some(run_metadata);
+    }
     pub fn print(&mut self) {
+        self.build_report_table();
+        if let Some(run_metadata) = &self.run_metadata {
+            eprintln!("{}", run_metadata.header_lines());
+        }
+        eprintln!("{}", self.report_table.as_ref().unwrap());
+    }
+    /// Adds a JSON value as the field `key` of the JSON report, e.g. the `detail` object.
+    pub fn add_json_field(&mut self, key: &'static str, value: String) {
+        self.json_fields.push((key, value));
+    }
+    /// Prints the report as a single JSON line, for when log messages are JSON lines
+    pub fn print_json_line(&mut self) {
+        self.build_report_table();
+        let report_table = self.report_table.as_ref().unwrap().to_string();
+        let line = match &self.run_metadata {
+            Some(run_metadata) => {
+                crate::util::json_logger::JsonLogger::format_message_with_metadata(
+                    log::Level::Info,
+                    &report_table,
+                    &run_metadata.to_json(),
+                )
+            }
+            None => crate::util::json_logger::JsonLogger::format_message(
+                log::Level::Info,
+                &report_table,
+            ),
+        };
+        let line = if self.json_fields.is_empty() {
+            line
+        } else {
+            // Insert the fields before the closing brace
+            let fields: String = self
+                .json_fields
+                .iter()
+                .map(|(key, value)| format!(",\"{key}\":{value}"))
+                .collect();
+            format!("{}{fields}}}", &line[..line.len() - 1])
+        };
+        eprintln!("{line}");
+    }
+    fn build_report_table(&mut self) {
         let mut global_stats_table = Table::new(&self.stats);
         global_stats_table = format_global_stats_sub_table(&global_stats_table);
         let mut detected_attributes_table = Table::new(&self.detected_attributes);
@@ -91,22 +316,102 @@ impl Report {
             SubtableColor::Yellow,
         );
 
-        if self.filter_stats_table.is_some() {
+        let attributes_row = if self.filter_stats_table.is_some() {
             let filter_stats_table = format_sub_table(
                 self.filter_stats_table.as_ref().unwrap(),
                 "Filter Stats".to_string(),
                 SubtableColor::Purple,
             );
-            let multi_table = tabled::col![
-                global_stats_table,
-                tabled::row![detected_attributes_table, filter_stats_table]
-            ];
-            self.report_table = Some(format_super_table(&multi_table, self.processing_time));
+            tabled::row![detected_attributes_table, filter_stats_table]
         } else {
-            let multi_table =
-                tabled::col![global_stats_table, tabled::row![detected_attributes_table]];
-            self.report_table = Some(format_super_table(&multi_table, self.processing_time));
+            tabled::row![detected_attributes_table]
+        };
+        // Stack the sub tables vertically, the first occurrences of errors goes on top
+        let mut multi_table_builder = tabled::builder::Builder::default();
+        if let Some(first_errors_table) = &self.first_errors_table {
+            let first_errors_table = format_sub_table(
+                first_errors_table,
+                "First Error Occurrences".to_string(),
+                SubtableColor::Red,
+            );
+            multi_table_builder.add_record([first_errors_table.to_string()]);
+        }
+        multi_table_builder.add_record([global_stats_table.to_string()]);
+        multi_table_builder.add_record([attributes_row.to_string()]);
+        if let Some(trigger_stats_table) = &self.trigger_stats_table {
+            let trigger_stats_table = format_sub_table(
+                trigger_stats_table,
+                "Trigger Stats".to_string(),
+                SubtableColor::Blue,
+            );
+            multi_table_builder.add_record([trigger_stats_table.to_string()]);
+        }
+        if let Some(link_health_table) = &self.link_health_table {
+            let link_health_table = format_sub_table(
+                link_health_table,
+                "Link Health".to_string(),
+                SubtableColor::Yellow,
+            );
+            multi_table_builder.add_record([link_health_table.to_string()]);
+        }
+        if let Some(tdt_timeout_stats_table) = &self.tdt_timeout_stats_table {
+            let tdt_timeout_stats_table = format_sub_table(
+                tdt_timeout_stats_table,
+                "TDT Timeouts".to_string(),
+                SubtableColor::Green,
+            );
+            multi_table_builder.add_record([tdt_timeout_stats_table.to_string()]);
+        }
+        if let Some(interleaving_table) = &self.interleaving_table {
+            let interleaving_table = format_sub_table(
+                interleaving_table,
+                "Interleaving".to_string(),
+                SubtableColor::Blue,
+            );
+            multi_table_builder.add_record([interleaving_table.to_string()]);
+        }
+        if let Some(throughput_table) = &self.throughput_table {
+            let throughput_table = format_sub_table(
+                throughput_table,
+                "Throughput".to_string(),
+                SubtableColor::Green,
+            );
+            multi_table_builder.add_record([throughput_table.to_string()]);
         }
+        if let Some(entropy_table) = &self.entropy_table {
+            let entropy_table = format_sub_table(
+                entropy_table,
+                "Payload Entropy".to_string(),
+                SubtableColor::Blue,
+            );
+            multi_table_builder.add_record([entropy_table.to_string()]);
+        }
+        if let Some(payload_uniformity_table) = &self.payload_uniformity_table {
+            let payload_uniformity_table = format_sub_table(
+                payload_uniformity_table,
+                "Payload Uniformity".to_string(),
+                SubtableColor::Blue,
+            );
+            multi_table_builder.add_record([payload_uniformity_table.to_string()]);
+        }
+        if let Some(quality_score_table) = &self.quality_score_table {
+            let quality_score_table = format_sub_table(
+                quality_score_table,
+                "Quality Score".to_string(),
+                SubtableColor::Green,
+            );
+            multi_table_builder.add_record([quality_score_table.to_string()]);
+        }
+        if let Some(checks_executed_table) = &self.checks_executed_table {
+            let checks_executed_table = format_sub_table(
+                checks_executed_table,
+                "Checks Executed".to_string(),
+                SubtableColor::Purple,
+            );
+            multi_table_builder.add_record([checks_executed_table.to_string()]);
+        }
+        let multi_table = multi_table_builder.build();
+        self.report_table = Some(format_super_table(&multi_table, self.processing_time));
         if self.fatal_error.is_some() {
             let mut error_table = self.report_table.clone().unwrap();
             error_table
@@ -121,7 +426,17 @@ impl Report {
                 );
             self.report_table = Some(error_table);
         }
-        eprintln!("{}", self.report_table.as_ref().unwrap());
+        if let Some(warning) = &self.warning {
+            let mut warning_table = self.report_table.clone().unwrap();
+            warning_table
+                .with(Panel::header(format!("WARNING: {warning}")))
+                .with(
+                    Modify::new(Rows::single(0))
+                        .with(Alignment::center())
+                        .with(Format::new(|x| x.yellow().bold().to_string())),
+                );
+            self.report_table = Some(warning_table);
+        }
     }
 }
 
@@ -290,6 +605,109 @@ mod tests {
         assert_stderr_contains!(report.print(), "725800");
     }
 
+    #[test]
+    fn test_summary_contains_trigger_stats() {
+        let processing_time = std::time::Instant::now();
+        let mut report = Report::new(processing_time.elapsed());
+        let mut trigger_stats = TriggerStats::new(3);
+        trigger_stats.missing_hb = 42;
+        trigger_stats.missing_hbfs = 1337;
+        report.add_trigger_stats(Table::new(vec![trigger_stats]));
+        assert_stderr_contains!(report.print(), "TRIGGER STATS");
+        assert_stderr_contains!(report.print(), "SOC/SOT not opening HBF");
+        assert_stderr_contains!(report.print(), "42");
+        assert_stderr_contains!(report.print(), "Missing HBFs (skipped orbits)");
+        assert_stderr_contains!(report.print(), "1337");
+    }
+
+    #[test]
+    fn test_summary_contains_link_health() {
+        let processing_time = std::time::Instant::now();
+        let mut report = Report::new(processing_time.elapsed());
+        let mut link_health = LinkHealth::new(3);
+        link_health.idle_pages = 7;
+        link_health.ddw0_transmission_timeouts = 2;
+        report.add_link_health(Table::new(vec![link_health]));
+        assert_stderr_contains!(report.print(), "LINK HEALTH");
+        assert_stderr_contains!(report.print(), "Pages with only padding/idle");
+        assert_stderr_contains!(report.print(), "DDW0 transmission timeouts");
+    }
+
+    #[test]
+    fn test_summary_contains_warning() {
+        let processing_time = std::time::Instant::now();
+        let mut report = Report::new(processing_time.elapsed());
+        report.add_warning("Filter kept none of the 18 CDPs of the input!".to_string());
+        assert_stderr_contains!(
+            report.print(),
+            "WARNING: Filter kept none of the 18 CDPs of the input!"
+        );
+        assert_stderr_contains!(
+            report.print_json_line(),
+            "WARNING: Filter kept none of the 18 CDPs of the input!"
+        );
+    }
+
+    #[test]
+    fn test_summary_contains_tdt_timeout_stats() {
+        use crate::words::status_words::TdtTimeout;
+        let processing_time = std::time::Instant::now();
+        let mut report = Report::new(processing_time.elapsed());
+        let mut tdt_timeout_stats = TdtTimeoutStats::new(0, 17);
+        tdt_timeout_stats.increment(TdtTimeout::InIdle);
+        tdt_timeout_stats.increment(TdtTimeout::InIdle);
+        tdt_timeout_stats.increment(TdtTimeout::ToStart);
+        assert_eq!(tdt_timeout_stats.to_start, 1);
+        assert_eq!(tdt_timeout_stats.start_stop, 0);
+        assert_eq!(tdt_timeout_stats.in_idle, 2);
+        report.add_tdt_timeout_stats(Table::new(vec![tdt_timeout_stats]));
+        assert_stderr_contains!(report.print(), "TDT TIMEOUTS");
+        assert_stderr_contains!(report.print(), "timeout_in_idle");
+    }
+
+    #[test]
+    fn test_summary_as_json_line() {
+        let processing_time = std::time::Instant::now();
+        let mut report = Report::new(processing_time.elapsed());
+        report.add_stat(StatSummary::new(
+            "Total RDHs".to_string(),
+            "725800".to_string(),
+            None,
+        ));
+        assert_stderr_contains!(report.print_json_line(), "\"level\":\"INFO\",\"msg\":\"");
+        assert_stderr_contains!(report.print_json_line(), "725800");
+        // The multi-line table is escaped into a single line
+        let table = report.report_table.as_ref().unwrap().to_string();
+        assert!(table.lines().count() > 1);
+    }
+
+    #[test]
+    fn test_first_errors_printed_before_global_stats() {
+        use gag::BufferRedirect;
+        use std::io::Read;
+        let processing_time = std::time::Instant::now();
+        let mut report = Report::new(processing_time.elapsed());
+        report.add_stat(StatSummary::new(
+            "Total RDHs".to_string(),
+            "725800".to_string(),
+            None,
+        ));
+        report.add_first_errors(Table::new(vec![FirstErrorOccurrence {
+            code: "E30".to_string(),
+            first_occurrence: "0x40: [E30] ID is not 0xE0: 0xE1".to_string(),
+        }]));
+
+        let mut buf = BufferRedirect::stderr().unwrap();
+        report.print();
+        let mut output = String::new();
+        buf.read_to_string(&mut output).unwrap();
+        drop(buf);
+
+        let first_errors_pos = output.find("0x40: [E30] ID is not 0xE0: 0xE1").unwrap();
+        let global_stats_pos = output.find("Total RDHs").unwrap();
+        assert!(first_errors_pos < global_stats_pos);
+    }
+
     #[test]
     fn test_fatal_error_report() {
         let processing_time = std::time::Instant::now();
@@ -3,13 +3,44 @@
 //! Finally when the event loop breaks (at the end of execution), it will print a summary of the stats collected, using the Report struct.
 
 use crate::{
-    stats::report::{Report, StatSummary},
-    util::lib::Config,
+    stats::{
+        accumulators::{add_link_count, Accumulators},
+        cdp_detail::{cdp_details_to_json, CdpDetail},
+        error_csv::{ErrorCsv, ErrorCsvRow, Severity},
+        error_free_stretch::ErrorPositions,
+        error_log::ErrorLogWriter,
+        final_hbf::FinalHbf,
+        first_errors::FirstErrors,
+        interleaving::{InterleavingRow, LinkInterleaving},
+        link_activity::{silent_links, LinkActivity, SilentLink},
+        link_error_mute::{LinkErrorAction, LinkErrorMute},
+        link_throughput::{ThroughputCounters, ThroughputRow},
+        memory_usage::MemoryUsage,
+        page_counts::PageCounts,
+        payload_entropy::{merge_histograms, EntropyRow, LinkByteHistogram},
+        payload_uniformity::{merge_uniformity, LinkPayloadUniformity, PayloadUniformityRow},
+        quality_score::{LinkQualityCounts, QualityConfig, QualityInputs, QualityScore},
+        reorder_buffer::ReorderBuffer,
+        report::{
+            format_byte_size, CheckExecuted, FirstErrorOccurrence, LinkHealth, Report, StatSummary,
+            TdtTimeoutStats, TriggerStats,
+        },
+        timeframes::TfSummary,
+    },
+    util::{command_hook::CommandHooks, config::ItsStave, lib::Config},
+    validators::{
+        check_registry::{CheckEvaluations, KnownCheck},
+        hw_map::HwMapSummary,
+    },
+    words::{rdh::TriggerType, status_words::TdtTimeout},
 };
 use log::error;
-use std::sync::{
-    atomic::{AtomicBool, AtomicU32},
-    Arc,
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU32},
+        Arc,
+    },
 };
 
 /// Possible stats that can be sent to the StatsController.
@@ -18,6 +49,17 @@ pub enum StatType {
     Fatal(String),
     /// Non-fatal error, reported but processing continues.
     Error(String),
+    /// Warning, logged as a warning, or reported as an error in strict mode.
+    Warning(String),
+    /// Violation of an internal invariant of fastpasta, a bug rather than an error in the data, reported as an error.
+    InternalError(String),
+    /// Non-fatal error attributed to a link, reported as [StatType::Error] until the link is auto-muted.
+    LinkError {
+        /// The link ID.
+        link_id: u8,
+        /// The error message.
+        msg: String,
+    },
     /// Increment the total RDHs seen.
     RDHsSeen(u8),
     /// Increment the total RDHs filtered.
@@ -26,6 +68,15 @@ pub enum StatType {
     PayloadSize(u32),
     /// Add a link to the list of links observed.
     LinksObserved(u8),
+    /// Add a FEE ID to the list of FEE IDs observed, with the link it was first seen on.
+    FeeIdObserved {
+        /// The FEE ID.
+        fee_id: u16,
+        /// The link ID of the first RDH with the FEE ID.
+        link_id: u8,
+    },
+    /// Add a CRU ID to the list of CRU IDs observed.
+    CruIdObserved(u16),
     /// Record the RDH version detected.
     RdhVersion(u8),
     /// Record the data format detected.
@@ -39,6 +90,112 @@ pub enum StatType {
         /// The stave number.
         stave: u8,
     },
+    /// Counters accumulated locally by the sender, e.g. for a whole [CdpChunk][crate::input::data_wrapper::CdpChunk].
+    Accumulated(Accumulators),
+    /// Record a page opening an HBF without the HB trigger bit set, on the given link.
+    MissingHbTrigger(u8),
+    /// Record a page not opening an HBF with the SOC or SOT trigger bit set, on the given link.
+    IllegalSocSotTrigger(u8),
+    /// Record HBFs missing between consecutive HBFs of a link, detected by the orbit skipping more than 1.
+    MissingHbfs {
+        /// The link ID.
+        link_id: u8,
+        /// The number of missing HBFs.
+        count: u32,
+    },
+    /// Record a page with a payload of only padding or idle words, on the given link.
+    IdlePage(u8),
+    /// Record the pages of a link, counted for a whole [CdpChunk][crate::input::data_wrapper::CdpChunk].
+    LinkPages {
+        /// The link ID.
+        link_id: u8,
+        /// The pages counted.
+        page_counts: PageCounts,
+    },
+    /// Record a DDW0 with the transmission_timeout flag set, on the given link.
+    Ddw0TransmissionTimeout(u8),
+    /// Record a timeout flag set in a TDT, on the given layer/stave.
+    TdtTimeout {
+        /// The layer number.
+        layer: u8,
+        /// The stave number.
+        stave: u8,
+        /// The timeout flag that was set.
+        timeout: TdtTimeout,
+    },
+    /// The last HBF of a link has no stop page at the end of the input.
+    UnclosedHbf {
+        /// The link ID.
+        link_id: u8,
+        /// The last HBF of the link.
+        hbf: FinalHbf,
+    },
+    /// The last page of each link, sent when the whole input was processed.
+    LinkActivity(Box<Vec<LinkActivity>>),
+    /// Details of CDPs for the detail section of the JSON report, sent in batches by the link validators.
+    CdpDetails(Box<Vec<CdpDetail>>),
+    /// Number of timeframes seen on all links, sent when the whole input was processed.
+    Timeframes(TfSummary),
+    /// Number of combinations of FEE ID, CRU ID, link ID and endpoint verified against the hardware map, sent when the analysis finishes.
+    HwMap(HwMapSummary),
+    /// The payload of the last CDP of the input is truncated by the end of the input.
+    TruncatedPayload {
+        /// The link ID of the CDP.
+        link_id: u8,
+        /// Memory position of the RDH of the CDP.
+        mem_pos: u64,
+        /// Payload size from the RDH.
+        payload_size: usize,
+        /// Bytes of the payload missing from the input.
+        missing_bytes: usize,
+    },
+    /// Identifies the run, shown at the top of the report.
+    RunMetadata(Box<crate::util::run_metadata::RunMetadata>),
+    /// Largest gaps between consecutive pages of the same HBF of each link, sent by the input scanner when they change.
+    Interleaving(Box<Vec<LinkInterleaving>>),
+    /// Bytes and orbits with data of each link, sent when the whole input was processed.
+    Throughput(Box<ThroughputCounters>),
+    /// The output written and dropped, sent by the writer when it finishes after a write failed with `--keep-going-on-write-error`.
+    WriteFailure(crate::write::writer::WriteSummary),
+    /// The CDPs that failed the checks written with `--save-failing-cdps`, sent when all link validators finished.
+    FailingCdps(Box<crate::write::failing_cdps::FailingCdps>),
+    /// The CDPs with APE words kept with `--filter-ape` and the APE words of each type, sent by the writer when it finishes.
+    ApeWords(Box<crate::write::ape_filter::ApeWordCounts>),
+    /// The HBFs without data words dropped from the output with `--drop-empty-hbfs`, sent by the writer when it finishes.
+    EmptyHbfsDropped(crate::write::empty_hbfs::DroppedEmptyHbfs),
+    /// The most bytes of CDP chunks in flight between the reader and its consumer at once, sent when the pipeline is done if the memory usage is tracked.
+    PeakInFlightBytes(u64),
+    /// The CDPs handled by the reader, the analysis or the writer, sent by each when it finishes.
+    PipelineCounts(crate::stats::pipeline_counts::ComponentCounts),
+    /// Number of evaluations of each check done by a link validator, sent when it finishes.
+    CheckEvaluations(Box<CheckEvaluations>),
+    /// HBFs and lanes checked by a link validator for the quality score, sent when it finishes.
+    LinkQuality(LinkQualityCounts),
+    /// The payload checks of the CDPs of a link were skipped, as their RDH system ID is not the one of the check target, sent when the link validator finishes.
+    OtherSystemLink {
+        /// The link ID.
+        link_id: u8,
+        /// System ID of the first CDP of the link with another system ID than the check target.
+        system_id: u8,
+        /// Number of CDPs whose payload checks were skipped.
+        cdps: u64,
+    },
+    /// A CDP was dispatched to a link validator, only sent if errors are reported in order.
+    CdpDispatched {
+        /// The link ID of the link validator.
+        link_id: u8,
+        /// Memory position of the RDH of the CDP.
+        mem_pos: u64,
+        /// Memory position of the RDH of the next CDP.
+        next_mem_pos: u64,
+    },
+    /// A link validator finished processing a CDP, only sent if errors are reported in order.
+    LinkProgress {
+        /// The link ID of the link validator.
+        link_id: u8,
+        /// Memory position of the RDH of the CDP.
+        mem_pos: u64,
+    },
 }
 
 /// The StatsController receives stats and builds a summary report that is printed at the end of execution.
@@ -47,25 +204,110 @@ pub struct StatsController {
     pub rdhs_seen: u64,
     /// Total RDHs filtered.
     pub rdhs_filtered: u64,
+    /// RDHs filtered of each link, by link ID.
+    pub rdhs_filtered_per_link: Vec<(u8, u64)>,
+    /// RDHs excluded with `--exclude-link` of each link, by link ID.
+    pub rdhs_excluded_per_link: Vec<(u8, u64)>,
     /// Total payload size.
     pub payload_size: u64,
+    /// Total bytes of the input scanned.
+    pub input_size: u64,
     /// Links observed.
     pub links_observed: Vec<u8>,
+    /// FEE IDs observed.
+    pub fee_ids_observed: Vec<u16>,
+    /// CRU IDs observed.
+    pub cru_ids_observed: Vec<u16>,
     /// Time from [StatsController] is instantiated, to all data processing threads disconnected their [StatType] producer channel.
     pub processing_time: std::time::Instant,
     total_errors: AtomicU32,
     non_atomic_total_errors: u64,
     max_tolerate_errors: u32,
+    fail_fast: bool,
     recv_stats_channel: std::sync::mpsc::Receiver<StatType>,
     end_processing_flag: Arc<AtomicBool>,
-    link_to_filter: Option<u8>,
+    links_to_filter: Option<Vec<u8>>,
+    links_to_exclude: Option<Vec<u8>>,
+    fee_id_to_filter: Option<u16>,
+    cru_id_to_filter: Option<u16>,
+    its_stave_to_filter: Option<ItsStave>,
+    trigger_to_filter: Option<TriggerType>,
+    packet_range_to_filter: Option<crate::util::config::PacketRange>,
+    filter_enabled: bool,
+    filter_warning: Option<String>,
+    strict: bool,
+    expect_data_format: Option<u8>,
+    internal_errors: u32,
+    run_metadata: Option<crate::util::run_metadata::RunMetadata>,
+    interleaving: Vec<LinkInterleaving>,
+    throughput: Option<ThroughputCounters>,
+    /// Histograms of the sampled payload bytes of each link, empty unless the entropy is analyzed.
+    byte_histograms: Vec<LinkByteHistogram>,
+    /// Counts of the distinct payloads of each link, empty unless the payload uniformity is analyzed.
+    payload_uniformity: Vec<LinkPayloadUniformity>,
+    write_failure: Option<crate::write::writer::WriteSummary>,
+    failing_cdps: Option<crate::write::failing_cdps::FailingCdps>,
+    empty_hbfs_dropped: Option<crate::write::empty_hbfs::DroppedEmptyHbfs>,
+    ape_words: Option<crate::write::ape_filter::ApeWordCounts>,
+    /// Peaks of the memory usage, [None] unless the memory usage is tracked.
+    memory_usage: Option<MemoryUsage>,
+    /// CDPs handled by each component of the pipeline, reconciled when all are done.
+    pipeline_counts: crate::stats::pipeline_counts::PipelineCounts,
+    silent_link_orbits: u32,
+    silent_links: Vec<SilentLink>,
+    /// Link ID, system ID and number of CDPs of the links whose payload checks were skipped as they carry another system than the check target.
+    other_system_links: Vec<(u8, u8, u64)>,
+    timeframes: Option<TfSummary>,
+    hw_map: Option<HwMapSummary>,
+    /// Details of the CDPs for the JSON report, [None] if the report has no CDP detail.
+    cdp_details: Option<Vec<CdpDetail>>,
+    report_detail_max_cdps: u64,
+    force: bool,
     rdh_version: u8,
     data_formats_observed: Vec<u8>,
     hbfs_seen: u32,
     fatal_error: Option<String>,
     layers_staves_seen: Vec<(u8, u8)>,
     view_active: bool,
+    log_format_json: bool,
+    reorder_buffer: Option<ReorderBuffer<ReportedError>>,
+    trigger_stats: Vec<TriggerStats>,
+    link_health: Vec<LinkHealth>,
+    tdt_timeout_stats: Vec<TdtTimeoutStats>,
+    first_errors: FirstErrors,
+    link_error_mute: Option<LinkErrorMute>,
+    error_positions: ErrorPositions,
+    link_error_positions: HashMap<u8, ErrorPositions>,
+    check_evaluations: CheckEvaluations,
+    /// Reasons the checks are skipped, [None] if no checks are enabled and the checks executed are not reported.
+    check_skip_reasons: Option<Vec<Option<&'static str>>>,
+    muted_checks: Vec<KnownCheck>,
+    /// The link each FEE ID was first seen on.
+    link_fee_ids: Vec<(u8, u16)>,
+    error_csv: Option<ErrorCsv>,
+    error_log: Option<ErrorLogWriter>,
+    /// Weights and thresholds of the quality score, [None] if the quality score is not computed.
+    quality_config: Option<QualityConfig>,
+    link_quality: Vec<LinkQualityCounts>,
+    quality_score: Option<QualityScore>,
+    /// Commands run when an error is reported and when the run completes, [None] if none are set.
+    command_hooks: Option<CommandHooks>,
+}
+
+/// An error message, the link it was reported from if it is known, and if the link is auto-muted.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct ReportedError {
+    msg: String,
+    link_id: Option<u8>,
+    muted: bool,
+}
+
+impl AsRef<str> for ReportedError {
+    fn as_ref(&self) -> &str {
+        &self.msg
+    }
 }
+
 impl StatsController {
     /// Creates a new StatsController from a [Config], a [std::sync::mpsc::Receiver] for [StatType], and a [std::sync::Arc] of an [AtomicBool] that is used to signal to other threads to exit if a fatal error occurs.
     pub fn new(
@@ -73,79 +315,232 @@ impl StatsController {
         recv_stats_channel: std::sync::mpsc::Receiver<StatType>,
         end_processing_flag: Arc<AtomicBool>,
     ) -> Self {
-        StatsController {
+        let mut stats_controller = StatsController {
             rdhs_seen: 0,
             rdhs_filtered: 0,
+            rdhs_filtered_per_link: Vec::new(),
+            rdhs_excluded_per_link: Vec::new(),
             payload_size: 0,
+            input_size: 0,
             links_observed: Vec::new(),
+            fee_ids_observed: Vec::new(),
+            cru_ids_observed: Vec::new(),
             processing_time: std::time::Instant::now(),
             total_errors: AtomicU32::new(0),
-            max_tolerate_errors: config.max_tolerate_errors(),
+            // Fail-fast stops at the first error, regardless of the max tolerated errors
+            max_tolerate_errors: if config.fail_fast() {
+                1
+            } else {
+                config.max_tolerate_errors()
+            },
+            fail_fast: config.fail_fast(),
             non_atomic_total_errors: 0,
             recv_stats_channel,
             end_processing_flag,
-            link_to_filter: config.filter_link(),
+            links_to_filter: config.filter_links().map(<[u8]>::to_vec),
+            links_to_exclude: config.exclude_links().map(<[u8]>::to_vec),
+            fee_id_to_filter: config.filter_fee(),
+            cru_id_to_filter: config.filter_cru(),
+            its_stave_to_filter: config.filter_its_stave(),
+            trigger_to_filter: config.filter_trigger(),
+            packet_range_to_filter: config.filter_packet_range(),
+            // Keeping all links is not a filter that can keep none or all of the CDPs by mistake
+            filter_enabled: config.filter_selects_cdps(),
+            filter_warning: None,
+            strict: config.strict(),
+            expect_data_format: config.expect_data_format(),
+            internal_errors: 0,
+            run_metadata: None,
+            interleaving: Vec::new(),
+            throughput: None,
+            byte_histograms: Vec::new(),
+            payload_uniformity: Vec::new(),
+            write_failure: None,
+            failing_cdps: None,
+            empty_hbfs_dropped: None,
+            ape_words: None,
+            memory_usage: config.track_memory().then(MemoryUsage::default),
+            pipeline_counts: Default::default(),
+            silent_link_orbits: config.silent_link_orbits(),
+            silent_links: Vec::new(),
+            other_system_links: Vec::new(),
+            timeframes: None,
+            hw_map: None,
+            cdp_details: (config.report_detail() == Some(crate::util::config::ReportDetail::Cdp))
+                .then(Vec::new),
+            report_detail_max_cdps: config.report_detail_max_cdps(),
+            force: config.force(),
             rdh_version: 0,
             data_formats_observed: Vec::new(),
             hbfs_seen: 0,
             fatal_error: None,
             layers_staves_seen: Vec::new(),
-            view_active: config.view().is_some(),
+            view_active: config.view().is_some()
+                || config.list_links().is_some()
+                || config.count().is_some(),
+            log_format_json: config.log_format() == crate::util::config::LogFormat::Json,
+            reorder_buffer: if config.ordered() {
+                Some(ReorderBuffer::default())
+            } else {
+                None
+            },
+            trigger_stats: Vec::new(),
+            link_health: Vec::new(),
+            tdt_timeout_stats: Vec::new(),
+            first_errors: FirstErrors::default(),
+            link_error_mute: config.auto_mute_link_after().map(LinkErrorMute::new),
+            error_positions: ErrorPositions::default(),
+            link_error_positions: HashMap::new(),
+            check_evaluations: CheckEvaluations::default(),
+            check_skip_reasons: config.check().map(|_| {
+                KnownCheck::ALL
+                    .iter()
+                    .map(|check| check.requirement().skip_reason(config))
+                    .collect()
+            }),
+            muted_checks: Vec::new(),
+            link_fee_ids: Vec::new(),
+            error_csv: None,
+            error_log: None,
+            quality_config: None,
+            link_quality: Vec::new(),
+            quality_score: None,
+            command_hooks: CommandHooks::new(
+                config.on_error_exec(),
+                config.on_complete_exec(),
+                config
+                    .input_file()
+                    .as_ref()
+                    .map_or("stdin".to_string(), |path| path.display().to_string()),
+                crate::util::command_hook::COMMAND_TIMEOUT,
+            ),
+        };
+        if let Some(path) = config.error_csv() {
+            match ErrorCsv::create(path, config.error_csv_include_muted()) {
+                Ok(error_csv) => stats_controller.error_csv = Some(error_csv),
+                Err(e) => stats_controller.report_fatal(e),
+            }
+        }
+        if let Some(path) = config.error_log_binary() {
+            match ErrorLogWriter::create(path) {
+                Ok(error_log) => stats_controller.error_log = Some(error_log),
+                Err(e) => stats_controller.report_fatal(e),
+            }
         }
+        if config.quality_score() {
+            match config.quality_config() {
+                Some(path) => match QualityConfig::read(path) {
+                    Ok(quality_config) => stats_controller.quality_config = Some(quality_config),
+                    Err(e) => stats_controller.report_fatal(e),
+                },
+                None => stats_controller.quality_config = Some(QualityConfig::default()),
+            }
+        }
+        stats_controller
     }
 
     /// Starts the event loop for the StatsController
     /// This function will block until the channel is closed
     pub fn run(&mut self) {
         loop {
-            match self.recv_stats_channel.recv() {
-                Ok(stats_update) => self.update(stats_update),
+            match self.recv_stat() {
+                Ok(Some(stats_update)) => self.update(stats_update),
+                Ok(None) => (),
                 Err(_) => {
+                    if let Some(memory_usage) = &mut self.memory_usage {
+                        memory_usage.sample();
+                    }
+                    if let Some(reorder_buffer) = &mut self.reorder_buffer {
+                        // All data processing threads are done, release whatever is left
+                        let remaining = reorder_buffer.flush();
+                        remaining.into_iter().for_each(|err| {
+                            self.report_link_error(err.link_id, err.msg, err.muted)
+                        });
+                    }
+                    for discrepancy in self.pipeline_counts.reconcile() {
+                        self.report_internal_error(discrepancy);
+                    }
+                    self.warn_unterminated_hbfs();
+                    self.compute_quality_score();
+                    self.filter_warning = self.filter_coverage_warning();
+                    if let Some(warning) = self.filter_warning.clone() {
+                        self.report_warning(warning);
+                    }
+                    if let Some(error_log) = &mut self.error_log {
+                        if let Err(e) = error_log.flush() {
+                            log::error!("Failed to write the binary error log: {e}");
+                        }
+                    }
                     if self.view_active {
                         // Avoid printing the report in the middle of a view
                         log::info!("View active, skipping report summary printout.")
                     } else {
                         self.print();
                     }
+                    if let Some(command_hooks) = self.command_hooks.take() {
+                        command_hooks.complete(self.verdict(), self.reported_errors());
+                    }
                     break;
                 }
             }
         }
     }
 
+    /// Receives the next stat, and samples the memory usage while receiving if it is tracked.
+    ///
+    /// Returns `Ok(None)` if the memory usage is tracked and no stat was received before the next sample is due.
+    fn recv_stat(&mut self) -> Result<Option<StatType>, std::sync::mpsc::RecvError> {
+        let memory_usage = match &mut self.memory_usage {
+            Some(memory_usage) => memory_usage,
+            None => return self.recv_stats_channel.recv().map(Some),
+        };
+        memory_usage.sample_if_due();
+        match self
+            .recv_stats_channel
+            .recv_timeout(crate::stats::memory_usage::SAMPLE_INTERVAL)
+        {
+            Ok(stat) => Ok(Some(stat)),
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => Ok(None),
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => Err(std::sync::mpsc::RecvError),
+        }
+    }
+
     fn update(&mut self, stat: StatType) {
         //self.print();
         match stat {
             StatType::Error(msg) => {
-                if self.fatal_error.is_some() {
-                    // Stop processing any error messages
-                    log::trace!("Fatal error already seen, ignoring error: {}", msg);
-                    return;
+                self.record_error_position(None, &msg);
+                self.dispatch_error(None, msg, false)
+            }
+            StatType::Warning(msg) => self.report_warning(msg),
+            StatType::InternalError(msg) => self.report_internal_error(msg),
+            StatType::LinkError { link_id, msg } => {
+                self.record_error_position(Some(link_id), &msg);
+                let action = match &mut self.link_error_mute {
+                    Some(link_error_mute) => link_error_mute.record(link_id),
+                    None => LinkErrorAction::Report,
+                };
+                if action == LinkErrorAction::Mute {
+                    log::warn!(
+                        "link {link_id} auto-muted after {} errors",
+                        self.link_error_mute.as_ref().unwrap().mute_after()
+                    );
                 }
-                if self.max_tolerate_errors == 0 {
-                    error!("{msg}");
-                    self.non_atomic_total_errors += 1;
-                } else {
-                    let prv_err_cnt = self.total_errors.load(std::sync::atomic::Ordering::SeqCst);
-                    if prv_err_cnt >= self.max_tolerate_errors {
-                        return;
-                    }
-                    error!("{msg}");
-                    let prv_err_cnt = self
-                        .total_errors
-                        .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-                    log::info!("Error count: {}", prv_err_cnt + 1);
-                    if prv_err_cnt + 1 == self.max_tolerate_errors {
-                        log::info!("Errors reached maximum tolerated errors, exiting...");
-                        self.end_processing_flag
-                            .store(true, std::sync::atomic::Ordering::SeqCst);
-                    }
+                let muted = action != LinkErrorAction::Report;
+                if muted {
+                    self.record_muted_check(&msg);
                 }
+                self.dispatch_error(Some(link_id), msg, muted)
             }
             StatType::RDHsSeen(val) => self.rdhs_seen += val as u64,
             StatType::RDHsFiltered(val) => self.rdhs_filtered += val as u64,
             StatType::PayloadSize(size) => self.payload_size += size as u64,
             StatType::LinksObserved(val) => self.links_observed.push(val),
+            StatType::FeeIdObserved { fee_id, link_id } => {
+                self.fee_ids_observed.push(fee_id);
+                self.link_fee_ids.push((link_id, fee_id));
+            }
+            StatType::CruIdObserved(cru_id) => self.cru_ids_observed.push(cru_id),
             StatType::RdhVersion(version) => self.rdh_version = version,
             StatType::DataFormat(version) => {
                 if !self.data_formats_observed.contains(&version) {
@@ -153,33 +548,603 @@ impl StatsController {
                 }
             }
             StatType::HBFsSeen(val) => self.hbfs_seen += val,
-            StatType::Fatal(err) => {
-                if self.fatal_error.is_some() {
-                    // Stop processing any error messages
-                    log::trace!("Fatal error already seen, ignoring error: {}", err);
-                    return;
-                }
-                self.end_processing_flag
-                    .store(true, std::sync::atomic::Ordering::SeqCst);
-                log::error!("FATAL: {err}\nShutting down...");
-                self.fatal_error = Some(err);
+            StatType::Accumulated(acc) => {
+                self.rdhs_seen += acc.rdhs_seen;
+                self.rdhs_filtered += acc.rdhs_filtered;
+                acc.rdhs_filtered_per_link
+                    .iter()
+                    .for_each(|(link_id, rdhs)| {
+                        add_link_count(&mut self.rdhs_filtered_per_link, *link_id, *rdhs)
+                    });
+                acc.rdhs_excluded_per_link
+                    .iter()
+                    .for_each(|(link_id, rdhs)| {
+                        add_link_count(&mut self.rdhs_excluded_per_link, *link_id, *rdhs)
+                    });
+                self.payload_size += acc.payload_size;
+                self.hbfs_seen += acc.hbfs_seen;
+                self.input_size += acc.input_size;
+                merge_histograms(&mut self.byte_histograms, &acc.byte_histograms);
+                merge_uniformity(&mut self.payload_uniformity, &acc.payload_uniformity);
             }
+            StatType::Fatal(err) => self.report_fatal(err),
             StatType::LayerStaveSeen { layer, stave } => {
                 // Only add if not already seen
                 if !self.layers_staves_seen.contains(&(layer, stave)) {
                     self.layers_staves_seen.push((layer, stave));
                 }
             }
+            StatType::MissingHbTrigger(link) => self.trigger_stats_mut(link).missing_hb += 1,
+            StatType::IllegalSocSotTrigger(link) => {
+                self.trigger_stats_mut(link).illegal_soc_sot += 1
+            }
+            StatType::MissingHbfs { link_id, count } => {
+                self.trigger_stats_mut(link_id).missing_hbfs += count
+            }
+            StatType::IdlePage(link) => self.link_health_mut(link).idle_pages += 1,
+            StatType::Ddw0TransmissionTimeout(link) => {
+                self.link_health_mut(link).ddw0_transmission_timeouts += 1
+            }
+            StatType::LinkPages {
+                link_id,
+                page_counts,
+            } => self.link_health_mut(link_id).add_page_counts(&page_counts),
+            StatType::TdtTimeout {
+                layer,
+                stave,
+                timeout,
+            } => self.tdt_timeout_stats_mut(layer, stave).increment(timeout),
+            StatType::UnclosedHbf { link_id, hbf } => self.report_structural_surprise(format!(
+                "link {link_id}: final HBF (orbit {:#X}) not closed, {} pages read",
+                hbf.orbit,
+                hbf.pages
+            )),
+            StatType::LinkActivity(links) => {
+                self.silent_links = silent_links(&links, self.silent_link_orbits);
+                for silent_link in self.silent_links.clone() {
+                    self.report_warning(silent_link.to_string());
+                }
+            }
+            StatType::CdpDetails(mut details) => self.add_cdp_details(&mut details),
+            StatType::Timeframes(tf_summary) => self.timeframes = Some(tf_summary),
+            StatType::HwMap(hw_map_summary) => self.hw_map = Some(hw_map_summary),
+            StatType::TruncatedPayload {
+                link_id,
+                mem_pos,
+                payload_size,
+                missing_bytes,
+            } => self.report_structural_surprise(format!(
+                "{mem_pos:#X}: link {link_id}: payload truncated by end of input, {missing_bytes} of {payload_size} bytes missing"
+            )),
+            StatType::RunMetadata(run_metadata) => self.run_metadata = Some(*run_metadata),
+            StatType::Interleaving(interleaving) => self.interleaving = *interleaving,
+            StatType::Throughput(throughput) => self.throughput = Some(*throughput),
+            StatType::WriteFailure(write_summary) => self.write_failure = Some(write_summary),
+            StatType::FailingCdps(failing_cdps) => self.failing_cdps = Some(*failing_cdps),
+            StatType::EmptyHbfsDropped(dropped) => self.empty_hbfs_dropped = Some(dropped),
+            StatType::ApeWords(ape_words) => self.ape_words = Some(*ape_words),
+            StatType::PeakInFlightBytes(peak) => {
+                if let Some(memory_usage) = &mut self.memory_usage {
+                    memory_usage.record_peak_in_flight(peak);
+                }
+            }
+            StatType::PipelineCounts(counts) => self.pipeline_counts.record(counts),
+            StatType::OtherSystemLink {
+                link_id,
+                system_id,
+                cdps,
+            } => {
+                log::warn!(
+                    "link {link_id} carries system {} — payload checks skipped for {cdps} CDPs, check them anyway with --force-check-all-links",
+                    system_as_string(system_id)
+                );
+                self.other_system_links.push((link_id, system_id, cdps));
+                self.other_system_links.sort_unstable();
+            }
+            StatType::CheckEvaluations(check_evaluations) => {
+                self.check_evaluations.merge(&check_evaluations)
+            }
+            StatType::LinkQuality(link_quality) => self.link_quality.push(link_quality),
+            StatType::CdpDispatched {
+                link_id,
+                mem_pos,
+                next_mem_pos,
+            } => {
+                if let Some(reorder_buffer) = &mut self.reorder_buffer {
+                    reorder_buffer.dispatched(link_id, mem_pos, next_mem_pos);
+                    self.release_ordered_errors();
+                }
+            }
+            StatType::LinkProgress { link_id, mem_pos } => {
+                if let Some(reorder_buffer) = &mut self.reorder_buffer {
+                    reorder_buffer.progress(link_id, mem_pos);
+                    self.release_ordered_errors();
+                }
+            }
+        }
+    }
+
+    /// Records the memory position of an error, globally and for the link it is attributed to, for the error-free stretch stats.
+    fn record_error_position(&mut self, link_id: Option<u8>, msg: &str) {
+        if let Some(mem_pos) = crate::stats::lib::mem_pos_from_error_msg(msg) {
+            self.error_positions.record(mem_pos);
+            if let Some(link_id) = link_id {
+                self.link_error_positions
+                    .entry(link_id)
+                    .or_default()
+                    .record(mem_pos);
+            }
+        }
+    }
+
+    /// Reports an error, or buffers it if errors are reported in order.
+    fn dispatch_error(&mut self, link_id: Option<u8>, msg: String, muted: bool) {
+        if let Some(reorder_buffer) = &mut self.reorder_buffer {
+            reorder_buffer.push(ReportedError {
+                msg,
+                link_id,
+                muted,
+            });
+        } else {
+            self.report_link_error(link_id, msg, muted);
+        }
+    }
+
+    fn trigger_stats_mut(&mut self, link: u8) -> &mut TriggerStats {
+        if let Some(idx) = self
+            .trigger_stats
+            .iter()
+            .position(|stats| stats.link == link)
+        {
+            &mut self.trigger_stats[idx]
+        } else {
+            self.trigger_stats.push(TriggerStats::new(link));
+            self.trigger_stats.last_mut().unwrap()
+        }
+    }
+
+    fn link_health_mut(&mut self, link: u8) -> &mut LinkHealth {
+        if let Some(idx) = self
+            .link_health
+            .iter()
+            .position(|health| health.link == link)
+        {
+            &mut self.link_health[idx]
+        } else {
+            self.link_health.push(LinkHealth::new(link));
+            self.link_health.last_mut().unwrap()
+        }
+    }
+
+    fn tdt_timeout_stats_mut(&mut self, layer: u8, stave: u8) -> &mut TdtTimeoutStats {
+        if let Some(idx) = self
+            .tdt_timeout_stats
+            .iter()
+            .position(|stats| stats.layer == layer && stats.stave == stave)
+        {
+            &mut self.tdt_timeout_stats[idx]
+        } else {
+            self.tdt_timeout_stats
+                .push(TdtTimeoutStats::new(layer, stave));
+            self.tdt_timeout_stats.last_mut().unwrap()
+        }
+    }
+
+    fn release_ordered_errors(&mut self) {
+        if let Some(reorder_buffer) = &mut self.reorder_buffer {
+            let released = reorder_buffer.release();
+            released
+                .into_iter()
+                .for_each(|err| self.report_link_error(err.link_id, err.msg, err.muted));
+        }
+    }
+
+    fn report_internal_error(&mut self, msg: String) {
+        self.internal_errors += 1;
+        // Not ordered, as the memory positions may be what is wrong
+        self.report_error(format!(
+            "{msg} (internal error of fastpasta, please report it as a bug)"
+        ));
+    }
+
+    fn report_error(&mut self, msg: String) {
+        self.report_link_error(None, msg, false);
+    }
+
+    /// Reports an error from a link, if it is known.
+    ///
+    /// Errors of auto-muted links count towards the total errors and `--max-errors` like any other error, but are not printed.
+    fn report_link_error(&mut self, link_id: Option<u8>, msg: String, muted: bool) {
+        if self.fatal_error.is_some() {
+            // Stop processing any error messages
+            log::trace!("Fatal error already seen, ignoring error: {}", msg);
+            return;
+        }
+        if self.max_tolerate_errors == 0 {
+            self.first_errors.record(&msg);
+            self.emit_error(&msg, link_id, muted);
+            self.non_atomic_total_errors += 1;
+        } else {
+            let prv_err_cnt = self.total_errors.load(std::sync::atomic::Ordering::SeqCst);
+            if prv_err_cnt >= self.max_tolerate_errors {
+                // Errors from the data that was in flight when processing was stopped
+                log::trace!("Max tolerated errors reached, ignoring error: {}", msg);
+                return;
+            }
+            self.first_errors.record(&msg);
+            self.emit_error(&msg, link_id, muted);
+            let prv_err_cnt = self
+                .total_errors
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            log::info!("Error count: {}", prv_err_cnt + 1);
+            if prv_err_cnt + 1 == self.max_tolerate_errors {
+                if self.fail_fast {
+                    log::info!("Fail-fast: stopping at the first error, exiting...");
+                } else {
+                    log::info!("Errors reached maximum tolerated errors, exiting...");
+                }
+                self.end_processing_flag
+                    .store(true, std::sync::atomic::Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// Warns about links where the pages opening an HBF and the stop pages differ by more than 1 at the end of the input.
+    fn warn_unterminated_hbfs(&mut self) {
+        let mut link_health: Vec<&LinkHealth> = self.link_health.iter().collect();
+        link_health.sort_by_key(|health| health.link);
+        let warnings: Vec<String> = link_health
+            .into_iter()
+            .filter(|health| health.page_counts.has_unterminated_hbfs())
+            .map(|health| {
+                format!(
+                    "Link {}: {} pages opening an HBF (pages_counter 0) but {} stop pages, HBFs are unterminated or truncated",
+                    health.link,
+                    health.page_counts.opening_pages,
+                    health.page_counts.stop_pages
+                )
+            })
+            .collect();
+        warnings
+            .into_iter()
+            .for_each(|warning| self.report_warning(warning));
+    }
+
+    /// Prints the error, writes it to the error files and runs the error hook, errors of auto-muted links are only written to the error files.
+    fn emit_error(&mut self, msg: &str, link_id: Option<u8>, muted: bool) {
+        if muted {
+            self.write_error_files(msg, link_id, Severity::Muted);
+            return;
+        }
+        error!("{msg}");
+        self.write_error_files(msg, link_id, Severity::Error);
+        self.run_error_hook(msg, link_id);
+    }
+
+    /// Runs the `--on-error-exec` command for a reported error, if set.
+    fn run_error_hook(&mut self, msg: &str, link_id: Option<u8>) {
+        if let Some(command_hooks) = &mut self.command_hooks {
+            command_hooks.error_reported(
+                super::first_errors::error_code_from_error_msg(msg),
+                crate::stats::lib::mem_pos_from_error_msg(msg),
+                link_id,
+            );
+        }
+    }
+
+    /// Errors reported so far.
+    fn reported_errors(&self) -> u64 {
+        if self.max_tolerate_errors == 0 {
+            self.non_atomic_total_errors
+        } else {
+            self.total_errors.load(std::sync::atomic::Ordering::SeqCst) as u64
+        }
+    }
+
+    /// Verdict of the run for the `--on-complete-exec` command: `fatal` if processing was stopped by a fatal error, `errors` if errors were reported, otherwise `ok`.
+    fn verdict(&self) -> &'static str {
+        if self.fatal_error.is_some() {
+            "fatal"
+        } else if self.reported_errors() > 0 {
+            "errors"
+        } else {
+            "ok"
+        }
+    }
+
+    /// Computes the quality score from the stats collected, if enabled, and warns if it is below the threshold of the quality config.
+    fn compute_quality_score(&mut self) {
+        let quality_config = match &self.quality_config {
+            Some(quality_config) => quality_config,
+            None => return,
+        };
+        let inputs = QualityInputs {
+            link_quality: self.link_quality.clone(),
+            link_hbfs: self
+                .link_health
+                .iter()
+                .map(|health| (health.link, health.page_counts.opening_pages))
+                .collect(),
+        };
+        let quality_score = QualityScore::compute(quality_config, &inputs);
+        if let Some(warning) = quality_score.warning() {
+            self.report_warning(warning);
+        }
+        self.quality_score = Some(quality_score);
+    }
+
+    /// Maps the severity of a warning, it is reported as an error in strict mode.
+    fn report_warning(&mut self, warning: String) {
+        if self.strict {
+            self.report_error(warning);
+        } else {
+            log::warn!("{warning}");
+            // Only warnings from a check, with an error code, are validation errors
+            if super::first_errors::error_code_from_error_msg(&warning).is_some() {
+                self.write_error_files(&warning, None, Severity::Warning);
+            }
+        }
+    }
+
+    /// Writes an error to the error CSV and the binary error log, if they are enabled.
+    ///
+    /// If writing a file fails, it is reported once and no more errors are written to it.
+    fn write_error_files(&mut self, msg: &str, link_id: Option<u8>, severity: Severity) {
+        if self.error_csv.is_none() && self.error_log.is_none() {
+            return;
+        }
+        let mut row = ErrorCsvRow::from_error_msg(msg, link_id);
+        row.fee_id = row
+            .link_id
+            .and_then(|link_id| fee_id_of_link(&self.link_fee_ids, link_id));
+        if let Some(error_csv) = &mut self.error_csv {
+            if let Err(e) = error_csv.write(&row, severity) {
+                log::error!("Failed to write the error CSV, no more errors are written to it: {e}");
+                self.error_csv = None;
+            }
+        }
+        if let Some(error_log) = &mut self.error_log {
+            if let Err(e) = error_log.write(&row, severity) {
+                log::error!(
+                    "Failed to write the binary error log, no more errors are written to it: {e}"
+                );
+                self.error_log = None;
+            }
+        }
+    }
+
+    /// Adds details of CDPs for the JSON report, and stops processing if there are more than allowed, unless forced.
+    fn add_cdp_details(&mut self, details: &mut Vec<CdpDetail>) {
+        let cdp_details = match &mut self.cdp_details {
+            Some(cdp_details) => cdp_details,
+            None => return,
+        };
+        cdp_details.append(details);
+        if cdp_details.len() as u64 > self.report_detail_max_cdps && !self.force {
+            self.cdp_details = None;
+            self.report_fatal(format!(
+                "`--report-detail cdp` would list more than {} CDPs, raise the limit with --report-detail-max-cdps or use --force",
+                self.report_detail_max_cdps
+            ));
+        }
+    }
+
+    /// Maps the severity of a structural surprise in the input, such as a truncated payload, it is a warning, or fatal in strict mode.
+    fn report_structural_surprise(&mut self, surprise: String) {
+        if self.strict {
+            self.report_fatal(surprise);
+        } else {
+            log::warn!("{surprise}");
+        }
+    }
+
+    fn report_fatal(&mut self, err: String) {
+        if self.fatal_error.is_some() {
+            // Stop processing any error messages
+            log::trace!("Fatal error already seen, ignoring error: {}", err);
+            return;
+        }
+        self.end_processing_flag
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        log::error!("FATAL: {err}\nShutting down...");
+        self.fatal_error = Some(err);
+    }
+
+    /// Returns a warning if a filter is enabled, but kept none or all of the CDPs of the input, as it is likely a typo in the filter.
+    ///
+    /// If none were kept, the warning lists the links and FEE IDs present in the input.
+    fn filter_coverage_warning(&self) -> Option<String> {
+        if !self.filter_enabled || self.rdhs_seen == 0 || self.fatal_error.is_some() {
+            return None;
+        }
+        if self.rdhs_filtered == 0 {
+            let mut links = self.links_observed.clone();
+            links.sort_unstable();
+            let mut fee_ids = self.fee_ids_observed.clone();
+            fee_ids.sort_unstable();
+            let links_not_present = match &self.links_to_filter {
+                Some(links_to_filter)
+                    if !links_to_filter
+                        .iter()
+                        .any(|link_id| links.contains(link_id)) =>
+                {
+                    format!(
+                        " None of the links to filter are present: {}.",
+                        links_as_string(links_to_filter)
+                    )
+                }
+                _ => String::new(),
+            };
+            let fee_id_not_present = match self.fee_id_to_filter {
+                Some(fee_id) if !fee_ids.contains(&fee_id) => {
+                    format!(" The FEE ID to filter is not present: {fee_id:#X}.")
+                }
+                _ => String::new(),
+            };
+            let cru_id_not_present = match self.cru_id_to_filter {
+                Some(cru_id) if !self.cru_ids_observed.contains(&cru_id) => {
+                    let mut cru_ids = self.cru_ids_observed.clone();
+                    cru_ids.sort_unstable();
+                    format!(
+                        " The CRU ID to filter is not present: {cru_id}, CRU IDs present: {}.",
+                        cru_ids_as_string(&cru_ids)
+                    )
+                }
+                _ => String::new(),
+            };
+            let its_stave_not_present = match self.its_stave_to_filter {
+                Some(its_stave)
+                    if !fee_ids
+                        .iter()
+                        .any(|fee_id| ItsStave::from_fee_id(*fee_id) == its_stave) =>
+                {
+                    format!(" The ITS stave to filter is not present: {its_stave}.")
+                }
+                _ => String::new(),
+            };
+            Some(format!(
+                "Filter kept none of the {} CDPs of the input!{links_not_present}{fee_id_not_present}{cru_id_not_present}{its_stave_not_present} Links present: {}. FEE IDs present: {}",
+                self.rdhs_seen,
+                links_as_string(&links),
+                fee_ids
+                    .iter()
+                    .map(|fee_id| format!("{fee_id:#X}"))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ))
+        } else if self.rdhs_filtered == self.rdhs_seen {
+            Some(format!(
+                "Filter kept all {} CDPs of the input, the filter had no effect",
+                self.rdhs_seen
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Records the check reporting a muted error, to mark it as muted in the checks executed.
+    fn record_muted_check(&mut self, msg: &str) {
+        if let Some(check) = crate::stats::first_errors::error_code_from_error_msg(msg)
+            .and_then(KnownCheck::from_error_code)
+        {
+            if !self.muted_checks.contains(&check) {
+                self.muted_checks.push(check);
+            }
+        }
+    }
+
+    /// Lists the status of each known check, or [None] if no checks are enabled.
+    fn checks_executed(&self) -> Option<Vec<CheckExecuted>> {
+        let skip_reasons = self.check_skip_reasons.as_ref()?;
+        Some(
+            KnownCheck::ALL
+                .iter()
+                .zip(skip_reasons)
+                .map(|(check, skip_reason)| {
+                    // The RDH version is checked by the input scanner for every RDH
+                    let evaluations = if *check == KnownCheck::RdhVersion {
+                        self.rdhs_seen
+                    } else {
+                        self.check_evaluations.get(*check)
+                    };
+                    let status = match skip_reason {
+                        Some(reason) => format!("skipped ({reason})"),
+                        None if self.muted_checks.contains(check) => format!(
+                            "muted ({evaluations} evaluations, errors of auto-muted links not printed)"
+                        ),
+                        None => format!("ran ({evaluations} evaluations)"),
+                    };
+                    CheckExecuted {
+                        code: check.code().to_string(),
+                        check: check.description().to_string(),
+                        status,
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns true if processing was stopped at the first error by `--fail-fast`.
+    pub fn failed_fast(&self) -> bool {
+        self.fail_fast && self.total_errors.load(std::sync::atomic::Ordering::SeqCst) > 0
+    }
+
+    /// Prints the minimal report of `--fail-fast`: the error that stopped the processing and how far the input was processed.
+    fn print_fail_fast(&self) {
+        let mut report = Report::new(self.processing_time.elapsed());
+        if let Some(err) = &self.fatal_error {
+            report.add_fatal_error(err.clone());
+        }
+        let first_errors: Vec<FirstErrorOccurrence> = self
+            .first_errors
+            .sorted_by_mem_pos()
+            .into_iter()
+            .map(|(code, first_occurrence)| FirstErrorOccurrence {
+                code,
+                first_occurrence,
+            })
+            .collect();
+        report.add_first_errors(tabled::Table::new(first_errors));
+        report.add_stat(StatSummary::new(
+            "Fail-fast".to_string(),
+            "stopped at the first error".to_string(),
+            Some("the rest of the input is not checked".to_string()),
+        ));
+        report.add_stat(StatSummary::new(
+            "Total RDHs".to_string(),
+            self.rdhs_seen.to_string(),
+            None,
+        ));
+        if self.log_format_json {
+            report.print_json_line();
+        } else {
+            report.print();
         }
     }
 
     /// Builds and prints the report
     fn print(&self) {
+        if self.failed_fast() {
+            self.print_fail_fast();
+            return;
+        }
         let mut report = Report::new(self.processing_time.elapsed());
+        if let Some(run_metadata) = &self.run_metadata {
+            report.add_run_metadata(run_metadata.clone());
+        }
         if let Some(err) = &self.fatal_error {
             report.add_fatal_error(err.clone());
         }
+        if let Some(warning) = &self.filter_warning {
+            report.add_warning(warning.clone());
+        }
+        if !self.first_errors.is_empty() {
+            let first_errors: Vec<FirstErrorOccurrence> = self
+                .first_errors
+                .sorted_by_mem_pos()
+                .into_iter()
+                .map(|(code, first_occurrence)| FirstErrorOccurrence {
+                    code,
+                    first_occurrence,
+                })
+                .collect();
+            report.add_first_errors(tabled::Table::new(first_errors));
+        }
         // Add global stats
+        if self.strict {
+            report.add_stat(StatSummary::new(
+                "Strict mode".to_string(),
+                "active".to_string(),
+                Some("warnings reported as errors".to_string()),
+            ));
+        }
+        if let Some(expect_data_format) = self.expect_data_format {
+            report.add_stat(StatSummary::new(
+                "Expected data format".to_string(),
+                expect_data_format.to_string(),
+                Some("RDHs with another data format reported as errors".to_string()),
+            ));
+        }
         if self.max_tolerate_errors == 0 {
             report.add_stat(StatSummary::new(
                 "Total Errors".to_string(),
@@ -195,6 +1160,104 @@ impl StatsController {
                 None,
             ));
         }
+        if self.internal_errors > 0 {
+            report.add_stat(StatSummary::new(
+                "Internal Errors".to_string(),
+                self.internal_errors.to_string(),
+                Some("bugs in fastpasta, included in the total errors".to_string()),
+            ));
+        }
+        if let Some(link_error_mute) = &self.link_error_mute {
+            let muted_links = link_error_mute.muted_links();
+            if !muted_links.is_empty() {
+                let muted_links_string = muted_links
+                    .iter()
+                    .map(|(link_id, muted_errors)| format!("{link_id} ({muted_errors} errors)"))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                report.add_stat(StatSummary::new(
+                    "Auto-muted links".to_string(),
+                    muted_links_string,
+                    None,
+                ));
+            }
+        }
+        if let Some(write_failure) = &self.write_failure {
+            report.add_stat(StatSummary::new(
+                "Output".to_string(),
+                format!(
+                    "{} CDPs written, {} CDPs dropped",
+                    write_failure.cdps, write_failure.dropped_cdps
+                ),
+                Some("output dropped after a write error".to_string()),
+            ));
+        }
+        if let Some(failing_cdps) = &self.failing_cdps {
+            report.add_stat(StatSummary::new(
+                "Failing CDPs".to_string(),
+                format!(
+                    "{} saved to {}",
+                    failing_cdps.mem_positions.len(),
+                    failing_cdps.path.display()
+                ),
+                failing_cdps_offsets(&failing_cdps.mem_positions),
+            ));
+        }
+        if let Some(ape_words) = &self.ape_words {
+            report.add_stat(StatSummary::new(
+                "CDPs with APE words".to_string(),
+                ape_words.cdps.to_string(),
+                None,
+            ));
+            ape_words.words.iter().for_each(|(ape_word, count)| {
+                report.add_stat(StatSummary::new(
+                    ape_word.to_string(),
+                    count.to_string(),
+                    None,
+                ))
+            });
+        }
+        if let Some(dropped) = &self.empty_hbfs_dropped {
+            report.add_stat(StatSummary::new(
+                "Empty HBFs dropped".to_string(),
+                dropped.hbfs.to_string(),
+                Some(format!("{} without data words not written", dropped.cdps)),
+            ));
+        }
+        if let Some(memory_usage) = &self.memory_usage {
+            report.add_stat(StatSummary::new(
+                "Peak memory (RSS)".to_string(),
+                match memory_usage.peak_rss() {
+                    Some(rss) => format_byte_size(&rss),
+                    None => "unavailable".to_string(),
+                },
+                None,
+            ));
+            report.add_stat(StatSummary::new(
+                "Peak CDP chunks in flight".to_string(),
+                format_byte_size(&memory_usage.peak_in_flight()),
+                Some("read but not yet checked, viewed or written".to_string()),
+            ));
+        }
+        let error_free_stretch =
+            self.error_positions
+                .error_free_stretch(0, self.input_size, self.rdhs_seen);
+        report.add_stat(StatSummary::new(
+            "Longest error-free stretch".to_string(),
+            format!(
+                "{} (~{} CDPs)",
+                format_byte_size(&error_free_stretch.longest_bytes),
+                error_free_stretch.longest_cdps
+            ),
+            None,
+        ));
+        if let Some(mean_distance) = error_free_stretch.mean_distance {
+            report.add_stat(StatSummary::new(
+                "Mean distance between errors".to_string(),
+                format_byte_size(&(mean_distance.round() as u64)),
+                None,
+            ));
+        }
         report.add_stat(StatSummary::new(
             "Total RDHs".to_string(),
             self.rdhs_seen.to_string(),
@@ -213,6 +1276,105 @@ impl StatsController {
             observed_links_string,
             None,
         ));
+        // The worst case of the interleaving of all links
+        if let Some(link) = self
+            .interleaving
+            .iter()
+            .max_by_key(|link| link.max_gap_cdps)
+        {
+            report.add_stat(StatSummary::new(
+                "Max interleaving (CDPs)".to_string(),
+                link.max_gap_cdps.to_string(),
+                (link.max_gap_cdps > 0)
+                    .then(|| format!("link {} at {:#X}", link.link_id, link.max_gap_cdps_mem_pos)),
+            ));
+        }
+        if let Some(link) = self
+            .interleaving
+            .iter()
+            .max_by_key(|link| link.max_gap_bytes)
+        {
+            report.add_stat(StatSummary::new(
+                "Max interleaving (bytes)".to_string(),
+                format_byte_size(&link.max_gap_bytes),
+                (link.max_gap_bytes > 0)
+                    .then(|| format!("link {} at {:#X}", link.link_id, link.max_gap_bytes_mem_pos)),
+            ));
+        }
+        if !self.silent_links.is_empty() {
+            let silent_links_string = self
+                .silent_links
+                .iter()
+                .map(|silent_link| {
+                    format!(
+                        "{} ({} orbits)",
+                        silent_link.activity.link_id, silent_link.orbits_before_end
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+            report.add_stat(StatSummary::new(
+                "Silent links".to_string(),
+                silent_links_string,
+                Some(format!(
+                    "more than {} orbits without pages before the end of the data",
+                    self.silent_link_orbits
+                )),
+            ));
+        }
+        if !self.other_system_links.is_empty() {
+            let other_system_links_string = self
+                .other_system_links
+                .iter()
+                .map(|(link_id, system_id, cdps)| {
+                    format!(
+                        "link {link_id}: {} ({cdps} CDPs)",
+                        system_as_string(*system_id)
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+            report.add_stat(StatSummary::new(
+                "Payload checks skipped".to_string(),
+                other_system_links_string,
+                Some("RDH system_id is not the one of the check target".to_string()),
+            ));
+        }
+        if let Some(hw_map_summary) = &self.hw_map {
+            report.add_stat(StatSummary::new(
+                "Hardware map".to_string(),
+                format!("{} verified", hw_map_summary.verified),
+                Some(format!(
+                    "{} mismatched combinations of FEE ID, CRU ID, link ID and endpoint",
+                    hw_map_summary.mismatched
+                )),
+            ));
+        }
+        if let Some(tf_summary) = &self.timeframes {
+            report.add_stat(StatSummary::new(
+                "Complete timeframes".to_string(),
+                tf_summary.complete.to_string(),
+                Some(format!(
+                    "{} with wrong HBF count, {} incomplete leading, {} incomplete trailing",
+                    tf_summary.deviating,
+                    tf_summary.incomplete_leading,
+                    tf_summary.incomplete_trailing
+                )),
+            ));
+        }
+        if let Some(quality_score) = &self.quality_score {
+            report.add_stat(StatSummary::new(
+                "Quality score".to_string(),
+                quality_score
+                    .score
+                    .map_or("-".to_string(), |score| format!("{score:.1} / 100")),
+                Some(format!(
+                    "from {} of {} components",
+                    quality_score.components_used(),
+                    quality_score.components.len()
+                )),
+            ));
+        }
         // Sort and format layers and staves seen
         let mut layers_staves_seen = self.layers_staves_seen.clone();
         layers_staves_seen.sort();
@@ -222,18 +1384,21 @@ impl StatsController {
             .collect::<Vec<String>>()
             .join(", ");
         // Format and add payload size seen/loaded
-        let payload_string = match self.payload_size {
-            0..=1024 => format!("{} B", self.payload_size),
-            1025..=1048576 => {
-                format!("{:.3} KiB", self.payload_size as f64 / 1024_f64)
-            }
-            1048577..=1073741824 => {
-                format!("{:.3} MiB", self.payload_size as f64 / 1048576_f64)
-            }
-            _ => format!("{:.3} GiB", self.payload_size as f64 / 1073741824_f64),
+        let payload_string = format_byte_size(&self.payload_size);
+        // Pages without payloads are valid, but make it clear that no payload was checked
+        let payload_notes = |rdhs: u64| {
+            (self.payload_size == 0 && rdhs > 0)
+                .then(|| "no payloads present, the input contains only RDHs".to_string())
         };
         // If no filtering, the HBFs seen is from the total RDHs
-        if self.link_to_filter.is_none() {
+        if self.links_to_filter.is_none()
+            && self.links_to_exclude.is_none()
+            && self.fee_id_to_filter.is_none()
+            && self.cru_id_to_filter.is_none()
+            && self.its_stave_to_filter.is_none()
+            && self.trigger_to_filter.is_none()
+            && self.packet_range_to_filter.is_none()
+        {
             report.add_stat(StatSummary::new(
                 "Total HBFs".to_string(),
                 self.hbfs_seen.to_string(),
@@ -249,38 +1414,71 @@ impl StatsController {
             report.add_stat(StatSummary::new(
                 "Total Payload Size".to_string(),
                 payload_string,
-                None,
+                payload_notes(self.rdhs_seen),
             ));
         } else {
             let mut filtered_stats: Vec<StatSummary> = Vec::new();
-            filtered_stats.push(StatSummary::new(
-                "RDHs".to_string(),
-                self.rdhs_filtered.to_string(),
-                None,
-            ));
+            if self.rdhs_filtered_per_link.is_empty() {
+                filtered_stats.push(StatSummary::new("RDHs".to_string(), "0".to_string(), None));
+            }
+            filtered_stats.extend(self.rdhs_filtered_per_link.iter().map(|(link_id, rdhs)| {
+                StatSummary::new(format!("RDHs of link {link_id}"), rdhs.to_string(), None)
+            }));
+            filtered_stats.extend(self.rdhs_excluded_per_link.iter().map(|(link_id, rdhs)| {
+                StatSummary::new(
+                    format!("RDHs excluded of link {link_id}"),
+                    rdhs.to_string(),
+                    None,
+                )
+            }));
             filtered_stats.push(StatSummary::new(
                 "HBFs".to_string(),
                 self.hbfs_seen.to_string(),
                 None,
             ));
-            let payload_string = match self.payload_size {
-                0..=1024 => format!("{} B", self.payload_size),
-                1025..=1048576 => {
-                    format!("{:.3} KiB", self.payload_size as f64 / 1024_f64)
-                }
-                1048577..=1073741824 => {
-                    format!("{:.3} MiB", self.payload_size as f64 / 1048576_f64)
-                }
-                _ => format!("{:.3} GiB", self.payload_size as f64 / 1073741824_f64),
-            };
             filtered_stats.push(StatSummary::new(
                 "Total Payload Size".to_string(),
                 payload_string,
-                None,
+                payload_notes(self.rdhs_filtered),
             ));
-            let filtered_links =
-                summerize_filtered_links(self.link_to_filter.unwrap(), self.links_observed.clone());
-            filtered_stats.push(filtered_links);
+            if let Some(links_to_filter) = &self.links_to_filter {
+                filtered_stats.push(summerize_filtered_links(
+                    links_to_filter,
+                    &self.links_observed,
+                ));
+            }
+            if let Some(links_to_exclude) = &self.links_to_exclude {
+                filtered_stats.push(summerize_excluded_links(
+                    links_to_exclude,
+                    &self.links_observed,
+                ));
+            }
+            if let Some(fee_id) = self.fee_id_to_filter {
+                filtered_stats.push(summerize_filtered_fee_id(fee_id, &self.fee_ids_observed));
+            }
+            if let Some(cru_id) = self.cru_id_to_filter {
+                filtered_stats.push(summerize_filtered_cru_id(cru_id, &self.cru_ids_observed));
+            }
+            if let Some(its_stave) = self.its_stave_to_filter {
+                filtered_stats.push(summerize_filtered_its_stave(
+                    its_stave,
+                    &self.fee_ids_observed,
+                ));
+            }
+            if let Some(trigger) = self.trigger_to_filter {
+                filtered_stats.push(StatSummary::new(
+                    "Trigger type".to_string(),
+                    trigger.name().to_string(),
+                    Some(format!("{} CDPs matched", self.rdhs_filtered)),
+                ));
+            }
+            if let Some(packet_range) = self.packet_range_to_filter {
+                filtered_stats.push(StatSummary::new(
+                    "Packet range".to_string(),
+                    packet_range.to_string(),
+                    Some(format!("{} CDPs selected", self.rdhs_filtered)),
+                ));
+            }
             filtered_stats.push(StatSummary::new(
                 "Layers and Staves seen".to_string(),
                 layers_staves_seen_string,
@@ -306,19 +1504,333 @@ impl StatsController {
             .join(", ");
         report.add_detected_attribute("Data Format".to_string(), observed_data_formats_string);
 
-        report.print();
+        if !self.trigger_stats.is_empty() {
+            let mut trigger_stats = self.trigger_stats.clone();
+            trigger_stats.sort_by_key(|stats| stats.link);
+            report.add_trigger_stats(tabled::Table::new(trigger_stats));
+        }
+        if !self.link_health.is_empty() {
+            let mut link_health = self.link_health.clone();
+            link_health.sort_by_key(|health| health.link);
+            let no_errors = ErrorPositions::default();
+            link_health.iter_mut().for_each(|health| {
+                let (start, end) = health.page_counts.span();
+                health.error_free_stretch = self
+                    .link_error_positions
+                    .get(&health.link)
+                    .unwrap_or(&no_errors)
+                    .error_free_stretch(start, end, health.page_counts.pages as u64)
+            });
+            report.add_link_health(tabled::Table::new(link_health));
+        }
+        if !self.tdt_timeout_stats.is_empty() {
+            let mut tdt_timeout_stats = self.tdt_timeout_stats.clone();
+            tdt_timeout_stats.sort_by_key(|stats| (stats.layer, stats.stave));
+            report.add_tdt_timeout_stats(tabled::Table::new(tdt_timeout_stats));
+        }
+        if !self.interleaving.is_empty() {
+            report.add_interleaving(tabled::Table::new(
+                self.interleaving.iter().map(InterleavingRow::from),
+            ));
+        }
+        if let Some(throughput) = &self.throughput {
+            let throughputs = throughput.throughputs();
+            report.add_throughput(tabled::Table::new(
+                throughputs.iter().map(ThroughputRow::from),
+            ));
+            let throughputs_json: Vec<String> =
+                throughputs.iter().map(|link| link.to_json()).collect();
+            report.add_json_field("throughput", format!("[{}]", throughputs_json.join(",")));
+        }
+        if !self.byte_histograms.is_empty() {
+            report.add_entropy(tabled::Table::new(
+                self.byte_histograms.iter().map(EntropyRow::from),
+            ));
+            let entropy_json: Vec<String> = self
+                .byte_histograms
+                .iter()
+                .map(|histogram| histogram.to_json())
+                .collect();
+            report.add_json_field("entropy", format!("[{}]", entropy_json.join(",")));
+        }
+        if !self.payload_uniformity.is_empty() {
+            report.add_payload_uniformity(tabled::Table::new(
+                self.payload_uniformity
+                    .iter()
+                    .map(PayloadUniformityRow::from),
+            ));
+            let payload_uniformity_json: Vec<String> = self
+                .payload_uniformity
+                .iter()
+                .map(|link| link.to_json())
+                .collect();
+            report.add_json_field(
+                "payload_uniformity",
+                format!("[{}]", payload_uniformity_json.join(",")),
+            );
+        }
+        if let Some(quality_score) = &self.quality_score {
+            report.add_quality_score(tabled::Table::new(quality_score.rows()));
+            report.add_json_field("quality", quality_score.to_json());
+        }
+        if let Some(cdp_details) = &self.cdp_details {
+            report.add_json_field("detail", cdp_details_to_json(cdp_details));
+        }
+        if let Some(memory_usage) = &self.memory_usage {
+            report.add_json_field("memory", memory_usage.to_json());
+        }
+        if let Some(checks_executed) = self.checks_executed() {
+            report.add_checks_executed(tabled::Table::new(checks_executed));
+        }
+
+        if self.log_format_json {
+            report.print_json_line();
+        } else {
+            report.print();
+        }
+    }
+}
+
+/// Formats a system ID with the name of the system, e.g. `0x3 (TPC)`.
+fn system_as_string(system_id: u8) -> String {
+    match crate::validators::rdh::system_name(system_id) {
+        Some(name) => format!("{system_id:#x} ({name})"),
+        None => format!("{system_id:#x}"),
     }
 }
 
+/// The FEE ID of a link, [None] if the link is unknown or carries more than one FEE ID.
+fn fee_id_of_link(link_fee_ids: &[(u8, u16)], link_id: u8) -> Option<u16> {
+    let mut fee_ids = link_fee_ids
+        .iter()
+        .filter(|(link, _)| *link == link_id)
+        .map(|(_, fee_id)| *fee_id);
+    match (fee_ids.next(), fee_ids.next()) {
+        (Some(fee_id), None) => Some(fee_id),
+        _ => None,
+    }
+}
+
+/// Max number of memory offsets of failing CDPs shown in the summary.
+const MAX_FAILING_CDP_OFFSETS_SHOWN: usize = 10;
+
+/// Formats the memory offsets of the failing CDPs, the first [MAX_FAILING_CDP_OFFSETS_SHOWN] followed by the number of offsets left out.
+fn failing_cdps_offsets(mem_positions: &[u64]) -> Option<String> {
+    if mem_positions.is_empty() {
+        return None;
+    }
+    let mut offsets = mem_positions
+        .iter()
+        .take(MAX_FAILING_CDP_OFFSETS_SHOWN)
+        .map(|mem_pos| format!("{mem_pos:#X}"))
+        .collect::<Vec<String>>()
+        .join(", ");
+    if mem_positions.len() > MAX_FAILING_CDP_OFFSETS_SHOWN {
+        offsets.push_str(&format!(
+            " and {} more",
+            mem_positions.len() - MAX_FAILING_CDP_OFFSETS_SHOWN
+        ));
+    }
+    Some(format!("at {offsets}"))
+}
+
 /// Helper functions to format the summary
-fn summerize_filtered_links(link_to_filter: u8, links_observed: Vec<u8>) -> StatSummary {
+fn summerize_filtered_links(links_to_filter: &[u8], links_observed: &[u8]) -> StatSummary {
     let mut filtered_links_stat = StatSummary::new("Link ID".to_string(), "".to_string(), None);
+    let (found, not_found): (Vec<u8>, Vec<u8>) = links_to_filter
+        .iter()
+        .partition(|link_id| links_observed.contains(link_id));
     // Format links that were filtered, separated by commas
-    if links_observed.contains(&link_to_filter) {
-        filtered_links_stat.value = link_to_filter.to_string();
+    filtered_links_stat.value = if found.is_empty() {
+        "<<none>>".to_string()
     } else {
-        filtered_links_stat.value = "<<none>>".to_string();
-        filtered_links_stat.notes = format!("not found: {link_to_filter}");
+        links_as_string(&found)
+    };
+    if !not_found.is_empty() {
+        filtered_links_stat.notes = format!("not found: {}", links_as_string(&not_found));
     }
     filtered_links_stat
 }
+
+fn summerize_excluded_links(links_to_exclude: &[u8], links_observed: &[u8]) -> StatSummary {
+    let mut excluded_links_stat =
+        StatSummary::new("Excluded Link ID".to_string(), "".to_string(), None);
+    let (found, not_found): (Vec<u8>, Vec<u8>) = links_to_exclude
+        .iter()
+        .partition(|link_id| links_observed.contains(link_id));
+    excluded_links_stat.value = if found.is_empty() {
+        "<<none>>".to_string()
+    } else {
+        links_as_string(&found)
+    };
+    if !not_found.is_empty() {
+        excluded_links_stat.notes = format!("not found: {}", links_as_string(&not_found));
+    }
+    excluded_links_stat
+}
+
+fn summerize_filtered_fee_id(fee_id_to_filter: u16, fee_ids_observed: &[u16]) -> StatSummary {
+    if fee_ids_observed.contains(&fee_id_to_filter) {
+        StatSummary::new("FEE ID".to_string(), format!("{fee_id_to_filter:#X}"), None)
+    } else {
+        let mut fee_ids = fee_ids_observed.to_vec();
+        fee_ids.sort_unstable();
+        StatSummary::new(
+            "FEE ID".to_string(),
+            "<<none>>".to_string(),
+            Some(format!(
+                "not found: {fee_id_to_filter:#X}, FEE IDs present: {}",
+                fee_ids
+                    .iter()
+                    .map(|fee_id| format!("{fee_id:#X}"))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )),
+        )
+    }
+}
+
+fn summerize_filtered_cru_id(cru_id_to_filter: u16, cru_ids_observed: &[u16]) -> StatSummary {
+    let mut cru_ids = cru_ids_observed.to_vec();
+    cru_ids.sort_unstable();
+    // The CRU IDs present are always listed, as the CRU IDs of an input are rarely known in advance
+    if cru_ids.contains(&cru_id_to_filter) {
+        StatSummary::new(
+            "CRU ID".to_string(),
+            cru_id_to_filter.to_string(),
+            Some(format!("CRU IDs present: {}", cru_ids_as_string(&cru_ids))),
+        )
+    } else {
+        StatSummary::new(
+            "CRU ID".to_string(),
+            "<<none>>".to_string(),
+            Some(format!(
+                "not found: {cru_id_to_filter}, CRU IDs present: {}",
+                cru_ids_as_string(&cru_ids)
+            )),
+        )
+    }
+}
+
+fn summerize_filtered_its_stave(
+    its_stave_to_filter: ItsStave,
+    fee_ids_observed: &[u16],
+) -> StatSummary {
+    let mut its_staves: Vec<ItsStave> = fee_ids_observed
+        .iter()
+        .map(|fee_id| ItsStave::from_fee_id(*fee_id))
+        .collect();
+    its_staves.sort_unstable();
+    its_staves.dedup();
+    if its_staves.contains(&its_stave_to_filter) {
+        StatSummary::new(
+            "ITS stave".to_string(),
+            its_stave_to_filter.to_string(),
+            None,
+        )
+    } else {
+        StatSummary::new(
+            "ITS stave".to_string(),
+            "<<none>>".to_string(),
+            Some(format!(
+                "not found: {its_stave_to_filter}, staves present: {}",
+                its_staves
+                    .iter()
+                    .map(ItsStave::to_string)
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )),
+        )
+    }
+}
+
+fn cru_ids_as_string(cru_ids: &[u16]) -> String {
+    cru_ids
+        .iter()
+        .map(|cru_id| cru_id.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn links_as_string(link_ids: &[u8]) -> String {
+    link_ids
+        .iter()
+        .map(|link_id| link_id.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::config::Opt;
+
+    fn stats_controller_from(args: &[&str]) -> (StatsController, Arc<AtomicBool>) {
+        let config = <Opt as structopt::StructOpt>::from_iter(
+            ["fastpasta", "input.raw"].iter().chain(args.iter()),
+        );
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let (_send, recv) = std::sync::mpsc::channel();
+        (
+            StatsController::new(&config, recv, stop_flag.clone()),
+            stop_flag,
+        )
+    }
+
+    fn link_error(link_id: u8, mem_pos: u64) -> StatType {
+        StatType::LinkError {
+            link_id,
+            msg: format!("{mem_pos:#X}: [E30] IHW ID is not 0xE0"),
+        }
+    }
+
+    #[test]
+    fn test_muted_errors_count_towards_max_errors() {
+        let (mut stats, stop_flag) = stats_controller_from(&[
+            "--auto-mute-link-after",
+            "1",
+            "--max-errors",
+            "3",
+            "check",
+            "all",
+            "its",
+        ]);
+        // The second and third error of link 0 are muted
+        stats.update(link_error(0, 0x40));
+        stats.update(link_error(0, 0x80));
+        stats.update(link_error(1, 0xC0));
+        assert_eq!(stats.reported_errors(), 3);
+        assert!(stop_flag.load(std::sync::atomic::Ordering::SeqCst));
+        // Errors in flight when processing is stopped are not counted, muted or not
+        stats.update(link_error(0, 0x100));
+        stats.update(link_error(1, 0x140));
+        assert_eq!(stats.reported_errors(), 3);
+    }
+
+    #[test]
+    fn test_muted_errors_count_without_max_errors() {
+        let (mut stats, stop_flag) =
+            stats_controller_from(&["--auto-mute-link-after", "1", "check", "all", "its"]);
+        stats.update(link_error(0, 0x40));
+        stats.update(link_error(1, 0x80));
+        stats.update(link_error(0, 0xC0));
+        stats.update(link_error(0, 0x100));
+        assert_eq!(stats.reported_errors(), 4);
+        assert!(!stop_flag.load(std::sync::atomic::Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_muted_error_fails_fast() {
+        let (mut stats, stop_flag) = stats_controller_from(&[
+            "--auto-mute-link-after",
+            "0",
+            "--fail-fast",
+            "check",
+            "all",
+            "its",
+        ]);
+        stats.update(link_error(1, 0x40));
+        assert!(stats.failed_fast());
+        assert!(stop_flag.load(std::sync::atomic::Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,302 @@
+//! Contains the [TfTracker] that finds the timeframe (TF) boundaries of each link, and verifies the number of HBFs per TF.
+//!
+//! A TF starts with an HBF opened by an RDH with the TF trigger bit set, and should contain a fixed number of HBFs, e.g. 128.
+//! HBFs before the first TF boundary of a link form an incomplete leading TF, and the last TF of a link is incomplete if the data ends before it has all its HBFs.
+use crate::words::lib::RDH;
+use crate::words::rdh::TriggerType;
+
+/// A TF with another number of HBFs than expected, or a link without any TF boundary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TfDeviation {
+    /// A TF has another number of HBFs than expected.
+    HbfCount {
+        /// Link ID.
+        link_id: u8,
+        /// Memory position of the RDH opening the TF.
+        mem_pos: u64,
+        /// Orbit of the TF.
+        orbit: u32,
+        /// HBFs in the TF.
+        hbfs: u32,
+        /// Expected HBFs per TF.
+        expected: u32,
+    },
+    /// No HBF of a link was opened with the TF trigger bit set.
+    NoTfBoundary {
+        /// Link ID.
+        link_id: u8,
+        /// HBFs of the link.
+        hbfs: u32,
+    },
+}
+
+impl TfDeviation {
+    /// The link of the deviation.
+    pub fn link_id(&self) -> u8 {
+        match self {
+            TfDeviation::HbfCount { link_id, .. } | TfDeviation::NoTfBoundary { link_id, .. } => {
+                *link_id
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for TfDeviation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TfDeviation::HbfCount {
+                link_id,
+                mem_pos,
+                orbit,
+                hbfs,
+                expected,
+            } => write!(
+                f,
+                "{mem_pos:#X}: [E17] link {link_id}: TF at orbit {orbit:#X} has {hbfs} HBFs, expected {expected}"
+            ),
+            TfDeviation::NoTfBoundary { link_id, hbfs } => write!(
+                f,
+                "[E17] link {link_id}: no HBF opened with the TF trigger bit set in {hbfs} HBFs"
+            ),
+        }
+    }
+}
+
+/// Number of TFs seen on all links.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TfSummary {
+    /// TFs with the expected number of HBFs.
+    pub complete: u32,
+    /// TFs between two TF boundaries with another number of HBFs than expected.
+    pub deviating: u32,
+    /// Links with HBFs before their first TF boundary.
+    pub incomplete_leading: u32,
+    /// Links where the data ends before the last TF has the expected number of HBFs.
+    pub incomplete_trailing: u32,
+}
+
+/// The current TF of a link.
+#[derive(Debug, Clone, Copy)]
+struct LinkTf {
+    link_id: u8,
+    /// Orbit and memory position of the RDH opening the current TF, [None] before the first TF boundary.
+    start: Option<(u32, u64)>,
+    /// HBFs in the current TF, or before the first TF boundary.
+    hbfs: u32,
+}
+
+/// Tracks the TFs of each link, updated with every RDH in the order they appear in the input.
+#[derive(Debug)]
+pub struct TfTracker {
+    hbf_per_tf: u32,
+    links: Vec<LinkTf>,
+    summary: TfSummary,
+    /// TFs closed by a TF boundary, whose number of HBFs was verified.
+    evaluations: u64,
+}
+
+impl TfTracker {
+    /// Creates a [TfTracker] expecting `hbf_per_tf` HBFs in every TF.
+    pub fn new(hbf_per_tf: u32) -> Self {
+        Self {
+            hbf_per_tf,
+            links: Vec::new(),
+            summary: TfSummary::default(),
+            evaluations: 0,
+        }
+    }
+
+    /// Records the page of an [RDH] at the memory position `mem_pos`.
+    ///
+    /// Returns a deviation if the page closes a TF with another number of HBFs than expected.
+    pub fn record<T: RDH>(&mut self, rdh: &T, mem_pos: u64) -> Option<TfDeviation> {
+        if rdh.pages_counter() != 0 {
+            return None;
+        }
+        let link_id = rdh.link_id();
+        let idx = match self.links.iter().position(|link| link.link_id == link_id) {
+            Some(idx) => idx,
+            None => {
+                self.links.push(LinkTf {
+                    link_id,
+                    start: None,
+                    hbfs: 0,
+                });
+                self.links.len() - 1
+            }
+        };
+        if !TriggerType::Tf.is_set(rdh.rdh2().trigger_type) {
+            self.links[idx].hbfs += 1;
+            return None;
+        }
+        let link = std::mem::replace(
+            &mut self.links[idx],
+            LinkTf {
+                link_id,
+                start: Some((rdh.rdh1().orbit, mem_pos)),
+                hbfs: 1,
+            },
+        );
+        match link.start {
+            None if link.hbfs > 0 => {
+                self.summary.incomplete_leading += 1;
+                None
+            }
+            None => None,
+            Some(_) if link.hbfs == self.hbf_per_tf => {
+                self.evaluations += 1;
+                self.summary.complete += 1;
+                None
+            }
+            Some((orbit, mem_pos)) => {
+                self.evaluations += 1;
+                self.summary.deviating += 1;
+                Some(TfDeviation::HbfCount {
+                    link_id,
+                    mem_pos,
+                    orbit,
+                    hbfs: link.hbfs,
+                    expected: self.hbf_per_tf,
+                })
+            }
+        }
+    }
+
+    /// Number of times the HBFs of a TF were verified, including the last TF of each link verified by [TfTracker::finish].
+    pub fn evaluations(&self) -> u64 {
+        self.evaluations + self.links.len() as u64
+    }
+
+    /// Closes the last TF of each link at the end of the data, and returns the deviations found in them and the [TfSummary] of all the TFs.
+    ///
+    /// The last TF of a link is complete if it has the expected number of HBFs, incomplete if it has fewer, and deviating if it has more.
+    pub fn finish(mut self) -> (Vec<TfDeviation>, TfSummary) {
+        self.links.sort_by_key(|link| link.link_id);
+        let mut deviations = Vec::new();
+        for link in &self.links {
+            match link.start {
+                None => deviations.push(TfDeviation::NoTfBoundary {
+                    link_id: link.link_id,
+                    hbfs: link.hbfs,
+                }),
+                Some(_) if link.hbfs == self.hbf_per_tf => self.summary.complete += 1,
+                Some(_) if link.hbfs < self.hbf_per_tf => self.summary.incomplete_trailing += 1,
+                Some((orbit, mem_pos)) => {
+                    self.summary.deviating += 1;
+                    deviations.push(TfDeviation::HbfCount {
+                        link_id: link.link_id,
+                        mem_pos,
+                        orbit,
+                        hbfs: link.hbfs,
+                        expected: self.hbf_per_tf,
+                    });
+                }
+            }
+        }
+        (deviations, self.summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh_cru::{test_data::CORRECT_RDH_CRU_V7, RdhCRU, V7};
+
+    const HB: u32 = 0b10;
+    const TF: u32 = 0b1000_0000_0000;
+
+    // A page opening an HBF on link 0 one orbit after CORRECT_RDH_CRU_V7 per HBF, with the TF bit set if `tf`
+    fn hbf(hbf_idx: u32, tf: bool) -> RdhCRU<V7> {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        rdh.rdh1.orbit += hbf_idx;
+        rdh.rdh2.pages_counter = 0;
+        rdh.rdh2.trigger_type = if tf { HB | TF } else { HB };
+        rdh
+    }
+
+    #[test]
+    fn test_two_tfs_one_missing_hbf() {
+        let mut tracker = TfTracker::new(4);
+        // 2 leading HBFs, a TF of 4 HBFs, a TF of 3 HBFs, and a last TF of 4 HBFs
+        let tf_starts = [2, 6, 9];
+        let deviations: Vec<TfDeviation> = (0..13)
+            .filter_map(|hbf_idx| {
+                let rdh = hbf(hbf_idx, tf_starts.contains(&hbf_idx));
+                // Pages that don't open an HBF are not counted
+                let mut next_page = hbf(hbf_idx, false);
+                next_page.rdh2.pages_counter = 1;
+                assert!(tracker.record(&next_page, 0).is_none());
+                tracker.record(&rdh, hbf_idx as u64 * 0x100)
+            })
+            .collect();
+        let orbit = CORRECT_RDH_CRU_V7.rdh1().orbit;
+        assert_eq!(
+            deviations,
+            vec![TfDeviation::HbfCount {
+                link_id: 0,
+                mem_pos: 0x600,
+                orbit: orbit + 6,
+                hbfs: 3,
+                expected: 4
+            }]
+        );
+        assert_eq!(
+            deviations[0].to_string(),
+            "0x600: [E17] link 0: TF at orbit 0xB7DD57B has 3 HBFs, expected 4"
+        );
+        // The two TFs closed by a TF boundary, and the last TF of the link
+        assert_eq!(tracker.evaluations(), 3);
+        let (deviations, summary) = tracker.finish();
+        assert!(deviations.is_empty());
+        assert_eq!(
+            summary,
+            TfSummary {
+                complete: 2,
+                deviating: 1,
+                incomplete_leading: 1,
+                incomplete_trailing: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_trailing_tf() {
+        let mut tracker = TfTracker::new(4);
+        for hbf_idx in 0..3 {
+            assert!(tracker.record(&hbf(hbf_idx, hbf_idx == 0), 0).is_none());
+        }
+        let (deviations, summary) = tracker.finish();
+        assert!(deviations.is_empty());
+        assert_eq!(summary.incomplete_trailing, 1);
+
+        // The last TF has more HBFs than expected
+        let mut tracker = TfTracker::new(2);
+        for hbf_idx in 0..3 {
+            assert!(tracker.record(&hbf(hbf_idx, hbf_idx == 0), 0).is_none());
+        }
+        let (deviations, summary) = tracker.finish();
+        assert_eq!(deviations.len(), 1);
+        assert_eq!(summary.deviating, 1);
+    }
+
+    #[test]
+    fn test_no_tf_boundary() {
+        let mut tracker = TfTracker::new(128);
+        for hbf_idx in 0..5 {
+            assert!(tracker.record(&hbf(hbf_idx, false), 0).is_none());
+        }
+        let (deviations, summary) = tracker.finish();
+        assert_eq!(
+            deviations,
+            vec![TfDeviation::NoTfBoundary {
+                link_id: 0,
+                hbfs: 5
+            }]
+        );
+        assert_eq!(
+            deviations[0].to_string(),
+            "[E17] link 0: no HBF opened with the TF trigger bit set in 5 HBFs"
+        );
+        assert_eq!(summary, TfSummary::default());
+    }
+}
@@ -0,0 +1,201 @@
+//! Contains the [TriggerConsistencyTracker] that compares the trigger type of the HBFs of all links of a CRU at the same orbit.
+//!
+//! The trigger information is distributed to all links of a CRU, so every link opening an HBF at an orbit should see the same trigger type.
+//! The trigger types are only kept for a sliding window of the most recent orbits, which bounds the memory used regardless of the size of the input.
+use crate::words::lib::RDH;
+use std::collections::BTreeMap;
+
+/// A link that opened an HBF at an orbit with another trigger type than the first link of its CRU at that orbit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TriggerMismatch {
+    /// Memory position of the RDH with the differing trigger type.
+    pub mem_pos: u64,
+    /// Orbit of the HBFs.
+    pub orbit: u32,
+    /// CRU ID of the links.
+    pub cru_id: u16,
+    /// The first link that opened an HBF at the orbit, and its trigger type.
+    pub first: (u8, u32),
+    /// The link with the differing trigger type, and its trigger type.
+    pub differing: (u8, u32),
+}
+
+impl std::fmt::Display for TriggerMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:#X}: [E18] CRU {} orbit {:#X}: trigger_type of link {} is {:#X}, but {:#X} on link {}",
+            self.mem_pos,
+            self.cru_id,
+            self.orbit,
+            self.differing.0,
+            self.differing.1,
+            self.first.1,
+            self.first.0
+        )
+    }
+}
+
+/// The trigger type of a link opening an HBF.
+#[derive(Debug, Clone, Copy)]
+struct HbfTrigger {
+    link_id: u8,
+    trigger_type: u32,
+}
+
+/// Tracks the trigger types of the HBFs of each CRU in a sliding window of orbits, updated with every RDH in the order they appear in the input.
+#[derive(Debug)]
+pub struct TriggerConsistencyTracker {
+    window_orbits: u32,
+    /// The trigger type of each link opening an HBF, per CRU ID and orbit.
+    hbfs: BTreeMap<(u32, u16), Vec<HbfTrigger>>,
+    latest_orbit: Option<u32>,
+    comparisons: u64,
+}
+
+impl TriggerConsistencyTracker {
+    /// Creates a [TriggerConsistencyTracker] that keeps the trigger types of the last `window_orbits` orbits.
+    pub fn new(window_orbits: u32) -> Self {
+        Self {
+            window_orbits,
+            hbfs: BTreeMap::new(),
+            latest_orbit: None,
+            comparisons: 0,
+        }
+    }
+
+    /// Records the page of an [RDH] at the memory position `mem_pos`.
+    ///
+    /// Returns a mismatch if the page opens an HBF with another trigger type than the first link of the same CRU opening an HBF at that orbit.
+    /// Pages at an orbit that already left the window are not compared.
+    pub fn record<T: RDH>(&mut self, rdh: &T, mem_pos: u64) -> Option<TriggerMismatch> {
+        if rdh.pages_counter() != 0 {
+            return None;
+        }
+        let orbit = rdh.rdh1().orbit;
+        let latest_orbit = *self
+            .latest_orbit
+            .insert(self.latest_orbit.map_or(orbit, |latest| latest.max(orbit)));
+        let oldest_orbit = latest_orbit.saturating_sub(self.window_orbits - 1);
+        if orbit < oldest_orbit {
+            return None;
+        }
+        // Forget the orbits that left the window
+        if matches!(self.hbfs.keys().next(), Some((first_orbit, _)) if *first_orbit < oldest_orbit)
+        {
+            self.hbfs = self.hbfs.split_off(&(oldest_orbit, 0));
+        }
+
+        let hbf = HbfTrigger {
+            link_id: rdh.link_id(),
+            trigger_type: rdh.trigger_type(),
+        };
+        let cru_id = rdh.cru_id();
+        let triggers = self.hbfs.entry((orbit, cru_id)).or_default();
+        let first = match triggers.first() {
+            Some(first) => *first,
+            None => {
+                triggers.push(hbf);
+                return None;
+            }
+        };
+        if triggers.iter().any(|seen| seen.link_id == hbf.link_id) {
+            return None;
+        }
+        triggers.push(hbf);
+        self.comparisons += 1;
+        if first.trigger_type == hbf.trigger_type {
+            return None;
+        }
+        Some(TriggerMismatch {
+            mem_pos,
+            orbit,
+            cru_id,
+            first: (first.link_id, first.trigger_type),
+            differing: (hbf.link_id, hbf.trigger_type),
+        })
+    }
+
+    /// Number of HBFs compared to the HBF of another link at the same orbit.
+    pub fn comparisons(&self) -> u64 {
+        self.comparisons
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh_cru::{test_data::CORRECT_RDH_CRU_V7, RdhCRU, V7};
+
+    const HB: u32 = 0b10;
+    const PHT: u32 = 0b1_0000;
+
+    // A page opening an HBF on `link_id` `orbit_offset` orbits after CORRECT_RDH_CRU_V7
+    fn hbf(link_id: u8, orbit_offset: u32, trigger_type: u32) -> RdhCRU<V7> {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        rdh.link_id = link_id;
+        rdh.rdh1.orbit += orbit_offset;
+        rdh.rdh2.pages_counter = 0;
+        rdh.rdh2.trigger_type = trigger_type;
+        rdh
+    }
+
+    #[test]
+    fn test_two_links_one_mismatching_orbit() {
+        let mut tracker = TriggerConsistencyTracker::new(16);
+        let mut mismatches = Vec::new();
+        for orbit_offset in 0..10 {
+            // Link 0 has a PhT at the 5th orbit, link 3 doesn't
+            let link_0_trigger = if orbit_offset == 4 { HB | PHT } else { HB };
+            for (link_id, trigger_type) in [(0, link_0_trigger), (3, HB)] {
+                let mem_pos = (orbit_offset as u64 * 2 + (link_id as u64 / 3)) * 0x100;
+                mismatches
+                    .extend(tracker.record(&hbf(link_id, orbit_offset, trigger_type), mem_pos));
+                // Pages that don't open an HBF are not compared
+                let mut next_page = hbf(link_id, orbit_offset, 0);
+                next_page.rdh2.pages_counter = 1;
+                assert!(tracker.record(&next_page, mem_pos).is_none());
+            }
+        }
+        let orbit = CORRECT_RDH_CRU_V7.rdh1().orbit + 4;
+        assert_eq!(
+            mismatches,
+            vec![TriggerMismatch {
+                mem_pos: 0x900,
+                orbit,
+                cru_id: CORRECT_RDH_CRU_V7.cru_id(),
+                first: (0, HB | PHT),
+                differing: (3, HB),
+            }]
+        );
+        assert_eq!(
+            mismatches[0].to_string(),
+            format!("0x900: [E18] CRU {} orbit 0xB7DD579: trigger_type of link 3 is 0x2, but 0x12 on link 0", CORRECT_RDH_CRU_V7.cru_id())
+        );
+        assert_eq!(tracker.comparisons(), 10);
+    }
+
+    #[test]
+    fn test_orbits_outside_window_are_forgotten() {
+        let mut tracker = TriggerConsistencyTracker::new(4);
+        assert!(tracker.record(&hbf(0, 0, HB | PHT), 0).is_none());
+        assert!(tracker.record(&hbf(0, 10, HB), 0).is_none());
+        // The first orbit left the window, so it is no longer compared
+        assert!(tracker.record(&hbf(1, 0, HB), 0).is_none());
+        assert_eq!(tracker.hbfs.len(), 1);
+        // Within the window
+        assert!(tracker.record(&hbf(1, 7, HB), 0).is_none());
+        assert!(tracker.record(&hbf(0, 7, HB | PHT), 0).is_some());
+        assert_eq!(tracker.hbfs.len(), 2);
+    }
+
+    #[test]
+    fn test_links_of_other_crus_are_not_compared() {
+        let mut tracker = TriggerConsistencyTracker::new(4);
+        let mut other_cru = hbf(1, 0, HB | PHT);
+        other_cru.cruid_dw = crate::words::rdh::CruidDw(CORRECT_RDH_CRU_V7.cru_id() + 1);
+        assert!(tracker.record(&hbf(0, 0, HB), 0).is_none());
+        assert!(tracker.record(&other_cru, 0).is_none());
+        assert!(tracker.record(&hbf(1, 0, HB | PHT), 0).is_some());
+    }
+}
@@ -1,4 +1,9 @@
 //! Definition of the Config trait, and an implementation of it on struct created by parsing CL arguments.
 
+pub mod checkpoint;
+pub mod command_hook;
 pub mod config;
+pub mod json_logger;
 pub mod lib;
+pub mod rdh_predicate;
+pub mod run_metadata;
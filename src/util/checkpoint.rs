@@ -0,0 +1,330 @@
+//! Contains the [Checkpoint] that stores the state of the running checks of each link, to resume checking an input where a previous run stopped.
+//!
+//! The checkpoint is saved to the file set with `--checkpoint-file` every [CHECKPOINT_INTERVAL] while checking, and when checking ends or is stopped.
+//! If the file exists on startup, processing resumes from the memory offset it was saved at.
+use super::lib::InputOutput;
+use crate::input::bufreader_wrapper::BufferedReaderWrapper;
+use crate::validators::link_validator::LinkValidatorState;
+use crate::words::lib::RDH;
+use std::path::{Path, PathBuf};
+
+/// Time between the checkpoints saved while checking.
+pub const CHECKPOINT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// State of the running checks of all links, and the position in the input it was saved at.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Checkpoint {
+    /// Canonical path of the input the checkpoint was saved from.
+    input_file: PathBuf,
+    rdh_version: u8,
+    /// Memory offset of the first RDH that was not checked.
+    last_offset: u64,
+    link_states: Vec<LinkValidatorState>,
+}
+
+impl Checkpoint {
+    /// Creates a new [Checkpoint] from the states of the link validators, the last offset is the end of the last CDP checked on any link.
+    pub fn new(
+        input_file: &Path,
+        rdh_version: u8,
+        link_states: Vec<LinkValidatorState>,
+    ) -> Result<Self, std::io::Error> {
+        let last_offset = link_states
+            .iter()
+            .map(|state| state.next_mem_pos)
+            .max()
+            .unwrap_or(0);
+        Self::at_offset(input_file, rdh_version, link_states, last_offset)
+    }
+
+    /// Creates a new [Checkpoint] from the states of the link validators, resuming at `last_offset`.
+    pub fn at_offset(
+        input_file: &Path,
+        rdh_version: u8,
+        link_states: Vec<LinkValidatorState>,
+        last_offset: u64,
+    ) -> Result<Self, std::io::Error> {
+        Ok(Self {
+            input_file: input_file.canonicalize()?,
+            rdh_version,
+            last_offset,
+            link_states,
+        })
+    }
+
+    /// Memory offset of the first RDH that was not checked.
+    pub fn last_offset(&self) -> u64 {
+        self.last_offset
+    }
+
+    /// RDH version of the input the checkpoint was saved from.
+    pub fn rdh_version(&self) -> u8 {
+        self.rdh_version
+    }
+
+    /// Takes the states of the link validators.
+    pub fn take_link_states(&mut self) -> Vec<LinkValidatorState> {
+        std::mem::take(&mut self.link_states)
+    }
+
+    /// Checks that the checkpoint was saved from the same input file, and that the input is at least as long as the last offset.
+    pub fn validate_input(&self, input_file: &Path) -> Result<(), String> {
+        let input_file = input_file
+            .canonicalize()
+            .map_err(|e| format!("Failed to resolve input path {input_file:?}: {e}"))?;
+        if input_file != self.input_file {
+            return Err(format!(
+                "Checkpoint was saved from {:?}, not from the input {input_file:?}",
+                self.input_file
+            ));
+        }
+        let input_size = std::fs::metadata(&input_file)
+            .map_err(|e| format!("Failed to read size of input {input_file:?}: {e}"))?
+            .len();
+        if input_size < self.last_offset {
+            return Err(format!(
+                "Input is {input_size} bytes, shorter than the checkpoint offset of {} bytes",
+                self.last_offset
+            ));
+        }
+        Ok(())
+    }
+
+    /// Saves the checkpoint to a file, by writing to a temporary file first and then renaming it.
+    pub fn save(&self, path: &Path) -> Result<(), std::io::Error> {
+        let mut tmp_file_name = path.file_name().unwrap_or_default().to_os_string();
+        tmp_file_name.push(".tmp");
+        let tmp_path = path.with_file_name(tmp_file_name);
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(&tmp_path)?);
+        bincode::serialize_into(&mut writer, self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        std::io::Write::flush(&mut writer)?;
+        std::fs::rename(tmp_path, path)
+    }
+
+    /// Loads a checkpoint from a file.
+    pub fn load(path: &Path) -> Result<Self, std::io::Error> {
+        let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        bincode::deserialize_from(reader)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Loads the checkpoint if a checkpoint file is set and exists, validates it against the input, and seeks the reader to the last offset of the checkpoint.
+///
+/// Returns `None` if there is no checkpoint to resume from.
+pub fn resume_input(
+    config: &impl InputOutput,
+    reader: &mut dyn BufferedReaderWrapper,
+) -> Result<Option<Checkpoint>, String> {
+    let checkpoint_path = match config.checkpoint_file() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(None),
+    };
+    let input_file = config
+        .input_file()
+        .as_ref()
+        .ok_or("Resuming from a checkpoint requires an input file")?;
+    let checkpoint = Checkpoint::load(checkpoint_path)
+        .map_err(|e| format!("Failed to load checkpoint {checkpoint_path:?}: {e}"))?;
+    checkpoint.validate_input(input_file)?;
+    BufferedReaderWrapper::seek_relative(reader, checkpoint.last_offset as i64)
+        .map_err(|e| format!("Failed to seek to checkpoint offset: {e}"))?;
+    log::info!(
+        "Resuming from checkpoint {checkpoint_path:?} at {:#X}",
+        checkpoint.last_offset
+    );
+    Ok(Some(checkpoint))
+}
+
+/// Saves a [Checkpoint] of the link validator states to the checkpoint file if one is set, errors are logged.
+pub fn save_checkpoint(
+    config: &impl InputOutput,
+    rdh_version: u8,
+    link_states: Vec<LinkValidatorState>,
+) {
+    let (checkpoint_path, input_file) = match (config.checkpoint_file(), config.input_file()) {
+        (Some(checkpoint_path), Some(input_file)) => (checkpoint_path, input_file),
+        _ => return,
+    };
+    match Checkpoint::new(input_file, rdh_version, link_states)
+        .and_then(|checkpoint| checkpoint.save(checkpoint_path).map(|_| checkpoint))
+    {
+        Ok(checkpoint) => log::info!(
+            "Saved checkpoint {checkpoint_path:?} at {:#X}",
+            checkpoint.last_offset
+        ),
+        Err(e) => log::error!("Failed to save checkpoint {checkpoint_path:?}: {e}"),
+    }
+}
+
+/// Saves a [Checkpoint] every [CHECKPOINT_INTERVAL] while checking, from the latest snapshots sent by the link validators.
+///
+/// The links are checked in their own threads, so the snapshots are taken at different memory positions.
+/// The checkpoint resumes at the earliest position a link still needs, and the restored link validators skip the CDPs they already checked.
+pub struct PeriodicCheckpoint {
+    snapshot_recv: std::sync::mpsc::Receiver<LinkValidatorState>,
+    /// Latest snapshot of each link.
+    link_states: Vec<LinkValidatorState>,
+    /// Memory position of the first CDP of each link dispatched in this run.
+    first_mem_pos: Vec<(u8, u64)>,
+    last_save: std::time::Instant,
+}
+
+impl PeriodicCheckpoint {
+    /// Creates a new [PeriodicCheckpoint], and the producer channel the link validators send their snapshots through.
+    pub fn new() -> (Self, std::sync::mpsc::Sender<LinkValidatorState>) {
+        let (snapshot_send, snapshot_recv) = std::sync::mpsc::channel();
+        (
+            Self {
+                snapshot_recv,
+                link_states: Vec::new(),
+                first_mem_pos: Vec::new(),
+                last_save: std::time::Instant::now(),
+            },
+            snapshot_send,
+        )
+    }
+
+    /// Records the links of the [RDH]s dispatched to the link validators, and their memory positions.
+    pub fn dispatched<T: RDH>(&mut self, rdhs: &[T], mem_positions: &[u64]) {
+        for (rdh, mem_pos) in rdhs.iter().zip(mem_positions) {
+            if !self
+                .first_mem_pos
+                .iter()
+                .any(|(link_id, _)| *link_id == rdh.link_id())
+            {
+                self.first_mem_pos.push((rdh.link_id(), *mem_pos));
+            }
+        }
+    }
+
+    /// Saves a checkpoint if [CHECKPOINT_INTERVAL] passed since the last one, with the states restored from a previous checkpoint of the links not seen yet.
+    pub fn save_if_due(
+        &mut self,
+        config: &impl InputOutput,
+        rdh_version: u8,
+        unseen_link_states: &[LinkValidatorState],
+    ) {
+        for state in self.snapshot_recv.try_iter() {
+            match self
+                .link_states
+                .iter_mut()
+                .find(|latest| latest.link_id == state.link_id)
+            {
+                Some(latest) => *latest = state,
+                None => self.link_states.push(state),
+            }
+        }
+        if self.last_save.elapsed() < CHECKPOINT_INTERVAL {
+            return;
+        }
+        self.last_save = std::time::Instant::now();
+        let (checkpoint_path, input_file) = match (config.checkpoint_file(), config.input_file()) {
+            (Some(checkpoint_path), Some(input_file)) => (checkpoint_path, input_file),
+            _ => return,
+        };
+        let last_offset = match resume_offset(
+            &self.first_mem_pos,
+            self.link_states
+                .iter()
+                .map(|state| (state.link_id, state.next_mem_pos)),
+        ) {
+            Some(last_offset) => last_offset,
+            None => return,
+        };
+        let mut link_states = self.link_states.clone();
+        link_states.extend_from_slice(unseen_link_states);
+        match Checkpoint::at_offset(input_file, rdh_version, link_states, last_offset)
+            .and_then(|checkpoint| checkpoint.save(checkpoint_path))
+        {
+            Ok(()) => log::debug!("Saved checkpoint {checkpoint_path:?} at {last_offset:#X}"),
+            Err(e) => log::error!("Failed to save checkpoint {checkpoint_path:?}: {e}"),
+        }
+    }
+}
+
+/// Earliest memory position a link still needs to be checked from, the position after its latest snapshot, or its first CDP if it has none.
+///
+/// Returns [None] if no link was dispatched.
+fn resume_offset(
+    first_mem_pos: &[(u8, u64)],
+    next_mem_pos: impl Iterator<Item = (u8, u64)> + Clone,
+) -> Option<u64> {
+    first_mem_pos
+        .iter()
+        .map(|(link_id, first_mem_pos)| {
+            next_mem_pos
+                .clone()
+                .find(|(snapshot_link_id, _)| snapshot_link_id == link_id)
+                .map_or(*first_mem_pos, |(_, next_mem_pos)| next_mem_pos)
+        })
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tmp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("fastpasta_{}_{name}", std::process::id()))
+    }
+
+    #[test]
+    fn test_save_load() {
+        let input_file = tmp_path("checkpoint_input.raw");
+        std::fs::write(&input_file, [0; 128]).unwrap();
+        let checkpoint = Checkpoint::new(&input_file, 7, Vec::new()).unwrap();
+        let checkpoint_file = tmp_path("checkpoint.bin");
+
+        checkpoint.save(&checkpoint_file).unwrap();
+        let loaded = Checkpoint::load(&checkpoint_file).unwrap();
+        assert_eq!(loaded, checkpoint);
+        assert_eq!(loaded.rdh_version(), 7);
+        assert_eq!(loaded.last_offset(), 0);
+        assert!(loaded.validate_input(&input_file).is_ok());
+
+        std::fs::remove_file(input_file).unwrap();
+        std::fs::remove_file(checkpoint_file).unwrap();
+    }
+
+    #[test]
+    fn test_validate_input() {
+        let input_file = tmp_path("validate_input.raw");
+        let other_input_file = tmp_path("validate_other_input.raw");
+        std::fs::write(&input_file, [0; 128]).unwrap();
+        std::fs::write(&other_input_file, [0; 128]).unwrap();
+        let mut checkpoint = Checkpoint::new(&input_file, 7, Vec::new()).unwrap();
+
+        let err = checkpoint.validate_input(&other_input_file).unwrap_err();
+        assert!(err.contains("not from the input"), "{err}");
+
+        checkpoint.last_offset = 256;
+        let err = checkpoint.validate_input(&input_file).unwrap_err();
+        assert_eq!(
+            err,
+            "Input is 128 bytes, shorter than the checkpoint offset of 256 bytes"
+        );
+
+        std::fs::remove_file(input_file).unwrap();
+        std::fs::remove_file(other_input_file).unwrap();
+    }
+
+    #[test]
+    fn test_resume_offset() {
+        assert_eq!(resume_offset(&[], std::iter::empty()), None);
+        // Link 2 is behind link 0, and link 5 has no snapshot yet
+        let snapshots = [(0, 0x3000), (2, 0x1800)];
+        assert_eq!(
+            resume_offset(&[(0, 0x0), (2, 0x40)], snapshots.iter().copied()),
+            Some(0x1800)
+        );
+        assert_eq!(
+            resume_offset(
+                &[(0, 0x0), (2, 0x40), (5, 0x1000)],
+                snapshots.iter().copied()
+            ),
+            Some(0x1000)
+        );
+    }
+}
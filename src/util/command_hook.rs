@@ -0,0 +1,371 @@
+//! Contains the [CommandHooks] that run user commands when an error is reported and when the run completes, set with `--on-error-exec` and `--on-complete-exec`.
+//!
+//! A command is a [CommandTemplate], whose arguments are separated by whitespace, and whose placeholders such as `{code}` are substituted before it is run.
+//! The command is run directly, not by a shell, so the values substituted can't be interpreted by a shell.
+//!
+//! The commands run one at a time on a notifier thread, so a slow command never blocks the [StatsController][crate::stats::stats_controller::StatsController].
+//! A command still running after [COMMAND_TIMEOUT] is killed, and when the run completes, all commands get [COMMAND_TIMEOUT] in total to finish.
+//! Failing to run a command is logged as a warning, and never stops the processing.
+use std::collections::HashSet;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, OnceLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Time a command may run before it is killed, and time all commands still to run may take once the run completes.
+pub const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Placeholders of the `--on-error-exec` command.
+pub const ON_ERROR_PLACEHOLDERS: [&str; 3] = ["code", "mem_pos", "link"];
+/// Placeholders of the `--on-complete-exec` command.
+pub const ON_COMPLETE_PLACEHOLDERS: [&str; 3] = ["verdict", "errors", "input"];
+
+/// A command with placeholders of the form `{name}` in its arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandTemplate {
+    args: Vec<String>,
+}
+
+impl CommandTemplate {
+    /// Parses a command, the arguments are separated by whitespace and may only contain the given placeholders.
+    pub fn parse(s: &str, placeholders: &[&str]) -> Result<Self, String> {
+        let args: Vec<String> = s.split_whitespace().map(str::to_string).collect();
+        if args.is_empty() {
+            return Err("Invalid command: it is empty".to_string());
+        }
+        for arg in &args {
+            let mut rest = arg.as_str();
+            while let Some(start) = rest.find('{') {
+                let end = match rest[start..].find('}') {
+                    Some(end) => start + end,
+                    None => return Err(format!("Invalid command '{s}': unclosed '{{' in '{arg}'")),
+                };
+                let name = &rest[start + 1..end];
+                if !placeholders.contains(&name) {
+                    return Err(format!(
+                        "Invalid command '{s}': unknown placeholder '{{{name}}}', the placeholders are {}",
+                        placeholders
+                            .iter()
+                            .map(|placeholder| format!("{{{placeholder}}}"))
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    ));
+                }
+                rest = &rest[end + 1..];
+            }
+        }
+        Ok(Self { args })
+    }
+
+    /// The arguments of the command, with each placeholder substituted by its value.
+    pub fn expand(&self, values: &[(&str, String)]) -> Vec<String> {
+        self.args
+            .iter()
+            .map(|arg| {
+                values.iter().fold(arg.clone(), |arg, (name, value)| {
+                    arg.replace(&format!("{{{name}}}"), value)
+                })
+            })
+            .collect()
+    }
+}
+
+/// Runs the `--on-error-exec` and `--on-complete-exec` commands on a notifier thread.
+///
+/// The error command is run at most once per error code, for the first error reported with the code.
+pub struct CommandHooks {
+    on_error: Option<CommandTemplate>,
+    on_complete: Option<CommandTemplate>,
+    codes_notified: HashSet<String>,
+    /// The input file, or `stdin`, for the `{input}` placeholder.
+    input: String,
+    send_command: Sender<Vec<String>>,
+    notifier: JoinHandle<()>,
+    timeout: Duration,
+    /// Set when the run completes, commands still running after it are killed, and commands not started yet are skipped.
+    deadline: Arc<OnceLock<Instant>>,
+}
+
+impl CommandHooks {
+    /// Spawns the notifier thread, [None] if no command is set.
+    pub fn new(
+        on_error: Option<CommandTemplate>,
+        on_complete: Option<CommandTemplate>,
+        input: String,
+        timeout: Duration,
+    ) -> Option<Self> {
+        if on_error.is_none() && on_complete.is_none() {
+            return None;
+        }
+        let (send_command, recv_command) = std::sync::mpsc::channel::<Vec<String>>();
+        let deadline = Arc::new(OnceLock::new());
+        let notifier = std::thread::Builder::new()
+            .name("notifier_thread".to_string())
+            .spawn({
+                let deadline = deadline.clone();
+                move || {
+                    recv_command
+                        .into_iter()
+                        .for_each(|args| run_command(&args, timeout, &deadline))
+                }
+            })
+            .expect("Failed to spawn notifier thread");
+        Some(Self {
+            on_error,
+            on_complete,
+            codes_notified: HashSet::new(),
+            input,
+            send_command,
+            notifier,
+            timeout,
+            deadline,
+        })
+    }
+
+    /// Runs the error command for an error, unless it already ran for an error with the same code, errors without a code share one.
+    pub fn error_reported(
+        &mut self,
+        code: Option<&str>,
+        mem_pos: Option<u64>,
+        link_id: Option<u8>,
+    ) {
+        let on_error = match &self.on_error {
+            Some(on_error) => on_error,
+            None => return,
+        };
+        if !self
+            .codes_notified
+            .insert(code.unwrap_or_default().to_string())
+        {
+            return;
+        }
+        let args = on_error.expand(&[
+            ("code", code.unwrap_or_default().to_string()),
+            (
+                "mem_pos",
+                mem_pos.map_or(String::new(), |mem_pos| format!("{mem_pos:#X}")),
+            ),
+            (
+                "link",
+                link_id.map_or(String::new(), |link_id| link_id.to_string()),
+            ),
+        ]);
+        // The notifier thread only stops when the hooks are dropped
+        self.send_command.send(args).unwrap();
+    }
+
+    /// Runs the complete command, and waits for all commands to finish, for at most the timeout in total.
+    pub fn complete(self, verdict: &str, errors: u64) {
+        // Only set once, here
+        let _ = self.deadline.set(Instant::now() + self.timeout);
+        if let Some(on_complete) = &self.on_complete {
+            let args = on_complete.expand(&[
+                ("verdict", verdict.to_string()),
+                ("errors", errors.to_string()),
+                ("input", self.input.clone()),
+            ]);
+            self.send_command.send(args).unwrap();
+        }
+        drop(self.send_command);
+        if self.notifier.join().is_err() {
+            log::warn!("Notifier thread panicked, some commands may not have run");
+        }
+    }
+}
+
+/// Runs a command and waits for it to finish, killing it after `timeout` or once the `deadline` is passed, failures are logged as warnings.
+///
+/// The output of the command is discarded, as stdout may be the data output of fastpasta, and stderr carries its log and report.
+fn run_command(args: &[String], timeout: Duration, deadline: &OnceLock<Instant>) {
+    let command_line = args.join(" ");
+    let deadline_passed = || {
+        deadline
+            .get()
+            .is_some_and(|deadline| Instant::now() >= *deadline)
+    };
+    if deadline_passed() {
+        log::warn!("Command '{command_line}' not run, the commands did not finish within {timeout:?} of the end of the run");
+        return;
+    }
+    let mut child = match std::process::Command::new(&args[0])
+        .args(&args[1..])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            log::warn!("Failed to run command '{command_line}': {e}");
+            return;
+        }
+    };
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return,
+            Ok(Some(status)) => {
+                log::warn!("Command '{command_line}' failed: {status}");
+                return;
+            }
+            Ok(None) if started.elapsed() >= timeout => {
+                log::warn!("Command '{command_line}' still running after {timeout:?}, killing it");
+                let _ = child.kill();
+                let _ = child.wait();
+                return;
+            }
+            Ok(None) if deadline_passed() => {
+                log::warn!("Command '{command_line}' still running {timeout:?} after the end of the run, killing it");
+                let _ = child.kill();
+                let _ = child.wait();
+                return;
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(10)),
+            Err(e) => {
+                log::warn!("Failed to wait for command '{command_line}': {e}");
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A script that touches a file in the directory of its first argument, named by the other arguments.
+    fn touch_script(dir: &std::path::Path) -> String {
+        let script = dir.join("touch.sh");
+        std::fs::write(&script, "#!/bin/sh\ntouch \"$1/$2_$3\"\n").unwrap();
+        script.display().to_string()
+    }
+
+    fn files_in(dir: &std::path::Path) -> Vec<String> {
+        let mut files: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|file| file != "touch.sh")
+            .collect();
+        files.sort();
+        files
+    }
+
+    fn test_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("fastpasta_{name}_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_parse_template() {
+        let template =
+            CommandTemplate::parse("notify.sh  --code={code} {mem_pos}", &ON_ERROR_PLACEHOLDERS)
+                .unwrap();
+        assert_eq!(
+            template.expand(&[("code", "E30".to_string()), ("mem_pos", "0x40".to_string())]),
+            vec!["notify.sh", "--code=E30", "0x40"]
+        );
+        assert_eq!(
+            CommandTemplate::parse("notify.sh {verdict}", &ON_ERROR_PLACEHOLDERS).unwrap_err(),
+            "Invalid command 'notify.sh {verdict}': unknown placeholder '{verdict}', the placeholders are {code}, {mem_pos}, {link}"
+        );
+        assert_eq!(
+            CommandTemplate::parse("notify.sh {code", &ON_ERROR_PLACEHOLDERS).unwrap_err(),
+            "Invalid command 'notify.sh {code': unclosed '{' in '{code'"
+        );
+        assert!(CommandTemplate::parse(" ", &ON_COMPLETE_PLACEHOLDERS).is_err());
+    }
+
+    #[test]
+    fn test_error_command_once_per_code() {
+        let dir = test_dir("on_error");
+        let script = touch_script(&dir);
+        let on_error = CommandTemplate::parse(
+            &format!("sh {script} {} {{code}} {{mem_pos}}", dir.display()),
+            &ON_ERROR_PLACEHOLDERS,
+        )
+        .unwrap();
+        let mut hooks = CommandHooks::new(
+            Some(on_error),
+            None,
+            "input.raw".to_string(),
+            COMMAND_TIMEOUT,
+        )
+        .unwrap();
+        hooks.error_reported(Some("E30"), Some(0x40), Some(0));
+        hooks.error_reported(Some("E30"), Some(0x80), Some(0));
+        hooks.error_reported(Some("E11"), Some(0x1C0), Some(2));
+        hooks.error_reported(None, None, None);
+        hooks.error_reported(None, Some(0x200), None);
+        hooks.complete("errors", 5);
+        assert_eq!(files_in(&dir), vec!["E11_0x1C0", "E30_0x40", "_"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_complete_command() {
+        let dir = test_dir("on_complete");
+        let script = touch_script(&dir);
+        let on_complete = CommandTemplate::parse(
+            &format!(
+                "sh {script} {} {{verdict}} {{errors}}_{{input}}",
+                dir.display()
+            ),
+            &ON_COMPLETE_PLACEHOLDERS,
+        )
+        .unwrap();
+        let mut hooks = CommandHooks::new(
+            None,
+            Some(on_complete),
+            "input.raw".to_string(),
+            COMMAND_TIMEOUT,
+        )
+        .unwrap();
+        // No error command, nothing to run
+        hooks.error_reported(Some("E30"), Some(0x40), Some(0));
+        hooks.complete("ok", 0);
+        assert_eq!(files_in(&dir), vec!["ok_0_input.raw"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_failing_commands_are_not_fatal() {
+        let on_error =
+            CommandTemplate::parse("/nonexistent/notify.sh {code}", &ON_ERROR_PLACEHOLDERS)
+                .unwrap();
+        let on_complete = CommandTemplate::parse("sleep 5", &ON_COMPLETE_PLACEHOLDERS).unwrap();
+        let mut hooks = CommandHooks::new(
+            Some(on_error),
+            Some(on_complete),
+            "stdin".to_string(),
+            Duration::from_millis(50),
+        )
+        .unwrap();
+        hooks.error_reported(Some("E30"), None, None);
+        let started = std::time::Instant::now();
+        hooks.complete("ok", 0);
+        // The sleep is killed after the timeout
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(CommandHooks::new(None, None, "stdin".to_string(), COMMAND_TIMEOUT).is_none());
+    }
+
+    #[test]
+    fn test_one_deadline_for_all_commands() {
+        let on_error = CommandTemplate::parse("sleep 5", &ON_ERROR_PLACEHOLDERS).unwrap();
+        let on_complete = CommandTemplate::parse("sleep 5", &ON_COMPLETE_PLACEHOLDERS).unwrap();
+        let mut hooks = CommandHooks::new(
+            Some(on_error),
+            Some(on_complete),
+            "stdin".to_string(),
+            Duration::from_millis(300),
+        )
+        .unwrap();
+        for code in ["E10", "E11", "E12", "E13", "E14"] {
+            hooks.error_reported(Some(code), None, None);
+        }
+        let started = std::time::Instant::now();
+        hooks.complete("errors", 5);
+        // Each command timing out in turn would take 6 times the timeout
+        assert!(started.elapsed() < Duration::from_millis(1200));
+    }
+}
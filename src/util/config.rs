@@ -8,10 +8,18 @@
 use std::path::PathBuf;
 use structopt::{clap::arg_enum, StructOpt};
 
+use super::command_hook::CommandTemplate;
 use super::lib::{Checks, Config, DataOutputMode, Filter, InputOutput, Util, Views};
+use super::rdh_predicate::RdhPredicate;
+use crate::validators::its_payload_fsm_cont::PayloadWord;
+pub use crate::validators::rdh::FeeIdBounds;
+use crate::words::rdh::TriggerType;
 /// The Opt struct uses the [StructOpt] procedural macros and implements the [Config] trait, to provide convenient access to the command line arguments.
 #[derive(StructOpt, Debug)]
 #[structopt(setting = structopt::clap::AppSettings::ColoredHelp,
+    group = structopt::clap::ArgGroup::with_name("filter").multiple(true),
+    group = structopt::clap::ArgGroup::with_name("extract-hbfs"),
+    group = structopt::clap::ArgGroup::with_name("link-filter"),
     name = "fastPASTA - fast Protocol Analysis Scanning Tool for ALICE",
     about = "\n\
 Usage flow:  [INPUT] -> [FILTER] -> [VALIDATE/VIEW/OUTPUT]
@@ -42,46 +50,502 @@ pub struct Opt {
     #[structopt(short = "e", long = "max-errors", default_value = "0", global = true)]
     max_tolerate_errors: u32,
 
-    /// Set CRU link ID to filter by
-    #[structopt(short = "f", long, global = true)]
-    filter_link: Option<u8>,
+    /// Stop processing at the first error, print only that error and a minimal report, and exit with code 4
+    #[structopt(long = "fail-fast", global = true)]
+    fail_fast: bool,
 
-    /// Output raw data (default: stdout), requires a link to filter by. If Checks or Views are enabled, the output is supressed.
+    /// Once this many errors are reported from a link, further errors from the link are counted but not printed
+    #[structopt(long = "auto-mute-link-after", default_value = "1000", global = true)]
+    auto_mute_link_after: u32,
+
+    /// Print all errors from every link, regardless of how many errors a link has
+    #[structopt(
+        long = "no-auto-mute",
+        global = true,
+        conflicts_with("auto-mute-link-after")
+    )]
+    no_auto_mute: bool,
+
+    /// Run a command when an error is reported, at most once per error code, e.g. `'notify.sh {code} {mem_pos}'`. The placeholders are {code}, {mem_pos} and {link}
+    #[structopt(long = "on-error-exec", parse(try_from_str = parse_on_error_exec), global = true)]
+    on_error_exec: Option<CommandTemplate>,
+
+    /// Run a command when the run completes, e.g. `'notify.sh {verdict} {errors}'`. The placeholders are {verdict} (ok, errors or fatal), {errors} and {input}
+    #[structopt(long = "on-complete-exec", parse(try_from_str = parse_on_complete_exec), global = true)]
+    on_complete_exec: Option<CommandTemplate>,
+
+    /// Report errors strictly in order of memory position, at the cost of buffering them until all lower memory positions are processed
+    #[structopt(long = "ordered", global = true)]
+    ordered: bool,
+
+    /// Format of log messages on stderr, `json` writes each message as a JSON line
+    #[structopt(long = "log-format", default_value = "human", possible_values = &LogFormat::variants(), case_insensitive = true, global = true)]
+    log_format: LogFormat,
+
+    /// Append the link, layer and stave, orbit and pages counter of the current RDH to the payload error messages
+    #[structopt(long = "verbose-errors", global = true)]
+    verbose_errors: bool,
+
+    /// Log a warning for each TDT with a timeout flag set (timeout_to_start, timeout_start_stop, timeout_in_idle)
+    #[structopt(long = "warn-tdt-timeouts", global = true)]
+    warn_tdt_timeouts: bool,
+
+    /// Trigger bits that are allowed to differ between the TDH and RDH trigger_type, separated by commas, e.g. `hbr,hc`. Differences in these bits are logged as warnings instead of errors
+    #[structopt(long = "ignore-tdh-trigger-bits", global = true)]
+    ignore_tdh_trigger_bits: Option<TriggerBits>,
+
+    /// Size in bytes of the payload words shown by `view raw-words`. The ITS checks and the HBF view only support 10 byte GBT words
+    #[structopt(long = "payload-word-size", default_value = "10", global = true)]
+    payload_word_size: std::num::NonZeroUsize,
+
+    /// GBT wide mode, the payload words are 16 byte GBT frames whose upper 6 bytes are checked to be the parity of the word followed by 0x00, instead of the padding of data format 0. The views show all 16 bytes
+    #[structopt(long = "gbt-wide", global = true)]
+    gbt_wide: bool,
+
+    /// View the first HBFs while the checks process the whole input, `hbf` renders them as `view hbf` does. The view is written in one block when `--view-limit` HBFs are viewed or the input ends
+    #[structopt(long = "with-view", possible_values = &ConcurrentView::variants(), case_insensitive = true, global = true)]
+    with_view: Option<ConcurrentView>,
+
+    /// Number of HBFs viewed with `--with-view`, counted by their stop pages
+    #[structopt(long = "view-limit", default_value = "10", global = true)]
+    view_limit: std::num::NonZeroU32,
+
+    /// File to write the view of `--with-view` to, instead of stderr
+    #[structopt(long = "view-output", requires = "with-view", global = true)]
+    view_output: Option<PathBuf>,
+
+    /// Expected RDH system ID instead of the one of the check target, in hex (`0x99`) or decimal, e.g. for lab setups
+    #[structopt(long = "expect-system-id", parse(try_from_str = parse_system_id), global = true)]
+    expect_system_id: Option<u8>,
+
+    /// Expected RDH data format, every RDH with another data format is reported as an error, e.g. in acceptance tests of new firmware
+    #[structopt(long = "expect-data-format", possible_values = &["0", "2"], global = true)]
+    expect_data_format: Option<u8>,
+
+    /// Largest valid bunch counter of the RDHs and TDHs instead of the last bunch crossing of an LHC orbit (0xDEB), in hex (`0xFFF`) or decimal, e.g. for lab setups
+    #[structopt(long = "max-bc", parse(try_from_str = parse_max_bc), global = true)]
+    max_bc: Option<u16>,
+
+    /// Valid (inclusive) ranges of the layer and stave number in the FEE ID instead of the ITS ones, e.g. `layer=0..0,stave=0..1`. A range that is left out keeps the ITS range
+    #[structopt(long = "fee-id-bounds", global = true)]
+    fee_id_bounds: Option<FeeIdBounds>,
+
+    /// Write one line per CDP to stderr as it is handed to the thread that handles it, with its memory position, link, FEE ID, orbit, pages counter, stop bit, payload size and the name of that thread
+    #[structopt(long = "trace-cdps", global = true)]
+    trace_cdps: bool,
+
+    /// Only trace every Nth CDP with `--trace-cdps` (default: every CDP)
+    #[structopt(long = "trace-every", global = true, requires("trace-cdps"))]
+    trace_every: Option<std::num::NonZeroU32>,
+
+    /// Analyses of the input stream to add to the report, repeat the option for several analyses. `interleaving` measures the largest gap between consecutive pages of the same HBF per link, in CDPs and bytes. `throughput` estimates the throughput and dead time of each link from its bytes and the orbits in which it sent data. `entropy` estimates how compressible the payloads of each link are, in bits per byte. `payload-uniformity` counts the distinct payload contents of each link, with the share of the most common one and the first outliers, e.g. for calibration runs
+    #[structopt(long = "analyze", number_of_values = 1, possible_values = &Analysis::VALID_NAMES, case_insensitive = true, global = true)]
+    analyze: Vec<Analysis>,
+
+    /// With `--analyze throughput`, also report the throughput and dead time of each link in consecutive windows of this many orbits
+    #[structopt(long = "throughput-window", global = true)]
+    throughput_window: Option<std::num::NonZeroU32>,
+
+    /// With `--analyze entropy`, sample the payload of every Nth CDP of each link
+    #[structopt(long = "entropy-sample-every", default_value = "10", global = true)]
+    entropy_sample_every: std::num::NonZeroU32,
+
+    /// Configured source of the triggers of the run, the TDH internal_trigger bit is checked against it. With `external`, the bc, orbit and trigger type of every opening TDH are compared to the RDH
+    #[structopt(long = "trigger-source", default_value = "any", possible_values = &TriggerSource::variants(), case_insensitive = true, global = true)]
+    trigger_source: TriggerSource,
+
+    /// Decode payloads without 0x00 padding as data format 2 when the RDH declares data format 0, instead of skipping them after reporting the mismatch
+    #[structopt(long = "trust-payload-format", global = true)]
+    trust_payload_format: bool,
+
+    /// Check the payloads of links whose RDH system_id is not the one of the check target, e.g. links of another detector on a shared CRU, instead of skipping them
+    #[structopt(long = "force-check-all-links", global = true)]
+    force_check_all_links: bool,
+
+    /// Add details to the JSON report, `cdp` lists every CDP with its RDH fields and the number of each kind of word in its payload. Requires `--log-format json` and an ITS check
+    #[structopt(long = "report-detail", possible_values = &ReportDetail::variants(), case_insensitive = true, global = true)]
+    report_detail: Option<ReportDetail>,
+
+    /// Stop with an error if `--report-detail cdp` would list more than this many CDPs, unless `--force` is given
+    #[structopt(
+        long = "report-detail-max-cdps",
+        default_value = "100000",
+        global = true
+    )]
+    report_detail_max_cdps: u64,
+
+    /// Verify that each link has exactly this many HBFs between consecutive HBFs opened with the TF trigger bit set, e.g. 128, and report the timeframes in the summary
+    #[structopt(long = "hbf-per-tf", global = true)]
+    hbf_per_tf: Option<std::num::NonZeroU32>,
+
+    /// Add a 0-100 quality score of the run to the report, computed from the error-free HBFs, the completeness of the expected links and the lanes with fatal status
+    #[structopt(long = "quality-score", global = true)]
+    quality_score: bool,
+
+    /// File with the weights and thresholds of the quality score, and the links expected in the data, one `key = value` setting per line. Implies `--quality-score`
+    #[structopt(long = "quality-config", parse(from_os_str), global = true)]
+    quality_config: Option<PathBuf>,
+
+    /// Report links whose last page is more than this many orbits before the end of the data as having gone silent
+    #[structopt(long = "silent-link-orbits", default_value = "128", global = true)]
+    silent_link_orbits: u32,
+
+    /// With `check all`, compare the trigger types of the HBFs of all links of a CRU at the same orbit within a sliding window of this many orbits
+    #[structopt(
+        long = "trigger-consistency-orbits",
+        default_value = "256",
+        global = true
+    )]
+    trigger_consistency_orbits: std::num::NonZeroU32,
+
+    /// Verify internal invariants of fastpasta that are only verified in debug builds, such as the memory positions of the CDPs being in order. Violations are reported as internal errors
+    #[structopt(long = "paranoid", global = true)]
+    paranoid: bool,
+
+    /// Number of CDPs the reader sends to the checks or views at a time. Results don't depend on it, it trades memory for less overhead
+    #[structopt(long = "cdp-chunk-size", default_value = "100", global = true)]
+    cdp_chunk_size: std::num::NonZeroUsize,
+
+    /// Track the peak memory usage, the resident set size of the process and the bytes of CDP chunks in flight between the threads, and show it in the report
+    #[structopt(long = "track-memory", global = true)]
+    track_memory: bool,
+
+    /// Stop processing if an RDH has a different version than the first RDH of the input, instead of continuing to parse it as the first version
+    #[structopt(long = "strict-version", global = true)]
+    strict_version: bool,
+
+    /// Report a link whose first RDH has a nonzero packet_counter or pages_counter as an error instead of a warning, as the input does not start at a packet boundary
+    #[structopt(long = "strict-start", global = true)]
+    strict_start: bool,
+
+    /// Maximum strictness for acceptance tests: warnings are reported as errors, all optional checks are enabled, unknown system IDs are forbidden, and structural surprises such as truncated payloads stop processing
+    #[structopt(long = "strict", global = true)]
+    strict: bool,
+
+    /// Check that the orbit increments by exactly 1 between HBFs of a link, flagging skipped orbits (default on for the `ITS` target)
+    #[structopt(long = "expect-hbf-per-orbit", global = true)]
+    expect_hbf_per_orbit: bool,
+
+    /// Don't check that the orbit increments by exactly 1 between HBFs of a link
+    #[structopt(
+        long = "no-expect-hbf-per-orbit",
+        global = true,
+        conflicts_with("expect-hbf-per-orbit")
+    )]
+    no_expect_hbf_per_orbit: bool,
+
+    /// CSV file with the columns `fee_id,cru_id,link_id,endpoint`, mapping each FEE to where it is cabled. With a check, the FEE ID, CRU ID, link ID and endpoint of every CDP must match the row of its FEE
+    #[structopt(long = "hw-map", parse(from_os_str), global = true)]
+    hw_map: Option<PathBuf>,
+
+    /// Parallelism of the checks, `link` checks each link in its own thread, `payload` additionally checks the payloads of a link in parallel
+    #[structopt(long = "parallel", default_value = "link", possible_values = &ParallelMode::variants(), case_insensitive = true, global = true)]
+    parallel: ParallelMode,
+
+    /// Set CRU link IDs to filter by, separated by commas (e.g. `0,2,5`), or `all` to keep the CDPs of all links
+    #[structopt(
+        short = "f",
+        long,
+        global = true,
+        groups = &["filter", "link-filter"]
+    )]
+    filter_link: Option<LinkFilter>,
+
+    /// Set CRU link IDs to exclude, separated by commas (e.g. `8` or `3,8`). The CDPs of all other links are kept
+    #[structopt(
+        long = "exclude-link",
+        parse(try_from_str = parse_exclude_links),
+        global = true,
+        groups = &["filter", "link-filter"]
+    )]
+    exclude_link: Option<LinkFilter>,
+
+    /// Set FEE ID to filter by, in hex (`0x502A`) or decimal, e.g. to select one link when the same link ID is used on several CRUs
+    #[structopt(long = "filter-fee", parse(try_from_str = parse_fee_id), global = true, group = "filter")]
+    filter_fee: Option<u16>,
+
+    /// Set CRU ID to filter by, in hex (`0x1A`) or decimal, e.g. to select one CRU of a file recorded on an FLP with several CRUs
+    #[structopt(long = "filter-cru", parse(try_from_str = parse_cru_id), global = true, group = "filter")]
+    filter_cru: Option<u16>,
+
+    /// Set trigger type to filter by, e.g. `PhT`, `SOC`, `EOC`, `HB`, `SOT` or `EOT`. Only the CDPs with the bit of the trigger type set in the RDH trigger_type are kept
+    #[structopt(long = "filter-trigger", global = true, group = "filter")]
+    filter_trigger: Option<TriggerType>,
+
+    /// Set ITS stave to filter by, as layer and stave number, e.g. `L3_12`. Only the CDPs whose FEE ID belongs to the stave are kept
+    #[structopt(long = "filter-its-stave", global = true, group = "filter")]
+    filter_its_stave: Option<ItsStave>,
+
+    /// Set an inclusive range of RDH orbits to filter by, in hex (`0x0B7DD500`) or decimal, e.g. `--filter-orbit-range 0x0B7DD500 0x0B7DD600`. Stops reading once all links seen so far are past the end of the range
+    #[structopt(
+        long = "filter-orbit-range",
+        number_of_values = 2,
+        value_names = &["start", "end"],
+        parse(try_from_str = parse_orbit),
+        global = true,
+        group = "filter"
+    )]
+    filter_orbit_range: Option<Vec<u32>>,
+
+    /// Set an inclusive range of packet numbers to filter by, e.g. `--filter-packet-range 100..200`. The packet number counts the wrap-arounds of the 8 bit RDH packet_counter of each link since the start of the input, so packet 300 is the page with packet_counter 44 after the first wrap-around. Combine it with `--filter-link` to select the packets of a link. Stops reading once all links seen so far are past the end of the range
+    #[structopt(long = "filter-packet-range", global = true, group = "filter")]
+    filter_packet_range: Option<PacketRange>,
+
+    /// File with one hex memory offset per line, only the CDPs with an RDH at one of the offsets are kept
+    #[structopt(
+        long = "extract-offsets",
+        parse(from_os_str),
+        global = true,
+        group = "filter"
+    )]
+    extract_offsets: Option<PathBuf>,
+
+    /// Only keep the CDPs whose RDH matches an expression such as `stop_bit==1 && pages_counter>30`, see the `count` subcommand for the syntax
+    #[structopt(long = "filter-where", global = true, group = "filter")]
+    filter_where: Option<RdhPredicate>,
+
+    /// Keep only the first N complete HBFs of each link, and stop reading once all links seen so far have N HBFs. Requires an output without checks or views
+    #[structopt(
+        long = "extract-first-hbfs",
+        visible_alias = "filter-max-hbfs",
+        global = true,
+        groups = &["filter", "extract-hbfs"]
+    )]
+    extract_first_hbfs: Option<std::num::NonZeroU32>,
+
+    /// Keep only the last N complete HBFs of each link, buffered until the end of the input. Requires an output without checks or views
+    #[structopt(
+        long = "extract-last-hbfs",
+        global = true,
+        groups = &["filter", "extract-hbfs"]
+    )]
+    extract_last_hbfs: Option<std::num::NonZeroU32>,
+
+    /// Only keep the CDPs whose payload contains a diagnostic APE word, a GBT word with an ID in the 0xFx range that is not a TDT or CDW. Requires an output without checks or views
+    #[structopt(long = "filter-ape", global = true, group = "filter")]
+    filter_ape: bool,
+
+    /// Drop the HBFs whose pages contain no data words, only RDHs and status words, from the output. Either all pages of an HBF are written or none
+    #[structopt(long = "drop-empty-hbfs", global = true, group = "filter")]
+    drop_empty_hbfs: bool,
+
+    /// Pages after which an HBF without a stop page is truncated when the HBFs of each link are extracted, the rest of its pages are dropped
+    #[structopt(long = "max-hbf-pages", default_value = "10000", global = true)]
+    max_hbf_pages: std::num::NonZeroU32,
+
+    /// MiB after which an HBF without a stop page is truncated when the HBFs of each link are extracted, the rest of its pages are dropped
+    #[structopt(long = "max-hbf-mib", default_value = "128", global = true)]
+    max_hbf_mib: std::num::NonZeroU32,
+
+    /// Output raw data (default: stdout), requires a filter or offsets to extract. If Checks or Views are enabled, the output is supressed.
     #[structopt(
         name = "OUTPUT DATA",
         short = "o",
         long = "output",
         parse(from_os_str),
         global = true,
-        requires("filter-link")
+        requires("filter")
     )]
     output: Option<PathBuf>,
+
+    /// Write the output even if it is an unmodified copy of the input, e.g. with `--filter-link all`
+    #[structopt(long = "force", global = true)]
+    force: bool,
+
+    /// Skip probing the first RDHs of the input, and process it even if it does not look like CRU raw data
+    #[structopt(long = "skip-input-probe", global = true)]
+    skip_input_probe: bool,
+
+    /// If writing the output fails, e.g. on a full disk, report it once and continue the checks, dropping the rest of the output
+    #[structopt(long = "keep-going-on-write-error", global = true)]
+    keep_going_on_write_error: bool,
+
+    /// Save the state of the running checks to this file on exit, and resume from it on startup if it exists. Requires an input file.
+    #[structopt(
+        long = "checkpoint-file",
+        parse(from_os_str),
+        global = true,
+        requires("INPUT DATA")
+    )]
+    checkpoint_file: Option<PathBuf>,
+
+    /// Write every reported error to this CSV file as it is reported, one row per error, errors of muted links are left out
+    #[structopt(long = "error-csv", parse(from_os_str), global = true)]
+    error_csv: Option<PathBuf>,
+
+    /// Also write the errors of muted links to the error CSV, with the severity `muted`
+    #[structopt(long = "error-csv-include-muted", global = true, requires("error-csv"))]
+    error_csv_include_muted: bool,
+
+    /// Write every reported error to this compact binary file, including the errors of muted links, decode it with `fastpasta errors decode <FILE>`
+    #[structopt(long = "error-log-binary", parse(from_os_str), global = true)]
+    error_log_binary: Option<PathBuf>,
+
+    /// Write the CDPs with errors to this file, as raw data that can be read again by fastpasta, only used with checks
+    #[structopt(long = "save-failing-cdps", parse(from_os_str), global = true)]
+    save_failing_cdps: Option<PathBuf>,
 }
 
 /// Implementing the config super trait requires implementing all the sub traits
 impl Config for Opt {}
 
+impl Opt {
+    /// Rewrites the effective configuration of `--strict`, all options implied by strict mode are set here.
+    ///
+    /// Options that loosen the checks are overridden:
+    /// - The RDH version may not change within the input.
+    /// - Each link must start at a packet boundary.
+    /// - The orbit must increment by exactly 1 between HBFs.
+    /// - No TDH trigger bits are ignored.
+    /// - TDT timeout flags are reported, and as errors due to the severity of warnings in strict mode.
+    ///
+    /// The severity of warnings is mapped to errors by the [StatsController][crate::stats::stats_controller::StatsController].
+    pub fn apply_strict_mode(mut self) -> Self {
+        if self.strict {
+            self.strict_version = true;
+            self.strict_start = true;
+            self.expect_hbf_per_orbit = true;
+            self.no_expect_hbf_per_orbit = false;
+            self.ignore_tdh_trigger_bits = None;
+            self.warn_tdt_timeouts = true;
+        }
+        self
+    }
+}
+
 impl Views for Opt {
     #[inline]
     fn view(&self) -> Option<View> {
         if let Some(sub_cmd) = &self.cmd {
             match sub_cmd {
-                Command::View(view) => match view {
-                    View::Rdh => Some(View::Rdh),
-                    View::Hbf => Some(View::Hbf),
-                },
+                Command::View(view) => Some(view.clone()),
                 _ => None,
             }
         } else {
             None
         }
     }
+    #[inline]
+    fn payload_word_size(&self) -> usize {
+        self.payload_word_size.get()
+    }
+    #[inline]
+    fn list_links(&self) -> Option<ListLinks> {
+        match &self.cmd {
+            Some(Command::ListLinks(list_links)) => Some(list_links.clone()),
+            _ => None,
+        }
+    }
+    #[inline]
+    fn count(&self) -> Option<Count> {
+        match &self.cmd {
+            Some(Command::Count(count)) => Some(count.clone()),
+            _ => None,
+        }
+    }
+    #[inline]
+    fn decode_errors(&self) -> Option<DecodeErrors> {
+        match &self.cmd {
+            Some(Command::Errors(Errors::Decode(decode_errors))) => Some(decode_errors.clone()),
+            _ => None,
+        }
+    }
+    #[inline]
+    fn verify_filter(&self) -> Option<VerifyFilter> {
+        match &self.cmd {
+            Some(Command::VerifyFilter(verify_filter)) => Some(verify_filter.clone()),
+            _ => None,
+        }
+    }
+    #[inline]
+    fn with_view(&self) -> Option<ConcurrentView> {
+        self.with_view
+    }
+    #[inline]
+    fn view_limit(&self) -> std::num::NonZeroU32 {
+        self.view_limit
+    }
+    #[inline]
+    fn view_output(&self) -> Option<&PathBuf> {
+        self.view_output.as_ref()
+    }
 }
 
 impl Filter for Opt {
     #[inline]
-    fn filter_link(&self) -> Option<u8> {
-        self.filter_link
+    fn filter_links(&self) -> Option<&[u8]> {
+        match &self.filter_link {
+            Some(LinkFilter::Links(link_ids)) => Some(link_ids),
+            Some(LinkFilter::All) | None => None,
+        }
+    }
+    #[inline]
+    fn exclude_links(&self) -> Option<&[u8]> {
+        match &self.exclude_link {
+            Some(LinkFilter::Links(link_ids)) => Some(link_ids),
+            Some(LinkFilter::All) | None => None,
+        }
+    }
+    #[inline]
+    fn filter_fee(&self) -> Option<u16> {
+        self.filter_fee
+    }
+    #[inline]
+    fn filter_cru(&self) -> Option<u16> {
+        self.filter_cru
+    }
+    #[inline]
+    fn filter_trigger(&self) -> Option<TriggerType> {
+        self.filter_trigger
+    }
+    #[inline]
+    fn filter_its_stave(&self) -> Option<ItsStave> {
+        self.filter_its_stave
+    }
+    #[inline]
+    fn filter_orbit_range(&self) -> Option<(u32, u32)> {
+        self.filter_orbit_range
+            .as_ref()
+            .map(|orbits| (orbits[0], orbits[1]))
+    }
+    #[inline]
+    fn filter_packet_range(&self) -> Option<PacketRange> {
+        self.filter_packet_range
+    }
+    #[inline]
+    fn filter_all_links(&self) -> bool {
+        self.filter_link == Some(LinkFilter::All)
+    }
+    #[inline]
+    fn extract_offsets(&self) -> &Option<PathBuf> {
+        &self.extract_offsets
+    }
+    #[inline]
+    fn filter_where(&self) -> Option<RdhPredicate> {
+        self.filter_where.clone()
+    }
+    #[inline]
+    fn extract_hbfs(&self) -> Option<HbfExtract> {
+        match (self.extract_first_hbfs, self.extract_last_hbfs) {
+            (Some(hbfs), _) => Some(HbfExtract::First(hbfs.get())),
+            (None, Some(hbfs)) => Some(HbfExtract::Last(hbfs.get())),
+            (None, None) => None,
+        }
+    }
+    #[inline]
+    fn filter_ape(&self) -> bool {
+        self.filter_ape
+    }
+    #[inline]
+    fn drop_empty_hbfs(&self) -> bool {
+        self.drop_empty_hbfs
+    }
+    #[inline]
+    fn hbf_cap(&self) -> HbfCap {
+        HbfCap {
+            pages: self.max_hbf_pages.get(),
+            bytes: self.max_hbf_mib.get() as u64 * 1024 * 1024,
+        }
     }
 }
 
@@ -94,12 +558,37 @@ impl Checks for Opt {
                     Check::All(target) => Some(Check::All(target.clone())),
                     Check::Sanity(target) => Some(Check::Sanity(target.clone())),
                 },
-                Command::View(_) => None,
+                Command::View(_)
+                | Command::ListLinks(_)
+                | Command::Count(_)
+                | Command::Errors(_)
+                | Command::VerifyFilter(_) => None,
             }
         } else {
             None
         }
     }
+    #[inline]
+    fn parallel(&self) -> ParallelMode {
+        self.parallel.clone()
+    }
+    #[inline]
+    fn expect_hbf_per_orbit(&self) -> bool {
+        if self.no_expect_hbf_per_orbit {
+            false
+        } else {
+            self.expect_hbf_per_orbit
+                || matches!(self.check().and_then(|c| c.target()), Some(System::ITS))
+        }
+    }
+    #[inline]
+    fn hw_map(&self) -> &Option<PathBuf> {
+        &self.hw_map
+    }
+    #[inline]
+    fn gbt_wide(&self) -> bool {
+        self.gbt_wide
+    }
 }
 
 impl InputOutput for Opt {
@@ -111,6 +600,38 @@ impl InputOutput for Opt {
     fn output(&self) -> &Option<PathBuf> {
         &self.output
     }
+    #[inline]
+    fn checkpoint_file(&self) -> &Option<PathBuf> {
+        &self.checkpoint_file
+    }
+    #[inline]
+    fn error_csv(&self) -> &Option<PathBuf> {
+        &self.error_csv
+    }
+    #[inline]
+    fn error_csv_include_muted(&self) -> bool {
+        self.error_csv_include_muted
+    }
+    #[inline]
+    fn error_log_binary(&self) -> &Option<PathBuf> {
+        &self.error_log_binary
+    }
+    #[inline]
+    fn save_failing_cdps(&self) -> &Option<PathBuf> {
+        &self.save_failing_cdps
+    }
+    #[inline]
+    fn force(&self) -> bool {
+        self.force
+    }
+    #[inline]
+    fn skip_input_probe(&self) -> bool {
+        self.skip_input_probe
+    }
+    #[inline]
+    fn keep_going_on_write_error(&self) -> bool {
+        self.keep_going_on_write_error
+    }
     // Determine data output mode
     #[inline]
     fn output_mode(&self) -> DataOutputMode {
@@ -125,7 +646,13 @@ impl InputOutput for Opt {
             }
         }
         // if output is not set, but checks or prints are enabled, suppress output
-        else if self.check().is_some() || self.view().is_some() {
+        else if self.check().is_some()
+            || self.view().is_some()
+            || self.list_links().is_some()
+            || self.count().is_some()
+            || self.decode_errors().is_some()
+            || self.verify_filter().is_some()
+        {
             DataOutputMode::None
         }
         // if output is not set and no checks are enabled, output to stdout
@@ -144,6 +671,149 @@ impl Util for Opt {
     fn max_tolerate_errors(&self) -> u32 {
         self.max_tolerate_errors
     }
+    #[inline]
+    fn fail_fast(&self) -> bool {
+        self.fail_fast
+    }
+    #[inline]
+    fn auto_mute_link_after(&self) -> Option<u32> {
+        if self.no_auto_mute {
+            None
+        } else {
+            Some(self.auto_mute_link_after)
+        }
+    }
+    #[inline]
+    fn ordered(&self) -> bool {
+        self.ordered
+    }
+    #[inline]
+    fn on_error_exec(&self) -> Option<CommandTemplate> {
+        self.on_error_exec.clone()
+    }
+    #[inline]
+    fn on_complete_exec(&self) -> Option<CommandTemplate> {
+        self.on_complete_exec.clone()
+    }
+    #[inline]
+    fn verbose_errors(&self) -> bool {
+        self.verbose_errors
+    }
+    #[inline]
+    fn warn_tdt_timeouts(&self) -> bool {
+        self.warn_tdt_timeouts
+    }
+    #[inline]
+    fn ignore_tdh_trigger_bits(&self) -> Vec<TriggerType> {
+        self.ignore_tdh_trigger_bits
+            .as_ref()
+            .map(|bits| bits.0.clone())
+            .unwrap_or_default()
+    }
+    #[inline]
+    fn trace_cdps(&self) -> Option<u32> {
+        if self.trace_cdps {
+            Some(self.trace_every.map_or(1, |every| every.get()))
+        } else {
+            None
+        }
+    }
+    #[inline]
+    fn expect_system_id(&self) -> Option<u8> {
+        self.expect_system_id
+    }
+    #[inline]
+    fn expect_data_format(&self) -> Option<u8> {
+        self.expect_data_format
+    }
+    #[inline]
+    fn fee_id_bounds(&self) -> Option<FeeIdBounds> {
+        self.fee_id_bounds
+    }
+    #[inline]
+    fn max_bc(&self) -> u16 {
+        self.max_bc.unwrap_or(crate::words::constants::MAX_BC)
+    }
+    #[inline]
+    fn strict_version(&self) -> bool {
+        self.strict_version
+    }
+    #[inline]
+    fn strict_start(&self) -> bool {
+        self.strict_start
+    }
+    #[inline]
+    fn strict(&self) -> bool {
+        self.strict
+    }
+    #[inline]
+    fn trigger_source(&self) -> TriggerSource {
+        self.trigger_source
+    }
+    #[inline]
+    fn trust_payload_format(&self) -> bool {
+        self.trust_payload_format
+    }
+    #[inline]
+    fn force_check_all_links(&self) -> bool {
+        self.force_check_all_links
+    }
+    #[inline]
+    fn log_format(&self) -> LogFormat {
+        self.log_format.clone()
+    }
+    #[inline]
+    fn analyze(&self, analysis: Analysis) -> bool {
+        self.analyze.contains(&analysis)
+    }
+    #[inline]
+    fn report_detail(&self) -> Option<ReportDetail> {
+        self.report_detail
+    }
+    #[inline]
+    fn report_detail_max_cdps(&self) -> u64 {
+        self.report_detail_max_cdps
+    }
+    #[inline]
+    fn throughput_window(&self) -> Option<u32> {
+        self.throughput_window.map(|orbits| orbits.get())
+    }
+    #[inline]
+    fn entropy_sample_every(&self) -> std::num::NonZeroU32 {
+        self.entropy_sample_every
+    }
+    #[inline]
+    fn hbf_per_tf(&self) -> Option<u32> {
+        self.hbf_per_tf.map(|hbf_per_tf| hbf_per_tf.get())
+    }
+    #[inline]
+    fn quality_score(&self) -> bool {
+        self.quality_score || self.quality_config.is_some()
+    }
+    #[inline]
+    fn quality_config(&self) -> &Option<PathBuf> {
+        &self.quality_config
+    }
+    #[inline]
+    fn silent_link_orbits(&self) -> u32 {
+        self.silent_link_orbits
+    }
+    #[inline]
+    fn trigger_consistency_orbits(&self) -> u32 {
+        self.trigger_consistency_orbits.get()
+    }
+    #[inline]
+    fn paranoid(&self) -> bool {
+        self.paranoid || cfg!(debug_assertions)
+    }
+    #[inline]
+    fn cdp_chunk_size(&self) -> usize {
+        self.cdp_chunk_size.get()
+    }
+    #[inline]
+    fn track_memory(&self) -> bool {
+        self.track_memory
+    }
 }
 
 /// Possible subcommands at the upper level
@@ -153,6 +823,14 @@ pub enum Command {
     Check(Check),
     /// [View] subcommand to enable views, needs to be followed by a [View] type subcommand
     View(View),
+    /// [ListLinks] subcommand to list the links found in the input by scanning only the RDHs
+    ListLinks(ListLinks),
+    /// [Count] subcommand to count the CDPs whose RDH matches an expression by scanning only the RDHs
+    Count(Count),
+    /// [Errors] subcommand to work with binary error logs, needs to be followed by an [Errors] type subcommand
+    Errors(Errors),
+    /// [VerifyFilter] subcommand to verify that a filtered output contains exactly the CDPs of its source kept by a filter
+    VerifyFilter(VerifyFilter),
 }
 
 /// Check subcommand to enable checks, needs to be followed by a check type subcommand and a target system
@@ -188,7 +866,393 @@ pub enum View {
     Rdh,
     /// Print formatted HBFs to stdout
     #[structopt(setting = structopt::clap::AppSettings::ColoredHelp)]
-    Hbf,
+    Hbf(HbfViewOptions),
+    /// Print the payloads as words of `--payload-word-size` bytes with their memory positions, without decoding them as ITS words
+    #[structopt(setting = structopt::clap::AppSettings::ColoredHelp)]
+    RawWords,
+}
+
+/// Options for the HBF view
+#[derive(structopt::StructOpt, Debug, Clone, Default)]
+pub struct HbfViewOptions {
+    /// Only show words of the given types, separated by commas, e.g. `tdh,tdt` (valid types: ihw, tdh, tdt, ddw0)
+    #[structopt(long)]
+    pub only: Option<WordTypeFilter>,
+    /// Don't show RDHs
+    #[structopt(long)]
+    pub no_rdh: bool,
+    /// Append the UTC time of the orbit and bc of each RDH and TDH, computed from the time of orbit 0 given with `--orbit-zero-utc`
+    #[structopt(long, requires = "orbit-zero-utc")]
+    pub absolute_time: bool,
+    /// UTC time of orbit 0, e.g. `2023-04-12T11:46:40.5Z` or the seconds since the Unix epoch
+    #[structopt(long, parse(try_from_str = crate::util::run_metadata::parse_utc))]
+    pub orbit_zero_utc: Option<std::time::SystemTime>,
+}
+
+/// Set of [PayloadWord] types to show in a view, parsed from a comma separated list of word type names
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordTypeFilter(Vec<PayloadWord>);
+
+impl WordTypeFilter {
+    /// Names of the word types that can be filtered by.
+    pub const VALID_NAMES: [&'static str; 4] = ["ihw", "tdh", "tdt", "ddw0"];
+
+    /// Returns true if the word type is in the filter.
+    pub fn contains(&self, word: &PayloadWord) -> bool {
+        self.0.contains(word)
+    }
+}
+
+impl std::str::FromStr for WordTypeFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = Vec::new();
+        for name in s.split(',').map(|name| name.trim().to_lowercase()) {
+            match name.as_str() {
+                "ihw" => words.extend([PayloadWord::IHW, PayloadWord::IHW_continuation]),
+                "tdh" => words.extend([
+                    PayloadWord::TDH,
+                    PayloadWord::TDH_continuation,
+                    PayloadWord::TDH_after_packet_done,
+                ]),
+                "tdt" => words.push(PayloadWord::TDT),
+                "ddw0" => words.push(PayloadWord::DDW0),
+                _ => {
+                    return Err(format!(
+                        "Unknown word type '{name}', valid word types are: {}",
+                        Self::VALID_NAMES.join(", ")
+                    ))
+                }
+            }
+        }
+        Ok(Self(words))
+    }
+}
+
+/// Set of [TriggerType] bits, parsed from a comma separated list of trigger type names
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriggerBits(Vec<TriggerType>);
+
+impl std::str::FromStr for TriggerBits {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<TriggerType>, String>>()
+            .map(Self)
+    }
+}
+
+fn parse_system_id(s: &str) -> Result<u8, String> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex_digits) => u8::from_str_radix(hex_digits, 16),
+        None => s.parse(),
+    }
+    .map_err(|e| format!("Invalid system ID '{s}': {e}"))
+}
+
+fn parse_exclude_links(s: &str) -> Result<LinkFilter, String> {
+    if s.eq_ignore_ascii_case("all") {
+        return Err("Excluding all links would keep none of the CDPs".to_string());
+    }
+    s.parse()
+}
+
+fn parse_fee_id(s: &str) -> Result<u16, String> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex_digits) => u16::from_str_radix(hex_digits, 16),
+        None => s.parse(),
+    }
+    .map_err(|e| format!("Invalid FEE ID '{s}': {e}"))
+}
+
+fn parse_cru_id(s: &str) -> Result<u16, String> {
+    let cru_id = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex_digits) => u16::from_str_radix(hex_digits, 16),
+        None => s.parse(),
+    }
+    .map_err(|e| format!("Invalid CRU ID '{s}': {e}"))?;
+    if cru_id > 0xFFF {
+        return Err(format!(
+            "Invalid CRU ID '{s}': the CRU ID is 12 bits, at most 4095"
+        ));
+    }
+    Ok(cru_id)
+}
+
+fn parse_orbit(s: &str) -> Result<u32, String> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex_digits) => u32::from_str_radix(hex_digits, 16),
+        None => s.parse(),
+    }
+    .map_err(|e| format!("Invalid orbit '{s}': {e}"))
+}
+
+fn parse_max_bc(s: &str) -> Result<u16, String> {
+    let max_bc = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex_digits) => u16::from_str_radix(hex_digits, 16),
+        None => s.parse(),
+    }
+    .map_err(|e| format!("Invalid maximum bc '{s}': {e}"))?;
+    if max_bc > crate::words::constants::BC_FIELD_MAX {
+        return Err(format!(
+            "Invalid maximum bc '{s}': the bc field only has 12 bits, the maximum is {:#X}",
+            crate::words::constants::BC_FIELD_MAX
+        ));
+    }
+    Ok(max_bc)
+}
+
+fn parse_on_error_exec(s: &str) -> Result<CommandTemplate, String> {
+    CommandTemplate::parse(s, &super::command_hook::ON_ERROR_PLACEHOLDERS)
+}
+
+fn parse_on_complete_exec(s: &str) -> Result<CommandTemplate, String> {
+    CommandTemplate::parse(s, &super::command_hook::ON_COMPLETE_PLACEHOLDERS)
+}
+
+/// Value of `--filter-link`, a list of link IDs or all links.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkFilter {
+    /// Keep the CDPs of any of the links, sorted by link ID without duplicates.
+    Links(Vec<u8>),
+    /// Keep the CDPs of all links.
+    All,
+}
+
+impl std::str::FromStr for LinkFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("all") {
+            return Ok(LinkFilter::All);
+        }
+        let mut link_ids = s
+            .split(',')
+            .map(|link_id| {
+                link_id.trim().parse().map_err(|_| {
+                    format!("Expected link IDs (0-255) separated by commas or `all`, got '{s}'")
+                })
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
+        link_ids.sort_unstable();
+        link_ids.dedup();
+        Ok(LinkFilter::Links(link_ids))
+    }
+}
+
+/// An ITS stave, identified by its layer and stave number, e.g. `L3_12`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ItsStave {
+    /// Layer, 0-6.
+    pub layer: u8,
+    /// Stave number within the layer.
+    pub stave: u8,
+}
+
+impl ItsStave {
+    /// The stave of the FEE ID of an RDH.
+    pub fn from_fee_id(fee_id: u16) -> Self {
+        Self {
+            layer: crate::words::lib::layer_from_feeid(fee_id),
+            stave: crate::words::lib::stave_number_from_feeid(fee_id),
+        }
+    }
+}
+
+impl std::fmt::Display for ItsStave {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "L{}_{}", self.layer, self.stave)
+    }
+}
+
+impl std::str::FromStr for ItsStave {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (layer, stave) = s
+            .strip_prefix(['L', 'l'])
+            .and_then(|layer_stave| layer_stave.split_once('_'))
+            .ok_or_else(|| {
+                format!("Expected an ITS stave as `L<layer>_<stave>`, e.g. `L3_12`, got '{s}'")
+            })?;
+        let layer: u8 = layer
+            .parse()
+            .map_err(|e| format!("Invalid layer in '{s}': {e}"))?;
+        let stave: u8 = stave
+            .parse()
+            .map_err(|e| format!("Invalid stave in '{s}': {e}"))?;
+        let stave_count = crate::words::lib::stave_count_from_layer(layer);
+        if stave_count == 0 {
+            return Err(format!("Invalid layer in '{s}': the ITS has layers 0 to 6"));
+        }
+        if stave >= stave_count {
+            return Err(format!(
+                "Invalid stave in '{s}': layer {layer} has staves 0 to {}",
+                stave_count - 1
+            ));
+        }
+        Ok(Self { layer, stave })
+    }
+}
+
+/// Inclusive range of packet numbers to filter by, e.g. `100..200`, see [FilterPacketRange][crate::input::cdp_filter::FilterPacketRange].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketRange {
+    /// First packet number kept.
+    pub start: u64,
+    /// Last packet number kept.
+    pub end: u64,
+}
+
+impl PacketRange {
+    /// Returns true if the packet number is in the range.
+    pub fn contains(&self, packet_number: u64) -> bool {
+        (self.start..=self.end).contains(&packet_number)
+    }
+}
+
+impl std::fmt::Display for PacketRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+impl std::str::FromStr for PacketRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s.split_once("..").ok_or_else(|| {
+            format!("Expected a packet range as `<start>..<end>`, e.g. `100..200`, got '{s}'")
+        })?;
+        let start: u64 = start
+            .parse()
+            .map_err(|e| format!("Invalid start of packet range '{s}': {e}"))?;
+        let end: u64 = end
+            .parse()
+            .map_err(|e| format!("Invalid end of packet range '{s}': {e}"))?;
+        if start > end {
+            return Err(format!(
+                "Packet range '{s}' ends before it starts: {start} > {end}"
+            ));
+        }
+        Ok(Self { start, end })
+    }
+}
+
+/// HBFs of each link to keep, set with `--extract-first-hbfs` or `--extract-last-hbfs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HbfExtract {
+    /// The first N complete HBFs.
+    First(u32),
+    /// The last N complete HBFs.
+    Last(u32),
+}
+
+/// Size of an HBF after which an HBF without a stop page is truncated, set with `--max-hbf-pages` and `--max-hbf-mib`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HbfCap {
+    /// Pages of the HBF.
+    pub pages: u32,
+    /// Bytes of the RDHs and payloads of the pages of the HBF.
+    pub bytes: u64,
+}
+
+/// Options for listing the links found in the input
+#[derive(structopt::StructOpt, Debug, Clone)]
+#[structopt(setting = structopt::clap::AppSettings::ColoredHelp, about = "List the links found in the input along with the number of CDPs of each link.\n\
+Only RDHs are read, payloads are skipped.")]
+pub struct ListLinks {
+    /// Print the list as JSON
+    #[structopt(long)]
+    pub json: bool,
+    /// Only scan the first N MB of the input, the list is then marked as sampled
+    #[structopt(long = "first-n-mb")]
+    pub first_n_mb: Option<u64>,
+}
+
+/// Options for counting the CDPs matching an expression
+#[derive(structopt::StructOpt, Debug, Clone)]
+#[structopt(setting = structopt::clap::AppSettings::ColoredHelp, about = "Count the CDPs whose RDH matches an expression, and the total number of CDPs.\n\
+Only RDHs are read, payloads are skipped.\n\
+The expression compares RDH fields and integers with ==, !=, <, <=, > and >=, combined with && and || and grouped with parentheses.\n\
+Fields: version, header_size, fee_id, priority_bit, system_id, bc, orbit, trigger_type, pages_counter, stop_bit, data_format,\n\
+link_id, packet_counter, cru_id, dw, payload_size, memory_size, offset_to_next.")]
+pub struct Count {
+    /// Expression the RDH of a CDP must match, e.g. 'stop_bit==1 && pages_counter>30'
+    #[structopt(long = "where")]
+    pub predicate: RdhPredicate,
+}
+
+/// Errors subcommand to work with the binary error logs written with `--error-log-binary`
+#[derive(structopt::StructOpt, Debug, Clone)]
+#[structopt(setting = structopt::clap::AppSettings::ColoredHelp, about = "Work with the binary error logs written with `--error-log-binary`.")]
+pub enum Errors {
+    /// Print the errors of a binary error log as text or CSV, optionally only those with a code, from a link or in a range of memory positions
+    #[structopt(setting = structopt::clap::AppSettings::ColoredHelp)]
+    Decode(DecodeErrors),
+}
+
+/// Options for decoding a binary error log
+#[derive(structopt::StructOpt, Debug, Clone)]
+pub struct DecodeErrors {
+    /// Binary error log written with `--error-log-binary`
+    #[structopt(name = "ERROR LOG", parse(from_os_str))]
+    pub file: PathBuf,
+    /// Print the errors as CSV, with the columns of `--error-csv` (the stave column is left empty)
+    #[structopt(long)]
+    pub csv: bool,
+    /// Only print the errors with this code, e.g. `E30`
+    #[structopt(long, parse(try_from_str = parse_error_code))]
+    pub code: Option<u16>,
+    /// Only print the errors of this link
+    #[structopt(long)]
+    pub link: Option<u8>,
+    /// Only print the errors at or after this memory position, in hex (`0x140`) or decimal
+    #[structopt(long, parse(try_from_str = parse_mem_pos))]
+    pub from: Option<u64>,
+    /// Only print the errors at or before this memory position, in hex (`0x140`) or decimal
+    #[structopt(long, parse(try_from_str = parse_mem_pos))]
+    pub to: Option<u64>,
+}
+
+/// Options for verifying a filtered output against its source
+#[derive(structopt::StructOpt, Debug, Clone)]
+#[structopt(setting = structopt::clap::AppSettings::ColoredHelp, about = "Verify that a filtered output contains exactly the CDPs of its source kept by a filter, in the same order and byte for byte.\n\
+Prints PASS, or FAIL with the first difference and its offsets in both files, and exits with code 1 if it fails.")]
+pub struct VerifyFilter {
+    /// Source file the output was filtered from
+    #[structopt(name = "SOURCE", parse(from_os_str))]
+    pub source: PathBuf,
+    /// Filtered output to verify
+    #[structopt(name = "OUTPUT", parse(from_os_str))]
+    pub output: PathBuf,
+    /// CRU link IDs kept by the filter, separated by commas (e.g. `0,2,5`)
+    #[structopt(long, use_delimiter = true)]
+    pub link: Option<Vec<u8>>,
+    /// FEE ID kept by the filter, in hex (`0x502A`) or decimal
+    #[structopt(long, parse(try_from_str = parse_fee_id))]
+    pub fee: Option<u16>,
+    /// CRU ID kept by the filter, in hex (`0x1A`) or decimal
+    #[structopt(long, parse(try_from_str = parse_cru_id))]
+    pub cru: Option<u16>,
+}
+
+fn parse_error_code(s: &str) -> Result<u16, String> {
+    s.strip_prefix('E')
+        .unwrap_or(s)
+        .parse()
+        .map_err(|e| format!("Invalid error code '{s}', expected e.g. `E30`: {e}"))
+}
+
+fn parse_mem_pos(s: &str) -> Result<u64, String> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex_digits) => u64::from_str_radix(hex_digits, 16),
+        None => s.parse(),
+    }
+    .map_err(|e| format!("Invalid memory position '{s}': {e}"))
 }
 
 /// Target system for checks
@@ -206,3 +1270,87 @@ arg_enum! {
         ITS,
     }
 }
+
+arg_enum! {
+/// Modes of parallelism for the checks
+#[derive(Debug, Clone, PartialEq)]
+    pub enum ParallelMode {
+        Link,
+        Payload,
+    }
+}
+
+/// Analyses of the input stream that can be added to the report
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Analysis {
+    /// Largest gap between the pages of an HBF per link.
+    Interleaving,
+    /// Throughput and dead time per link.
+    Throughput,
+    /// Entropy of the payload bytes per link.
+    Entropy,
+    /// Distinct payload contents per link.
+    PayloadUniformity,
+}
+
+impl Analysis {
+    /// Names of the analyses, as given to `--analyze`.
+    pub const VALID_NAMES: [&'static str; 4] = [
+        "interleaving",
+        "throughput",
+        "entropy",
+        "payload-uniformity",
+    ];
+}
+
+impl std::str::FromStr for Analysis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "interleaving" => Ok(Analysis::Interleaving),
+            "throughput" => Ok(Analysis::Throughput),
+            "entropy" => Ok(Analysis::Entropy),
+            "payload-uniformity" => Ok(Analysis::PayloadUniformity),
+            _ => Err(format!(
+                "Unknown analysis '{s}', valid analyses are: {}",
+                Self::VALID_NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+arg_enum! {
+/// Source of the triggers a run is configured for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum TriggerSource {
+        Internal,
+        External,
+        Any,
+    }
+}
+
+arg_enum! {
+/// Views that can be generated while the checks run, with `--with-view`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ConcurrentView {
+        Hbf,
+    }
+}
+
+arg_enum! {
+/// Details that can be added to the JSON report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ReportDetail {
+        Cdp,
+    }
+}
+
+arg_enum! {
+/// Formats of the log messages written to stderr
+#[derive(Debug, Clone, PartialEq)]
+    pub enum LogFormat {
+        Human,
+        Json,
+    }
+}
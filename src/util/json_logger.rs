@@ -0,0 +1,208 @@
+//! Contains the [JsonLogger] that writes every log message as a JSON line to stderr.
+//!
+//! Error messages formatted as `0x<hex>: [E<code>] <message>` get their memory position and error code as separate fields,
+//! all other messages are written with only the timestamp, level and message.
+use crate::stats::lib::{error_code_from_error_msg, mem_pos_from_error_msg};
+use std::io::Write;
+
+/// Logger that writes each log record as a JSON line to stderr.
+pub struct JsonLogger {
+    level: log::LevelFilter,
+}
+
+impl JsonLogger {
+    /// Creates a new [JsonLogger] from a verbosity level, following the same levels as the human readable logger.
+    pub fn new(verbosity: u8) -> Self {
+        let level = match verbosity {
+            0 => log::LevelFilter::Error,
+            1 => log::LevelFilter::Warn,
+            2 => log::LevelFilter::Info,
+            3 => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        };
+        Self { level }
+    }
+
+    /// Installs the [JsonLogger] as the global logger.
+    pub fn init(self) -> Result<(), log::SetLoggerError> {
+        let level = self.level;
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(level);
+        Ok(())
+    }
+
+    /// Formats a message as a JSON line with the current time, without the trailing newline.
+    pub fn format_message(level: log::Level, msg: &str) -> String {
+        let ts = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        let ts = format!("{}.{:03}", ts.as_secs(), ts.subsec_millis());
+        format_json_line(&ts, level, msg)
+    }
+
+    /// Formats a message as a JSON line with the current time, and a JSON object as the `metadata` field, without the trailing newline.
+    pub fn format_message_with_metadata(level: log::Level, msg: &str, metadata: &str) -> String {
+        let line = Self::format_message(level, msg);
+        // Insert the metadata before the closing brace
+        format!("{},\"metadata\":{metadata}}}", &line[..line.len() - 1])
+    }
+}
+
+impl log::Log for JsonLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        // Only log messages from fastpasta, the same as the human readable logger
+        metadata.level() <= self.level && metadata.target().starts_with("fastpasta")
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            let line = Self::format_message(record.level(), &record.args().to_string());
+            // Lock stderr to avoid interleaving lines from different threads
+            let _ = writeln!(std::io::stderr().lock(), "{line}");
+        }
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
+
+fn format_json_line(ts: &str, level: log::Level, msg: &str) -> String {
+    let level = level.as_str().to_uppercase();
+    match (error_code_from_error_msg(msg), mem_pos_from_error_msg(msg)) {
+        (Some(code), Some(mem_pos)) => {
+            let msg = msg
+                .split_once(']')
+                .map_or(msg, |(_, after_code)| after_code.trim_start());
+            format!(
+                r#"{{"ts":{ts},"level":"{level}","code":"{code}","mem_pos":{mem_pos},"msg":"{}"}}"#,
+                escape_json(msg)
+            )
+        }
+        _ => format!(
+            r#"{{"ts":{ts},"level":"{level}","msg":"{}"}}"#,
+            escape_json(msg)
+        ),
+    }
+}
+
+pub(crate) fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_validator_error() {
+        let line = format_json_line(
+            "1681300000.123",
+            log::Level::Error,
+            "0x5E: [E44] TDH trigger_bc is not increasing, previous: 0x1, current: 0x0.",
+        );
+        assert_eq!(
+            line,
+            r#"{"ts":1681300000.123,"level":"ERROR","code":"E44","mem_pos":94,"msg":"TDH trigger_bc is not increasing, previous: 0x1, current: 0x0."}"#
+        );
+    }
+
+    #[test]
+    fn test_format_plain_message() {
+        let line = format_json_line("1.000", log::Level::Warn, "Resetting CDP Payload FSM");
+        assert_eq!(
+            line,
+            r#"{"ts":1.000,"level":"WARN","msg":"Resetting CDP Payload FSM"}"#
+        );
+    }
+
+    #[test]
+    fn test_format_message_with_metadata() {
+        let line = JsonLogger::format_message_with_metadata(
+            log::Level::Info,
+            "Report",
+            r#"{"version":"1.0.0"}"#,
+        );
+        assert!(line.starts_with(r#"{"ts":"#), "{line}");
+        assert!(
+            line.ends_with(r#","level":"INFO","msg":"Report","metadata":{"version":"1.0.0"}}"#),
+            "{line}"
+        );
+    }
+
+    #[test]
+    fn test_format_escapes_message() {
+        let line = format_json_line(
+            "1.000",
+            log::Level::Error,
+            "RDH offset to next is 0 \"invalid\"\n[0x40]:\tRDH",
+        );
+        assert_eq!(
+            line,
+            r#"{"ts":1.000,"level":"ERROR","msg":"RDH offset to next is 0 \"invalid\"\n[0x40]:\tRDH"}"#
+        );
+    }
+
+    #[test]
+    fn test_log_writes_json_lines_to_stderr() {
+        use gag::BufferRedirect;
+        use log::Log;
+        use std::io::Read;
+
+        let logger = JsonLogger::new(1);
+        let mut buf = BufferRedirect::stderr().unwrap();
+        logger.log(
+            &log::Record::builder()
+                .args(format_args!("0x40: [E30] ID is not 0xE0: 0xE1"))
+                .level(log::Level::Error)
+                .target("fastpasta::stats::stats_controller")
+                .build(),
+        );
+        logger.log(
+            &log::Record::builder()
+                .args(format_args!("Resetting CDP Payload FSM"))
+                .level(log::Level::Warn)
+                .target("fastpasta::validators::cdp_running")
+                .build(),
+        );
+        // Filtered by verbosity
+        logger.log(
+            &log::Record::builder()
+                .args(format_args!("Exit successful"))
+                .level(log::Level::Info)
+                .target("fastpasta")
+                .build(),
+        );
+        // Filtered by module
+        logger.log(
+            &log::Record::builder()
+                .args(format_args!("Other crate"))
+                .level(log::Level::Error)
+                .target("other_crate")
+                .build(),
+        );
+        let mut output = String::new();
+        buf.read_to_string(&mut output).unwrap();
+        drop(buf);
+
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2, "{output}");
+        assert!(lines[0].starts_with(r#"{"ts":"#));
+        assert!(lines[0].ends_with(
+            r#""level":"ERROR","code":"E30","mem_pos":64,"msg":"ID is not 0xE0: 0xE1"}"#
+        ));
+        assert!(lines[1].ends_with(r#""level":"WARN","msg":"Resetting CDP Payload FSM"}"#));
+    }
+}
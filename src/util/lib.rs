@@ -1,7 +1,11 @@
 //! Contains the [Config] super trait, and all the sub traits required by it
 //!
 //! Implementing the [Config] super trait is required by configs passed to structs in other modules as part of instantiation.
-use super::config::{Check, View};
+use super::config::{
+    Analysis, Check, ConcurrentView, Count, DecodeErrors, HbfCap, HbfExtract, ItsStave, ListLinks,
+    LogFormat, PacketRange, ParallelMode, ReportDetail, TriggerSource, VerifyFilter, View,
+};
+use super::rdh_predicate::RdhPredicate;
 
 /// Super trait for all the traits that needed to be implemented by the config struct
 pub trait Config: Util + Filter + InputOutput + Checks + Views + Send + Sync {}
@@ -12,12 +16,126 @@ pub trait Util {
     fn verbosity(&self) -> u8;
     /// Maximum number of errors to tolerate before exiting
     fn max_tolerate_errors(&self) -> u32;
+    /// Stop processing at the first error, printing only that error and a minimal report
+    fn fail_fast(&self) -> bool;
+    /// Number of errors reported from a link before its errors are only counted, `None` if links are never muted.
+    fn auto_mute_link_after(&self) -> Option<u32>;
+    /// Report errors in order of memory position, regardless of which thread detected them
+    fn ordered(&self) -> bool;
+    /// Command to run when an error is reported, at most once per error code, `None` if not set.
+    fn on_error_exec(&self) -> Option<super::command_hook::CommandTemplate>;
+    /// Command to run when the run completes, `None` if not set.
+    fn on_complete_exec(&self) -> Option<super::command_hook::CommandTemplate>;
+    /// Append the context of the current RDH to payload error messages.
+    fn verbose_errors(&self) -> bool;
+    /// Log a warning for each TDT timeout flag that is set.
+    fn warn_tdt_timeouts(&self) -> bool;
+    /// Trigger bits that may differ between the TDH and RDH trigger_type, reported as warnings instead of errors.
+    fn ignore_tdh_trigger_bits(&self) -> Vec<crate::words::rdh::TriggerType>;
+    /// Log a line for every Nth CDP leaving the input scanner, `None` if CDPs are not traced.
+    fn trace_cdps(&self) -> Option<u32>;
+    /// Expected RDH system ID that overrides the one of the check target, `None` if not set.
+    fn expect_system_id(&self) -> Option<u8>;
+    /// Expected RDH data format, `None` if any defined data format is accepted.
+    fn expect_data_format(&self) -> Option<u8>;
+    /// Valid ranges of the layer and stave number in the FEE ID that override the built-in ones, `None` if not set.
+    fn fee_id_bounds(&self) -> Option<crate::util::config::FeeIdBounds>;
+    /// Largest valid bunch counter of the RDHs and TDHs, [MAX_BC][crate::words::constants::MAX_BC] unless overridden.
+    fn max_bc(&self) -> u16;
+    /// Stop processing if the RDH version changes within the input.
+    fn strict_version(&self) -> bool;
+    /// Report a link that does not start at a packet boundary as an error instead of a warning.
+    fn strict_start(&self) -> bool;
+    /// Strict mode, warnings are reported as errors and structural surprises are fatal, see [Opt::apply_strict_mode][crate::util::config::Opt::apply_strict_mode].
+    fn strict(&self) -> bool;
+    /// Configured source of the triggers, the TDH internal_trigger bit is checked against it.
+    fn trigger_source(&self) -> TriggerSource;
+    /// Decode payloads that appear to be data format 2 as such, even if the RDH declares data format 0.
+    fn trust_payload_format(&self) -> bool;
+    /// Check the payloads of links carrying another system than the check target, instead of skipping them.
+    fn force_check_all_links(&self) -> bool;
+    /// Format of the log messages.
+    fn log_format(&self) -> LogFormat;
+    /// Returns true if the [Analysis] of the input stream is enabled.
+    fn analyze(&self, analysis: Analysis) -> bool;
+    /// Orbits per window in which the throughput of each link is also estimated, `None` if only the whole data is.
+    fn throughput_window(&self) -> Option<u32>;
+    /// With `--analyze entropy`, the payload of every Nth CDP of each link is sampled for the entropy estimate.
+    fn entropy_sample_every(&self) -> std::num::NonZeroU32;
+    /// Details added to the JSON report, `None` if the report has no detail section.
+    fn report_detail(&self) -> Option<ReportDetail>;
+    /// Maximum number of CDPs listed in the detail section of the JSON report, unless forced.
+    fn report_detail_max_cdps(&self) -> u64;
+    /// HBFs expected in every timeframe, `None` if the timeframes are not verified.
+    fn hbf_per_tf(&self) -> Option<u32>;
+    /// Add the quality score of the run to the report.
+    fn quality_score(&self) -> bool;
+    /// File with the weights and thresholds of the quality score, `None` if the defaults are used.
+    fn quality_config(&self) -> &Option<std::path::PathBuf>;
+    /// Orbits a link may be silent before the end of the data, before it is reported as having gone silent.
+    fn silent_link_orbits(&self) -> u32;
+    /// Orbits in the sliding window in which the trigger types of the HBFs of all links of a CRU are compared.
+    fn trigger_consistency_orbits(&self) -> u32;
+    /// Verify internal invariants of fastpasta, always enabled in debug builds.
+    fn paranoid(&self) -> bool;
+    /// Number of CDPs read into each [CdpChunk][crate::input::data_wrapper::CdpChunk] sent from the reader.
+    fn cdp_chunk_size(&self) -> usize;
+    /// Track the peak memory usage of the pipeline.
+    fn track_memory(&self) -> bool;
 }
 
 /// Trait for all filter options
 pub trait Filter {
-    /// Link ID to filter by
-    fn filter_link(&self) -> Option<u8>;
+    /// Link IDs to filter by, sorted without duplicates
+    fn filter_links(&self) -> Option<&[u8]>;
+    /// Link IDs to exclude, sorted without duplicates
+    fn exclude_links(&self) -> Option<&[u8]>;
+    /// FEE ID to filter by.
+    fn filter_fee(&self) -> Option<u16>;
+    /// CRU ID to filter by.
+    fn filter_cru(&self) -> Option<u16>;
+    /// Trigger type to filter by.
+    fn filter_trigger(&self) -> Option<crate::words::rdh::TriggerType>;
+    /// ITS stave to filter by.
+    fn filter_its_stave(&self) -> Option<ItsStave>;
+    /// Inclusive range of RDH orbits to filter by, as the first and last orbit.
+    fn filter_orbit_range(&self) -> Option<(u32, u32)>;
+    /// Inclusive range of packet numbers to filter by, counting the wrap-arounds of the packet_counter of each link.
+    fn filter_packet_range(&self) -> Option<PacketRange>;
+    /// Keep the CDPs of all links, set with `--filter-link all`.
+    fn filter_all_links(&self) -> bool;
+    /// File with the memory offsets of the CDPs to extract.
+    fn extract_offsets(&self) -> &Option<std::path::PathBuf>;
+    /// Expression the RDH of a CDP must match for the CDP to be kept.
+    fn filter_where(&self) -> Option<RdhPredicate>;
+    /// The first or last N complete HBFs of each link to keep.
+    fn extract_hbfs(&self) -> Option<HbfExtract>;
+    /// Only keep the CDPs whose payload contains an APE word.
+    fn filter_ape(&self) -> bool;
+    /// Drop the HBFs without data words from the output.
+    fn drop_empty_hbfs(&self) -> bool;
+    /// Size after which an HBF without a stop page is truncated when the HBFs of each link are extracted.
+    fn hbf_cap(&self) -> HbfCap;
+    /// Returns true if any filter is set, meaning the filtered data can be written out.
+    fn filter_enabled(&self) -> bool {
+        self.filter_selects_cdps() || self.filter_all_links()
+    }
+    /// Returns true if any filter that selects CDPs is set, i.e. any filter except keeping all links.
+    fn filter_selects_cdps(&self) -> bool {
+        self.filter_links().is_some()
+            || self.exclude_links().is_some()
+            || self.filter_fee().is_some()
+            || self.filter_cru().is_some()
+            || self.filter_trigger().is_some()
+            || self.filter_its_stave().is_some()
+            || self.filter_orbit_range().is_some()
+            || self.filter_packet_range().is_some()
+            || self.extract_offsets().is_some()
+            || self.filter_where().is_some()
+            || self.extract_hbfs().is_some()
+            || self.filter_ape()
+            || self.drop_empty_hbfs()
+    }
 }
 
 /// Trait for all input/output options
@@ -26,6 +144,22 @@ pub trait InputOutput {
     fn input_file(&self) -> &Option<std::path::PathBuf>;
     /// Output file to write to.
     fn output(&self) -> &Option<std::path::PathBuf>;
+    /// File to save the state of the running checks to, and resume from.
+    fn checkpoint_file(&self) -> &Option<std::path::PathBuf>;
+    /// CSV file every reported error is written to.
+    fn error_csv(&self) -> &Option<std::path::PathBuf>;
+    /// Also write the errors of muted links to the error CSV.
+    fn error_csv_include_muted(&self) -> bool;
+    /// File to write the reported errors to in the compact binary format, if set.
+    fn error_log_binary(&self) -> &Option<std::path::PathBuf>;
+    /// File to write the CDPs that failed the checks to, if set.
+    fn save_failing_cdps(&self) -> &Option<std::path::PathBuf>;
+    /// Write the output even if it is an unmodified copy of the input.
+    fn force(&self) -> bool;
+    /// Skip probing the first RDHs of the input.
+    fn skip_input_probe(&self) -> bool;
+    /// Keep processing if writing the output fails, and drop the rest of the output.
+    fn keep_going_on_write_error(&self) -> bool;
     /// Output mode of the data writing (file, stdout, none)
     fn output_mode(&self) -> DataOutputMode;
 }
@@ -35,12 +169,129 @@ pub trait InputOutput {
 pub trait Checks {
     /// Type of Check to perform.
     fn check(&self) -> Option<Check>;
+    /// Parallelism of the checks.
+    fn parallel(&self) -> ParallelMode;
+    /// Check that the orbit increments by exactly 1 between HBFs of a link.
+    fn expect_hbf_per_orbit(&self) -> bool;
+    /// CSV file mapping each FEE ID to its CRU ID, link ID and endpoint, every CDP is verified against it.
+    fn hw_map(&self) -> &Option<std::path::PathBuf>;
+    /// GBT wide mode, the payload words are 16 byte GBT frames whose upper 6 bytes are checked.
+    fn gbt_wide(&self) -> bool;
 }
 
 /// Trait for all view options.
 pub trait Views {
     /// Type of View to generate.
     fn view(&self) -> Option<View>;
+    /// Size in bytes of the payload words of the raw words view.
+    fn payload_word_size(&self) -> usize;
+    /// Options for listing the links of the input, if enabled.
+    fn list_links(&self) -> Option<ListLinks>;
+    /// Options for counting the CDPs matching an expression, if enabled.
+    fn count(&self) -> Option<Count>;
+    /// Options for decoding a binary error log, if enabled.
+    fn decode_errors(&self) -> Option<DecodeErrors>;
+    /// Options for verifying a filtered output against its source, if enabled.
+    fn verify_filter(&self) -> Option<VerifyFilter>;
+    /// View generated from the first HBFs while the checks run, if enabled.
+    fn with_view(&self) -> Option<ConcurrentView>;
+    /// Number of HBFs viewed with [Views::with_view].
+    fn view_limit(&self) -> std::num::NonZeroU32;
+    /// File the view of [Views::with_view] is written to, stderr if not set.
+    fn view_output(&self) -> Option<&std::path::PathBuf>;
+}
+
+/// Checks that the payload word size is supported by the checks and views of the [Config].
+///
+/// The ITS checks and the HBF view decode ITS GBT words, and refuse to run with any other word size.
+pub fn validate_payload_word_size(config: &impl Config) -> Result<(), String> {
+    let word_size = config.payload_word_size();
+    if word_size == crate::validators::link_validator::GBT_WORD_SIZE {
+        return Ok(());
+    }
+    if config.gbt_wide() {
+        return Err(format!(
+            "The words of GBT wide mode are 16 bytes, it can't be combined with a payload word size of {word_size} bytes"
+        ));
+    }
+    if config.check().and_then(|check| check.target()).is_some() {
+        return Err(format!(
+            "ITS checks require a payload word size of 10 bytes, but it is set to {word_size} bytes"
+        ));
+    }
+    if matches!(config.view(), Some(View::Hbf(_)))
+        || config.with_view() == Some(ConcurrentView::Hbf)
+    {
+        return Err(format!(
+            "The HBF view requires a payload word size of 10 bytes, but it is set to {word_size} bytes"
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that the data written out by the [Config] is not an unmodified copy of the input, unless forced.
+///
+/// With `--filter-link all` and no other filter, every CDP is kept, so writing them to a file is a pointless copy of the input.
+pub fn validate_output(config: &impl Config) -> Result<(), String> {
+    let copies_input = config.filter_all_links()
+        && config.extract_offsets().is_none()
+        && config.filter_where().is_none()
+        && config.filter_orbit_range().is_none()
+        && config.filter_packet_range().is_none()
+        && config.filter_trigger().is_none()
+        && config.extract_hbfs().is_none()
+        && !config.filter_ape()
+        && !config.drop_empty_hbfs();
+    if copies_input && config.output_mode() == DataOutputMode::File && !config.force() {
+        return Err(
+            "`--filter-link all` keeps every CDP, the output file would be a copy of the input, use --force to write it anyway"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Checks that the view of the first HBFs set with `--with-view` is generated alongside checks.
+pub fn validate_with_view(config: &impl Config) -> Result<(), String> {
+    if config.with_view().is_some() && config.check().is_none() {
+        return Err(
+            "`--with-view` views the first HBFs while the checks run, use it with `check`, or use `view` alone"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Checks that the orbit range to filter by of the [Config] does not end before it starts.
+pub fn validate_filter_orbit_range(config: &impl Config) -> Result<(), String> {
+    match config.filter_orbit_range() {
+        Some((start, end)) if start > end => Err(format!(
+            "`--filter-orbit-range` ends before it starts: {start:#X} > {end:#X}"
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Checks that the details added to the JSON report by the [Config] can be collected.
+///
+/// The details are only in the JSON report, and the word counts of the CDPs are collected by the ITS payload checks.
+pub fn validate_report_detail(config: &impl Config) -> Result<(), String> {
+    if config.report_detail().is_none() {
+        return Ok(());
+    }
+    if config.log_format() != LogFormat::Json {
+        return Err(
+            "`--report-detail` adds to the JSON report, use it with `--log-format json`"
+                .to_string(),
+        );
+    }
+    if !matches!(
+        config.check().and_then(|check| check.target()),
+        Some(super::config::System::ITS)
+    ) {
+        return Err("`--report-detail cdp` counts the words found by the ITS payload checks, use it with `check sanity its` or `check all its`".to_string());
+    }
+    Ok(())
 }
 
 /// Enum for all possible data output modes.
@@ -53,3 +304,385 @@ pub enum DataOutputMode {
     /// Do not write data out.
     None,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::config::Opt;
+
+    fn config_from(args: &[&str]) -> Opt {
+        <Opt as structopt::StructOpt>::from_iter(
+            ["fastpasta", "input.raw"].iter().chain(args.iter()),
+        )
+    }
+
+    #[test]
+    fn test_strict_mode_overrides_loose_options() {
+        let args = [
+            "--strict",
+            "--no-expect-hbf-per-orbit",
+            "--ignore-tdh-trigger-bits",
+            "hbr",
+            "check",
+            "all",
+        ];
+        let config = config_from(&args).apply_strict_mode();
+        assert!(config.strict());
+        assert!(config.strict_version());
+        assert!(config.strict_start());
+        assert!(config.expect_hbf_per_orbit());
+        assert!(config.ignore_tdh_trigger_bits().is_empty());
+        assert!(config.warn_tdt_timeouts());
+
+        // Without --strict the options are kept
+        let config = config_from(&args[1..]).apply_strict_mode();
+        assert!(!config.strict());
+        assert!(!config.strict_version());
+        assert!(!config.strict_start());
+        assert!(!config.expect_hbf_per_orbit());
+        assert_eq!(config.ignore_tdh_trigger_bits().len(), 1);
+    }
+
+    #[test]
+    fn test_max_bc() {
+        assert_eq!(
+            config_from(&["check", "sanity"]).max_bc(),
+            crate::words::constants::MAX_BC
+        );
+        assert_eq!(
+            config_from(&["--max-bc", "0xFFF", "check", "sanity"]).max_bc(),
+            0xFFF
+        );
+        assert_eq!(
+            config_from(&["--max-bc", "100", "check", "sanity"]).max_bc(),
+            100
+        );
+        assert!(<Opt as structopt::StructOpt>::from_iter_safe(
+            ["fastpasta", "--max-bc", "0x1000",]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_validate_payload_word_size() {
+        assert!(validate_payload_word_size(&config_from(&["check", "all", "its"])).is_ok());
+        assert!(validate_payload_word_size(&config_from(&[
+            "--payload-word-size",
+            "16",
+            "view",
+            "raw-words"
+        ]))
+        .is_ok());
+        assert!(validate_payload_word_size(&config_from(&[
+            "--payload-word-size",
+            "16",
+            "check",
+            "sanity"
+        ]))
+        .is_ok());
+        assert_eq!(
+            validate_payload_word_size(&config_from(&[
+                "--payload-word-size",
+                "16",
+                "check",
+                "all",
+                "its"
+            ])),
+            Err(
+                "ITS checks require a payload word size of 10 bytes, but it is set to 16 bytes"
+                    .to_string()
+            )
+        );
+        assert!(validate_payload_word_size(&config_from(&[
+            "--payload-word-size",
+            "32",
+            "view",
+            "hbf"
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn test_filter_link_all() {
+        let config = config_from(&["-f", "all", "-o", "stdout"]);
+        assert!(config.filter_all_links());
+        assert_eq!(config.filter_links(), None);
+        assert!(config.filter_enabled());
+        // Keeping all links can't keep none of the CDPs by mistake
+        assert!(!config.filter_selects_cdps());
+        let config = config_from(&["-f", "3"]);
+        assert!(!config.filter_all_links());
+        assert_eq!(config.filter_links(), Some(&[3][..]));
+        let config = config_from(&["-f", "5,0, 2,0"]);
+        assert_eq!(config.filter_links(), Some(&[0, 2, 5][..]));
+        assert!(<Opt as structopt::StructOpt>::from_iter_safe([
+            "fastpasta",
+            "input.raw",
+            "-f",
+            "0,,2"
+        ])
+        .is_err());
+        assert!(<Opt as structopt::StructOpt>::from_iter_safe([
+            "fastpasta",
+            "input.raw",
+            "-f",
+            "256"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_exclude_link() {
+        let config = config_from(&["--exclude-link", "8,3", "-o", "stdout"]);
+        assert_eq!(config.exclude_links(), Some(&[3, 8][..]));
+        assert_eq!(config.filter_links(), None);
+        assert!(config.filter_enabled());
+        assert!(<Opt as structopt::StructOpt>::from_iter_safe([
+            "fastpasta",
+            "input.raw",
+            "--exclude-link",
+            "all"
+        ])
+        .is_err());
+        let err = <Opt as structopt::StructOpt>::from_iter_safe([
+            "fastpasta",
+            "input.raw",
+            "--exclude-link",
+            "8",
+            "-f",
+            "2",
+        ])
+        .unwrap_err();
+        assert_eq!(err.kind, structopt::clap::ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_filter_fee() {
+        let config = config_from(&["--filter-fee", "0x502A", "-o", "stdout"]);
+        assert_eq!(config.filter_fee(), Some(0x502A));
+        assert!(config.filter_enabled());
+        assert_eq!(
+            config_from(&["--filter-fee", "20522"]).filter_fee(),
+            Some(0x502A)
+        );
+        assert!(<Opt as structopt::StructOpt>::from_iter_safe([
+            "fastpasta",
+            "input.raw",
+            "--filter-fee",
+            "0x1502A"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_filter_cru() {
+        let config = config_from(&["--filter-cru", "0x1A", "-f", "2", "-o", "stdout"]);
+        assert_eq!(config.filter_cru(), Some(0x1A));
+        assert_eq!(config.filter_links(), Some(&[2][..]));
+        assert!(config.filter_enabled());
+        assert_eq!(
+            config_from(&["--filter-cru", "26"]).filter_cru(),
+            Some(0x1A)
+        );
+        assert!(<Opt as structopt::StructOpt>::from_iter_safe([
+            "fastpasta",
+            "input.raw",
+            "--filter-cru",
+            "0x1000"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_hbf_cap() {
+        assert_eq!(
+            config_from(&[]).hbf_cap(),
+            HbfCap {
+                pages: 10000,
+                bytes: 128 * 1024 * 1024
+            }
+        );
+        assert_eq!(
+            config_from(&["--max-hbf-pages", "50", "--max-hbf-mib", "1"]).hbf_cap(),
+            HbfCap {
+                pages: 50,
+                bytes: 1024 * 1024
+            }
+        );
+        assert!(<Opt as structopt::StructOpt>::from_iter_safe([
+            "fastpasta",
+            "input.raw",
+            "--max-hbf-pages",
+            "0"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_filter_trigger() {
+        let config = config_from(&["--filter-trigger", "pht", "-o", "stdout"]);
+        assert_eq!(
+            config.filter_trigger(),
+            Some(crate::words::rdh::TriggerType::PhT)
+        );
+        assert!(config.filter_enabled());
+        assert!(<Opt as structopt::StructOpt>::from_iter_safe([
+            "fastpasta",
+            "input.raw",
+            "--filter-trigger",
+            "physics"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_filter_packet_range() {
+        let config = config_from(&["--filter-packet-range", "100..200", "-f", "3"]);
+        assert_eq!(
+            config.filter_packet_range(),
+            Some(PacketRange {
+                start: 100,
+                end: 200
+            })
+        );
+        assert!(config.filter_enabled());
+        for (range, err) in [
+            ("100-200", "Expected a packet range as `<start>..<end>`"),
+            ("..200", "Invalid start of packet range '..200'"),
+            (
+                "200..100",
+                "Packet range '200..100' ends before it starts: 200 > 100",
+            ),
+        ] {
+            let e = <Opt as structopt::StructOpt>::from_iter_safe([
+                "fastpasta",
+                "--filter-packet-range",
+                range,
+            ])
+            .unwrap_err();
+            assert!(e.message.contains(err), "{}", e.message);
+        }
+    }
+
+    #[test]
+    fn test_filter_orbit_range() {
+        let config = config_from(&[
+            "--filter-orbit-range",
+            "0x0b7dd500",
+            "192796160",
+            "check",
+            "sanity",
+        ]);
+        assert_eq!(
+            config.filter_orbit_range(),
+            Some((0x0B7D_D500, 0x0B7D_D600))
+        );
+        assert!(config.filter_enabled());
+        assert!(validate_filter_orbit_range(&config).is_ok());
+        let reversed = config_from(&["--filter-orbit-range", "0x0b7dd600", "0x0b7dd500"]);
+        assert_eq!(
+            validate_filter_orbit_range(&reversed).unwrap_err(),
+            "`--filter-orbit-range` ends before it starts: 0xB7DD600 > 0xB7DD500"
+        );
+        // Both the start and the end are required
+        assert!(<Opt as structopt::StructOpt>::from_iter_safe([
+            "fastpasta",
+            "input.raw",
+            "--filter-orbit-range",
+            "0x0b7dd500"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_filter_its_stave() {
+        let config = config_from(&["--filter-its-stave", "L3_12", "-o", "stdout"]);
+        assert_eq!(
+            config.filter_its_stave(),
+            Some(ItsStave {
+                layer: 3,
+                stave: 12
+            })
+        );
+        assert!(config.filter_enabled());
+        assert_eq!("l0_11".parse::<ItsStave>().unwrap().to_string(), "L0_11");
+        assert_eq!(
+            "L0_12".parse::<ItsStave>(),
+            Err("Invalid stave in 'L0_12': layer 0 has staves 0 to 11".to_string())
+        );
+        assert_eq!(
+            "L7_0".parse::<ItsStave>(),
+            Err("Invalid layer in 'L7_0': the ITS has layers 0 to 6".to_string())
+        );
+        assert!("3_12".parse::<ItsStave>().is_err());
+        assert!("L3-12".parse::<ItsStave>().is_err());
+    }
+
+    #[test]
+    fn test_drop_empty_hbfs() {
+        let config = config_from(&["--drop-empty-hbfs", "-o", "with_data.raw"]);
+        assert!(config.drop_empty_hbfs());
+        assert!(config.filter_enabled());
+        assert!(!config_from(&["-f", "2"]).drop_empty_hbfs());
+    }
+
+    #[test]
+    fn test_filter_ape() {
+        let config = config_from(&["--filter-ape", "-o", "ape.raw"]);
+        assert!(config.filter_ape());
+        assert!(config.filter_enabled());
+        assert!(validate_output(&config_from(&[
+            "-f",
+            "all",
+            "--filter-ape",
+            "-o",
+            "ape.raw"
+        ]))
+        .is_ok());
+    }
+
+    #[test]
+    fn test_validate_output() {
+        // A plain copy of the input to a file
+        assert!(validate_output(&config_from(&["-f", "all", "-o", "copy.raw"])).is_err());
+        assert!(validate_output(&config_from(&["-f", "all", "-o", "copy.raw", "--force"])).is_ok());
+        // Other filters still apply
+        assert!(validate_output(&config_from(&[
+            "-f",
+            "all",
+            "--filter-where",
+            "stop_bit==1",
+            "-o",
+            "copy.raw"
+        ]))
+        .is_ok());
+        assert!(validate_output(&config_from(&[
+            "-f",
+            "all",
+            "--drop-empty-hbfs",
+            "-o",
+            "copy.raw"
+        ]))
+        .is_ok());
+        assert!(validate_output(&config_from(&["-f", "all", "-o", "stdout"])).is_ok());
+        assert!(validate_output(&config_from(&["-f", "2", "-o", "link_2.raw"])).is_ok());
+    }
+
+    #[test]
+    fn test_validate_with_view() {
+        let config = config_from(&["check", "all", "its", "--with-view", "hbf"]);
+        assert!(validate_with_view(&config).is_ok());
+        assert_eq!(config.view_limit().get(), 10);
+        assert_eq!(config.view_output(), None);
+        assert!(validate_with_view(&config_from(&["view", "rdh", "--with-view", "hbf"])).is_err());
+        assert!(validate_payload_word_size(&config_from(&[
+            "--payload-word-size",
+            "16",
+            "check",
+            "sanity",
+            "--with-view",
+            "hbf"
+        ]))
+        .is_err());
+    }
+}
@@ -0,0 +1,619 @@
+//! Contains the [RdhPredicate], a boolean expression over the fields of an [RDH] such as `stop_bit==1 && pages_counter>30`.
+//!
+//! The expression language is deliberately tiny:
+//! - Named RDH fields, see [RdhField::NAMES], and integer literals, decimal or hex prefixed with `0x`.
+//! - The comparisons `==`, `!=`, `<`, `<=`, `>` and `>=`.
+//! - `&&` and `||`, where `&&` binds tighter than `||`, and parentheses for grouping.
+//!
+//! It is parsed with [str::parse] by a small recursive descent parser, and evaluated per RDH with [RdhPredicate::eval].
+use crate::words::lib::RDH;
+
+/// A field of an [RDH] that can be used in an [RdhPredicate].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RdhField {
+    /// [RDH::version]
+    Version,
+    /// Header size of [RDH0][crate::words::rdh::Rdh0]
+    HeaderSize,
+    /// [RDH::fee_id]
+    FeeId,
+    /// Priority bit of [RDH0][crate::words::rdh::Rdh0]
+    PriorityBit,
+    /// System ID of [RDH0][crate::words::rdh::Rdh0]
+    SystemId,
+    /// Bunch counter of [RDH1][crate::words::rdh::Rdh1]
+    Bc,
+    /// Orbit of [RDH1][crate::words::rdh::Rdh1]
+    Orbit,
+    /// [RDH::trigger_type]
+    TriggerType,
+    /// [RDH::pages_counter]
+    PagesCounter,
+    /// [RDH::stop_bit]
+    StopBit,
+    /// [RDH::data_format]
+    DataFormat,
+    /// [RDH::link_id]
+    LinkId,
+    /// [RDH::packet_counter]
+    PacketCounter,
+    /// [RDH::cru_id]
+    CruId,
+    /// [RDH::dw]
+    Dw,
+    /// [RDH::payload_size]
+    PayloadSize,
+    /// [RDH::memory_size]
+    MemorySize,
+    /// [RDH::offset_to_next]
+    OffsetToNext,
+}
+
+impl RdhField {
+    /// All fields, in the order of [RdhField::NAMES].
+    pub const ALL: [RdhField; 18] = [
+        RdhField::Version,
+        RdhField::HeaderSize,
+        RdhField::FeeId,
+        RdhField::PriorityBit,
+        RdhField::SystemId,
+        RdhField::Bc,
+        RdhField::Orbit,
+        RdhField::TriggerType,
+        RdhField::PagesCounter,
+        RdhField::StopBit,
+        RdhField::DataFormat,
+        RdhField::LinkId,
+        RdhField::PacketCounter,
+        RdhField::CruId,
+        RdhField::Dw,
+        RdhField::PayloadSize,
+        RdhField::MemorySize,
+        RdhField::OffsetToNext,
+    ];
+
+    /// The names of the fields as written in an expression.
+    pub const NAMES: [&'static str; 18] = [
+        "version",
+        "header_size",
+        "fee_id",
+        "priority_bit",
+        "system_id",
+        "bc",
+        "orbit",
+        "trigger_type",
+        "pages_counter",
+        "stop_bit",
+        "data_format",
+        "link_id",
+        "packet_counter",
+        "cru_id",
+        "dw",
+        "payload_size",
+        "memory_size",
+        "offset_to_next",
+    ];
+
+    /// Returns the field with the name, or [None] if there is no such field.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .position(|field_name| *field_name == name)
+            .map(|idx| Self::ALL[idx])
+    }
+
+    /// Returns the value of the field in the [RDH].
+    pub fn value<T: RDH>(self, rdh: &T) -> u64 {
+        match self {
+            RdhField::Version => rdh.version() as u64,
+            RdhField::HeaderSize => rdh.rdh0().header_size as u64,
+            RdhField::FeeId => rdh.fee_id() as u64,
+            RdhField::PriorityBit => rdh.rdh0().priority_bit as u64,
+            RdhField::SystemId => rdh.rdh0().system_id as u64,
+            RdhField::Bc => rdh.rdh1().bc() as u64,
+            RdhField::Orbit => rdh.rdh1().orbit as u64,
+            RdhField::TriggerType => rdh.trigger_type() as u64,
+            RdhField::PagesCounter => rdh.pages_counter() as u64,
+            RdhField::StopBit => rdh.stop_bit() as u64,
+            RdhField::DataFormat => rdh.data_format() as u64,
+            RdhField::LinkId => rdh.link_id() as u64,
+            RdhField::PacketCounter => rdh.packet_counter() as u64,
+            RdhField::CruId => rdh.cru_id() as u64,
+            RdhField::Dw => rdh.dw() as u64,
+            RdhField::PayloadSize => rdh.payload_size() as u64,
+            RdhField::MemorySize => rdh.memory_size() as u64,
+            RdhField::OffsetToNext => rdh.offset_to_next() as u64,
+        }
+    }
+}
+
+/// An operand of a comparison.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    /// The value of a field of the RDH.
+    Field(RdhField),
+    /// An integer literal.
+    Literal(u64),
+}
+
+impl Operand {
+    fn value<T: RDH>(self, rdh: &T) -> u64 {
+        match self {
+            Operand::Field(field) => field.value(rdh),
+            Operand::Literal(value) => value,
+        }
+    }
+}
+
+/// A comparison operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    /// `==`
+    Eq,
+    /// `!=`
+    Ne,
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+}
+
+impl CmpOp {
+    fn compare(self, lhs: u64, rhs: u64) -> bool {
+        match self {
+            CmpOp::Eq => lhs == rhs,
+            CmpOp::Ne => lhs != rhs,
+            CmpOp::Lt => lhs < rhs,
+            CmpOp::Le => lhs <= rhs,
+            CmpOp::Gt => lhs > rhs,
+            CmpOp::Ge => lhs >= rhs,
+        }
+    }
+}
+
+/// The parsed expression tree of an [RdhPredicate].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    /// A comparison of two operands.
+    Cmp(Operand, CmpOp, Operand),
+    /// Both sub expressions are true.
+    And(Box<Expr>, Box<Expr>),
+    /// Either sub expression is true.
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval<T: RDH>(&self, rdh: &T) -> bool {
+        match self {
+            Expr::Cmp(lhs, op, rhs) => op.compare(lhs.value(rdh), rhs.value(rdh)),
+            Expr::And(lhs, rhs) => lhs.eval(rdh) && rhs.eval(rdh),
+            Expr::Or(lhs, rhs) => lhs.eval(rdh) || rhs.eval(rdh),
+        }
+    }
+}
+
+/// A boolean expression over the fields of an [RDH], parsed from a string such as `stop_bit==1 && pages_counter>30`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RdhPredicate {
+    expr: Expr,
+    source: String,
+}
+
+impl RdhPredicate {
+    /// Evaluates the predicate on the [RDH].
+    pub fn eval<T: RDH>(&self, rdh: &T) -> bool {
+        self.expr.eval(rdh)
+    }
+
+    /// The parsed expression tree.
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+}
+
+impl std::fmt::Display for RdhPredicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source.trim())
+    }
+}
+
+impl std::str::FromStr for RdhPredicate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(s)?;
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(parser.error_at(
+                token.column,
+                &format!("Expected `&&`, `||` or the end of the expression, found {token}"),
+            ));
+        }
+        Ok(Self {
+            expr,
+            source: s.to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TokenKind {
+    Ident(String),
+    Int(u64),
+    Cmp(CmpOp),
+    And,
+    Or,
+    LParen,
+    RParen,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Token {
+    kind: TokenKind,
+    /// Column of the first character of the token, starting at 0.
+    column: usize,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            TokenKind::Ident(name) => write!(f, "`{name}`"),
+            TokenKind::Int(value) => write!(f, "`{value}`"),
+            TokenKind::Cmp(op) => write!(
+                f,
+                "`{}`",
+                match op {
+                    CmpOp::Eq => "==",
+                    CmpOp::Ne => "!=",
+                    CmpOp::Lt => "<",
+                    CmpOp::Le => "<=",
+                    CmpOp::Gt => ">",
+                    CmpOp::Ge => ">=",
+                }
+            ),
+            TokenKind::And => write!(f, "`&&`"),
+            TokenKind::Or => write!(f, "`||`"),
+            TokenKind::LParen => write!(f, "`(`"),
+            TokenKind::RParen => write!(f, "`)`"),
+        }
+    }
+}
+
+/// Recursive descent parser of the grammar:
+///
+/// ```text
+/// or         := and ( "||" and )*
+/// and        := primary ( "&&" primary )*
+/// primary    := "(" or ")" | comparison
+/// comparison := operand cmp_op operand
+/// operand    := field | integer
+/// ```
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Result<Self, String> {
+        let mut parser = Self {
+            source,
+            tokens: vec![],
+            pos: 0,
+        };
+        parser.tokenize()?;
+        Ok(parser)
+    }
+
+    /// Formats an error message pointing at the column of the expression.
+    fn error_at(&self, column: usize, msg: &str) -> String {
+        format!(
+            "column {}: {msg}\n  {}\n  {}^",
+            column + 1,
+            self.source,
+            " ".repeat(column)
+        )
+    }
+
+    fn end_column(&self) -> usize {
+        self.source.chars().count()
+    }
+
+    fn tokenize(&mut self) -> Result<(), String> {
+        let chars: Vec<char> = self.source.chars().collect();
+        let mut column = 0;
+        while column < chars.len() {
+            let c = chars[column];
+            let next = chars.get(column + 1).copied();
+            let start = column;
+            let kind = match (c, next) {
+                (c, _) if c.is_whitespace() => {
+                    column += 1;
+                    continue;
+                }
+                ('&', Some('&')) => TokenKind::And,
+                ('|', Some('|')) => TokenKind::Or,
+                ('=', Some('=')) => TokenKind::Cmp(CmpOp::Eq),
+                ('!', Some('=')) => TokenKind::Cmp(CmpOp::Ne),
+                ('<', Some('=')) => TokenKind::Cmp(CmpOp::Le),
+                ('>', Some('=')) => TokenKind::Cmp(CmpOp::Ge),
+                ('<', _) => TokenKind::Cmp(CmpOp::Lt),
+                ('>', _) => TokenKind::Cmp(CmpOp::Gt),
+                ('(', _) => TokenKind::LParen,
+                (')', _) => TokenKind::RParen,
+                ('=', _) => return Err(self.error_at(start, "Expected `==` for equality")),
+                ('&', _) => return Err(self.error_at(start, "Expected `&&`")),
+                ('|', _) => return Err(self.error_at(start, "Expected `||`")),
+                (c, _) if c.is_ascii_alphanumeric() || c == '_' => {
+                    let len = chars[start..]
+                        .iter()
+                        .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+                        .count();
+                    let word: String = chars[start..start + len].iter().collect();
+                    column += len;
+                    self.tokens.push(Token {
+                        kind: self.word_to_token(&word, start)?,
+                        column: start,
+                    });
+                    continue;
+                }
+                (c, _) => return Err(self.error_at(start, &format!("Unexpected character '{c}'"))),
+            };
+            column += match kind {
+                TokenKind::Cmp(CmpOp::Lt | CmpOp::Gt) | TokenKind::LParen | TokenKind::RParen => 1,
+                _ => 2,
+            };
+            self.tokens.push(Token {
+                kind,
+                column: start,
+            });
+        }
+        Ok(())
+    }
+
+    fn word_to_token(&self, word: &str, column: usize) -> Result<TokenKind, String> {
+        if word.starts_with(|c: char| c.is_ascii_digit()) {
+            let parsed = match word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")) {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => word.parse(),
+            };
+            parsed
+                .map(TokenKind::Int)
+                .map_err(|_| self.error_at(column, &format!("Invalid integer '{word}'")))
+        } else if RdhField::from_name(word).is_some() {
+            Ok(TokenKind::Ident(word.to_string()))
+        } else {
+            Err(self.error_at(
+                column,
+                &format!(
+                    "Unknown RDH field '{word}', valid fields are: {}",
+                    RdhField::NAMES.join(", ")
+                ),
+            ))
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next_if(&mut self, kind: &TokenKind) -> bool {
+        if self.peek().map(|token| &token.kind) == Some(kind) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_and()?;
+        while self.next_if(&TokenKind::Or) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_primary()?;
+        while self.next_if(&TokenKind::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_primary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        let open_column = self.peek().map(|token| token.column);
+        if self.next_if(&TokenKind::LParen) {
+            let expr = self.parse_or()?;
+            if !self.next_if(&TokenKind::RParen) {
+                return Err(self.error_at(
+                    open_column.unwrap(),
+                    "Unclosed `(`, expected a matching `)`",
+                ));
+            }
+            return Ok(expr);
+        }
+        let lhs = self.parse_operand()?;
+        let op = match self.peek().cloned() {
+            Some(Token {
+                kind: TokenKind::Cmp(op),
+                ..
+            }) => {
+                self.pos += 1;
+                op
+            }
+            Some(token) => {
+                return Err(self.error_at(
+                    token.column,
+                    &format!("Expected a comparison operator, found {token}"),
+                ))
+            }
+            None => {
+                return Err(self.error_at(
+                    self.end_column(),
+                    "Expected a comparison operator, found the end of the expression",
+                ))
+            }
+        };
+        let rhs = self.parse_operand()?;
+        Ok(Expr::Cmp(lhs, op, rhs))
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand, String> {
+        match self.peek().cloned() {
+            Some(Token {
+                kind: TokenKind::Ident(name),
+                ..
+            }) => {
+                self.pos += 1;
+                Ok(Operand::Field(RdhField::from_name(&name).unwrap()))
+            }
+            Some(Token {
+                kind: TokenKind::Int(value),
+                ..
+            }) => {
+                self.pos += 1;
+                Ok(Operand::Literal(value))
+            }
+            Some(token) => Err(self.error_at(
+                token.column,
+                &format!("Expected an RDH field or an integer, found {token}"),
+            )),
+            None => Err(self.error_at(
+                self.end_column(),
+                "Expected an RDH field or an integer, found the end of the expression",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh_cru::test_data::CORRECT_RDH_CRU_V7;
+
+    fn parse(s: &str) -> RdhPredicate {
+        s.parse().unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    #[test]
+    fn test_field_names_match_fields() {
+        for (field, name) in RdhField::ALL.iter().zip(RdhField::NAMES) {
+            assert_eq!(RdhField::from_name(name), Some(*field));
+        }
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        // `&&` binds tighter than `||`
+        let predicate = parse("link_id == 0 || stop_bit==1 && pages_counter>30");
+        assert_eq!(
+            *predicate.expr(),
+            Expr::Or(
+                Box::new(Expr::Cmp(
+                    Operand::Field(RdhField::LinkId),
+                    CmpOp::Eq,
+                    Operand::Literal(0)
+                )),
+                Box::new(Expr::And(
+                    Box::new(Expr::Cmp(
+                        Operand::Field(RdhField::StopBit),
+                        CmpOp::Eq,
+                        Operand::Literal(1)
+                    )),
+                    Box::new(Expr::Cmp(
+                        Operand::Field(RdhField::PagesCounter),
+                        CmpOp::Gt,
+                        Operand::Literal(30)
+                    ))
+                ))
+            )
+        );
+    }
+
+    #[test]
+    fn test_eval() {
+        let rdh = CORRECT_RDH_CRU_V7;
+        assert!(parse("link_id==0").eval(&rdh));
+        assert!(parse("fee_id == 0x502A && payload_size >= 5024").eval(&rdh));
+        assert!(!parse("fee_id != 0x502a").eval(&rdh));
+        assert!(parse("(stop_bit==1 || pages_counter<1) && orbit==0xB7DD575").eval(&rdh));
+        assert!(!parse("stop_bit==1 && (pages_counter<1 || link_id>0)").eval(&rdh));
+        assert!(parse("memory_size > payload_size").eval(&rdh));
+        assert!(parse("0 <= version").eval(&rdh));
+    }
+
+    #[test]
+    fn test_display_is_source() {
+        assert_eq!(
+            parse(" stop_bit==1 && pages_counter>30 ").to_string(),
+            "stop_bit==1 && pages_counter>30"
+        );
+    }
+
+    #[test]
+    fn test_error_unknown_field() {
+        let err = "stopbit == 1".parse::<RdhPredicate>().unwrap_err();
+        assert!(
+            err.starts_with("column 1: Unknown RDH field 'stopbit', valid fields are: version, "),
+            "{err}"
+        );
+        assert!(
+            err.ends_with("offset_to_next\n  stopbit == 1\n  ^"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_error_missing_operator() {
+        let err = "stop_bit==1 && pages_counter 30"
+            .parse::<RdhPredicate>()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "column 30: Expected a comparison operator, found `30`\n  stop_bit==1 && pages_counter 30\n                               ^"
+        );
+    }
+
+    #[test]
+    fn test_error_single_equals() {
+        let err = "link_id = 2".parse::<RdhPredicate>().unwrap_err();
+        assert!(err.starts_with("column 9: Expected `==` for equality"));
+    }
+
+    #[test]
+    fn test_error_unexpected_end() {
+        let err = "link_id == 2 &&".parse::<RdhPredicate>().unwrap_err();
+        assert!(err.starts_with(
+            "column 16: Expected an RDH field or an integer, found the end of the expression"
+        ));
+        let err = "".parse::<RdhPredicate>().unwrap_err();
+        assert!(err.starts_with("column 1: Expected an RDH field or an integer"));
+    }
+
+    #[test]
+    fn test_error_unclosed_paren() {
+        let err = "(link_id == 2 || link_id == 3"
+            .parse::<RdhPredicate>()
+            .unwrap_err();
+        assert!(err.starts_with("column 1: Unclosed `(`, expected a matching `)`"));
+    }
+
+    #[test]
+    fn test_error_trailing_tokens() {
+        let err = "link_id == 2 3".parse::<RdhPredicate>().unwrap_err();
+        assert!(err
+            .starts_with("column 14: Expected `&&`, `||` or the end of the expression, found `3`"));
+    }
+
+    #[test]
+    fn test_error_invalid_integer() {
+        let err = "link_id == 0xZZ".parse::<RdhPredicate>().unwrap_err();
+        assert!(err.starts_with("column 12: Invalid integer '0xZZ'"));
+    }
+}
@@ -0,0 +1,316 @@
+//! Contains the [RunMetadata] that identifies a run in the report: the fastpasta version, the command line, the input, and when the run started.
+//!
+//! It is built at startup with [RunMetadata::new] and sent to the [StatsController][crate::stats::stats_controller::StatsController], which renders it at the top of the report.
+use super::json_logger::escape_json;
+use super::lib::Config;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Git hash of the build, set by the build script, `unknown` if built outside a git repository.
+pub const GIT_HASH: &str = match option_env!("FASTPASTA_GIT_HASH") {
+    Some(hash) => hash,
+    None => "unknown",
+};
+
+/// The input of the run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputIdentity {
+    /// Input read from stdin.
+    Stdin,
+    /// Input read from a file, size and modification time are [None] if they could not be read.
+    File {
+        /// Path as given on the command line.
+        path: PathBuf,
+        /// Size in bytes.
+        size: Option<u64>,
+        /// Last modification time.
+        mtime: Option<SystemTime>,
+    },
+}
+
+impl InputIdentity {
+    /// Identifies the input file, or stdin if there is none.
+    pub fn new(input_file: &Option<PathBuf>) -> Self {
+        match input_file {
+            None => InputIdentity::Stdin,
+            Some(path) => {
+                let metadata = std::fs::metadata(path).ok();
+                InputIdentity::File {
+                    path: path.clone(),
+                    size: metadata.as_ref().map(|metadata| metadata.len()),
+                    mtime: metadata.and_then(|metadata| metadata.modified().ok()),
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for InputIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputIdentity::Stdin => write!(f, "stdin"),
+            InputIdentity::File { path, size, mtime } => {
+                write!(f, "{}", path.display())?;
+                let size = size.map_or("size unknown".to_string(), |size| format!("{size} bytes"));
+                let mtime = mtime.map_or("modification time unknown".to_string(), |mtime| {
+                    format!("modified {}", format_utc(mtime))
+                });
+                write!(f, " ({size}, {mtime})")
+            }
+        }
+    }
+}
+
+/// Identifies a run for traceability of its report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunMetadata {
+    /// Version of fastpasta.
+    pub version: &'static str,
+    /// Git hash of the build.
+    pub git_hash: &'static str,
+    /// Command line as invoked, with arguments quoted so it can be copy-pasted.
+    pub command_line: String,
+    /// The input of the run.
+    pub input: InputIdentity,
+    /// When the run started.
+    pub started: SystemTime,
+}
+
+impl RunMetadata {
+    /// Builds the [RunMetadata] of the current process, from its command line arguments and the input file of the [Config].
+    pub fn new(config: &impl Config) -> Self {
+        Self::from_args(
+            std::env::args_os().map(|arg| arg.to_string_lossy().into_owned()),
+            InputIdentity::new(config.input_file()),
+            SystemTime::now(),
+        )
+    }
+
+    /// Builds the [RunMetadata] from command line arguments, the first being the program name.
+    pub fn from_args(
+        args: impl IntoIterator<Item = String>,
+        input: InputIdentity,
+        started: SystemTime,
+    ) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_hash: GIT_HASH,
+            command_line: args
+                .into_iter()
+                .map(|arg| quote_arg(&arg))
+                .collect::<Vec<String>>()
+                .join(" "),
+            input,
+            started,
+        }
+    }
+
+    /// The lines printed above the human readable report.
+    pub fn header_lines(&self) -> String {
+        format!(
+            "fastpasta {} (git {})\nCommand: {}\nInput: {}\nRun started: {}",
+            self.version,
+            self.git_hash,
+            self.command_line,
+            self.input,
+            format_utc(self.started)
+        )
+    }
+
+    /// The `metadata` object of the JSON report.
+    pub fn to_json(&self) -> String {
+        let input = match &self.input {
+            InputIdentity::Stdin => r#""stdin""#.to_string(),
+            InputIdentity::File { path, size, mtime } => format!(
+                r#"{{"path":"{}","size":{},"mtime":{}}}"#,
+                escape_json(&path.display().to_string()),
+                size.map_or("null".to_string(), |size| size.to_string()),
+                mtime.map_or("null".to_string(), |mtime| format!(
+                    r#""{}""#,
+                    format_utc(mtime)
+                ))
+            ),
+        };
+        format!(
+            r#"{{"version":"{}","git_hash":"{}","command_line":"{}","input":{input},"started":"{}"}}"#,
+            self.version,
+            self.git_hash,
+            escape_json(&self.command_line),
+            format_utc(self.started)
+        )
+    }
+}
+
+/// Quotes a command line argument with single quotes if the shell would otherwise split or expand it.
+pub fn quote_arg(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// Formats a time as an ISO 8601 UTC timestamp with seconds, e.g. `2023-04-12T11:46:40Z`.
+pub fn format_utc(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs());
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+    // Civil date from days since the epoch, valid for all dates after 1970
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// Formats a time as an ISO 8601 UTC timestamp with microseconds, e.g. `2023-04-12T11:46:40.500000Z`.
+pub fn format_utc_micros(time: SystemTime) -> String {
+    let micros = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.subsec_micros());
+    let secs = format_utc(time);
+    format!("{}.{micros:06}Z", secs.trim_end_matches('Z'))
+}
+
+/// Parses an ISO 8601 UTC timestamp with optional fractional seconds, e.g. `2023-04-12T11:46:40.5Z`, or the seconds since the Unix epoch, e.g. `1681300000.5`.
+pub fn parse_utc(s: &str) -> Result<SystemTime, String> {
+    let invalid = || {
+        format!("Invalid UTC time '{s}', expected e.g. '2023-04-12T11:46:40.5Z' or the seconds since the Unix epoch")
+    };
+    let since_epoch = |secs: f64| {
+        if secs.is_finite() && secs >= 0.0 {
+            Ok(std::time::UNIX_EPOCH + std::time::Duration::from_secs_f64(secs))
+        } else {
+            Err(invalid())
+        }
+    };
+    if let Ok(secs) = s.parse::<f64>() {
+        return since_epoch(secs);
+    }
+    let (date, time) = s
+        .strip_suffix('Z')
+        .and_then(|s| s.split_once('T'))
+        .ok_or_else(invalid)?;
+    let date: Vec<u64> = date
+        .split('-')
+        .map(|field| field.parse::<u64>().map_err(|_| invalid()))
+        .collect::<Result<_, _>>()?;
+    let (hours_minutes, secs) = time.rsplit_once(':').ok_or_else(invalid)?;
+    let (hours, minutes) = hours_minutes.split_once(':').ok_or_else(invalid)?;
+    let (hours, minutes) = match (hours.parse::<u64>(), minutes.parse::<u64>()) {
+        (Ok(hours), Ok(minutes)) if hours < 24 && minutes < 60 => (hours, minutes),
+        _ => return Err(invalid()),
+    };
+    let secs = match secs.parse::<f64>() {
+        Ok(secs) if (0.0..60.0).contains(&secs) => secs,
+        _ => return Err(invalid()),
+    };
+    let (year, month, day) = match date[..] {
+        [year, month, day]
+            if year >= 1970 && (1..=12).contains(&month) && (1..=31).contains(&day) =>
+        {
+            (year, month, day)
+        }
+        _ => return Err(invalid()),
+    };
+    // Days since the epoch from the civil date, the inverse of the conversion in format_utc
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let month_from_march = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_from_march + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+    since_epoch((days * 86400 + hours * 3600 + minutes * 60) as f64 + secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_format_utc() {
+        assert_eq!(format_utc(std::time::UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        let time = std::time::UNIX_EPOCH + Duration::from_secs(1681300000);
+        assert_eq!(format_utc(time), "2023-04-12T11:46:40Z");
+        // Leap day
+        let time = std::time::UNIX_EPOCH + Duration::from_secs(1709164800 + 3599);
+        assert_eq!(format_utc(time), "2024-02-29T00:59:59Z");
+    }
+
+    #[test]
+    fn test_parse_utc() {
+        let time = parse_utc("2023-04-12T11:46:40Z").unwrap();
+        assert_eq!(format_utc(time), "2023-04-12T11:46:40Z");
+        let time = parse_utc("2024-02-29T00:59:59.25Z").unwrap();
+        assert_eq!(format_utc_micros(time), "2024-02-29T00:59:59.250000Z");
+        assert_eq!(
+            parse_utc("1681300000.5").unwrap(),
+            parse_utc("2023-04-12T11:46:40.5Z").unwrap()
+        );
+        for invalid in [
+            "2023-04-12 11:46:40",
+            "2023-13-12T11:46:40Z",
+            "2023-04-12T11:60:00Z",
+            "-1",
+        ] {
+            assert!(parse_utc(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_quote_arg() {
+        assert_eq!(quote_arg("--filter-link"), "--filter-link");
+        assert_eq!(quote_arg("/data/run 1.raw"), "'/data/run 1.raw'");
+        assert_eq!(quote_arg("it's"), r"'it'\''s'");
+        assert_eq!(quote_arg(""), "''");
+    }
+
+    #[test]
+    fn test_metadata_header_and_json() {
+        let started = std::time::UNIX_EPOCH + Duration::from_secs(1681300000);
+        let metadata = RunMetadata::from_args(
+            ["fastpasta", "run 1.raw", "check", "all", "its"].map(String::from),
+            InputIdentity::File {
+                path: PathBuf::from("run 1.raw"),
+                size: Some(1824),
+                mtime: Some(started),
+            },
+            started,
+        );
+        let header_lines = metadata.header_lines();
+        let lines: Vec<&str> = header_lines.lines().collect();
+        assert_eq!(
+            lines[0],
+            format!("fastpasta {} (git {GIT_HASH})", env!("CARGO_PKG_VERSION"))
+        );
+        assert_eq!(lines[1], "Command: fastpasta 'run 1.raw' check all its");
+        assert_eq!(
+            lines[2],
+            "Input: run 1.raw (1824 bytes, modified 2023-04-12T11:46:40Z)"
+        );
+        assert_eq!(lines[3], "Run started: 2023-04-12T11:46:40Z");
+        assert!(metadata.to_json().ends_with(
+            r#""command_line":"fastpasta 'run 1.raw' check all its","input":{"path":"run 1.raw","size":1824,"mtime":"2023-04-12T11:46:40Z"},"started":"2023-04-12T11:46:40Z"}"#
+        ));
+
+        let stdin_metadata =
+            RunMetadata::from_args(["fastpasta".to_string()], InputIdentity::Stdin, started);
+        assert!(stdin_metadata.header_lines().contains("Input: stdin\n"));
+        assert!(stdin_metadata.to_json().contains(r#""input":"stdin""#));
+    }
+}
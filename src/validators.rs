@@ -1,9 +1,20 @@
 //! Contains the LinkValidator struct that is the entry point for all data validation, also contains all the subvalidators.
+//!
+//! Without the `pipeline` feature, only the sanity validators of the words are built: [rdh], [status_words] and [data_words].
+#[cfg(feature = "pipeline")]
 pub mod cdp_running;
+#[cfg(feature = "pipeline")]
+pub mod check_registry;
 pub mod data_words;
+#[cfg(feature = "pipeline")]
+pub mod hw_map;
+#[cfg(feature = "pipeline")]
 pub mod its_payload_fsm_cont;
+#[cfg(feature = "pipeline")]
 pub mod lib;
+#[cfg(feature = "pipeline")]
 pub mod link_validator;
 pub mod rdh;
+#[cfg(feature = "pipeline")]
 pub mod rdh_running;
 pub mod status_words;
@@ -2,17 +2,22 @@
 //!
 //! [CdpRunningValidator] delegates sanity checks to word specific sanity checkers.
 use super::data_words::DATA_WORD_SANITY_CHECKER;
+use crate::stats::cdp_detail::WordCounts;
+use crate::util::config::TriggerSource;
 use crate::util::lib::Config;
+use crate::validators::check_registry::{CheckEvaluations, KnownCheck};
 use crate::validators::its_payload_fsm_cont::ItsPayloadFsmContinuous;
-use crate::validators::its_payload_fsm_cont::PayloadWord;
+use crate::validators::its_payload_fsm_cont::{PayloadFsmState, PayloadWord};
+use crate::validators::link_validator::GBT_WORD_SIZE;
 use crate::words::data_words::{
-    ob_data_word_id_to_input_number_connector, ob_data_word_id_to_lane,
+    ob_data_word_id_to_input_number_connector, ob_data_word_id_to_lane, DataWordKind,
 };
-use crate::words::lib::RDH;
+use crate::words::lib::{GBT_WIDE_WORD_SIZE, RDH};
+use crate::words::rdh::TriggerType;
 use crate::words::status_words::{is_lane_active, Cdw};
 use crate::{
     stats::stats_controller::StatType,
-    validators::status_words::STATUS_WORD_SANITY_CHECKER,
+    validators::status_words::{StatusWordSanityChecker, STATUS_WORD_SANITY_CHECKER},
     words::status_words::{Ddw0, Ihw, StatusWord, Tdh, Tdt},
 };
 
@@ -41,6 +46,70 @@ impl CdpRunningLocalConfig {
     }
 }
 
+/// Destination of the errors found by the [CdpRunningValidator].
+pub(crate) enum ErrorSink {
+    /// Send errors to the [StatsController][crate::stats::stats_controller::StatsController].
+    Channel(std::sync::mpsc::Sender<StatType>),
+    /// Send errors to the [StatsController][crate::stats::stats_controller::StatsController], attributed to the link of the current RDH.
+    LinkChannel(std::sync::mpsc::Sender<StatType>),
+    /// Collect errors, to be retrieved with [CdpRunningValidator::take_errors].
+    Collect(std::cell::RefCell<Vec<String>>),
+}
+
+/// Result of the stateless checks of a GBT word as a data word, which don't depend on the state carried across CDPs.
+///
+/// Used to split the checks of a data word into a stateless phase that can be done in parallel for many CDPs, see [precheck_payload][super::link_validator::precheck_payload],
+/// and a stateful phase done in order with [CdpRunningValidator::check_prechecked].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrecheckedDataWord {
+    sanity_error: Option<String>,
+    lane: u8,
+    ob_input_connector: u8,
+}
+
+impl PrecheckedDataWord {
+    /// Does the sanity check of the data word, and extracts the lane and OB input connector from the ID.
+    pub fn new(data_word_slice: &[u8]) -> Self {
+        let id = data_word_slice[9];
+        let sanity_error = DATA_WORD_SANITY_CHECKER
+            .check_any(data_word_slice)
+            .err()
+            .map(|e| format!("[E70] {e}"));
+        let (lane, ob_input_connector) = match id >> 5 {
+            // Inner Barrel
+            0b001 => (id & 0x1F, 0),
+            // Outer Barrel
+            0b010 => (
+                ob_data_word_id_to_lane(id),
+                ob_data_word_id_to_input_number_connector(id),
+            ),
+            _ => (0, 0),
+        };
+        Self {
+            sanity_error,
+            lane,
+            ob_input_connector,
+        }
+    }
+}
+
+/// Snapshot of the state of a [CdpRunningValidator] that is carried across CDPs, see [CdpRunningValidator::snapshot].
+///
+/// Status words are stored as their raw bytes.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CdpRunningState {
+    fsm_state: PayloadFsmState,
+    current_ihw: Option<Vec<u8>>,
+    current_tdh: Option<Vec<u8>>,
+    previous_tdh: Option<Vec<u8>>,
+    current_tdt: Option<Vec<u8>>,
+    current_ddw0: Option<Vec<u8>>,
+    previous_cdw: Option<Vec<u8>>,
+    is_new_data: bool,
+    open_packet_tdh_mem_pos: Option<u64>,
+    previous_tdt_mem_pos: Option<u64>,
+}
+
 /// Checks the CDP payload and reports any errors.
 pub struct CdpRunningValidator<T: RDH> {
     config: CdpRunningLocalConfig,
@@ -53,10 +122,25 @@ pub struct CdpRunningValidator<T: RDH> {
     current_ddw0: Option<Ddw0>,
     previous_cdw: Option<Cdw>,
     gbt_word_counter: u16,
-    pub(crate) stats_send_ch: std::sync::mpsc::Sender<StatType>,
+    last_gbt_word: [u8; GBT_WORD_SIZE], // The last GBT word checked, reported if the payload ends after it in the middle of a packet
+    pub(crate) error_sink: ErrorSink,
     payload_mem_pos: u64,
     gbt_word_padding_size_bytes: u8,
-    is_new_data: bool, // Flag used to indicate start of new CDP payload where a CDW is valid
+    gbt_wide: bool, // Words are 16 bytes, with upper bytes that are checked, regardless of the data format
+    is_new_data: bool, // Flag used to indicate start of new CDP payload or packet, where one or more CDWs are valid
+    open_packet_tdh_mem_pos: Option<u64>, // Memory position of the TDH opening the current packet, until its TDT
+    previous_tdt_mem_pos: Option<u64>,    // Memory position of the previous TDT, until the next TDH
+    starts_mid_hbf: bool, // The input starts mid-HBF, until the first TDH, which may continue a packet of a page before the input
+    verbose_errors: bool,
+    warn_tdt_timeouts: bool,
+    ignore_tdh_trigger_bits: Vec<TriggerType>,
+    trigger_source: TriggerSource,
+    status_word_sanity_checker: StatusWordSanityChecker,
+    check_evaluations: CheckEvaluations,
+    word_counts: WordCounts,
+    errors_reported: std::cell::Cell<u64>,
+    /// Lanes with the fatal status in any DDW0, [None] until a DDW0 is seen.
+    fatal_lanes: Option<u32>,
 }
 
 impl<T: RDH> Default for CdpRunningValidator<T> {
@@ -74,10 +158,24 @@ impl<T: RDH> Default for CdpRunningValidator<T> {
             current_ddw0: None,
             previous_cdw: None,
             gbt_word_counter: 0,
-            stats_send_ch: std::sync::mpsc::channel().0,
+            last_gbt_word: [0; GBT_WORD_SIZE],
+            error_sink: ErrorSink::Channel(std::sync::mpsc::channel().0),
             payload_mem_pos: 0,
             gbt_word_padding_size_bytes: 0,
+            gbt_wide: false,
             is_new_data: false,
+            open_packet_tdh_mem_pos: None,
+            previous_tdt_mem_pos: None,
+            starts_mid_hbf: false,
+            verbose_errors: false,
+            warn_tdt_timeouts: false,
+            ignore_tdh_trigger_bits: Vec::new(),
+            trigger_source: TriggerSource::Any,
+            status_word_sanity_checker: STATUS_WORD_SANITY_CHECKER,
+            check_evaluations: CheckEvaluations::default(),
+            word_counts: WordCounts::default(),
+            errors_reported: std::cell::Cell::new(0),
+            fatal_lanes: None,
         }
     }
 }
@@ -85,6 +183,22 @@ impl<T: RDH> Default for CdpRunningValidator<T> {
 impl<T: RDH> CdpRunningValidator<T> {
     /// Creates a new [CdpRunningValidator] from a [Config] and a [StatType] producer channel.
     pub fn new(config: &impl Config, stats_send_ch: std::sync::mpsc::Sender<StatType>) -> Self {
+        Self::with_error_sink(config, ErrorSink::Channel(stats_send_ch))
+    }
+
+    /// Creates a new [CdpRunningValidator] that collects errors instead of sending them, retrieve them with [CdpRunningValidator::take_errors].
+    ///
+    /// Useful for validating data without a [StatsController][crate::stats::stats_controller::StatsController] running.
+    /// The checks are configured the same way as with [CdpRunningValidator::new].
+    /// Warnings, e.g. of TDT timeouts with `--warn-tdt-timeouts`, are not collected, they are logged.
+    pub fn new_collecting(config: &impl Config) -> Self {
+        Self::with_error_sink(
+            config,
+            ErrorSink::Collect(std::cell::RefCell::new(Vec::new())),
+        )
+    }
+
+    fn with_error_sink(config: &impl Config, error_sink: ErrorSink) -> Self {
         Self {
             config: CdpRunningLocalConfig::new(config),
             its_state_machine: ItsPayloadFsmContinuous::default(),
@@ -96,10 +210,99 @@ impl<T: RDH> CdpRunningValidator<T> {
             current_ddw0: None,
             previous_cdw: None,
             gbt_word_counter: 0,
-            stats_send_ch,
+            last_gbt_word: [0; GBT_WORD_SIZE],
+            error_sink,
             payload_mem_pos: 0,
             gbt_word_padding_size_bytes: 0,
+            gbt_wide: config.gbt_wide(),
             is_new_data: false,
+            open_packet_tdh_mem_pos: None,
+            previous_tdt_mem_pos: None,
+            starts_mid_hbf: false,
+            verbose_errors: config.verbose_errors(),
+            warn_tdt_timeouts: config.warn_tdt_timeouts(),
+            ignore_tdh_trigger_bits: config.ignore_tdh_trigger_bits(),
+            trigger_source: config.trigger_source(),
+            status_word_sanity_checker: StatusWordSanityChecker::new().with_max_bc(config.max_bc()),
+            check_evaluations: CheckEvaluations::default(),
+            word_counts: WordCounts::default(),
+            errors_reported: std::cell::Cell::new(0),
+            fatal_lanes: None,
+        }
+    }
+
+    /// Takes the errors collected so far, if the [CdpRunningValidator] was created with [CdpRunningValidator::new_collecting].
+    pub fn take_errors(&mut self) -> Vec<String> {
+        match &mut self.error_sink {
+            ErrorSink::Collect(errors) => errors.take(),
+            ErrorSink::Channel(_) | ErrorSink::LinkChannel(_) => Vec::new(),
+        }
+    }
+
+    /// Takes the number of evaluations of each check counted so far.
+    pub fn take_check_evaluations(&mut self) -> CheckEvaluations {
+        std::mem::take(&mut self.check_evaluations)
+    }
+
+    /// Takes the number of each kind of word checked since the last call.
+    pub fn take_word_counts(&mut self) -> WordCounts {
+        std::mem::take(&mut self.word_counts)
+    }
+
+    /// Number of errors reported so far.
+    pub fn errors_reported(&self) -> u64 {
+        self.errors_reported.get()
+    }
+
+    /// Mask of the lanes with the fatal status in any DDW0 seen, [None] if no DDW0 was seen.
+    pub fn fatal_lanes(&self) -> Option<u32> {
+        self.fatal_lanes
+    }
+
+    /// Takes a snapshot of the state carried across CDPs, which can be restored with [CdpRunningValidator::restore].
+    pub fn snapshot(&self) -> CdpRunningState {
+        fn word_bytes<W: StatusWord>(word: &Option<W>) -> Option<Vec<u8>> {
+            word.as_ref().map(|word| word.to_byte_slice().to_vec())
+        }
+        CdpRunningState {
+            fsm_state: self.its_state_machine.state(),
+            current_ihw: word_bytes(&self.current_ihw),
+            current_tdh: word_bytes(&self.current_tdh),
+            previous_tdh: word_bytes(&self.previous_tdh),
+            current_tdt: word_bytes(&self.current_tdt),
+            current_ddw0: word_bytes(&self.current_ddw0),
+            previous_cdw: word_bytes(&self.previous_cdw),
+            is_new_data: self.is_new_data,
+            open_packet_tdh_mem_pos: self.open_packet_tdh_mem_pos,
+            previous_tdt_mem_pos: self.previous_tdt_mem_pos,
+        }
+    }
+
+    /// Restores the state from a snapshot taken with [CdpRunningValidator::snapshot].
+    pub fn restore(&mut self, state: &CdpRunningState) -> Result<(), std::io::Error> {
+        fn load_word<W: StatusWord>(bytes: &Option<Vec<u8>>) -> Result<Option<W>, std::io::Error> {
+            bytes
+                .as_ref()
+                .map(|bytes| W::load(&mut bytes.as_slice()))
+                .transpose()
+        }
+        self.its_state_machine.restore(state.fsm_state);
+        self.current_ihw = load_word(&state.current_ihw)?;
+        self.current_tdh = load_word(&state.current_tdh)?;
+        self.previous_tdh = load_word(&state.previous_tdh)?;
+        self.current_tdt = load_word(&state.current_tdt)?;
+        self.current_ddw0 = load_word(&state.current_ddw0)?;
+        self.previous_cdw = load_word(&state.previous_cdw)?;
+        self.is_new_data = state.is_new_data;
+        self.open_packet_tdh_mem_pos = state.open_packet_tdh_mem_pos;
+        self.previous_tdt_mem_pos = state.previous_tdt_mem_pos;
+        Ok(())
+    }
+
+    /// Attributes the errors sent to the [StatsController][crate::stats::stats_controller::StatsController] to the link of the current RDH.
+    pub(crate) fn attribute_errors_to_link(&mut self) {
+        if let ErrorSink::Channel(stats_send_ch) = &self.error_sink {
+            self.error_sink = ErrorSink::LinkChannel(stats_send_ch.clone());
         }
     }
 
@@ -111,27 +314,44 @@ impl<T: RDH> CdpRunningValidator<T> {
 
     /// Helper function to format and report an error
     ///
-    /// Takes in the error string slice and the word slice
-    /// Adds the current memory position to the error string
-    /// Sends the error to the stats channel
+    /// Takes in the error string slice and the word slice, which may be shorter than a GBT word
+    /// Adds the current memory position to the error string, and the context of the current RDH with `--verbose-errors`
+    /// Sends the error to the error sink
     #[inline]
     fn report_error(&self, error: &str, word_slice: &[u8]) {
+        self.errors_reported.set(self.errors_reported.get() + 1);
         let mem_pos = self.calc_current_word_mem_pos();
-        self.stats_send_ch
-            .send(StatType::Error(format!(
-                "{mem_pos:#X}: {error} [{:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X}]",
-                word_slice[0],
-                word_slice[1],
-                word_slice[2],
-                word_slice[3],
-                word_slice[4],
-                word_slice[5],
-                word_slice[6],
-                word_slice[7],
-                word_slice[8],
-                word_slice[9],
-                            )))
-            .expect("Failed to send error to stats channel");
+        let mut error_msg = format!("{mem_pos:#X}: {error} [{}]", format_gbt_word(word_slice));
+        if self.verbose_errors {
+            if let Some(rdh) = &self.current_rdh {
+                error_msg.push(' ');
+                error_msg.push_str(&rdh_error_context(rdh));
+            }
+        }
+        match &self.error_sink {
+            ErrorSink::Channel(stats_send_ch) => stats_send_ch
+                .send(StatType::Error(error_msg))
+                .expect("Failed to send error to stats channel"),
+            ErrorSink::LinkChannel(stats_send_ch) => stats_send_ch
+                .send(StatType::LinkError {
+                    link_id: self.current_rdh.as_ref().unwrap().link_id(),
+                    msg: error_msg,
+                })
+                .expect("Failed to send error to stats channel"),
+            ErrorSink::Collect(errors) => errors.borrow_mut().push(error_msg),
+        }
+    }
+
+    /// Sends a warning to the [StatsController][crate::stats::stats_controller::StatsController], which decides its severity, or logs it if errors are collected.
+    fn report_warning(&self, warning: String) {
+        match &self.error_sink {
+            ErrorSink::Channel(stats_send_ch) | ErrorSink::LinkChannel(stats_send_ch) => {
+                stats_send_ch
+                    .send(StatType::Warning(warning))
+                    .expect("Failed to send warning to stats channel")
+            }
+            ErrorSink::Collect(_) => log::warn!("{warning}"),
+        }
     }
 
     /// Resets the state machine to the initial state and logs a warning
@@ -141,6 +361,23 @@ impl<T: RDH> CdpRunningValidator<T> {
     pub fn reset_fsm(&mut self) {
         log::warn!("Resetting CDP Payload FSM");
         self.its_state_machine.reset_fsm();
+        self.open_packet_tdh_mem_pos = None;
+        self.previous_tdt_mem_pos = None;
+    }
+
+    /// Starts the checks of an input that starts mid-HBF, so that the first TDH may continue a packet from a page before the start of the input.
+    ///
+    /// Has to be called before the first GBT word is checked.
+    pub fn start_mid_hbf(&mut self) {
+        self.starts_mid_hbf = true;
+    }
+
+    /// Starts the checks of a single stop page, so that its DDW0 is expected as if the packets of the previous pages were done.
+    ///
+    /// Has to be called before the first GBT word is checked.
+    pub fn start_at_stop_page(&mut self) {
+        self.its_state_machine
+            .restore(PayloadFsmState::Ddw0OrTdhOrIhwAfterPacketDone);
     }
 
     /// This function has to be called for every RDH
@@ -150,49 +387,162 @@ impl<T: RDH> CdpRunningValidator<T> {
     #[inline]
     pub fn set_current_rdh(&mut self, rdh: &T, rdh_mem_pos: u64) {
         self.current_rdh = Some(T::load(&mut rdh.to_byte_slice()).unwrap());
-        self.payload_mem_pos = rdh_mem_pos + 64;
-        if rdh.data_format() == 0 {
-            self.gbt_word_padding_size_bytes = 6; // Data format 0
+        self.payload_mem_pos = rdh_mem_pos + rdh.header_size_bytes() as u64;
+        self.set_data_format(rdh.data_format());
+        self.is_new_data = true;
+        self.gbt_word_counter = 0;
+        if rdh.pages_counter() == 0 {
+            // The pairing of TDHs and TDTs is checked within an HBF
+            self.open_packet_tdh_mem_pos = None;
+            self.previous_tdt_mem_pos = None;
+        }
+    }
+
+    /// Sets the data format the payload of the current RDH is decoded with, if it is not the one declared in the RDH
+    ///
+    /// Has to be called after [CdpRunningValidator::set_current_rdh], as the padding determines the memory position of each word
+    #[inline]
+    pub fn set_data_format(&mut self, data_format: u8) {
+        if data_format == 0 || self.gbt_wide {
+            self.gbt_word_padding_size_bytes = 6; // Data format 0 or GBT wide mode
         } else {
             self.gbt_word_padding_size_bytes = 0; // Data format 2
         }
-        self.is_new_data = true;
-        self.gbt_word_counter = 0;
     }
 
     /// This function has to be called for every GBT word
     #[inline]
     pub fn check(&mut self, gbt_word: &[u8]) {
-        debug_assert!(gbt_word.len() == 10);
+        self.check_word(gbt_word, None);
+    }
+
+    /// Same as [CdpRunningValidator::check], but uses the result of the stateless checks of the word if it is a data word.
+    #[inline]
+    pub fn check_prechecked(&mut self, gbt_word: &[u8], prechecked: &PrecheckedDataWord) {
+        self.check_word(gbt_word, Some(prechecked));
+    }
+
+    /// Same as [CdpRunningValidator::check], but for a 16 byte word of GBT wide mode.
+    ///
+    /// The lower 10 bytes are checked as a GBT word, the upper 6 bytes are not checked as their layout is not documented.
+    #[inline]
+    pub fn check_wide(&mut self, wide_word: &[u8], prechecked: Option<&PrecheckedDataWord>) {
+        if wide_word.len() != GBT_WIDE_WORD_SIZE {
+            self.gbt_word_counter += 1;
+            self.report_error(
+                &format!(
+                    "[E70] GBT wide word is {} bytes, expected {GBT_WIDE_WORD_SIZE}",
+                    wide_word.len()
+                ),
+                wide_word,
+            );
+            return;
+        }
+        self.check_word(&wide_word[..GBT_WORD_SIZE], prechecked);
+    }
+
+    #[inline]
+    fn check_word(&mut self, gbt_word: &[u8], prechecked: Option<&PrecheckedDataWord>) {
         self.gbt_word_counter += 1; // Tracks the number of GBT words seen in the current CDP
+        if gbt_word.len() != GBT_WORD_SIZE {
+            // A malformed payload, the word can't be decoded
+            self.report_error(
+                &format!(
+                    "[E70] GBT word is {} bytes, expected {GBT_WORD_SIZE}",
+                    gbt_word.len()
+                ),
+                gbt_word,
+            );
+            return;
+        }
 
+        if self.starts_mid_hbf
+            && self.its_state_machine.state() == PayloadFsmState::TdhAfterIhw
+            && gbt_word[9] == 0xE8
+        {
+            self.starts_mid_hbf = false;
+            if Tdh::load(&mut <&[u8]>::clone(&gbt_word))
+                .unwrap()
+                .continuation()
+                == 1
+            {
+                // The packet was opened in a page before the start of the input
+                self.its_state_machine
+                    .restore(PayloadFsmState::ContinuationTdh);
+            }
+        }
+        self.check_ihw_outside_packet(gbt_word);
+        self.last_gbt_word.copy_from_slice(gbt_word);
         let current_word = self.its_state_machine.advance(gbt_word);
+        self.check_tdh_tdt_pairing(gbt_word);
 
         match current_word {
             PayloadWord::IHW => {
+                self.word_counts.ihw += 1;
                 self.process_status_word(StatusWordKind::Ihw(gbt_word));
                 self.check_rdh_at_initial_ihw(gbt_word);
             }
             PayloadWord::IHW_continuation => {
+                self.word_counts.ihw += 1;
                 self.process_status_word(StatusWordKind::Ihw(gbt_word))
             }
             PayloadWord::TDH => {
+                self.word_counts.tdh += 1;
                 self.process_status_word(StatusWordKind::Tdh(gbt_word));
+                self.check_tdh_trigger_source(gbt_word);
                 self.check_tdh_no_continuation(gbt_word);
+                self.is_new_data = true;
             }
             PayloadWord::TDH_continuation => {
+                self.word_counts.tdh += 1;
                 self.process_status_word(StatusWordKind::Tdh(gbt_word));
+                self.check_tdh_trigger_source(gbt_word);
                 self.check_tdh_continuation(gbt_word);
             }
             PayloadWord::TDH_after_packet_done => {
+                self.word_counts.tdh += 1;
                 self.process_status_word(StatusWordKind::Tdh(gbt_word));
+                self.check_tdh_trigger_source(gbt_word);
                 self.check_tdh_by_was_tdt_packet_done_true(gbt_word);
+                self.is_new_data = true;
+            }
+            PayloadWord::TDT => {
+                self.word_counts.tdt += 1;
+                self.process_status_word(StatusWordKind::Tdt(gbt_word))
             }
-            PayloadWord::TDT => self.process_status_word(StatusWordKind::Tdt(gbt_word)),
             // DataWord and CDW are handled together
-            PayloadWord::CDW | PayloadWord::DataWord => self.process_data_word(gbt_word),
+            PayloadWord::CDW | PayloadWord::DataWord => {
+                if gbt_word[9] == 0xF8 {
+                    self.word_counts.cdw += 1;
+                } else {
+                    self.word_counts.data += 1;
+                }
+                self.process_data_word(gbt_word, prechecked)
+            }
+
+            PayloadWord::DDW0 => {
+                self.word_counts.ddw0 += 1;
+                self.process_status_word(StatusWordKind::Ddw0(gbt_word))
+            }
+        }
+    }
 
-            PayloadWord::DDW0 => self.process_status_word(StatusWordKind::Ddw0(gbt_word)),
+    /// Checks that the payload of the current RDH ends where ending is legal, after a TDT, a DDW0 or at a continuation boundary.
+    ///
+    /// Has to be called after the last GBT word of each payload, the error is reported at the last word with the state of the payload state machine.
+    pub fn check_payload_end(&mut self) {
+        if !self.config.running_checks || self.gbt_word_counter == 0 {
+            return;
+        }
+        self.check_evaluations.count(KnownCheck::PayloadEnd);
+        if !self.its_state_machine.is_terminal_state() {
+            self.report_error(
+                &format!(
+                    "[E51] Payload ended in the middle of an open packet, expecting {}",
+                    self.its_state_machine.current_state_name()
+                ),
+                &self.last_gbt_word,
+            );
         }
     }
 
@@ -220,15 +570,19 @@ impl<T: RDH> CdpRunningValidator<T> {
             StatusWordKind::Ihw(ihw_as_slice) => {
                 let ihw = Ihw::load(&mut <&[u8]>::clone(&ihw_as_slice)).unwrap();
                 log::debug!("{ihw}");
-                if let Err(e) = STATUS_WORD_SANITY_CHECKER.sanity_check_ihw(&ihw) {
+                self.check_evaluations.count(KnownCheck::IhwSanity);
+                self.check_evaluations.count(KnownCheck::IhwActiveLanes);
+                if let Err(e) = self.status_word_sanity_checker.sanity_check_ihw(&ihw) {
                     self.report_error(&format!("[E30] {e}"), ihw_as_slice);
                 }
+                self.check_ihw_active_lanes_in_layer(&ihw, ihw_as_slice);
                 self.current_ihw = Some(ihw);
             }
             StatusWordKind::Tdh(tdh_as_slice) => {
                 let tdh = Tdh::load(&mut <&[u8]>::clone(&tdh_as_slice)).unwrap();
                 log::debug!("{tdh}");
-                if let Err(e) = STATUS_WORD_SANITY_CHECKER.sanity_check_tdh(&tdh) {
+                self.check_evaluations.count(KnownCheck::TdhSanity);
+                if let Err(e) = self.status_word_sanity_checker.sanity_check_tdh(&tdh) {
                     self.report_error(&format!("[E40] {e}"), tdh_as_slice);
                 }
                 // Swap current and last TDH, then replace current with the new TDH
@@ -238,45 +592,143 @@ impl<T: RDH> CdpRunningValidator<T> {
             StatusWordKind::Tdt(tdt_as_slice) => {
                 let tdt = Tdt::load(&mut <&[u8]>::clone(&tdt_as_slice)).unwrap();
                 log::debug!("{tdt}");
-                if let Err(e) = STATUS_WORD_SANITY_CHECKER.sanity_check_tdt(&tdt) {
+                self.check_evaluations.count(KnownCheck::TdtSanity);
+                if let Err(e) = self.status_word_sanity_checker.sanity_check_tdt(&tdt) {
                     self.report_error(&format!("[E50] {e}"), tdt_as_slice);
                 }
+                self.report_tdt_timeouts(&tdt);
                 self.current_tdt = Some(tdt);
             }
             StatusWordKind::Ddw0(ddw0_as_slice) => {
                 let ddw0 = Ddw0::load(&mut <&[u8]>::clone(&ddw0_as_slice)).unwrap();
                 log::debug!("{ddw0}");
-                if let Err(e) = STATUS_WORD_SANITY_CHECKER.sanity_check_ddw0(&ddw0) {
+                self.check_evaluations.count(KnownCheck::Ddw0Sanity);
+                if let Err(e) = self.status_word_sanity_checker.sanity_check_ddw0(&ddw0) {
                     self.report_error(&format!("[E60] {e}"), ddw0_as_slice);
                 }
 
+                self.report_ddw0_transmission_timeout(&ddw0);
+
                 // Additional state dependent checks on RDH
                 self.check_rdh_at_ddw0(ddw0_as_slice);
+                *self.fatal_lanes.get_or_insert(0) |= ddw0.fatal_lanes();
                 self.current_ddw0 = Some(ddw0);
             }
         }
     }
 
+    /// Checks that no bits are set in the IHW active_lanes above the number of lanes of the layer of the current RDH's FEE ID
+    #[inline]
+    fn check_ihw_active_lanes_in_layer(&self, ihw: &Ihw, ihw_slice: &[u8]) {
+        let layer =
+            crate::words::lib::layer_from_feeid(self.current_rdh.as_ref().unwrap().fee_id());
+        let lane_count = crate::words::lib::lane_count_from_layer(layer);
+        let active_lanes = ihw.active_lanes();
+        let illegal_lanes = active_lanes >> lane_count;
+        if illegal_lanes != 0 {
+            let illegal_bits = (lane_count..28)
+                .filter(|bit| active_lanes & (1 << bit) != 0)
+                .map(|bit| bit.to_string())
+                .collect::<Vec<String>>()
+                .join(", ");
+            self.report_error(
+                &format!("[E31] IHW active_lanes {active_lanes:#X} has bits set above the {lane_count} lanes of layer {layer}, illegal bits: {illegal_bits}."),
+                ihw_slice,
+            );
+        }
+    }
+
+    /// Counts each timeout flag set in a TDT per stave, and logs a warning for each if enabled.
+    #[inline]
+    fn report_tdt_timeouts(&self, tdt: &Tdt) {
+        let timeouts = tdt.timeouts();
+        if timeouts.is_empty() {
+            return;
+        }
+        let fee_id = self.current_rdh.as_ref().unwrap().fee_id();
+        let layer = crate::words::lib::layer_from_feeid(fee_id);
+        let stave = crate::words::lib::stave_number_from_feeid(fee_id);
+        for timeout in timeouts {
+            if self.warn_tdt_timeouts {
+                self.report_warning(format!(
+                    "{:#X}: TDT {} flag set on stave L{layer}_{stave}",
+                    self.calc_current_word_mem_pos(),
+                    timeout.marker()
+                ));
+            }
+            if let ErrorSink::Channel(stats_send_ch) | ErrorSink::LinkChannel(stats_send_ch) =
+                &self.error_sink
+            {
+                stats_send_ch
+                    .send(StatType::TdtTimeout {
+                        layer,
+                        stave,
+                        timeout,
+                    })
+                    .expect("Failed to send TDT timeout to stats channel");
+            }
+        }
+    }
+
+    /// Counts a DDW0 with the transmission_timeout flag set on the link of the current RDH.
+    #[inline]
+    fn report_ddw0_transmission_timeout(&self, ddw0: &Ddw0) {
+        if !ddw0.transmission_timeout() {
+            return;
+        }
+        if let ErrorSink::Channel(stats_send_ch) | ErrorSink::LinkChannel(stats_send_ch) =
+            &self.error_sink
+        {
+            stats_send_ch
+                .send(StatType::Ddw0TransmissionTimeout(
+                    self.current_rdh.as_ref().unwrap().link_id(),
+                ))
+                .expect("Failed to send DDW0 transmission timeout to stats channel");
+        }
+    }
+
     /// Takes a slice of bytes expected to be a data word, and checks if it has a valid identifier.
+    ///
+    /// CDWs are only valid as the first word(s) following the IHW/TDH that starts a new payload or packet.
     #[inline]
-    fn process_data_word(&mut self, data_word_slice: &[u8]) {
+    fn process_data_word(
+        &mut self,
+        data_word_slice: &[u8],
+        prechecked: Option<&PrecheckedDataWord>,
+    ) {
         let id_index = 9;
-        if self.is_new_data && data_word_slice[id_index] == 0xF8 {
-            // CDW
-            self.process_cdw(data_word_slice);
+        let data_word_kind = DataWordKind::from_id(data_word_slice[id_index]);
+        if data_word_kind == Some(DataWordKind::Calibration) {
+            self.check_evaluations.count(KnownCheck::CdwPosition);
+            if self.is_new_data {
+                // CDW, more CDWs can follow, so the flag is kept set
+                self.process_cdw(data_word_slice);
+                return;
+            }
+            self.report_error("[E82] CDW at illegal position", data_word_slice);
         } else {
             // Regular data word
-            if let Err(e) = DATA_WORD_SANITY_CHECKER.check_any(data_word_slice) {
-                self.report_error(&format!("[E70] {e}"), data_word_slice);
+            self.check_evaluations.count(KnownCheck::DataWordSanity);
+            let checked_now;
+            let prechecked = match prechecked {
+                Some(prechecked) => prechecked,
+                None => {
+                    checked_now = PrecheckedDataWord::new(data_word_slice);
+                    &checked_now
+                }
+            };
+            if let Some(e) = &prechecked.sanity_error {
+                self.report_error(e, data_word_slice);
                 log::debug!("Data word: {data_word_slice:?}");
             }
-            let id_3_msb = data_word_slice[id_index] >> 5;
-            if id_3_msb == 0b001 {
-                // Inner Barrel
-                self.process_ib_data_word(data_word_slice);
-            } else if id_3_msb == 0b010 {
-                // Outer Barrel
-                self.process_ob_data_word(data_word_slice);
+            match data_word_kind {
+                Some(DataWordKind::InnerBarrel) => {
+                    self.process_ib_data_word(data_word_slice, prechecked)
+                }
+                Some(DataWordKind::OuterBarrel) => {
+                    self.process_ob_data_word(data_word_slice, prechecked)
+                }
+                _ => (),
             }
         }
 
@@ -284,11 +736,12 @@ impl<T: RDH> CdpRunningValidator<T> {
     }
 
     #[inline]
-    fn process_ib_data_word(&mut self, ib_slice: &[u8]) {
+    fn process_ib_data_word(&mut self, ib_slice: &[u8], prechecked: &PrecheckedDataWord) {
         if !self.config.running_checks {
             return;
         }
-        let lane_id = ib_slice[9] & 0x1F;
+        self.check_evaluations.count(KnownCheck::IbLaneActive);
+        let lane_id = prechecked.lane;
         // lane in active_lanes
         let active_lanes = self.current_ihw.as_ref().unwrap().active_lanes();
         if !is_lane_active(lane_id, active_lanes) {
@@ -300,11 +753,13 @@ impl<T: RDH> CdpRunningValidator<T> {
     }
 
     #[inline]
-    fn process_ob_data_word(&mut self, ob_slice: &[u8]) {
+    fn process_ob_data_word(&mut self, ob_slice: &[u8], prechecked: &PrecheckedDataWord) {
         if !self.config.running_checks {
             return;
         }
-        let lane_id = ob_data_word_id_to_lane(ob_slice[9]);
+        self.check_evaluations.count(KnownCheck::ObLaneActive);
+        self.check_evaluations.count(KnownCheck::ObConnector);
+        let lane_id = prechecked.lane;
         // lane in active_lanes
         let active_lanes = self.current_ihw.as_ref().unwrap().active_lanes();
         if !is_lane_active(lane_id, active_lanes) {
@@ -315,7 +770,7 @@ impl<T: RDH> CdpRunningValidator<T> {
         }
 
         // lane in connector <= 6
-        let input_number_connector = ob_data_word_id_to_input_number_connector(ob_slice[9]);
+        let input_number_connector = prechecked.ob_input_connector;
         if input_number_connector > 6 {
             self.report_error(
                 &format!("[E73] OB Data Word has input connector {input_number_connector} > 6."),
@@ -333,6 +788,7 @@ impl<T: RDH> CdpRunningValidator<T> {
         log::debug!("{cdw}");
 
         if let Some(previous_cdw) = self.previous_cdw.as_ref() {
+            self.check_evaluations.count(KnownCheck::CdwIndex);
             if previous_cdw.calibration_user_fields() != cdw.calibration_user_fields()
                 && cdw.calibration_word_index() != 0
             {
@@ -351,12 +807,27 @@ impl<T: RDH> CdpRunningValidator<T> {
         if !self.config.running_checks {
             return;
         }
-        if self.current_tdh.as_ref().unwrap().internal_trigger() != 1 {
-            self.report_error("[E43] TDH internal trigger is not 1", tdh_slice);
-            let tmp_rdh = self.current_rdh.as_ref().unwrap();
-            log::debug!("{tmp_rdh}");
+        if self.trigger_source == TriggerSource::Any {
+            self.check_evaluations.count(KnownCheck::TdhInternalTrigger);
+            if self.current_tdh.as_ref().unwrap().internal_trigger() != 1 {
+                self.report_error("[E43] TDH internal trigger is not 1", tdh_slice);
+                let tmp_rdh = self.current_rdh.as_ref().unwrap();
+                log::debug!("{tmp_rdh}");
+            }
+        }
+        if self.trigger_source == TriggerSource::External {
+            self.check_evaluations.count(KnownCheck::TdhTrigger);
+            let current_tdh = self.current_tdh.as_ref().unwrap();
+            if current_tdh.trigger_orbit != self.current_rdh.as_ref().unwrap().rdh1().orbit {
+                self.report_error(
+                    "[E44] TDH trigger_orbit is not equal to RDH orbit",
+                    tdh_slice,
+                );
+            }
+            self.check_tdh_trigger_matches_rdh(tdh_slice);
         }
         if let Some(previous_tdh) = self.previous_tdh.as_ref() {
+            self.check_evaluations.count(KnownCheck::TdhTrigger);
             if previous_tdh.trigger_bc() > self.current_tdh.as_ref().unwrap().trigger_bc() {
                 self.report_error(
                     &format!(
@@ -370,12 +841,77 @@ impl<T: RDH> CdpRunningValidator<T> {
         }
     }
 
+    /// Checks that each packet is opened by exactly one TDH and closed by exactly one TDT
+    ///
+    /// TDHs and TDTs are identified by their ID rather than by the state machine,
+    /// as it can accept a duplicated TDH or TDT as another word when it resyncs.
+    #[inline]
+    fn check_tdh_tdt_pairing(&mut self, gbt_word: &[u8]) {
+        if !self.config.running_checks {
+            return;
+        }
+        match gbt_word[9] {
+            0xE8 => {
+                self.check_evaluations.count(KnownCheck::TdhTdtPairing);
+                let mem_pos = self.calc_current_word_mem_pos();
+                if let Some(open_tdh_mem_pos) = self.open_packet_tdh_mem_pos {
+                    self.report_error(
+                        &format!("[E45] TDH follows the TDH at {open_tdh_mem_pos:#X} without a TDT in between"),
+                        gbt_word,
+                    );
+                }
+                // A TDH with no data has no TDT closing it
+                self.open_packet_tdh_mem_pos =
+                    if crate::words::status_words::util::tdh_no_data(gbt_word) {
+                        None
+                    } else {
+                        Some(mem_pos)
+                    };
+                self.previous_tdt_mem_pos = None;
+            }
+            0xF0 => {
+                self.check_evaluations.count(KnownCheck::TdhTdtPairing);
+                if self.open_packet_tdh_mem_pos.is_none() {
+                    if let Some(previous_tdt_mem_pos) = self.previous_tdt_mem_pos {
+                        self.report_error(
+                            &format!("[E45] TDT follows the TDT at {previous_tdt_mem_pos:#X} without a TDH in between"),
+                            gbt_word,
+                        );
+                    }
+                }
+                self.open_packet_tdh_mem_pos = None;
+                self.previous_tdt_mem_pos = Some(self.calc_current_word_mem_pos());
+            }
+            _ => (),
+        }
+    }
+
+    /// Checks that an IHW is not in the middle of a packet, i.e. between a TDH and the TDT closing it.
+    ///
+    /// A misplaced IHW starts a new sequence, so the words following it are checked against it
+    /// instead of the state machine resyncing on them.
+    #[inline]
+    fn check_ihw_outside_packet(&mut self, gbt_word: &[u8]) {
+        if !self.config.running_checks || gbt_word[9] != 0xE0 {
+            return;
+        }
+        self.check_evaluations.count(KnownCheck::IhwOutsidePacket);
+        if let Some(open_tdh_mem_pos) = self.open_packet_tdh_mem_pos.take() {
+            self.report_error(
+                &format!("[E32] IHW in the middle of the packet opened by the TDH at {open_tdh_mem_pos:#X}, without a TDT in between"),
+                gbt_word,
+            );
+            self.its_state_machine.restore(PayloadFsmState::InitialIhw);
+        }
+    }
+
     /// Checks RDH stop_bit and pages_counter when a DDW0 is observed
     #[inline]
     fn check_rdh_at_ddw0(&mut self, ddw0_slice: &[u8]) {
         if !self.config.running_checks {
             return;
         }
+        self.check_evaluations.count(KnownCheck::RdhAtDdw0);
         if self.current_rdh.as_ref().unwrap().stop_bit() != 1 {
             self.report_error("[E11] DDW0 observed but RDH stop bit is not 1", ddw0_slice);
         }
@@ -389,6 +925,7 @@ impl<T: RDH> CdpRunningValidator<T> {
         if !self.config.running_checks {
             return;
         }
+        self.check_evaluations.count(KnownCheck::RdhAtIhw);
         if self.current_rdh.as_ref().unwrap().stop_bit() != 0 {
             self.report_error("[E12] IHW observed but RDH stop bit is not 0", ihw_slice);
         }
@@ -400,19 +937,32 @@ impl<T: RDH> CdpRunningValidator<T> {
         if !self.config.running_checks {
             return;
         }
+        self.check_evaluations.count(KnownCheck::TdhContinuation);
         if self.current_tdh.as_ref().unwrap().continuation() != 1 {
             self.report_error("[E41] TDH continuation is not 1", tdh_slice);
         }
 
         if let Some(previous_tdh) = self.previous_tdh.as_ref() {
+            self.check_evaluations.count(KnownCheck::TdhTrigger);
             if previous_tdh.trigger_bc() != self.current_tdh.as_ref().unwrap().trigger_bc() {
                 self.report_error("[E44] TDH trigger_bc is not the same", tdh_slice);
             }
             if previous_tdh.trigger_orbit != self.current_tdh.as_ref().unwrap().trigger_orbit {
                 self.report_error("[E44] TDH trigger_orbit is not the same", tdh_slice);
             }
-            if previous_tdh.trigger_type() != self.current_tdh.as_ref().unwrap().trigger_type() {
-                self.report_error("[E44] TDH trigger_type is not the same", tdh_slice);
+            let (previous_trigger_type, current_trigger_type) = (
+                previous_tdh.trigger_type() as u32,
+                self.current_tdh.as_ref().unwrap().trigger_type() as u32,
+            );
+            if previous_trigger_type != current_trigger_type {
+                self.report_error(
+                    &format!(
+                        "[E44] TDH trigger_type is not the same, previous: {previous_trigger_type:#X} ({}), current: {current_trigger_type:#X} ({})",
+                        TriggerType::names_of(previous_trigger_type),
+                        TriggerType::names_of(current_trigger_type)
+                    ),
+                    tdh_slice,
+                );
             }
         }
     }
@@ -423,6 +973,8 @@ impl<T: RDH> CdpRunningValidator<T> {
         if !self.config.running_checks {
             return;
         }
+        self.check_evaluations.count(KnownCheck::TdhNoContinuation);
+        self.check_evaluations.count(KnownCheck::TdhTrigger);
         let current_rdh = self.current_rdh.as_ref().expect("RDH should be set");
         let current_tdh = self
             .current_tdh
@@ -440,32 +992,129 @@ impl<T: RDH> CdpRunningValidator<T> {
             );
         }
 
-        if current_rdh.pages_counter() == 0
-            && (current_tdh.internal_trigger() == 1 || current_rdh.rdh2().is_pht_trigger())
+        if self.trigger_source == TriggerSource::External
+            || (current_rdh.pages_counter() == 0
+                && (current_tdh.internal_trigger() == 1 || current_rdh.rdh2().is_pht_trigger()))
         {
             // In this case the bc and trigger_type of the TDH and RDH should match
-            if current_rdh.rdh1().bc() != current_tdh.trigger_bc() {
+            self.check_tdh_trigger_matches_rdh(tdh_slice);
+        }
+    }
+
+    /// Checks the TDH internal_trigger bit against the configured [TriggerSource], for every TDH.
+    #[inline]
+    fn check_tdh_trigger_source(&mut self, tdh_slice: &[u8]) {
+        if !self.config.running_checks {
+            return;
+        }
+        let expected_internal_trigger = match self.trigger_source {
+            TriggerSource::Internal => 1,
+            TriggerSource::External => 0,
+            TriggerSource::Any => return,
+        };
+        self.check_evaluations.count(KnownCheck::TdhInternalTrigger);
+        let internal_trigger = self.current_tdh.as_ref().unwrap().internal_trigger();
+        if internal_trigger != expected_internal_trigger {
+            self.report_error(
+                &format!(
+                    "[E43] TDH internal_trigger is {internal_trigger}, expected {expected_internal_trigger} for --trigger-source {}",
+                    self.trigger_source.to_string().to_lowercase()
+                ),
+                tdh_slice,
+            );
+        }
+    }
+
+    /// Checks that the bc and trigger_type of the current TDH match the current RDH, as they should for the TDH of the trigger that opened the RDH.
+    #[inline]
+    fn check_tdh_trigger_matches_rdh(&self, tdh_slice: &[u8]) {
+        let current_rdh = self.current_rdh.as_ref().expect("RDH should be set");
+        let current_tdh = self.current_tdh.as_ref().expect("TDH should be set");
+        if current_rdh.rdh1().bc() != current_tdh.trigger_bc() {
+            self.report_error(
+                &format!(
+                    "[E44] TDH trigger_bc is not equal to RDH bc, TDH: {:#X}, RDH: {:#X}.",
+                    current_tdh.trigger_bc(),
+                    current_rdh.rdh1().bc()
+                ),
+                tdh_slice,
+            );
+        }
+        self.check_tdh_trigger_type_bits(
+            current_tdh.trigger_type(),
+            current_rdh.rdh2().trigger_type,
+            tdh_slice,
+        );
+    }
+
+    /// Compares the trigger bits of the TDH with the 12 LSB of the RDH trigger type, and reports each bit that differs with its own sub-code `E44xx` where `xx` is the bit.
+    ///
+    /// Differences in bits that are configured to be ignored are logged as warnings.
+    #[inline]
+    fn check_tdh_trigger_type_bits(
+        &self,
+        tdh_trigger_type: u16,
+        rdh_trigger_type: u32,
+        tdh_slice: &[u8],
+    ) {
+        let tdh_trigger_type = tdh_trigger_type as u32;
+        for trigger in TriggerType::TDH_BITS {
+            let (in_tdh, in_rdh) = (
+                trigger.is_set(tdh_trigger_type),
+                trigger.is_set(rdh_trigger_type),
+            );
+            if in_tdh == in_rdh {
+                continue;
+            }
+            let difference = if in_rdh {
+                format!("TDH missing {} bit present in RDH", trigger.name())
+            } else {
+                format!("TDH has {}, RDH does not", trigger.name())
+            };
+            let difference = format!(
+                "{difference}, TDH: {tdh_trigger_type:#X} ({}), RDH: {rdh_trigger_type:#X} ({})",
+                TriggerType::names_of(tdh_trigger_type),
+                TriggerType::names_of(rdh_trigger_type)
+            );
+            if self.ignore_tdh_trigger_bits.contains(&trigger) {
+                self.report_warning(format!(
+                    "{:#X}: {difference} (ignored trigger bit)",
+                    self.calc_current_word_mem_pos()
+                ));
+            } else {
                 self.report_error(
-                    &format!(
-                        "[E44] TDH trigger_bc is not equal to RDH bc, TDH: {:#X}, RDH: {:#X}.",
-                        current_tdh.trigger_bc(),
-                        current_rdh.rdh1().bc()
-                    ),
+                    &format!("[E44{:02}] {difference}", trigger.bit()),
                     tdh_slice,
                 );
             }
-            // TDH only has the 12 LSB of the trigger type
-            if current_rdh.rdh2().trigger_type as u16 & 0xFFF != current_tdh.trigger_type() {
-                let tmp_rdh_trig = current_rdh.rdh2().trigger_type as u16;
-                self.report_error(
-                        &format!("[E44] TDH trigger_type is not equal to RDH trigger_type, TDH: {:#X}, RDH: {tmp_rdh_trig:#X}", current_tdh.trigger_type()),
-                        tdh_slice,
-                    );
-            }
         }
     }
 }
 
+/// Formats the link, layer and stave, orbit and pages counter of an [RDH], appended to payload error messages with `--verbose-errors`.
+fn rdh_error_context<T: RDH>(rdh: &T) -> String {
+    let layer = crate::words::lib::layer_from_feeid(rdh.fee_id());
+    let stave = crate::words::lib::stave_number_from_feeid(rdh.fee_id());
+    let orbit = rdh.rdh1().orbit;
+    format!(
+        "(link {}, L{layer}_{stave:02}, orbit {orbit:#010X}, page {})",
+        rdh.link_id(),
+        rdh.pages_counter()
+    )
+}
+
+/// Formats the bytes of a GBT word for an error message, the missing bytes of a short word are shown as `--`.
+fn format_gbt_word(word_slice: &[u8]) -> String {
+    let mut bytes: Vec<String> = word_slice
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect();
+    if bytes.len() < GBT_WORD_SIZE {
+        bytes.resize(GBT_WORD_SIZE, "--".to_string());
+    }
+    bytes.join(" ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -484,7 +1133,7 @@ mod tests {
 
         let (send, stats_recv_ch) = std::sync::mpsc::channel();
         let mut validator = CdpRunningValidator::<RdhCRU<V7>>::default();
-        validator.stats_send_ch = send;
+        validator.error_sink = ErrorSink::Channel(send);
         let rdh_mem_pos = 0;
 
         validator.set_current_rdh(&CORRECT_RDH_CRU_V7, rdh_mem_pos);
@@ -503,7 +1152,7 @@ mod tests {
 
         let (send, stats_recv_ch) = std::sync::mpsc::channel();
         let mut validator = CdpRunningValidator::<RdhCRU<V7>>::default();
-        validator.stats_send_ch = send;
+        validator.error_sink = ErrorSink::Channel(send);
         let rdh_mem_pos = 0x0;
 
         validator.set_current_rdh(&CORRECT_RDH_CRU_V7, rdh_mem_pos);
@@ -521,6 +1170,72 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_short_words_reported_without_panic() {
+        let (send, stats_recv_ch) = std::sync::mpsc::channel();
+        let mut validator = CdpRunningValidator::<RdhCRU<V7>>::default();
+        validator.error_sink = ErrorSink::Channel(send);
+        validator.set_current_rdh(&CORRECT_RDH_CRU_V7, 0x0);
+        validator.check(&[0xFF, 0x3F, 0x00]);
+        validator.check(&[]);
+        let errors: Vec<String> = stats_recv_ch
+            .try_iter()
+            .map(|stat| match stat {
+                StatType::Error(msg) => msg,
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "0x40: [E70] GBT word is 3 bytes, expected 10 [FF 3F 00 -- -- -- -- -- -- --]",
+                "0x4A: [E70] GBT word is 0 bytes, expected 10 [-- -- -- -- -- -- -- -- -- --]",
+            ]
+        );
+        // The state of the payload is unaffected, the next word is still expected to be an IHW
+        validator.check(&[0xFF, 0x3F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE0]);
+        assert!(stats_recv_ch.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_invalidate_ihw_verbose_errors() {
+        let raw_data_ihw = [0xFF, 0x3F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE1];
+        let config: crate::util::config::Opt =
+            <crate::util::config::Opt as structopt::StructOpt>::from_iter(&[
+                "fastpasta",
+                "--verbose-errors",
+                "check",
+                "sanity",
+                "its",
+            ]);
+        let (send, stats_recv_ch) = std::sync::mpsc::channel();
+        let mut validator = CdpRunningValidator::<RdhCRU<V7>>::new(&config, send);
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        rdh.link_id = 3;
+        rdh.rdh2.pages_counter = 2;
+        validator.set_current_rdh(&rdh, 0x100);
+        validator.check(&raw_data_ihw);
+
+        match stats_recv_ch.recv() {
+            Ok(StatType::Error(msg)) => assert_eq!(
+                msg,
+                "0x140: [E30] ID is not 0xE0: 0xE1  [FF 3F 00 00 00 00 00 00 00 E1] (link 3, L5_42, orbit 0x0B7DD575, page 2)"
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_rdh_error_context() {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        rdh.rdh0.fee_id = crate::words::rdh::FeeId(0x3005);
+        rdh.rdh1.orbit = 0x1;
+        assert_eq!(
+            rdh_error_context(&rdh),
+            "(link 0, L3_05, orbit 0x00000001, page 0)"
+        );
+    }
+
     #[test]
     fn test_expect_ihw_invalidate_tdh() {
         const _VALID_ID: u8 = 0xF0;
@@ -529,7 +1244,7 @@ mod tests {
 
         let (send, stats_recv_ch) = std::sync::mpsc::channel();
         let mut validator = CdpRunningValidator::<RdhCRU<V7>>::default();
-        validator.stats_send_ch = send;
+        validator.error_sink = ErrorSink::Channel(send);
         let rdh_mem_pos = 0x0; // RDH size is 64 bytes
 
         validator.set_current_rdh(&CORRECT_RDH_CRU_V7, rdh_mem_pos); // Data format is 2
@@ -556,7 +1271,7 @@ mod tests {
 
         let (send, stats_recv_ch) = std::sync::mpsc::channel();
         let mut validator = CdpRunningValidator::<RdhCRU<V7>>::default();
-        validator.stats_send_ch = send;
+        validator.error_sink = ErrorSink::Channel(send);
         let rdh_mem_pos = 0x0; // RDH size is 64 bytes
 
         validator.set_current_rdh(&CORRECT_RDH_CRU_V7, rdh_mem_pos); // Data format is 2
@@ -601,7 +1316,7 @@ mod tests {
             .returning(|| Option::Some(crate::util::config::Check::All(Target { system: None })));
         let mut validator = CdpRunningValidator::<RdhCRU<V7>>::default();
         validator.set_config(&mock_cfg);
-        validator.stats_send_ch = send;
+        validator.error_sink = ErrorSink::Channel(send);
         let rdh_mem_pos = 0x0; // RDH size is 64 bytes
 
         validator.set_current_rdh(&CORRECT_RDH_CRU_V7, rdh_mem_pos); // Data format is 2
@@ -651,4 +1366,474 @@ mod tests {
             _ => unreachable!(),
         }
     }
+
+    // Checks an IHW and a TDH with the given trigger type, following CORRECT_RDH_CRU_V7, and returns the errors without the word bytes
+    fn tdh_trigger_type_errors(
+        tdh_trigger_type: u16,
+        ignore_tdh_trigger_bits: Vec<TriggerType>,
+    ) -> Vec<String> {
+        let mut validator = CdpRunningValidator::<RdhCRU<V7>>::default();
+        validator.config.running_checks = true;
+        validator.error_sink = ErrorSink::Collect(std::cell::RefCell::new(Vec::new()));
+        validator.ignore_tdh_trigger_bits = ignore_tdh_trigger_bits;
+        let mut tdh = TDH;
+        // Internal trigger is set, so the TDH trigger type is compared to the RDH
+        tdh[0] = tdh_trigger_type as u8;
+        tdh[1] = 0x10 | (tdh_trigger_type >> 8) as u8;
+
+        validator.set_current_rdh(&CORRECT_RDH_CRU_V7, 0);
+        validator.check(&IHW);
+        validator.check(&tdh);
+        validator
+            .take_errors()
+            .into_iter()
+            .map(|e| e.rsplit_once(" [").unwrap().0.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_tdh_trigger_type_single_bit_differences() {
+        assert_eq!({ CORRECT_RDH_CRU_V7.rdh2().trigger_type }, 0x6A03);
+        let expected_errors = [
+            (0xA03, vec![]),
+            (
+                0xA01,
+                vec!["0x4A: [E4401] TDH missing HB bit present in RDH, TDH: 0xA01 (ORBIT|SOC|TF), RDH: 0x6A03 (ORBIT|HB|SOC|TF)"],
+            ),
+            (
+                0xA13,
+                vec!["0x4A: [E4404] TDH has PhT, RDH does not, TDH: 0xA13 (ORBIT|HB|PhT|SOC|TF), RDH: 0x6A03 (ORBIT|HB|SOC|TF)"],
+            ),
+            (
+                0x803,
+                vec!["0x4A: [E4409] TDH missing SOC bit present in RDH, TDH: 0x803 (ORBIT|HB|TF), RDH: 0x6A03 (ORBIT|HB|SOC|TF)"],
+            ),
+            (
+                0xA0B,
+                vec!["0x4A: [E4403] TDH has HC, RDH does not, TDH: 0xA0B (ORBIT|HB|HC|SOC|TF), RDH: 0x6A03 (ORBIT|HB|SOC|TF)"],
+            ),
+            // Each differing bit is reported separately
+            (
+                0x211,
+                vec![
+                    "0x4A: [E4401] TDH missing HB bit present in RDH, TDH: 0x211 (ORBIT|PhT|SOC), RDH: 0x6A03 (ORBIT|HB|SOC|TF)",
+                    "0x4A: [E4404] TDH has PhT, RDH does not, TDH: 0x211 (ORBIT|PhT|SOC), RDH: 0x6A03 (ORBIT|HB|SOC|TF)",
+                    "0x4A: [E4411] TDH missing TF bit present in RDH, TDH: 0x211 (ORBIT|PhT|SOC), RDH: 0x6A03 (ORBIT|HB|SOC|TF)",
+                ],
+            ),
+        ];
+        for (tdh_trigger_type, expected) in expected_errors {
+            assert_eq!(tdh_trigger_type_errors(tdh_trigger_type, vec![]), expected);
+        }
+    }
+
+    #[test]
+    fn test_tdh_trigger_type_ignored_bit_not_an_error() {
+        // Only the HBr bit differs
+        assert!(tdh_trigger_type_errors(0xA07, vec![TriggerType::HbReject]).is_empty());
+        // The HBr bit is ignored, but the PhT bit is not
+        assert_eq!(
+            tdh_trigger_type_errors(0xA17, vec![TriggerType::HbReject]),
+            vec!["0x4A: [E4404] TDH has PhT, RDH does not, TDH: 0xA17 (ORBIT|HB|HBr|PhT|SOC|TF), RDH: 0x6A03 (ORBIT|HB|SOC|TF)"]
+        );
+    }
+
+    // Checks an IHW, a TDH, a TDT with packet_done and a TDH opening the next packet with the given bc, following CORRECT_RDH_CRU_V7, and returns the errors without the word bytes
+    fn trigger_source_errors(
+        trigger_source: TriggerSource,
+        internal_trigger: bool,
+        next_tdh_bc: u8,
+    ) -> Vec<String> {
+        let mut validator = CdpRunningValidator::<RdhCRU<V7>>::default();
+        validator.config.running_checks = true;
+        validator.error_sink = ErrorSink::Collect(std::cell::RefCell::new(Vec::new()));
+        validator.trigger_source = trigger_source;
+        let mut tdh = TDH;
+        if !internal_trigger {
+            tdh[1] &= !0x10;
+        }
+        let mut next_tdh = tdh;
+        next_tdh[2] = next_tdh_bc;
+        let tdt_packet_done = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xF0];
+
+        validator.set_current_rdh(&CORRECT_RDH_CRU_V7, 0);
+        validator.check(&IHW);
+        validator.check(&tdh);
+        validator.check(&tdt_packet_done);
+        validator.check(&next_tdh);
+        validator
+            .take_errors()
+            .into_iter()
+            .map(|e| e.rsplit_once(" [").unwrap().0.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_trigger_source_internal() {
+        assert!(trigger_source_errors(TriggerSource::Internal, true, 0).is_empty());
+        assert_eq!(
+            trigger_source_errors(TriggerSource::Internal, false, 0),
+            vec![
+                "0x4A: [E43] TDH internal_trigger is 0, expected 1 for --trigger-source internal",
+                "0x5E: [E43] TDH internal_trigger is 0, expected 1 for --trigger-source internal"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trigger_source_external() {
+        assert!(trigger_source_errors(TriggerSource::External, false, 0).is_empty());
+        assert_eq!(
+            trigger_source_errors(TriggerSource::External, true, 0),
+            vec![
+                "0x4A: [E43] TDH internal_trigger is 1, expected 0 for --trigger-source external",
+                "0x5E: [E43] TDH internal_trigger is 1, expected 0 for --trigger-source external"
+            ]
+        );
+        // The TDH opening the next packet is also compared to the RDH
+        assert_eq!(
+            trigger_source_errors(TriggerSource::External, false, 0x10),
+            vec!["0x5E: [E44] TDH trigger_bc is not equal to RDH bc, TDH: 0x10, RDH: 0x0."]
+        );
+    }
+
+    #[test]
+    fn test_trigger_source_any() {
+        assert!(trigger_source_errors(TriggerSource::Any, true, 0x10).is_empty());
+        assert_eq!(
+            trigger_source_errors(TriggerSource::Any, false, 0),
+            vec!["0x5E: [E43] TDH internal trigger is not 1"]
+        );
+    }
+
+    fn tdh_tdt_pairing_errors(words: &[[u8; 10]]) -> Vec<String> {
+        let mut validator = CdpRunningValidator::<RdhCRU<V7>>::default();
+        validator.config.running_checks = true;
+        validator.error_sink = ErrorSink::Collect(std::cell::RefCell::new(Vec::new()));
+
+        validator.set_current_rdh(&CORRECT_RDH_CRU_V7, 0);
+        words.iter().for_each(|word| validator.check(word));
+        validator
+            .take_errors()
+            .into_iter()
+            .filter(|e| e.contains("[E45]"))
+            .map(|e| e.rsplit_once(" [").unwrap().0.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_tdh_tdt_pairing_duplicated_tdt() {
+        let tdt_packet_done = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xF0];
+        assert!(
+            tdh_tdt_pairing_errors(&[IHW, TDH, tdt_packet_done, TDH, tdt_packet_done]).is_empty()
+        );
+        assert_eq!(
+            tdh_tdt_pairing_errors(&[IHW, TDH, tdt_packet_done, tdt_packet_done, TDH]),
+            vec!["0x5E: [E45] TDT follows the TDT at 0x54 without a TDH in between"]
+        );
+    }
+
+    #[test]
+    fn test_tdh_tdt_pairing_tdh_without_tdt() {
+        let tdt_packet_done = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xF0];
+        assert_eq!(
+            tdh_tdt_pairing_errors(&[IHW, TDH, TDH, tdt_packet_done]),
+            vec!["0x54: [E45] TDH follows the TDH at 0x4A without a TDT in between"]
+        );
+    }
+
+    fn payload_end_errors(words: &[[u8; 10]]) -> Vec<String> {
+        let mut validator = CdpRunningValidator::<RdhCRU<V7>>::default();
+        validator.config.running_checks = true;
+        validator.error_sink = ErrorSink::Collect(std::cell::RefCell::new(Vec::new()));
+
+        validator.set_current_rdh(&CORRECT_RDH_CRU_V7, 0);
+        words.iter().for_each(|word| validator.check(word));
+        validator.check_payload_end();
+        validator
+            .take_errors()
+            .into_iter()
+            .filter(|e| e.contains("[E51]"))
+            .collect()
+    }
+
+    #[test]
+    fn test_payload_ends_after_tdh() {
+        assert_eq!(
+            payload_end_errors(&[IHW, TDH]),
+            vec!["0x4A: [E51] Payload ended in the middle of an open packet, expecting data after TDH [03 1A 00 00 75 D5 7D 0B 00 E8]"]
+        );
+        assert_eq!(
+            payload_end_errors(&[IHW, TDH, IB_DATA_WORD]),
+            vec!["0x54: [E51] Payload ended in the middle of an open packet, expecting data after data [00 00 00 00 00 00 00 00 00 20]"]
+        );
+    }
+
+    #[test]
+    fn test_payload_ends_after_tdt() {
+        let tdt_packet_done = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xF0];
+        let tdt_packet_not_done = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF0];
+        assert!(payload_end_errors(&[IHW, TDH, IB_DATA_WORD, tdt_packet_done]).is_empty());
+        assert!(payload_end_errors(&[IHW, TDH, IB_DATA_WORD, tdt_packet_not_done]).is_empty());
+        assert!(payload_end_errors(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_ihw_in_the_middle_of_packet() {
+        let tdt_packet_done = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xF0];
+        let mut validator = CdpRunningValidator::<RdhCRU<V7>>::default();
+        validator.config.running_checks = true;
+        validator.error_sink = ErrorSink::Collect(std::cell::RefCell::new(Vec::new()));
+
+        validator.set_current_rdh(&CORRECT_RDH_CRU_V7, 0);
+        [IHW, TDH, IB_DATA_WORD, IHW]
+            .iter()
+            .for_each(|word| validator.check(word));
+        assert_eq!(
+            validator.take_errors(),
+            vec!["0x5E: [E32] IHW in the middle of the packet opened by the TDH at 0x4A, without a TDT in between [FF 3F 00 00 00 00 00 00 00 E0]"]
+        );
+        // The following words are checked against the misplaced IHW
+        [TDH, IB_DATA_WORD, tdt_packet_done]
+            .iter()
+            .for_each(|word| validator.check(word));
+        assert!(validator.take_errors().is_empty());
+        assert_eq!(
+            validator.its_state_machine.state(),
+            PayloadFsmState::Ddw0OrTdhOrIhwAfterPacketDone
+        );
+    }
+
+    // IHW, TDH and TDH trigger/orbit matching CORRECT_RDH_CRU_V7
+    const IHW: [u8; 10] = [0xFF, 0x3F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE0];
+    const TDH: [u8; 10] = [0x03, 0x1A, 0x00, 0x00, 0x75, 0xD5, 0x7D, 0x0B, 0x00, 0xE8];
+    const CDW: [u8; 10] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF8];
+    const IB_DATA_WORD: [u8; 10] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20];
+
+    #[test]
+    fn test_max_bc_from_config() {
+        // TDH with trigger_bc 0xDEC, one above the last bunch crossing of an orbit
+        let mut tdh = TDH;
+        tdh[2] = 0xEC;
+        tdh[3] = 0x0D;
+        let tdh_bc_errors = |args: &[&str]| -> Vec<String> {
+            let config: crate::util::config::Opt =
+                <crate::util::config::Opt as structopt::StructOpt>::from_iter(args);
+            let (send, stats_recv_ch) = std::sync::mpsc::channel();
+            let mut validator = CdpRunningValidator::<RdhCRU<V7>>::new(&config, send);
+            validator.set_current_rdh(&CORRECT_RDH_CRU_V7, 0);
+            validator.check(&IHW);
+            validator.check(&tdh);
+            stats_recv_ch
+                .try_iter()
+                .filter_map(|stat| match stat {
+                    StatType::Error(msg) if msg.contains("trigger_bc above") => Some(msg),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(
+            tdh_bc_errors(&["fastpasta", "check", "sanity", "its"]),
+            vec!["0x4A: [E40] trigger_bc above 0xDEB: 0xDEC  [03 1A EC 0D 75 D5 7D 0B 00 E8]"]
+        );
+        assert!(
+            tdh_bc_errors(&["fastpasta", "--max-bc", "0xFFF", "check", "sanity", "its"]).is_empty()
+        );
+    }
+
+    #[test]
+    fn test_validate_leading_double_cdw() {
+        let (send, stats_recv_ch) = std::sync::mpsc::channel();
+        let mut validator = CdpRunningValidator::<RdhCRU<V7>>::default();
+        validator.error_sink = ErrorSink::Channel(send);
+
+        validator.set_current_rdh(&CORRECT_RDH_CRU_V7, 0);
+        validator.check(&IHW);
+        validator.check(&TDH);
+        validator.check(&CDW);
+        validator.check(&CDW);
+        validator.check(&IB_DATA_WORD);
+
+        let errors: Vec<String> = stats_recv_ch
+            .try_iter()
+            .filter_map(|stat| match stat {
+                StatType::Error(msg) => Some(msg),
+                _ => None,
+            })
+            .collect();
+        assert!(
+            errors
+                .iter()
+                .all(|msg| !msg.contains("[E70]") && !msg.contains("[E82]")),
+            "{errors:?}"
+        );
+        // The position of every CDW is checked, also when it is valid
+        assert_eq!(
+            validator
+                .take_check_evaluations()
+                .get(KnownCheck::CdwPosition),
+            2
+        );
+    }
+
+    #[test]
+    fn test_word_counts() {
+        let mut validator = CdpRunningValidator::<RdhCRU<V7>>::default();
+        let tdt_packet_done = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xF0];
+        validator.set_current_rdh(&CORRECT_RDH_CRU_V7, 0);
+        for word in [IHW, TDH, CDW, IB_DATA_WORD, IB_DATA_WORD, tdt_packet_done] {
+            validator.check(&word);
+        }
+        assert_eq!(
+            validator.take_word_counts(),
+            WordCounts {
+                ihw: 1,
+                tdh: 1,
+                tdt: 1,
+                ddw0: 0,
+                cdw: 1,
+                data: 2,
+            }
+        );
+        assert_eq!(validator.take_word_counts(), WordCounts::default());
+    }
+
+    #[test]
+    fn test_count_tdt_timeouts() {
+        use crate::words::status_words::TdtTimeout;
+        let (send, stats_recv_ch) = std::sync::mpsc::channel();
+        let mut validator = CdpRunningValidator::<RdhCRU<V7>>::default();
+        validator.error_sink = ErrorSink::Channel(send);
+        let fee_id = CORRECT_RDH_CRU_V7.fee_id();
+        let expected_layer = crate::words::lib::layer_from_feeid(fee_id);
+        let expected_stave = crate::words::lib::stave_number_from_feeid(fee_id);
+
+        let tdt_timeouts = [
+            (0x80, TdtTimeout::ToStart),
+            (0x40, TdtTimeout::StartStop),
+            (0x20, TdtTimeout::InIdle),
+        ];
+        for (timeout_byte, expected_timeout) in tdt_timeouts {
+            let tdt = [0, 0, 0, 0, 0, 0, 0, timeout_byte, 0x01, 0xF0];
+            validator.set_current_rdh(&CORRECT_RDH_CRU_V7, 0);
+            validator.check(&IHW);
+            validator.check(&TDH);
+            validator.check(&tdt);
+            let timeouts: Vec<(u8, u8, TdtTimeout)> = stats_recv_ch
+                .try_iter()
+                .filter_map(|stat| match stat {
+                    StatType::TdtTimeout {
+                        layer,
+                        stave,
+                        timeout,
+                    } => Some((layer, stave, timeout)),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(
+                timeouts,
+                vec![(expected_layer, expected_stave, expected_timeout)]
+            );
+        }
+    }
+
+    #[test]
+    fn test_ddw0_index_error_and_transmission_timeout_counted() {
+        let (send, stats_recv_ch) = std::sync::mpsc::channel();
+        let mut validator = CdpRunningValidator::<RdhCRU<V7>>::default();
+        validator.error_sink = ErrorSink::Channel(send);
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        rdh.link_id = 3;
+        rdh.rdh2.pages_counter = 1;
+        rdh.rdh2.stop_bit = 1;
+        let tdt_packet_done = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xF0];
+        // Index 1 and transmission_timeout set
+        let ddw0 = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x12, 0xE4];
+        validator.set_current_rdh(&rdh, 0);
+        for word in [IHW, TDH, tdt_packet_done, ddw0] {
+            validator.check(&word);
+        }
+        let mut errors = Vec::new();
+        let mut timeout_links = Vec::new();
+        for stat in stats_recv_ch.try_iter() {
+            match stat {
+                StatType::Error(msg) => errors.push(msg),
+                StatType::Ddw0TransmissionTimeout(link_id) => timeout_links.push(link_id),
+                _ => (),
+            }
+        }
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert!(errors[0].contains("[E60]"), "{errors:?}");
+        assert!(errors[0].contains("index is not 0"), "{errors:?}");
+        assert_eq!(timeout_links, vec![3]);
+    }
+
+    fn ihw_errors_on_layer(layer: u8, ihw: &[u8]) -> Vec<String> {
+        let (send, stats_recv_ch) = std::sync::mpsc::channel();
+        let mut validator = CdpRunningValidator::<RdhCRU<V7>>::default();
+        validator.error_sink = ErrorSink::Channel(send);
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        rdh.rdh0.fee_id = crate::words::rdh::FeeId((layer as u16) << 12);
+        validator.set_current_rdh(&rdh, 0);
+        validator.check(ihw);
+        stats_recv_ch
+            .try_iter()
+            .filter_map(|stat| match stat {
+                StatType::Error(msg) => Some(msg),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // IHW with lane 0 and lane 20 active
+    const IHW_LANE_0_AND_20: [u8; 10] =
+        [0x01, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE0];
+
+    #[test]
+    fn test_ihw_active_lanes_above_layer_lane_count() {
+        let errors = ihw_errors_on_layer(0, &IHW_LANE_0_AND_20);
+        assert_eq!(
+            errors,
+            vec!["0x40: [E31] IHW active_lanes 0x100001 has bits set above the 9 lanes of layer 0, illegal bits: 20. [01 00 10 00 00 00 00 00 00 E0]"]
+        );
+        // Middle barrel has 16 lanes
+        assert_eq!(ihw_errors_on_layer(3, &IHW_LANE_0_AND_20).len(), 1);
+    }
+
+    #[test]
+    fn test_ihw_active_lanes_within_layer_lane_count() {
+        assert!(ihw_errors_on_layer(6, &IHW_LANE_0_AND_20).is_empty());
+        // All 9 lanes of an inner barrel stave
+        let ihw_9_lanes = [0xFF, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE0];
+        assert!(ihw_errors_on_layer(0, &ihw_9_lanes).is_empty());
+    }
+
+    #[test]
+    fn test_invalidate_cdw_after_data_word() {
+        let (send, stats_recv_ch) = std::sync::mpsc::channel();
+        let mut validator = CdpRunningValidator::<RdhCRU<V7>>::default();
+        validator.error_sink = ErrorSink::Channel(send);
+
+        validator.set_current_rdh(&CORRECT_RDH_CRU_V7, 0);
+        validator.check(&IHW);
+        validator.check(&TDH);
+        validator.check(&IB_DATA_WORD);
+        validator.check(&CDW);
+
+        let errors: Vec<String> = stats_recv_ch
+            .try_iter()
+            .filter_map(|stat| match stat {
+                StatType::Error(msg) => Some(msg),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            errors.last().unwrap(),
+            "0x5E: [E82] CDW at illegal position [00 00 00 00 00 00 00 00 00 F8]"
+        );
+        assert!(
+            errors.iter().all(|msg| !msg.contains("[E70]")),
+            "{errors:?}"
+        );
+    }
 }
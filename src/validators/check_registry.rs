@@ -0,0 +1,463 @@
+//! Contains the registry of [KnownCheck]s with their error codes and requirements, and the [CheckEvaluations] counting how many times each check was evaluated.
+//!
+//! Validators count the evaluations locally and send them to the [StatsController][crate::stats::stats_controller::StatsController] when they finish,
+//! which summarizes which checks ran, and which were skipped and why, in the report.
+use crate::util::config::{Check, System};
+use crate::util::lib::{Checks, Util};
+
+/// What it takes for a [KnownCheck] to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckRequirement {
+    /// Runs on all input, regardless of the checks enabled.
+    Always,
+    /// Runs with `check sanity` or `check all`.
+    AnyCheck,
+    /// Runs with `check all`.
+    RunningChecks,
+    /// Runs with `check all` if HBFs are expected once per orbit.
+    HbfPerOrbit,
+    /// Runs with `check sanity its` or `check all its`.
+    ItsTarget,
+    /// Runs with `check all its`.
+    ItsRunningChecks,
+    /// Runs with any check if a hardware map is given.
+    HwMap,
+    /// Runs with any check if the HBFs per timeframe are given.
+    HbfPerTf,
+    /// Runs with any check if a data format is expected.
+    ExpectDataFormat,
+}
+
+impl CheckRequirement {
+    /// Returns why a check with this requirement is skipped with the `config`, or [None] if it runs.
+    pub fn skip_reason(&self, config: &(impl Checks + Util)) -> Option<&'static str> {
+        let check = match config.check() {
+            Some(check) => check,
+            None if *self == CheckRequirement::Always => return None,
+            None => return Some("no checks enabled"),
+        };
+        let running = matches!(check, Check::All(_));
+        let its = matches!(check.target(), Some(System::ITS));
+        match self {
+            CheckRequirement::Always | CheckRequirement::AnyCheck => None,
+            CheckRequirement::RunningChecks if !running => Some("requires `check all`"),
+            CheckRequirement::HbfPerOrbit if !running => Some("requires `check all`"),
+            CheckRequirement::HbfPerOrbit if !config.expect_hbf_per_orbit() => {
+                Some("disabled, enable with `--expect-hbf-per-orbit`")
+            }
+            CheckRequirement::ItsTarget if !its => Some("requires the ITS target"),
+            CheckRequirement::ItsRunningChecks if !its || !running => {
+                Some("requires `check all its`")
+            }
+            CheckRequirement::HwMap if config.hw_map().is_none() => Some("requires `--hw-map`"),
+            CheckRequirement::HbfPerTf if config.hbf_per_tf().is_none() => {
+                Some("requires `--hbf-per-tf`")
+            }
+            CheckRequirement::ExpectDataFormat if config.expect_data_format().is_none() => {
+                Some("requires `--expect-data-format`")
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The checks known to fastPASTA, in the order they are listed in the report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KnownCheck {
+    /// RDH version is the same as the first RDH, done by the input scanner.
+    RdhVersion,
+    /// RDH sanity.
+    RdhSanity,
+    /// RDH data format is defined.
+    RdhDataFormat,
+    /// RDH data format is the expected data format.
+    RdhExpectedDataFormat,
+    /// Payload of an RDH declaring data format 0 is padded.
+    PayloadPadding,
+    /// RDH fields across pages.
+    RdhRunning,
+    /// SOC/SOT trigger only on pages opening an HBF.
+    SocSotAtHbfStart,
+    /// Orbit increments by 1 between HBFs.
+    OrbitPerHbf,
+    /// HBFs per timeframe, done by the analysis thread.
+    HbfPerTf,
+    /// RDH trigger type same on all links of a CRU at an orbit, done by the analysis thread.
+    TriggerAcrossLinks,
+    /// RDH FEE ID, CRU ID, link ID and endpoint match the hardware map, done by the analysis thread.
+    HwMap,
+    /// RDH stop bit and pages counter when a DDW0 is observed.
+    RdhAtDdw0,
+    /// RDH stop bit when an initial IHW is observed.
+    RdhAtIhw,
+    /// IHW sanity.
+    IhwSanity,
+    /// IHW active lanes within the lanes of the layer.
+    IhwActiveLanes,
+    /// IHW outside of an open packet.
+    IhwOutsidePacket,
+    /// TDH sanity.
+    TdhSanity,
+    /// TDH continuation when the previous TDT packet is not done.
+    TdhContinuation,
+    /// TDH without continuation following an IHW.
+    TdhNoContinuation,
+    /// TDH internal trigger when the previous TDT packet is done.
+    TdhInternalTrigger,
+    /// TDH trigger consistent with the previous TDH and the RDH.
+    TdhTrigger,
+    /// Exactly one TDH opening and one TDT closing each packet.
+    TdhTdtPairing,
+    /// TDT sanity.
+    TdtSanity,
+    /// Payload ends after a TDT, a DDW0 or at a continuation boundary.
+    PayloadEnd,
+    /// DDW0 sanity.
+    Ddw0Sanity,
+    /// Data word sanity.
+    DataWordSanity,
+    /// OB data word lane is active.
+    ObLaneActive,
+    /// IB data word lane is active.
+    IbLaneActive,
+    /// OB data word input connector.
+    ObConnector,
+    /// CDW index.
+    CdwIndex,
+    /// CDW position.
+    CdwPosition,
+}
+
+impl KnownCheck {
+    /// All the known checks.
+    pub const ALL: [KnownCheck; 31] = [
+        KnownCheck::RdhVersion,
+        KnownCheck::RdhSanity,
+        KnownCheck::RdhDataFormat,
+        KnownCheck::RdhExpectedDataFormat,
+        KnownCheck::PayloadPadding,
+        KnownCheck::RdhRunning,
+        KnownCheck::SocSotAtHbfStart,
+        KnownCheck::OrbitPerHbf,
+        KnownCheck::HbfPerTf,
+        KnownCheck::TriggerAcrossLinks,
+        KnownCheck::HwMap,
+        KnownCheck::RdhAtDdw0,
+        KnownCheck::RdhAtIhw,
+        KnownCheck::IhwSanity,
+        KnownCheck::IhwActiveLanes,
+        KnownCheck::IhwOutsidePacket,
+        KnownCheck::TdhSanity,
+        KnownCheck::TdhContinuation,
+        KnownCheck::TdhNoContinuation,
+        KnownCheck::TdhInternalTrigger,
+        KnownCheck::TdhTrigger,
+        KnownCheck::TdhTdtPairing,
+        KnownCheck::TdtSanity,
+        KnownCheck::PayloadEnd,
+        KnownCheck::Ddw0Sanity,
+        KnownCheck::DataWordSanity,
+        KnownCheck::ObLaneActive,
+        KnownCheck::IbLaneActive,
+        KnownCheck::ObConnector,
+        KnownCheck::CdwIndex,
+        KnownCheck::CdwPosition,
+    ];
+
+    /// The error code of the errors reported by the check, `-` if they have none.
+    pub fn code(&self) -> &'static str {
+        match self {
+            KnownCheck::RdhVersion => "E15",
+            KnownCheck::RdhSanity => "E10",
+            KnownCheck::RdhDataFormat => "E16",
+            KnownCheck::RdhExpectedDataFormat => "E21",
+            KnownCheck::PayloadPadding => "E20",
+            KnownCheck::RdhRunning => "-",
+            KnownCheck::SocSotAtHbfStart => "E13",
+            KnownCheck::OrbitPerHbf => "E14",
+            KnownCheck::HbfPerTf => "E17",
+            KnownCheck::TriggerAcrossLinks => "E18",
+            KnownCheck::HwMap => "E19",
+            KnownCheck::RdhAtDdw0 => "E11",
+            KnownCheck::RdhAtIhw => "E12",
+            KnownCheck::IhwSanity => "E30",
+            KnownCheck::IhwActiveLanes => "E31",
+            KnownCheck::IhwOutsidePacket => "E32",
+            KnownCheck::TdhSanity => "E40",
+            KnownCheck::TdhContinuation => "E41",
+            KnownCheck::TdhNoContinuation => "E42",
+            KnownCheck::TdhInternalTrigger => "E43",
+            KnownCheck::TdhTrigger => "E44",
+            KnownCheck::TdhTdtPairing => "E45",
+            KnownCheck::TdtSanity => "E50",
+            KnownCheck::PayloadEnd => "E51",
+            KnownCheck::Ddw0Sanity => "E60",
+            KnownCheck::DataWordSanity => "E70",
+            KnownCheck::ObLaneActive => "E71",
+            KnownCheck::IbLaneActive => "E72",
+            KnownCheck::ObConnector => "E73",
+            KnownCheck::CdwIndex => "E81",
+            KnownCheck::CdwPosition => "E82",
+        }
+    }
+
+    /// Short description of the check.
+    pub fn description(&self) -> &'static str {
+        match self {
+            KnownCheck::RdhVersion => "RDH version same as the first RDH",
+            KnownCheck::RdhSanity => "RDH sanity",
+            KnownCheck::RdhDataFormat => "RDH data format defined",
+            KnownCheck::RdhExpectedDataFormat => "RDH data format as expected",
+            KnownCheck::PayloadPadding => "Payload padded as data format 0",
+            KnownCheck::RdhRunning => "RDH fields across pages",
+            KnownCheck::SocSotAtHbfStart => "SOC/SOT only opening an HBF",
+            KnownCheck::OrbitPerHbf => "Orbit +1 between HBFs",
+            KnownCheck::HbfPerTf => "HBFs per timeframe",
+            KnownCheck::TriggerAcrossLinks => "RDH trigger same across links",
+            KnownCheck::HwMap => "RDH cabling matches hardware map",
+            KnownCheck::RdhAtDdw0 => "RDH at DDW0",
+            KnownCheck::RdhAtIhw => "RDH at initial IHW",
+            KnownCheck::IhwSanity => "IHW sanity",
+            KnownCheck::IhwActiveLanes => "IHW active lanes in layer",
+            KnownCheck::IhwOutsidePacket => "IHW outside of packets",
+            KnownCheck::TdhSanity => "TDH sanity",
+            KnownCheck::TdhContinuation => "TDH continuation",
+            KnownCheck::TdhNoContinuation => "TDH no continuation",
+            KnownCheck::TdhInternalTrigger => "TDH internal trigger",
+            KnownCheck::TdhTrigger => "TDH trigger vs TDH/RDH",
+            KnownCheck::TdhTdtPairing => "One TDH/TDT pair per packet",
+            KnownCheck::TdtSanity => "TDT sanity",
+            KnownCheck::PayloadEnd => "Payload ends outside of packets",
+            KnownCheck::Ddw0Sanity => "DDW0 sanity",
+            KnownCheck::DataWordSanity => "Data word sanity",
+            KnownCheck::ObLaneActive => "OB lane active",
+            KnownCheck::IbLaneActive => "IB lane active",
+            KnownCheck::ObConnector => "OB input connector",
+            KnownCheck::CdwIndex => "CDW index",
+            KnownCheck::CdwPosition => "CDW position",
+        }
+    }
+
+    /// What it takes for the check to run.
+    pub fn requirement(&self) -> CheckRequirement {
+        match self {
+            KnownCheck::RdhVersion => CheckRequirement::Always,
+            KnownCheck::RdhSanity | KnownCheck::RdhDataFormat => CheckRequirement::AnyCheck,
+            KnownCheck::RdhRunning
+            | KnownCheck::SocSotAtHbfStart
+            | KnownCheck::TriggerAcrossLinks => CheckRequirement::RunningChecks,
+            KnownCheck::OrbitPerHbf => CheckRequirement::HbfPerOrbit,
+            KnownCheck::HwMap => CheckRequirement::HwMap,
+            KnownCheck::HbfPerTf => CheckRequirement::HbfPerTf,
+            KnownCheck::RdhExpectedDataFormat => CheckRequirement::ExpectDataFormat,
+            KnownCheck::PayloadPadding
+            | KnownCheck::IhwSanity
+            | KnownCheck::IhwActiveLanes
+            | KnownCheck::TdhSanity
+            | KnownCheck::TdtSanity
+            | KnownCheck::Ddw0Sanity
+            | KnownCheck::DataWordSanity
+            | KnownCheck::CdwPosition => CheckRequirement::ItsTarget,
+            KnownCheck::RdhAtDdw0
+            | KnownCheck::RdhAtIhw
+            | KnownCheck::TdhContinuation
+            | KnownCheck::TdhNoContinuation
+            | KnownCheck::TdhInternalTrigger
+            | KnownCheck::TdhTrigger
+            | KnownCheck::TdhTdtPairing
+            | KnownCheck::PayloadEnd
+            | KnownCheck::IhwOutsidePacket
+            | KnownCheck::ObLaneActive
+            | KnownCheck::IbLaneActive
+            | KnownCheck::ObConnector
+            | KnownCheck::CdwIndex => CheckRequirement::ItsRunningChecks,
+        }
+    }
+
+    /// Returns the check reporting errors with the error code, sub-codes such as `E4401` belong to the check of `E44`.
+    pub fn from_error_code(code: &str) -> Option<KnownCheck> {
+        KnownCheck::ALL
+            .into_iter()
+            .find(|check| check.code() != "-" && code.starts_with(check.code()))
+    }
+}
+
+/// Number of evaluations of each [KnownCheck], counted locally by a validator.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CheckEvaluations([u64; KnownCheck::ALL.len()]);
+
+impl CheckEvaluations {
+    /// Counts an evaluation of the check.
+    #[inline]
+    pub fn count(&mut self, check: KnownCheck) {
+        self.0[check as usize] += 1;
+    }
+
+    /// Counts `evaluations` evaluations of the check.
+    #[inline]
+    pub fn count_n(&mut self, check: KnownCheck, evaluations: u64) {
+        self.0[check as usize] += evaluations;
+    }
+
+    /// Number of evaluations of the check.
+    pub fn get(&self, check: KnownCheck) -> u64 {
+        self.0[check as usize]
+    }
+
+    /// Adds the evaluations of `other` to the evaluations of `self`.
+    pub fn merge(&mut self, other: &CheckEvaluations) {
+        self.0
+            .iter_mut()
+            .zip(other.0.iter())
+            .for_each(|(evaluations, other_evaluations)| *evaluations += other_evaluations);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::config::Opt;
+
+    fn config_from(args: &[&str]) -> Opt {
+        <Opt as structopt::StructOpt>::from_iter(
+            ["fastpasta", "input.raw"].iter().chain(args.iter()),
+        )
+    }
+
+    #[test]
+    fn test_all_checks_in_order_of_discriminant() {
+        for (idx, check) in KnownCheck::ALL.iter().enumerate() {
+            assert_eq!(*check as usize, idx);
+        }
+    }
+
+    #[test]
+    fn test_skip_reasons_check_sanity() {
+        let config = config_from(&["check", "sanity"]);
+        assert_eq!(
+            KnownCheck::RdhSanity.requirement().skip_reason(&config),
+            None
+        );
+        assert_eq!(
+            KnownCheck::RdhRunning.requirement().skip_reason(&config),
+            Some("requires `check all`")
+        );
+        assert_eq!(
+            KnownCheck::IhwSanity.requirement().skip_reason(&config),
+            Some("requires the ITS target")
+        );
+        assert_eq!(
+            KnownCheck::TdhTrigger.requirement().skip_reason(&config),
+            Some("requires `check all its`")
+        );
+        assert_eq!(
+            KnownCheck::HwMap.requirement().skip_reason(&config),
+            Some("requires `--hw-map`")
+        );
+        assert_eq!(
+            KnownCheck::HwMap
+                .requirement()
+                .skip_reason(&config_from(&["--hw-map", "map.csv", "check", "sanity"])),
+            None
+        );
+        assert_eq!(
+            KnownCheck::HbfPerTf.requirement().skip_reason(&config),
+            Some("requires `--hbf-per-tf`")
+        );
+        assert_eq!(
+            KnownCheck::HbfPerTf
+                .requirement()
+                .skip_reason(&config_from(&["--hbf-per-tf", "128", "check", "sanity"])),
+            None
+        );
+        assert_eq!(
+            KnownCheck::RdhExpectedDataFormat
+                .requirement()
+                .skip_reason(&config_from(&["check", "sanity"])),
+            Some("requires `--expect-data-format`")
+        );
+        assert_eq!(
+            KnownCheck::RdhExpectedDataFormat
+                .requirement()
+                .skip_reason(&config_from(&[
+                    "--expect-data-format",
+                    "2",
+                    "check",
+                    "sanity"
+                ])),
+            None
+        );
+        let config = config_from(&["check", "all", "its", "--no-expect-hbf-per-orbit"]);
+        assert_eq!(
+            KnownCheck::OrbitPerHbf.requirement().skip_reason(&config),
+            Some("disabled, enable with `--expect-hbf-per-orbit`")
+        );
+        assert_eq!(
+            KnownCheck::TdhTrigger.requirement().skip_reason(&config),
+            None
+        );
+        let config = config_from(&["view", "rdh"]);
+        assert_eq!(
+            KnownCheck::RdhVersion.requirement().skip_reason(&config),
+            None
+        );
+        assert_eq!(
+            KnownCheck::RdhSanity.requirement().skip_reason(&config),
+            Some("no checks enabled")
+        );
+    }
+
+    #[test]
+    fn test_from_error_code() {
+        assert_eq!(
+            KnownCheck::from_error_code("E4401"),
+            Some(KnownCheck::TdhTrigger)
+        );
+        assert_eq!(
+            KnownCheck::from_error_code("E30"),
+            Some(KnownCheck::IhwSanity)
+        );
+        assert_eq!(
+            KnownCheck::from_error_code("E17"),
+            Some(KnownCheck::HbfPerTf)
+        );
+        assert_eq!(KnownCheck::from_error_code("E99"), None);
+    }
+
+    /// Collects the error codes, e.g. `E30` of `[E30]`, of all the sources in `dir` and its sub-directories.
+    fn error_codes_in_sources(dir: &std::path::Path, codes: &mut Vec<(String, String)>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                error_codes_in_sources(&path, codes);
+                continue;
+            }
+            let source = std::fs::read_to_string(&path).unwrap();
+            for (idx, _) in source.match_indices("[E") {
+                let digits: String = source[idx + 2..]
+                    .chars()
+                    .take_while(char::is_ascii_digit)
+                    .collect();
+                if !digits.is_empty() && source[idx + 2 + digits.len()..].starts_with(']') {
+                    codes.push((format!("E{digits}"), path.display().to_string()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_reported_error_code_is_a_known_check() {
+        let mut codes = Vec::new();
+        error_codes_in_sources(
+            &std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+            &mut codes,
+        );
+        assert!(!codes.is_empty());
+        for (code, file) in codes {
+            assert!(
+                KnownCheck::from_error_code(&code).is_some(),
+                "{code} reported in {file} has no known check"
+            );
+        }
+    }
+}
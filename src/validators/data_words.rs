@@ -1,5 +1,6 @@
 //! Performs sanity checks on data words
 use crate::words::data_words::*;
+use crate::words::status_words::ApeWord;
 use std::fmt::Write;
 
 /// Convenience const struct to avoid having to instantiate the struct elsewhere
@@ -16,6 +17,9 @@ impl DataWordSanityChecker {
 
         if !self.is_valid_any_id(id) {
             write!(err_str, "ID is invalid: {id:#02X}").unwrap();
+            if let Some(name) = ApeWord::from_id(id).and_then(ApeWord::name) {
+                write!(err_str, ", APE {name}").unwrap();
+            }
             // Early return if ID is wrong
             return Err(err_str);
         }
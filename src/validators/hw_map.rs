@@ -0,0 +1,323 @@
+//! Contains the [HwMap] of which CRU, link and endpoint each FEE is cabled to, and the [HwMapValidator] that verifies every CDP against it.
+//!
+//! The map is read from a CSV file with the columns `fee_id,cru_id,link_id,endpoint`, maintained by operations.
+//! A combination of FEE ID, CRU ID, link ID and endpoint that doesn't match the row of the FEE is only reported the first time it is seen.
+use crate::words::lib::RDH;
+use std::collections::{HashMap, HashSet};
+
+/// The header of the hardware map CSV file.
+pub const HW_MAP_HEADER: &str = "fee_id,cru_id,link_id,endpoint";
+
+/// A row of the hardware map, where a FEE is cabled to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HwMapRow {
+    /// FEE ID.
+    pub fee_id: u16,
+    /// CRU ID.
+    pub cru_id: u16,
+    /// Link ID.
+    pub link_id: u8,
+    /// Endpoint, the DW field of the RDH.
+    pub endpoint: u8,
+}
+
+impl HwMapRow {
+    /// The combination of the FEE ID, CRU ID, link ID and endpoint of an [RDH].
+    pub fn from_rdh<T: RDH>(rdh: &T) -> Self {
+        Self {
+            fee_id: rdh.fee_id(),
+            cru_id: rdh.cru_id(),
+            link_id: rdh.link_id(),
+            endpoint: rdh.dw(),
+        }
+    }
+}
+
+impl std::fmt::Display for HwMapRow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "fee_id {:#X}, cru_id {}, link_id {}, endpoint {}",
+            self.fee_id, self.cru_id, self.link_id, self.endpoint
+        )
+    }
+}
+
+/// The rows of the hardware map, by FEE ID.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HwMap {
+    rows: HashMap<u16, HwMapRow>,
+}
+
+impl HwMap {
+    /// Reads the hardware map from a CSV file.
+    pub fn read(path: &std::path::Path) -> Result<Self, std::io::Error> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            std::io::Error::new(
+                e.kind(),
+                format!("Failed to read hardware map {}: {e}", path.display()),
+            )
+        })?;
+        Self::parse(&content).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid hardware map {}: {e}", path.display()),
+            )
+        })
+    }
+
+    /// Parses the hardware map from CSV with the header `fee_id,cru_id,link_id,endpoint`, values are in hex (`0x502A`) or decimal.
+    ///
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn parse(csv: &str) -> Result<Self, String> {
+        let mut lines = csv
+            .lines()
+            .enumerate()
+            .map(|(line_idx, line)| (line_idx + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+        match lines.next() {
+            Some((_, header)) if header.replace(' ', "") == HW_MAP_HEADER => (),
+            _ => {
+                return Err(format!(
+                    "the first line must be the header '{HW_MAP_HEADER}'"
+                ))
+            }
+        }
+        let mut rows = HashMap::new();
+        for (line_number, line) in lines {
+            let row = parse_row(line).map_err(|e| format!("line {line_number}: {e}"))?;
+            if let Some(previous) = rows.insert(row.fee_id, row) {
+                return Err(format!(
+                    "line {line_number}: FEE ID {:#X} is already mapped to {previous}",
+                    row.fee_id
+                ));
+            }
+        }
+        Ok(Self { rows })
+    }
+
+    /// The row of a FEE ID, [None] if the FEE is not in the map.
+    pub fn get(&self, fee_id: u16) -> Option<&HwMapRow> {
+        self.rows.get(&fee_id)
+    }
+
+    /// Number of rows of the map.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns true if the map has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
+
+fn parse_row(line: &str) -> Result<HwMapRow, String> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    if fields.len() != 4 {
+        return Err(format!(
+            "expected 4 columns, found {}: '{line}'",
+            fields.len()
+        ));
+    }
+    Ok(HwMapRow {
+        fee_id: parse_value(fields[0], "fee_id")?,
+        cru_id: parse_value(fields[1], "cru_id")?,
+        link_id: parse_value(fields[2], "link_id")?,
+        endpoint: parse_value(fields[3], "endpoint")?,
+    })
+}
+
+fn parse_value<T: TryFrom<u32>>(s: &str, column: &str) -> Result<T, String> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex_digits) => u32::from_str_radix(hex_digits, 16),
+        None => s.parse(),
+    }
+    .ok()
+    .and_then(|value| T::try_from(value).ok())
+    .ok_or_else(|| format!("invalid {column} '{s}'"))
+}
+
+/// A combination of FEE ID, CRU ID, link ID and endpoint that doesn't match the hardware map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HwMapMismatch {
+    /// Memory position of the first RDH with the combination.
+    pub mem_pos: u64,
+    /// The combination seen in the RDH.
+    pub seen: HwMapRow,
+    /// The row of the FEE in the map, [None] if the FEE is not in the map.
+    pub expected: Option<HwMapRow>,
+}
+
+impl std::fmt::Display for HwMapMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.expected {
+            Some(expected) => write!(
+                f,
+                "{:#X}: [E19] {} does not match the hardware map, expected {expected}",
+                self.mem_pos, self.seen
+            ),
+            None => write!(
+                f,
+                "{:#X}: [E19] {} is not in the hardware map, FEE ID {:#X} has no row",
+                self.mem_pos, self.seen, self.seen.fee_id
+            ),
+        }
+    }
+}
+
+/// Number of combinations of FEE ID, CRU ID, link ID and endpoint seen in the data, that match or don't match the hardware map.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HwMapSummary {
+    /// Combinations matching a row of the map.
+    pub verified: u32,
+    /// Combinations not matching the row of their FEE, or with a FEE that is not in the map.
+    pub mismatched: u32,
+}
+
+/// Verifies the FEE ID, CRU ID, link ID and endpoint of every CDP against the [HwMap].
+#[derive(Debug)]
+pub struct HwMapValidator {
+    map: HwMap,
+    seen: HashSet<HwMapRow>,
+    summary: HwMapSummary,
+    evaluations: u64,
+}
+
+impl HwMapValidator {
+    /// Creates a [HwMapValidator] verifying CDPs against the `map`.
+    pub fn new(map: HwMap) -> Self {
+        Self {
+            map,
+            seen: HashSet::new(),
+            summary: HwMapSummary::default(),
+            evaluations: 0,
+        }
+    }
+
+    /// Verifies the [RDH] at the memory position `mem_pos`.
+    ///
+    /// Returns a mismatch the first time a combination that doesn't match the map is seen.
+    pub fn check<T: RDH>(&mut self, rdh: &T, mem_pos: u64) -> Option<HwMapMismatch> {
+        self.evaluations += 1;
+        let seen = HwMapRow::from_rdh(rdh);
+        if !self.seen.insert(seen) {
+            return None;
+        }
+        let expected = self.map.get(seen.fee_id).copied();
+        if expected == Some(seen) {
+            self.summary.verified += 1;
+            return None;
+        }
+        self.summary.mismatched += 1;
+        Some(HwMapMismatch {
+            mem_pos,
+            seen,
+            expected,
+        })
+    }
+
+    /// Number of CDPs verified against the map.
+    pub fn evaluations(&self) -> u64 {
+        self.evaluations
+    }
+
+    /// The number of verified and mismatched combinations.
+    pub fn summary(&self) -> HwMapSummary {
+        self.summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh::CruidDw;
+    use crate::words::rdh_cru::{test_data::CORRECT_RDH_CRU_V7, RdhCRU, V7};
+
+    // CORRECT_RDH_CRU_V7 is FEE 0x502A on CRU 24 link 0 endpoint 0
+    const MAP: &str = "\
+# Test setup
+fee_id,cru_id,link_id,endpoint
+0x502A,24,0,0
+
+0x502B, 24, 2, 1
+";
+
+    fn cdp(fee_id: u16, link_id: u8, cruid_dw: u16) -> RdhCRU<V7> {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        rdh.rdh0.fee_id = crate::words::rdh::FeeId(fee_id);
+        rdh.link_id = link_id;
+        rdh.cruid_dw = CruidDw(cruid_dw);
+        rdh
+    }
+
+    #[test]
+    fn test_parse_map() {
+        let map = HwMap::parse(MAP).unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(
+            map.get(0x502B),
+            Some(&HwMapRow {
+                fee_id: 0x502B,
+                cru_id: 24,
+                link_id: 2,
+                endpoint: 1
+            })
+        );
+        assert!(map.get(0x502C).is_none());
+    }
+
+    #[test]
+    fn test_parse_invalid_map() {
+        assert!(HwMap::parse("0x502A,24,0,0")
+            .unwrap_err()
+            .contains("header"));
+        assert_eq!(
+            HwMap::parse("fee_id,cru_id,link_id,endpoint\n0x502A,24,0").unwrap_err(),
+            "line 2: expected 4 columns, found 3: '0x502A,24,0'"
+        );
+        assert_eq!(
+            HwMap::parse("fee_id,cru_id,link_id,endpoint\n0x502A,24,256,0").unwrap_err(),
+            "line 2: invalid link_id '256'"
+        );
+        assert_eq!(
+            HwMap::parse("fee_id,cru_id,link_id,endpoint\n0x502A,24,0,0\n0x502A,24,1,0")
+                .unwrap_err(),
+            "line 3: FEE ID 0x502A is already mapped to fee_id 0x502A, cru_id 24, link_id 0, endpoint 0"
+        );
+    }
+
+    #[test]
+    fn test_mis_cabled_cdp_reported_once() {
+        let mut validator = HwMapValidator::new(HwMap::parse(MAP).unwrap());
+        let mut mismatches = Vec::new();
+        for orbit in 0..3 {
+            let mem_pos = orbit * 0x300;
+            mismatches.extend(validator.check(&cdp(0x502A, 0, 24), mem_pos));
+            // FEE 0x502B is cabled to link 2 of endpoint 1, but sends on link 3 of endpoint 0
+            mismatches.extend(validator.check(&cdp(0x502B, 3, 24), mem_pos + 0x100));
+            // A FEE that is not in the map
+            mismatches.extend(validator.check(&cdp(0x502C, 4, 24), mem_pos + 0x200));
+        }
+        assert_eq!(mismatches.len(), 2);
+        assert_eq!(
+            mismatches[0].to_string(),
+            "0x100: [E19] fee_id 0x502B, cru_id 24, link_id 3, endpoint 0 does not match the hardware map, expected fee_id 0x502B, cru_id 24, link_id 2, endpoint 1"
+        );
+        assert_eq!(
+            mismatches[1].to_string(),
+            "0x200: [E19] fee_id 0x502C, cru_id 24, link_id 4, endpoint 0 is not in the hardware map, FEE ID 0x502C has no row"
+        );
+        assert_eq!(
+            validator.summary(),
+            HwMapSummary {
+                verified: 1,
+                mismatched: 2
+            }
+        );
+        assert_eq!(validator.evaluations(), 9);
+        // The endpoint is the 4 MSB of the CRU ID field
+        assert!(validator.check(&cdp(0x502B, 2, 0x1018), 0).is_none());
+        assert_eq!(validator.summary().verified, 2);
+    }
+}
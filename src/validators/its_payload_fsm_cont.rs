@@ -2,6 +2,7 @@
 #![allow(non_camel_case_types)] // An exception to the Rust naming convention, for the state machine macro types
 
 /// Payload word types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PayloadWord {
     /// ITS Header Word
     IHW,
@@ -83,6 +84,35 @@ sm! {
     }
 }
 
+/// The states of the [ItsPayloadFsmContinuous], named after the state and the event that led to it.
+///
+/// Used to save and restore the state machine, see [ItsPayloadFsmContinuous::state] and [ItsPayloadFsmContinuous::restore].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum PayloadFsmState {
+    /// Initial state, expecting an IHW
+    InitialIhw,
+    /// Expecting an IHW after a DDW0
+    IhwAfterDdw0,
+    /// Expecting a TDH after an IHW
+    TdhAfterIhw,
+    /// Expecting a DDW0 or TDH after a TDH with no_data set
+    Ddw0OrTdhAfterNoData,
+    /// Expecting data after a TDH with no_data not set
+    DataAfterTdh,
+    /// Expecting data after data
+    DataAfterData,
+    /// Expecting a DDW0, TDH or IHW after a TDT with packet_done set
+    Ddw0OrTdhOrIhwAfterPacketDone,
+    /// Expecting an IHW in continuation after a TDT with packet_done not set
+    ContinuationIhw,
+    /// Expecting a TDH in continuation
+    ContinuationTdh,
+    /// Expecting data in continuation after a TDH
+    ContinuationDataAfterTdh,
+    /// Expecting data in continuation after data
+    ContinuationDataAfterData,
+}
+
 use self::ITS_Payload_Continuous::IHW_;
 /// State machine for ITS payload continuous mode.
 pub struct ItsPayloadFsmContinuous {
@@ -107,6 +137,87 @@ impl ItsPayloadFsmContinuous {
         self.state_machine = ITS_Payload_Continuous::Machine::new(IHW_).as_enum();
     }
 
+    /// Returns the current state of the state machine.
+    pub fn state(&self) -> PayloadFsmState {
+        use ITS_Payload_Continuous::Variant::*;
+        match self.state_machine {
+            InitialIHW_(_) => PayloadFsmState::InitialIhw,
+            IHW_By_WasDdw0(_) => PayloadFsmState::IhwAfterDdw0,
+            TDH_By_WasIhw(_) => PayloadFsmState::TdhAfterIhw,
+            DDW0_or_TDH_By_NoDataTrue(_) => PayloadFsmState::Ddw0OrTdhAfterNoData,
+            DATA_By_NoDataFalse(_) => PayloadFsmState::DataAfterTdh,
+            DATA_By_WasData(_) => PayloadFsmState::DataAfterData,
+            DDW0_or_TDH_or_IHW_By_WasTDTpacketDoneTrue(_) => {
+                PayloadFsmState::Ddw0OrTdhOrIhwAfterPacketDone
+            }
+            c_IHW_By_WasTDTpacketDoneFalse(_) => PayloadFsmState::ContinuationIhw,
+            c_TDH_By_Next(_) => PayloadFsmState::ContinuationTdh,
+            c_DATA_By_Next(_) => PayloadFsmState::ContinuationDataAfterTdh,
+            c_DATA_By_WasData(_) => PayloadFsmState::ContinuationDataAfterData,
+        }
+    }
+
+    /// Returns a short name of the current state, as the words expected next.
+    pub fn current_state_name(&self) -> &'static str {
+        match self.state() {
+            PayloadFsmState::InitialIhw => "initial IHW",
+            PayloadFsmState::IhwAfterDdw0 => "IHW after DDW0",
+            PayloadFsmState::TdhAfterIhw => "TDH after IHW",
+            PayloadFsmState::Ddw0OrTdhAfterNoData => "DDW0 or TDH after TDH with no data",
+            PayloadFsmState::DataAfterTdh => "data after TDH",
+            PayloadFsmState::DataAfterData => "data after data",
+            PayloadFsmState::Ddw0OrTdhOrIhwAfterPacketDone => {
+                "DDW0, TDH or IHW after TDT with packet done"
+            }
+            PayloadFsmState::ContinuationIhw => "IHW in continuation",
+            PayloadFsmState::ContinuationTdh => "TDH in continuation",
+            PayloadFsmState::ContinuationDataAfterTdh => "data in continuation after TDH",
+            PayloadFsmState::ContinuationDataAfterData => "data in continuation after data",
+        }
+    }
+
+    /// Returns true if a payload can legally end in the current state, after a TDT, a DDW0, or before the first word.
+    ///
+    /// In any other state a packet or a sequence of words is still open, and its words are expected in the same payload.
+    pub fn is_terminal_state(&self) -> bool {
+        matches!(
+            self.state(),
+            PayloadFsmState::InitialIhw
+                | PayloadFsmState::IhwAfterDdw0
+                | PayloadFsmState::Ddw0OrTdhOrIhwAfterPacketDone
+                | PayloadFsmState::ContinuationIhw
+        )
+    }
+
+    /// Restores the state machine to a state previously returned by [ItsPayloadFsmContinuous::state].
+    ///
+    /// The state machine can only be constructed in the initial state, so the state is reached by replaying the events leading to it.
+    pub fn restore(&mut self, state: PayloadFsmState) {
+        use ITS_Payload_Continuous::*;
+        let tdh = || Machine::new(IHW_).transition(_WasIhw);
+        let data = || tdh().transition(_NoDataFalse);
+        let c_tdh = || data().transition(_WasTDTpacketDoneFalse).transition(_Next);
+        self.state_machine = match state {
+            PayloadFsmState::InitialIhw => Machine::new(IHW_).as_enum(),
+            PayloadFsmState::IhwAfterDdw0 => {
+                tdh().transition(_NoDataTrue).transition(_WasDdw0).as_enum()
+            }
+            PayloadFsmState::TdhAfterIhw => tdh().as_enum(),
+            PayloadFsmState::Ddw0OrTdhAfterNoData => tdh().transition(_NoDataTrue).as_enum(),
+            PayloadFsmState::DataAfterTdh => data().as_enum(),
+            PayloadFsmState::DataAfterData => data().transition(_WasData).as_enum(),
+            PayloadFsmState::Ddw0OrTdhOrIhwAfterPacketDone => {
+                data().transition(_WasTDTpacketDoneTrue).as_enum()
+            }
+            PayloadFsmState::ContinuationIhw => data().transition(_WasTDTpacketDoneFalse).as_enum(),
+            PayloadFsmState::ContinuationTdh => c_tdh().as_enum(),
+            PayloadFsmState::ContinuationDataAfterTdh => c_tdh().transition(_Next).as_enum(),
+            PayloadFsmState::ContinuationDataAfterData => {
+                c_tdh().transition(_Next).transition(_WasData).as_enum()
+            }
+        };
+    }
+
     /// Advance the state machine by one word.
     ///
     /// Takes a slice of 10 bytes representing the GBT word.
@@ -211,3 +322,73 @@ impl ItsPayloadFsmContinuous {
         current_word
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_STATES: [PayloadFsmState; 11] = [
+        PayloadFsmState::InitialIhw,
+        PayloadFsmState::IhwAfterDdw0,
+        PayloadFsmState::TdhAfterIhw,
+        PayloadFsmState::Ddw0OrTdhAfterNoData,
+        PayloadFsmState::DataAfterTdh,
+        PayloadFsmState::DataAfterData,
+        PayloadFsmState::Ddw0OrTdhOrIhwAfterPacketDone,
+        PayloadFsmState::ContinuationIhw,
+        PayloadFsmState::ContinuationTdh,
+        PayloadFsmState::ContinuationDataAfterTdh,
+        PayloadFsmState::ContinuationDataAfterData,
+    ];
+
+    #[test]
+    fn test_restore_all_states() {
+        let mut fsm = ItsPayloadFsmContinuous::default();
+        assert_eq!(fsm.state(), PayloadFsmState::InitialIhw);
+        for state in ALL_STATES {
+            fsm.restore(state);
+            assert_eq!(fsm.state(), state);
+        }
+    }
+
+    #[test]
+    fn test_terminal_states() {
+        let mut fsm = ItsPayloadFsmContinuous::default();
+        let mut terminal_states = Vec::new();
+        for state in ALL_STATES {
+            fsm.restore(state);
+            if fsm.is_terminal_state() {
+                terminal_states.push(fsm.current_state_name());
+            }
+        }
+        assert_eq!(
+            terminal_states,
+            [
+                "initial IHW",
+                "IHW after DDW0",
+                "DDW0, TDH or IHW after TDT with packet done",
+                "IHW in continuation"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_restored_fsm_advances_as_original() {
+        const IHW: [u8; 10] = [0xFF, 0x3F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE0];
+        const TDH: [u8; 10] = [0x03, 0x1A, 0x00, 0x00, 0x75, 0xD5, 0x7D, 0x0B, 0x00, 0xE8];
+        const DATA_WORD: [u8; 10] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20];
+        const TDT_PACKET_NOT_DONE: [u8; 10] =
+            [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF0];
+        let mut fsm = ItsPayloadFsmContinuous::default();
+        for word in [IHW, TDH, DATA_WORD, TDT_PACKET_NOT_DONE] {
+            fsm.advance(&word);
+        }
+        let mut restored_fsm = ItsPayloadFsmContinuous::default();
+        restored_fsm.restore(fsm.state());
+        assert_eq!(restored_fsm.state(), PayloadFsmState::ContinuationIhw);
+        for word in [IHW, TDH, DATA_WORD] {
+            assert_eq!(restored_fsm.advance(&word), fsm.advance(&word));
+            assert_eq!(restored_fsm.state(), fsm.state());
+        }
+    }
+}
@@ -1,7 +1,20 @@
 //! Contains the [check_cdp_chunk] function, which iterates over and comnsumes a [`data_wrapper::CdpChunk<T>`], dispatching the data to the correct thread based on the Link ID running an instance of [LinkValidator].
-use super::link_validator::LinkValidator;
+use super::link_validator::{LinkValidator, LinkValidatorState};
 use crate::{input::data_wrapper, util, words::lib::RDH};
 type CdpTuple<T> = (T, Vec<u8>, u64);
+
+/// What the link validators are initialized with when [check_cdp_chunk] spawns them, besides the config.
+pub struct LinkValidatorInit<T: RDH> {
+    /// States to restore link validators from when they are spawned, e.g. from a [Checkpoint][crate::util::checkpoint::Checkpoint].
+    pub restored_link_states: Vec<LinkValidatorState>,
+    /// Producer channel to send the CDPs with errors to, if they are saved with `--save-failing-cdps`.
+    pub failing_cdps_ch: Option<crossbeam_channel::Sender<CdpTuple<T>>>,
+    /// Producer channel to send snapshots of the link validator states to, for the periodic checkpoints of `--checkpoint-file`.
+    pub snapshot_ch: Option<std::sync::mpsc::Sender<LinkValidatorState>>,
+    /// Traces the CDPs dispatched to the link validators with `--trace-cdps`.
+    pub cdp_tracer: Option<crate::input::cdp_trace::CdpTracer>,
+}
+
 /// Iterates over and consumes a [`data_wrapper::CdpChunk<T>`], dispatching the data to the correct thread running an instance of [LinkValidator].
 ///
 /// If a link validator thread does not exist for the link id of the current rdh, a new one is spawned
@@ -10,24 +23,33 @@ type CdpTuple<T> = (T, Vec<u8>, u64);
 /// * `cdp_chunk` - The cdp chunk to be processed
 /// * `links` - A vector of link ids that have been seen so far
 /// * `link_process_channels` - A vector of producer channels to send data to the link validator threads
-/// * `validator_thread_handles` - A vector of handles to the link validator threads
+/// * `validator_thread_handles` - A vector of handles to the link validator threads, that return the state of the validator when joined
+/// * `link_validator_init` - What the link validators are initialized with when they are spawned, the restored states are removed as they are used
 /// * `config` - The config object
 /// * `stats_sender_channel` - The producer channel to send stats to the stats controller
 pub fn check_cdp_chunk<T: RDH + 'static>(
     cdp_chunk: data_wrapper::CdpChunk<T>,
     links: &mut Vec<u8>,
     link_process_channels: &mut Vec<crossbeam_channel::Sender<CdpTuple<T>>>,
-    validator_thread_handles: &mut Vec<std::thread::JoinHandle<()>>,
+    validator_thread_handles: &mut Vec<std::thread::JoinHandle<Option<LinkValidatorState>>>,
+    link_validator_init: &mut LinkValidatorInit<T>,
     config: std::sync::Arc<impl util::lib::Config + 'static>,
     stats_sender_channel: std::sync::mpsc::Sender<crate::stats::stats_controller::StatType>,
 ) {
+    log::trace!("Dispatching chunk of {}", cdp_chunk.summary());
     for (rdh, data, mem_pos) in cdp_chunk.into_iter() {
-        if let Some(link_index) = links.iter().position(|&x| x == rdh.link_id()) {
-            link_process_channels
-                .get(link_index)
-                .unwrap()
-                .send((rdh, data, mem_pos))
+        if config.ordered() {
+            // Let the stats controller know which memory positions are being processed, before the link validator can report any errors
+            stats_sender_channel
+                .send(crate::stats::stats_controller::StatType::CdpDispatched {
+                    link_id: rdh.link_id(),
+                    mem_pos,
+                    next_mem_pos: mem_pos + rdh.offset_to_next() as u64,
+                })
                 .unwrap();
+        }
+        let link_index = if let Some(link_index) = links.iter().position(|&x| x == rdh.link_id()) {
+            link_index
         } else {
             links.push(rdh.link_id());
             let (send_channel, recv_channel) =
@@ -40,19 +62,50 @@ pub fn check_cdp_chunk<T: RDH + 'static>(
                     .spawn({
                         let config = config.clone();
                         let stats_sender_channel = stats_sender_channel.clone();
-                        let mut link_validator =
-                            LinkValidator::new(&*config, stats_sender_channel, recv_channel);
+                        let mut link_validator = LinkValidator::new(
+                            &*config,
+                            stats_sender_channel.clone(),
+                            recv_channel,
+                        );
+                        if let Some(failing_cdps_ch) = &link_validator_init.failing_cdps_ch {
+                            link_validator.save_failing_cdps(failing_cdps_ch.clone());
+                        }
+                        if let Some(snapshot_ch) = &link_validator_init.snapshot_ch {
+                            link_validator.send_snapshots(snapshot_ch.clone());
+                        }
+                        if let Some(idx) = link_validator_init
+                            .restored_link_states
+                            .iter()
+                            .position(|state| state.link_id == rdh.link_id())
+                        {
+                            let state = link_validator_init.restored_link_states.swap_remove(idx);
+                            if let Err(e) = link_validator.restore(&state) {
+                                stats_sender_channel
+                                    .send(crate::stats::stats_controller::StatType::Error(format!(
+                                        "Failed to restore state of link {} from checkpoint: {e}",
+                                        state.link_id
+                                    )))
+                                    .unwrap();
+                            } else if let Some(snapshot_ch) = &link_validator_init.snapshot_ch {
+                                // Until its first snapshot, the link resumes from the restored state
+                                let _ = snapshot_ch.send(state);
+                            }
+                        }
                         move || {
                             link_validator.run();
+                            link_validator.snapshot()
                         }
                     })
                     .expect("Failed to spawn link validator thread"),
             );
-            link_process_channels
-                .last()
-                .unwrap()
-                .send((rdh, data, mem_pos))
-                .unwrap();
+            links.len() - 1
+        };
+        if let Some(cdp_tracer) = &mut link_validator_init.cdp_tracer {
+            let handler = validator_thread_handles[link_index].thread().name();
+            cdp_tracer.trace(&rdh, data.len(), mem_pos, handler.unwrap_or("unnamed"));
         }
+        link_process_channels[link_index]
+            .send((rdh, data, mem_pos))
+            .unwrap();
     }
 }
@@ -3,12 +3,35 @@
 //! A [LinkValidator] is created for each link that is being checked.
 //! The [LinkValidator] is responsible for creating and running all the subvalidators.
 //! It also contains an [AllocRingBuffer] that is used to store the previous two [RDH]s, to be able to include them in error messages.
-use crate::{util::lib::Config, words::lib::RDH};
+use crate::{
+    stats::{cdp_detail::CdpDetail, quality_score::LinkQualityCounts},
+    util::{
+        config::{ParallelMode, ReportDetail},
+        lib::Config,
+    },
+    validators::{
+        cdp_running::PrecheckedDataWord,
+        check_registry::{CheckEvaluations, KnownCheck},
+    },
+    words::lib::{GBT_WIDE_WORD_SIZE, RDH},
+};
+use rayon::prelude::*;
 use ringbuffer::{AllocRingBuffer, RingBufferExt, RingBufferWrite};
 
+/// Max number of CDPs received at once and prechecked in parallel, when checking payloads in parallel.
+const PARALLEL_PAYLOAD_BATCH_SIZE: usize = 64;
+
+/// Number of [CdpDetail]s collected before they are sent to the [StatsController][crate::stats::stats_controller::StatsController] in one message.
+const CDP_DETAIL_BATCH_SIZE: usize = 1024;
+
+/// Number of CDPs checked between the snapshots sent for the periodic checkpoints of `--checkpoint-file`.
+const CHECKPOINT_SNAPSHOT_CDPS: u32 = 100;
+
 struct LinkValidatorConfig {
     running_checks: bool,
     target: Option<crate::util::config::System>,
+    expect_hbf_per_orbit: bool,
+    parallel_payload: bool,
 }
 
 impl LinkValidatorConfig {
@@ -19,25 +42,46 @@ impl LinkValidatorConfig {
                 Check::All(_) => Self {
                     running_checks: true,
                     target: check.target(),
+                    expect_hbf_per_orbit: config.expect_hbf_per_orbit(),
+                    parallel_payload: config.parallel() == ParallelMode::Payload,
                 },
                 _ => Self {
                     running_checks: false,
                     target: check.target(),
+                    expect_hbf_per_orbit: false,
+                    parallel_payload: config.parallel() == ParallelMode::Payload,
                 },
             },
             None => Self {
                 running_checks: false,
                 target: None,
+                expect_hbf_per_orbit: false,
+                parallel_payload: false,
             },
         }
     }
 }
 
+/// Snapshot of the state of a [LinkValidator], used to resume checks from a [Checkpoint][crate::util::checkpoint::Checkpoint].
+///
+/// Taken with [LinkValidator::snapshot] and restored with [LinkValidator::restore].
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LinkValidatorState {
+    /// The link ID of the link validator.
+    pub link_id: u8,
+    /// Memory position of the RDH following the last CDP checked.
+    pub next_mem_pos: u64,
+    prev_rdhs: Vec<Vec<u8>>,
+    rdh_running: crate::validators::rdh_running::RdhRunningState,
+    cdp_running: crate::validators::cdp_running::CdpRunningState,
+}
+
 /// Main validator that handles all checks on a specific link.
 ///
 /// A [LinkValidator] is created for each link that is being checked.
 pub struct LinkValidator<T: RDH> {
     config: LinkValidatorConfig,
+    report_progress: bool,
     /// Producer channel to send stats through.
     pub send_stats_ch: std::sync::mpsc::Sender<crate::stats::stats_controller::StatType>,
     /// Consumer channel to receive data from.
@@ -46,10 +90,43 @@ pub struct LinkValidator<T: RDH> {
     rdh_running_validator: crate::validators::rdh_running::RdhCruRunningChecker<T>,
     rdh_sanity_validator: crate::validators::rdh::RdhCruSanityValidator<T>,
     prev_rdhs: AllocRingBuffer<T>,
+    last_rdh_mem_pos: u64,
+    check_evaluations: CheckEvaluations,
+    /// Details of the CDPs checked since they were last sent, [None] if the report has no CDP detail.
+    cdp_details: Option<Vec<CdpDetail>>,
+    trust_payload_format: bool,
+    /// A payload that appears to be data format 2 despite the RDH declaring 0 was reported, further ones are not.
+    unpadded_format_0_reported: bool,
+    /// RDH system ID of the payloads that are checked, the one of the check target unless overridden.
+    target_system_id: u8,
+    /// RDH data format every RDH must have, set with `--expect-data-format`.
+    expect_data_format: Option<u8>,
+    force_check_all_links: bool,
+    strict_start: bool,
+    /// Link ID, system ID of the first CDP of another system than the target, and the number of CDPs of other systems whose payload checks were skipped.
+    other_system_cdps: Option<(u8, u8, u64)>,
+    /// HBFs checked and HBFs with errors for the quality score, [None] if the quality score is not computed.
+    quality_counts: Option<LinkQualityCounts>,
+    /// The current HBF is already counted as an HBF with errors.
+    hbf_errored: bool,
+    /// Errors reported by this validator, not counting those of the [CdpRunningValidator][crate::validators::cdp_running::CdpRunningValidator].
+    errors_reported: u64,
+    /// Payload words are the 16 byte words of GBT wide mode.
+    gbt_wide: bool,
+    /// Producer channel to send the CDPs with errors through, if they are saved with `--save-failing-cdps`.
+    failing_cdps_ch: Option<crossbeam_channel::Sender<CdpTuple<T>>>,
+    /// Producer channel to send snapshots of the state through, for the periodic checkpoints of `--checkpoint-file`.
+    snapshot_ch: Option<std::sync::mpsc::Sender<LinkValidatorState>>,
+    cdps_since_snapshot: u32,
+    /// CDPs before this memory position were checked before the state was restored, and are skipped.
+    resume_mem_pos: u64,
 }
 
 type CdpTuple<T> = (T, Vec<u8>, u64);
 
+/// Result of the stateless checks of a CDP payload, see [precheck_payload].
+pub type PayloadPrecheck = Result<Vec<PrecheckedDataWord>, String>;
+
 impl<T: RDH> LinkValidator<T> {
     /// Creates a new [LinkValidator] from a [Config] and a [StatType][crate::stats::stats_controller::StatType] producer channel.
     pub fn new(
@@ -58,7 +135,7 @@ impl<T: RDH> LinkValidator<T> {
         data_rcv_channel: crossbeam_channel::Receiver<CdpTuple<T>>,
     ) -> Self {
         let local_cfg = LinkValidatorConfig::new(global_config);
-        let rdh_sanity_validator = if let Some(system) = local_cfg.target.clone() {
+        let mut rdh_sanity_validator = if let Some(system) = local_cfg.target.clone() {
             match system {
                 crate::util::config::System::ITS => {
                     crate::validators::rdh::RdhCruSanityValidator::<T>::with_specialization(
@@ -69,22 +146,154 @@ impl<T: RDH> LinkValidator<T> {
         } else {
             crate::validators::rdh::RdhCruSanityValidator::default()
         };
+        if let Some(system_id) = global_config.expect_system_id() {
+            rdh_sanity_validator.expect_system_id(system_id);
+        }
+        if let Some(bounds) = global_config.fee_id_bounds() {
+            rdh_sanity_validator.set_fee_id_bounds(bounds);
+        }
+        if global_config.strict() {
+            rdh_sanity_validator.forbid_unknown_system_ids();
+        }
+        rdh_sanity_validator.set_max_bc(global_config.max_bc());
+        let mut cdp_validator = crate::validators::cdp_running::CdpRunningValidator::new(
+            global_config,
+            send_stats_ch.clone(),
+        );
+        cdp_validator.attribute_errors_to_link();
         Self {
             config: local_cfg,
-            send_stats_ch: send_stats_ch.clone(),
+            report_progress: global_config.ordered(),
+            send_stats_ch,
             data_rcv_channel,
-            cdp_validator: crate::validators::cdp_running::CdpRunningValidator::new(
-                global_config,
-                send_stats_ch,
-            ),
+            cdp_validator,
             rdh_running_validator: crate::validators::rdh_running::RdhCruRunningChecker::default(),
             rdh_sanity_validator,
             prev_rdhs: AllocRingBuffer::with_capacity(2),
+            last_rdh_mem_pos: 0,
+            check_evaluations: CheckEvaluations::default(),
+            cdp_details: (global_config.report_detail() == Some(ReportDetail::Cdp))
+                .then(|| Vec::with_capacity(CDP_DETAIL_BATCH_SIZE)),
+            trust_payload_format: global_config.trust_payload_format(),
+            unpadded_format_0_reported: false,
+            target_system_id: global_config
+                .expect_system_id()
+                .unwrap_or(super::rdh::ITS_SYSTEM_ID),
+            expect_data_format: global_config.expect_data_format(),
+            force_check_all_links: global_config.force_check_all_links(),
+            strict_start: global_config.strict_start(),
+            other_system_cdps: None,
+            quality_counts: global_config
+                .quality_score()
+                .then(LinkQualityCounts::default),
+            hbf_errored: false,
+            errors_reported: 0,
+            gbt_wide: global_config.gbt_wide(),
+            failing_cdps_ch: None,
+            snapshot_ch: None,
+            cdps_since_snapshot: 0,
+            resume_mem_pos: 0,
+        }
+    }
+
+    /// Sends every CDP an error is reported for through `failing_cdps_ch`, to be saved with `--save-failing-cdps`.
+    pub fn save_failing_cdps(&mut self, failing_cdps_ch: crossbeam_channel::Sender<CdpTuple<T>>) {
+        self.failing_cdps_ch = Some(failing_cdps_ch);
+    }
+
+    /// Sends a [snapshot][LinkValidator::snapshot] of the state through `snapshot_ch` every [CHECKPOINT_SNAPSHOT_CDPS] CDPs, for the periodic checkpoints of `--checkpoint-file`.
+    pub fn send_snapshots(&mut self, snapshot_ch: std::sync::mpsc::Sender<LinkValidatorState>) {
+        self.snapshot_ch = Some(snapshot_ch);
+    }
+
+    /// Takes a snapshot of the state carried across CDPs, returns `None` if no CDPs were checked.
+    pub fn snapshot(&self) -> Option<LinkValidatorState> {
+        let last_rdh = self.prev_rdhs.back()?;
+        Some(LinkValidatorState {
+            link_id: last_rdh.link_id(),
+            next_mem_pos: self.last_rdh_mem_pos + last_rdh.offset_to_next() as u64,
+            prev_rdhs: self
+                .prev_rdhs
+                .iter()
+                .map(|rdh| rdh.to_byte_slice().to_vec())
+                .collect(),
+            rdh_running: self.rdh_running_validator.snapshot(),
+            cdp_running: self.cdp_validator.snapshot(),
+        })
+    }
+
+    /// Restores the state from a snapshot taken with [LinkValidator::snapshot], to continue checks as if the CDPs before it were just checked.
+    pub fn restore(&mut self, state: &LinkValidatorState) -> Result<(), std::io::Error> {
+        self.prev_rdhs.clear();
+        for rdh_bytes in &state.prev_rdhs {
+            self.prev_rdhs.push(T::load(&mut rdh_bytes.as_slice())?);
+        }
+        if let Some(last_rdh) = self.prev_rdhs.back() {
+            self.last_rdh_mem_pos = state.next_mem_pos - last_rdh.offset_to_next() as u64;
         }
+        // A checkpoint saved while other links were behind resumes before this link's next CDP
+        self.resume_mem_pos = state.next_mem_pos;
+        self.rdh_running_validator.restore(&state.rdh_running)?;
+        self.cdp_validator.restore(&state.cdp_running)
     }
 
     /// Event loop where data is received and validation starts
+    ///
+    /// When there is no more data, the number of evaluations of each check is sent to the [StatsController][crate::stats::stats_controller::StatsController],
+    /// with the HBFs and lanes checked if the quality score is computed.
     pub fn run(&mut self) {
+        if self.config.parallel_payload && self.config.target.is_some() {
+            self.run_parallel_payload();
+        } else {
+            self.run_sequential();
+        }
+        self.flush_cdp_details();
+        if let (Some(mut quality_counts), Some(last_rdh)) =
+            (self.quality_counts, self.prev_rdhs.back())
+        {
+            quality_counts.link_id = last_rdh.link_id();
+            if let Some(fatal_lanes) = self.cdp_validator.fatal_lanes() {
+                quality_counts.lanes = crate::words::status_words::Ddw0::LANES;
+                quality_counts.fatal_lanes = fatal_lanes.count_ones();
+            }
+            self.send_stats_ch
+                .send(crate::stats::stats_controller::StatType::LinkQuality(
+                    quality_counts,
+                ))
+                .unwrap();
+        }
+        if let Some((link_id, system_id, cdps)) = self.other_system_cdps {
+            self.send_stats_ch
+                .send(crate::stats::stats_controller::StatType::OtherSystemLink {
+                    link_id,
+                    system_id,
+                    cdps,
+                })
+                .unwrap();
+        }
+        self.check_evaluations
+            .merge(&self.cdp_validator.take_check_evaluations());
+        self.send_stats_ch
+            .send(crate::stats::stats_controller::StatType::CheckEvaluations(
+                Box::new(std::mem::take(&mut self.check_evaluations)),
+            ))
+            .unwrap();
+    }
+
+    /// Sends the [CdpDetail]s collected so far to the [StatsController][crate::stats::stats_controller::StatsController].
+    fn flush_cdp_details(&mut self) {
+        if let Some(cdp_details) = &mut self.cdp_details {
+            if !cdp_details.is_empty() {
+                self.send_stats_ch
+                    .send(crate::stats::stats_controller::StatType::CdpDetails(
+                        Box::new(std::mem::take(cdp_details)),
+                    ))
+                    .unwrap();
+            }
+        }
+    }
+
+    fn run_sequential(&mut self) {
         loop {
             let cdp_tuple = match self.data_rcv_channel.recv() {
                 Ok(data) => data,
@@ -93,37 +302,339 @@ impl<T: RDH> LinkValidator<T> {
                     break;
                 }
             };
-            self.do_checks(cdp_tuple);
+            self.do_checks_and_report_progress(cdp_tuple, None);
+        }
+    }
+
+    /// Event loop that receives batches of CDPs, and does the stateless payload checks of each batch in parallel,
+    /// before doing the stateful checks of each CDP in order.
+    ///
+    /// The errors are the same, and in the same order, as when checking each CDP as it is received.
+    fn run_parallel_payload(&mut self) {
+        let mut batch = Vec::with_capacity(PARALLEL_PAYLOAD_BATCH_SIZE);
+        loop {
+            // Block until data is available, then take what is already received, up to the batch size
+            match self.data_rcv_channel.recv() {
+                Ok(data) => batch.push(data),
+                Err(_) => {
+                    log::trace!("LinkValidator: No more data to process");
+                    break;
+                }
+            };
+            batch.extend(
+                self.data_rcv_channel
+                    .try_iter()
+                    .take(PARALLEL_PAYLOAD_BATCH_SIZE - 1),
+            );
+            let gbt_wide = self.gbt_wide;
+            let prechecks: Vec<PayloadPrecheck> = batch
+                .par_iter()
+                .map(|(rdh, payload, _)| {
+                    if gbt_wide {
+                        precheck_payload_wide(payload)
+                    } else {
+                        precheck_payload(payload, trusted_data_format(payload, rdh.data_format()))
+                    }
+                })
+                .collect();
+            batch
+                .drain(..)
+                .zip(prechecks)
+                .for_each(|(cdp_tuple, precheck)| {
+                    self.do_checks_and_report_progress(cdp_tuple, Some(precheck))
+                });
+        }
+    }
+
+    fn do_checks_and_report_progress(
+        &mut self,
+        cdp_tuple: CdpTuple<T>,
+        precheck: Option<PayloadPrecheck>,
+    ) {
+        let (link_id, mem_pos) = (cdp_tuple.0.link_id(), cdp_tuple.2);
+        if mem_pos < self.resume_mem_pos {
+            log::trace!("{mem_pos:#X}: Skipping CDP checked before the checkpoint");
+        } else {
+            self.do_checks(cdp_tuple, precheck);
+            self.send_snapshot_if_due();
+        }
+        if self.report_progress {
+            self.send_stats_ch
+                .send(crate::stats::stats_controller::StatType::LinkProgress { link_id, mem_pos })
+                .unwrap();
+        }
+    }
+
+    fn send_snapshot_if_due(&mut self) {
+        if let Some(snapshot_ch) = &self.snapshot_ch {
+            self.cdps_since_snapshot += 1;
+            if self.cdps_since_snapshot >= CHECKPOINT_SNAPSHOT_CDPS {
+                self.cdps_since_snapshot = 0;
+                if let Some(state) = self.snapshot() {
+                    // The receiver is gone if checking stopped, the final checkpoint is saved from the joined validators
+                    let _ = snapshot_ch.send(state);
+                }
+            }
         }
     }
 
-    fn do_checks(&mut self, cdp_tuple: CdpTuple<T>) {
+    fn do_checks(&mut self, cdp_tuple: CdpTuple<T>, precheck: Option<PayloadPrecheck>) {
         let (rdh, payload, rdh_mem_pos) = cdp_tuple;
+        let errors_before = self.errors_reported + self.cdp_validator.errors_reported();
 
         self.do_rdh_checks(&rdh, rdh_mem_pos);
 
         if let Some(system) = &self.config.target {
             match system {
+                crate::util::config::System::ITS if self.is_other_system(&rdh) => {
+                    // Only the RDH is checked, the payload is not in the format of the target
+                }
                 crate::util::config::System::ITS => {
                     self.cdp_validator.set_current_rdh(&rdh, rdh_mem_pos);
-                    if !payload.is_empty() {
-                        self.do_payload_checks(&payload, rdh.data_format());
+                    if payload.is_empty() {
+                        // Nothing to check
+                    } else if !is_supported_data_format(rdh.data_format()) {
+                        // Already reported by the RDH checks, decoding it as another data format would only give misleading errors
+                        log::debug!(
+                            "{rdh_mem_pos:#X}: Skipping payload with unsupported data format {}",
+                            rdh.data_format()
+                        );
+                    } else if let Some(data_format) =
+                        self.payload_data_format(&rdh, &payload, rdh_mem_pos)
+                    {
+                        if is_payload_only_idle(&payload, data_format) {
+                            self.send_stats_ch
+                                .send(crate::stats::stats_controller::StatType::Warning(format!(
+                                    "{rdh_mem_pos:#X}: Payload of {} bytes has no status or data words, only padding/idle words",
+                                    payload.len()
+                                )))
+                                .unwrap();
+                            self.send_stats_ch
+                                .send(crate::stats::stats_controller::StatType::IdlePage(
+                                    rdh.link_id(),
+                                ))
+                                .unwrap();
+                        } else {
+                            match precheck {
+                                Some(precheck) => self.do_prechecked_payload_checks(
+                                    &payload,
+                                    data_format,
+                                    rdh.link_id(),
+                                    precheck,
+                                ),
+                                None => {
+                                    self.do_payload_checks(&payload, data_format, rdh.link_id())
+                                }
+                            }
+                        }
                     }
                 }
             }
         }
 
+        if let Some(cdp_details) = &mut self.cdp_details {
+            cdp_details.push(CdpDetail::new(
+                &rdh,
+                rdh_mem_pos,
+                payload.len(),
+                self.cdp_validator.take_word_counts(),
+            ));
+            if cdp_details.len() >= CDP_DETAIL_BATCH_SIZE {
+                self.flush_cdp_details();
+            }
+        }
+        let cdp_errored =
+            self.errors_reported + self.cdp_validator.errors_reported() > errors_before;
+        self.count_hbf_quality(&rdh, cdp_errored);
+        if cdp_errored {
+            if let Some(failing_cdps_ch) = &self.failing_cdps_ch {
+                failing_cdps_ch
+                    .send((
+                        T::load(&mut rdh.to_byte_slice()).unwrap(),
+                        payload,
+                        rdh_mem_pos,
+                    ))
+                    .unwrap();
+            }
+        }
         self.prev_rdhs.push(rdh);
+        self.last_rdh_mem_pos = rdh_mem_pos;
+    }
+
+    /// Counts the HBFs checked for the quality score, and those with at least one error, the first CDP checked opens an HBF even if the link starts mid-HBF.
+    fn count_hbf_quality(&mut self, rdh: &T, cdp_errored: bool) {
+        if let Some(quality_counts) = &mut self.quality_counts {
+            if rdh.pages_counter() == 0 || self.prev_rdhs.is_empty() {
+                quality_counts.hbfs += 1;
+                self.hbf_errored = false;
+            }
+            if cdp_errored && !self.hbf_errored {
+                quality_counts.errored_hbfs += 1;
+                self.hbf_errored = true;
+            }
+        }
+    }
+
+    /// Returns true if the RDH system ID is not the one of the check target, and counts the CDP as one whose payload checks are skipped.
+    ///
+    /// Always false with `--force-check-all-links`.
+    fn is_other_system(&mut self, rdh: &T) -> bool {
+        let system_id = rdh.rdh0().system_id;
+        if self.force_check_all_links || system_id == self.target_system_id {
+            return false;
+        }
+        self.other_system_cdps
+            .get_or_insert((rdh.link_id(), system_id, 0))
+            .2 += 1;
+        true
+    }
+
+    /// Returns the data format to decode the payload with, or [None] if it should not be decoded.
+    ///
+    /// If the RDH declares data format 0 but the payload [appears to be unpadded][is_unpadded_despite_format_0], the mismatch is reported once per link,
+    /// and the payload is decoded as data format 2 with `--trust-payload-format`, otherwise it is skipped.
+    ///
+    /// With `--gbt-wide` the words are always 16 bytes, so the payload is decoded as data format 0.
+    fn payload_data_format(&mut self, rdh: &T, payload: &[u8], rdh_mem_pos: u64) -> Option<u8> {
+        if self.gbt_wide {
+            return Some(0);
+        }
+        if rdh.data_format() == 0 {
+            self.check_evaluations.count(KnownCheck::PayloadPadding);
+        }
+        if !is_unpadded_despite_format_0(payload, rdh.data_format()) {
+            return Some(rdh.data_format());
+        }
+        if !self.unpadded_format_0_reported {
+            self.unpadded_format_0_reported = true;
+            let consequence = if self.trust_payload_format {
+                "decoding it as data format 2 due to --trust-payload-format"
+            } else {
+                "skipping the payloads of this link that appear to be unpadded, use --trust-payload-format to decode them as data format 2"
+            };
+            self.report_rdh_error(
+                rdh,
+                format!("[E20] payload appears to be data format 2 despite RDH declaring 0, {consequence}"),
+                rdh_mem_pos,
+            );
+        }
+        if self.trust_payload_format {
+            self.cdp_validator.set_data_format(2);
+            Some(2)
+        } else {
+            log::debug!("{rdh_mem_pos:#X}: Skipping payload that appears to be data format 2 despite RDH declaring 0");
+            None
+        }
+    }
+
+    /// Reports a link whose first RDH does not start at a packet boundary, and continues the running checks from it, so only gaps after it are reported.
+    ///
+    /// Reported as a warning, or as an error with `--strict-start`.
+    fn check_stream_start(&mut self, rdh: &T, rdh_mem_pos: u64) {
+        if rdh.packet_counter() == 0 && rdh.pages_counter() == 0 {
+            return;
+        }
+        let msg = format!(
+            "link {} starts mid-stream: packet_counter={}, pages_counter={}",
+            rdh.link_id(),
+            rdh.packet_counter(),
+            rdh.pages_counter()
+        );
+        if self.strict_start {
+            self.report_rdh_error(rdh, msg, rdh_mem_pos);
+        } else {
+            self.send_stats_ch
+                .send(crate::stats::stats_controller::StatType::Warning(format!(
+                    "{rdh_mem_pos:#X}: {msg}"
+                )))
+                .unwrap();
+        }
+        self.rdh_running_validator.start_mid_stream(rdh);
+        if rdh.pages_counter() != 0 {
+            self.cdp_validator.start_mid_hbf();
+        }
     }
 
     fn do_rdh_checks(&mut self, rdh: &T, rdh_mem_pos: u64) {
+        if self.prev_rdhs.is_empty() {
+            self.check_stream_start(rdh, rdh_mem_pos);
+        }
+        self.check_evaluations.count(KnownCheck::RdhSanity);
+        self.check_evaluations.count(KnownCheck::RdhDataFormat);
         if let Err(e) = self.rdh_sanity_validator.sanity_check(rdh) {
             self.report_rdh_error(rdh, e, rdh_mem_pos);
         }
+        // Data format 1 passes the sanity check of the field, but is not defined, so it is reported on its own
+        if rdh.data_format() == 1 {
+            self.report_rdh_error(
+                rdh,
+                "[E16] RDH data_format is 1, only data format 0 and 2 are defined, the payload can't be decoded".to_string(),
+                rdh_mem_pos,
+            );
+        }
+        if let Some(expected) = self.expect_data_format {
+            self.check_evaluations
+                .count(KnownCheck::RdhExpectedDataFormat);
+            if rdh.data_format() != expected {
+                self.report_rdh_error(
+                    rdh,
+                    format!(
+                        "[E21] RDH data_format is {}, expected data format {expected}",
+                        rdh.data_format()
+                    ),
+                    rdh_mem_pos,
+                );
+            }
+        }
         if self.config.running_checks {
+            self.check_evaluations.count(KnownCheck::RdhRunning);
+            self.check_evaluations.count(KnownCheck::SocSotAtHbfStart);
             if let Err(e) = self.rdh_running_validator.check(rdh) {
                 self.report_rdh_error(rdh, e, rdh_mem_pos);
             }
+            if let Err(w) = self
+                .rdh_running_validator
+                .check_hb_trigger_at_hbf_start(rdh)
+            {
+                self.send_stats_ch
+                    .send(crate::stats::stats_controller::StatType::Warning(format!(
+                        "{rdh_mem_pos:#X}: {w}"
+                    )))
+                    .unwrap();
+                self.send_stats_ch
+                    .send(crate::stats::stats_controller::StatType::MissingHbTrigger(
+                        rdh.link_id(),
+                    ))
+                    .unwrap();
+            }
+            if let Err(e) = self
+                .rdh_running_validator
+                .check_soc_sot_only_at_hbf_start(rdh)
+            {
+                self.report_rdh_error(rdh, e, rdh_mem_pos);
+                self.send_stats_ch
+                    .send(
+                        crate::stats::stats_controller::StatType::IllegalSocSotTrigger(
+                            rdh.link_id(),
+                        ),
+                    )
+                    .unwrap();
+            }
+            if self.config.expect_hbf_per_orbit {
+                self.check_evaluations.count(KnownCheck::OrbitPerHbf);
+                if let Err((missing_hbfs, e)) = self
+                    .rdh_running_validator
+                    .check_orbit_increments_by_one_per_hbf(rdh)
+                {
+                    self.report_rdh_error(rdh, e, rdh_mem_pos);
+                    self.send_stats_ch
+                        .send(crate::stats::stats_controller::StatType::MissingHbfs {
+                            link_id: rdh.link_id(),
+                            count: missing_hbfs,
+                        })
+                        .unwrap();
+                }
+            }
         }
     }
 
@@ -135,33 +646,258 @@ impl<T: RDH> LinkValidator<T> {
         });
         error.push_str(&format!("  current :  {rdh} <--- Error detected here\n"));
 
+        self.report_link_error(rdh.link_id(), format!("{rdh_mem_pos:#X}: {error}"));
+    }
+
+    fn report_link_error(&mut self, link_id: u8, msg: String) {
+        self.errors_reported += 1;
         self.send_stats_ch
-            .send(crate::stats::stats_controller::StatType::Error(format!(
-                "{rdh_mem_pos:#X}: {error}"
-            )))
+            .send(crate::stats::stats_controller::StatType::LinkError { link_id, msg })
             .unwrap();
     }
 
-    fn do_payload_checks(&mut self, payload: &[u8], data_format: u8) {
+    fn do_prechecked_payload_checks(
+        &mut self,
+        payload: &[u8],
+        data_format: u8,
+        link_id: u8,
+        precheck: PayloadPrecheck,
+    ) {
+        if self.gbt_wide {
+            match precheck {
+                Ok(prechecked_words) => {
+                    preprocess_payload_wide(payload)
+                        .expect("Payload was already preprocessed")
+                        .zip(prechecked_words.iter())
+                        .for_each(|(wide_word, prechecked)| {
+                            self.cdp_validator.check_wide(wide_word, Some(prechecked));
+                        });
+                    self.cdp_validator.check_payload_end();
+                }
+                Err(e) => {
+                    self.report_link_error(link_id, e);
+                    self.cdp_validator.reset_fsm();
+                }
+            }
+            return;
+        }
+        match precheck {
+            Ok(prechecked_words) => {
+                preprocess_payload(payload, data_format)
+                    .expect("Payload was already preprocessed")
+                    .zip(prechecked_words.iter())
+                    .for_each(|(gbt_word, prechecked)| {
+                        self.cdp_validator
+                            .check_prechecked(&gbt_word[..10], prechecked);
+                    });
+                self.cdp_validator.check_payload_end();
+            }
+            Err(e) => {
+                self.report_link_error(link_id, e);
+                self.cdp_validator.reset_fsm();
+            }
+        }
+    }
+
+    fn do_payload_checks(&mut self, payload: &[u8], data_format: u8, link_id: u8) {
+        if self.gbt_wide {
+            match preprocess_payload_wide(payload) {
+                Ok(wide_words) => {
+                    wide_words.for_each(|wide_word| {
+                        self.cdp_validator.check_wide(wide_word, None);
+                    });
+                    self.cdp_validator.check_payload_end();
+                }
+                Err(e) => {
+                    self.report_link_error(link_id, e);
+                    self.cdp_validator.reset_fsm();
+                }
+            }
+            return;
+        }
         match preprocess_payload(payload, data_format) {
-            Ok(gbt_word_chunks) => gbt_word_chunks.for_each(|gbt_word| {
-                self.cdp_validator.check(&gbt_word[..10]); // Take 10 bytes as flavor 0 would have additional 6 bytes of padding
-            }),
+            Ok(gbt_word_chunks) => {
+                gbt_word_chunks.for_each(|gbt_word| {
+                    self.cdp_validator.check(&gbt_word[..10]); // Take 10 bytes as flavor 0 would have additional 6 bytes of padding
+                });
+                self.cdp_validator.check_payload_end();
+            }
             Err(e) => {
-                self.send_stats_ch
-                    .send(crate::stats::stats_controller::StatType::Error(e))
-                    .unwrap();
+                self.report_link_error(link_id, e);
                 self.cdp_validator.reset_fsm();
             }
         }
     }
 }
 
+/// Does the stateless checks of each GBT word of a payload as a data word, for the stateful checks with [CdpRunningValidator::check_prechecked][crate::validators::cdp_running::CdpRunningValidator::check_prechecked].
+///
+/// Returns the same error as [preprocess_payload] if the payload can't be split into GBT words.
+pub fn precheck_payload(payload: &[u8], data_format: u8) -> PayloadPrecheck {
+    Ok(preprocess_payload(payload, data_format)?
+        .map(|gbt_word| PrecheckedDataWord::new(&gbt_word[..10]))
+        .collect())
+}
+
+/// Same as [precheck_payload], but for the 16 byte words of GBT wide mode, see [preprocess_payload_wide].
+pub fn precheck_payload_wide(payload: &[u8]) -> PayloadPrecheck {
+    Ok(preprocess_payload_wide(payload)?
+        .map(|wide_word| PrecheckedDataWord::new(&wide_word[..GBT_WORD_SIZE]))
+        .collect())
+}
+
+/// Classification of a GBT word by whether it carries any information.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GbtWordKind {
+    /// All bytes are 0xFF, as the padding at the end of a payload.
+    Padding,
+    /// All bytes are 0x00, as a GBT idle word.
+    Idle,
+    /// Any other word, expected to be a status or data word.
+    Word,
+}
+
+impl GbtWordKind {
+    /// Classifies a GBT word, or what is left of one at the end of a payload.
+    pub fn classify(gbt_word: &[u8]) -> Self {
+        if gbt_word.iter().all(|&byte| byte == 0xFF) {
+            GbtWordKind::Padding
+        } else if gbt_word.iter().all(|&byte| byte == 0x00) {
+            GbtWordKind::Idle
+        } else {
+            GbtWordKind::Word
+        }
+    }
+}
+
+/// Checks if the data format is one that payloads can be decoded with: 0 (GBT words padded to 16 bytes) or 2 (GBT words without padding).
+pub fn is_supported_data_format(data_format: u8) -> bool {
+    matches!(data_format, 0 | 2)
+}
+
+/// Checks if a payload contains no status or data words, only padding and idle words.
+///
+/// Words are 16 bytes in data format 0 (10 bytes followed by 6 bytes of 0x00 padding), and 10 bytes otherwise.
+pub fn is_payload_only_idle(payload: &[u8], data_format: u8) -> bool {
+    let word_size = if data_format == 0 { 16 } else { 10 };
+    payload
+        .chunks(word_size)
+        .all(|word| GbtWordKind::classify(&word[..word.len().min(10)]) != GbtWordKind::Word)
+}
+
+/// Size in bytes of an ITS GBT word.
+pub const GBT_WORD_SIZE: usize = 10;
+
+/// Number of GBT words at the start of a payload whose IDs are inspected by [is_unpadded_despite_format_0].
+const UNPADDED_FORMAT_0_DETECTION_WORDS: usize = 3;
+
+/// Detects the CRU misconfiguration where the RDH declares data format 0, but the GBT words of the payload are not padded, as in data format 2.
+///
+/// The payload appears to be unpadded if the 6 bytes after the first GBT word are not the 0x00 padding of data format 0,
+/// and the first GBT words at their positions without padding all have valid ITS word IDs.
+pub fn is_unpadded_despite_format_0(payload: &[u8], data_format: u8) -> bool {
+    if data_format != 0
+        || payload.len() < 16
+        || payload[GBT_WORD_SIZE..16].iter().all(|&byte| byte == 0x00)
+    {
+        return false;
+    }
+    let unpadded_words: Vec<&[u8]> = payload
+        .chunks_exact(GBT_WORD_SIZE)
+        .take(UNPADDED_FORMAT_0_DETECTION_WORDS)
+        .take_while(|gbt_word| GbtWordKind::classify(gbt_word) != GbtWordKind::Padding)
+        .collect();
+    !unpadded_words.is_empty()
+        && unpadded_words
+            .iter()
+            .all(|gbt_word| is_valid_word_id(gbt_word[9]))
+}
+
+/// Returns the data format a payload is decoded with if `--trust-payload-format` is set.
+///
+/// That is data format 2 if the payload [appears to be unpadded][is_unpadded_despite_format_0] despite data format 0, otherwise the data format of the RDH.
+pub fn trusted_data_format(payload: &[u8], data_format: u8) -> u8 {
+    if is_unpadded_despite_format_0(payload, data_format) {
+        2
+    } else {
+        data_format
+    }
+}
+
+/// Checks if the ID of a GBT word is the ID of an ITS status word or data word.
+fn is_valid_word_id(id: u8) -> bool {
+    matches!(id, 0xE0 | 0xE8 | 0xF0 | 0xE4 | 0xF8)
+        || crate::validators::data_words::DATA_WORD_SANITY_CHECKER
+            .check_any(&[0, 0, 0, 0, 0, 0, 0, 0, 0, id])
+            .is_ok()
+}
+
 /// Utility function to preprocess the payload and return an iterator over the GBT words
 pub fn preprocess_payload(
     payload: &[u8],
     data_format: u8,
 ) -> Result<impl Iterator<Item = &[u8]>, String> {
+    preprocess_payload_with_word_size(payload, data_format, GBT_WORD_SIZE)
+}
+
+/// Preprocesses a payload of GBT wide mode and returns its words of [GBT_WIDE_WORD_SIZE] bytes.
+///
+/// Words of only 0xFF at the end of the payload are padding and are not returned.
+/// Returns an error if the payload is not a whole number of words.
+pub fn preprocess_payload_wide(payload: &[u8]) -> Result<std::slice::ChunksExact<'_, u8>, String> {
+    if !payload
+        .chunks_exact(GBT_WIDE_WORD_SIZE)
+        .remainder()
+        .is_empty()
+    {
+        return Err(format!(
+            "Payload of {} bytes is not a whole number of {GBT_WIDE_WORD_SIZE} byte GBT wide words: Skipping current payload",
+            payload.len()
+        ));
+    }
+    let padding_words = payload
+        .rchunks_exact(GBT_WIDE_WORD_SIZE)
+        .take_while(|wide_word| GbtWordKind::classify(wide_word) == GbtWordKind::Padding)
+        .count();
+    Ok(
+        payload[..payload.len() - padding_words * GBT_WIDE_WORD_SIZE]
+            .chunks_exact(GBT_WIDE_WORD_SIZE),
+    )
+}
+
+/// Preprocesses a payload with [preprocess_payload_wide] in GBT wide mode, otherwise with [preprocess_payload], and returns its GBT words.
+///
+/// The ID of each word is at index 9, the words are 16 bytes in GBT wide mode and in data format 0, and 10 bytes in data format 2.
+pub fn payload_gbt_words(
+    payload: &[u8],
+    data_format: u8,
+    gbt_wide: bool,
+) -> Result<std::slice::ChunksExact<'_, u8>, String> {
+    if gbt_wide {
+        preprocess_payload_wide(payload)
+    } else {
+        preprocess_payload_with_word_size(payload, data_format, GBT_WORD_SIZE)
+    }
+}
+
+/// Preprocesses the payload and returns the words of `word_size` bytes.
+///
+/// The data format and padding rules of the ITS only apply to words of [GBT_WORD_SIZE] bytes.
+/// Other word sizes are sliced as is, and bytes at the end of the payload that don't make up a whole word are left in the remainder of the returned [ChunksExact][std::slice::ChunksExact].
+pub fn preprocess_payload_with_word_size(
+    payload: &[u8],
+    data_format: u8,
+    word_size: usize,
+) -> Result<std::slice::ChunksExact<'_, u8>, String> {
+    // An empty payload has no words in any data format, e.g. the pages of an input with only RDHs
+    if word_size != GBT_WORD_SIZE || payload.is_empty() {
+        return Ok(payload.chunks_exact(word_size));
+    }
+    if !is_supported_data_format(data_format) {
+        return Err(format!(
+            "Data format {data_format} is not supported, only data format 0 and 2: Skipping current payload"
+        ));
+    }
     // Retrieve end of payload padding from payload
     let ff_padding = payload
         .iter()
@@ -204,13 +940,13 @@ pub fn preprocess_payload(
     //    Before calling chunks_exact
     else if ff_padding.len() > 9 {
         let last_idx_before_padding = payload.len() - ff_padding.len();
-        let chunks = payload[..last_idx_before_padding].chunks_exact(10);
+        let chunks = payload[..last_idx_before_padding].chunks_exact(GBT_WORD_SIZE);
         debug_assert!(chunks.remainder().is_empty());
         debug_assert!(data_format == 2);
         chunks
     } else {
         // Simply divide into 10 byte chunks and assert that the remainder is padding bytes
-        let chunks = payload.chunks_exact(10);
+        let chunks = payload.chunks_exact(GBT_WORD_SIZE);
         debug_assert!(chunks.remainder().iter().all(|&x| x == 0xFF)); // Asserts that the payload padding is 0xFF
         debug_assert!(data_format == 2);
         chunks
@@ -218,3 +954,542 @@ pub fn preprocess_payload(
 
     Ok(gbt_word_chunks)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::stats_controller::StatType;
+    use crate::words::rdh_cru::{test_data::CORRECT_RDH_CRU_V7, RdhCRU, V7};
+
+    const IHW: [u8; 10] = [0xFF, 0x3F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE0];
+    const OB_DATA_WORD: [u8; 10] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40];
+    const TDT_PACKET_NOT_DONE: [u8; 10] =
+        [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF0];
+    const TDT_PACKET_DONE: [u8; 10] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xF0];
+    const DDW0: [u8; 10] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE4];
+
+    fn tdh(orbit: u32, continuation: bool) -> [u8; 10] {
+        let orbit = orbit.to_le_bytes();
+        let flags = if continuation { 0x58 } else { 0x18 };
+        [
+            0x03, flags, 0x00, 0x00, orbit[0], orbit[1], orbit[2], orbit[3], 0x00, 0xE8,
+        ]
+    }
+
+    /// Two HBFs of 3 pages each, where the trigger is split across the first 2 pages, and the last page only has a DDW0
+    fn cdp_fixture() -> Vec<CdpTuple<RdhCRU<V7>>> {
+        cdp_fixture_with_hbfs(2)
+    }
+
+    fn cdp_fixture_with_hbfs(hbfs: u32) -> Vec<CdpTuple<RdhCRU<V7>>> {
+        let mut cdps = Vec::new();
+        let mut mem_pos = 0;
+        for orbit in (0..hbfs).map(|hbf| 0x0B7D_D575 + hbf) {
+            let pages: [(u8, Vec<[u8; 10]>); 3] = [
+                (
+                    0,
+                    vec![IHW, tdh(orbit, false), OB_DATA_WORD, TDT_PACKET_NOT_DONE],
+                ),
+                (
+                    0,
+                    vec![IHW, tdh(orbit, true), OB_DATA_WORD, TDT_PACKET_DONE],
+                ),
+                (1, vec![DDW0]),
+            ];
+            for (pages_counter, (stop_bit, words)) in pages.into_iter().enumerate() {
+                let mut payload: Vec<u8> = words.concat();
+                payload.resize((payload.len() + 15) / 16 * 16, 0xFF);
+                let mut rdh = CORRECT_RDH_CRU_V7;
+                rdh.rdh1.orbit = orbit;
+                // Without the SOC bit, as the trigger type is the same on every page
+                rdh.rdh2.trigger_type = 0x6803;
+                rdh.rdh2.pages_counter = pages_counter as u16;
+                rdh.rdh2.stop_bit = stop_bit;
+                rdh.offset_new_packet = 64 + payload.len() as u16;
+                rdh.memory_size = rdh.offset_new_packet;
+                let next_mem_pos = mem_pos + rdh.offset_new_packet as u64;
+                cdps.push((rdh, payload, mem_pos));
+                mem_pos = next_mem_pos;
+            }
+        }
+        cdps
+    }
+
+    fn its_check_all_config() -> crate::util::config::Opt {
+        <crate::util::config::Opt as structopt::StructOpt>::from_iter([
+            "fastpasta",
+            "check",
+            "all",
+            "its",
+        ])
+    }
+
+    fn check_cdps(
+        link_validator: &mut LinkValidator<RdhCRU<V7>>,
+        cdps: Vec<CdpTuple<RdhCRU<V7>>>,
+        stats_recv: &std::sync::mpsc::Receiver<StatType>,
+    ) -> Vec<String> {
+        cdps.into_iter()
+            .for_each(|cdp| link_validator.do_checks(cdp, None));
+        stats_recv
+            .try_iter()
+            .filter_map(|stat| match stat {
+                StatType::Error(msg) | StatType::LinkError { msg, .. } => Some(msg),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_resume_from_snapshot_same_errors_as_continuous_run() {
+        let config = its_check_all_config();
+        let (send_stats, stats_recv) = std::sync::mpsc::channel();
+        let (_, data_recv) = crossbeam_channel::unbounded();
+
+        let mut continuous_validator =
+            LinkValidator::new(&config, send_stats.clone(), data_recv.clone());
+        let continuous_errors = check_cdps(&mut continuous_validator, cdp_fixture(), &stats_recv);
+
+        // Check the first half, then take a snapshot, and serialize it as if saved to a checkpoint file
+        let mut cdps = cdp_fixture();
+        let second_half = cdps.split_off(4);
+        let mut first_validator =
+            LinkValidator::new(&config, send_stats.clone(), data_recv.clone());
+        let mut resumed_errors = check_cdps(&mut first_validator, cdps, &stats_recv);
+        let snapshot = first_validator.snapshot().unwrap();
+        assert_eq!(snapshot.link_id, CORRECT_RDH_CRU_V7.link_id());
+        assert_eq!(snapshot.next_mem_pos, second_half[0].2);
+        let saved_snapshot = bincode::serialize(&snapshot).unwrap();
+
+        // Resume on the second half with a new validator
+        let mut resumed_validator =
+            LinkValidator::new(&config, send_stats.clone(), data_recv.clone());
+        resumed_validator
+            .restore(&bincode::deserialize(&saved_snapshot).unwrap())
+            .unwrap();
+        resumed_errors.extend(check_cdps(&mut resumed_validator, second_half, &stats_recv));
+        assert!(continuous_errors.is_empty(), "{continuous_errors:?}");
+        assert_eq!(resumed_errors, continuous_errors);
+
+        // Without restoring, the second half starts mid-HBF, which is a warning rather than gaps
+        let mut fresh_validator = LinkValidator::new(&config, send_stats, data_recv);
+        cdp_fixture()
+            .split_off(4)
+            .into_iter()
+            .for_each(|cdp| fresh_validator.do_checks(cdp, None));
+        let fresh_stats: Vec<StatType> = stats_recv.try_iter().collect();
+        assert!(
+            matches!(&fresh_stats[0], StatType::Warning(msg) if msg.contains("starts mid-stream"))
+        );
+        assert!(!fresh_stats
+            .iter()
+            .any(|stat| matches!(stat, StatType::Error(_) | StatType::LinkError { .. })));
+    }
+
+    #[test]
+    fn test_resume_skips_cdps_before_snapshot() {
+        let config = its_check_all_config();
+        let (send_stats, stats_recv) = std::sync::mpsc::channel();
+        let (_, data_recv) = crossbeam_channel::unbounded();
+        let mut first_validator =
+            LinkValidator::new(&config, send_stats.clone(), data_recv.clone());
+        let mut cdps = cdp_fixture();
+        cdps.truncate(4);
+        check_cdps(&mut first_validator, cdps, &stats_recv);
+        let snapshot = first_validator.snapshot().unwrap();
+
+        // A checkpoint saved while another link was behind resumes before the next CDP of this link
+        let mut resumed_validator = LinkValidator::new(&config, send_stats, data_recv);
+        resumed_validator.restore(&snapshot).unwrap();
+        cdp_fixture()
+            .into_iter()
+            .for_each(|cdp| resumed_validator.do_checks_and_report_progress(cdp, None));
+        assert_eq!(
+            resumed_validator.snapshot().unwrap().next_mem_pos,
+            cdp_fixture()
+                .last()
+                .map(|(rdh, _, mem_pos)| mem_pos + rdh.offset_to_next() as u64)
+                .unwrap()
+        );
+        assert!(!stats_recv.try_iter().any(|stat| matches!(
+            stat,
+            StatType::Error(_) | StatType::LinkError { .. } | StatType::Warning(_)
+        )));
+    }
+
+    // Checks the CDPs through the event loop of a link validator, and returns the errors in the order they were reported
+    fn run_link_validator(args: &[&str], cdps: Vec<CdpTuple<RdhCRU<V7>>>) -> Vec<String> {
+        let config = <crate::util::config::Opt as structopt::StructOpt>::from_iter(args);
+        let (send_stats, stats_recv) = std::sync::mpsc::channel();
+        let (send_data, data_recv) = crossbeam_channel::unbounded();
+        cdps.into_iter()
+            .for_each(|cdp| send_data.send(cdp).unwrap());
+        drop(send_data);
+        LinkValidator::new(&config, send_stats, data_recv).run();
+        stats_recv
+            .try_iter()
+            .filter_map(|stat| match stat {
+                StatType::Error(msg) | StatType::LinkError { msg, .. } => Some(msg),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parallel_payload_same_errors_as_sequential() {
+        // Invalid data word IDs, and a payload with too much padding
+        let corrupted_fixture = || {
+            let mut cdps = cdp_fixture_with_hbfs(100);
+            cdps[0].1[29] = 0x29;
+            cdps[3].1[29] = 0x2A;
+            cdps[100].1[29] = 0x29;
+            cdps[200].1.extend([0xFF; 16]);
+            cdps
+        };
+
+        let sequential_errors =
+            run_link_validator(&["fastpasta", "check", "all", "its"], corrupted_fixture());
+        let parallel_errors = run_link_validator(
+            &["fastpasta", "check", "all", "its", "--parallel", "payload"],
+            corrupted_fixture(),
+        );
+        assert_eq!(
+            sequential_errors
+                .iter()
+                .filter(|e| e.contains("[E70]"))
+                .count(),
+            3,
+            "{sequential_errors:?}"
+        );
+        assert!(sequential_errors
+            .iter()
+            .any(|e| e.starts_with("End of payload 0xFF padding is 22 bytes")));
+        assert_eq!(parallel_errors, sequential_errors);
+    }
+
+    #[test]
+    fn test_check_evaluations_sent_when_done() {
+        use crate::validators::check_registry::{CheckEvaluations, KnownCheck};
+        let check_evaluations = |args: &[&str]| -> CheckEvaluations {
+            let config = <crate::util::config::Opt as structopt::StructOpt>::from_iter(args);
+            let (send_stats, stats_recv) = std::sync::mpsc::channel();
+            let (send_data, data_recv) = crossbeam_channel::unbounded();
+            cdp_fixture()
+                .into_iter()
+                .for_each(|cdp| send_data.send(cdp).unwrap());
+            drop(send_data);
+            LinkValidator::new(&config, send_stats, data_recv).run();
+            stats_recv
+                .try_iter()
+                .find_map(|stat| match stat {
+                    StatType::CheckEvaluations(check_evaluations) => Some(*check_evaluations),
+                    _ => None,
+                })
+                .expect("Check evaluations should be sent when the link validator is done")
+        };
+
+        let sanity = check_evaluations(&["fastpasta", "check", "sanity"]);
+        assert_eq!(
+            sanity.get(KnownCheck::RdhSanity),
+            cdp_fixture().len() as u64
+        );
+        assert_eq!(sanity.get(KnownCheck::RdhRunning), 0);
+        assert_eq!(sanity.get(KnownCheck::IhwSanity), 0);
+
+        let all_its = check_evaluations(&["fastpasta", "check", "all", "its"]);
+        assert_eq!(
+            all_its.get(KnownCheck::RdhRunning),
+            cdp_fixture().len() as u64
+        );
+        assert!(all_its.get(KnownCheck::IhwSanity) > 0);
+        assert!(all_its.get(KnownCheck::DataWordSanity) > 0);
+        assert_eq!(
+            check_evaluations(&["fastpasta", "check", "all", "its", "--parallel", "payload"]),
+            all_its
+        );
+    }
+
+    #[test]
+    fn test_classify_gbt_words() {
+        assert_eq!(GbtWordKind::classify(&[0xFF; 10]), GbtWordKind::Padding);
+        assert_eq!(GbtWordKind::classify(&[0xFF; 6]), GbtWordKind::Padding);
+        assert_eq!(GbtWordKind::classify(&[0x00; 10]), GbtWordKind::Idle);
+        assert_eq!(GbtWordKind::classify(&IHW), GbtWordKind::Word);
+        assert_eq!(GbtWordKind::classify(&DDW0), GbtWordKind::Word);
+    }
+
+    #[test]
+    fn test_is_payload_only_idle() {
+        assert!(is_payload_only_idle(&[0xFF; 160], 2));
+        assert!(is_payload_only_idle(&[0x00; 160], 0));
+        let mut idle_then_padding = vec![0x00; 20];
+        idle_then_padding.extend([0xFF; 12]);
+        assert!(is_payload_only_idle(&idle_then_padding, 2));
+        let mut ddw0_payload = DDW0.to_vec();
+        ddw0_payload.extend([0xFF; 6]);
+        assert!(!is_payload_only_idle(&ddw0_payload, 2));
+    }
+
+    #[test]
+    fn test_all_padding_page_one_warning_no_errors() {
+        let (send_stats, stats_recv) = std::sync::mpsc::channel();
+        let (_, data_recv) = crossbeam_channel::unbounded();
+        let mut link_validator = LinkValidator::new(&its_check_all_config(), send_stats, data_recv);
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        rdh.offset_new_packet = 64 + 160;
+        rdh.memory_size = rdh.offset_new_packet;
+
+        link_validator.do_checks((rdh, vec![0xFF; 160], 0), None);
+
+        let stats: Vec<StatType> = stats_recv.try_iter().collect();
+        let errors: Vec<&String> = stats
+            .iter()
+            .filter_map(|stat| match stat {
+                StatType::Error(msg) | StatType::LinkError { msg, .. } => Some(msg),
+                _ => None,
+            })
+            .collect();
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(
+            stats
+                .iter()
+                .filter(|stat| matches!(stat, StatType::IdlePage(0)))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_data_format_1_reported_payload_not_decoded() {
+        let (send_stats, stats_recv) = std::sync::mpsc::channel();
+        let (_, data_recv) = crossbeam_channel::unbounded();
+        let mut link_validator = LinkValidator::new(&its_check_all_config(), send_stats, data_recv);
+        let mut cdps = cdp_fixture();
+        // The first HBF claims data format 1
+        cdps.iter_mut().take(3).for_each(|(rdh, _, _)| {
+            rdh.dataformat_reserved0 = crate::words::rdh::DataformatReserved(1);
+        });
+        let data_format_1_mem_pos: Vec<u64> = cdps.iter().take(3).map(|cdp| cdp.2).collect();
+
+        let errors = check_cdps(&mut link_validator, cdps, &stats_recv);
+
+        assert_eq!(errors.len(), 3, "{errors:?}");
+        for (error, mem_pos) in errors.iter().zip(data_format_1_mem_pos) {
+            assert!(
+                error.starts_with(&format!("{mem_pos:#X}: [E16] RDH data_format is 1, only data format 0 and 2 are defined, the payload can't be decoded")),
+                "{error}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_expect_data_format() {
+        let check_with_expected_format = |data_format: &str| {
+            let config = <crate::util::config::Opt as structopt::StructOpt>::from_iter([
+                "fastpasta",
+                "check",
+                "all",
+                "its",
+                "--expect-data-format",
+                data_format,
+            ]);
+            let (send_stats, stats_recv) = std::sync::mpsc::channel();
+            let (_, data_recv) = crossbeam_channel::unbounded();
+            let mut link_validator = LinkValidator::new(&config, send_stats, data_recv);
+            check_cdps(&mut link_validator, cdp_fixture(), &stats_recv)
+        };
+        // The fixture is data format 2
+        let errors = check_with_expected_format("0");
+        assert_eq!(errors.len(), cdp_fixture().len(), "{errors:?}");
+        for (error, (_, _, mem_pos)) in errors.iter().zip(cdp_fixture()) {
+            assert!(
+                error.starts_with(&format!(
+                    "{mem_pos:#X}: [E21] RDH data_format is 2, expected data format 0"
+                )),
+                "{error}"
+            );
+        }
+        assert!(check_with_expected_format("2").is_empty());
+        // Only data format 0 and 2 can be expected
+        assert!(
+            <crate::util::config::Opt as structopt::StructOpt>::from_iter_safe([
+                "fastpasta",
+                "--expect-data-format",
+                "1",
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_is_unpadded_despite_format_0() {
+        let words = [IHW, tdh(0x0B7D_D575, false), OB_DATA_WORD, TDT_PACKET_DONE];
+        let mut unpadded_payload = words.concat();
+        unpadded_payload.resize(48, 0xFF);
+        assert!(is_unpadded_despite_format_0(&unpadded_payload, 0));
+        assert_eq!(trusted_data_format(&unpadded_payload, 0), 2);
+        // As declared
+        assert!(!is_unpadded_despite_format_0(&unpadded_payload, 2));
+        let padded_payload: Vec<u8> = words
+            .iter()
+            .flat_map(|word| word.iter().copied().chain([0x00; 6]))
+            .collect();
+        assert!(!is_unpadded_despite_format_0(&padded_payload, 0));
+        assert_eq!(trusted_data_format(&padded_payload, 0), 0);
+        // The bytes after the first word are not padding, but not a valid word either
+        let mut garbage_payload = unpadded_payload.clone();
+        garbage_payload[19] = 0x99;
+        assert!(!is_unpadded_despite_format_0(&garbage_payload, 0));
+        // A single word followed by the 0xFF padding at the end of the payload
+        let mut single_word_payload = DDW0.to_vec();
+        single_word_payload.resize(16, 0xFF);
+        assert!(is_unpadded_despite_format_0(&single_word_payload, 0));
+    }
+
+    /// The fixture with data format 0 declared in every RDH, while the payloads are not padded.
+    fn unpadded_format_0_fixture() -> Vec<CdpTuple<RdhCRU<V7>>> {
+        let mut cdps = cdp_fixture();
+        cdps.iter_mut().for_each(|(rdh, _, _)| {
+            rdh.dataformat_reserved0 = crate::words::rdh::DataformatReserved(0);
+        });
+        cdps
+    }
+
+    #[test]
+    fn test_unpadded_format_0_reported_once_and_skipped() {
+        let (send_stats, stats_recv) = std::sync::mpsc::channel();
+        let (_, data_recv) = crossbeam_channel::unbounded();
+        let mut link_validator = LinkValidator::new(&its_check_all_config(), send_stats, data_recv);
+
+        let errors = check_cdps(
+            &mut link_validator,
+            unpadded_format_0_fixture(),
+            &stats_recv,
+        );
+
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert!(
+            errors[0].starts_with("0x0: [E20] payload appears to be data format 2 despite RDH declaring 0, skipping the payloads of this link that appear to be unpadded"),
+            "{}",
+            errors[0]
+        );
+    }
+
+    #[test]
+    fn test_trust_payload_format_decodes_as_format_2() {
+        let config = <crate::util::config::Opt as structopt::StructOpt>::from_iter([
+            "fastpasta",
+            "check",
+            "all",
+            "its",
+            "--trust-payload-format",
+        ]);
+        let (send_stats, stats_recv) = std::sync::mpsc::channel();
+        let (_, data_recv) = crossbeam_channel::unbounded();
+        let mut link_validator = LinkValidator::new(&config, send_stats, data_recv);
+
+        let errors = check_cdps(
+            &mut link_validator,
+            unpadded_format_0_fixture(),
+            &stats_recv,
+        );
+
+        // Only the mismatch is reported, the payloads are decoded without errors
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert!(
+            errors[0].starts_with("0x0: [E20] payload appears to be data format 2 despite RDH declaring 0, decoding it as data format 2 due to --trust-payload-format"),
+            "{}",
+            errors[0]
+        );
+    }
+
+    #[test]
+    fn test_preprocess_payload_unsupported_data_format() {
+        let mut payload = [IHW, IHW].concat();
+        payload.resize(32, 0xFF);
+        assert!(preprocess_payload(&payload, 2).is_ok());
+        for data_format in [1, 3] {
+            assert_eq!(
+                preprocess_payload(&payload, data_format).err().unwrap(),
+                format!("Data format {data_format} is not supported, only data format 0 and 2: Skipping current payload")
+            );
+        }
+    }
+
+    #[test]
+    fn test_preprocess_empty_payload() {
+        for data_format in [0, 1, 2] {
+            assert_eq!(preprocess_payload(&[], data_format).unwrap().count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_preprocess_payload_wide() {
+        let mut payload = vec![0x00; 2 * GBT_WIDE_WORD_SIZE];
+        payload.extend([0xFF; GBT_WIDE_WORD_SIZE]);
+        assert_eq!(preprocess_payload_wide(&payload).unwrap().count(), 2);
+        assert_eq!(preprocess_payload_wide(&[]).unwrap().count(), 0);
+        payload.push(0xFF);
+        assert_eq!(
+            preprocess_payload_wide(&payload).err().unwrap(),
+            "Payload of 49 bytes is not a whole number of 16 byte GBT wide words: Skipping current payload"
+        );
+    }
+
+    /// The CDPs with each GBT word of the payloads extended to the 16 bytes of GBT wide mode, with arbitrary upper bytes
+    fn gbt_wide_fixture() -> Vec<CdpTuple<RdhCRU<V7>>> {
+        let mut mem_pos = 0;
+        cdp_fixture()
+            .into_iter()
+            .map(|(mut rdh, payload, _)| {
+                let payload: Vec<u8> = preprocess_payload(&payload, 2)
+                    .unwrap()
+                    .flat_map(|gbt_word| [gbt_word, &[0xE4, 0x00, 0x00, 0x00, 0x00, 0xAA]].concat())
+                    .collect();
+                rdh.offset_new_packet = 64 + payload.len() as u16;
+                rdh.memory_size = rdh.offset_new_packet;
+                let rdh_mem_pos = mem_pos;
+                mem_pos += rdh.offset_new_packet as u64;
+                (rdh, payload, rdh_mem_pos)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_gbt_wide_lower_bytes_checked() {
+        let sequential_args = ["fastpasta", "check", "all", "its", "--gbt-wide"];
+        let parallel_args = [
+            "fastpasta",
+            "check",
+            "all",
+            "its",
+            "--gbt-wide",
+            "--parallel",
+            "payload",
+        ];
+        let errors = run_link_validator(&sequential_args, gbt_wide_fixture());
+        assert!(errors.is_empty(), "{errors:?}");
+
+        // Corrupt the ID of the TDH of the first page, the upper bytes are not checked
+        let corrupted_fixture = || {
+            let mut cdps = gbt_wide_fixture();
+            cdps[0].1[GBT_WIDE_WORD_SIZE + GBT_WORD_SIZE - 1] = 0xE9;
+            cdps
+        };
+        let errors = run_link_validator(&sequential_args, corrupted_fixture());
+        assert!(!errors.is_empty());
+        assert!(errors[0].starts_with("0x50: "), "{errors:?}");
+        assert_eq!(
+            run_link_validator(&parallel_args, corrupted_fixture()),
+            errors
+        );
+    }
+
+    #[test]
+    fn test_snapshot_before_any_cdp() {
+        let (send_stats, _stats_recv) = std::sync::mpsc::channel();
+        let (_, data_recv) = crossbeam_channel::unbounded::<CdpTuple<RdhCRU<V7>>>();
+        let link_validator = LinkValidator::new(&its_check_all_config(), send_stats, data_recv);
+        assert!(link_validator.snapshot().is_none());
+    }
+}
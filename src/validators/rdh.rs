@@ -1,6 +1,7 @@
 //! contains the [RdhCruSanityValidator] that contains all the sanity checks for an [RDH].
 //!
 //! The [RdhCruSanityValidator] is composed of multiple subvalidators, each checking an [RDH] subword.
+use crate::words::constants::MAX_BC;
 use crate::words::lib::RDH;
 use crate::words::rdh::{FeeId, Rdh0, Rdh1, Rdh2, Rdh3};
 use std::fmt::Write as _;
@@ -11,10 +12,70 @@ pub enum SpecializeChecks {
     ITS,
 }
 
+/// Valid (inclusive) ranges of the layer and stave number in a FEE ID, parsed from e.g. `layer=0..0,stave=0..1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeIdBounds {
+    /// Min and max layer.
+    pub layer: (u8, u8),
+    /// Min and max stave number.
+    pub stave: (u8, u8),
+}
+
+impl FeeIdBounds {
+    /// The FEE ID bounds of the ITS.
+    pub const ITS: Self = Self {
+        layer: (0, 6),
+        stave: (0, 47),
+    };
+}
+
+impl std::str::FromStr for FeeIdBounds {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bounds = Self::ITS;
+        for bound in s.split(',').map(str::trim) {
+            let (name, range) = bound
+                .split_once('=')
+                .ok_or_else(|| format!("Expected `name=min..max`, got '{bound}'"))?;
+            let (min, max) = range
+                .split_once("..")
+                .ok_or_else(|| format!("Expected a range `min..max`, got '{range}'"))?;
+            let min: u8 = min
+                .trim()
+                .parse()
+                .map_err(|e| format!("Invalid min in '{bound}': {e}"))?;
+            let max: u8 = max
+                .trim()
+                .parse()
+                .map_err(|e| format!("Invalid max in '{bound}': {e}"))?;
+            if min > max {
+                return Err(format!("Min is larger than max in '{bound}'"));
+            }
+            // The layer is 3 bits and the stave number 6 bits of the FEE ID
+            match name.trim() {
+                "layer" if max <= 7 => bounds.layer = (min, max),
+                "stave" if max <= 63 => bounds.stave = (min, max),
+                "layer" | "stave" => {
+                    return Err(format!(
+                        "Max is out of range of the FEE ID field in '{bound}'"
+                    ))
+                }
+                _ => {
+                    return Err(format!(
+                        "Unknown FEE ID field '{name}', valid fields are: layer, stave"
+                    ))
+                }
+            }
+        }
+        Ok(bounds)
+    }
+}
+
 /// Validator for the RDH CRU sanity checks.
 pub struct RdhCruSanityValidator<T: RDH> {
     rdh0_validator: Rdh0Validator,
-    rdh1_validator: &'static Rdh1Validator,
+    rdh1_validator: Rdh1Validator,
     rdh2_validator: &'static Rdh2Validator,
     rdh3_validator: &'static Rdh3Validator,
     _phantom: std::marker::PhantomData<T>,
@@ -31,19 +92,51 @@ impl<T: RDH> Default for RdhCruSanityValidator<T> {
 /// Const values used by the RdhCrusanityValidator
 const RDH1_VALIDATOR: Rdh1Validator = Rdh1Validator {
     valid_rdh1: Rdh1::test_new(0, 0, 0),
+    max_bc: MAX_BC,
 };
 const RDH2_VALIDATOR: Rdh2Validator = Rdh2Validator {};
 const RDH3_VALIDATOR: Rdh3Validator = Rdh3Validator {};
-const FEE_ID_SANITY_VALIDATOR: FeeIdSanityValidator = FeeIdSanityValidator::new((0, 6), (0, 47));
-
-/// Specialized for ITS
-const ITS_SYSTEM_ID: u8 = 32;
+const FEE_ID_SANITY_VALIDATOR: FeeIdSanityValidator =
+    FeeIdSanityValidator::new(FeeIdBounds::ITS.layer, FeeIdBounds::ITS.stave);
+
+/// System ID of the ITS.
+pub const ITS_SYSTEM_ID: u8 = 32;
+/// System IDs assigned to the ALICE detectors and the test system, with their names.
+const KNOWN_SYSTEMS: [(u8, &str); 19] = [
+    (3, "TPC"),
+    (4, "TRD"),
+    (5, "TOF"),
+    (6, "HMP"),
+    (7, "PHS"),
+    (8, "CPV"),
+    (10, "MCH"),
+    (15, "ZDC"),
+    (17, "TRG"),
+    (18, "EMC"),
+    (19, "TST"),
+    (ITS_SYSTEM_ID, "ITS"),
+    (33, "FDD"),
+    (34, "FT0"),
+    (35, "FV0"),
+    (36, "MFT"),
+    (37, "MID"),
+    (38, "DCS"),
+    (39, "FOC"),
+];
+
+/// Returns the name of the system with the system ID, or [None] if the system ID is not assigned to any system.
+pub fn system_name(system_id: u8) -> Option<&'static str> {
+    KNOWN_SYSTEMS
+        .iter()
+        .find(|(id, _)| *id == system_id)
+        .map(|(_, name)| *name)
+}
 impl<T: RDH> RdhCruSanityValidator<T> {
     /// Creates a new [RdhCruSanityValidator] with default values.
     pub fn new() -> Self {
         Self {
             rdh0_validator: Rdh0Validator::default(),
-            rdh1_validator: &RDH1_VALIDATOR,
+            rdh1_validator: RDH1_VALIDATOR,
             rdh2_validator: &RDH2_VALIDATOR,
             rdh3_validator: &RDH3_VALIDATOR,
             _phantom: std::marker::PhantomData,
@@ -60,7 +153,7 @@ impl<T: RDH> RdhCruSanityValidator<T> {
                     0,
                     Some(ITS_SYSTEM_ID),
                 ),
-                rdh1_validator: &RDH1_VALIDATOR,
+                rdh1_validator: RDH1_VALIDATOR,
                 rdh2_validator: &RDH2_VALIDATOR,
                 rdh3_validator: &RDH3_VALIDATOR,
                 _phantom: std::marker::PhantomData,
@@ -77,6 +170,26 @@ impl<T: RDH> RdhCruSanityValidator<T> {
         }
     }
 
+    /// Overrides the expected system ID, e.g. for lab setups with a different system ID than the target system.
+    pub fn expect_system_id(&mut self, system_id: u8) {
+        self.rdh0_validator.system_id = Some(system_id);
+    }
+
+    /// Reports system IDs that are not assigned to any system, if no system ID is expected, e.g. in strict mode without a check target.
+    pub fn forbid_unknown_system_ids(&mut self) {
+        self.rdh0_validator.forbid_unknown_system_id = true;
+    }
+
+    /// Overrides the valid ranges of the layer and stave number in the FEE ID.
+    pub fn set_fee_id_bounds(&mut self, bounds: FeeIdBounds) {
+        self.rdh0_validator.fee_id = FeeIdSanityValidator::new(bounds.layer, bounds.stave);
+    }
+
+    /// Overrides the largest valid bunch counter, [MAX_BC] by default.
+    pub fn set_max_bc(&mut self, max_bc: u16) {
+        self.rdh1_validator.max_bc = max_bc;
+    }
+
     /// Performs the sanity checks on an [RDH].
     /// Returns [Ok] or an error type containing a [String] describing the error, if the sanity check failed.
     #[inline]
@@ -208,6 +321,7 @@ struct Rdh0Validator {
     fee_id: FeeIdSanityValidator,
     priority_bit: u8,
     system_id: Option<u8>,
+    forbid_unknown_system_id: bool,
     reserved0: u16,
 }
 
@@ -230,6 +344,7 @@ impl Rdh0Validator {
             fee_id,
             priority_bit,
             system_id,
+            forbid_unknown_system_id: false,
             reserved0: 0,
         }
     }
@@ -281,6 +396,9 @@ impl Rdh0Validator {
                 err_cnt += 1;
                 write!(err_str, "system_id = {:#x} ", rdh0.system_id).unwrap();
             }
+        } else if self.forbid_unknown_system_id && system_name(rdh0.system_id).is_none() {
+            err_cnt += 1;
+            write!(err_str, "system_id = {:#x} (unknown) ", rdh0.system_id).unwrap();
         }
 
         if rdh0.reserved0 != self.reserved0 {
@@ -298,6 +416,7 @@ impl Rdh0Validator {
 /// Validator for the [RDH] subword [RDH1][Rdh1].
 struct Rdh1Validator {
     valid_rdh1: Rdh1,
+    max_bc: u16,
 }
 impl Rdh1Validator {
     pub fn sanity_check(&self, rdh1: &Rdh1) -> Result<(), String> {
@@ -313,8 +432,7 @@ impl Rdh1Validator {
             )
             .unwrap();
         }
-        // Max bunch counter is 0xdeb
-        if rdh1.bc() > 0xdeb {
+        if rdh1.bc() > self.max_bc {
             err_cnt += 1;
             write!(err_str, "{} = {:#x} ", stringify!(bc), rdh1.bc()).unwrap();
         }
@@ -389,6 +507,7 @@ impl Rdh3Validator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::words::constants::BC_FIELD_MAX;
     use crate::words::rdh_cru::test_data::{CORRECT_RDH_CRU_V6, CORRECT_RDH_CRU_V7};
 
     #[test]
@@ -665,7 +784,7 @@ mod tests {
     #[test]
     fn validate_rdh_cru_v7() {
         let mut validator = RdhCruSanityValidator::new();
-        validator.rdh1_validator = &RDH1_VALIDATOR;
+        validator.rdh1_validator = RDH1_VALIDATOR;
         let res = validator.sanity_check(&CORRECT_RDH_CRU_V7);
         assert!(res.is_ok());
     }
@@ -719,6 +838,85 @@ mod tests {
         assert!(res.is_err());
     }
 
+    #[test]
+    fn validate_rdh_cru_v7_custom_system_id() {
+        let mut validator = RdhCruSanityValidator::with_specialization(SpecializeChecks::ITS);
+        validator.expect_system_id(0x99);
+        let mut rdh_cru = CORRECT_RDH_CRU_V7;
+        rdh_cru.rdh0.system_id = 0x99;
+        assert!(validator.sanity_check(&rdh_cru).is_ok());
+        rdh_cru.rdh0.system_id = ITS_SYSTEM_ID;
+        assert!(validator.sanity_check(&rdh_cru).is_err());
+    }
+
+    #[test]
+    fn invalidate_rdh_cru_v7_unknown_system_id() {
+        let mut validator = RdhCruSanityValidator::default();
+        validator.forbid_unknown_system_ids();
+        let mut rdh_cru = CORRECT_RDH_CRU_V7;
+        assert!(validator.sanity_check(&rdh_cru).is_ok());
+        rdh_cru.rdh0.system_id = 0x99;
+        let err = validator.sanity_check(&rdh_cru).unwrap_err();
+        assert!(err.contains("system_id = 0x99 (unknown)"), "{err}");
+        // Not checked without forbidding unknown system IDs
+        assert!(RdhCruSanityValidator::default()
+            .sanity_check(&rdh_cru)
+            .is_ok());
+    }
+
+    #[test]
+    fn custom_fee_id_bounds_flip_acceptance() {
+        let mut validator = RdhCruSanityValidator::with_specialization(SpecializeChecks::ITS);
+        let mut rdh_cru = CORRECT_RDH_CRU_V7;
+        // Layer 5, stave 42
+        assert!(validator.sanity_check(&rdh_cru).is_ok());
+        validator.set_fee_id_bounds("layer=0..0,stave=0..1".parse().unwrap());
+        assert!(validator.sanity_check(&rdh_cru).is_err());
+        rdh_cru.rdh0.fee_id = FeeId(0x0001);
+        assert!(validator.sanity_check(&rdh_cru).is_ok());
+
+        // Layer 7 and stave 48 are out of the ITS bounds
+        rdh_cru.rdh0.fee_id = FeeId(0x7030);
+        validator.set_fee_id_bounds(FeeIdBounds::ITS);
+        assert!(validator.sanity_check(&rdh_cru).is_err());
+        validator.set_fee_id_bounds("layer=7..7, stave=0..63".parse().unwrap());
+        assert!(validator.sanity_check(&rdh_cru).is_ok());
+    }
+
+    #[test]
+    fn custom_max_bc_flips_acceptance() {
+        let mut validator = RdhCruSanityValidator::with_specialization(SpecializeChecks::ITS);
+        let mut rdh_cru = CORRECT_RDH_CRU_V7;
+        rdh_cru.rdh1 = Rdh1::test_new(MAX_BC, 0, 0);
+        assert!(validator.sanity_check(&rdh_cru).is_ok());
+        rdh_cru.rdh1 = Rdh1::test_new(MAX_BC + 1, 0, 0);
+        assert!(validator
+            .sanity_check(&rdh_cru)
+            .unwrap_err()
+            .contains("bc = 0xdec"));
+        validator.set_max_bc(BC_FIELD_MAX);
+        assert!(validator.sanity_check(&rdh_cru).is_ok());
+        validator.set_max_bc(0x100);
+        rdh_cru.rdh1 = Rdh1::test_new(0x101, 0, 0);
+        assert!(validator.sanity_check(&rdh_cru).is_err());
+    }
+
+    #[test]
+    fn parse_fee_id_bounds() {
+        assert_eq!(
+            "stave=3..5".parse::<FeeIdBounds>(),
+            Ok(FeeIdBounds {
+                layer: FeeIdBounds::ITS.layer,
+                stave: (3, 5)
+            })
+        );
+        assert!("layer=2..1".parse::<FeeIdBounds>().is_err());
+        assert!("layer=0..8".parse::<FeeIdBounds>().is_err());
+        assert!("stave=0..64".parse::<FeeIdBounds>().is_err());
+        assert!("fiber=0..1".parse::<FeeIdBounds>().is_err());
+        assert!("layer=0".parse::<FeeIdBounds>().is_err());
+    }
+
     #[test]
     fn validate_rdh_cru_v6() {
         let mut validator = RdhCruSanityValidator::default();
@@ -4,6 +4,20 @@ use crate::words::{
     rdh::{Rdh1, Rdh2},
 };
 use std::fmt::Write;
+
+/// Snapshot of the state of a [RdhCruRunningChecker], see [RdhCruRunningChecker::snapshot].
+///
+/// [RDH]s are stored as their raw bytes, to not depend on the [RDH] version.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RdhRunningState {
+    expect_pages_counter: u16,
+    first_rdh_cru: Option<Vec<u8>>,
+    second_rdh_cru: Option<Vec<u8>>,
+    expect_pages_counter_increment: u16,
+    last_rdh_cru: Option<Vec<u8>>,
+    last_hbf_orbit: Option<u32>,
+}
+
 /// Performs running (stateful) checks on [RDH]s.
 pub struct RdhCruRunningChecker<T: RDH> {
     expect_pages_counter: u16,
@@ -12,6 +26,8 @@ pub struct RdhCruRunningChecker<T: RDH> {
     second_rdh_cru: Option<T>,
     expect_pages_counter_increment: u16,
     last_rdh_cru: Option<T>,
+    // Orbit of the last RDH opening an HBF
+    last_hbf_orbit: Option<u32>,
 }
 
 impl<T: RDH> Default for RdhCruRunningChecker<T> {
@@ -29,9 +45,48 @@ impl<T: RDH> RdhCruRunningChecker<T> {
             second_rdh_cru: None,
             expect_pages_counter_increment: 1,
             last_rdh_cru: None,
+            last_hbf_orbit: None,
         }
     }
 
+    /// Takes a snapshot of the state carried across CDPs, which can be restored with [RdhCruRunningChecker::restore].
+    pub fn snapshot(&self) -> RdhRunningState {
+        let rdh_bytes = |rdh: &Option<T>| rdh.as_ref().map(|rdh| rdh.to_byte_slice().to_vec());
+        RdhRunningState {
+            expect_pages_counter: self.expect_pages_counter,
+            first_rdh_cru: rdh_bytes(&self.first_rdh_cru),
+            second_rdh_cru: rdh_bytes(&self.second_rdh_cru),
+            expect_pages_counter_increment: self.expect_pages_counter_increment,
+            last_rdh_cru: rdh_bytes(&self.last_rdh_cru),
+            last_hbf_orbit: self.last_hbf_orbit,
+        }
+    }
+
+    /// Restores the state from a snapshot taken with [RdhCruRunningChecker::snapshot].
+    pub fn restore(&mut self, state: &RdhRunningState) -> Result<(), std::io::Error> {
+        let load_rdh = |bytes: &Option<Vec<u8>>| -> Result<Option<T>, std::io::Error> {
+            bytes
+                .as_ref()
+                .map(|bytes| T::load(&mut bytes.as_slice()))
+                .transpose()
+        };
+        self.expect_pages_counter = state.expect_pages_counter;
+        self.first_rdh_cru = load_rdh(&state.first_rdh_cru)?;
+        self.second_rdh_cru = load_rdh(&state.second_rdh_cru)?;
+        self.expect_pages_counter_increment = state.expect_pages_counter_increment;
+        self.last_rdh_cru = load_rdh(&state.last_rdh_cru)?;
+        self.last_hbf_orbit = state.last_hbf_orbit;
+        Ok(())
+    }
+
+    /// Continues the checks from the first RDH of an input that does not start at a packet boundary, as if the pages before it were checked.
+    ///
+    /// Must be called before the first [RdhCruRunningChecker::check], so that a first RDH with a nonzero `pages_counter` is not reported as a gap.
+    pub fn start_mid_stream(&mut self, first_rdh: &T) {
+        debug_assert!(self.first_rdh_cru.is_none());
+        self.expect_pages_counter = first_rdh.pages_counter();
+    }
+
     /// Does running checks across CDPs maintaining state based on the previous RDH
     ///
     /// No checks that are dependent on CDP payload state are done here (instead see cdp_running.rs)
@@ -41,8 +96,17 @@ impl<T: RDH> RdhCruRunningChecker<T> {
             self.first_rdh_cru = Some(T::load(&mut rdh.to_byte_slice()).unwrap());
         } else if self.second_rdh_cru.is_none() {
             self.second_rdh_cru = Some(T::load(&mut rdh.to_byte_slice()).unwrap());
-            self.expect_pages_counter_increment =
-                self.second_rdh_cru.as_ref().unwrap().rdh2().pages_counter;
+            let (first_pages_counter, first_stop_bit) = self
+                .first_rdh_cru
+                .as_ref()
+                .map_or((0, 0), |first| (first.pages_counter(), first.stop_bit()));
+            if first_pages_counter == 0 {
+                self.expect_pages_counter_increment = rdh.pages_counter();
+            } else if first_stop_bit == 0 {
+                // Started mid-HBF, the increment is the difference to the first page
+                self.expect_pages_counter_increment =
+                    rdh.pages_counter().wrapping_sub(first_pages_counter);
+            }
         }
 
         let mut err_str = String::new();
@@ -132,6 +196,69 @@ impl<T: RDH> RdhCruRunningChecker<T> {
         Ok(())
     }
 
+    /// Checks that a page opening an HBF (`pages_counter == 0`) has the HB trigger bit set.
+    ///
+    /// A missing HB trigger bit indicates that the CRU was fed wrong trigger information, it is reported as a warning.
+    #[inline]
+    pub fn check_hb_trigger_at_hbf_start(&self, rdh: &T) -> Result<(), String> {
+        if rdh.pages_counter() == 0 && !rdh.rdh2().is_hb_trigger() {
+            let tmp_trigger_type = rdh.trigger_type();
+            return Err(format!(
+                "HBF opened without HB trigger bit set, trigger_type = {tmp_trigger_type:#X}."
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks that only a page opening an HBF (`pages_counter == 0`) has the SOC or SOT trigger bit set.
+    #[inline]
+    pub fn check_soc_sot_only_at_hbf_start(&self, rdh: &T) -> Result<(), String> {
+        let rdh2 = rdh.rdh2();
+        if rdh.pages_counter() != 0 && (rdh2.is_soc_trigger() || rdh2.is_sot_trigger()) {
+            let tmp_trigger_type = rdh.trigger_type();
+            let tmp_pages_counter = rdh.pages_counter();
+            return Err(format!(
+                "[E13] SOC/SOT trigger bit set on page {tmp_pages_counter} that does not open an HBF, trigger_type = {tmp_trigger_type:#X}."
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks that the orbit increments by exactly 1 between RDHs opening an HBF (`pages_counter == 0`).
+    ///
+    /// In continuous mode each HBF spans exactly one orbit, so a larger increment means HBFs are missing.
+    /// On error, the number of missing HBFs is returned along with the error message.
+    #[inline]
+    pub fn check_orbit_increments_by_one_per_hbf(&mut self, rdh: &T) -> Result<(), (u32, String)> {
+        if rdh.pages_counter() != 0 {
+            return Ok(());
+        }
+        let current_orbit = rdh.rdh1().orbit;
+        let last_hbf_orbit = match self.last_hbf_orbit.replace(current_orbit) {
+            Some(orbit) => orbit,
+            None => return Ok(()),
+        };
+        match current_orbit.wrapping_sub(last_hbf_orbit) {
+            1 => Ok(()),
+            // Anything larger is considered the orbit going backwards (or wrapping around)
+            delta @ 2..=0x7FFF_FFFF => {
+                let missing_hbfs = delta - 1;
+                Err((
+                    missing_hbfs,
+                    format!(
+                        "[E14] Orbit skipped {missing_hbfs} HBF(s), previous HBF orbit: {last_hbf_orbit:#X}, current: {current_orbit:#X}."
+                    ),
+                ))
+            }
+            _ => Err((
+                0,
+                format!(
+                    "[E14] Orbit did not increment by 1 between HBFs, previous HBF orbit: {last_hbf_orbit:#X}, current: {current_orbit:#X}."
+                ),
+            )),
+        }
+    }
+
     /// If the previous stop bit was 1, the current RDH's orbit counter should be different
     #[inline]
     fn check_orbit_counter_changes(&self, rdh1: &Rdh1) -> Result<(), String> {
@@ -238,6 +365,28 @@ mod tests {
         assert!(res.is_ok());
     }
 
+    #[test]
+    fn test_snapshot_restore() {
+        let mut rdh_cru_checker = RdhCruRunningChecker::<RdhCRU<V7>>::new();
+        let rdh_1 = RdhCRU::<V7>::load(&mut CORRECT_RDH_CRU_V7.to_byte_slice()).unwrap();
+        let rdh_2 = RdhCRU::<V7>::load(&mut CORRECT_RDH_CRU_V7_NEXT.to_byte_slice()).unwrap();
+        let rdh_3_stop =
+            RdhCRU::<V7>::load(&mut CORRECT_RDH_CRU_V7_NEXT_NEXT_STOP.to_byte_slice()).unwrap();
+        assert!(rdh_cru_checker.check(&rdh_1).is_ok());
+        assert!(rdh_cru_checker.check(&rdh_2).is_ok());
+
+        let mut restored_checker = RdhCruRunningChecker::<RdhCRU<V7>>::new();
+        restored_checker
+            .restore(&rdh_cru_checker.snapshot())
+            .unwrap();
+        assert_eq!(restored_checker.snapshot(), rdh_cru_checker.snapshot());
+        // Continues where the first checker left off, a new checker would flag the page counter
+        assert!(restored_checker.check(&rdh_3_stop).is_ok());
+        assert!(RdhCruRunningChecker::<RdhCRU<V7>>::new()
+            .check(&rdh_3_stop)
+            .is_err());
+    }
+
     #[test]
     fn test_invalid_first_second_is_same() {
         let mut rdh_cru_checker = RdhCruRunningChecker::<RdhCRU<V7>>::new();
@@ -281,6 +430,29 @@ mod tests {
         println!("{:?}", res);
     }
 
+    #[test]
+    fn test_start_mid_stream() {
+        let rdh_2 = RdhCRU::<V7>::load(&mut CORRECT_RDH_CRU_V7_NEXT.to_byte_slice()).unwrap();
+        let rdh_3_stop =
+            RdhCRU::<V7>::load(&mut CORRECT_RDH_CRU_V7_NEXT_NEXT_STOP.to_byte_slice()).unwrap();
+        let mut next_hbf = RdhCRU::<V7>::load(&mut CORRECT_RDH_CRU_V7.to_byte_slice()).unwrap();
+        next_hbf.rdh1.orbit += 1;
+        let mut next_hbf_2 = RdhCRU::<V7>::load(&mut rdh_2.to_byte_slice()).unwrap();
+        next_hbf_2.rdh1.orbit += 1;
+        // Without knowing the input starts mid-HBF, the first page is a gap
+        assert!(RdhCruRunningChecker::<RdhCRU<V7>>::new()
+            .check(&rdh_2)
+            .is_err());
+
+        let mut rdh_cru_checker = RdhCruRunningChecker::<RdhCRU<V7>>::new();
+        rdh_cru_checker.start_mid_stream(&rdh_2);
+        for rdh in [&rdh_2, &rdh_3_stop, &next_hbf, &next_hbf_2] {
+            assert_eq!(rdh_cru_checker.check(rdh), Ok(()));
+        }
+        // A genuine gap later on is still reported
+        assert!(rdh_cru_checker.check(&next_hbf_2).is_err());
+    }
+
     #[test]
     fn test_invalid_orbit_same_after_stop() {
         let mut rdh_cru_checker = RdhCruRunningChecker::<RdhCRU<V7>>::new();
@@ -354,4 +526,140 @@ mod tests {
         assert!(err_str.contains(&"Detector"));
         assert!(err_str.contains(&"FeeId"));
     }
+
+    /// Loads a copy of [CORRECT_RDH_CRU_V7] with the given trigger type and pages counter
+    fn rdh_with_trigger(trigger_type: u32, pages_counter: u16) -> RdhCRU<V7> {
+        let mut rdh = RdhCRU::<V7>::load(&mut CORRECT_RDH_CRU_V7.to_byte_slice()).unwrap();
+        rdh.rdh2.trigger_type = trigger_type;
+        rdh.rdh2.pages_counter = pages_counter;
+        rdh
+    }
+
+    /// Loads a copy of [CORRECT_RDH_CRU_V7] with the given orbit and pages counter
+    fn rdh_with_orbit(orbit: u32, pages_counter: u16) -> RdhCRU<V7> {
+        let mut rdh = RdhCRU::<V7>::load(&mut CORRECT_RDH_CRU_V7.to_byte_slice()).unwrap();
+        rdh.rdh1.orbit = orbit;
+        rdh.rdh2.pages_counter = pages_counter;
+        rdh
+    }
+
+    #[test]
+    fn test_orbit_increments_by_one_per_hbf() {
+        let mut rdh_cru_checker = RdhCruRunningChecker::<RdhCRU<V7>>::new();
+        // Two pages per HBF, wrapping around at the max orbit
+        for orbit in [0xFFFF_FFFE, 0xFFFF_FFFF, 0, 1] {
+            for pages_counter in 0..2 {
+                let rdh = rdh_with_orbit(orbit, pages_counter);
+                assert!(rdh_cru_checker
+                    .check_orbit_increments_by_one_per_hbf(&rdh)
+                    .is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_skipped_orbit_between_hbfs() {
+        let mut rdh_cru_checker = RdhCruRunningChecker::<RdhCRU<V7>>::new();
+        let stream = [
+            (0x10, 0),
+            (0x10, 1),
+            (0x11, 0),
+            (0x11, 1),
+            (0x13, 0),
+            (0x13, 1),
+        ];
+        let errors: Vec<(u32, String)> = stream
+            .into_iter()
+            .filter_map(|(orbit, pages_counter)| {
+                rdh_cru_checker
+                    .check_orbit_increments_by_one_per_hbf(&rdh_with_orbit(orbit, pages_counter))
+                    .err()
+            })
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0],
+            (
+                1,
+                "[E14] Orbit skipped 1 HBF(s), previous HBF orbit: 0x11, current: 0x13."
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_orbit_not_incremented_between_hbfs() {
+        let mut rdh_cru_checker = RdhCruRunningChecker::<RdhCRU<V7>>::new();
+        let rdh = rdh_with_orbit(0x10, 0);
+        assert!(rdh_cru_checker
+            .check_orbit_increments_by_one_per_hbf(&rdh)
+            .is_ok());
+        let (missing_hbfs, err) = rdh_cru_checker
+            .check_orbit_increments_by_one_per_hbf(&rdh)
+            .unwrap_err();
+        assert_eq!(missing_hbfs, 0);
+        assert!(err.contains("did not increment"));
+        let (missing_hbfs, _) = rdh_cru_checker
+            .check_orbit_increments_by_one_per_hbf(&rdh_with_orbit(0x5, 0))
+            .unwrap_err();
+        assert_eq!(missing_hbfs, 0);
+    }
+
+    const HB: u32 = 0b10;
+    const SOT: u32 = 0b1000_0000;
+    const SOC: u32 = 0b10_0000_0000;
+    const TF: u32 = 0b1000_0000_0000;
+
+    #[test]
+    fn test_hb_trigger_at_hbf_start() {
+        let rdh_cru_checker = RdhCruRunningChecker::<RdhCRU<V7>>::new();
+        let opening_pages = [HB, HB | TF, HB | SOC | TF, HB | SOT];
+        for trigger_type in opening_pages {
+            let rdh = rdh_with_trigger(trigger_type, 0);
+            assert!(rdh_cru_checker.check_hb_trigger_at_hbf_start(&rdh).is_ok());
+        }
+        // Only pages opening an HBF need the HB trigger bit
+        let rdh = rdh_with_trigger(TF, 1);
+        assert!(rdh_cru_checker.check_hb_trigger_at_hbf_start(&rdh).is_ok());
+    }
+
+    #[test]
+    fn test_missing_hb_trigger_at_hbf_start() {
+        let rdh_cru_checker = RdhCruRunningChecker::<RdhCRU<V7>>::new();
+        let rdh = rdh_with_trigger(SOC | TF, 0);
+        let res = rdh_cru_checker.check_hb_trigger_at_hbf_start(&rdh);
+        assert_eq!(
+            res.unwrap_err(),
+            "HBF opened without HB trigger bit set, trigger_type = 0xA00."
+        );
+    }
+
+    #[test]
+    fn test_soc_sot_only_at_hbf_start() {
+        let rdh_cru_checker = RdhCruRunningChecker::<RdhCRU<V7>>::new();
+        let rdh = rdh_with_trigger(HB | SOC | SOT, 0);
+        assert!(rdh_cru_checker
+            .check_soc_sot_only_at_hbf_start(&rdh)
+            .is_ok());
+        let rdh = rdh_with_trigger(HB | TF, 1);
+        assert!(rdh_cru_checker
+            .check_soc_sot_only_at_hbf_start(&rdh)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_soc_sot_on_page_not_opening_hbf() {
+        let rdh_cru_checker = RdhCruRunningChecker::<RdhCRU<V7>>::new();
+        let rdh = rdh_with_trigger(HB | SOC, 1);
+        let res = rdh_cru_checker.check_soc_sot_only_at_hbf_start(&rdh);
+        assert_eq!(
+            res.unwrap_err(),
+            "[E13] SOC/SOT trigger bit set on page 1 that does not open an HBF, trigger_type = 0x202."
+        );
+        let rdh = rdh_with_trigger(HB | SOT, 2);
+        assert!(rdh_cru_checker
+            .check_soc_sot_only_at_hbf_start(&rdh)
+            .unwrap_err()
+            .contains("page 2"));
+    }
 }
@@ -2,6 +2,7 @@
 //!
 //! Each validator is aggregated by the [StatusWordSanityChecker] struct.
 
+use crate::words::constants::MAX_BC;
 use crate::words::status_words::{Ddw0, Ihw, StatusWord, Tdh, Tdt};
 use std::fmt::Write;
 
@@ -26,6 +27,12 @@ impl StatusWordSanityChecker {
         }
     }
 
+    /// Overrides the largest valid trigger bunch counter of the [TDH][Tdh], [MAX_BC] by default.
+    pub const fn with_max_bc(mut self, max_bc: u16) -> Self {
+        self.tdh_validator.max_bc = max_bc;
+        self
+    }
+
     /// Checks if argument is a valid [IHW][Ihw] status word.
     pub fn sanity_check_ihw(&self, ihw: &Ihw) -> Result<(), String> {
         self.ihw_validator.sanity_check(ihw)
@@ -75,9 +82,13 @@ impl StatusWordValidator<Ihw> for IhwValidator {
     }
 }
 
-const TDH_VALIDATOR: TdhValidator = TdhValidator { valid_id: 0xE8 };
+const TDH_VALIDATOR: TdhValidator = TdhValidator {
+    valid_id: 0xE8,
+    max_bc: MAX_BC,
+};
 struct TdhValidator {
     valid_id: u8,
+    max_bc: u16,
 }
 impl StatusWordValidator<Tdh> for TdhValidator {
     fn sanity_check(&self, tdh: &Tdh) -> Result<(), String> {
@@ -106,6 +117,16 @@ impl StatusWordValidator<Tdh> for TdhValidator {
         //if tdh.trigger_orbit == 0
 
         // Trigger Bunch Crossing ID check
+        if tdh.trigger_bc() > self.max_bc {
+            err_cnt += 1;
+            write!(
+                err_str,
+                "trigger_bc above {:#X}: {:#X} ",
+                self.max_bc,
+                tdh.trigger_bc()
+            )
+            .unwrap();
+        }
 
         // Trigger Type check (12 lowest bits of trigger type received from CTP)
         // All values are valid except 0x0
@@ -227,6 +248,25 @@ mod tests {
         assert!(TDH_VALIDATOR.sanity_check(&tdh_bad).is_err());
     }
 
+    #[test]
+    fn test_tdh_validator_max_bc() {
+        // trigger_bc 0xDEC, one above the last bunch crossing
+        let raw_data_tdh = [0x03, 0x1A, 0xEC, 0x0D, 0x75, 0xD5, 0x7D, 0x0B, 0x00, 0xE8];
+        let tdh = Tdh::load(&mut raw_data_tdh.as_slice()).unwrap();
+        assert_eq!(
+            STATUS_WORD_SANITY_CHECKER
+                .sanity_check_tdh(&tdh)
+                .unwrap_err(),
+            "trigger_bc above 0xDEB: 0xDEC "
+        );
+        let checker = StatusWordSanityChecker::new().with_max_bc(0xFFF);
+        assert!(checker.sanity_check_tdh(&tdh).is_ok());
+        let checker = StatusWordSanityChecker::new().with_max_bc(0x100);
+        let raw_data_tdh = [0x03, 0x1A, 0x01, 0x01, 0x75, 0xD5, 0x7D, 0x0B, 0x00, 0xE8];
+        let tdh = Tdh::load(&mut raw_data_tdh.as_slice()).unwrap();
+        assert!(checker.sanity_check_tdh(&tdh).is_err());
+    }
+
     #[test]
     #[should_panic]
     fn test_tdh_validator_bad_id() {
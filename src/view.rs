@@ -1,4 +1,8 @@
 //! Contains the view generators for the human readable data views.
-mod hbf_view;
+pub(crate) mod concurrent_view;
+mod count_cdps;
+pub(crate) mod hbf_view;
 pub mod lib;
+mod list_links;
+mod raw_words_view;
 mod rdh_view;
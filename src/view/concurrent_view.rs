@@ -0,0 +1,128 @@
+//! Contains the [ConcurrentHbfView] that views the first HBFs of the input while the checks run, set with `--with-view hbf`.
+//!
+//! The CDPs are borrowed from each chunk before it is passed to the checks, and their view is rendered into a buffer.
+//! The buffer is written in one block when `--view-limit` HBFs are viewed or the input ends, so the view is not interleaved with the log messages.
+use super::hbf_view::write_hbf_view;
+use crate::input::data_wrapper::CdpChunk;
+use crate::util::config::HbfViewOptions;
+use crate::validators::its_payload_fsm_cont::ItsPayloadFsmContinuous;
+use crate::words::lib::RDH;
+use std::io::Write;
+
+/// Renders the HBF view of the first HBFs of the input, and writes it to stderr or a file once.
+pub(crate) struct ConcurrentHbfView {
+    /// HBFs left to view, counted by their stop pages.
+    hbfs_left: u32,
+    gbt_wide: bool,
+    its_payload_fsm_cont: ItsPayloadFsmContinuous,
+    rendered: Vec<u8>,
+    /// File to write the view to, stderr if not set.
+    output: Option<std::path::PathBuf>,
+    written: bool,
+}
+
+impl ConcurrentHbfView {
+    /// Creates a [ConcurrentHbfView] of the first `view_limit` HBFs, the payload words are 16 byte GBT wide words if `gbt_wide` is set.
+    pub(crate) fn new(
+        view_limit: std::num::NonZeroU32,
+        gbt_wide: bool,
+        output: Option<std::path::PathBuf>,
+    ) -> Self {
+        Self {
+            hbfs_left: view_limit.get(),
+            gbt_wide,
+            its_payload_fsm_cont: ItsPayloadFsmContinuous::default(),
+            rendered: Vec::new(),
+            output,
+            written: false,
+        }
+    }
+
+    /// Renders the CDPs of a chunk up to the stop page of the last HBF to view, and writes the view once the last HBF is rendered.
+    ///
+    /// Payloads that can't be split into GBT words are left out of the view, they are reported by the checks.
+    pub(crate) fn view<T: RDH>(&mut self, cdp_chunk: &CdpChunk<T>) -> Result<(), std::io::Error> {
+        if self.hbfs_left == 0 {
+            return Ok(());
+        }
+        let mut cdps_to_view = 0;
+        for rdh in cdp_chunk.rdh_slice() {
+            if self.hbfs_left == 0 {
+                break;
+            }
+            cdps_to_view += 1;
+            if rdh.stop_bit() == 1 {
+                self.hbfs_left -= 1;
+            }
+        }
+        write_hbf_view(
+            cdp_chunk.into_iter().take(cdps_to_view),
+            &mut self.its_payload_fsm_cont,
+            &HbfViewOptions::default(),
+            self.gbt_wide,
+            &mut self.rendered,
+            |_| {},
+        )?;
+        if self.hbfs_left == 0 {
+            self.write()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the view if it was not written yet, e.g. if the input ended before the last HBF to view.
+    pub(crate) fn finish(mut self) -> Result<(), std::io::Error> {
+        self.write()
+    }
+
+    fn write(&mut self) -> Result<(), std::io::Error> {
+        if self.written {
+            return Ok(());
+        }
+        self.written = true;
+        let rendered = std::mem::take(&mut self.rendered);
+        match &self.output {
+            Some(path) => std::fs::write(path, rendered),
+            None => std::io::stderr().lock().write_all(&rendered),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh_cru::test_data::CORRECT_RDH_CRU_V7;
+
+    #[test]
+    fn test_view_first_hbfs() {
+        let mut cdp_chunk = crate::view::hbf_view::tests::cdp_chunk_fixture();
+        let mut stop_rdh = CORRECT_RDH_CRU_V7;
+        stop_rdh.rdh2.stop_bit = 1;
+        stop_rdh.rdh2.pages_counter = 1;
+        cdp_chunk.push(stop_rdh, Vec::new(), 0x70);
+        cdp_chunk.push(CORRECT_RDH_CRU_V7, Vec::new(), 0xB0);
+        let output = std::env::temp_dir().join(format!(
+            "fastpasta_{}_concurrent_view.txt",
+            std::process::id()
+        ));
+        let mut view = ConcurrentHbfView::new(
+            std::num::NonZeroU32::new(1).unwrap(),
+            false,
+            Some(output.clone()),
+        );
+        view.view(&cdp_chunk).unwrap();
+        // The chunk is only borrowed, and the view is written as soon as the first HBF is viewed
+        assert_eq!(cdp_chunk.len(), 3);
+        let rendered = std::fs::read_to_string(&output).unwrap();
+        let rdh_lines: Vec<&str> = rendered
+            .lines()
+            .filter(|line| line.contains(": RDH v7"))
+            .collect();
+        assert_eq!(rdh_lines.len(), 2, "{rendered}");
+        assert!(rdh_lines[1].starts_with("      70: RDH v7"), "{rendered}");
+        // Nothing more is viewed or written
+        view.view(&cdp_chunk).unwrap();
+        view.finish().unwrap();
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), rendered);
+        std::fs::remove_file(output).unwrap();
+    }
+}
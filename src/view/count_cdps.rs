@@ -0,0 +1,35 @@
+//! Contains the [count_cdps] function that scans only the RDHs of the input and counts the CDPs matching an [RdhPredicate][crate::util::rdh_predicate::RdhPredicate].
+use crate::input::{bufreader_wrapper::BufferedReaderWrapper, input_scanner::InputScanner};
+use crate::util::config::Count;
+use crate::words::lib::RDH;
+use std::io::Write;
+
+/// Scans the RDHs of the input, skipping the payloads, and prints how many of the CDPs match the expression of [Count::predicate].
+///
+/// If a filter is set, only the CDPs kept by the filter are counted.
+pub(crate) fn count_cdps<T: RDH>(
+    mut scanner: InputScanner<impl BufferedReaderWrapper + ?Sized>,
+    opts: &Count,
+) -> Result<(), std::io::Error> {
+    let (mut matching, mut total) = (0_u64, 0_u64);
+    let scan_result = loop {
+        match scanner.load_rdh_skip_payload::<T>() {
+            Ok(rdh) => {
+                total += 1;
+                if opts.predicate.eval(&rdh) {
+                    matching += 1;
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break Ok(()),
+            Err(e) => break Err(e),
+        }
+    };
+    scanner.flush_stats();
+
+    writeln!(
+        std::io::stdout().lock(),
+        "{matching} of {total} CDPs match `{}`",
+        opts.predicate
+    )?;
+    scan_result
+}
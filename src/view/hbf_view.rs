@@ -1,26 +1,62 @@
 use crate::input;
 use crate::stats::stats_controller;
+use crate::util::config::HbfViewOptions;
 use crate::validators::its_payload_fsm_cont::ItsPayloadFsmContinuous;
-use crate::validators::link_validator::preprocess_payload;
+use crate::validators::link_validator::{
+    preprocess_payload_wide, preprocess_payload_with_word_size, GBT_WORD_SIZE,
+};
 use crate::words::lib::RDH;
 use std::io::Write;
 
+/// Writes the HBF view of a [CdpChunk][input::data_wrapper::CdpChunk] to stdout, and sends the payloads that can't be split into GBT words as errors to the [StatsController][stats_controller::StatsController].
 pub(crate) fn hbf_view<T: RDH>(
     cdp_chunk: input::data_wrapper::CdpChunk<T>,
     send_stats_ch: &std::sync::mpsc::Sender<stats_controller::StatType>,
     its_payload_fsm_cont: &mut ItsPayloadFsmContinuous,
+    opts: &HbfViewOptions,
+    gbt_wide: bool,
 ) -> Result<(), std::io::Error> {
     let mut stdio_lock = std::io::stdout().lock();
-    print_start_of_hbf_header_text(&mut stdio_lock)?;
-    for (rdh, payload, rdh_mem_pos) in cdp_chunk.into_iter() {
-        print_rdh_hbf_view(&rdh, &rdh_mem_pos, &mut stdio_lock)?;
+    write_hbf_view(
+        &cdp_chunk,
+        its_payload_fsm_cont,
+        opts,
+        gbt_wide,
+        &mut stdio_lock,
+        |e| {
+            send_stats_ch
+                .send(stats_controller::StatType::Error(e))
+                .unwrap()
+        },
+    )
+}
+
+/// Writes the HBF view of borrowed CDPs, e.g. of a [CdpChunk][input::data_wrapper::CdpChunk], to any writer.
+///
+/// Payloads that can't be split into GBT words are skipped and passed to `on_payload_error`, and the payload state machine is reset.
+/// With `gbt_wide` the payload words are the 16 byte words of GBT wide mode, and all 16 bytes of each word are shown.
+pub(crate) fn write_hbf_view<'a, T: RDH + 'a>(
+    cdps: impl IntoIterator<Item = (&'a T, &'a [u8], u64)>,
+    its_payload_fsm_cont: &mut ItsPayloadFsmContinuous,
+    opts: &HbfViewOptions,
+    gbt_wide: bool,
+    stdio_lock: &mut impl Write,
+    mut on_payload_error: impl FnMut(String),
+) -> Result<(), std::io::Error> {
+    print_start_of_hbf_header_text(stdio_lock)?;
+    for (rdh, payload, rdh_mem_pos) in cdps {
+        if !opts.no_rdh {
+            print_rdh_hbf_view(rdh, &rdh_mem_pos, opts, stdio_lock)?;
+        }
 
-        let gbt_word_chunks = match preprocess_payload(&payload, rdh.data_format()) {
+        let gbt_word_chunks = match if gbt_wide {
+            preprocess_payload_wide(payload)
+        } else {
+            preprocess_payload_with_word_size(payload, rdh.data_format(), GBT_WORD_SIZE)
+        } {
             Ok(gbt_word_chunks) => Some(gbt_word_chunks),
             Err(e) => {
-                send_stats_ch
-                    .send(stats_controller::StatType::Error(e))
-                    .unwrap();
+                on_payload_error(e);
                 its_payload_fsm_cont.reset_fsm();
                 None
             }
@@ -28,16 +64,25 @@ pub(crate) fn hbf_view<T: RDH>(
 
         if let Some(gbt_words) = gbt_word_chunks {
             for (idx, gbt_word) in gbt_words.enumerate() {
-                let gbt_word_slice = &gbt_word[..10];
-                let current_word_type = its_payload_fsm_cont.advance(gbt_word_slice);
-                let current_mem_pos =
-                    calc_current_word_mem_pos(idx, rdh.data_format(), rdh_mem_pos);
+                // The padding of data format 0 is not shown, the upper bytes of GBT wide mode are
+                let word_slice = if gbt_wide {
+                    gbt_word
+                } else {
+                    &gbt_word[..GBT_WORD_SIZE]
+                };
+                let current_word_type = its_payload_fsm_cont.advance(&gbt_word[..GBT_WORD_SIZE]);
+                let current_mem_pos = calc_current_word_mem_pos(
+                    idx,
+                    if gbt_wide { 0 } else { rdh.data_format() },
+                    rdh_mem_pos + rdh.header_size_bytes() as u64,
+                );
                 let mem_pos_str = format!("{current_mem_pos:>8X}:");
                 generate_payload_word_view(
-                    gbt_word_slice,
+                    word_slice,
                     current_word_type,
                     mem_pos_str,
-                    &mut stdio_lock,
+                    opts,
+                    stdio_lock,
                 )?;
             }
         }
@@ -45,9 +90,7 @@ pub(crate) fn hbf_view<T: RDH>(
     Ok(())
 }
 
-fn print_start_of_hbf_header_text(
-    stdio_lock: &mut std::io::StdoutLock,
-) -> Result<(), std::io::Error> {
+fn print_start_of_hbf_header_text(stdio_lock: &mut impl Write) -> Result<(), std::io::Error> {
     writeln!(
         stdio_lock,
         "\nMemory    Word{:>37}{:>12}{:>12}{:>12}{:>12}",
@@ -64,48 +107,51 @@ fn print_start_of_hbf_header_text(
 fn print_rdh_hbf_view<T: RDH>(
     rdh: &T,
     rdh_mem_pos: &u64,
-    stdio_lock: &mut std::io::StdoutLock,
+    opts: &HbfViewOptions,
+    stdio_lock: &mut impl Write,
 ) -> Result<(), std::io::Error> {
     let trig_str = rdh_trigger_type_as_string(rdh);
+    let orbit = rdh.rdh1().orbit;
+    let time_str = orbit_bc_as_string(orbit, rdh.rdh1().bc(), opts);
 
     writeln!(
         stdio_lock,
-        "{rdh_mem_pos:>8X}: RDH v{}       {trig_str:>28}                                #{:<18}",
+        "{rdh_mem_pos:>8X}: RDH v{}       {trig_str:>28}                                #{:<18} {time_str}",
         rdh.version(),
         rdh.link_id()
     )?;
     Ok(())
 }
 
-const PHT_BIT_MASK: u32 = 0b1_0000;
-const SOC_BIT_MASK: u32 = 0b10_0000_0000;
-const HB_BIT_MASK: u32 = 0b10;
-fn rdh_trigger_type_as_string<T: RDH>(rdh: &T) -> String {
-    let trigger_type = rdh.trigger_type();
-    // Priorities describing the trigger as follows:
-    // 1. SOC
-    // 2. HB
-    // 3. PhT
-    if trigger_type & SOC_BIT_MASK != 0 {
-        String::from("SOC  ")
-    } else if trigger_type & HB_BIT_MASK != 0 {
-        String::from("HB   ")
-    } else if trigger_type & PHT_BIT_MASK != 0 {
-        String::from("PhT  ")
-    } else {
-        String::from("Other")
+/// Formats an orbit and bc, followed by their UTC time if the absolute time is enabled.
+fn orbit_bc_as_string(orbit: u32, bc: u16, opts: &HbfViewOptions) -> String {
+    let orbit_bc_str = format!("orbit {orbit:#010X} bc {bc:#05X}");
+    match opts.orbit_zero_utc {
+        Some(orbit_zero_utc) if opts.absolute_time => {
+            let time = orbit_zero_utc + crate::words::lib::orbit_bc_to_duration(orbit, bc);
+            format!(
+                "{orbit_bc_str} {}",
+                crate::util::run_metadata::format_utc_micros(time)
+            )
+        }
+        _ => orbit_bc_str,
     }
 }
 
+fn rdh_trigger_type_as_string<T: RDH>(rdh: &T) -> String {
+    let class = crate::words::rdh::TriggerType::class_name_of(rdh.trigger_type());
+    format!("{class:<5}")
+}
+
 /// Calculates the current position in the memory of the current word.
 ///
-/// Current payload position is the first byte after the current RDH
+/// Current payload position is the first byte after the header of the current RDH
 /// The gbt word position then relative to the current payload is then:
 /// relative_mem_pos = gbt_word_counter * (10 + gbt_word_padding_size_bytes)
 /// And the absolute position in the memory is then:
 /// gbt_word_mem_pos = payload_mem_pos + relative_mem_pos
 #[inline]
-fn calc_current_word_mem_pos(word_idx: usize, data_format: u8, rdh_mem_pos: u64) -> u64 {
+fn calc_current_word_mem_pos(word_idx: usize, data_format: u8, payload_mem_pos: u64) -> u64 {
     let gbt_word_padding: u64 = if data_format == 0 {
         6
     } else {
@@ -115,19 +161,28 @@ fn calc_current_word_mem_pos(word_idx: usize, data_format: u8, rdh_mem_pos: u64)
 
     let gbt_word_memory_size_bytes: u64 = 10 + gbt_word_padding;
     let relative_mem_pos = word_idx as u64 * gbt_word_memory_size_bytes;
-    relative_mem_pos + rdh_mem_pos + 64
+    relative_mem_pos + payload_mem_pos
 }
 
+/// Writes the view of a payload word, decoded from its first 10 bytes, with all the bytes of the word shown.
 fn generate_payload_word_view(
-    gbt_word_slice: &[u8],
+    word_slice: &[u8],
     word_type: crate::validators::its_payload_fsm_cont::PayloadWord,
     mem_pos_str: String,
-    stdio_lock: &mut std::io::StdoutLock,
+    opts: &HbfViewOptions,
+    stdio_lock: &mut impl Write,
 ) -> Result<(), std::io::Error> {
     use crate::validators::its_payload_fsm_cont::PayloadWord;
     use crate::words::status_words::util::*;
 
-    let word_slice_str = format_word_slice(gbt_word_slice);
+    if let Some(word_filter) = &opts.only {
+        if !word_filter.contains(&word_type) {
+            return Ok(());
+        }
+    }
+
+    let word_slice_str = format_word_slice(word_slice);
+    let gbt_word_slice = &word_slice[..GBT_WORD_SIZE];
     match word_type {
         PayloadWord::IHW | PayloadWord::IHW_continuation => {
             writeln!(stdio_lock, "{mem_pos_str} IHW {word_slice_str}")?;
@@ -136,33 +191,45 @@ fn generate_payload_word_view(
             let trigger_str = tdh_trigger_as_string(gbt_word_slice);
             let continuation_str = tdh_continuation_as_string(gbt_word_slice);
             let no_data_str = tdh_no_data_as_string(gbt_word_slice);
+            let time_str = orbit_bc_as_string(
+                tdh_trigger_orbit(gbt_word_slice),
+                tdh_trigger_bc(gbt_word_slice),
+                opts,
+            );
             writeln!(
                             stdio_lock,
-                            "{mem_pos_str} TDH {word_slice_str} {trigger_str}  {continuation_str}        {no_data_str}"
+                            "{mem_pos_str} TDH {word_slice_str} {trigger_str}  {continuation_str}        {no_data_str} {time_str}"
                         )?;
         }
         PayloadWord::TDH_continuation => {
             let trigger_str = tdh_trigger_as_string(gbt_word_slice);
             let continuation_str = tdh_continuation_as_string(gbt_word_slice);
+            let time_str = orbit_bc_as_string(
+                tdh_trigger_orbit(gbt_word_slice),
+                tdh_trigger_bc(gbt_word_slice),
+                opts,
+            );
             writeln!(
                 stdio_lock,
-                "{mem_pos_str} TDH {word_slice_str} {trigger_str}  {continuation_str}"
+                "{mem_pos_str} TDH {word_slice_str} {trigger_str}  {continuation_str}                {time_str}"
             )?;
         }
         PayloadWord::TDT => {
             let packet_status_str = tdt_packet_done_as_string(gbt_word_slice);
             let error_reporting_str = ddw0_tdt_lane_status_as_string(gbt_word_slice);
+            let timeouts_str = tdt_timeouts_as_string(gbt_word_slice);
             writeln!(
                             stdio_lock,
-                            "{mem_pos_str} TDT {word_slice_str} {packet_status_str:>18}                             {error_reporting_str}",
+                            "{mem_pos_str} TDT {word_slice_str} {packet_status_str:>18}                             {error_reporting_str} {timeouts_str}",
                         )?;
         }
         PayloadWord::DDW0 => {
             let error_reporting_str = ddw0_tdt_lane_status_as_string(gbt_word_slice);
+            let index_and_timeout_str = ddw0_index_and_timeout_as_string(gbt_word_slice);
 
             writeln!(
                             stdio_lock,
-                            "{mem_pos_str} DDW {word_slice_str}                                                {error_reporting_str}",
+                            "{mem_pos_str} DDW {word_slice_str}                                                {error_reporting_str} {index_and_timeout_str}",
                         )?;
         }
         // Ignore these cases
@@ -172,17 +239,195 @@ fn generate_payload_word_view(
 }
 
 fn format_word_slice(word_slice: &[u8]) -> String {
-    format!(
-        "[{:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X}]",
-        word_slice[0],
-        word_slice[1],
-        word_slice[2],
-        word_slice[3],
-        word_slice[4],
-        word_slice[5],
-        word_slice[6],
-        word_slice[7],
-        word_slice[8],
-        word_slice[9],
-    )
+    let bytes: Vec<String> = word_slice
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect();
+    format!("[{}]", bytes.join(" "))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::input::data_wrapper::CdpChunk;
+    use crate::validators::its_payload_fsm_cont::PayloadWord;
+    use crate::words::rdh_cru::{test_data::CORRECT_RDH_CRU_V7, RdhCRU, V7};
+
+    fn word_view(word_slice: &[u8], word_type: PayloadWord) -> String {
+        let mut view = Vec::new();
+        generate_payload_word_view(
+            word_slice,
+            word_type,
+            "      40:".to_string(),
+            &HbfViewOptions::default(),
+            &mut view,
+        )
+        .unwrap();
+        String::from_utf8(view).unwrap()
+    }
+
+    #[test]
+    fn test_tdt_view_timeout_markers() {
+        let tdt_no_timeout = [0, 0, 0, 0, 0, 0, 0, 0x00, 0x01, 0xF0];
+        assert!(!word_view(&tdt_no_timeout, PayloadWord::TDT).contains("TO_"));
+        let tdt_to_start = [0, 0, 0, 0, 0, 0, 0, 0x80, 0x01, 0xF0];
+        let view = word_view(&tdt_to_start, PayloadWord::TDT);
+        assert!(view.starts_with("      40: TDT [00 00 00 00 00 00 00 80 01 F0]"));
+        assert!(view.trim_end().ends_with("TO_START"), "{view}");
+        let tdt_start_stop_in_idle = [0, 0, 0, 0, 0, 0, 0, 0x60, 0x01, 0xF0];
+        let view = word_view(&tdt_start_stop_in_idle, PayloadWord::TDT);
+        assert!(view.trim_end().ends_with("TO_START_STOP TO_IDLE"), "{view}");
+    }
+
+    #[test]
+    fn test_ddw0_view_index_and_timeout() {
+        let ddw0 = [0, 0, 0, 0, 0, 0, 0, 0, 0x00, 0xE4];
+        let view = word_view(&ddw0, PayloadWord::DDW0);
+        assert!(view.starts_with("      40: DDW [00 00 00 00 00 00 00 00 00 E4]"));
+        assert!(view.trim_end().ends_with("index 0"), "{view}");
+        let ddw0_timeout = [0, 0, 0, 0, 0, 0, 0, 0, 0x02, 0xE4];
+        let view = word_view(&ddw0_timeout, PayloadWord::DDW0);
+        assert!(view.trim_end().ends_with("index 0 TX_TIMEOUT"), "{view}");
+    }
+
+    #[test]
+    fn test_gbt_wide_word_view_all_bytes() {
+        let ddw0_wide = [0, 0, 0, 0, 0, 0, 0, 0, 0x00, 0xE4, 0xE4, 0, 0, 0, 0, 0];
+        let view = word_view(&ddw0_wide, PayloadWord::DDW0);
+        assert!(view.starts_with("      40: DDW [00 00 00 00 00 00 00 00 00 E4 E4 00 00 00 00 00]"));
+        assert!(view.trim_end().ends_with("index 0"), "{view}");
+    }
+
+    #[test]
+    fn test_tdh_view_orbit_bc() {
+        // TDH with trigger_bc 0x123 and trigger_orbit 0xB7DD575
+        let tdh = [0x03, 0x1A, 0x23, 0x01, 0x75, 0xD5, 0x7D, 0x0B, 0x00, 0xE8];
+        let view = word_view(&tdh, PayloadWord::TDH);
+        assert!(view.starts_with("      40: TDH [03 1A 23 01 75 D5 7D 0B 00 E8]"));
+        assert!(
+            view.trim_end().ends_with("orbit 0x0B7DD575 bc 0x123"),
+            "{view}"
+        );
+    }
+
+    #[test]
+    fn test_hbf_view_absolute_time() {
+        // The orbit 0xB7DD575 at bc 0 of CORRECT_RDH_CRU_V7 is 192796021 / 11245.5 s = 17144.281801 s after orbit 0
+        let view_lines = hbf_view_from_args(&[
+            "fastpasta",
+            "view",
+            "hbf",
+            "--absolute-time",
+            "--orbit-zero-utc",
+            "2023-04-12T11:46:40Z",
+        ]);
+        let expected_time = crate::util::run_metadata::format_utc_micros(
+            crate::util::run_metadata::parse_utc("2023-04-12T11:46:40Z").unwrap()
+                + std::time::Duration::from_secs_f64(0xB7DD575 as f64 / 11245.5),
+        );
+        assert_eq!(expected_time, "2023-04-12T16:32:24.281801Z");
+        assert!(
+            view_lines[0].ends_with(&format!("orbit 0x0B7DD575 bc 0x000 {expected_time}")),
+            "{}",
+            view_lines[0]
+        );
+        // The TDH has the same orbit and bc as the RDH
+        assert!(
+            view_lines[2].trim_end().ends_with(&expected_time),
+            "{}",
+            view_lines[2]
+        );
+
+        // Without --absolute-time, only the orbit and bc are shown
+        let view_lines = hbf_view_from_args(&["fastpasta", "view", "hbf"]);
+        assert!(view_lines[0].ends_with("orbit 0x0B7DD575 bc 0x000"));
+
+        // The orbit zero is required
+        assert!(
+            <crate::util::config::Opt as structopt::StructOpt>::from_iter_safe(&[
+                "fastpasta",
+                "view",
+                "hbf",
+                "--absolute-time",
+            ])
+            .is_err()
+        );
+    }
+
+    /// A single CDP with an IHW, TDH, TDT and DDW0.
+    pub(crate) fn cdp_chunk_fixture() -> CdpChunk<RdhCRU<V7>> {
+        let mut payload = vec![
+            0xFF, 0x3F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE0, // IHW
+            0x03, 0x1A, 0x00, 0x00, 0x75, 0xD5, 0x7D, 0x0B, 0x00, 0xE8, // TDH
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xF0, // TDT
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE4, // DDW0
+        ];
+        payload.resize(48, 0xFF);
+        let mut cdp_chunk = CdpChunk::new();
+        cdp_chunk.push(CORRECT_RDH_CRU_V7, payload, 0);
+        cdp_chunk
+    }
+
+    fn hbf_view_from_args(args: &[&str]) -> Vec<String> {
+        use crate::util::lib::Views;
+        let config: crate::util::config::Opt =
+            <crate::util::config::Opt as structopt::StructOpt>::from_iter(args);
+        let opts = match config.view() {
+            Some(crate::util::config::View::Hbf(opts)) => opts,
+            _ => unreachable!(),
+        };
+        crate::api::render_hbf_view(cdp_chunk_fixture(), &opts)
+            .lines()
+            .filter(|line| !line.is_empty())
+            .skip(2) // Header text
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    fn word_types(view_lines: &[String]) -> Vec<&str> {
+        view_lines
+            .iter()
+            .map(|line| line.split_whitespace().nth(1).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_hbf_view_all_words() {
+        let view_lines = hbf_view_from_args(&["fastpasta", "view", "hbf"]);
+        assert_eq!(
+            word_types(&view_lines),
+            vec!["RDH", "IHW", "TDH", "TDT", "DDW"]
+        );
+    }
+
+    #[test]
+    fn test_hbf_view_only_tdt() {
+        let view_lines = hbf_view_from_args(&["fastpasta", "view", "hbf", "--only", "tdt"]);
+        assert_eq!(word_types(&view_lines), vec!["RDH", "TDT"]);
+    }
+
+    #[test]
+    fn test_hbf_view_only_tdh_ddw0_no_rdh() {
+        let view_lines =
+            hbf_view_from_args(&["fastpasta", "view", "hbf", "--only", "tdh,ddw0", "--no-rdh"]);
+        assert_eq!(word_types(&view_lines), vec!["TDH", "DDW"]);
+    }
+
+    #[test]
+    fn test_hbf_view_only_unknown_word_type() {
+        let err = <crate::util::config::Opt as structopt::StructOpt>::from_iter_safe(&[
+            "fastpasta",
+            "view",
+            "hbf",
+            "--only",
+            "tdt,rdh",
+        ])
+        .unwrap_err();
+        assert!(
+            err.message
+                .contains("Unknown word type 'rdh', valid word types are: ihw, tdh, tdt, ddw0"),
+            "{}",
+            err.message
+        );
+    }
 }
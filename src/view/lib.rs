@@ -4,18 +4,54 @@ use crate::words::lib::RDH;
 use crate::{input, stats::stats_controller, util};
 
 /// Calls a specific view generator based on the [View][util::config::View] type.
+///
+/// With `gbt_wide` the payload words are the 16 byte words of GBT wide mode, which replace the `payload_word_size` of the raw words view.
 #[inline]
 pub fn generate_view<T: RDH>(
     view: crate::util::config::View,
     cdp_chunk: input::data_wrapper::CdpChunk<T>,
     send_stats_ch: &std::sync::mpsc::Sender<stats_controller::StatType>,
     its_payload_fsm_cont: &mut ItsPayloadFsmContinuous,
+    payload_word_size: usize,
+    gbt_wide: bool,
 ) -> Result<(), std::io::Error> {
     match view {
         util::config::View::Rdh => super::rdh_view::rdh_view(cdp_chunk)?,
-        util::config::View::Hbf => {
-            super::hbf_view::hbf_view(cdp_chunk, send_stats_ch, its_payload_fsm_cont)?
+        util::config::View::Hbf(opts) => super::hbf_view::hbf_view(
+            cdp_chunk,
+            send_stats_ch,
+            its_payload_fsm_cont,
+            &opts,
+            gbt_wide,
+        )?,
+        util::config::View::RawWords => {
+            let word_size = if gbt_wide {
+                crate::words::lib::GBT_WIDE_WORD_SIZE
+            } else {
+                payload_word_size
+            };
+            super::raw_words_view::raw_words_view(cdp_chunk, word_size, send_stats_ch)?
         }
     }
     Ok(())
 }
+
+/// Scans only the RDHs of the input and prints the links found, see [ListLinks][util::config::ListLinks].
+pub fn list_links<T: RDH>(
+    scanner: input::input_scanner::InputScanner<
+        impl input::bufreader_wrapper::BufferedReaderWrapper + ?Sized,
+    >,
+    opts: &util::config::ListLinks,
+) -> Result<(), std::io::Error> {
+    super::list_links::list_links::<T>(scanner, opts)
+}
+
+/// Scans only the RDHs of the input and prints how many CDPs match an expression, see [Count][util::config::Count].
+pub fn count_cdps<T: RDH>(
+    scanner: input::input_scanner::InputScanner<
+        impl input::bufreader_wrapper::BufferedReaderWrapper + ?Sized,
+    >,
+    opts: &util::config::Count,
+) -> Result<(), std::io::Error> {
+    super::count_cdps::count_cdps::<T>(scanner, opts)
+}
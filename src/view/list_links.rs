@@ -0,0 +1,190 @@
+//! Contains the [list_links] function that scans only the RDHs of the input and lists the links found.
+use crate::input::{
+    bufreader_wrapper::BufferedReaderWrapper,
+    input_scanner::{InputScanner, ScanCDP},
+};
+use crate::util::config::ListLinks;
+use crate::words::lib::RDH;
+use std::io::Write;
+use tabled::{Panel, Table, Tabled};
+
+/// A distinct link found in the input, and the number of CDPs seen from it.
+#[derive(Tabled, Debug, PartialEq)]
+struct LinkSummary {
+    #[tabled(rename = "link ID")]
+    link_id: u8,
+    #[tabled(rename = "FEE ID")]
+    fee_id: u16,
+    #[tabled(rename = "CRU ID")]
+    cru_id: u16,
+    #[tabled(rename = "system ID")]
+    system_id: u8,
+    #[tabled(rename = "CDPs")]
+    cdps: u64,
+}
+
+/// Collects the distinct (link ID, FEE ID, CRU ID, system ID) tuples, sorted in that order.
+#[derive(Default)]
+struct LinkList {
+    links: Vec<LinkSummary>,
+    /// Set if the scan was stopped before reaching the end of the input.
+    sampled: bool,
+    scanned_bytes: u64,
+}
+
+impl LinkList {
+    fn record<T: RDH>(&mut self, rdh: &T) {
+        let key = (
+            rdh.link_id(),
+            rdh.fee_id(),
+            rdh.cru_id(),
+            rdh.rdh0().system_id,
+        );
+        match self.links.binary_search_by_key(&key, |link| {
+            (link.link_id, link.fee_id, link.cru_id, link.system_id)
+        }) {
+            Ok(idx) => self.links[idx].cdps += 1,
+            Err(idx) => self.links.insert(
+                idx,
+                LinkSummary {
+                    link_id: key.0,
+                    fee_id: key.1,
+                    cru_id: key.2,
+                    system_id: key.3,
+                    cdps: 1,
+                },
+            ),
+        }
+    }
+
+    fn sampled_disclaimer(&self) -> String {
+        format!(
+            "Sampled: only the first {} bytes of the input were scanned, links and CDP counts may be incomplete",
+            self.scanned_bytes
+        )
+    }
+
+    fn to_table(&self) -> String {
+        let mut table = Table::new(&self.links);
+        table.with(tabled::Style::modern());
+        if self.sampled {
+            table.with(Panel::footer(self.sampled_disclaimer()));
+        }
+        table.to_string()
+    }
+
+    fn to_json(&self) -> String {
+        let links = self
+            .links
+            .iter()
+            .map(|link| {
+                format!(
+                    r#"{{"link_id":{},"fee_id":{},"cru_id":{},"system_id":{},"cdps":{}}}"#,
+                    link.link_id, link.fee_id, link.cru_id, link.system_id, link.cdps
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+        format!(
+            r#"{{"sampled":{},"scanned_bytes":{},"links":[{links}]}}"#,
+            self.sampled, self.scanned_bytes
+        )
+    }
+}
+
+/// Scans the RDHs of the input, skipping the payloads, and prints a table of the links found with the number of CDPs of each.
+///
+/// If [ListLinks::first_n_mb] is set, the scan stops after that many MB, and the list is marked as sampled if the input continues beyond it.
+pub(crate) fn list_links<T: RDH>(
+    mut scanner: InputScanner<impl BufferedReaderWrapper + ?Sized>,
+    opts: &ListLinks,
+) -> Result<(), std::io::Error> {
+    let byte_limit = opts.first_n_mb.map(|mb| mb * 1024 * 1024);
+    let mut link_list = LinkList::default();
+    let scan_result = loop {
+        link_list.scanned_bytes = scanner.current_mem_pos();
+        let limit_reached = matches!(byte_limit, Some(limit) if link_list.scanned_bytes >= limit);
+        match scanner.load_rdh_skip_payload::<T>() {
+            // Only sampled if another RDH was left unscanned beyond the limit
+            Ok(_) if limit_reached => {
+                link_list.sampled = true;
+                break Ok(());
+            }
+            Ok(rdh) => link_list.record(&rdh),
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break Ok(()),
+            Err(e) => break Err(e),
+        }
+    };
+    scanner.flush_stats();
+
+    let mut stdio_lock = std::io::stdout().lock();
+    if opts.json {
+        writeln!(stdio_lock, "{}", link_list.to_json())?;
+    } else {
+        writeln!(stdio_lock, "{}", link_list.to_table())?;
+    }
+    scan_result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh_cru::test_data::CORRECT_RDH_CRU_V7;
+
+    fn link_list_from_links(links: &[u8]) -> LinkList {
+        let mut link_list = LinkList::default();
+        for link in links {
+            let mut rdh = CORRECT_RDH_CRU_V7;
+            rdh.link_id = *link;
+            link_list.record(&rdh);
+        }
+        link_list
+    }
+
+    #[test]
+    fn test_record_counts_and_sorts_links() {
+        let link_list = link_list_from_links(&[3, 0, 3, 3, 0, 1]);
+        let links_cdps: Vec<(u8, u64)> = link_list
+            .links
+            .iter()
+            .map(|link| (link.link_id, link.cdps))
+            .collect();
+        assert_eq!(links_cdps, vec![(0, 2), (1, 1), (3, 3)]);
+    }
+
+    #[test]
+    fn test_record_distinguishes_fee_id() {
+        let mut link_list = LinkList::default();
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        link_list.record(&rdh);
+        rdh.rdh0.fee_id = crate::words::rdh::FeeId(0x1234);
+        link_list.record(&rdh);
+        assert_eq!(link_list.links.len(), 2);
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut link_list = link_list_from_links(&[0, 0, 2]);
+        link_list.scanned_bytes = 0x13E0 * 3;
+        let rdh = CORRECT_RDH_CRU_V7;
+        let (fee_id, cru_id, system_id) = (rdh.fee_id(), rdh.cru_id(), rdh.rdh0().system_id);
+        assert_eq!(
+            link_list.to_json(),
+            format!(
+                r#"{{"sampled":false,"scanned_bytes":15264,"links":[{{"link_id":0,"fee_id":{fee_id},"cru_id":{cru_id},"system_id":{system_id},"cdps":2}},{{"link_id":2,"fee_id":{fee_id},"cru_id":{cru_id},"system_id":{system_id},"cdps":1}}]}}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_table_sampled_disclaimer() {
+        let mut link_list = link_list_from_links(&[0]);
+        assert!(!link_list.to_table().contains("Sampled"));
+        link_list.sampled = true;
+        link_list.scanned_bytes = 1024 * 1024;
+        let table = link_list.to_table();
+        println!("{table}");
+        assert!(table.contains("CDPs"));
+        assert!(table.contains("Sampled: only the first 1048576 bytes"));
+    }
+}
@@ -0,0 +1,135 @@
+use crate::input;
+use crate::stats::stats_controller;
+use crate::validators::link_validator::preprocess_payload_with_word_size;
+use crate::words::lib::RDH;
+use std::io::Write;
+
+pub(crate) fn raw_words_view<T: RDH>(
+    cdp_chunk: input::data_wrapper::CdpChunk<T>,
+    word_size: usize,
+    send_stats_ch: &std::sync::mpsc::Sender<stats_controller::StatType>,
+) -> Result<(), std::io::Error> {
+    let mut stdio_lock = std::io::stdout().lock();
+    write_raw_words_view(cdp_chunk, word_size, send_stats_ch, &mut stdio_lock)
+}
+
+fn write_raw_words_view<T: RDH>(
+    cdp_chunk: input::data_wrapper::CdpChunk<T>,
+    word_size: usize,
+    send_stats_ch: &std::sync::mpsc::Sender<stats_controller::StatType>,
+    stdio_lock: &mut impl Write,
+) -> Result<(), std::io::Error> {
+    writeln!(stdio_lock, "\nMemory    Word ({word_size} bytes)")?;
+    writeln!(stdio_lock, "Position\n")?;
+    for (rdh, payload, rdh_mem_pos) in cdp_chunk.into_iter() {
+        writeln!(
+            stdio_lock,
+            "{rdh_mem_pos:>8X}: RDH v{} link {} payload {} B",
+            rdh.version(),
+            rdh.link_id(),
+            payload.len()
+        )?;
+        let words = match preprocess_payload_with_word_size(&payload, rdh.data_format(), word_size)
+        {
+            Ok(words) => words,
+            Err(e) => {
+                send_stats_ch
+                    .send(stats_controller::StatType::Error(e))
+                    .unwrap();
+                continue;
+            }
+        };
+        let mut word_mem_pos = rdh_mem_pos + rdh.rdh0().header_size as u64;
+        let remainder = words.remainder();
+        for word in words {
+            // In data format 0 each word is followed by 6 bytes of 0x00 padding, which are not shown
+            let padding_start = word.len().min(word_size);
+            writeln!(
+                stdio_lock,
+                "{word_mem_pos:>8X}: {}",
+                bytes_as_hex(&word[..padding_start])
+            )?;
+            word_mem_pos += word.len() as u64;
+        }
+        if !remainder.is_empty() {
+            writeln!(
+                stdio_lock,
+                "{word_mem_pos:>8X}: {} ({} trailing bytes)",
+                bytes_as_hex(remainder),
+                remainder.len()
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn bytes_as_hex(word: &[u8]) -> String {
+    word.iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::data_wrapper::CdpChunk;
+    use crate::words::rdh_cru::{test_data::CORRECT_RDH_CRU_V7, RdhCRU, V7};
+
+    #[test]
+    fn test_raw_words_view_16_byte_words() {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        rdh.offset_new_packet = 64 + 40;
+        rdh.memory_size = 64 + 40;
+        let payload: Vec<u8> = (0..40).collect();
+        let mut cdp_chunk = CdpChunk::<RdhCRU<V7>>::new();
+        cdp_chunk.push(rdh, payload, 0x100);
+        let (send_stats_ch, recv_stats_ch) = std::sync::mpsc::channel();
+
+        let mut output = Vec::new();
+        write_raw_words_view(cdp_chunk, 16, &send_stats_ch, &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().skip(4).collect();
+        assert_eq!(
+            lines,
+            [
+                "     100: RDH v7 link 0 payload 40 B",
+                "     140: 00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F",
+                "     150: 10 11 12 13 14 15 16 17 18 19 1A 1B 1C 1D 1E 1F",
+                "     160: 20 21 22 23 24 25 26 27 (8 trailing bytes)",
+            ]
+        );
+        assert!(recv_stats_ch.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_raw_words_view_data_format_0_padding_not_shown() {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        rdh.offset_new_packet = 64 + 32;
+        rdh.memory_size = 64 + 32;
+        rdh.dataformat_reserved0 = crate::words::rdh::DataformatReserved(0);
+        let mut payload: Vec<u8> = (0..10).collect();
+        payload.extend([0; 6]);
+        payload.extend(10..20);
+        payload.extend([0; 6]);
+        let mut cdp_chunk = CdpChunk::<RdhCRU<V7>>::new();
+        cdp_chunk.push(rdh, payload, 0x100);
+        let (send_stats_ch, recv_stats_ch) = std::sync::mpsc::channel();
+
+        let mut output = Vec::new();
+        write_raw_words_view(cdp_chunk, 10, &send_stats_ch, &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().skip(4).collect();
+        assert_eq!(
+            lines,
+            [
+                "     100: RDH v7 link 0 payload 32 B",
+                "     140: 00 01 02 03 04 05 06 07 08 09",
+                "     150: 0A 0B 0C 0D 0E 0F 10 11 12 13",
+            ]
+        );
+        assert!(recv_stats_ch.try_recv().is_err());
+    }
+}
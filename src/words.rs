@@ -1,5 +1,6 @@
 //! Word definitions for the GBT/CRU/ITS data format
 
+pub mod constants;
 pub mod data_words;
 pub mod lib;
 pub mod rdh;
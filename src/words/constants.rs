@@ -0,0 +1,15 @@
+//! LHC and ALICE timing constants used by the checks, stats and views.
+//!
+//! The maximum bunch counter can be overridden with `--max-bc`, e.g. for lab setups with another bunch counter range.
+
+/// LHC orbit frequency in Hz, an orbit lasts about 88.9 µs.
+pub const LHC_ORBIT_FREQUENCY_HZ: f64 = 11245.5;
+
+/// Bunch crossings in an LHC orbit.
+pub const BC_PER_ORBIT: u16 = 3564;
+
+/// Largest valid bunch counter of an RDH or TDH, the last bunch crossing of an orbit (0xDEB).
+pub const MAX_BC: u16 = BC_PER_ORBIT - 1;
+
+/// Largest value of the 12 bit bunch counter fields of the RDH and TDH.
+pub const BC_FIELD_MAX: u16 = 0xFFF;
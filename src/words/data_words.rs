@@ -57,6 +57,32 @@ fn ob_lane(ob_id: ObLane) -> u8 {
 //      [2:0] = input number on the connector
 //           * 0b000 - 0b111 (0-6 on the connector)
 
+/// Kind of an ITS data word, classified by the ID of the GBT word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataWordKind {
+    /// Calibration data word (CDW), ID 0xF8
+    Calibration,
+    /// Inner Barrel data word, ID 0b001X_XXXX
+    InnerBarrel,
+    /// Outer Barrel data word, ID 0b010X_XXXX
+    OuterBarrel,
+}
+
+impl DataWordKind {
+    /// Classifies the ID of a GBT word, returns [None] if it is not the ID of a data word, e.g. of a status word.
+    #[inline]
+    pub fn from_id(id: u8) -> Option<Self> {
+        if id == 0xF8 {
+            return Some(DataWordKind::Calibration);
+        }
+        match id >> 5 {
+            0b001 => Some(DataWordKind::InnerBarrel),
+            0b010 => Some(DataWordKind::OuterBarrel),
+            _ => None,
+        }
+    }
+}
+
 /// Convenience tuple of the min/max range for the ID of an IL data word (9 lanes)
 pub const VALID_IL_ID_MIN_MAX: (u8, u8) = (0x20, 0x28);
 
@@ -49,6 +49,8 @@ pub trait RDH:
     /// Returns the size of the payload in bytes.
     /// This size is EXCLUDING the size of the RDH.
     fn payload_size(&self) -> u16;
+    /// Returns the memory size of the CDP in bytes, INCLUDING the size of the RDH.
+    fn memory_size(&self) -> u16;
     /// Returns the offset to the next [RDH] in bytes.
     fn offset_to_next(&self) -> u16;
     /// Returns the value of the stop bit.
@@ -67,8 +69,34 @@ pub trait RDH:
     fn dw(&self) -> u8;
     /// Returns the value of the packet counter.
     fn packet_counter(&self) -> u8;
+    /// Returns the size of the [RDH] in bytes, the header_size if it is [plausible][RDH::is_header_size_plausible], otherwise [RDH_SIZE_BYTES].
+    fn header_size_bytes(&self) -> u16 {
+        if self.is_header_size_plausible() {
+            self.rdh0().header_size as u16
+        } else {
+            RDH_SIZE_BYTES
+        }
+    }
+    /// Checks if the header_size is a plausible size of the [RDH] in bytes.
+    ///
+    /// It has to fit the fields of the [RDH] and at most [MAX_RDH_SIZE_BYTES], and be within the memory size, which is within the offset to the next [RDH].
+    fn is_header_size_plausible(&self) -> bool {
+        let header_size = self.rdh0().header_size as u16;
+        (RDH_SIZE_BYTES..=MAX_RDH_SIZE_BYTES).contains(&header_size)
+            && header_size <= self.memory_size()
+            && self.memory_size() <= self.offset_to_next()
+    }
 }
 
+/// Size of the [RDH] in bytes, the size of its fields and the header_size of all RDHs of the CRU.
+pub const RDH_SIZE_BYTES: u16 = 64;
+
+/// Largest header_size of an [RDH] in bytes considered plausible, the header is assumed to have [RDH_SIZE_BYTES] if it declares more.
+pub const MAX_RDH_SIZE_BYTES: u16 = 128;
+
+/// Size in bytes of a GBT word in GBT wide mode, the 10 bytes of the word followed by the 6 upper bytes of the GBT frame.
+pub const GBT_WIDE_WORD_SIZE: usize = 16;
+
 /// Trait used to convert a struct to a byte slice.
 /// All structs that are used to represent a full GBT word (not sub RDH words) must implement this trait.
 pub trait ByteSlice {
@@ -112,3 +140,33 @@ pub fn layer_from_feeid(fee_id: u16) -> u8 {
     let layer_lsb_idx: u8 = 12;
     ((fee_id >> layer_lsb_idx) & layer_mask) as u8
 }
+/// Returns the number of lanes of a stave in the given layer
+///
+/// Inner barrel (layer 0-2): 9 lanes, middle barrel (layer 3-4): 16 lanes, outer barrel (layer 5-6): 28 lanes
+pub fn lane_count_from_layer(layer: u8) -> u8 {
+    match layer {
+        0..=2 => 9,
+        3..=4 => 16,
+        _ => 28,
+    }
+}
+/// Returns the number of staves of the given ITS layer, or 0 if the ITS has no such layer
+pub fn stave_count_from_layer(layer: u8) -> u8 {
+    match layer {
+        0 => 12,
+        1 => 16,
+        2 => 20,
+        3 => 24,
+        4 => 30,
+        5 => 42,
+        6 => 48,
+        _ => 0,
+    }
+}
+
+/// Time elapsed from the start of orbit 0 to the bunch crossing `bc` of `orbit`.
+pub fn orbit_bc_to_duration(orbit: u32, bc: u16) -> std::time::Duration {
+    use super::constants::{BC_PER_ORBIT, LHC_ORBIT_FREQUENCY_HZ};
+    let orbits = orbit as f64 + bc as f64 / BC_PER_ORBIT as f64;
+    std::time::Duration::from_secs_f64(orbits / LHC_ORBIT_FREQUENCY_HZ)
+}
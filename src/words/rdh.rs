@@ -169,6 +169,138 @@ impl Display for Rdh1 {
     }
 }
 
+/// Named bits of the trigger type, the TDH has the 12 LSB of the RDH trigger type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerType {
+    /// Orbit, bit 0.
+    Orbit,
+    /// Heart Beat, bit 1.
+    Hb,
+    /// Heart Beat reject, bit 2.
+    HbReject,
+    /// Health Check, bit 3.
+    HealthCheck,
+    /// Physics Trigger, bit 4.
+    PhT,
+    /// Pre Pulse, bit 5.
+    PrePulse,
+    /// Calibration, bit 6.
+    Calibration,
+    /// Start Of Triggered data, bit 7.
+    Sot,
+    /// End Of Triggered data, bit 8.
+    Eot,
+    /// Start Of Continuous data, bit 9.
+    Soc,
+    /// End Of Continuous data, bit 10.
+    Eoc,
+    /// Time Frame, bit 11.
+    Tf,
+}
+
+impl TriggerType {
+    /// The trigger types of the 12 LSB of the trigger type, which are also in the TDH, ordered by bit.
+    pub const TDH_BITS: [TriggerType; 12] = [
+        TriggerType::Orbit,
+        TriggerType::Hb,
+        TriggerType::HbReject,
+        TriggerType::HealthCheck,
+        TriggerType::PhT,
+        TriggerType::PrePulse,
+        TriggerType::Calibration,
+        TriggerType::Sot,
+        TriggerType::Eot,
+        TriggerType::Soc,
+        TriggerType::Eoc,
+        TriggerType::Tf,
+    ];
+
+    /// The trigger types that classify a trigger, by priority, e.g. a SOC trigger also has the HB bit set.
+    const CLASS_PRIORITY: [TriggerType; 10] = [
+        TriggerType::Soc,
+        TriggerType::Eoc,
+        TriggerType::Sot,
+        TriggerType::Eot,
+        TriggerType::Hb,
+        TriggerType::PhT,
+        TriggerType::Calibration,
+        TriggerType::HealthCheck,
+        TriggerType::PrePulse,
+        TriggerType::HbReject,
+    ];
+
+    /// The trigger type with the highest priority set in a trigger type field, [None] if only the orbit or TF bits are set.
+    pub fn class_of(trigger_type: u32) -> Option<TriggerType> {
+        Self::CLASS_PRIORITY
+            .into_iter()
+            .find(|trigger| trigger.is_set(trigger_type))
+    }
+
+    /// Name of the class of a trigger type field, `Other` if it has no class.
+    pub fn class_name_of(trigger_type: u32) -> &'static str {
+        Self::class_of(trigger_type).map_or("Other", TriggerType::name)
+    }
+
+    /// Names of all the bits set in the 12 LSB of a trigger type field, separated by `|`, e.g. `ORBIT|HB|TF`.
+    pub fn names_of(trigger_type: u32) -> String {
+        let names: Vec<&str> = Self::TDH_BITS
+            .iter()
+            .filter(|trigger| trigger.is_set(trigger_type))
+            .map(|trigger| trigger.name())
+            .collect();
+        if names.is_empty() {
+            String::from("none")
+        } else {
+            names.join("|")
+        }
+    }
+
+    /// Bit position of the trigger type.
+    pub fn bit(self) -> u8 {
+        self as u8
+    }
+
+    /// Checks if the bit of the trigger type is set in a trigger type field.
+    pub fn is_set(self, trigger_type: u32) -> bool {
+        trigger_type >> self.bit() & 0x1 == 1
+    }
+
+    /// Short name of the trigger type, as used in the trigger documentation.
+    pub fn name(self) -> &'static str {
+        match self {
+            TriggerType::Orbit => "ORBIT",
+            TriggerType::Hb => "HB",
+            TriggerType::HbReject => "HBr",
+            TriggerType::HealthCheck => "HC",
+            TriggerType::PhT => "PhT",
+            TriggerType::PrePulse => "PP",
+            TriggerType::Calibration => "CAL",
+            TriggerType::Sot => "SOT",
+            TriggerType::Eot => "EOT",
+            TriggerType::Soc => "SOC",
+            TriggerType::Eoc => "EOC",
+            TriggerType::Tf => "TF",
+        }
+    }
+}
+
+impl std::str::FromStr for TriggerType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::TDH_BITS
+            .into_iter()
+            .find(|trigger_type| trigger_type.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::TDH_BITS.iter().map(|t| t.name()).collect();
+                format!(
+                    "Unknown trigger type '{s}', valid trigger types are: {}",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
 /// Represents the RDH2 subword of the RDH.
 #[repr(packed)]
 #[derive(Clone, Copy)]
@@ -187,7 +319,31 @@ impl Rdh2 {
     /// Checks if the 4th bit of the trigger type is set, which indicates that the trigger type is PhT.
     #[inline]
     pub fn is_pht_trigger(&self) -> bool {
-        self.trigger_type >> 4 & 0x1 == 1
+        TriggerType::PhT.is_set(self.trigger_type)
+    }
+
+    /// Checks if the 1st bit of the trigger type is set, which indicates that the trigger type is HB.
+    #[inline]
+    pub fn is_hb_trigger(&self) -> bool {
+        TriggerType::Hb.is_set(self.trigger_type)
+    }
+
+    /// Checks if the 7th bit of the trigger type is set, which indicates that the trigger type is SOT.
+    #[inline]
+    pub fn is_sot_trigger(&self) -> bool {
+        TriggerType::Sot.is_set(self.trigger_type)
+    }
+
+    /// Checks if the 9th bit of the trigger type is set, which indicates that the trigger type is SOC.
+    #[inline]
+    pub fn is_soc_trigger(&self) -> bool {
+        TriggerType::Soc.is_set(self.trigger_type)
+    }
+
+    /// Checks if the 11th bit of the trigger type is set, which indicates that the trigger type is TF.
+    #[inline]
+    pub fn is_tf_trigger(&self) -> bool {
+        TriggerType::Tf.is_set(self.trigger_type)
     }
 }
 
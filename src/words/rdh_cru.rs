@@ -167,7 +167,11 @@ impl<Version: std::marker::Send + std::marker::Sync> super::lib::RDH for RdhCRU<
     }
     #[inline]
     fn payload_size(&self) -> u16 {
-        self.memory_size - 64 // 64 bytes are the RDH size. Payload size is the memory size minus the RDH size.
+        self.memory_size - self.header_size_bytes() // Payload size is the memory size minus the RDH size.
+    }
+    #[inline]
+    fn memory_size(&self) -> u16 {
+        self.memory_size
     }
     #[inline]
     fn offset_to_next(&self) -> u16 {
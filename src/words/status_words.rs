@@ -10,17 +10,11 @@ pub mod util {
     //! These functions takes raw byte slices for performance reasons.
     //! It is crucial that the word is known before being passed to any function here.
 
-    /// Takes a full TDH slice and returns a string description of the trigger field
+    /// Takes a full TDH slice and returns the name of the class of the trigger_type field, e.g. `SOC`, `HB` or `PhT`
     pub fn tdh_trigger_as_string(tdh_slice: &[u8]) -> String {
-        if tdh_soc_trigger(tdh_slice) {
-            String::from("SOC     ")
-        } else if tdh_internal_trigger(tdh_slice) {
-            String::from("Internal")
-        } else if tdh_physics_trigger(tdh_slice) {
-            String::from("PhT     ")
-        } else {
-            String::from("Other   ")
-        }
+        let class =
+            crate::words::rdh::TriggerType::class_name_of(tdh_trigger_type(tdh_slice) as u32);
+        format!("{class:<8}")
     }
 
     /// Takes a full TDH slice and returns a string description of the continuation field
@@ -60,27 +54,26 @@ pub mod util {
         debug_assert!(tdh_slice.len() == 10);
         tdh_slice[1] & 0b10_0000 != 0
     }
-    /// Takes a full TDH slice and returns if continuation bit is set
-    pub fn tdh_continuation(tdh_slice: &[u8]) -> bool {
+    /// Takes a full TDH slice and returns the trigger_orbit field [63:32]
+    pub fn tdh_trigger_orbit(tdh_slice: &[u8]) -> u32 {
         debug_assert!(tdh_slice.len() == 10);
-        tdh_slice[1] & 0b100_0000 != 0
+        u32::from_le_bytes([tdh_slice[4], tdh_slice[5], tdh_slice[6], tdh_slice[7]])
     }
-
-    /// Takes a full TDH slice and returns if the SOC trigger bit [9] is set
-    fn tdh_soc_trigger(tdh_slice: &[u8]) -> bool {
+    /// Takes a full TDH slice and returns the trigger_bc field [27:16]
+    pub fn tdh_trigger_bc(tdh_slice: &[u8]) -> u16 {
         debug_assert!(tdh_slice.len() == 10);
-        const SOC_BIT_MASK: u8 = 0b10;
-        tdh_slice[1] & SOC_BIT_MASK != 0
+        u16::from_le_bytes([tdh_slice[2], tdh_slice[3]]) & 0x0FFF
     }
-    /// Takes a full TDH slice and returns if the internal trigger bit [12] is set
-    fn tdh_internal_trigger(tdh_slice: &[u8]) -> bool {
+    /// Takes a full TDH slice and returns if continuation bit is set
+    pub fn tdh_continuation(tdh_slice: &[u8]) -> bool {
         debug_assert!(tdh_slice.len() == 10);
-        tdh_slice[1] & 0b1_0000 != 0
+        tdh_slice[1] & 0b100_0000 != 0
     }
-    /// Takes a full TDH slice and returns if the physics trigger bit [4] is set
-    fn tdh_physics_trigger(tdh_slice: &[u8]) -> bool {
+
+    /// Takes a full TDH slice and returns the trigger_type field [11:0]
+    pub fn tdh_trigger_type(tdh_slice: &[u8]) -> u16 {
         debug_assert!(tdh_slice.len() == 10);
-        tdh_slice[0] & 0b1_0000 != 0
+        u16::from_le_bytes([tdh_slice[0], tdh_slice[1]]) & 0x0FFF
     }
 
     /// Takes a full TDT slice and returns if packet_done bit is set
@@ -99,6 +92,17 @@ pub mod util {
         }
     }
 
+    /// Takes a full TDT slice and returns the markers of the timeout flags that are set, separated by spaces
+    pub fn tdt_timeouts_as_string(tdt_slice: &[u8]) -> String {
+        debug_assert!(tdt_slice.len() == 10);
+        super::TdtTimeout::ALL
+            .iter()
+            .filter(|timeout| tdt_slice[7] & timeout.bit_mask() != 0)
+            .map(|timeout| timeout.marker())
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
     /// Takes a DDW0 slice and returns true if any lanes status is not OK
     #[allow(dead_code)]
     fn ddw0_lane_status_not_ok(ddw0_slice: &[u8]) -> bool {
@@ -151,11 +155,26 @@ pub mod util {
     }
 
     /// Takes a DDW0 slice and returns if the transmission timeout bit [65] is set
-    #[allow(dead_code)]
-    fn ddw0_transmission_timeout(ddw0_slice: &[u8]) -> bool {
+    pub fn ddw0_transmission_timeout(ddw0_slice: &[u8]) -> bool {
         debug_assert!(ddw0_slice.len() == 10);
         ddw0_slice[8] & 0b10 != 0
     }
+
+    /// Takes a DDW0 slice and returns the index field [71:68]
+    pub fn ddw0_index(ddw0_slice: &[u8]) -> u8 {
+        debug_assert!(ddw0_slice.len() == 10);
+        ddw0_slice[8] >> 4
+    }
+
+    /// Takes a DDW0 slice and returns a string description of the index, followed by a marker if the transmission timeout bit is set
+    pub fn ddw0_index_and_timeout_as_string(ddw0_slice: &[u8]) -> String {
+        let index = ddw0_index(ddw0_slice);
+        if ddw0_transmission_timeout(ddw0_slice) {
+            format!("index {index} TX_TIMEOUT")
+        } else {
+            format!("index {index}")
+        }
+    }
 }
 
 /// Trait to implement for all status words
@@ -166,6 +185,17 @@ pub trait StatusWord: std::fmt::Debug + PartialEq + Sized + ByteSlice + Display
     fn load<T: std::io::Read>(reader: &mut T) -> Result<Self, std::io::Error>
     where
         Self: Sized;
+    /// Deserializes the status word from the 16 bytes of a GBT word in GBT wide mode
+    ///
+    /// The upper 6 bytes are skipped, their layout is not documented
+    fn load_wide<T: std::io::Read>(reader: &mut T) -> Result<Self, std::io::Error>
+    where
+        Self: Sized,
+    {
+        let mut wide_word = [0u8; super::lib::GBT_WIDE_WORD_SIZE];
+        reader.read_exact(&mut wide_word)?;
+        Self::load(&mut &wide_word[..10])
+    }
     /// Sanity check that returns true if all reserved bits are 0
     fn is_reserved_0(&self) -> bool;
 }
@@ -394,6 +424,44 @@ impl PartialEq for Tdh {
     }
 }
 
+/// The timeout flags of a [TDT][Tdt], each indicating a readout problem even if the data is otherwise valid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TdtTimeout {
+    /// The timeout_to_start flag, bit \[63\].
+    ToStart,
+    /// The timeout_start_stop flag, bit \[62\].
+    StartStop,
+    /// The timeout_in_idle flag, bit \[61\].
+    InIdle,
+}
+
+impl TdtTimeout {
+    /// All the timeout flags, in order of their bit position from MSB to LSB.
+    pub const ALL: [TdtTimeout; 3] = [
+        TdtTimeout::ToStart,
+        TdtTimeout::StartStop,
+        TdtTimeout::InIdle,
+    ];
+
+    /// Short marker used to annotate the flag in views.
+    pub fn marker(&self) -> &'static str {
+        match self {
+            TdtTimeout::ToStart => "TO_START",
+            TdtTimeout::StartStop => "TO_START_STOP",
+            TdtTimeout::InIdle => "TO_IDLE",
+        }
+    }
+
+    // Mask of the flag in the byte containing bits [63:56]
+    fn bit_mask(&self) -> u8 {
+        match self {
+            TdtTimeout::ToStart => 0b1000_0000,
+            TdtTimeout::StartStop => 0b0100_0000,
+            TdtTimeout::InIdle => 0b0010_0000,
+        }
+    }
+}
+
 /// Struct representing the TDT
 #[repr(packed)]
 pub struct Tdt {
@@ -433,15 +501,26 @@ impl Tdt {
     }
     /// Returns true if the timeout_to_start bit is set.
     pub fn timeout_to_start(&self) -> bool {
-        (self.timeout_to_start_timeout_start_stop_timeout_in_idle_res2 & 0b1000_0000) != 0
+        self.is_timeout_set(TdtTimeout::ToStart)
     }
     /// Returns true if the timeout_start_stop bit is set.
     pub fn timeout_start_stop(&self) -> bool {
-        (self.timeout_to_start_timeout_start_stop_timeout_in_idle_res2 & 0b0100_0000) != 0
+        self.is_timeout_set(TdtTimeout::StartStop)
     }
     /// Returns true if the timeout_in_idle bit is set.
     pub fn timeout_in_idle(&self) -> bool {
-        (self.timeout_to_start_timeout_start_stop_timeout_in_idle_res2 & 0b0010_0000) != 0
+        self.is_timeout_set(TdtTimeout::InIdle)
+    }
+    /// Returns true if the given timeout flag is set.
+    pub fn is_timeout_set(&self, timeout: TdtTimeout) -> bool {
+        (self.timeout_to_start_timeout_start_stop_timeout_in_idle_res2 & timeout.bit_mask()) != 0
+    }
+    /// Returns the timeout flags that are set.
+    pub fn timeouts(&self) -> Vec<TdtTimeout> {
+        TdtTimeout::ALL
+            .into_iter()
+            .filter(|timeout| self.is_timeout_set(*timeout))
+            .collect()
     }
     /// Returns the integer value of the reserved2 field.
     pub fn reserved2(&self) -> u8 {
@@ -549,6 +628,8 @@ pub struct Ddw0 {
 }
 
 impl Ddw0 {
+    /// Number of lanes in the lane_status field, 2 bits per lane.
+    pub const LANES: u32 = 28;
     /// Returns the integer value of the index field.
     pub fn index(&self) -> u8 {
         (self.index & 0xF0) >> 4
@@ -565,6 +646,13 @@ impl Ddw0 {
     pub fn lane_status(&self) -> u64 {
         self.res3_lane_status & 0x00ff_ffff_ffff_ffff
     }
+    /// Returns a mask with bit N set if lane N has the fatal status (0b11) in the lane_status field.
+    pub fn fatal_lanes(&self) -> u32 {
+        let lane_status = self.lane_status();
+        (0..Self::LANES)
+            .filter(|lane| (lane_status >> (2 * lane)) & 0b11 == 0b11)
+            .fold(0, |mask, lane| mask | 1 << lane)
+    }
     /// Returns the 2 reserved bits 66 & 64 in position 2 & 0.
     pub fn reserved0_1(&self) -> u8 {
         self.index & 0b0000_0101
@@ -699,6 +787,56 @@ impl PartialEq for Cdw {
     }
 }
 
+/// A diagnostic APE word, a GBT word with an ID in the 0xFx range that is not the ID of a [TDT][Tdt] (0xF0), a [CDW][Cdw] (0xF8) or padding (0xFF).
+///
+/// The known IDs are the ALPIDE protocol extension codes added by the readout unit, e.g. 0xF6 for a protocol error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ApeWord(u8);
+
+impl ApeWord {
+    /// Classifies the ID of a GBT word, returns [None] if it is not the ID of an APE word.
+    #[inline]
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0xF0 | 0xF8 => None,
+            0xF1..=0xFE => Some(ApeWord(id)),
+            _ => None,
+        }
+    }
+
+    /// The ID of the APE word.
+    pub fn id(self) -> u8 {
+        self.0
+    }
+
+    /// The name of the APE word, [None] if the ID is not a known APE code.
+    pub fn name(self) -> Option<&'static str> {
+        match self.0 {
+            0xF2 => Some("strip start"),
+            0xF4 => Some("detector timeout"),
+            0xF5 => Some("out of table"),
+            0xF6 => Some("protocol error"),
+            0xF7 => Some("lane FIFO overflow"),
+            0xF9 => Some("pending detector event limit"),
+            0xFA => Some("pending lane event limit"),
+            0xFB => Some("O2N error"),
+            0xFC => Some("rate missing trigger"),
+            0xFD => Some("PE data missing"),
+            0xFE => Some("OOT data missing"),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ApeWord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "APE {name} ({:#04X})", self.0),
+            None => write!(f, "APE {:#04X}", self.0),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -749,6 +887,73 @@ mod tests {
         assert_eq!(tdh, loaded_tdh);
     }
 
+    #[test]
+    fn tdh_load_wide() {
+        // The TDH of `tdh_read_write` followed by 6 upper bytes, which are skipped
+        let raw_data_tdh_wide = [
+            0x03, 0x1A, 0x00, 0x00, 0x75, 0xD5, 0x7D, 0x0B, 0x00, 0xE8, 0x27, 0x00, 0x00, 0x00,
+            0x00, 0x80,
+        ];
+        let tdh = Tdh::load_wide(&mut raw_data_tdh_wide.as_slice()).unwrap();
+        assert_eq!(tdh.to_byte_slice(), &raw_data_tdh_wide[..10]);
+        // Too short to be a wide word
+        assert!(Tdh::load_wide(&mut &raw_data_tdh_wide[..10]).is_err());
+    }
+
+    #[test]
+    fn tdh_trigger_as_string_classes() {
+        use super::util::{tdh_trigger_as_string, tdh_trigger_type};
+        // Internal trigger, no data and continuation bits are set, they are not part of the trigger_type
+        let tdh = |trigger_type: u16| {
+            let [lsb, msb] = trigger_type.to_le_bytes();
+            [
+                lsb,
+                msb | 0x70,
+                0x00,
+                0x00,
+                0x75,
+                0xD5,
+                0x7D,
+                0x0B,
+                0x00,
+                0xE8,
+            ]
+        };
+        let expected_classes = [
+            // ORBIT|HB|SOC|TF
+            (0xA03, "SOC     "),
+            // ORBIT|HB|EOC
+            (0x403, "EOC     "),
+            // ORBIT|HB|SOT
+            (0x083, "SOT     "),
+            // ORBIT|HB|EOT
+            (0x103, "EOT     "),
+            // ORBIT|HB|TF
+            (0x803, "HB      "),
+            (0x010, "PhT     "),
+            (0x040, "CAL     "),
+            (0x008, "HC      "),
+            (0x020, "PP      "),
+            (0x004, "HBr     "),
+            // ORBIT|TF
+            (0x801, "Other   "),
+            (0x000, "Other   "),
+        ];
+        for (trigger_type, expected) in expected_classes {
+            assert_eq!(tdh_trigger_type(&tdh(trigger_type)), trigger_type);
+            assert_eq!(tdh_trigger_as_string(&tdh(trigger_type)), expected);
+        }
+    }
+
+    #[test]
+    fn ddw0_fatal_lanes() {
+        // Lane 0 fatal, lane 1 error, lanes 4 to 7 fatal, lane 27 fatal
+        let raw_data_ddw0 = [0x0B, 0xFF, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x00, 0x00, 0xE4];
+        let ddw0 = Ddw0::load(&mut raw_data_ddw0.as_slice()).unwrap();
+        assert_eq!(ddw0.fatal_lanes(), 1 << 27 | 0b1111_0001);
+        assert_eq!(ddw0.fatal_lanes().count_ones(), 6);
+    }
+
     #[test]
     fn tdt_read_write() {
         const VALID_ID: u8 = 0xF0;
@@ -822,6 +1027,63 @@ mod tests {
         assert_eq!(tdt, loaded_tdt);
     }
 
+    #[test]
+    fn tdt_timeouts() {
+        let timeout_bytes = [
+            (0x80, vec![TdtTimeout::ToStart]),
+            (0x40, vec![TdtTimeout::StartStop]),
+            (0x20, vec![TdtTimeout::InIdle]),
+            (0xA0, vec![TdtTimeout::ToStart, TdtTimeout::InIdle]),
+            (0x1F, vec![]),
+        ];
+        for (timeout_byte, expected) in timeout_bytes {
+            let raw_data_tdt = [0, 0, 0, 0, 0, 0, 0, timeout_byte, 0x01, 0xF0];
+            let tdt = Tdt::load(&mut raw_data_tdt.as_slice()).unwrap();
+            assert_eq!(tdt.timeouts(), expected);
+            assert_eq!(tdt.timeout_to_start(), timeout_byte & 0x80 != 0);
+            assert_eq!(tdt.timeout_start_stop(), timeout_byte & 0x40 != 0);
+            assert_eq!(tdt.timeout_in_idle(), timeout_byte & 0x20 != 0);
+        }
+    }
+
+    #[test]
+    fn tdt_timeouts_as_string() {
+        use super::util::tdt_timeouts_as_string;
+        let tdt_no_timeout = [0, 0, 0, 0, 0, 0, 0, 0x00, 0x01, 0xF0];
+        assert_eq!(tdt_timeouts_as_string(&tdt_no_timeout), "");
+        let tdt_to_start = [0, 0, 0, 0, 0, 0, 0, 0x80, 0x01, 0xF0];
+        assert_eq!(tdt_timeouts_as_string(&tdt_to_start), "TO_START");
+        let tdt_start_stop = [0, 0, 0, 0, 0, 0, 0, 0x40, 0x01, 0xF0];
+        assert_eq!(tdt_timeouts_as_string(&tdt_start_stop), "TO_START_STOP");
+        let tdt_in_idle = [0, 0, 0, 0, 0, 0, 0, 0x20, 0x01, 0xF0];
+        assert_eq!(tdt_timeouts_as_string(&tdt_in_idle), "TO_IDLE");
+        let tdt_all = [0, 0, 0, 0, 0, 0, 0, 0xE0, 0x01, 0xF0];
+        assert_eq!(
+            tdt_timeouts_as_string(&tdt_all),
+            "TO_START TO_START_STOP TO_IDLE"
+        );
+    }
+
+    #[test]
+    fn ddw0_index_and_timeout_as_string() {
+        use super::util::ddw0_index_and_timeout_as_string;
+        let ddw0 = [0, 0, 0, 0, 0, 0, 0, 0, 0x00, 0xE4];
+        assert_eq!(ddw0_index_and_timeout_as_string(&ddw0), "index 0");
+        let ddw0_timeout = [0, 0, 0, 0, 0, 0, 0, 0, 0x02, 0xE4];
+        assert_eq!(
+            ddw0_index_and_timeout_as_string(&ddw0_timeout),
+            "index 0 TX_TIMEOUT"
+        );
+        let ddw0_index_1_timeout = [0, 0, 0, 0, 0, 0, 0, 0, 0x12, 0xE4];
+        assert_eq!(
+            ddw0_index_and_timeout_as_string(&ddw0_index_1_timeout),
+            "index 1 TX_TIMEOUT"
+        );
+        let loaded = Ddw0::load(&mut ddw0_index_1_timeout.as_slice()).unwrap();
+        assert_eq!(loaded.index(), 1);
+        assert!(loaded.transmission_timeout());
+    }
+
     #[test]
     fn ddw0_read_write() {
         const VALID_ID: u8 = 0xE4;
@@ -899,4 +1161,17 @@ mod tests {
         let loaded_cdw = Cdw::load(&mut cdw.to_byte_slice()).unwrap();
         assert_eq!(cdw, loaded_cdw);
     }
+
+    #[test]
+    fn test_ape_word_from_id() {
+        assert_eq!(
+            ApeWord::from_id(0xF6).unwrap().to_string(),
+            "APE protocol error (0xF6)"
+        );
+        assert_eq!(ApeWord::from_id(0xF3).unwrap().to_string(), "APE 0xF3");
+        // TDT, CDW, padding and other words are not APE words
+        for id in [0xF0, 0xF8, 0xFF, 0xE8, 0xE4, 0x40] {
+            assert_eq!(ApeWord::from_id(id), None);
+        }
+    }
 }
@@ -1,4 +1,9 @@
 //! All functionality related to writing data to a file or stdout
 
+pub mod ape_filter;
+pub mod empty_hbfs;
+pub mod failing_cdps;
+pub mod hbf_extract;
 pub mod lib;
+pub mod verify_filter;
 pub mod writer;
@@ -0,0 +1,113 @@
+//! Contains the [ApeFilter] that only keeps the CDPs whose payload contains a diagnostic [ApeWord], set with `--filter-ape`.
+//!
+//! The IDs of the GBT words of each payload are scanned before the CDP is passed to the writer, and the APE words found are counted per type.
+//! A payload that can't be split into GBT words is not kept.
+use crate::input::data_wrapper::CdpChunk;
+use crate::validators::link_validator::payload_gbt_words;
+use crate::words::lib::RDH;
+use crate::words::status_words::ApeWord;
+use std::collections::BTreeMap;
+
+/// The CDPs kept by the [ApeFilter] and the APE words they contain, sent to the [StatsController][crate::stats::stats_controller::StatsController] when the writer finishes.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ApeWordCounts {
+    /// CDPs with at least one APE word.
+    pub cdps: u64,
+    /// Number of APE words of each type, in order of their IDs.
+    pub words: BTreeMap<ApeWord, u64>,
+}
+
+/// Keeps the CDPs with APE words.
+pub struct ApeFilter {
+    gbt_wide: bool,
+    counts: ApeWordCounts,
+}
+
+impl ApeFilter {
+    /// Creates an [ApeFilter], the payload words are 16 byte GBT wide words if `gbt_wide` is set.
+    pub fn new(gbt_wide: bool) -> Self {
+        Self {
+            gbt_wide,
+            counts: ApeWordCounts::default(),
+        }
+    }
+
+    /// Consumes a [CdpChunk] and returns the CDPs whose payload contains an APE word.
+    pub fn filter<T: RDH>(&mut self, cdp_chunk: CdpChunk<T>) -> CdpChunk<T> {
+        let mut selected = CdpChunk::new();
+        for cdp in cdp_chunk.into_iter() {
+            if self.count_ape_words(&cdp.1, cdp.0.data_format()) {
+                selected.push_tuple(cdp);
+            }
+        }
+        selected
+    }
+
+    /// Counts the APE words of a payload, returns true if it has any.
+    fn count_ape_words(&mut self, payload: &[u8], data_format: u8) -> bool {
+        let gbt_words = match payload_gbt_words(payload, data_format, self.gbt_wide) {
+            Ok(gbt_words) => gbt_words,
+            Err(_) => return false,
+        };
+        let mut has_ape_words = false;
+        for ape_word in gbt_words.filter_map(|gbt_word| ApeWord::from_id(gbt_word[9])) {
+            *self.counts.words.entry(ape_word).or_default() += 1;
+            has_ape_words = true;
+        }
+        if has_ape_words {
+            self.counts.cdps += 1;
+        }
+        has_ape_words
+    }
+
+    /// Returns the CDPs kept and the APE words counted.
+    pub fn finish(self) -> ApeWordCounts {
+        self.counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh_cru::{test_data::CORRECT_RDH_CRU_V7, RdhCRU, V7};
+
+    const IHW: [u8; 10] = [0xFF, 0x3F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE0];
+    const TDT: [u8; 10] = [0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xF0];
+    const APE_PROTOCOL_ERROR: [u8; 10] =
+        [0x2A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF6];
+    const APE_STRIP_START: [u8; 10] = [0x2A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF2];
+
+    /// A CDP in data format 2, with the GBT words as the payload, padded to 16 bytes
+    fn cdp(gbt_words: &[[u8; 10]], mem_pos: u64) -> (RdhCRU<V7>, Vec<u8>, u64) {
+        let mut payload = gbt_words.concat();
+        while payload.len() % 16 != 0 {
+            payload.push(0xFF);
+        }
+        (CORRECT_RDH_CRU_V7, payload, mem_pos)
+    }
+
+    #[test]
+    fn test_keep_cdps_with_ape_words() {
+        let mut cdp_chunk = CdpChunk::new();
+        cdp_chunk.push_tuple(cdp(&[IHW, TDT], 0x00));
+        cdp_chunk.push_tuple(cdp(&[IHW, APE_PROTOCOL_ERROR, APE_STRIP_START], 0x100));
+        cdp_chunk.push_tuple(cdp(&[], 0x200));
+        cdp_chunk.push_tuple(cdp(&[APE_PROTOCOL_ERROR, IHW], 0x300));
+        let mut ape_filter = ApeFilter::new(false);
+        let selected = ape_filter.filter(cdp_chunk);
+        assert_eq!(selected.rdh_mem_pos_slice(), [0x100, 0x300]);
+        let counts = ape_filter.finish();
+        assert_eq!(counts.cdps, 2);
+        assert_eq!(
+            counts
+                .words
+                .iter()
+                .map(|(ape_word, count)| (ape_word.to_string(), *count))
+                .collect::<Vec<_>>(),
+            [
+                ("APE strip start (0xF2)".to_string(), 1),
+                ("APE protocol error (0xF6)".to_string(), 2)
+            ]
+        );
+    }
+}
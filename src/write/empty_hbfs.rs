@@ -0,0 +1,189 @@
+//! Contains the [EmptyHbfDropper] that drops the HBFs without data words from the CDPs passed to the writer, set with `--drop-empty-hbfs`.
+//!
+//! The pages of an HBF are buffered until a page with a data word is seen, then the buffered pages and the rest of the HBF are written,
+//! if the HBF ends without a data word, all of its pages are dropped. Either all pages of an HBF are written, or none.
+//! An HBF starts with a page with pages_counter 0 or after a stop page, and an HBF left open at the end of the input without a data word is dropped.
+use super::hbf_extract::into_cdp_chunk;
+use crate::input::data_wrapper::CdpChunk;
+use crate::stats::pipeline_counts::CdpCount;
+use crate::validators::link_validator::payload_gbt_words;
+use crate::words::data_words::DataWordKind;
+use crate::words::lib::RDH;
+
+type CdpTuple<T> = (T, Vec<u8>, u64);
+
+/// The HBF of a link being written or buffered.
+struct LinkHbf<T: RDH> {
+    link_id: u8,
+    /// Pages of the HBF without a data word so far.
+    pages: Vec<CdpTuple<T>>,
+    /// A data word was seen in the HBF, the rest of its pages are written as they come.
+    has_data: bool,
+}
+
+/// HBFs dropped by the [EmptyHbfDropper], sent to the [StatsController][crate::stats::stats_controller::StatsController] when the writer finishes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DroppedEmptyHbfs {
+    /// HBFs without data words.
+    pub hbfs: u64,
+    /// CDPs of the HBFs without data words.
+    pub cdps: CdpCount,
+}
+
+/// Drops the HBFs of each link that contain no data words, only RDHs and status words.
+pub struct EmptyHbfDropper<T: RDH> {
+    links: Vec<LinkHbf<T>>,
+    gbt_wide: bool,
+    dropped: DroppedEmptyHbfs,
+}
+
+impl<T: RDH> EmptyHbfDropper<T> {
+    /// Creates an [EmptyHbfDropper], the payload words are 16 byte GBT wide words if `gbt_wide` is set.
+    pub fn new(gbt_wide: bool) -> Self {
+        Self {
+            links: Vec::new(),
+            gbt_wide,
+            dropped: DroppedEmptyHbfs::default(),
+        }
+    }
+
+    /// Consumes a [CdpChunk] and returns the CDPs of HBFs with data words that can be written now, in the order of their memory positions.
+    pub fn filter(&mut self, cdp_chunk: CdpChunk<T>) -> CdpChunk<T> {
+        let mut selected = Vec::new();
+        for cdp in cdp_chunk.into_iter() {
+            let link_id = cdp.0.link_id();
+            let link_idx = match self.links.iter().position(|link| link.link_id == link_id) {
+                Some(idx) => idx,
+                None => {
+                    self.links.push(LinkHbf {
+                        link_id,
+                        pages: Vec::new(),
+                        has_data: false,
+                    });
+                    self.links.len() - 1
+                }
+            };
+            if cdp.0.pages_counter() == 0 {
+                // An HBF without a stop page ends at the start of the next
+                self.end_hbf(link_idx);
+            }
+            let stop_bit = cdp.0.stop_bit() == 1;
+            let gbt_wide = self.gbt_wide;
+            let link = &mut self.links[link_idx];
+            if link.has_data {
+                selected.push(cdp);
+            } else if has_data_words(&cdp.1, cdp.0.data_format(), gbt_wide) {
+                link.has_data = true;
+                selected.append(&mut link.pages);
+                selected.push(cdp);
+            } else {
+                link.pages.push(cdp);
+            }
+            if stop_bit {
+                self.end_hbf(link_idx);
+            }
+        }
+        into_cdp_chunk(selected)
+    }
+
+    /// Ends the HBF of a link, its pages are dropped if they are still buffered.
+    fn end_hbf(&mut self, link_idx: usize) {
+        let link = &mut self.links[link_idx];
+        if !link.pages.is_empty() {
+            self.dropped.hbfs += 1;
+            link.pages.drain(..).for_each(|(_, payload, _)| {
+                self.dropped.cdps += CdpCount {
+                    cdps: 1,
+                    bytes: 64 + payload.len() as u64,
+                }
+            });
+        }
+        link.has_data = false;
+    }
+
+    /// Drops the HBFs left open at the end of the input without a data word, and returns the HBFs dropped.
+    pub fn finish(mut self) -> DroppedEmptyHbfs {
+        (0..self.links.len()).for_each(|link_idx| self.end_hbf(link_idx));
+        self.dropped
+    }
+}
+
+/// Checks if a payload contains a data word, a payload that can't be split into GBT words is assumed to contain data, so it is not dropped.
+fn has_data_words(payload: &[u8], data_format: u8, gbt_wide: bool) -> bool {
+    match payload_gbt_words(payload, data_format, gbt_wide) {
+        Ok(mut gbt_words) => gbt_words.any(|gbt_word| DataWordKind::from_id(gbt_word[9]).is_some()),
+        Err(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh_cru::{test_data::CORRECT_RDH_CRU_V7, RdhCRU, V7};
+
+    const IHW: [u8; 10] = [0xFF, 0x3F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE0];
+    const TDH: [u8; 10] = [0x03, 0x1A, 0x00, 0x00, 0x75, 0xD5, 0x7D, 0x0B, 0x00, 0xE8];
+    const OB_DATA_WORD: [u8; 10] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40];
+
+    /// A page of a link in data format 2, with the GBT words as the payload, padded to 16 bytes
+    fn page(
+        link_id: u8,
+        pages_counter: u16,
+        stop_bit: bool,
+        gbt_words: &[[u8; 10]],
+        mem_pos: u64,
+    ) -> CdpTuple<RdhCRU<V7>> {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        rdh.link_id = link_id;
+        rdh.rdh2.pages_counter = pages_counter;
+        rdh.rdh2.stop_bit = stop_bit as u8;
+        let mut payload = gbt_words.concat();
+        while payload.len() % 16 != 0 {
+            payload.push(0xFF);
+        }
+        (rdh, payload, mem_pos)
+    }
+
+    #[test]
+    fn test_drop_empty_hbfs() {
+        let mut dropper = EmptyHbfDropper::new(false);
+        let selected = dropper.filter(into_cdp_chunk(vec![
+            // Empty HBF on link 0, interleaved with an HBF with data on link 2
+            page(0, 0, false, &[IHW, TDH], 0x000),
+            page(2, 0, false, &[IHW, TDH], 0x100),
+            page(0, 1, true, &[], 0x200),
+            page(2, 1, false, &[OB_DATA_WORD], 0x300),
+        ]));
+        // The first page of link 2 is written with the page that has a data word
+        assert_eq!(selected.rdh_mem_pos_slice(), [0x100, 0x300]);
+        let selected = dropper.filter(into_cdp_chunk(vec![
+            page(2, 2, true, &[], 0x400),
+            page(0, 0, false, &[IHW, TDH, OB_DATA_WORD], 0x500),
+            // An HBF left open without data
+            page(2, 0, false, &[IHW, TDH], 0x600),
+        ]));
+        assert_eq!(selected.rdh_mem_pos_slice(), [0x400, 0x500]);
+        assert_eq!(
+            dropper.finish(),
+            DroppedEmptyHbfs {
+                hbfs: 2,
+                cdps: CdpCount {
+                    cdps: 3,
+                    bytes: 3 * 64 + 32 + 32
+                }
+            }
+        );
+    }
+
+    #[test]
+    fn test_hbf_without_stop_page_ends_at_next_hbf() {
+        let mut dropper = EmptyHbfDropper::new(false);
+        let selected = dropper.filter(into_cdp_chunk(vec![
+            page(0, 0, false, &[IHW, TDH], 0x000),
+            page(0, 1, false, &[], 0x100),
+            page(0, 0, false, &[IHW, TDH, OB_DATA_WORD], 0x200),
+        ]));
+        assert_eq!(selected.rdh_mem_pos_slice(), [0x200]);
+        assert_eq!(dropper.finish().hbfs, 1);
+    }
+}
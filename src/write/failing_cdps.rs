@@ -0,0 +1,109 @@
+//! Contains [spawn_failing_cdps_writer] that writes the CDPs that failed the checks to a separate file, set with `--save-failing-cdps`.
+//!
+//! The link validators send each CDP they reported an error for, which is written unmodified, so the file can be read and checked again by fastpasta.
+//! The CDPs of different links are written in the order they failed, which is not necessarily their order in the input.
+use super::writer::{BufferedWriter, Writer};
+use crate::input::data_wrapper::CdpChunk;
+use crate::stats::stats_controller::StatType;
+use crate::words::lib::RDH;
+
+/// Max number of failing CDPs buffered before they are written.
+const BUFFER_SIZE: usize = 1024;
+
+type CdpTuple<T> = (T, Vec<u8>, u64);
+
+/// The CDPs written to the file of failing CDPs, sent to the [StatsController][crate::stats::stats_controller::StatsController] when the writer finishes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailingCdps {
+    /// The file the CDPs were written to.
+    pub path: std::path::PathBuf,
+    /// Memory positions of the RDHs of the CDPs in the input, in ascending order.
+    pub mem_positions: Vec<u64>,
+}
+
+/// Creates the file of failing CDPs and spawns a thread writing the CDPs received on the returned channel to it.
+///
+/// When all senders are dropped, the writer is finalized, and the [FailingCdps] written are sent to the [StatsController][crate::stats::stats_controller::StatsController],
+/// or an error if the remaining CDPs could not be written.
+pub fn spawn_failing_cdps_writer<T: RDH + 'static>(
+    path: &std::path::Path,
+    stats_send_ch: std::sync::mpsc::Sender<StatType>,
+) -> std::io::Result<(
+    crossbeam_channel::Sender<CdpTuple<T>>,
+    std::thread::JoinHandle<()>,
+)> {
+    let mut writer = BufferedWriter::<T>::to_file(path, BUFFER_SIZE, stats_send_ch.clone())?;
+    let path = path.to_owned();
+    let (send_ch, recv_ch) = crossbeam_channel::bounded::<CdpTuple<T>>(crate::CHANNEL_CDP_CAPACITY);
+    let handle = std::thread::Builder::new()
+        .name("Failing CDPs Writer".to_string())
+        .spawn(move || {
+            let mut mem_positions = Vec::new();
+            for (rdh, payload, mem_pos) in recv_ch {
+                mem_positions.push(mem_pos);
+                let mut cdp_chunk = CdpChunk::new();
+                cdp_chunk.push(rdh, payload, mem_pos);
+                writer.push_cdp_chunk(cdp_chunk);
+            }
+            match writer.finalize() {
+                Ok(_) => {
+                    mem_positions.sort_unstable();
+                    stats_send_ch
+                        .send(StatType::FailingCdps(Box::new(FailingCdps {
+                            path,
+                            mem_positions,
+                        })))
+                        .unwrap();
+                }
+                Err(e) => stats_send_ch
+                    .send(StatType::Error(format!(
+                        "Failed to write the failing CDPs to {}: {e}",
+                        path.display()
+                    )))
+                    .unwrap(),
+            }
+        })?;
+    Ok((send_ch, handle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::lib::ByteSlice;
+    use crate::words::rdh_cru::{test_data::CORRECT_RDH_CRU_V7, RdhCRU, V7};
+
+    #[test]
+    fn test_failing_cdps_written_and_reported_in_order() {
+        let path = std::env::temp_dir().join("fastpasta_test_failing_cdps.raw");
+        let (send_stats_ch, recv_stats_ch) = std::sync::mpsc::channel();
+        let (failing_cdps_ch, handle) =
+            spawn_failing_cdps_writer::<RdhCRU<V7>>(&path, send_stats_ch).unwrap();
+        // Failing CDPs of different links are received out of order
+        failing_cdps_ch
+            .send((CORRECT_RDH_CRU_V7, vec![0xAA; 16], 0x100))
+            .unwrap();
+        failing_cdps_ch
+            .send((CORRECT_RDH_CRU_V7, vec![0xBB; 16], 0x40))
+            .unwrap();
+        drop(failing_cdps_ch);
+        handle.join().unwrap();
+
+        let written = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut expected = CORRECT_RDH_CRU_V7.to_byte_slice().to_vec();
+        expected.extend([0xAA; 16]);
+        expected.extend(CORRECT_RDH_CRU_V7.to_byte_slice());
+        expected.extend([0xBB; 16]);
+        assert_eq!(written, expected);
+        match recv_stats_ch.try_recv().unwrap() {
+            StatType::FailingCdps(failing_cdps) => assert_eq!(
+                *failing_cdps,
+                FailingCdps {
+                    path,
+                    mem_positions: vec![0x40, 0x100]
+                }
+            ),
+            _ => panic!("Expected the failing CDPs"),
+        }
+    }
+}
@@ -0,0 +1,438 @@
+//! Contains the [HbfExtractor] that selects the first or last N complete HBFs of each link from the CDPs passed to the writer.
+//!
+//! An HBF is complete if it starts with a page with pages_counter 0 and ends with a stop page, pages before the first opening page of a link and an HBF left open at the end of the input are not written.
+//! The pages of an HBF are buffered until its stop page, and with the last N HBFs, the complete HBFs are buffered until the end of the input.
+//! The buffered bytes of each link are capped, if an HBF or the last N HBFs of a link exceed the cap, the oldest pages are dropped and a warning is reported.
+//! An HBF that reaches the [HbfCap] without a stop page is finalized as truncated with a warning, so a never-closing HBF can't grow without bound, and the rest of its pages are dropped.
+use crate::input::data_wrapper::CdpChunk;
+use crate::stats::stats_controller::StatType;
+use crate::util::config::{HbfCap, HbfExtract};
+use crate::words::lib::RDH;
+use std::collections::VecDeque;
+
+/// Bytes of CDPs that are buffered per link.
+pub const MAX_BUFFERED_BYTES_PER_LINK: u64 = 256 * 1024 * 1024;
+
+type CdpTuple<T> = (T, Vec<u8>, u64);
+
+/// The HBFs of a link, the one being assembled and the complete ones that are not written yet.
+struct LinkHbfs<T: RDH> {
+    link_id: u8,
+    /// Pages of the HBF being assembled, empty before an opening page.
+    open_hbf: Vec<CdpTuple<T>>,
+    /// Bytes of the HBF being assembled.
+    open_hbf_bytes: u64,
+    /// The last HBF returned by [LinkHbfs::push] reached the [HbfCap] and was truncated.
+    truncated: bool,
+    /// Complete HBFs that are not written yet, only kept with the last N HBFs.
+    complete_hbfs: VecDeque<Vec<CdpTuple<T>>>,
+    /// Complete HBFs seen so far.
+    hbfs_seen: u32,
+    /// Bytes of the open and complete HBFs.
+    buffered_bytes: u64,
+    /// The cap of the buffered bytes was exceeded and reported.
+    cap_exceeded: bool,
+}
+
+impl<T: RDH> LinkHbfs<T> {
+    fn new(link_id: u8) -> Self {
+        Self {
+            link_id,
+            open_hbf: Vec::new(),
+            open_hbf_bytes: 0,
+            truncated: false,
+            complete_hbfs: VecDeque::new(),
+            hbfs_seen: 0,
+            buffered_bytes: 0,
+            cap_exceeded: false,
+        }
+    }
+
+    /// Adds a page to the open HBF, returns the HBF if the page completes it, or if the HBF reaches the `hbf_cap` without a stop page.
+    fn push(&mut self, cdp: CdpTuple<T>, hbf_cap: HbfCap) -> Option<Vec<CdpTuple<T>>> {
+        let rdh = &cdp.0;
+        if rdh.pages_counter() == 0 {
+            // An HBF without a stop page is dropped
+            self.drop_open_hbf();
+        } else if self.open_hbf.is_empty() {
+            // Not the start of a complete HBF
+            return None;
+        }
+        let stop_bit = rdh.stop_bit() == 1;
+        self.buffered_bytes += cdp_bytes(&cdp);
+        self.open_hbf_bytes += cdp_bytes(&cdp);
+        self.open_hbf.push(cdp);
+        self.truncated = !stop_bit
+            && (self.open_hbf.len() as u64 >= hbf_cap.pages as u64
+                || self.open_hbf_bytes >= hbf_cap.bytes);
+        if !stop_bit && !self.truncated {
+            return None;
+        }
+        self.hbfs_seen += 1;
+        self.open_hbf_bytes = 0;
+        Some(std::mem::take(&mut self.open_hbf))
+    }
+
+    fn drop_open_hbf(&mut self) {
+        self.buffered_bytes -= self.open_hbf_bytes;
+        self.open_hbf_bytes = 0;
+        self.open_hbf.clear();
+    }
+
+    fn drop_oldest_complete_hbf(&mut self) {
+        if let Some(hbf) = self.complete_hbfs.pop_front() {
+            self.buffered_bytes -= hbf.iter().map(cdp_bytes).sum::<u64>();
+        }
+    }
+}
+
+/// Bytes of a CDP in the output.
+fn cdp_bytes<T: RDH>(cdp: &CdpTuple<T>) -> u64 {
+    64 + cdp.1.len() as u64
+}
+
+/// Selects the first or last N complete HBFs of each link.
+pub struct HbfExtractor<T: RDH> {
+    extract: HbfExtract,
+    links: Vec<LinkHbfs<T>>,
+    hbf_cap: HbfCap,
+    max_buffered_bytes_per_link: u64,
+    stats_send_ch: std::sync::mpsc::Sender<StatType>,
+}
+
+impl<T: RDH> HbfExtractor<T> {
+    /// Creates an [HbfExtractor] selecting the HBFs of `extract`, truncating HBFs at `hbf_cap`, and a producer channel to report truncated HBFs and links exceeding the cap of buffered bytes.
+    pub fn new(
+        extract: HbfExtract,
+        hbf_cap: HbfCap,
+        stats_send_ch: std::sync::mpsc::Sender<StatType>,
+    ) -> Self {
+        Self::with_max_buffered_bytes(extract, hbf_cap, MAX_BUFFERED_BYTES_PER_LINK, stats_send_ch)
+    }
+
+    fn with_max_buffered_bytes(
+        extract: HbfExtract,
+        hbf_cap: HbfCap,
+        max_buffered_bytes_per_link: u64,
+        stats_send_ch: std::sync::mpsc::Sender<StatType>,
+    ) -> Self {
+        Self {
+            extract,
+            links: Vec::new(),
+            hbf_cap,
+            max_buffered_bytes_per_link,
+            stats_send_ch,
+        }
+    }
+
+    /// Consumes a [CdpChunk] and returns the CDPs that can be written now, in the order of their memory positions.
+    ///
+    /// With the first N HBFs, these are the pages of the HBFs completed by the chunk, with the last N HBFs nothing is written before [HbfExtractor::finish].
+    pub fn extract(&mut self, cdp_chunk: CdpChunk<T>) -> CdpChunk<T> {
+        let mut selected = Vec::new();
+        for cdp in cdp_chunk.into_iter() {
+            let link_id = cdp.0.link_id();
+            let link_idx = match self.links.iter().position(|link| link.link_id == link_id) {
+                Some(idx) => idx,
+                None => {
+                    self.links.push(LinkHbfs::new(link_id));
+                    self.links.len() - 1
+                }
+            };
+            let link = &mut self.links[link_idx];
+            let hbf = match self.extract {
+                HbfExtract::First(hbfs) if link.hbfs_seen >= hbfs => continue,
+                _ => link.push(cdp, self.hbf_cap),
+            };
+            if let Some(hbf) = hbf {
+                if link.truncated {
+                    self.report_truncated_hbf(link_id, &hbf);
+                }
+                let link = &mut self.links[link_idx];
+                match self.extract {
+                    HbfExtract::First(_) => {
+                        // Nothing else is buffered with the first N HBFs
+                        link.buffered_bytes = 0;
+                        selected.extend(hbf);
+                    }
+                    HbfExtract::Last(hbfs) => {
+                        link.complete_hbfs.push_back(hbf);
+                        if link.complete_hbfs.len() > hbfs as usize {
+                            link.drop_oldest_complete_hbf();
+                        }
+                    }
+                }
+            }
+            self.enforce_cap(link_idx);
+        }
+        into_cdp_chunk(selected)
+    }
+
+    /// Reports an HBF that reached the [HbfCap] without a stop page, with the memory positions of its first and last page.
+    fn report_truncated_hbf(&self, link_id: u8, hbf: &[CdpTuple<T>]) {
+        let (first_mem_pos, last_mem_pos) = match (hbf.first(), hbf.last()) {
+            (Some(first), Some(last)) => (first.2, last.2),
+            _ => return,
+        };
+        self.stats_send_ch
+            .send(StatType::Warning(format!(
+                "Link {link_id}: HBF at {first_mem_pos:#X} reached {} pages or {} bytes without a stop page at {last_mem_pos:#X}, it is truncated and the rest of its pages are dropped",
+                self.hbf_cap.pages, self.hbf_cap.bytes
+            )))
+            .unwrap();
+    }
+
+    /// Drops the oldest buffered pages of a link while it exceeds the cap, and reports it the first time.
+    fn enforce_cap(&mut self, link_idx: usize) {
+        let max_bytes = self.max_buffered_bytes_per_link;
+        let link = &mut self.links[link_idx];
+        if link.buffered_bytes <= max_bytes {
+            return;
+        }
+        while link.buffered_bytes > max_bytes && !link.complete_hbfs.is_empty() {
+            link.drop_oldest_complete_hbf();
+        }
+        if link.buffered_bytes > max_bytes {
+            link.drop_open_hbf();
+        }
+        if !link.cap_exceeded {
+            link.cap_exceeded = true;
+            self.stats_send_ch
+                .send(StatType::Warning(format!(
+                    "Link {}: HBFs to extract exceed {} buffered bytes, the oldest pages are dropped",
+                    link.link_id, max_bytes
+                )))
+                .unwrap();
+        }
+    }
+
+    /// Returns true if the first N HBFs of all links seen so far are selected, the rest of the input is then not needed.
+    pub fn all_links_done(&self) -> bool {
+        match self.extract {
+            HbfExtract::First(hbfs) => {
+                !self.links.is_empty() && self.links.iter().all(|link| link.hbfs_seen >= hbfs)
+            }
+            HbfExtract::Last(_) => false,
+        }
+    }
+
+    /// Returns the CDPs that are left to write at the end of the input, the last N complete HBFs of each link in the order of their memory positions.
+    ///
+    /// HBFs that are still open are not complete and dropped.
+    pub fn finish(self) -> CdpChunk<T> {
+        let selected = self
+            .links
+            .into_iter()
+            .flat_map(|link| link.complete_hbfs.into_iter().flatten())
+            .collect();
+        into_cdp_chunk(selected)
+    }
+}
+
+/// Collects CDPs into a [CdpChunk], in the order of their memory positions.
+pub(super) fn into_cdp_chunk<T: RDH>(mut cdps: Vec<CdpTuple<T>>) -> CdpChunk<T> {
+    cdps.sort_by_key(|(_, _, mem_pos)| *mem_pos);
+    let mut cdp_chunk = CdpChunk::with_capacity(cdps.len());
+    cdps.into_iter()
+        .for_each(|cdp_tuple| cdp_chunk.push_tuple(cdp_tuple));
+    cdp_chunk
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::rdh_cru::{test_data::CORRECT_RDH_CRU_V7, RdhCRU, V7};
+
+    const CDP_SIZE: u64 = 0x100;
+
+    const NO_HBF_CAP: HbfCap = HbfCap {
+        pages: u32::MAX,
+        bytes: u64::MAX,
+    };
+
+    /// `hbfs` HBFs of 3 pages on links 0 and 2, interleaved page by page, starting with the last page of an HBF on link 0
+    fn two_links_cdps(hbfs: u32) -> Vec<CdpTuple<RdhCRU<V7>>> {
+        let mut pages = vec![(0, 2)];
+        for _ in 0..hbfs {
+            for pages_counter in 0..3 {
+                pages.push((0, pages_counter));
+                pages.push((2, pages_counter));
+            }
+        }
+        pages
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (link_id, pages_counter))| {
+                let mut rdh = CORRECT_RDH_CRU_V7;
+                rdh.link_id = link_id;
+                rdh.rdh2.pages_counter = pages_counter;
+                rdh.rdh2.stop_bit = (pages_counter == 2) as u8;
+                let payload = vec![0; (CDP_SIZE - 64) as usize];
+                (rdh, payload, idx as u64 * CDP_SIZE)
+            })
+            .collect()
+    }
+
+    /// The link ID and pages counter of each CDP
+    fn pages(cdp_chunk: &CdpChunk<RdhCRU<V7>>) -> Vec<(u8, u16)> {
+        cdp_chunk
+            .rdh_slice()
+            .iter()
+            .map(|rdh| (rdh.link_id, rdh.rdh2.pages_counter))
+            .collect()
+    }
+
+    fn complete_hbfs(cdp_chunk: &CdpChunk<RdhCRU<V7>>, link_id: u8) -> usize {
+        cdp_chunk
+            .rdh_slice()
+            .iter()
+            .filter(|rdh| rdh.link_id == link_id && rdh.rdh2.stop_bit == 1)
+            .count()
+    }
+
+    #[test]
+    fn test_first_hbfs() {
+        let (send_stats_ch, _recv_stats_ch) = std::sync::mpsc::channel();
+        let mut extractor = HbfExtractor::new(HbfExtract::First(2), NO_HBF_CAP, send_stats_ch);
+        // The chunk boundary splits the second HBF of each link
+        let mut cdps = two_links_cdps(4);
+        let second_chunk = cdps.split_off(1 + 6 + 3);
+        let first_selected = extractor.extract(into_cdp_chunk(cdps));
+        // The last page of the HBF before the first opening page is not written
+        assert_eq!(
+            pages(&first_selected),
+            vec![(0, 0), (2, 0), (0, 1), (2, 1), (0, 2), (2, 2)]
+        );
+        assert!(!extractor.all_links_done());
+        let second_selected = extractor.extract(into_cdp_chunk(second_chunk));
+        assert_eq!(complete_hbfs(&second_selected, 0), 1);
+        assert_eq!(complete_hbfs(&second_selected, 2), 1);
+        assert_eq!(second_selected.len(), 6);
+        assert!(extractor.all_links_done());
+        assert!(extractor.finish().is_empty());
+    }
+
+    #[test]
+    fn test_last_hbfs() {
+        let (send_stats_ch, recv_stats_ch) = std::sync::mpsc::channel();
+        let mut extractor = HbfExtractor::new(HbfExtract::Last(2), NO_HBF_CAP, send_stats_ch);
+        let mut cdps = two_links_cdps(4);
+        // An HBF left open at the end of the input
+        let mut open_hbf_rdh = CORRECT_RDH_CRU_V7;
+        open_hbf_rdh.link_id = 2;
+        open_hbf_rdh.rdh2.pages_counter = 0;
+        cdps.push((open_hbf_rdh, vec![], 0x10000));
+        assert!(extractor.extract(into_cdp_chunk(cdps)).is_empty());
+        assert!(!extractor.all_links_done());
+        let selected = extractor.finish();
+        assert_eq!(complete_hbfs(&selected, 0), 2);
+        assert_eq!(complete_hbfs(&selected, 2), 2);
+        assert_eq!(selected.len(), 12);
+        // The last 2 HBFs in order of memory position
+        let first_mem_pos = (1 + 2 * 6) * CDP_SIZE;
+        assert_eq!(
+            selected.rdh_mem_pos_slice(),
+            (0..12)
+                .map(|idx| first_mem_pos + idx * CDP_SIZE)
+                .collect::<Vec<u64>>()
+        );
+        assert!(recv_stats_ch.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_last_hbfs_capped() {
+        let (send_stats_ch, recv_stats_ch) = std::sync::mpsc::channel();
+        // Only room for a single HBF of 3 pages per link
+        let mut extractor = HbfExtractor::with_max_buffered_bytes(
+            HbfExtract::Last(2),
+            NO_HBF_CAP,
+            4 * CDP_SIZE - 1,
+            send_stats_ch,
+        );
+        extractor.extract(into_cdp_chunk(two_links_cdps(4)));
+        let selected = extractor.finish();
+        assert_eq!(complete_hbfs(&selected, 0), 1);
+        assert_eq!(complete_hbfs(&selected, 2), 1);
+        let warnings: Vec<String> = recv_stats_ch
+            .try_iter()
+            .filter_map(|stat| match stat {
+                StatType::Warning(msg) => Some(msg),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "Link 0: HBFs to extract exceed 1023 buffered bytes, the oldest pages are dropped",
+                "Link 2: HBFs to extract exceed 1023 buffered bytes, the oldest pages are dropped"
+            ]
+        );
+    }
+
+    /// An HBF of `pages` pages on link 0 that never closes, followed by a complete HBF of 3 pages
+    fn never_closing_hbf_cdps(pages: u16) -> Vec<CdpTuple<RdhCRU<V7>>> {
+        (0..pages)
+            .chain(0..3)
+            .enumerate()
+            .map(|(idx, pages_counter)| {
+                let mut rdh = CORRECT_RDH_CRU_V7;
+                rdh.link_id = 0;
+                rdh.rdh2.pages_counter = pages_counter;
+                rdh.rdh2.stop_bit = (idx == pages as usize + 2) as u8;
+                let payload = vec![0; (CDP_SIZE - 64) as usize];
+                (rdh, payload, idx as u64 * CDP_SIZE)
+            })
+            .collect()
+    }
+
+    fn warnings(recv_stats_ch: &std::sync::mpsc::Receiver<StatType>) -> Vec<String> {
+        recv_stats_ch
+            .try_iter()
+            .filter_map(|stat| match stat {
+                StatType::Warning(msg) => Some(msg),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_never_closing_hbf_truncated_at_page_cap() {
+        let (send_stats_ch, recv_stats_ch) = std::sync::mpsc::channel();
+        let hbf_cap = HbfCap {
+            pages: 5,
+            bytes: u64::MAX,
+        };
+        let mut extractor = HbfExtractor::new(HbfExtract::Last(2), hbf_cap, send_stats_ch);
+        for cdp in never_closing_hbf_cdps(1000) {
+            extractor.extract(into_cdp_chunk(vec![cdp]));
+            // The pages buffered of the open HBF never exceed the cap
+            assert!(extractor.links[0].open_hbf.len() <= 5);
+        }
+        let selected = extractor.finish();
+        // The truncated HBF and the complete HBF after it
+        assert_eq!(
+            selected.rdh_mem_pos_slice(),
+            [0, 1, 2, 3, 4, 1000, 1001, 1002].map(|idx| idx * CDP_SIZE)
+        );
+        assert_eq!(
+            warnings(&recv_stats_ch),
+            ["Link 0: HBF at 0x0 reached 5 pages or 18446744073709551615 bytes without a stop page at 0x400, it is truncated and the rest of its pages are dropped"]
+        );
+    }
+
+    #[test]
+    fn test_never_closing_hbf_truncated_at_byte_cap() {
+        let (send_stats_ch, recv_stats_ch) = std::sync::mpsc::channel();
+        let hbf_cap = HbfCap {
+            pages: u32::MAX,
+            bytes: 3 * CDP_SIZE,
+        };
+        let mut extractor = HbfExtractor::new(HbfExtract::First(1), hbf_cap, send_stats_ch);
+        let selected = extractor.extract(into_cdp_chunk(never_closing_hbf_cdps(100)));
+        assert_eq!(pages(&selected), vec![(0, 0), (0, 1), (0, 2)]);
+        assert!(extractor.all_links_done());
+        assert_eq!(warnings(&recv_stats_ch).len(), 1);
+        assert!(extractor.links[0].open_hbf.is_empty());
+        assert_eq!(extractor.links[0].buffered_bytes, 0);
+    }
+}
@@ -5,9 +5,15 @@ use std::thread;
 
 use crossbeam_channel::Receiver;
 
+use super::ape_filter::ApeFilter;
+use super::empty_hbfs::EmptyHbfDropper;
+use super::hbf_extract::HbfExtractor;
 use super::writer::BufferedWriter;
 use super::writer::Writer;
 use crate::input::data_wrapper::CdpChunk;
+use crate::stats::memory_usage::InFlightBytes;
+use crate::stats::pipeline_counts::{CdpCount, ComponentCounts};
+use crate::stats::stats_controller::StatType;
 use crate::util::lib::Config;
 use crate::words::lib::RDH;
 
@@ -17,30 +23,127 @@ use crate::words::lib::RDH;
 const BUFFER_SIZE: usize = 1024 * 1024; // 1MB buffer
 
 /// Spawns a thread with the Writer running, and returns the thread handle.
+///
+/// If the first or last N HBFs of each link are extracted, the CDPs pass through an [HbfExtractor] before they are written.
+/// If the HBFs without data words are dropped, the CDPs pass through an [EmptyHbfDropper] first, so only HBFs with data are extracted.
+/// If only the CDPs with APE words are kept, they pass through an [ApeFilter] before anything else.
+/// Once the first N HBFs of all links seen so far are written, the stop flag is set as the rest of the input is not needed.
+///
+/// When there is no more data, the writer is finalized, and an error is sent to the [StatsController][crate::stats::stats_controller::StatsController] if the remaining data could not be written.
+/// If the output was dropped after a write error, the CDPs written and dropped are sent instead.
+///
+/// If the memory usage is tracked, the bytes of each chunk received are removed from the [InFlightBytes].
 pub fn spawn_writer<T: RDH + 'static>(
     config: Arc<impl Config + 'static>,
     stop_flag: Arc<AtomicBool>,
+    stats_send_ch: std::sync::mpsc::Sender<StatType>,
     data_channel: Receiver<CdpChunk<T>>,
+    in_flight_bytes: Option<Arc<InFlightBytes>>,
 ) -> thread::JoinHandle<()> {
     let writer_thread = thread::Builder::new().name("Writer".to_string());
     writer_thread
         .spawn({
-            let mut writer = BufferedWriter::<T>::new(&*config, BUFFER_SIZE);
-            move || loop {
-                // Receive chunk from checker
-                let cdps = match data_channel.recv() {
-                    Ok(cdp) => cdp,
-                    Err(e) => {
-                        debug_assert_eq!(e, crossbeam_channel::RecvError);
+            let mut writer = BufferedWriter::<T>::new(&*config, BUFFER_SIZE, stats_send_ch.clone());
+            let mut hbf_extractor = config
+                .extract_hbfs()
+                .map(|extract| {
+                    HbfExtractor::<T>::new(extract, config.hbf_cap(), stats_send_ch.clone())
+                });
+            let mut ape_filter = config
+                .filter_ape()
+                .then(|| ApeFilter::new(config.gbt_wide()));
+            let mut empty_hbf_dropper = config
+                .drop_empty_hbfs()
+                .then(|| EmptyHbfDropper::<T>::new(config.gbt_wide()));
+            let mut cdp_tracer = crate::input::cdp_trace::CdpTracer::new(&*config);
+            move || {
+                let mut drained = false;
+                loop {
+                    // Receive chunk from checker
+                    let cdps = match data_channel.recv() {
+                        Ok(cdp) => cdp,
+                        Err(e) => {
+                            debug_assert_eq!(e, crossbeam_channel::RecvError);
+                            drained = true;
+                            break;
+                        }
+                    };
+                    if let Some(in_flight_bytes) = &in_flight_bytes {
+                        in_flight_bytes.remove(CdpCount::of_chunk(&cdps).bytes);
+                    }
+                    if stop_flag.load(Ordering::SeqCst) {
+                        log::trace!("Stopping writer thread");
+                        break;
+                    }
+                    if let Some(cdp_tracer) = &mut cdp_tracer {
+                        let handler = crate::input::cdp_trace::current_thread_name();
+                        for (rdh, payload, mem_pos) in &cdps {
+                            cdp_tracer.trace(rdh, payload.len(), mem_pos, &handler);
+                        }
+                    }
+                    let cdps = match &mut ape_filter {
+                        Some(ape_filter) => ape_filter.filter(cdps),
+                        None => cdps,
+                    };
+                    let cdps = match &mut empty_hbf_dropper {
+                        Some(empty_hbf_dropper) => empty_hbf_dropper.filter(cdps),
+                        None => cdps,
+                    };
+                    let cdps = match &mut hbf_extractor {
+                        Some(hbf_extractor) => hbf_extractor.extract(cdps),
+                        None => cdps,
+                    };
+                    // Push data onto the writer's buffer, which will flush it when the buffer is full or when the writer is finalized
+                    writer.push_cdp_chunk(cdps);
+                    if matches!(&hbf_extractor, Some(hbf_extractor) if hbf_extractor.all_links_done())
+                    {
+                        log::info!("Writer: the HBFs to extract of all links are written, stopping");
+                        stop_flag.store(true, Ordering::SeqCst);
                         break;
                     }
-                };
-                if stop_flag.load(Ordering::SeqCst) {
-                    log::trace!("Stopping writer thread");
-                    break;
                 }
-                // Push data onto the writer's buffer, which will flush it when the buffer is full or when the writer is dropped
-                writer.push_cdp_chunk(cdps);
+                // Selecting CDPs or HBFs leaves out CDPs, so not every CDP received is written
+                let selecting_cdps = ape_filter.is_some()
+                    || hbf_extractor.is_some()
+                    || empty_hbf_dropper.is_some();
+                if let Some(ape_filter) = ape_filter {
+                    stats_send_ch
+                        .send(StatType::ApeWords(Box::new(ape_filter.finish())))
+                        .unwrap();
+                }
+                if let Some(empty_hbf_dropper) = empty_hbf_dropper {
+                    stats_send_ch
+                        .send(StatType::EmptyHbfsDropped(empty_hbf_dropper.finish()))
+                        .unwrap();
+                }
+                if let Some(hbf_extractor) = hbf_extractor {
+                    writer.push_cdp_chunk(hbf_extractor.finish());
+                }
+                let finalized = writer.finalize();
+                if let Ok(summary) = &finalized {
+                    stats_send_ch
+                        .send(StatType::PipelineCounts(ComponentCounts::Writer {
+                            written: CdpCount {
+                                cdps: summary.cdps,
+                                bytes: summary.bytes,
+                            },
+                            complete: drained && !selecting_cdps && !summary.failed,
+                        }))
+                        .unwrap();
+                }
+                match finalized {
+                    Ok(summary) if summary.failed => {
+                        stats_send_ch.send(StatType::WriteFailure(summary)).unwrap()
+                    }
+                    Ok(summary) => log::debug!(
+                        "Writer: wrote {} CDPs, {} bytes",
+                        summary.cdps,
+                        summary.bytes
+                    ),
+                    Err(e) => stats_send_ch
+                        .send(StatType::Error(format!("Failed to write the output: {e}")))
+                        .unwrap(),
+                }
             }
         })
         .expect("Failed to spawn writer thread")
@@ -0,0 +1,340 @@
+//! Contains [verify_filter] that validates a filtered output against its source, for the `verify-filter` subcommand.
+//!
+//! The source and the output are scanned CDP by CDP in lockstep, the filter is applied to the source only.
+//! Every CDP kept from the source must be in the output, in the same order, with the same RDH and payload bytes, and the output must contain nothing else.
+use crate::input::cdp_filter::{And, CdpFilter, FilterCru, FilterDecision, FilterFee, FilterLink};
+use crate::util::config::VerifyFilter;
+use crate::words::lib::{RDH, RDH_SIZE_BYTES};
+use crate::words::rdh_cru::{RdhCRU, V7};
+use std::io::Read;
+
+/// Size of an RDH in bytes, the RDHs are compared byte for byte.
+const RDH_SIZE: usize = RDH_SIZE_BYTES as usize;
+
+/// A CDP read by the [CdpReader], with the raw bytes of its RDH and payload.
+#[derive(Debug)]
+struct RawCdp {
+    rdh: [u8; RDH_SIZE],
+    payload: Vec<u8>,
+    mem_pos: u64,
+}
+
+/// Reads the CDPs of a file one at a time, by the offset to the next RDH of each RDH.
+struct CdpReader<R: Read> {
+    reader: R,
+    mem_pos: u64,
+}
+
+impl<R: Read> CdpReader<R> {
+    fn new(reader: R) -> Self {
+        Self { reader, mem_pos: 0 }
+    }
+
+    /// Reads the next CDP, [None] at the end of the file.
+    ///
+    /// The RDH is parsed as an RDH v7, the link ID, FEE ID, CRU ID and offset to the next RDH are at the same place in all supported RDH versions.
+    fn next_cdp(&mut self) -> std::io::Result<Option<(RdhCRU<V7>, RawCdp)>> {
+        let mut rdh_bytes = [0u8; RDH_SIZE];
+        let bytes_read = read_up_to(&mut self.reader, &mut rdh_bytes)?;
+        if bytes_read == 0 {
+            return Ok(None);
+        }
+        if bytes_read < RDH_SIZE {
+            return Err(truncated(self.mem_pos, "RDH"));
+        }
+        let rdh = RdhCRU::<V7>::load(&mut rdh_bytes.as_slice())?;
+        let payload_size = match (rdh.offset_to_next() as usize).checked_sub(RDH_SIZE) {
+            Some(payload_size) => payload_size,
+            None => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "RDH at {:#X} has an offset to the next RDH of {}, less than the RDH size",
+                        self.mem_pos,
+                        rdh.offset_to_next()
+                    ),
+                ))
+            }
+        };
+        let mut payload = vec![0u8; payload_size];
+        if read_up_to(&mut self.reader, &mut payload)? < payload_size {
+            return Err(truncated(self.mem_pos, "payload"));
+        }
+        let mem_pos = self.mem_pos;
+        self.mem_pos += rdh.offset_to_next() as u64;
+        Ok(Some((
+            rdh,
+            RawCdp {
+                rdh: rdh_bytes,
+                payload,
+                mem_pos,
+            },
+        )))
+    }
+
+    /// Reads the next CDP that the filter keeps, [None] at the end of the file.
+    fn next_kept_cdp(&mut self, filter: &mut impl CdpFilter) -> std::io::Result<Option<RawCdp>> {
+        while let Some((rdh, cdp)) = self.next_cdp()? {
+            if filter.keep(&rdh, cdp.mem_pos) == FilterDecision::Keep {
+                return Ok(Some(cdp));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Fills `buf` as far as possible, returns the bytes read, less than the length of `buf` only at the end of the file.
+fn read_up_to(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut bytes_read = 0;
+    while bytes_read < buf.len() {
+        match reader.read(&mut buf[bytes_read..]) {
+            Ok(0) => break,
+            Ok(n) => bytes_read += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(bytes_read)
+}
+
+fn truncated(mem_pos: u64, what: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::UnexpectedEof,
+        format!("The file ends in the {what} of the CDP at {mem_pos:#X}"),
+    )
+}
+
+/// The first difference between the source and the output found by [verify_filter].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the CDP in the output, counting from 0.
+    pub cdp_index: u64,
+    /// Offset of the difference in the source, [None] if the source has no more CDPs kept by the filter.
+    pub source_pos: Option<u64>,
+    /// Offset of the difference in the output, [None] if the output has no more CDPs.
+    pub output_pos: Option<u64>,
+    /// What differs.
+    pub description: String,
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pos = |pos: Option<u64>| match pos {
+            Some(pos) => format!("{pos:#X}"),
+            None => "end of file".to_string(),
+        };
+        write!(
+            f,
+            "CDP {}: {} (source at {}, output at {})",
+            self.cdp_index,
+            self.description,
+            pos(self.source_pos),
+            pos(self.output_pos)
+        )
+    }
+}
+
+/// The result of [verify_filter].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyFilterResult {
+    /// CDPs that are identical in the source and the output, before the first [Divergence] if any.
+    pub cdps: u64,
+    /// Bytes of the identical CDPs.
+    pub bytes: u64,
+    /// The first difference between the source and the output, [None] if the output is a lossless extraction of the source.
+    pub divergence: Option<Divergence>,
+}
+
+/// Scans the source and the output in lockstep, and verifies that the output contains exactly the CDPs of the source kept by the filter, in the same order and byte for byte.
+///
+/// Stops at the first [Divergence], an error is only returned if a file can't be read or ends in a CDP.
+pub fn verify_filter(
+    source: impl Read,
+    output: impl Read,
+    filter: &mut impl CdpFilter,
+) -> std::io::Result<VerifyFilterResult> {
+    let mut source = CdpReader::new(source);
+    let mut output = CdpReader::new(output);
+    let mut result = VerifyFilterResult {
+        cdps: 0,
+        bytes: 0,
+        divergence: None,
+    };
+    loop {
+        let cdp_index = result.cdps;
+        let divergence = |source_pos, output_pos, description: String| Divergence {
+            cdp_index,
+            source_pos,
+            output_pos,
+            description,
+        };
+        let (source_cdp, output_cdp) = match (
+            source.next_kept_cdp(filter)?,
+            output.next_cdp()?.map(|(_, cdp)| cdp),
+        ) {
+            (None, None) => return Ok(result),
+            (Some(source_cdp), None) => {
+                result.divergence = Some(divergence(
+                    Some(source_cdp.mem_pos),
+                    None,
+                    "missing from the output".to_string(),
+                ));
+                return Ok(result);
+            }
+            (None, Some(output_cdp)) => {
+                result.divergence = Some(divergence(
+                    None,
+                    Some(output_cdp.mem_pos),
+                    "not in the source, or not kept by the filter".to_string(),
+                ));
+                return Ok(result);
+            }
+            (Some(source_cdp), Some(output_cdp)) => (source_cdp, output_cdp),
+        };
+        if let Some(i) = first_difference(&source_cdp.rdh, &output_cdp.rdh) {
+            result.divergence = Some(divergence(
+                Some(source_cdp.mem_pos + i as u64),
+                Some(output_cdp.mem_pos + i as u64),
+                format!(
+                    "RDH byte {i} is {:#04X} in the source, {:#04X} in the output",
+                    source_cdp.rdh[i], output_cdp.rdh[i]
+                ),
+            ));
+            return Ok(result);
+        }
+        if let Some(i) = first_difference(&source_cdp.payload, &output_cdp.payload) {
+            let payload_offset = (RDH_SIZE + i) as u64;
+            result.divergence = Some(divergence(
+                Some(source_cdp.mem_pos + payload_offset),
+                Some(output_cdp.mem_pos + payload_offset),
+                format!(
+                    "payload byte {i} is {:#04X} in the source, {:#04X} in the output",
+                    source_cdp.payload[i], output_cdp.payload[i]
+                ),
+            ));
+            return Ok(result);
+        }
+        result.cdps += 1;
+        result.bytes += (RDH_SIZE + source_cdp.payload.len()) as u64;
+    }
+}
+
+/// Index of the first byte that differs, the RDHs are equal, so the payloads are of equal length.
+fn first_difference(source: &[u8], output: &[u8]) -> Option<usize> {
+    source.iter().zip(output).position(|(a, b)| a != b)
+}
+
+/// Runs the `verify-filter` subcommand, prints the PASS or FAIL verdict to stdout, and returns true if the output passed.
+pub fn run_verify_filter(opts: &VerifyFilter) -> Result<bool, String> {
+    let open = |path: &std::path::Path| {
+        crate::input::lib::open_input_file(path)
+            .map(std::io::BufReader::new)
+            .map_err(|e| e.to_string())
+    };
+    let source = open(&opts.source)?;
+    let output = open(&opts.output)?;
+    let mut filter = And(
+        opts.link.clone().map(FilterLink),
+        And(opts.fee.map(FilterFee), opts.cru.map(FilterCru)),
+    );
+    let result = verify_filter(source, output, &mut filter).map_err(|e| e.to_string())?;
+    match &result.divergence {
+        None => println!(
+            "PASS: {} CDPs ({} bytes) kept by the filter from {} are identical and in the same order in {}",
+            result.cdps,
+            result.bytes,
+            opts.source.display(),
+            opts.output.display()
+        ),
+        Some(divergence) => println!(
+            "FAIL: {divergence}, after {} identical CDPs ({} bytes)",
+            result.cdps, result.bytes
+        ),
+    }
+    Ok(result.divergence.is_none())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::lib::ByteSlice;
+    use crate::words::rdh_cru::test_data::CORRECT_RDH_CRU_V7;
+
+    fn cdp(link_id: u8, payload_byte: u8) -> Vec<u8> {
+        let mut rdh = CORRECT_RDH_CRU_V7;
+        rdh.link_id = link_id;
+        rdh.offset_new_packet = RDH_SIZE_BYTES + 16;
+        let mut cdp = rdh.to_byte_slice().to_vec();
+        cdp.extend([payload_byte; 16]);
+        cdp
+    }
+
+    #[test]
+    fn test_verify_filter_pass_and_localized_failure() {
+        let source = [cdp(0, 0xA0), cdp(2, 0xB0), cdp(0, 0xA1), cdp(2, 0xB1)].concat();
+        let mut output = [cdp(2, 0xB0), cdp(2, 0xB1)].concat();
+        let mut filter = FilterLink(vec![2]);
+        let result = verify_filter(source.as_slice(), output.as_slice(), &mut filter).unwrap();
+        assert_eq!(result.cdps, 2);
+        assert_eq!(result.bytes, 160);
+        assert_eq!(result.divergence, None);
+
+        // Corrupt the 4th payload byte of the second CDP of the output
+        output[80 + 64 + 3] = 0xFF;
+        let result = verify_filter(source.as_slice(), output.as_slice(), &mut filter).unwrap();
+        assert_eq!(result.cdps, 1);
+        assert_eq!(
+            result.divergence.unwrap().to_string(),
+            "CDP 1: payload byte 3 is 0xB1 in the source, 0xFF in the output (source at 0x133, output at 0x93)"
+        );
+    }
+
+    #[test]
+    fn test_verify_filter_missing_and_extra_cdps() {
+        let source = [cdp(0, 0xA0), cdp(2, 0xB0), cdp(2, 0xB1)].concat();
+        let output = cdp(2, 0xB0);
+        let result = verify_filter(
+            source.as_slice(),
+            output.as_slice(),
+            &mut FilterLink(vec![2]),
+        )
+        .unwrap();
+        assert_eq!(
+            result.divergence.unwrap().to_string(),
+            "CDP 1: missing from the output (source at 0xA0, output at end of file)"
+        );
+        // Without a filter, the CDP of link 0 comes first in the source
+        let result = verify_filter(
+            source.as_slice(),
+            output.as_slice(),
+            &mut None::<FilterLink>,
+        )
+        .unwrap();
+        assert!(result
+            .divergence
+            .unwrap()
+            .description
+            .starts_with("RDH byte 12 is 0x00 in the source, 0x02 in the output"));
+        let result = verify_filter(
+            output.as_slice(),
+            source.as_slice(),
+            &mut FilterLink(vec![2]),
+        )
+        .unwrap();
+        assert_eq!(
+            result.divergence.unwrap().to_string(),
+            "CDP 0: RDH byte 12 is 0x02 in the source, 0x00 in the output (source at 0xC, output at 0xC)"
+        );
+    }
+
+    #[test]
+    fn test_verify_filter_truncated_output() {
+        let source = cdp(2, 0xB0);
+        let output = &source[..70];
+        let e = verify_filter(source.as_slice(), output, &mut FilterLink(vec![2])).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "The file ends in the payload of the CDP at 0x0"
+        );
+    }
+}
@@ -2,9 +2,12 @@
 //!
 //! Receives data incrementally and once a certain amount is reached, it will
 //! write it out to file/stdout.
-//! Implements drop to flush the remaining data to the file once processing is done.
+//! Once processing is done, the remaining data is flushed with [BufferedWriter::finalize],
+//! or as a fallback when the writer is dropped, where errors are reported instead of returned.
+//! With `--keep-going-on-write-error`, a failed write is reported once, and all further output is dropped and counted instead.
 
 use crate::input::data_wrapper::CdpChunk;
+use crate::stats::stats_controller::StatType;
 use crate::util::lib::Config;
 use crate::words::lib::RDH;
 
@@ -22,19 +25,39 @@ pub trait Writer<T: RDH> {
     fn flush(&mut self) -> std::io::Result<()>;
 }
 
+/// The CDPs and bytes written by a [BufferedWriter], returned by [BufferedWriter::finalize].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WriteSummary {
+    /// CDPs written.
+    pub cdps: u64,
+    /// Bytes written.
+    pub bytes: u64,
+    /// A write failed, and the output was dropped from then on.
+    pub failed: bool,
+    /// CDPs dropped after a write failed, including the CDPs of the failed write.
+    pub dropped_cdps: u64,
+}
+
 /// A writer that uses a buffer to reduce the amount of syscalls.
 pub struct BufferedWriter<T: RDH> {
     filtered_rdhs_buffer: Vec<T>,
     filtered_payload_buffers: Vec<Vec<u8>>, // 1 Linked list per payload
-    buf_writer: Option<std::io::BufWriter<std::fs::File>>, // If no file is specified -> write to stdout
+    buf_writer: Option<std::io::BufWriter<Box<dyn std::io::Write + Send>>>, // If no file is specified -> write to stdout
     max_buffer_size: usize,
+    keep_going_on_write_error: bool,
+    stats_send_ch: std::sync::mpsc::Sender<StatType>,
+    summary: WriteSummary,
 }
 
 impl<T: RDH> BufferedWriter<T> {
-    /// Create a new BufferedWriter from a config and a max buffer size.
-    pub fn new(config: &impl Config, max_buffer_size: usize) -> Self {
-        // Create output file, and buf writer if specified
-        let buf_writer = match config.output() {
+    /// Create a new BufferedWriter from a config, a max buffer size, and a producer channel to report errors when the writer is dropped.
+    pub fn new(
+        config: &impl Config,
+        max_buffer_size: usize,
+        stats_send_ch: std::sync::mpsc::Sender<StatType>,
+    ) -> Self {
+        // Create output file if specified
+        let output: Option<Box<dyn std::io::Write + Send>> = match config.output() {
             Some(path) if "stdout".eq(path.to_str().unwrap()) => None,
             Some(path) => {
                 let path: std::path::PathBuf = path.to_owned();
@@ -44,18 +67,81 @@ impl<T: RDH> BufferedWriter<T> {
                     .append(true)
                     .open(path)
                     .expect("Failed to open/create output file");
-                let buf_writer = std::io::BufWriter::new(file);
-                Some(buf_writer)
+                Some(Box::new(file))
             }
             None => None,
         };
+        Self::with_output(
+            output,
+            max_buffer_size,
+            config.keep_going_on_write_error(),
+            stats_send_ch,
+        )
+    }
+
+    /// Create a new BufferedWriter writing to a file created at `path`, independently of the output of the config.
+    pub fn to_file(
+        path: &std::path::Path,
+        max_buffer_size: usize,
+        stats_send_ch: std::sync::mpsc::Sender<StatType>,
+    ) -> std::io::Result<Self> {
+        let file = std::fs::File::create(path)?;
+        Ok(Self::with_output(
+            Some(Box::new(file)),
+            max_buffer_size,
+            false,
+            stats_send_ch,
+        ))
+    }
+
+    /// Create a new BufferedWriter writing to `output`, or to stdout if it is [None].
+    fn with_output(
+        output: Option<Box<dyn std::io::Write + Send>>,
+        max_buffer_size: usize,
+        keep_going_on_write_error: bool,
+        stats_send_ch: std::sync::mpsc::Sender<StatType>,
+    ) -> Self {
         BufferedWriter {
             filtered_rdhs_buffer: Vec::with_capacity(max_buffer_size), // Will most likely not be filled as payloads are usually larger, but hard to say
             filtered_payload_buffers: Vec::with_capacity(max_buffer_size),
-            buf_writer,
+            buf_writer: output.map(std::io::BufWriter::new),
             max_buffer_size,
+            keep_going_on_write_error,
+            stats_send_ch,
+            summary: WriteSummary::default(),
         }
     }
+
+    /// Reports a failed write once, and drops all further output, counting the `cdps` of the failed write as dropped.
+    fn discard_after_write_error(&mut self, e: &std::io::Error, cdps: u64) {
+        log::error!("Failed to write the output, dropping the rest of the output: {e}");
+        // The stats controller may already be gone
+        let _ = self.stats_send_ch.send(StatType::Error(format!(
+            "Failed to write the output after {} CDPs, the rest of the output is dropped: {e}",
+            self.summary.cdps
+        )));
+        self.summary.failed = true;
+        self.summary.dropped_cdps += cdps;
+    }
+
+    /// Flushes the remaining data and the underlying file or stdout, and returns the [WriteSummary] of all the data written.
+    ///
+    /// Callers should finalize the writer rather than rely on the flush when it is dropped, as errors can only be reported there.
+    pub fn finalize(mut self) -> std::io::Result<WriteSummary> {
+        self.flush()?;
+        if self.summary.failed {
+            return Ok(self.summary);
+        }
+        let flushed = match &mut self.buf_writer {
+            Some(buf_writer) => std::io::Write::flush(buf_writer),
+            None => std::io::Write::flush(&mut std::io::stdout()),
+        };
+        match flushed {
+            Err(e) if self.keep_going_on_write_error => self.discard_after_write_error(&e, 0),
+            flushed => flushed?,
+        }
+        Ok(self.summary)
+    }
 }
 
 impl<T: RDH> Writer<T> for BufferedWriter<T> {
@@ -69,6 +155,10 @@ impl<T: RDH> Writer<T> for BufferedWriter<T> {
 
     #[inline]
     fn push_rdhs(&mut self, rdhs: Vec<T>) {
+        if self.summary.failed {
+            self.summary.dropped_cdps += rdhs.len() as u64;
+            return;
+        }
         if self.filtered_rdhs_buffer.len() + rdhs.len() >= self.max_buffer_size {
             self.flush().expect("Failed to flush buffer");
         }
@@ -77,6 +167,9 @@ impl<T: RDH> Writer<T> for BufferedWriter<T> {
 
     #[inline]
     fn push_payload(&mut self, payload: Vec<u8>) {
+        if self.summary.failed {
+            return;
+        }
         if self.filtered_payload_buffers.len() + 1 >= self.max_buffer_size {
             self.flush().expect("Failed to flush buffer");
         }
@@ -85,6 +178,10 @@ impl<T: RDH> Writer<T> for BufferedWriter<T> {
 
     #[inline]
     fn push_cdp_chunk(&mut self, cdp_chunk: CdpChunk<T>) {
+        if self.summary.failed {
+            self.summary.dropped_cdps += cdp_chunk.len() as u64;
+            return;
+        }
         if (self.filtered_rdhs_buffer.len() + cdp_chunk.len() >= self.max_buffer_size)
             || (self.filtered_payload_buffers.len() + cdp_chunk.len() >= self.max_buffer_size)
         {
@@ -96,12 +193,15 @@ impl<T: RDH> Writer<T> for BufferedWriter<T> {
         });
     }
 
+    /// Writes the buffered CDPs, pairing each RDH with a payload in the order they were pushed.
+    ///
+    /// If there are more RDHs than payloads or vice versa, the CDPs that could be paired are written, the rest is discarded and returned as an [InvalidData][std::io::ErrorKind::InvalidData] error.
+    ///
+    /// If the write fails and the writer keeps going on write errors, the error is reported instead of returned, and the buffered CDPs are dropped.
     #[inline]
     fn flush(&mut self) -> std::io::Result<()> {
-        debug_assert_eq!(
-            self.filtered_rdhs_buffer.len(),
-            self.filtered_payload_buffers.len()
-        );
+        let rdhs = self.filtered_rdhs_buffer.len();
+        let payloads = self.filtered_payload_buffers.len();
 
         let mut data = vec![];
         for (rdh, payload) in self
@@ -113,17 +213,48 @@ impl<T: RDH> Writer<T> for BufferedWriter<T> {
             data.extend(payload);
         }
 
-        self.write(&data)?;
         self.filtered_rdhs_buffer.clear();
         self.filtered_payload_buffers.clear();
+        let cdps = rdhs.min(payloads) as u64;
+        if self.summary.failed {
+            self.summary.dropped_cdps += cdps;
+            return Ok(());
+        }
+        match self.write(&data) {
+            Ok(()) => {
+                self.summary.cdps += cdps;
+                self.summary.bytes += data.len() as u64;
+            }
+            Err(e) if self.keep_going_on_write_error => {
+                self.discard_after_write_error(&e, cdps);
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        }
+        if rdhs != payloads {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "{rdhs} RDHs and {payloads} payloads were buffered, only the first {} CDPs were written",
+                    rdhs.min(payloads)
+                ),
+            ));
+        }
         Ok(())
     }
 }
 
 impl<T: RDH> Drop for BufferedWriter<T> {
+    /// Flushes the remaining data if the writer was not [finalized][BufferedWriter::finalize].
+    ///
+    /// Panicking while the thread is possibly already unwinding would abort the process, so errors are logged and sent to the [StatsController][crate::stats::stats_controller::StatsController].
     fn drop(&mut self) {
-        if std::mem::needs_drop::<Self>() {
-            self.flush().expect("Failed to flush buffer");
+        if let Err(e) = self.flush() {
+            log::error!("Failed to flush the output buffer: {e}");
+            // The stats controller may already be gone
+            let _ = self
+                .stats_send_ch
+                .send(StatType::Error(format!("Failed to write the output: {e}")));
         }
     }
 }
@@ -150,11 +281,22 @@ mod tests {
         "sanity",
     ];
 
+    fn errors_received(recv_stats_ch: &std::sync::mpsc::Receiver<StatType>) -> Vec<String> {
+        recv_stats_ch
+            .try_iter()
+            .filter_map(|stat| match stat {
+                StatType::Error(msg) => Some(msg),
+                _ => None,
+            })
+            .collect()
+    }
+
     #[test]
     fn test_buffered_writer() {
         let config: Opt = <Opt as structopt::StructOpt>::from_iter(&CONFIG_STR);
         {
-            let writer = BufferedWriter::<RdhCRU<V6>>::new(&config, 10);
+            let (send_stats_ch, _recv_stats_ch) = std::sync::mpsc::channel();
+            let writer = BufferedWriter::<RdhCRU<V6>>::new(&config, 10, send_stats_ch);
 
             assert!(writer.buf_writer.is_some());
         }
@@ -166,27 +308,153 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
-    // Should panic, Because when the writer is dropped, it flushes the buffer, which will panic because the number of RDHs and payloads are not equal
+    // When the writer is dropped, the number of RDHs and payloads are not equal, which is reported instead of panicking.
     // Empty payloads are counted.
     fn test_push_2_rdh_v7_buffer_is_2() {
         let config: Opt = <Opt as structopt::StructOpt>::from_iter(&CONFIG_STR);
         let rdhs = vec![CORRECT_RDH_CRU_V7, CORRECT_RDH_CRU_V7];
         let length = rdhs.len();
-        println!("length: {}", length);
+        let (send_stats_ch, recv_stats_ch) = std::sync::mpsc::channel();
         {
-            let mut writer = BufferedWriter::<RdhCRU<V7>>::new(&config, 10);
+            let mut writer = BufferedWriter::<RdhCRU<V7>>::new(&config, 10, send_stats_ch);
             writer.push_rdhs(rdhs);
             let buf_size = writer.filtered_rdhs_buffer.len();
-            println!("buf_size: {}", buf_size);
             assert_eq!(buf_size, length);
-            // Clean up before drop
-            let filepath = std::path::PathBuf::from(OUTPUT_FILE_STR);
-            // delete output file
-            std::fs::remove_file(filepath).unwrap();
+            writer.push_payload(vec![0; 10]);
+        }
+        assert_eq!(
+            errors_received(&recv_stats_ch),
+            vec!["Failed to write the output: 2 RDHs and 1 payloads were buffered, only the first 1 CDPs were written"]
+        );
+        // The matched CDP was still written
+        let filepath = std::path::PathBuf::from(OUTPUT_FILE_STR);
+        assert_eq!(std::fs::metadata(&filepath).unwrap().len(), 64 + 10);
+        // delete output file
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[test]
+    fn test_finalize_returns_summary_and_mismatch() {
+        let config: Opt = <Opt as structopt::StructOpt>::from_iter(&[
+            "fastpasta",
+            "../fastpasta_test_files/data_ols_ul.raw",
+            "-o test_finalize.raw",
+            "-f",
+            "2",
+        ]);
+        let filepath = std::path::PathBuf::from(" test_finalize.raw");
+        let (send_stats_ch, recv_stats_ch) = std::sync::mpsc::channel();
+        let mut cdp_chunk = CdpChunk::new();
+        cdp_chunk.push(CORRECT_RDH_CRU_V7, vec![0; 10], 0);
+        cdp_chunk.push(CORRECT_RDH_CRU_V7, vec![0; 20], 0x40);
+
+        let mut writer = BufferedWriter::<RdhCRU<V7>>::new(&config, 10, send_stats_ch.clone());
+        writer.push_cdp_chunk(cdp_chunk);
+        assert_eq!(
+            writer.finalize().unwrap(),
+            WriteSummary {
+                cdps: 2,
+                bytes: 2 * 64 + 30,
+                ..Default::default()
+            }
+        );
+        assert_eq!(std::fs::metadata(&filepath).unwrap().len(), 2 * 64 + 30);
+
+        // A payload without an RDH is returned as an error, and not reported again when the writer is dropped
+        let mut writer = BufferedWriter::<RdhCRU<V7>>::new(&config, 10, send_stats_ch);
+        writer.push_payload(vec![0; 10]);
+        let err = writer.finalize().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "0 RDHs and 1 payloads were buffered, only the first 0 CDPs were written"
+        );
+        assert!(errors_received(&recv_stats_ch).is_empty());
+
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    /// Accepts writes until `capacity` bytes are written, like a disk running full.
+    struct FailingWriter {
+        capacity: usize,
+    }
+
+    impl std::io::Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if buf.len() > self.capacity {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "No space left on device",
+                ));
+            }
+            self.capacity -= buf.len();
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
         }
     }
 
+    // A CDP too large to be buffered by the BufWriter, so each flush reaches the FailingWriter
+    fn large_cdp_chunk() -> CdpChunk<RdhCRU<V7>> {
+        let mut cdp_chunk = CdpChunk::new();
+        cdp_chunk.push(CORRECT_RDH_CRU_V7, vec![0; 0x4000 - 64], 0);
+        cdp_chunk
+    }
+
+    fn failing_writer(
+        keep_going_on_write_error: bool,
+        stats_send_ch: std::sync::mpsc::Sender<StatType>,
+    ) -> BufferedWriter<RdhCRU<V7>> {
+        // Room for a single CDP, and the writer flushes before every second CDP
+        let output = FailingWriter {
+            capacity: 0x4000 + 0x100,
+        };
+        BufferedWriter::with_output(
+            Some(Box::new(output)),
+            2,
+            keep_going_on_write_error,
+            stats_send_ch,
+        )
+    }
+
+    #[test]
+    fn test_keep_going_on_write_error_drops_output() {
+        let (send_stats_ch, recv_stats_ch) = std::sync::mpsc::channel();
+        let mut writer = failing_writer(true, send_stats_ch);
+        for _ in 0..4 {
+            writer.push_cdp_chunk(large_cdp_chunk());
+        }
+        // The failure is reported once, when the second CDP is written
+        assert_eq!(
+            errors_received(&recv_stats_ch),
+            vec!["Failed to write the output after 1 CDPs, the rest of the output is dropped: No space left on device"]
+        );
+        writer.push_rdhs(vec![CORRECT_RDH_CRU_V7]);
+        writer.push_payload(vec![0; 10]);
+        assert_eq!(
+            writer.finalize().unwrap(),
+            WriteSummary {
+                cdps: 1,
+                bytes: 0x4000,
+                failed: true,
+                dropped_cdps: 4,
+            }
+        );
+        assert!(errors_received(&recv_stats_ch).is_empty());
+    }
+
+    #[test]
+    fn test_write_error_is_returned_by_default() {
+        let (send_stats_ch, recv_stats_ch) = std::sync::mpsc::channel();
+        let mut writer = failing_writer(false, send_stats_ch);
+        writer.push_cdp_chunk(large_cdp_chunk());
+        writer.push_cdp_chunk(large_cdp_chunk());
+        let err = writer.finalize().unwrap_err();
+        assert_eq!(err.to_string(), "No space left on device");
+        assert!(errors_received(&recv_stats_ch).is_empty());
+    }
+
     #[test]
     fn test_push_2_rdh_v7_and_empty_payloads_buffers_are_2() {
         let config: Opt = <Opt as structopt::StructOpt>::from_iter(&CONFIG_STR);
@@ -197,7 +465,8 @@ mod tests {
 
         let length = cdp_chunk.len();
         {
-            let mut writer = BufferedWriter::<RdhCRU<V7>>::new(&config, 10);
+            let (send_stats_ch, _recv_stats_ch) = std::sync::mpsc::channel();
+            let mut writer = BufferedWriter::<RdhCRU<V7>>::new(&config, 10, send_stats_ch);
             writer.push_cdp_chunk(cdp_chunk);
             let buf_size = writer.filtered_rdhs_buffer.len();
             assert_eq!(buf_size, length);
@@ -0,0 +1,1702 @@
+//! Runs the fastpasta binary on the files in `tests/test-data` and compares stdout and stderr with the golden files in `tests/golden`.
+//!
+//! Output that changes between runs, such as the processing time of the report, is normalized before comparing.
+//! When a change in the output is intended, regenerate the golden files with `UPDATE_GOLDEN=1 cargo test --test golden` and review the diff.
+use assert_cmd::Command;
+use std::path::PathBuf;
+
+fn test_data(file_name: &str) -> String {
+    format!("{}/tests/test-data/{file_name}", env!("CARGO_MANIFEST_DIR"))
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(format!(
+        "{}/tests/golden/{name}",
+        env!("CARGO_MANIFEST_DIR")
+    ))
+}
+
+/// Removes the ANSI color codes, and replaces the line with the processing time and the variable fields of the run metadata
+fn normalize(output: &[u8]) -> String {
+    let output = String::from_utf8_lossy(output);
+    let mut normalized = String::with_capacity(output.len());
+    let mut chars = output.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // Skip the escape sequence up to and including its final letter
+            chars.by_ref().find(|c| c.is_ascii_alphabetic());
+        } else {
+            normalized.push(c);
+        }
+    }
+    normalized
+        .lines()
+        .map(|line| {
+            if line.contains("Processed in") {
+                "<processing time>"
+            } else if line.starts_with("fastpasta ") && line.contains("(git ") {
+                "<fastpasta version>"
+            } else if line.starts_with("Command: ") {
+                "Command: <command line>"
+            } else if line.starts_with("Input: ") {
+                "Input: <input>"
+            } else if line.starts_with("Run started: ") {
+                "Run started: <timestamp>"
+            } else {
+                line
+            }
+        })
+        .map(|line| format!("{line}\n"))
+        .collect()
+}
+
+fn assert_golden(name: &str, actual: &str) {
+    let path = golden_path(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "Failed to read golden file {}: {e}, generate it with UPDATE_GOLDEN=1",
+            path.display()
+        )
+    });
+    assert_eq!(
+        actual,
+        expected,
+        "Output differs from {}, if the change is intended, update it with UPDATE_GOLDEN=1",
+        path.display()
+    );
+}
+
+/// Runs fastpasta with the arguments, and compares its stdout and stderr with the golden files `<name>.stdout` and `<name>.stderr`
+fn run_golden(name: &str, args: &[&str]) {
+    let output = Command::cargo_bin("fastpasta")
+        .unwrap()
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_golden(&format!("{name}.stdout"), &normalize(&output.stdout));
+    assert_golden(&format!("{name}.stderr"), &normalize(&output.stderr));
+}
+
+#[test]
+fn check_sanity() {
+    run_golden(
+        "check_sanity",
+        &[&test_data("its_2_links.raw"), "check", "sanity"],
+    );
+}
+
+#[test]
+fn check_all_its() {
+    run_golden(
+        "check_all_its",
+        &[&test_data("its_2_links.raw"), "check", "all", "its"],
+    );
+}
+
+#[test]
+fn check_sanity_expect_data_format() {
+    // The test data is data format 2, the errors of both links are reported in order
+    run_golden(
+        "check_sanity_expect_data_format",
+        &[
+            &test_data("its_2_links.raw"),
+            "check",
+            "sanity",
+            "--expect-data-format",
+            "0",
+            "--ordered",
+        ],
+    );
+}
+
+#[test]
+fn check_all_its_errors() {
+    run_golden(
+        "check_all_its_errors",
+        &[&test_data("its_1_link_errors.raw"), "check", "all", "its"],
+    );
+}
+
+#[test]
+fn check_all_its_hbfs_across_chunks() {
+    // The HBFs of 3 pages span the chunk boundaries, the results are the same as with the default chunk size
+    for cdp_chunk_size in ["1", "2"] {
+        run_golden(
+            "check_all_its",
+            &[
+                &test_data("its_2_links.raw"),
+                "check",
+                "all",
+                "its",
+                "--cdp-chunk-size",
+                cdp_chunk_size,
+            ],
+        );
+        run_golden(
+            "check_all_its_errors",
+            &[
+                &test_data("its_1_link_errors.raw"),
+                "check",
+                "all",
+                "its",
+                "--cdp-chunk-size",
+                cdp_chunk_size,
+            ],
+        );
+    }
+}
+
+#[test]
+fn check_all_its_errors_ordered() {
+    run_golden(
+        "check_all_its_errors_ordered",
+        &[
+            &test_data("its_2_links_errors.raw"),
+            "check",
+            "all",
+            "its",
+            "--ordered",
+        ],
+    );
+}
+
+#[test]
+fn check_sanity_trace_cdps() {
+    run_golden(
+        "check_sanity_trace_cdps",
+        &[
+            &test_data("its_2_links.raw"),
+            "check",
+            "sanity",
+            "--trace-cdps",
+            "--trace-every",
+            "4",
+        ],
+    );
+}
+
+/// Writes the first `len` bytes of a test data file to a new file, as if the run was stopped abruptly, and returns its path
+fn truncated_test_data(file_name: &str, len: usize, truncated_name: &str) -> String {
+    let truncated_file = format!("{}/{truncated_name}", env!("CARGO_TARGET_TMPDIR"));
+    let data = std::fs::read(test_data(file_name)).unwrap();
+    std::fs::write(&truncated_file, &data[..len]).unwrap();
+    truncated_file
+}
+
+#[test]
+fn check_all_its_truncated_final_hbf() {
+    // Ends after the first page of the last HBF of link 2
+    let truncated_file = truncated_test_data("its_2_links.raw", 0x660, "truncated_at_rdh.raw");
+    run_golden(
+        "check_all_its_truncated_final_hbf",
+        &[&truncated_file, "check", "all", "its"],
+    );
+    std::fs::remove_file(truncated_file).unwrap();
+}
+
+#[test]
+fn check_all_its_truncated_payload() {
+    // Ends in the middle of the payload of the second page of the last HBF of link 2
+    let truncated_file = truncated_test_data("its_2_links.raw", 0x6B0, "truncated_in_payload.raw");
+    run_golden(
+        "check_all_its_truncated_payload",
+        &[&truncated_file, "check", "all", "its"],
+    );
+    std::fs::remove_file(truncated_file).unwrap();
+}
+
+/// Writes the CDPs of a test data file in a new order to a new file, and returns its path
+fn reordered_test_data(file_name: &str, order: &[usize], reordered_name: &str) -> String {
+    let reordered_file = format!("{}/{reordered_name}", env!("CARGO_TARGET_TMPDIR"));
+    let data = std::fs::read(test_data(file_name)).unwrap();
+    let mut cdps = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        // The offset to the next RDH is at byte 8 of the RDH
+        let offset_to_next = u16::from_le_bytes([data[pos + 8], data[pos + 9]]) as usize;
+        cdps.push(&data[pos..pos + offset_to_next]);
+        pos += offset_to_next;
+    }
+    let reordered: Vec<u8> = order.iter().flat_map(|idx| cdps[*idx].to_vec()).collect();
+    std::fs::write(&reordered_file, reordered).unwrap();
+    reordered_file
+}
+
+#[test]
+fn check_sanity_analyze_interleaving() {
+    // The first HBF of link 0 (CDP 0, 1, 2) and link 2 (CDP 3, 4, 5) interleaved,
+    // with 2 CDPs between the first and second page of link 0, the rest of the file is unchanged
+    let order: Vec<usize> = [0, 3, 4, 1, 5, 2].into_iter().chain(6..18).collect();
+    let reordered_file = reordered_test_data("its_2_links.raw", &order, "interleaved.raw");
+    run_golden(
+        "check_sanity_analyze_interleaving",
+        &[
+            &reordered_file,
+            "--analyze",
+            "interleaving",
+            "check",
+            "sanity",
+        ],
+    );
+    std::fs::remove_file(reordered_file).unwrap();
+}
+
+#[test]
+fn check_sanity_analyze_throughput() {
+    // 3 orbits of 2 links, in windows of 2 orbits
+    run_golden(
+        "check_sanity_analyze_throughput",
+        &[
+            &test_data("its_2_links.raw"),
+            "--analyze",
+            "throughput",
+            "--throughput-window",
+            "2",
+            "check",
+            "sanity",
+        ],
+    );
+}
+
+#[test]
+fn check_sanity_analyze_entropy() {
+    // 9 CDPs per link, every 2nd is sampled
+    run_golden(
+        "check_sanity_analyze_entropy",
+        &[
+            &test_data("its_2_links.raw"),
+            "--analyze",
+            "entropy",
+            "--entropy-sample-every",
+            "2",
+            "check",
+            "sanity",
+        ],
+    );
+}
+
+#[test]
+fn check_sanity_analyze_payload_uniformity() {
+    // 9 copies of the first CDP of link 0, with one payload byte changed in the 6th copy
+    let uniform_file = reordered_test_data("its_2_links.raw", &[0; 9], "uniform.raw");
+    let mut data = std::fs::read(&uniform_file).unwrap();
+    let cdp_size = data.len() / 9;
+    data[5 * cdp_size + 64] ^= 0xFF;
+    std::fs::write(&uniform_file, data).unwrap();
+    run_golden(
+        "check_sanity_analyze_payload_uniformity",
+        &[
+            &uniform_file,
+            "--analyze",
+            "payload-uniformity",
+            "check",
+            "sanity",
+        ],
+    );
+    std::fs::remove_file(uniform_file).unwrap();
+}
+
+/// Writes the RDHs of a test data file without their payloads to a new file, with `memory_size` and `offset_new_packet` set to the RDH size, and returns its path
+fn rdh_only_test_data(file_name: &str, data_format: u8, rdh_only_name: &str) -> String {
+    let rdh_only_file = format!("{}/{rdh_only_name}", env!("CARGO_TARGET_TMPDIR"));
+    let data = std::fs::read(test_data(file_name)).unwrap();
+    let mut rdhs = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let offset_to_next = u16::from_le_bytes([data[pos + 8], data[pos + 9]]) as usize;
+        let mut rdh = data[pos..pos + 64].to_vec();
+        // offset_new_packet and memory_size at byte 8 and 10, data format at byte 24
+        rdh[8..12].copy_from_slice(&[64, 0, 64, 0]);
+        rdh[24] = data_format;
+        rdhs.extend(rdh);
+        pos += offset_to_next;
+    }
+    std::fs::write(&rdh_only_file, rdhs).unwrap();
+    rdh_only_file
+}
+
+#[test]
+fn check_all_its_rdh_only() {
+    // The payloads of the fixture are stripped, the RDHs alone are valid and give no errors
+    let rdh_only_file = rdh_only_test_data("its_2_links.raw", 2, "rdh_only.raw");
+    run_golden(
+        "check_all_its_rdh_only",
+        &[&rdh_only_file, "check", "all", "its"],
+    );
+    std::fs::remove_file(rdh_only_file).unwrap();
+}
+
+#[test]
+fn view_hbf_rdh_only_data_format_0() {
+    let rdh_only_file = rdh_only_test_data("its_2_links.raw", 0, "rdh_only_data_format_0.raw");
+    run_golden(
+        "view_hbf_rdh_only_data_format_0",
+        &[&rdh_only_file, "view", "hbf"],
+    );
+    std::fs::remove_file(rdh_only_file).unwrap();
+}
+
+#[test]
+fn check_sanity_silent_link() {
+    // The last HBF of link 2 (CDP 15, 16, 17) is left out, so link 2 is silent for the last orbit
+    let order: Vec<usize> = (0..15).collect();
+    let reordered_file = reordered_test_data("its_2_links.raw", &order, "silent_link.raw");
+    run_golden(
+        "check_sanity_silent_link",
+        &[
+            &reordered_file,
+            "--silent-link-orbits",
+            "0",
+            "check",
+            "sanity",
+        ],
+    );
+    std::fs::remove_file(reordered_file).unwrap();
+}
+
+#[test]
+fn check_sanity_hbf_per_tf() {
+    // Every HBF of the test data is opened with the TF trigger bit set, so each TF has 1 HBF instead of 2
+    run_golden(
+        "check_sanity_hbf_per_tf",
+        &[
+            &test_data("its_2_links.raw"),
+            "--hbf-per-tf",
+            "2",
+            "check",
+            "sanity",
+        ],
+    );
+}
+
+#[test]
+fn check_sanity_hw_map_mis_cabled() {
+    // The FEE of link 2 is mapped to link 3, its CDPs are reported once
+    let hw_map_file = format!("{}/hw_map.csv", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(
+        &hw_map_file,
+        "fee_id,cru_id,link_id,endpoint\n0x502A,24,0,0\n0x502B,24,3,0\n",
+    )
+    .unwrap();
+    run_golden(
+        "check_sanity_hw_map_mis_cabled",
+        &[
+            &test_data("its_2_links.raw"),
+            "--hw-map",
+            &hw_map_file,
+            "check",
+            "sanity",
+        ],
+    );
+    std::fs::remove_file(hw_map_file).unwrap();
+}
+
+#[test]
+fn check_all_its_errors_quality_score() {
+    // Link 3 is expected but missing, the score is below the threshold
+    let quality_config_file = format!("{}/quality.cfg", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(
+        &quality_config_file,
+        "# Quality score of the test data\nweight.error_free_hbfs = 0.5\nwarn_below = 95\nexpected_links = 0, 2, 3\n",
+    )
+    .unwrap();
+    run_golden(
+        "check_all_its_errors_quality_score",
+        &[
+            &test_data("its_2_links_errors.raw"),
+            "--quality-config",
+            &quality_config_file,
+            "check",
+            "all",
+            "its",
+        ],
+    );
+    std::fs::remove_file(quality_config_file).unwrap();
+}
+
+#[test]
+fn check_all_its_errors_command_hooks() {
+    // The same error code is reported on both links, the error command runs once
+    let hook_dir = format!("{}/command_hooks", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::create_dir_all(&hook_dir).unwrap();
+    run_succeeding(&[
+        &test_data("its_2_links_errors.raw"),
+        "--on-error-exec",
+        &format!("touch {hook_dir}/error_{{code}}"),
+        "--on-complete-exec",
+        &format!("touch {hook_dir}/{{verdict}}_{{errors}}"),
+        "check",
+        "all",
+        "its",
+    ]);
+    let mut files: Vec<String> = std::fs::read_dir(&hook_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    files.sort();
+    assert_eq!(files, vec!["error_E70", "errors_2"]);
+    std::fs::remove_dir_all(hook_dir).unwrap();
+}
+
+#[test]
+fn check_all_its_errors_save_failing_cdps() {
+    let input = std::fs::read(test_data("its_2_links_errors.raw")).unwrap();
+    let failing_cdps_file = format!("{}/failing_cdps.raw", env!("CARGO_TARGET_TMPDIR"));
+    let (_, stderr) = run_succeeding(&[
+        &test_data("its_2_links_errors.raw"),
+        "--save-failing-cdps",
+        &failing_cdps_file,
+        "check",
+        "all",
+        "its",
+    ]);
+    assert!(
+        stderr.contains(&format!("2 saved to {failing_cdps_file}  at 0x130, 0x260")),
+        "{stderr}"
+    );
+    // The first page of link 2 in the first orbit and of link 0 in the second orbit have an invalid data word ID
+    // The CDPs of different links are written in the order they failed
+    let saved = std::fs::read(&failing_cdps_file).unwrap();
+    let mut saved_cdps: Vec<&[u8]> = saved.chunks(0x70).collect();
+    saved_cdps.sort_unstable_by_key(|cdp| cdp[12]);
+    assert_eq!(saved_cdps, [&input[0x260..0x2D0], &input[0x130..0x1A0]]);
+    // The saved CDPs can be checked again, with the same errors
+    let (_, stderr) = run_succeeding(&[&failing_cdps_file, "check", "all", "its"]);
+    assert!(
+        stderr.contains("ERROR - 0x54: [E70] ID is invalid: 0x2A"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("ERROR - 0xC4: [E70] ID is invalid: 0x2A"),
+        "{stderr}"
+    );
+    std::fs::remove_file(failing_cdps_file).unwrap();
+}
+
+#[test]
+fn verify_filter_extracted_link() {
+    let source = test_data("its_2_links.raw");
+    let link2_file = format!("{}/verify_filter_link2.raw", env!("CARGO_TARGET_TMPDIR"));
+    run_succeeding(&[&source, "-f", "2", "-o", &link2_file]);
+    let (stdout, _) = run_succeeding(&["verify-filter", &source, &link2_file, "--link", "2"]);
+    assert!(stdout.starts_with("PASS: "), "{stdout}");
+    // The output isn't the extraction of another link
+    let output = Command::cargo_bin("fastpasta")
+        .unwrap()
+        .args(["verify-filter", &source, &link2_file, "--link", "0"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(normalize(&output.stdout).starts_with("FAIL: CDP 0: RDH byte "));
+    // Corrupt one payload byte of the second CDP
+    let mut link2 = std::fs::read(&link2_file).unwrap();
+    let cdp_size = u16::from_le_bytes([link2[8], link2[9]]) as usize;
+    link2[cdp_size + 0x50] ^= 0xFF;
+    std::fs::write(&link2_file, &link2).unwrap();
+    let output = Command::cargo_bin("fastpasta")
+        .unwrap()
+        .args(["verify-filter", &source, &link2_file, "--link", "2"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = normalize(&output.stdout);
+    assert!(
+        stdout.starts_with("FAIL: CDP 1: payload byte 16 is "),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains(&format!("output at {:#X})", cdp_size + 0x50)),
+        "{stdout}"
+    );
+    std::fs::remove_file(link2_file).unwrap();
+}
+
+/// Splits CSV into its rows of fields, the quotes of quoted fields are removed, and quoted fields may span several lines
+fn csv_rows(csv: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut fields = vec![String::new()];
+    let mut in_quotes = false;
+    let mut chars = csv.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(String::new()),
+            '\n' if !in_quotes => rows.push(std::mem::replace(&mut fields, vec![String::new()])),
+            _ => fields.last_mut().unwrap().push(c),
+        }
+    }
+    rows
+}
+
+/// Runs fastpasta with the arguments and `--error-csv`, and returns the total errors of the report and the fields of each row of the CSV
+fn run_error_csv(csv_name: &str, args: &[&str]) -> (usize, Vec<Vec<String>>) {
+    let csv_file = format!("{}/{csv_name}", env!("CARGO_TARGET_TMPDIR"));
+    let output = Command::cargo_bin("fastpasta")
+        .unwrap()
+        .args(args)
+        .args(["--error-csv", &csv_file])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let total_errors = normalize(&output.stderr)
+        .lines()
+        .find_map(|line| {
+            let (_, value) = line.split_once("Total Errors")?;
+            value.split_whitespace().next()?.parse().ok()
+        })
+        .unwrap();
+    let csv = std::fs::read_to_string(&csv_file).unwrap();
+    std::fs::remove_file(csv_file).unwrap();
+    let mut rows = csv_rows(&csv);
+    assert_eq!(
+        rows.remove(0).join(","),
+        "code,memory_pos,link,fee_id,stave,orbit,severity,message,word_bytes"
+    );
+    for fields in &rows {
+        assert_eq!(fields.len(), 9, "{fields:?}");
+    }
+    (total_errors, rows)
+}
+
+fn severities(rows: &[Vec<String>]) -> Vec<&str> {
+    rows.iter().map(|fields| fields[6].as_str()).collect()
+}
+
+#[test]
+fn check_all_its_errors_error_csv() {
+    let (total_errors, rows) = run_error_csv(
+        "errors_ordered.csv",
+        &[
+            &test_data("its_2_links_errors.raw"),
+            "check",
+            "all",
+            "its",
+            "--ordered",
+        ],
+    );
+    assert_eq!(total_errors, 2);
+    assert_eq!(severities(&rows), vec!["error"; total_errors]);
+
+    // The second error of the link is muted, it is counted but only written to the CSV if muted errors are included
+    let errors_muted = [
+        &test_data("its_1_link_errors.raw"),
+        "check",
+        "all",
+        "its",
+        "--auto-mute-link-after",
+        "1",
+    ];
+    let (total_errors, rows) = run_error_csv("errors_muted.csv", &errors_muted);
+    assert_eq!(total_errors, 2);
+    assert_eq!(severities(&rows), vec!["error"]);
+    let (total_errors, rows) = run_error_csv(
+        "errors_include_muted.csv",
+        &[&errors_muted[..], &["--error-csv-include-muted"]].concat(),
+    );
+    assert_eq!(total_errors, 2);
+    assert_eq!(severities(&rows), vec!["error", "muted"]);
+}
+
+#[test]
+fn check_all_its_errors_error_log_binary() {
+    let error_log = format!("{}/errors_muted.fpe", env!("CARGO_TARGET_TMPDIR"));
+    let (_, mut csv_rows_written) = run_error_csv(
+        "errors_muted_verbose.csv",
+        &[
+            &test_data("its_1_link_errors.raw"),
+            "check",
+            "all",
+            "its",
+            "--verbose-errors",
+            "--auto-mute-link-after",
+            "1",
+            "--error-csv-include-muted",
+            "--error-log-binary",
+            &error_log,
+        ],
+    );
+    assert_eq!(severities(&csv_rows_written), vec!["error", "muted"]);
+    let (decoded_csv, _) = run_succeeding(&["errors", "decode", &error_log, "--csv"]);
+    // The stave is not stored in the binary error log
+    csv_rows_written
+        .iter_mut()
+        .for_each(|fields| fields[4].clear());
+    assert_eq!(csv_rows(&decoded_csv)[1..], csv_rows_written);
+
+    let (decoded_text, _) = run_succeeding(&["errors", "decode", &error_log, "--code", "E4401"]);
+    assert_eq!(
+        decoded_text,
+        "MUTED - 0x2AA: [E4401] TDH missing HB bit present in RDH, TDH: 0x801 (ORBIT|TF), RDH: 0x6803 (ORBIT|HB|TF) [01 18 00 00 77 D5 7D 0B 00 E8] (link 0, fee 0x502A, orbit 0x0B7DD577)\n"
+    );
+    std::fs::remove_file(error_log).unwrap();
+}
+
+#[test]
+fn check_all_its_errors_independent_of_chunk_size() {
+    // Swapping the second and third page of the first HBF of link 0 breaks the continuity of the pages across chunk boundaries
+    let reordered_file = reordered_test_data(
+        "its_2_links.raw",
+        &[0, 2, 1, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17],
+        "reordered_chunk_size.raw",
+    );
+    for input in [
+        test_data("its_1_link_errors.raw"),
+        test_data("its_2_links_errors.raw"),
+        reordered_file.clone(),
+    ] {
+        let sorted_errors = |cdp_chunk_size: &str| {
+            let (total_errors, mut rows) = run_error_csv(
+                "errors_chunk_size.csv",
+                &[
+                    &input,
+                    "check",
+                    "all",
+                    "its",
+                    "--verbose-errors",
+                    "--cdp-chunk-size",
+                    cdp_chunk_size,
+                ],
+            );
+            assert_eq!(total_errors, rows.len());
+            rows.sort();
+            rows
+        };
+        let default_chunk_size_errors = sorted_errors("100");
+        assert!(!default_chunk_size_errors.is_empty(), "{input}");
+        for cdp_chunk_size in ["1", "7"] {
+            assert_eq!(
+                sorted_errors(cdp_chunk_size),
+                default_chunk_size_errors,
+                "{input} with chunks of {cdp_chunk_size} CDPs"
+            );
+        }
+    }
+    std::fs::remove_file(reordered_file).unwrap();
+}
+
+#[test]
+fn view_hbf() {
+    run_golden(
+        "view_hbf",
+        &[&test_data("its_1_link_errors.raw"), "view", "hbf"],
+    );
+}
+
+#[test]
+fn view_raw_words_16_bytes() {
+    run_golden(
+        "view_raw_words_16_bytes",
+        &[
+            &test_data("its_2_links.raw"),
+            "--payload-word-size",
+            "16",
+            "view",
+            "raw-words",
+        ],
+    );
+}
+
+#[test]
+fn filter_link_to_file() {
+    let output_file = format!("{}/filter_link_2.raw", env!("CARGO_TARGET_TMPDIR"));
+    run_golden(
+        "filter_link_to_file",
+        &[&test_data("its_2_links.raw"), "-f", "2", "-o", &output_file],
+    );
+    // The filtered file only has the CDPs of link 2
+    run_golden(
+        "filter_link_to_file_view_rdh",
+        &[&output_file, "view", "rdh"],
+    );
+    std::fs::remove_file(output_file).unwrap();
+}
+
+#[test]
+fn exclude_link_to_file() {
+    let data = std::fs::read(test_data("its_2_links.raw")).unwrap();
+    let mut kept = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let offset_to_next = u16::from_le_bytes([data[pos + 8], data[pos + 9]]) as usize;
+        if data[pos + 12] != 0 {
+            kept.extend_from_slice(&data[pos..pos + offset_to_next]);
+        }
+        pos += offset_to_next;
+    }
+    let output_file = format!("{}/exclude_link_0.raw", env!("CARGO_TARGET_TMPDIR"));
+    // Link 7 is not in the input, and is listed as not found
+    run_golden(
+        "exclude_link_to_file",
+        &[
+            &test_data("its_2_links.raw"),
+            "--exclude-link",
+            "0,7",
+            "-o",
+            &output_file,
+        ],
+    );
+    assert_eq!(std::fs::read(&output_file).unwrap(), kept);
+    std::fs::remove_file(output_file).unwrap();
+}
+
+#[test]
+fn filter_links_to_file() {
+    // The last 2 HBFs of link 2 are moved to link 4, so the input has links 0, 2 and 4
+    let mut data = std::fs::read(test_data("its_2_links.raw")).unwrap();
+    let mut kept = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        // The link ID is at byte 12 and the offset to the next RDH at byte 8 of the RDH
+        let offset_to_next = u16::from_le_bytes([data[pos + 8], data[pos + 9]]) as usize;
+        if data[pos + 12] == 2 && pos > 0x300 {
+            data[pos + 12] = 4;
+        }
+        if data[pos + 12] != 2 {
+            kept.extend_from_slice(&data[pos..pos + offset_to_next]);
+        }
+        pos += offset_to_next;
+    }
+    let input_file = format!("{}/its_3_links.raw", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(&input_file, data).unwrap();
+    let output_file = format!("{}/filter_links_0_4.raw", env!("CARGO_TARGET_TMPDIR"));
+    run_golden(
+        "filter_links_to_file",
+        &[&input_file, "-f", "4,0,5", "-o", &output_file],
+    );
+    // The CDPs of links 0 and 4 are kept in their original order
+    assert_eq!(std::fs::read(&output_file).unwrap(), kept);
+    std::fs::remove_file(output_file).unwrap();
+}
+
+#[test]
+fn filter_links_none_in_input() {
+    let output_file = format!("{}/filter_links_5_7.raw", env!("CARGO_TARGET_TMPDIR"));
+    let (_, stderr) = run_succeeding(&[
+        &test_data("its_2_links.raw"),
+        "-f",
+        "5,7",
+        "-o",
+        &output_file,
+    ]);
+    assert!(
+        stderr.contains(
+            "Filter kept none of the 18 CDPs of the input! None of the links to filter are present: 5, 7. Links present: 0, 2."
+        ),
+        "{stderr}"
+    );
+    std::fs::remove_file(output_file).unwrap();
+}
+
+/// Writes the test data with link 2 renamed to link 0, as if the link ID is used on two CRUs and only the FEE IDs (0x502A and 0x502B) tell them apart.
+///
+/// Returns the path of the file and the CDPs of FEE ID 0x502B.
+fn same_link_id_test_data(file_name: &str, same_link_id_name: &str) -> (String, Vec<u8>) {
+    let mut data = std::fs::read(test_data(file_name)).unwrap();
+    let mut fee_502b = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let offset_to_next = u16::from_le_bytes([data[pos + 8], data[pos + 9]]) as usize;
+        if data[pos + 12] == 2 {
+            data[pos + 12] = 0;
+            fee_502b.extend_from_slice(&data[pos..pos + offset_to_next]);
+        }
+        pos += offset_to_next;
+    }
+    let same_link_id_file = format!("{}/{same_link_id_name}", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(&same_link_id_file, data).unwrap();
+    (same_link_id_file, fee_502b)
+}
+
+#[test]
+fn filter_cru_same_link_id_on_two_crus() {
+    // The CDPs of link 2 are moved to link 0 of CRU 25, the CDPs of link 0 are on CRU 24
+    let mut data = std::fs::read(test_data("its_2_links.raw")).unwrap();
+    let mut kept = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        // The CRU ID is in the 12 LSB of the 2 bytes at byte 14 of the RDH
+        let offset_to_next = u16::from_le_bytes([data[pos + 8], data[pos + 9]]) as usize;
+        if data[pos + 12] == 2 {
+            data[pos + 12] = 0;
+            data[pos + 14] += 1;
+            kept.extend_from_slice(&data[pos..pos + offset_to_next]);
+        }
+        pos += offset_to_next;
+    }
+    let input_file = format!("{}/its_link_0_two_crus.raw", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(&input_file, data).unwrap();
+    let output_file = format!("{}/filter_cru_25.raw", env!("CARGO_TARGET_TMPDIR"));
+    run_golden(
+        "filter_cru_to_file",
+        &[
+            &input_file,
+            "--filter-cru",
+            "25",
+            "-f",
+            "0",
+            "-o",
+            &output_file,
+        ],
+    );
+    assert_eq!(std::fs::read(&output_file).unwrap(), kept);
+    // A typo in the CRU ID lists the CRU IDs present
+    run_golden(
+        "filter_cru_not_in_input",
+        &[&input_file, "--filter-cru", "52", "-o", &output_file],
+    );
+    std::fs::remove_file(output_file).unwrap();
+    std::fs::remove_file(input_file).unwrap();
+}
+
+#[test]
+fn filter_fee_same_link_id_on_two_crus() {
+    let (input_file, kept) = same_link_id_test_data("its_2_links.raw", "its_link_0_twice.raw");
+    let output_file = format!("{}/filter_fee_502b.raw", env!("CARGO_TARGET_TMPDIR"));
+    run_golden(
+        "filter_fee_to_file",
+        &[&input_file, "--filter-fee", "0x502B", "-o", &output_file],
+    );
+    assert_eq!(std::fs::read(&output_file).unwrap(), kept);
+    // Only the CDPs of the FEE are checked, the other CDPs of link 0 don't break its continuity
+    run_golden(
+        "filter_fee_check_all_its",
+        &[&input_file, "--filter-fee", "0x502B", "check", "all", "its"],
+    );
+    std::fs::remove_file(output_file).unwrap();
+    std::fs::remove_file(input_file).unwrap();
+}
+
+#[test]
+fn filter_its_stave_same_link_id_on_two_crus() {
+    let (input_file, mut kept) =
+        same_link_id_test_data("its_2_links.raw", "its_link_0_twice_stave.raw");
+    // Layer 5 has staves 0 to 41, the FEE IDs of staves L5_42 and L5_43 become L5_40 and L5_41
+    let mut data = std::fs::read(&input_file).unwrap();
+    for cdps in [&mut data, &mut kept] {
+        let mut pos = 0;
+        while pos < cdps.len() {
+            cdps[pos + 2] -= 2;
+            pos += u16::from_le_bytes([cdps[pos + 8], cdps[pos + 9]]) as usize;
+        }
+    }
+    std::fs::write(&input_file, data).unwrap();
+    let output_file = format!("{}/filter_its_stave_l5_41.raw", env!("CARGO_TARGET_TMPDIR"));
+    run_golden(
+        "filter_its_stave_to_file",
+        &[
+            &input_file,
+            "--filter-its-stave",
+            "L5_41",
+            "-o",
+            &output_file,
+        ],
+    );
+    // The CDPs of stave L5_40 on the same link are excluded
+    assert_eq!(std::fs::read(&output_file).unwrap(), kept);
+    let (_, stderr) = run_succeeding(&[
+        &input_file,
+        "--filter-its-stave",
+        "L3_12",
+        "-o",
+        &output_file,
+    ]);
+    assert!(
+        stderr.contains("The ITS stave to filter is not present: L3_12.")
+            && stderr.contains("not found: L3_12, staves present: L5_40, L5_41"),
+        "{stderr}"
+    );
+    std::fs::remove_file(output_file).unwrap();
+    std::fs::remove_file(input_file).unwrap();
+}
+
+#[test]
+fn filter_fee_not_in_input() {
+    // The FEE IDs present are listed in the report
+    let output_file = format!("{}/filter_fee_502c.raw", env!("CARGO_TARGET_TMPDIR"));
+    run_golden(
+        "filter_fee_not_in_input",
+        &[
+            &test_data("its_2_links.raw"),
+            "--filter-fee",
+            "0x502C",
+            "-o",
+            &output_file,
+        ],
+    );
+    std::fs::remove_file(output_file).unwrap();
+}
+
+/// Runs fastpasta with the arguments, asserts that it succeeds, and returns its normalized stdout and stderr
+fn run_succeeding(args: &[&str]) -> (String, String) {
+    let output = Command::cargo_bin("fastpasta")
+        .unwrap()
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    (normalize(&output.stdout), normalize(&output.stderr))
+}
+
+#[test]
+fn drop_empty_hbfs() {
+    // Turn the only data word of each page of the first HBF of link 0 into an idle word
+    let mut input = std::fs::read(test_data("its_2_links.raw")).unwrap();
+    input[0x5D] = 0x00;
+    input[0xCD] = 0x00;
+    let input_file = format!("{}/drop_empty_hbfs_input.raw", env!("CARGO_TARGET_TMPDIR"));
+    let output_file = format!("{}/drop_empty_hbfs_output.raw", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(&input_file, &input).unwrap();
+    let (_, stderr) = run_succeeding(&[&input_file, "--drop-empty-hbfs", "-o", &output_file]);
+    assert!(
+        stderr.contains("Empty HBFs dropped          1                     3 CDPs (304 bytes) without data words not written"),
+        "{stderr}"
+    );
+    // All 3 pages of the HBF are dropped, the rest is written unchanged
+    assert_eq!(std::fs::read(&output_file).unwrap(), &input[0x130..]);
+    std::fs::remove_file(input_file).unwrap();
+    std::fs::remove_file(output_file).unwrap();
+}
+
+#[test]
+fn filter_ape() {
+    // Turn the data word of the first page of link 0 into an APE protocol error
+    let mut input = std::fs::read(test_data("its_2_links.raw")).unwrap();
+    input[0x5D] = 0xF6;
+    let input_file = format!("{}/filter_ape_input.raw", env!("CARGO_TARGET_TMPDIR"));
+    let output_file = format!("{}/filter_ape_output.raw", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(&input_file, &input).unwrap();
+    let (_, stderr) = run_succeeding(&[&input_file, "--filter-ape", "-o", &output_file]);
+    assert!(stderr.contains("CDPs with APE words         1"), "{stderr}");
+    assert!(stderr.contains("APE protocol error (0xF6)   1"), "{stderr}");
+    assert_eq!(std::fs::read(&output_file).unwrap(), &input[..0x70]);
+    // The APE word is named in the error of the data word checks
+    let (_, stderr) = run_succeeding(&[&input_file, "check", "all", "its"]);
+    assert!(
+        stderr.contains("[E70] ID is invalid: 0xF6, APE protocol error"),
+        "{stderr}"
+    );
+    std::fs::remove_file(input_file).unwrap();
+    std::fs::remove_file(output_file).unwrap();
+}
+
+#[test]
+fn extract_first_and_last_hbfs() {
+    // 3 HBFs of 3 pages on each link, at orbits 0xB7DD575 to 0xB7DD577
+    for (extract, hbfs, orbits) in [
+        ("--extract-first-hbfs", 2, "orbit<=0xB7DD576"),
+        ("--extract-last-hbfs", 1, "orbit==0xB7DD577"),
+    ] {
+        let output_file = format!("{}/extract_hbfs.raw", env!("CARGO_TARGET_TMPDIR"));
+        run_succeeding(&[
+            &test_data("its_2_links.raw"),
+            extract,
+            &hbfs.to_string(),
+            "-o",
+            &output_file,
+        ]);
+        for link_id in [0, 2] {
+            let (stdout, _) = run_succeeding(&[
+                &output_file,
+                "count",
+                "--where",
+                &format!("link_id=={link_id} && stop_bit==1 && {orbits}"),
+            ]);
+            assert!(
+                stdout.starts_with(&format!("{hbfs} of {} CDPs match", 2 * 3 * hbfs)),
+                "{extract}: {stdout}"
+            );
+        }
+        // The output is complete HBFs that are checked without errors
+        let (_, stderr) = run_succeeding(&[&output_file, "check", "all", "its"]);
+        assert!(
+            stderr
+                .lines()
+                .any(|line| line.split_whitespace().collect::<Vec<_>>()
+                    == ["│", "Total", "Errors", "0", "│"]),
+            "{extract}: {stderr}"
+        );
+        std::fs::remove_file(output_file).unwrap();
+    }
+}
+
+#[test]
+fn filter_max_hbfs_of_link() {
+    let output_file = format!("{}/filter_max_hbfs.raw", env!("CARGO_TARGET_TMPDIR"));
+    run_succeeding(&[
+        &test_data("its_2_links.raw"),
+        "-f",
+        "2",
+        "--filter-max-hbfs",
+        "1",
+        "-o",
+        &output_file,
+    ]);
+    // The first HBF of link 2, in orbit 0xB7DD575
+    let data = std::fs::read(test_data("its_2_links.raw")).unwrap();
+    assert_eq!(std::fs::read(&output_file).unwrap(), data[0x130..0x260]);
+    std::fs::remove_file(output_file).unwrap();
+}
+
+#[test]
+fn filter_link_all_copies_only_with_force() {
+    let output_file = format!("{}/filter_link_all.raw", env!("CARGO_TARGET_TMPDIR"));
+    let input_file = test_data("its_2_links.raw");
+    let output = Command::cargo_bin("fastpasta")
+        .unwrap()
+        .args([&input_file, "-f", "all", "-o", &output_file])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = normalize(&output.stderr);
+    assert!(
+        stderr.contains("use --force to write it anyway"),
+        "{stderr}"
+    );
+
+    let output = Command::cargo_bin("fastpasta")
+        .unwrap()
+        .args([&input_file, "-f", "all", "-o", &output_file, "--force"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        std::fs::read(&output_file).unwrap(),
+        std::fs::read(&input_file).unwrap()
+    );
+    std::fs::remove_file(output_file).unwrap();
+}
+
+#[test]
+fn filter_link_not_in_input() {
+    // A typo in the link ID, none of the CDPs are kept, the links and FEE IDs present are listed
+    let output_file = format!("{}/filter_link_1.raw", env!("CARGO_TARGET_TMPDIR"));
+    run_golden(
+        "filter_link_not_in_input",
+        &[&test_data("its_2_links.raw"), "-f", "1", "-o", &output_file],
+    );
+    std::fs::remove_file(output_file).unwrap();
+}
+
+#[test]
+fn filter_link_keeps_all() {
+    let output_file = format!("{}/filter_link_0.raw", env!("CARGO_TARGET_TMPDIR"));
+    run_golden(
+        "filter_link_keeps_all",
+        &[
+            &test_data("its_1_link_errors.raw"),
+            "-f",
+            "0",
+            "-o",
+            &output_file,
+        ],
+    );
+    std::fs::remove_file(output_file).unwrap();
+}
+
+#[test]
+fn count_where() {
+    run_golden(
+        "count_where",
+        &[
+            &test_data("its_2_links.raw"),
+            "count",
+            "--where",
+            "link_id==2 && (stop_bit==1 || pages_counter>1)",
+        ],
+    );
+}
+
+#[test]
+fn filter_where_to_file() {
+    let output_file = format!("{}/filter_where_stop_bit.raw", env!("CARGO_TARGET_TMPDIR"));
+    run_golden(
+        "filter_where_to_file",
+        &[
+            &test_data("its_2_links.raw"),
+            "--filter-where",
+            "stop_bit==1",
+            "-o",
+            &output_file,
+        ],
+    );
+    // The filtered file only has the CDPs that close an HBF
+    run_golden(
+        "filter_where_to_file_view_rdh",
+        &[&output_file, "view", "rdh"],
+    );
+    std::fs::remove_file(output_file).unwrap();
+}
+
+#[test]
+fn filter_orbit_range_to_file() {
+    let output_file = format!("{}/filter_orbit_range.raw", env!("CARGO_TARGET_TMPDIR"));
+    run_golden(
+        "filter_orbit_range_to_file",
+        &[
+            &test_data("its_2_links.raw"),
+            "--filter-orbit-range",
+            "0x0b7dd576",
+            "192796022",
+            "-o",
+            &output_file,
+        ],
+    );
+    // The HBFs of both links in orbit 0x0B7DD576
+    let data = std::fs::read(test_data("its_2_links.raw")).unwrap();
+    assert_eq!(std::fs::read(&output_file).unwrap(), data[0x260..0x4C0]);
+    std::fs::remove_file(output_file).unwrap();
+}
+
+#[test]
+fn filter_orbit_range_not_in_input() {
+    // No orbit of the input is in the range, the links and FEE IDs present are listed in the report
+    let output_file = format!(
+        "{}/filter_orbit_range_none.raw",
+        env!("CARGO_TARGET_TMPDIR")
+    );
+    run_golden(
+        "filter_orbit_range_not_in_input",
+        &[
+            &test_data("its_2_links.raw"),
+            "--filter-orbit-range",
+            "0",
+            "10",
+            "-o",
+            &output_file,
+        ],
+    );
+    std::fs::remove_file(output_file).unwrap();
+}
+
+#[test]
+fn filter_trigger_to_file() {
+    // The CDPs of link 2 are physics triggered
+    let mut data = std::fs::read(test_data("its_2_links.raw")).unwrap();
+    let mut kept = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        // The PhT bit is bit 4 of the trigger type at byte 32 of the RDH
+        let offset_to_next = u16::from_le_bytes([data[pos + 8], data[pos + 9]]) as usize;
+        if data[pos + 12] == 2 {
+            data[pos + 32] |= 0x10;
+            kept.extend_from_slice(&data[pos..pos + offset_to_next]);
+        }
+        pos += offset_to_next;
+    }
+    let input_file = format!("{}/its_link_2_pht.raw", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(&input_file, data).unwrap();
+    let output_file = format!("{}/filter_trigger_pht.raw", env!("CARGO_TARGET_TMPDIR"));
+    run_golden(
+        "filter_trigger_to_file",
+        &[&input_file, "--filter-trigger", "PhT", "-o", &output_file],
+    );
+    assert_eq!(std::fs::read(&output_file).unwrap(), kept);
+    std::fs::remove_file(output_file).unwrap();
+    std::fs::remove_file(input_file).unwrap();
+}
+
+#[test]
+fn count_where_invalid_expression() {
+    let output = Command::cargo_bin("fastpasta")
+        .unwrap()
+        .args([
+            &test_data("its_2_links.raw"),
+            "count",
+            "--where",
+            "stop_bit==1 && pages_count>30",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = normalize(&output.stderr);
+    assert!(
+        stderr.contains("column 16: Unknown RDH field 'pages_count', valid fields are: version,"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn check_all_its_strict() {
+    // The known-good file passes strict mode
+    run_golden(
+        "check_all_its_strict",
+        &[
+            &test_data("its_2_links.raw"),
+            "--strict",
+            "check",
+            "all",
+            "its",
+        ],
+    );
+}
+
+#[test]
+fn check_all_its_strict_truncated_payload() {
+    // A truncated payload is fatal in strict mode, the rest of the output depends on when the other threads see the stop flag
+    let truncated_file =
+        truncated_test_data("its_2_links.raw", 0x6B0, "strict_truncated_in_payload.raw");
+    let output = Command::cargo_bin("fastpasta")
+        .unwrap()
+        .args([&truncated_file, "--strict", "check", "all", "its"])
+        .output()
+        .unwrap();
+    let stderr = normalize(&output.stderr);
+    assert!(
+        stderr.contains(
+            "FATAL: 0x660: link 2: payload truncated by end of input, 32 of 48 bytes missing"
+        ),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("FATAL ERROR - EARLY TERMINATION"),
+        "{stderr}"
+    );
+    std::fs::remove_file(truncated_file).unwrap();
+}
+
+#[test]
+fn check_sanity_its_report_detail_cdp() {
+    let output = Command::cargo_bin("fastpasta")
+        .unwrap()
+        .args([
+            &test_data("its_2_links.raw"),
+            "--log-format",
+            "json",
+            "--report-detail",
+            "cdp",
+            "check",
+            "sanity",
+            "its",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let report_line = stderr.lines().last().unwrap();
+    // One entry per CDP, ordered by memory position
+    assert_eq!(
+        report_line.matches(r#"{"offset":"#).count(),
+        18,
+        "{report_line}"
+    );
+    assert!(
+        report_line.contains(r#","detail":{"cdps":[{"offset":0,"link":0,"fee":20522,"orbit":192796021,"pages_counter":0,"stop_bit":0,"payload_bytes":48,"words":{"ihw":1,"tdh":1,"tdt":1,"ddw0":0,"cdw":0,"data":1}},{"offset":112,"#),
+        "{report_line}"
+    );
+
+    // Refused above the CDP limit
+    let output = Command::cargo_bin("fastpasta")
+        .unwrap()
+        .args([
+            &test_data("its_2_links.raw"),
+            "--log-format",
+            "json",
+            "--report-detail",
+            "cdp",
+            "--report-detail-max-cdps",
+            "10",
+            "check",
+            "sanity",
+            "its",
+        ])
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("FATAL: `--report-detail cdp` would list more than 10 CDPs"));
+}
+
+#[test]
+fn check_sanity_track_memory() {
+    let (_, stderr) = run_succeeding(&[
+        &test_data("its_2_links.raw"),
+        "--track-memory",
+        "check",
+        "sanity",
+    ]);
+    assert!(stderr.contains("Peak memory (RSS)"), "{stderr}");
+    assert!(stderr.contains("Peak CDP chunks in flight"), "{stderr}");
+
+    let output = Command::cargo_bin("fastpasta")
+        .unwrap()
+        .args([
+            &test_data("its_2_links.raw"),
+            "--track-memory",
+            "--log-format",
+            "json",
+            "check",
+            "sanity",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let report_line = stderr.lines().last().unwrap();
+    let memory = report_line
+        .split_once(r#""memory":{"peak_rss_bytes":"#)
+        .unwrap_or_else(|| panic!("{report_line}"))
+        .1;
+    let (_, peak_in_flight) = memory.split_once(r#""peak_in_flight_bytes":"#).unwrap();
+    let peak_in_flight: u64 = peak_in_flight
+        .trim_end_matches('}')
+        .parse()
+        .unwrap_or_else(|e| panic!("{e}: {report_line}"));
+    // All 18 CDPs of the test data fit in one chunk
+    assert!(peak_in_flight >= 1824, "{report_line}");
+}
+
+#[test]
+fn check_all_its_fail_fast() {
+    // 1000 copies of a fixture with 2 errors, processing stops long before the end of the input
+    let data = std::fs::read(test_data("its_1_link_errors.raw")).unwrap();
+    let repeated_file = format!("{}/fail_fast.raw", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(&repeated_file, data.repeat(1000)).unwrap();
+    let output = Command::cargo_bin("fastpasta")
+        .unwrap()
+        .args([&repeated_file, "check", "all", "its", "--fail-fast"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4), "{output:?}");
+    let stderr = normalize(&output.stderr);
+    let error_lines: Vec<&str> = stderr
+        .lines()
+        .filter(|line| line.starts_with("ERROR - "))
+        .collect();
+    assert_eq!(
+        error_lines,
+        vec!["ERROR - 0x184: [E70] ID is invalid: 0x29 [00 00 00 00 00 00 00 00 00 29]"]
+    );
+    // Only the error that stopped the processing is in the first error occurrences
+    assert!(!stderr.contains("E4401"), "{stderr}");
+    assert!(stderr.contains("stopped at the first error"), "{stderr}");
+    let total_rdhs: u64 = stderr
+        .lines()
+        .find_map(|line| line.split("Total RDHs").nth(1))
+        .and_then(|rest| rest.split_whitespace().next())
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(total_rdhs < 12 * 1000, "{stderr}");
+    std::fs::remove_file(repeated_file).unwrap();
+}
+
+#[test]
+fn check_sanity_text_input_not_recognized() {
+    let text_file = format!("{}/not_raw_data.txt", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(&text_file, "Run 535069, ITS, 2 links\n".repeat(100)).unwrap();
+    let output = Command::cargo_bin("fastpasta")
+        .unwrap()
+        .args([&text_file, "check", "sanity"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Input does not look like CRU raw data: header_id 0x52 at 0x0 is not a known RDH version (6 or 7), use --skip-input-probe"),
+        "{stderr}"
+    );
+    // Without the probe, it fails on the RDH version as before
+    let output = Command::cargo_bin("fastpasta")
+        .unwrap()
+        .args([&text_file, "check", "sanity", "--skip-input-probe"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        !stderr.contains("Input does not look like CRU raw data"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("Unknown RDH version: 82"), "{stderr}");
+    std::fs::remove_file(text_file).unwrap();
+}
+
+#[test]
+fn check_sanity_input_recognized() {
+    let output = Command::cargo_bin("fastpasta")
+        .unwrap()
+        .args([&test_data("its_2_links.raw"), "check", "sanity", "-v", "2"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Input recognized as RDHv7/ITS with high confidence (4 RDHs probed)"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn check_all_its_other_system_link_payloads_skipped() {
+    // Link 2 carries TPC (system ID 3) instead of ITS, both links have an invalid data word
+    let mut data = std::fs::read(test_data("its_2_links_errors.raw")).unwrap();
+    let mut pos = 0;
+    while pos < data.len() {
+        // The system ID is at byte 5 and the link ID at byte 12 of the RDH
+        if data[pos + 12] == 2 {
+            data[pos + 5] = 3;
+        }
+        pos += u16::from_le_bytes([data[pos + 8], data[pos + 9]]) as usize;
+    }
+    let mixed_file = format!("{}/its_and_tpc_links.raw", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(&mixed_file, data).unwrap();
+    let output = Command::cargo_bin("fastpasta")
+        .unwrap()
+        .args([&mixed_file, "check", "all", "its"])
+        .output()
+        .unwrap();
+    let stderr = normalize(&output.stderr);
+    let payload_errors: Vec<&str> = stderr
+        .lines()
+        .filter(|line| line.starts_with("ERROR - ") && !line.contains("RDH sanity check failed"))
+        .collect();
+    assert_eq!(
+        payload_errors,
+        vec!["ERROR - 0x2B4: [E70] ID is invalid: 0x2A [00 00 00 00 00 00 00 00 00 2A]"]
+    );
+    // The RDH sanity checks still apply to the RDHs of link 2
+    assert!(stderr.contains("system_id = 0x3"), "{stderr}");
+    assert!(
+        stderr.contains("link 2 carries system 0x3 (TPC) — payload checks skipped for 6 CDPs"),
+        "{stderr}"
+    );
+    // All links are checked as ITS links when forced
+    let output = Command::cargo_bin("fastpasta")
+        .unwrap()
+        .args([
+            &mixed_file,
+            "check",
+            "all",
+            "its",
+            "--force-check-all-links",
+        ])
+        .output()
+        .unwrap();
+    let stderr = normalize(&output.stderr);
+    assert!(stderr.contains("0x184: [E70]"), "{stderr}");
+    assert!(!stderr.contains("payload checks skipped"), "{stderr}");
+    std::fs::remove_file(mixed_file).unwrap();
+}
+
+#[test]
+fn check_all_its_starts_mid_hbf() {
+    // Starts at the second page of the first HBF of link 0
+    let order: Vec<usize> = (1..18).collect();
+    let mid_hbf_file = reordered_test_data("its_2_links.raw", &order, "mid_hbf.raw");
+    // The second page of the second HBF of link 0 is at 0x260, its pages_counter at byte 36 of the RDH is set to 3
+    let mut data = std::fs::read(&mid_hbf_file).unwrap();
+    data[0x260 + 36] = 3;
+    std::fs::write(&mid_hbf_file, data).unwrap();
+    let run = |args: &[&str]| -> String {
+        let output = Command::cargo_bin("fastpasta")
+            .unwrap()
+            .args([&mid_hbf_file, "check", "all", "its"])
+            .args(args)
+            .output()
+            .unwrap();
+        normalize(&output.stderr)
+    };
+    let stderr = run(&[]);
+    let log_lines: Vec<&str> = stderr
+        .lines()
+        .filter(|line| line.starts_with("ERROR - ") || line.starts_with("WARN - "))
+        .collect();
+    // Only the genuine gap is an error
+    assert_eq!(
+        log_lines,
+        vec![
+            "WARN - 0x0: link 0 starts mid-stream: packet_counter=1, pages_counter=1",
+            "ERROR - 0x260: pages_counter = 3 expected: 1.",
+        ]
+    );
+    let stderr = run(&["--strict-start"]);
+    let error_lines: Vec<&str> = stderr
+        .lines()
+        .filter(|line| line.starts_with("ERROR - "))
+        .collect();
+    assert_eq!(
+        error_lines,
+        vec![
+            "ERROR - 0x0: link 0 starts mid-stream: packet_counter=1, pages_counter=1",
+            "ERROR - 0x260: pages_counter = 3 expected: 1.",
+        ]
+    );
+}
+
+#[test]
+fn check_sanity_input_path_errors() {
+    let run = |input: &str| {
+        let output = Command::cargo_bin("fastpasta")
+            .unwrap()
+            .args([input, "check", "sanity"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1), "{output:?}");
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+    let missing_file = format!("{}/does_not_exist.raw", env!("CARGO_TARGET_TMPDIR"));
+    let stderr = run(&missing_file);
+    assert!(
+        stderr.contains(&format!("input file '{missing_file}' does not exist")),
+        "{stderr}"
+    );
+    let stderr = run(env!("CARGO_TARGET_TMPDIR"));
+    assert!(
+        stderr.contains(&format!(
+            "input file '{}' is a directory",
+            env!("CARGO_TARGET_TMPDIR")
+        )),
+        "{stderr}"
+    );
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let unreadable_file = format!("{}/unreadable.raw", env!("CARGO_TARGET_TMPDIR"));
+        std::fs::copy(test_data("its_2_links.raw"), &unreadable_file).unwrap();
+        std::fs::set_permissions(&unreadable_file, std::fs::Permissions::from_mode(0o000)).unwrap();
+        // Permissions are not enforced for root
+        if std::fs::File::open(&unreadable_file).is_err() {
+            let stderr = run(&unreadable_file);
+            assert!(
+                stderr.contains(&format!("input file '{unreadable_file}' permission denied")),
+                "{stderr}"
+            );
+        }
+        std::fs::remove_file(unreadable_file).unwrap();
+    }
+}
+
+#[test]
+fn check_all_its_with_view() {
+    let view_file = format!(
+        "{}/check_all_its_with_view.txt",
+        env!("CARGO_TARGET_TMPDIR")
+    );
+    let (_, stderr) = run_succeeding(&[
+        &test_data("its_2_links.raw"),
+        "check",
+        "all",
+        "its",
+        "--with-view",
+        "hbf",
+        "--view-limit",
+        "2",
+        "--view-output",
+        &view_file,
+    ]);
+    let view = std::fs::read_to_string(&view_file).unwrap();
+    assert_golden("check_all_its_with_view.view", &view);
+    assert!(!stderr.contains("RDH v7"), "{stderr}");
+    assert!(stderr.contains("Total Errors"), "{stderr}");
+    // Without an output file, the same view is written to stderr in one block
+    let (_, stderr) = run_succeeding(&[
+        &test_data("its_2_links.raw"),
+        "check",
+        "all",
+        "its",
+        "--with-view",
+        "hbf",
+        "--view-limit",
+        "2",
+    ]);
+    assert!(stderr.contains(&view), "{stderr}");
+    std::fs::remove_file(view_file).unwrap();
+    // Failing to write the view is a warning, the checks still succeed
+    let unwritable_view_file = format!("{}/no_such_dir/view.txt", env!("CARGO_TARGET_TMPDIR"));
+    let (_, stderr) = run_succeeding(&[
+        &test_data("its_2_links.raw"),
+        "check",
+        "all",
+        "its",
+        "--with-view",
+        "hbf",
+        "--view-output",
+        &unwritable_view_file,
+    ]);
+    assert!(
+        stderr.contains("WARN - Failed to write the view of the first HBFs"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("ERROR"), "{stderr}");
+}
+
+#[test]
+fn filter_packet_range() {
+    let output_file = format!("{}/filter_packet_range.raw", env!("CARGO_TARGET_TMPDIR"));
+    let (_, stderr) = run_succeeding(&[
+        &test_data("its_2_links.raw"),
+        "--filter-packet-range",
+        "1..2",
+        "-f",
+        "2",
+        "-o",
+        &output_file,
+    ]);
+    assert!(
+        stderr.contains("Packet range            1..2   2 CDPs selected"),
+        "{stderr}"
+    );
+    // The second and third page of the first HBF of link 2
+    let input = std::fs::read(test_data("its_2_links.raw")).unwrap();
+    assert_eq!(std::fs::read(&output_file).unwrap(), &input[0x1A0..0x260]);
+    std::fs::remove_file(output_file).unwrap();
+}
+
+#[test]
+fn filter_packet_range_not_in_input() {
+    // No packet of the input is in the range, the filter warns instead of silently writing an empty file
+    let output_file = format!(
+        "{}/filter_packet_range_none.raw",
+        env!("CARGO_TARGET_TMPDIR")
+    );
+    let (_, stderr) = run_succeeding(&[
+        &test_data("its_2_links.raw"),
+        "--filter-packet-range",
+        "1000..2000",
+        "-o",
+        &output_file,
+    ]);
+    assert!(
+        stderr.contains("WARN - Filter kept none of the"),
+        "{stderr}"
+    );
+    assert!(std::fs::read(&output_file).unwrap().is_empty());
+    std::fs::remove_file(output_file).unwrap();
+}
+
+#[test]
+fn list_links_first_n_mb_sampled_only_if_input_continues() {
+    // RDHs without payload, 16384 of them fill exactly 1 MB
+    let mut rdh = std::fs::read(test_data("its_2_links.raw")).unwrap()[..64].to_vec();
+    rdh[8..12].copy_from_slice(&[0x40, 0, 0x40, 0]);
+    let one_mb_file = format!("{}/list_links_1_mb.raw", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(&one_mb_file, rdh.repeat(16384)).unwrap();
+    let list_links_json = |file: &str| {
+        let output = Command::cargo_bin("fastpasta")
+            .unwrap()
+            .args([file, "list-links", "--json", "--first-n-mb", "1"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    let stdout = list_links_json(&one_mb_file);
+    assert!(stdout.contains(r#""sampled":false"#), "{stdout}");
+    assert!(stdout.contains(r#""cdps":16384"#), "{stdout}");
+
+    let longer_file = format!("{}/list_links_1_mb_and_1.raw", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(&longer_file, rdh.repeat(16385)).unwrap();
+    let stdout = list_links_json(&longer_file);
+    assert!(stdout.contains(r#""sampled":true"#), "{stdout}");
+    assert!(stdout.contains(r#""cdps":16384"#), "{stdout}");
+    std::fs::remove_file(one_mb_file).unwrap();
+    std::fs::remove_file(longer_file).unwrap();
+}